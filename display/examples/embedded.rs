@@ -0,0 +1,85 @@
+//! Пример встраивания отображения мира в собственное окно tetra: мир рисуется в
+//! правой части окна, левая остается под элементы программы. Вместо настоящего мира
+//! кадры отправляет поток, который двигает травоядное вдоль ряда растений.
+//!
+//! Запуск: `cargo run -p display --example embedded`.
+
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+
+use display::{default_base_path, CellMarks, CellStuff, DirectoryAssets, EvolutionView, Frame, Topology, ViewConfig};
+use tetra::graphics::{self, Color, Rectangle};
+use tetra::{Context, ContextBuilder, State};
+
+// Размеры мира в ячейках.
+const WIDTH: usize = 16;
+const HEIGHT: usize = 8;
+
+// Ширина левой части окна, в которой мир не рисуется.
+const PANEL_WIDTH: f32 = 200.0;
+
+/// Состояние программы, в окно которой встроено отображение мира.
+struct Host {
+    // Отображение мира.
+    view: EvolutionView,
+    // Канал для получения кадров.
+    receiver: Receiver<Frame>,
+}
+
+impl State for Host {
+    fn update(&mut self, ctx: &mut Context) -> tetra::Result {
+        self.view.update(ctx, &self.receiver);
+
+        Ok(())
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> tetra::Result {
+        graphics::clear(ctx, Color::rgb(0.2, 0.2, 0.25));
+
+        let (width, height) = self.view.natural_size();
+        self.view.draw(ctx, Rectangle::new(PANEL_WIDTH, 0.0, width, height));
+
+        Ok(())
+    }
+}
+
+fn main() -> tetra::Result {
+    let (sender, receiver) = mpsc::channel();
+    let (control, _control_receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        for tick in 0.. {
+            let mut map: Vec<_> = (0..WIDTH).map(|x| (x, HEIGHT / 2 + 1, CellStuff::Plant, CellMarks::default())).collect();
+            map.push(((tick as usize) % WIDTH, HEIGHT / 2, CellStuff::HerbRight, CellMarks::default()));
+
+            if sender.send(Frame::new(tick, tick, map, Vec::new())).is_err() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(200));
+        }
+    });
+
+    let config = ViewConfig {
+        width: WIDTH,
+        height: HEIGHT,
+        topology: Topology::Torus,
+        spawn_margin: 0,
+        effect_frames: 0,
+        control,
+        texture_size: None,
+        assets: Box::new(DirectoryAssets::new(&default_base_path())),
+        title: None,
+        scale: None,
+    };
+
+    ContextBuilder::new("Встроенный мир", 800, 480)
+        .show_mouse(true)
+        .quit_on_escape(true)
+        .build()?
+        .run(|ctx| {
+            let view = EvolutionView::new(ctx, config).map_err(tetra::TetraError::PlatformError)?;
+
+            Ok(Host { view, receiver })
+        })
+}
@@ -0,0 +1,340 @@
+use std::io::{self, Stdout, Write};
+use std::sync::mpsc::{Receiver, Sender};
+use std::time::Duration;
+use crate::{Annotation, ControlMessage, Frame, FrameRequest};
+use crate::render_model::{self, Facing, Rgba, Sprite};
+
+use crossterm::{cursor, execute, queue, terminal};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Color, Print, ResetColor, SetAttribute, SetBackgroundColor};
+
+// Как долго (миллисекунды) ждать нажатия клавиши перед проверкой новых кадров.
+const POLL_MS: u64 = 30;
+
+const EMPTY_GLYPH: char = ' ';
+
+const PLANT_GLYPH: char = '*';
+
+const OBSTACLE_GLYPH: char = '#';
+
+const CARCASS_GLYPH: char = '%';
+
+const KILLED_ANIMAL_GLYPH: char = 'x';
+
+const DEAD_ANIMAL_GLYPH: char = '+';
+
+// Символы животных по направлению (порядок: влево, вправо, вперед, назад): строчная
+// буква - животное смотрит влево или вперед, прописная - вправо или назад. Животные,
+// смотрящие по вертикали, выводятся в инверсном цвете (см. `vertical`).
+const HERBIVORE_GLYPHS: [char; 4] = ['h', 'H', 'h', 'H'];
+
+const CARNIVORE_GLYPHS: [char; 4] = ['c', 'C', 'c', 'C'];
+
+const OMNIVORE_GLYPHS: [char; 4] = ['o', 'O', 'o', 'O'];
+
+/// Драйвер отображения мира символами в терминале. Работает без окна и графики,
+/// в том числе через SSH.
+pub struct Console {
+    // Канал для получения данных о состоянии мира.
+    receiver: Receiver<Frame>,
+
+    // Канал для отправки управляющих сообщений миру.
+    control: Sender<ControlMessage>,
+
+    // Размеры мира в ячейках.
+    world_size: (usize, usize),
+
+    // Заглавие (выводится в первой строке).
+    title: String,
+
+    // Номер итерации мира в последнем отображенном кадре.
+    tick: Option<u64>,
+
+    // Вывод в терминал.
+    stdout: Stdout,
+}
+
+impl Console {
+    /// Создает драйвер и отображает мир, пока пользователь не выйдет (клавиша "q"
+    /// или Esc).
+    ///
+    /// # Arguments
+    ///
+    /// * `width`: Ширина мира.
+    /// * `height`: Высота мира.
+    /// * `receiver`: Канал для получения данных.
+    /// * `control`: Канал для отправки управляющих сообщений миру.
+    /// * `title`: Заглавие.
+    ///
+    /// returns: Result<(), String>
+    pub(crate) fn run(
+        width: usize,
+        height: usize,
+        receiver: Receiver<Frame>,
+        control: Sender<ControlMessage>,
+        title: &str
+    ) -> Result<(), String> {
+        // Драйверу нужны все слои карты на каждой итерации.
+        let _ = control.send(ControlMessage::FrameRequest(FrameRequest::default()));
+
+        let mut console = Console {
+            receiver,
+            control,
+            world_size: (width, height),
+            title: title.to_owned(),
+            tick: None,
+            stdout: io::stdout(),
+        };
+
+        // Терминал восстанавливается при выходе из функции, в том числе по ошибке.
+        let _guard = TerminalGuard::enter(&mut console.stdout)?;
+
+        console.event_loop().map_err(|e| e.to_string())
+    }
+
+    /// Цикл отображения: обрабатывает нажатия клавиш и перерисовывает экран
+    /// на каждом полученном кадре.
+    fn event_loop(&mut self) -> io::Result<()> {
+        self.draw_status()?;
+
+        loop {
+            if event::poll(Duration::from_millis(POLL_MS))? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind != KeyEventKind::Press {
+                        continue;
+                    }
+
+                    match key.code {
+                        KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                        // В режиме raw Ctrl+C не прерывает программу.
+                        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            return Ok(());
+                        }
+                        // Перезапуск мира: кадры, отправленные до перезапуска, не нужны.
+                        KeyCode::Char('r') => {
+                            while self.receiver.try_recv().is_ok() {}
+                            self.tick = None;
+
+                            // Если мир уже завершил работу, перезапускать нечего.
+                            let _ = self.control.send(ControlMessage::Restart);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+
+            // Отображаем только самый свежий кадр, более старые пропускаем.
+            let mut latest = None;
+            while let Ok(frame) = self.receiver.try_recv() {
+                latest = Some(frame);
+            }
+
+            if let Some(frame) = latest {
+                self.draw(&frame)?;
+            }
+        }
+    }
+
+    /// Перерисовывает экран по кадру. Часть мира, которая не помещается в терминал,
+    /// не отображается.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame`: Кадр.
+    ///
+    /// returns: io::Result<()>
+    fn draw(&mut self, frame: &Frame) -> io::Result<()> {
+        let mut grid = vec![vec![EMPTY_GLYPH; self.world_size.0]; self.world_size.1];
+        // Ячейки, выводимые в инверсном цвете.
+        let mut reversed = vec![vec![false; self.world_size.0]; self.world_size.1];
+        for (x, y, stuff, _) in &frame.map {
+            if let Some(sprite) = render_model::sprite(*stuff) {
+                if let Some(cell) = grid.get_mut(*y).and_then(|row| row.get_mut(*x)) {
+                    *cell = glyph(sprite);
+                    reversed[*y][*x] = vertical(sprite);
+                }
+            }
+        }
+
+        // Отметки пользователя рисуются поверх всего остального: подсвеченные
+        // ячейки и линии - цветом фона, надписи - вместо содержимого ячеек.
+        let mut highlights: Vec<Vec<Option<Rgba>>> = vec![vec![None; self.world_size.0]; self.world_size.1];
+        for annotation in &frame.annotations {
+            match annotation {
+                Annotation::CellHighlight { x, y, color } => {
+                    if let Some(cell) = highlights.get_mut(*y).and_then(|row| row.get_mut(*x)) {
+                        *cell = Some(*color);
+                    }
+                }
+                Annotation::Line { from, to, color } => {
+                    for (x, y) in render_model::line_cells(*from, *to) {
+                        if let Some(cell) = highlights.get_mut(y).and_then(|row| row.get_mut(x)) {
+                            *cell = Some(*color);
+                        }
+                    }
+                }
+                Annotation::Text { x, y, string } => {
+                    if let Some(row) = grid.get_mut(*y) {
+                        for (cell, symbol) in row.iter_mut().skip(*x).zip(string.chars()) {
+                            *cell = symbol;
+                        }
+                    }
+                }
+            }
+        }
+
+        self.tick = Some(frame.tick);
+        self.draw_status()?;
+
+        // Первая строка терминала занята заглавием.
+        let (columns, rows) = terminal::size()?;
+        for (y, row) in grid.iter().take(rows.saturating_sub(1) as usize).enumerate() {
+            queue!(self.stdout, cursor::MoveTo(0, y as u16 + 1))?;
+
+            // Строка без подсветки и инверсных ячеек выводится целиком.
+            let marks = &highlights[y];
+            let inverse = &reversed[y];
+            if marks.iter().all(Option::is_none) && !inverse.contains(&true) {
+                let line: String = row.iter().take(columns as usize).collect();
+                queue!(self.stdout, Print(line))?;
+                continue;
+            }
+
+            for ((symbol, mark), inverse) in row.iter().zip(marks).zip(inverse).take(columns as usize) {
+                if *inverse {
+                    queue!(self.stdout, SetAttribute(Attribute::Reverse))?;
+                }
+                match mark {
+                    Some(tint) => queue!(self.stdout, SetBackgroundColor(terminal_color(*tint)), Print(symbol), ResetColor)?,
+                    None => queue!(self.stdout, Print(symbol))?,
+                }
+                if *inverse {
+                    queue!(self.stdout, SetAttribute(Attribute::NoReverse))?;
+                }
+            }
+        }
+
+        self.stdout.flush()
+    }
+
+    /// Выводит строку заглавия с номером итерации и подсказкой по клавишам.
+    fn draw_status(&mut self) -> io::Result<()> {
+        let tick = match self.tick {
+            Some(tick) => format!("итерация {}", tick),
+            None => "ожидание кадра".to_string(),
+        };
+
+        queue!(
+            self.stdout,
+            cursor::MoveTo(0, 0),
+            terminal::Clear(terminal::ClearType::CurrentLine),
+            Print(format!("{} - {} (q - выход, r - перезапуск)", self.title, tick))
+        )?;
+
+        self.stdout.flush()
+    }
+}
+
+/// Переводит терминал в режим отображения (альтернативный экран, raw режим, курсор
+/// скрыт) и возвращает его в исходное состояние при уничтожении.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    /// Переводит терминал в режим отображения.
+    ///
+    /// # Arguments
+    ///
+    /// * `stdout`: Вывод в терминал.
+    ///
+    /// returns: Result<TerminalGuard, String>
+    fn enter(stdout: &mut Stdout) -> Result<TerminalGuard, String> {
+        terminal::enable_raw_mode().map_err(|e| e.to_string())?;
+
+        // Терминал уже в raw режиме: восстановим его, даже если дальше что-то не выйдет.
+        let guard = TerminalGuard;
+        execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide).map_err(|e| e.to_string())?;
+
+        Ok(guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Символ изображения.
+fn glyph(sprite: Sprite) -> char {
+    match sprite {
+        Sprite::KilledAnimal => KILLED_ANIMAL_GLYPH,
+        Sprite::DeadAnimal => DEAD_ANIMAL_GLYPH,
+        Sprite::Herbivore(facing) => HERBIVORE_GLYPHS[facing_index(facing)],
+        Sprite::Carnivore(facing) => CARNIVORE_GLYPHS[facing_index(facing)],
+        Sprite::Omnivore(facing) => OMNIVORE_GLYPHS[facing_index(facing)],
+        Sprite::Carcass => CARCASS_GLYPH,
+        Sprite::Plant => PLANT_GLYPH,
+        Sprite::Obstacle => OBSTACLE_GLYPH,
+    }
+}
+
+/// Выводится ли изображение в инверсном цвете: животное смотрит по вертикали.
+fn vertical(sprite: Sprite) -> bool {
+    matches!(
+        sprite,
+        Sprite::Herbivore(Facing::Front | Facing::Back)
+            | Sprite::Carnivore(Facing::Front | Facing::Back)
+            | Sprite::Omnivore(Facing::Front | Facing::Back)
+    )
+}
+
+/// Цвет терминала для цвета отметки (прозрачность не учитывается).
+fn terminal_color(tint: Rgba) -> Color {
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+    Color::Rgb { r: channel(tint.0), g: channel(tint.1), b: channel(tint.2) }
+}
+
+/// Индекс символа животного для направления.
+fn facing_index(facing: Facing) -> usize {
+    match facing {
+        Facing::Left => 0,
+        Facing::Right => 1,
+        Facing::Front => 2,
+        Facing::Back => 3,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FACINGS: [Facing; 4] = [Facing::Left, Facing::Right, Facing::Front, Facing::Back];
+
+    #[test]
+    fn every_facing_is_distinguishable() {
+        let species: [fn(Facing) -> Sprite; 3] = [Sprite::Herbivore, Sprite::Carnivore, Sprite::Omnivore];
+        for new_sprite in species {
+            let looks: Vec<(char, bool)> = FACINGS.iter()
+                .map(|facing| (glyph(new_sprite(*facing)), vertical(new_sprite(*facing))))
+                .collect();
+            for (i, a) in looks.iter().enumerate() {
+                for b in &looks[i + 1..] {
+                    assert_ne!(a, b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn left_and_right_differ_by_case() {
+        assert_eq!(glyph(Sprite::Herbivore(Facing::Left)), 'h');
+        assert_eq!(glyph(Sprite::Herbivore(Facing::Right)), 'H');
+        assert_eq!(glyph(Sprite::Carnivore(Facing::Left)), 'c');
+        assert_eq!(glyph(Sprite::Carnivore(Facing::Right)), 'C');
+        assert!(!vertical(Sprite::Herbivore(Facing::Left)));
+        assert!(vertical(Sprite::Herbivore(Facing::Back)));
+        assert!(!vertical(Sprite::Plant));
+    }
+}
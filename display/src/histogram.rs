@@ -0,0 +1,112 @@
+//! Гистограмма значений с фиксированными границами корзин.
+
+use std::fmt;
+
+/// Гистограмма. Корзина `i` содержит значения меньше `bounds[i]` (и не меньше
+/// предыдущей границы), последняя корзина - значения не меньше последней границы.
+#[derive(Clone)]
+pub struct Histogram {
+    // Верхние границы корзин (по возрастанию).
+    bounds: Vec<u64>,
+    // Количество значений в каждой корзине (на одну больше, чем границ).
+    counts: Vec<u64>,
+}
+
+impl Histogram {
+    /// Конструктор. Создает пустую гистограмму.
+    ///
+    /// # Arguments
+    ///
+    /// * `bounds`: Верхние границы корзин, упорядоченные по возрастанию.
+    ///
+    /// returns: Histogram
+    pub fn new(bounds: &[u64]) -> Histogram {
+        Histogram {
+            bounds: bounds.to_vec(),
+            counts: vec![0; bounds.len() + 1],
+        }
+    }
+
+    /// Учитывает значение.
+    pub fn record(&mut self, value: u64) {
+        let index = self.bounds.iter()
+            .position(|bound| value < *bound)
+            .unwrap_or(self.bounds.len());
+
+        self.counts[index] += 1;
+    }
+
+    /// Количество значений в каждой корзине.
+    pub fn counts(&self) -> &[u64] {
+        &self.counts
+    }
+
+    /// Общее количество учтенных значений.
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+}
+
+impl fmt::Display for Histogram {
+    /// Текстовая таблица: диапазон корзины и количество значений в ней.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut lower = 0;
+
+        for (index, count) in self.counts.iter().enumerate() {
+            match self.bounds.get(index) {
+                Some(upper) => {
+                    writeln!(f, "{:>6} - {:<6} {}", lower, upper, count)?;
+                    lower = *upper;
+                }
+                None => writeln!(f, "{:>6} +        {}", lower, count)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_on_bounds_go_to_next_bucket() {
+        let mut histogram = Histogram::new(&[10, 20]);
+        for value in [0, 9, 10, 19, 20, 1000] {
+            histogram.record(value);
+        }
+
+        assert_eq!(histogram.counts(), &[2, 2, 2]);
+        assert_eq!(histogram.total(), 6);
+    }
+
+    #[test]
+    fn empty_histogram_has_zero_counts() {
+        let histogram = Histogram::new(&[10, 20]);
+
+        assert_eq!(histogram.counts(), &[0, 0, 0]);
+        assert_eq!(histogram.total(), 0);
+        assert_eq!(histogram.to_string(), "     0 - 10     0\n    10 - 20     0\n    20 +        0\n");
+    }
+
+    #[test]
+    fn single_value_fills_one_bucket() {
+        let mut histogram = Histogram::new(&[10, 20]);
+        histogram.record(15);
+
+        assert_eq!(histogram.counts(), &[0, 1, 0]);
+        assert_eq!(histogram.total(), 1);
+        assert_eq!(histogram.to_string(), "     0 - 10     0\n    10 - 20     1\n    20 +        0\n");
+    }
+
+    #[test]
+    fn histogram_without_bounds_has_single_bucket() {
+        let mut histogram = Histogram::new(&[]);
+        histogram.record(0);
+        histogram.record(u64::MAX);
+
+        assert_eq!(histogram.counts(), &[2]);
+        assert_eq!(histogram.to_string(), "     0 +        2\n");
+    }
+}
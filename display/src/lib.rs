@@ -1,7 +1,18 @@
 
+use std::env;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::Receiver;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::console::Console;
+use crate::render_model::Rgba;
 use crate::tetra::Window;
 
+pub use crate::tetra::{AssetSource, DirectoryAssets, EvolutionView, ViewConfig};
+
+mod console;
+pub mod histogram;
+pub mod rate;
+pub mod render_model;
 mod tetra;
 
 /// Перечисление определяет как образом можно отобразить ячейку.
@@ -18,39 +29,303 @@ pub enum CellStuff {
     CarnRight,
     CarnFront,
     CarnBack,
+    OmniLeft,
+    OmniRight,
+    OmniFront,
+    OmniBack,
+    /// Падаль (останки умерших от голода животных).
+    Carcass,
     Plant,
+    /// Непроходимая ячейка (скала, вода).
+    Obstacle,
     None,
 }
 
 // Синонимы типов
-pub type Point = (usize, usize, CellStuff);
+
+/// Отметки животного в ячейке, которые отображаются поверх его изображения.
+#[derive(Copy, Clone, PartialEq, Default, Debug)]
+pub struct CellMarks {
+    /// Животное принадлежит отслеживаемой династии.
+    pub dynasty: bool,
+    /// Молодое животное, защищенное от хищников.
+    pub juvenile: bool,
+}
+
+/// Точка карты: координаты ячейки, что в ней отображается и отметки животного в ячейке.
+pub type Point = (usize, usize, CellStuff, CellMarks);
 
 pub type Map = Vec<Point>;
 
+/// Событие в ячейке мира, которое отображается кратковременным эффектом поверх
+/// содержимого ячейки.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum EventKind {
+    /// Хищник убил жертву (координаты жертвы).
+    Kill,
+    /// Родилось животное (координаты новорожденного).
+    Birth,
+}
+
+/// Событие в ячейке: координаты ячейки и вид события.
+pub type CellEvent = (usize, usize, EventKind);
+
+/// Отметка поверх изображения мира, которую добавляет пользователь библиотеки
+/// (например, ячейки, важные для его анализа). Координаты - ячейки мира. Мир сам
+/// отметок не создает; драйверы рисуют их поверх всего остального.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Annotation {
+    /// Подсвеченная ячейка.
+    CellHighlight { x: usize, y: usize, color: Rgba },
+    /// Надпись, которая начинается в ячейке.
+    Text { x: usize, y: usize, string: String },
+    /// Линия между центрами двух ячеек.
+    Line { from: (usize, usize), to: (usize, usize), color: Rgba },
+}
+
+/// Ячейки мира, которые воспринимает животное, по областям восприятия (с учетом
+/// склейки краев и стен). Область близости - ячейки, соседние с животным.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct PerceptionFootprint {
+    /// Область впереди.
+    pub front: Vec<(usize, usize)>,
+    /// Область слева.
+    pub left: Vec<(usize, usize)>,
+    /// Область справа.
+    pub right: Vec<(usize, usize)>,
+    /// Область близости.
+    pub proximity: Vec<(usize, usize)>,
+}
+
+/// Кадр, который мир отправляет отображению: состояние мира и отметка времени
+/// отправки. Отметка состоит из номера кадра и системного времени в миллисекундах,
+/// что позволяет сравнивать ее вне процесса (в отличие от `Instant`).
+pub struct Frame {
+    // Номер кадра (монотонно возрастает).
+    pub sequence: u64,
+    // Номер итерации мира, состояние после которой передает кадр (после перезапуска
+    // мира начинается заново).
+    pub tick: u64,
+    // Время отправки кадра в миллисекундах от начала эпохи UNIX.
+    pub sent_at_ms: u64,
+    // Состояние мира.
+    pub map: Map,
+    // События в ячейках с момента предыдущего кадра.
+    pub transient_events: Vec<CellEvent>,
+    // Ответ на запрос истории численности (`ControlMessage::GetHistory`), если
+    // запрос пришел после предыдущего кадра.
+    pub history: Option<HistoryResponse>,
+    // Отметки пользователя библиотеки поверх изображения мира.
+    pub annotations: Vec<Annotation>,
+    // Ячейки, которые воспринимает выбранное животное, если отображение запросило
+    // их (`ControlMessage::ShowPerception`) и животное живо.
+    pub perception: Option<PerceptionFootprint>,
+}
+
+impl Frame {
+    /// Создает кадр с отметкой текущего времени.
+    ///
+    /// # Arguments
+    ///
+    /// * `sequence`: Номер кадра.
+    /// * `tick`: Номер итерации мира.
+    /// * `map`: Состояние мира.
+    /// * `transient_events`: События в ячейках с момента предыдущего кадра.
+    ///
+    /// returns: Frame
+    pub fn new(sequence: u64, tick: u64, map: Map, transient_events: Vec<CellEvent>) -> Frame {
+        Frame {
+            sequence,
+            tick,
+            sent_at_ms: now_ms(),
+            map,
+            transient_events,
+            history: None,
+            annotations: Vec::new(),
+            perception: None,
+        }
+    }
+
+    /// Возраст кадра (сколько миллисекунд прошло с момента отправки).
+    pub fn age_ms(&self) -> u64 {
+        now_ms().saturating_sub(self.sent_at_ms)
+    }
+}
+
+/// Численность травоядных, хищников и всеядных на одной итерации.
+pub type Population = (usize, usize, usize);
+
+/// Ответ мира на запрос истории численности видов.
+#[derive(Clone, PartialEq, Debug)]
+pub struct HistoryResponse {
+    /// Номер итерации, с которой начинается история.
+    pub first_tick: u64,
+    /// Численность видов на последовательных итерациях (от старых к новым).
+    pub populations: Vec<Population>,
+}
+
+/// Текущее системное время в миллисекундах от начала эпохи UNIX.
+pub(crate) fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Топология мира: что происходит с агентом, пересекающим край сетки.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Topology {
+    /// Края сетки склеены (тор): агент, ушедший за край, появляется с другой стороны.
+    Torus,
+    /// Края сетки - непроходимая стена.
+    Bounded,
+    /// Края склеены только по выбранным осям: по оси со склейкой агент, ушедший за
+    /// край, появляется с другой стороны, по оси без склейки упирается в стену.
+    /// Например, `wrap_x: true, wrap_y: false` - цилиндр с полюсами сверху и снизу.
+    Cylinder { wrap_x: bool, wrap_y: bool },
+}
+
+impl Topology {
+    /// Склеены ли края сетки по оси "x" (левый и правый).
+    pub fn wraps_x(&self) -> bool {
+        match *self {
+            Topology::Torus => true,
+            Topology::Bounded => false,
+            Topology::Cylinder { wrap_x, .. } => wrap_x,
+        }
+    }
+
+    /// Склеены ли края сетки по оси "y" (верхний и нижний).
+    pub fn wraps_y(&self) -> bool {
+        match *self {
+            Topology::Torus => true,
+            Topology::Bounded => false,
+            Topology::Cylinder { wrap_y, .. } => wrap_y,
+        }
+    }
+}
+
+/// Слои карты, которые отображение хочет получать в кадрах.
+#[derive(Copy, Clone, PartialEq)]
+pub struct LayerSet {
+    /// Растения.
+    pub plants: bool,
+    /// Живые животные.
+    pub animals: bool,
+    /// Умершие и убитые животные.
+    pub corpses: bool,
+    /// Принадлежность животных отслеживаемой династии.
+    pub dynasty: bool,
+}
+
+impl LayerSet {
+    /// Все слои.
+    pub const ALL: LayerSet = LayerSet {
+        plants: true,
+        animals: true,
+        corpses: true,
+        dynasty: true,
+    };
+}
+
+impl Default for LayerSet {
+    fn default() -> Self {
+        LayerSet::ALL
+    }
+}
+
+/// Запрос отображения к миру: какие слои карты собирать и как часто отправлять кадры.
+#[derive(Copy, Clone, PartialEq)]
+pub struct FrameRequest {
+    /// Слои карты, которые следует включать в кадр.
+    pub layers: LayerSet,
+    /// Кадр отправляется на каждой n-й итерации (0 и 1 - на каждой).
+    pub every_nth_tick: u32,
+}
+
+impl Default for FrameRequest {
+    fn default() -> Self {
+        FrameRequest {
+            layers: LayerSet::ALL,
+            every_nth_tick: 1,
+        }
+    }
+}
+
+/// Управляющие сообщения, которые отображение отправляет миру.
+#[derive(Copy, Clone)]
+pub enum ControlMessage {
+    /// Перезапустить мир с теми же настройками.
+    Restart,
+    /// Выбрать животное в ячейке с заданными координатами и отслеживать его династию.
+    FollowDynasty(usize, usize),
+    /// Изменить энергию выбранного животного на заданную величину (отладка).
+    AdjustEnergy(f32),
+    /// Сохранить текущее состояние мира в файл сценария.
+    ExportScenario,
+    /// Задать содержимое и частоту кадров. Драйвер отправляет запрос сразу после
+    /// запуска; до запроса мир отправляет все слои на каждой итерации.
+    FrameRequest(FrameRequest),
+    /// Запросить численность видов за последние n итераций. Ответ приходит в поле
+    /// `history` следующего кадра.
+    GetHistory(usize),
+    /// Включить или выключить передачу в кадрах ячеек, которые воспринимает
+    /// выбранное животное (поле `perception` кадра).
+    ShowPerception(bool),
+}
+
+/// Возвращает путь по умолчанию к директории, в которой лежит директория ресурсов
+/// `resources`: ближайшая к исполняемому файлу (сам его каталог или один из
+/// родительских), иначе корень исходников программы.
+///
+/// returns: String
+pub fn default_base_path() -> String {
+    // Корень рабочего пространства: родитель каталога этого пакета.
+    let workspace = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from("."));
+
+    let exe_dirs: Vec<PathBuf> = env::current_exe()
+        .map(|exe| exe.ancestors().skip(1).map(Path::to_path_buf).collect())
+        .unwrap_or_default();
+
+    exe_dirs.into_iter()
+        .find(|dir| dir.join("resources").is_dir())
+        .unwrap_or(workspace)
+        .to_string_lossy()
+        .into_owned()
+}
+
 /// Перечисление с типами драйверов.
 pub enum ScreenType {
     Tetra,
+    /// Символы в терминале (не требует окна, работает через SSH).
+    Console,
 }
 
+/// Отображает мир, пока пользователь не закроет окно или не выйдет из терминала.
+///
+/// # Arguments
+///
+/// * `screen_type`: Драйвер отображения.
+/// * `config`: Настройки отображения (драйвер терминала берет из них только размеры мира, канал и заглавие).
+/// * `receiver`: Канал для получения данных.
+///
+/// returns: Result<(), String> - ошибка, если настройки неверны или отображение не
+/// удалось создать.
 pub fn launch_screen(
     screen_type: ScreenType,
-    width: usize,
-    height: usize,
-    receiver: Receiver<Map>,
-    base_path: &str,
-    title: &str,
+    config: ViewConfig,
+    receiver: Receiver<Frame>,
 ) -> Result<(), String> {
+    config.validate()?;
+
     match screen_type {
-        ScreenType::Tetra => {
-            Window::new(
-                width,
-                height,
-                receiver,
-                base_path,
-                title
-            )?;
-
-            Ok(())
+        ScreenType::Tetra => Window::run(config, receiver),
+        ScreenType::Console => {
+            let title = config.title.unwrap_or_default();
+            Console::run(config.width, config.height, receiver, config.control, &title)
         }
     }
 }
\ No newline at end of file
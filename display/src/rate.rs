@@ -0,0 +1,129 @@
+//! Оценка частоты событий по скользящему окну.
+
+use std::collections::VecDeque;
+
+/// Оценивает частоту роста счетчика (например, итераций мира) в секунду по
+/// отсчетам за последние `window_ms` миллисекунд.
+pub struct RateMeter {
+    // Ширина окна в миллисекундах.
+    window_ms: u64,
+    // Отсчеты: время в миллисекундах и значение счетчика.
+    samples: VecDeque<(u64, u64)>,
+}
+
+impl RateMeter {
+    /// Конструктор.
+    ///
+    /// # Arguments
+    ///
+    /// * `window_ms`: Ширина окна в миллисекундах.
+    ///
+    /// returns: RateMeter
+    pub fn new(window_ms: u64) -> RateMeter {
+        RateMeter {
+            window_ms: window_ms.max(1),
+            samples: VecDeque::new(),
+        }
+    }
+
+    /// Добавляет отсчет. Если счетчик уменьшился (мир перезапущен) или время пошло
+    /// назад, прежние отсчеты отбрасываются. Отсчеты старше окна отбрасываются,
+    /// поэтому после паузы оценка не учитывает время до паузы.
+    ///
+    /// # Arguments
+    ///
+    /// * `now_ms`: Время отсчета в миллисекундах.
+    /// * `count`: Значение счетчика.
+    ///
+    /// returns: ()
+    pub fn record(&mut self, now_ms: u64, count: u64) {
+        if let Some(&(time, last)) = self.samples.back() {
+            if count < last || now_ms < time {
+                self.samples.clear();
+            }
+        }
+
+        self.samples.push_back((now_ms, count));
+
+        while let Some(&(time, _)) = self.samples.front() {
+            if now_ms - time > self.window_ms {
+                self.samples.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Частота в секунду. None - в окне меньше двух отсчетов или они получены в
+    /// одну и ту же миллисекунду.
+    pub fn rate(&self) -> Option<f64> {
+        let (first_time, first_count) = *self.samples.front()?;
+        let (last_time, last_count) = *self.samples.back()?;
+
+        if last_time == first_time {
+            return None;
+        }
+
+        Some((last_count - first_count) as f64 * 1000.0 / (last_time - first_time) as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_is_count_growth_per_second() {
+        let mut meter = RateMeter::new(1000);
+        meter.record(0, 0);
+        meter.record(250, 10);
+        meter.record(500, 30);
+
+        assert_eq!(meter.rate(), Some(60.0));
+    }
+
+    #[test]
+    fn rate_needs_two_distinct_times() {
+        let mut meter = RateMeter::new(1000);
+        assert_eq!(meter.rate(), None);
+
+        meter.record(100, 5);
+        assert_eq!(meter.rate(), None);
+
+        meter.record(100, 7);
+        assert_eq!(meter.rate(), None);
+    }
+
+    #[test]
+    fn samples_older_than_window_are_dropped() {
+        let mut meter = RateMeter::new(1000);
+        meter.record(0, 0);
+        meter.record(1000, 100);
+        // Отсчет на границе окна еще учитывается.
+        assert_eq!(meter.rate(), Some(100.0));
+
+        // После паузы в окне остается только последний отсчет.
+        meter.record(5000, 110);
+        assert_eq!(meter.rate(), None);
+
+        meter.record(5500, 160);
+        assert_eq!(meter.rate(), Some(100.0));
+    }
+
+    #[test]
+    fn counter_reset_or_clock_going_back_restarts_estimate() {
+        let mut meter = RateMeter::new(1000);
+        meter.record(0, 100);
+        meter.record(500, 200);
+
+        // Мир перезапущен: счетчик уменьшился.
+        meter.record(600, 0);
+        assert_eq!(meter.rate(), None);
+        meter.record(800, 10);
+        assert_eq!(meter.rate(), Some(50.0));
+
+        // Время пошло назад.
+        meter.record(100, 20);
+        assert_eq!(meter.rate(), None);
+    }
+}
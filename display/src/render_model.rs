@@ -0,0 +1,1016 @@
+//! Модель отображения: вычисления, не зависящие от графической библиотеки.
+//!
+//! Кадр мира и состояние окна преобразуются в список команд рисования. Команды -
+//! это обычные данные (координаты, изображения, цвета), поэтому их можно проверить
+//! без видеокарты или исполнить другим драйвером (например, при выгрузке кадра в файл).
+
+use crate::{Annotation, CellEvent, CellStuff, EventKind, PerceptionFootprint, Point, Population, Topology};
+
+// Монитор, размеры которого неизвестны (окно еще не создано).
+pub const DEFAULT_MONITOR: Monitor = Monitor { width: 1920, height: 1080, dpi_scale: 1.0 };
+
+pub const DYNASTY_COLOR: Rgba = Rgba(1.0, 0.843, 0.0, 1.0);
+
+pub const JUVENILE_COLOR: Rgba = Rgba(0.3, 0.8, 1.0, 1.0);
+
+pub const BORDER_COLOR: Rgba = Rgba(0.2, 0.2, 0.2, 1.0);
+
+pub const SEAM_COLOR: Rgba = Rgba(1.0, 1.0, 1.0, 0.6);
+
+pub const LATENCY_COLOR: Rgba = Rgba(1.0, 0.0, 0.0, 0.7);
+
+pub const SPAWN_MARGIN_COLOR: Rgba = Rgba(0.0, 0.0, 0.0, 0.25);
+
+pub const KILL_EFFECT_COLOR: Rgba = Rgba(0.9, 0.1, 0.1, 0.8);
+
+pub const BIRTH_EFFECT_COLOR: Rgba = Rgba(0.2, 0.9, 0.3, 0.8);
+
+/// Всеядное рисуется изображением хищника, окрашенным этим цветом.
+pub const OMNIVORE_TINT: Rgba = Rgba(0.6, 0.4, 1.0, 1.0);
+
+// Цвета линий численности видов на графике истории.
+pub const HISTORY_HERBIVORE_COLOR: Rgba = Rgba(0.2, 0.9, 0.3, 1.0);
+
+pub const HISTORY_CARNIVORE_COLOR: Rgba = Rgba(0.9, 0.1, 0.1, 1.0);
+
+pub const HISTORY_OMNIVORE_COLOR: Rgba = OMNIVORE_TINT;
+
+pub const HISTORY_BACKGROUND_COLOR: Rgba = Rgba(0.0, 0.0, 0.0, 0.4);
+
+pub const ANNOTATION_TEXT_COLOR: Rgba = Rgba(1.0, 1.0, 1.0, 1.0);
+
+// Рамки ячеек, которые воспринимает выбранное животное: впереди, слева, справа и
+// область близости.
+pub const PERCEPTION_FRONT_COLOR: Rgba = Rgba(1.0, 1.0, 0.2, 0.9);
+pub const PERCEPTION_LEFT_COLOR: Rgba = Rgba(0.2, 0.6, 1.0, 0.9);
+pub const PERCEPTION_RIGHT_COLOR: Rgba = Rgba(1.0, 0.4, 0.8, 0.9);
+pub const PERCEPTION_PROXIMITY_COLOR: Rgba = Rgba(1.0, 1.0, 1.0, 0.9);
+
+// Размеры графика истории численности на экране (пиксели): ширина совпадает
+// с количеством столбцов графика, толщина линии - высота точки столбца.
+const HISTORY_WIDTH: usize = 200;
+const HISTORY_HEIGHT: f32 = 60.0;
+const HISTORY_LINE: f32 = 2.0;
+
+// Сторона отметки молодого животного в углу ячейки (доля размера ячейки).
+const JUVENILE_MARK: f32 = 0.25;
+
+// Размеры столбца гистограммы на экране (пиксели).
+const LATENCY_BAR_WIDTH: f32 = 20.0;
+const LATENCY_BAR_HEIGHT: f32 = 100.0;
+const LATENCY_BAR_GAP: f32 = 2.0;
+
+/// Цвет: красный, зеленый, синий и прозрачность в диапазоне [0, 1].
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Rgba(pub f32, pub f32, pub f32, pub f32);
+
+/// Возможные варианты размера текстур.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum TextureSize {
+    Size63 = 63,
+    Size40 = 40,
+    Size20 = 20,
+}
+
+use self::TextureSize::*;
+const TEXTURE_SIZES: [TextureSize; 3] = [Size63, Size40, Size20];
+
+impl TextureSize {
+    /// Возвращает размер текстур по количеству пикселей. None - текстур такого
+    /// размера нет.
+    ///
+    /// # Arguments
+    ///
+    /// * `pixels`: Сторона текстуры в пикселях.
+    ///
+    /// returns: Option<TextureSize>
+    pub fn from_pixels(pixels: usize) -> Option<TextureSize> {
+        TEXTURE_SIZES.into_iter().find(|size| *size as usize == pixels)
+    }
+}
+
+/// Монитор, на котором открывается окно.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Monitor {
+    /// Размеры монитора в физических пикселях.
+    pub width: i32,
+    pub height: i32,
+    /// Количество физических пикселей в единице размера окна (больше единицы на
+    /// мониторах высокой плотности при включенном high DPI).
+    pub dpi_scale: f32,
+}
+
+/// Размеры окна и изображения мира.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct WindowLayout {
+    /// Размеры окна в единицах окна.
+    pub width: i32,
+    pub height: i32,
+    /// Размер текстур (он же размер ячейки до масштабирования).
+    pub texture_size: TextureSize,
+    /// Масштаб изображения мира в окне.
+    pub scale: f32,
+}
+
+/// Направление животного на изображении. Порядок совпадает с порядком загрузки
+/// текстур животных.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Facing {
+    Left,
+    Right,
+    Front,
+    Back,
+}
+
+/// Ось сетки мира.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Axis {
+    X,
+    Y,
+}
+
+/// Изображение, которым отображается содержимое ячейки.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Sprite {
+    KilledAnimal,
+    DeadAnimal,
+    Herbivore(Facing),
+    Carnivore(Facing),
+    Omnivore(Facing),
+    Carcass,
+    Plant,
+    Obstacle,
+}
+
+/// Команда рисования. Координаты - экранные, в пикселях.
+#[derive(Clone, PartialEq, Debug)]
+pub enum DrawCommand {
+    /// Изображение в позиции (x, y).
+    Sprite { sprite: Sprite, x: f32, y: f32 },
+    /// Рамка размером с ячейку в позиции (x, y).
+    Outline { x: f32, y: f32, tint: Rgba },
+    /// Стена по краям мира вдоль оси без склейки: левый и правый края для оси "x",
+    /// верхний и нижний для оси "y".
+    Border { axis: Axis, tint: Rgba },
+    /// Шов по оси со склейкой: линия x = 0 для оси "x", линия y = 0 для оси "y".
+    Seam { axis: Axis, tint: Rgba },
+    /// Закрашенный прямоугольник.
+    Bar { x: f32, y: f32, width: f32, height: f32, tint: Rgba },
+    /// Отрезок между двумя точками.
+    Line { from: (f32, f32), to: (f32, f32), tint: Rgba },
+    /// Надпись, левый верхний угол которой находится в позиции (x, y).
+    Text { x: f32, y: f32, text: String, tint: Rgba },
+}
+
+/// Состояние окна, от которого зависит отображение кадра.
+#[derive(Copy, Clone)]
+pub struct ViewState {
+    /// Размер текстур (он же размер ячейки на экране).
+    pub texture_size: TextureSize,
+    /// Размеры мира в ячейках.
+    pub world_size: (usize, usize),
+    /// Топология мира.
+    pub topology: Topology,
+    /// Ширина полосы вдоль краев мира, в которой агенты не размещаются.
+    pub spawn_margin: usize,
+    /// Отображать стену по краю ограниченного мира.
+    pub show_border: bool,
+    /// Отображать шов тора.
+    pub show_seam: bool,
+    /// Отображать гистограмму задержки.
+    pub show_latency: bool,
+    /// Отображать график истории численности видов.
+    pub show_history: bool,
+}
+
+/// Кратковременный эффект события в ячейке.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Effect {
+    /// Координаты ячейки.
+    pub x: usize,
+    pub y: usize,
+    /// Вид события.
+    pub kind: EventKind,
+    /// Сколько кадров отображения эффект еще длится.
+    pub frames_left: u32,
+}
+
+/// Эффекты событий, которые отображаются в данный момент. Эффект затухает
+/// с каждым кадром отображения, независимо от частоты кадров мира.
+pub struct Effects {
+    // Продолжительность эффекта в кадрах отображения.
+    lifetime: u32,
+    // Текущие эффекты.
+    effects: Vec<Effect>,
+}
+
+impl Effects {
+    /// Конструктор.
+    ///
+    /// # Arguments
+    ///
+    /// * `lifetime`: Продолжительность эффекта в кадрах отображения.
+    ///
+    /// returns: Effects
+    pub fn new(lifetime: u32) -> Effects {
+        Effects {
+            lifetime: lifetime.max(1),
+            effects: Vec::new(),
+        }
+    }
+
+    /// Добавляет эффекты событий из кадра мира.
+    ///
+    /// # Arguments
+    ///
+    /// * `events`: События в ячейках.
+    ///
+    /// returns: ()
+    pub fn add(&mut self, events: &[CellEvent]) {
+        for &(x, y, kind) in events {
+            self.effects.push(Effect { x, y, kind, frames_left: self.lifetime });
+        }
+    }
+
+    /// Прошел кадр отображения: эффекты затухают, закончившиеся удаляются.
+    pub fn advance(&mut self) {
+        for effect in &mut self.effects {
+            effect.frames_left -= 1;
+        }
+
+        self.effects.retain(|effect| effect.frames_left > 0);
+    }
+
+    /// Текущие эффекты.
+    pub fn effects(&self) -> &[Effect] {
+        &self.effects
+    }
+
+    /// Команды рисования эффектов: ячейка закрашивается цветом события, прозрачность
+    /// убывает по мере затухания эффекта.
+    ///
+    /// # Arguments
+    ///
+    /// * `texture_size`: Размер текстур.
+    ///
+    /// returns: Vec<DrawCommand>
+    pub fn commands(&self, texture_size: TextureSize) -> Vec<DrawCommand> {
+        let size = texture_size as usize as f32;
+
+        self.effects
+            .iter()
+            .map(|effect| {
+                let (x, y) = world_to_screen(texture_size, effect.x, effect.y);
+                let mut tint = effect_color(effect.kind);
+                tint.3 *= effect.frames_left as f32 / self.lifetime as f32;
+
+                DrawCommand::Bar { x, y, width: size, height: size, tint }
+            })
+            .collect()
+    }
+}
+
+/// Цвет эффекта события.
+///
+/// # Arguments
+///
+/// * `kind`: Вид события.
+///
+/// returns: Rgba
+pub fn effect_color(kind: EventKind) -> Rgba {
+    match kind {
+        EventKind::Kill => KILL_EFFECT_COLOR,
+        EventKind::Birth => BIRTH_EFFECT_COLOR,
+    }
+}
+
+/// Цвет прямоугольника, которым рисуется изображение, если файла его текстуры нет.
+///
+/// # Arguments
+///
+/// * `sprite`: Изображение.
+///
+/// returns: Rgba
+pub fn fallback_color(sprite: Sprite) -> Rgba {
+    match sprite {
+        Sprite::KilledAnimal => Rgba(0.6, 0.0, 0.0, 1.0),
+        Sprite::DeadAnimal => Rgba(0.5, 0.5, 0.5, 1.0),
+        Sprite::Herbivore(_) => Rgba(1.0, 1.0, 1.0, 1.0),
+        Sprite::Carnivore(_) => Rgba(0.2, 0.2, 0.2, 1.0),
+        Sprite::Omnivore(_) => OMNIVORE_TINT,
+        Sprite::Carcass => Rgba(0.55, 0.35, 0.2, 1.0),
+        Sprite::Plant => Rgba(0.1, 0.6, 0.1, 1.0),
+        Sprite::Obstacle => Rgba(0.45, 0.45, 0.47, 1.0),
+    }
+}
+
+/// Выбирает размеры окна, размер текстур и масштаб изображения мира. Без заданного
+/// размера текстур выбирается наибольший, при котором окно помещается на монитор;
+/// заданные размер текстур и масштаб используются как есть, даже если окно больше
+/// монитора.
+///
+/// # Arguments
+///
+/// * `width`: Ширина мира.
+/// * `height`: Высота мира.
+/// * `monitor`: Монитор.
+/// * `texture_size`: Размер текстур. None - выбирается по монитору.
+/// * `scale`: Масштаб изображения мира. None - без масштабирования.
+///
+/// returns: Result<WindowLayout, String> - ошибка, если масштаб не положителен или
+/// мир не помещается на монитор ни с одним размером текстур.
+pub fn window_layout(
+    width: usize,
+    height: usize,
+    monitor: Monitor,
+    texture_size: Option<TextureSize>,
+    scale: Option<f32>
+) -> Result<WindowLayout, String> {
+    let scale = validate_scale(scale)?;
+
+    let layout = |size: TextureSize| {
+        let cell = size as usize as f32 * scale;
+        WindowLayout {
+            width: (width as f32 * cell).round() as i32,
+            height: (height as f32 * cell).round() as i32,
+            texture_size: size,
+            scale,
+        }
+    };
+
+    if let Some(size) = texture_size {
+        return Ok(layout(size));
+    }
+
+    // Размеры монитора в единицах окна.
+    let dpi_scale = if monitor.dpi_scale > 0.0 { monitor.dpi_scale } else { 1.0 };
+    let max_width = (monitor.width as f32 / dpi_scale).floor() as i32;
+    let max_height = (monitor.height as f32 / dpi_scale).floor() as i32;
+
+    TEXTURE_SIZES.into_iter()
+        .map(layout)
+        .find(|layout| layout.width <= max_width && layout.height <= max_height)
+        .ok_or_else(|| "Мир слишком велик ".to_string())
+}
+
+/// Проверяет масштаб изображения мира.
+///
+/// # Arguments
+///
+/// * `scale`: Масштаб изображения мира. None - без масштабирования.
+///
+/// returns: Result<f32, String> - масштаб (1.0 без масштабирования) или ошибка, если
+/// масштаб не является конечным положительным числом.
+pub fn validate_scale(scale: Option<f32>) -> Result<f32, String> {
+    match scale {
+        None => Ok(1.0),
+        Some(scale) if scale.is_finite() && scale > 0.0 => Ok(scale),
+        Some(scale) => Err(format!("Масштаб изображения мира ({}) должен быть положительным числом", scale)),
+    }
+}
+
+/// Преобразует координаты мира в экранные координаты.
+///
+/// # Arguments
+///
+/// * `texture_size`: Размер текстур.
+/// * `x`: Координата ячейки по горизонтали.
+/// * `y`: Координата ячейки по вертикали.
+///
+/// returns: (f32, f32)
+pub fn world_to_screen(texture_size: TextureSize, x: usize, y: usize) -> (f32, f32) {
+    let size = texture_size as usize;
+
+    ((x * size) as f32, (y * size) as f32)
+}
+
+/// Преобразует экранные координаты в координаты ячейки мира. None - точка
+/// вне окна.
+///
+/// # Arguments
+///
+/// * `texture_size`: Размер текстур.
+/// * `x`: Экранная координата по горизонтали.
+/// * `y`: Экранная координата по вертикали.
+///
+/// returns: Option<(usize, usize)>
+pub fn screen_to_world(texture_size: TextureSize, x: f32, y: f32) -> Option<(usize, usize)> {
+    if x < 0.0 || y < 0.0 {
+        return None;
+    }
+
+    let size = texture_size as usize;
+
+    Some((x as usize / size, y as usize / size))
+}
+
+/// Возвращает изображение для содержимого ячейки. None - ячейка пуста.
+///
+/// # Arguments
+///
+/// * `stuff`: Содержимое ячейки.
+///
+/// returns: Option<Sprite>
+pub fn sprite(stuff: CellStuff) -> Option<Sprite> {
+    match stuff {
+        CellStuff::KilledAnimal => Some(Sprite::KilledAnimal),
+        CellStuff::DeadAnimal => Some(Sprite::DeadAnimal),
+        CellStuff::HerbLeft => Some(Sprite::Herbivore(Facing::Left)),
+        CellStuff::HerbRight => Some(Sprite::Herbivore(Facing::Right)),
+        CellStuff::HerbFront => Some(Sprite::Herbivore(Facing::Front)),
+        CellStuff::HerbBack => Some(Sprite::Herbivore(Facing::Back)),
+        CellStuff::CarnLeft => Some(Sprite::Carnivore(Facing::Left)),
+        CellStuff::CarnRight => Some(Sprite::Carnivore(Facing::Right)),
+        CellStuff::CarnFront => Some(Sprite::Carnivore(Facing::Front)),
+        CellStuff::CarnBack => Some(Sprite::Carnivore(Facing::Back)),
+        CellStuff::OmniLeft => Some(Sprite::Omnivore(Facing::Left)),
+        CellStuff::OmniRight => Some(Sprite::Omnivore(Facing::Right)),
+        CellStuff::OmniFront => Some(Sprite::Omnivore(Facing::Front)),
+        CellStuff::OmniBack => Some(Sprite::Omnivore(Facing::Back)),
+        CellStuff::Carcass => Some(Sprite::Carcass),
+        CellStuff::Plant => Some(Sprite::Plant),
+        CellStuff::Obstacle => Some(Sprite::Obstacle),
+        CellStuff::None => None,
+    }
+}
+
+/// Строит список команд рисования кадра.
+///
+/// # Arguments
+///
+/// * `view`: Состояние окна.
+/// * `map`: Карта мира из кадра.
+/// * `latency`: Количества кадров в корзинах гистограммы задержки.
+/// * `history`: Численность видов на последних итерациях (от старых к новым).
+///
+/// returns: Vec<DrawCommand>
+pub fn build(view: &ViewState, map: &[Point], latency: &[u64], history: &[Population]) -> Vec<DrawCommand> {
+    let mut commands = Vec::with_capacity(map.len() + latency.len() + 5);
+
+    // Полоса у краев мира затемняется под агентами.
+    commands.extend(margin_bars(view));
+
+    for p in map {
+        let (x, y) = world_to_screen(view.texture_size, p.0, p.1);
+
+        if let Some(sprite) = sprite(p.2) {
+            commands.push(DrawCommand::Sprite { sprite, x, y });
+        }
+
+        // Молодое животное, защищенное от хищников, отмечаем в углу ячейки.
+        if p.3.juvenile {
+            let side = view.texture_size as usize as f32 * JUVENILE_MARK;
+            commands.push(DrawCommand::Bar { x, y, width: side, height: side, tint: JUVENILE_COLOR });
+        }
+
+        // Животное отслеживаемой династии обводим рамкой.
+        if p.3.dynasty {
+            commands.push(DrawCommand::Outline { x, y, tint: DYNASTY_COLOR });
+        }
+    }
+
+    // Края мира зависят от топологии: шов по оси со склейкой, стена по оси без нее.
+    commands.extend(edge_commands(view));
+
+    if view.show_latency {
+        commands.extend(latency_bars(latency));
+    }
+
+    if view.show_history {
+        commands.extend(history_chart(view, history));
+    }
+
+    commands
+}
+
+/// Команды рисования краев мира по осям: шов, если края оси склеены, иначе стена.
+///
+/// # Arguments
+///
+/// * `view`: Состояние окна.
+///
+/// returns: Vec<DrawCommand>
+fn edge_commands(view: &ViewState) -> Vec<DrawCommand> {
+    let axes = [
+        (Axis::X, view.topology.wraps_x()),
+        (Axis::Y, view.topology.wraps_y()),
+    ];
+
+    let mut commands = Vec::with_capacity(axes.len());
+    for (axis, wraps) in axes {
+        if wraps && view.show_seam {
+            commands.push(DrawCommand::Seam { axis, tint: SEAM_COLOR });
+        } else if !wraps && view.show_border {
+            commands.push(DrawCommand::Border { axis, tint: BORDER_COLOR });
+        }
+    }
+
+    commands
+}
+
+/// Затемненные полосы вдоль краев мира, где агенты не размещаются: верхняя и нижняя
+/// во всю ширину, левая и правая между ними.
+///
+/// # Arguments
+///
+/// * `view`: Состояние окна.
+///
+/// returns: Vec<DrawCommand>
+fn margin_bars(view: &ViewState) -> Vec<DrawCommand> {
+    if view.spawn_margin == 0 {
+        return Vec::new();
+    }
+
+    let (width, height) = world_to_screen(view.texture_size, view.world_size.0, view.world_size.1);
+    let (margin, _) = world_to_screen(view.texture_size, view.spawn_margin, 0);
+    let bar = |x, y, width, height| DrawCommand::Bar { x, y, width, height, tint: SPAWN_MARGIN_COLOR };
+
+    vec![
+        bar(0.0, 0.0, width, margin),
+        bar(0.0, height - margin, width, margin),
+        bar(0.0, margin, margin, height - 2.0 * margin),
+        bar(width - margin, margin, margin, height - 2.0 * margin),
+    ]
+}
+
+/// Сжимает ряд значений до заданного количества столбцов: значение столбца -
+/// среднее значений, попавших в столбец. Если значений не больше, чем столбцов,
+/// каждому значению соответствует свой столбец.
+///
+/// # Arguments
+///
+/// * `values`: Значения (от старых к новым).
+/// * `columns`: Количество столбцов.
+///
+/// returns: Vec<f32>
+pub fn downsample(values: &[usize], columns: usize) -> Vec<f32> {
+    if columns == 0 || values.len() <= columns {
+        return values.iter().map(|value| *value as f32).collect();
+    }
+
+    (0..columns)
+        .map(|column| {
+            let start = column * values.len() / columns;
+            let end = (column + 1) * values.len() / columns;
+            let bucket = &values[start..end];
+
+            bucket.iter().sum::<usize>() as f32 / bucket.len() as f32
+        })
+        .collect()
+}
+
+/// График истории численности видов в левом нижнем углу мира: по линии на вид,
+/// по столбцу на пиксель ширины графика. Высота графика соответствует наибольшей
+/// численности вида за историю.
+///
+/// # Arguments
+///
+/// * `view`: Состояние окна.
+/// * `history`: Численность видов на последних итерациях (от старых к новым).
+///
+/// returns: Vec<DrawCommand>
+fn history_chart(view: &ViewState, history: &[Population]) -> Vec<DrawCommand> {
+    let max = history.iter().map(|p| p.0.max(p.1).max(p.2)).max().unwrap_or(0);
+    if max == 0 {
+        return Vec::new();
+    }
+
+    let bottom = (view.world_size.1 * view.texture_size as usize) as f32;
+    let top = bottom - HISTORY_HEIGHT - HISTORY_LINE;
+
+    let mut commands = vec![DrawCommand::Bar {
+        x: 0.0,
+        y: top,
+        width: HISTORY_WIDTH as f32,
+        height: HISTORY_HEIGHT + HISTORY_LINE,
+        tint: HISTORY_BACKGROUND_COLOR,
+    }];
+
+    let species: [(Vec<usize>, Rgba); 3] = [
+        (history.iter().map(|p| p.0).collect(), HISTORY_HERBIVORE_COLOR),
+        (history.iter().map(|p| p.1).collect(), HISTORY_CARNIVORE_COLOR),
+        (history.iter().map(|p| p.2).collect(), HISTORY_OMNIVORE_COLOR),
+    ];
+
+    for (values, tint) in species.iter() {
+        let columns = downsample(values, HISTORY_WIDTH);
+        // Короткая история растягивается на всю ширину графика.
+        let width = HISTORY_WIDTH as f32 / columns.len() as f32;
+
+        commands.extend(columns.iter().enumerate().map(|(index, value)| DrawCommand::Bar {
+            x: index as f32 * width,
+            y: bottom - HISTORY_LINE - HISTORY_HEIGHT * value / max as f32,
+            width,
+            height: HISTORY_LINE,
+            tint: *tint,
+        }));
+    }
+
+    commands
+}
+
+/// Столбцы гистограммы задержки в левом верхнем углу: по столбцу на корзину,
+/// высота столбца пропорциональна количеству кадров в корзине.
+///
+/// # Arguments
+///
+/// * `counts`: Количества кадров в корзинах.
+///
+/// returns: Vec<DrawCommand>
+fn latency_bars(counts: &[u64]) -> Vec<DrawCommand> {
+    let max = counts.iter().copied().max().unwrap_or(0);
+    if max == 0 {
+        return Vec::new();
+    }
+
+    counts
+        .iter()
+        .enumerate()
+        .map(|(index, count)| {
+            let height = LATENCY_BAR_HEIGHT * *count as f32 / max as f32;
+
+            DrawCommand::Bar {
+                x: index as f32 * (LATENCY_BAR_WIDTH + LATENCY_BAR_GAP),
+                y: LATENCY_BAR_HEIGHT - height,
+                width: LATENCY_BAR_WIDTH,
+                height,
+                tint: LATENCY_COLOR,
+            }
+        })
+        .collect()
+}
+
+/// Команды рисования отметок пользователя библиотеки. Отметки рисуются поверх
+/// всего остального, поэтому их команды исполняются последними.
+///
+/// # Arguments
+///
+/// * `view`: Состояние окна.
+/// * `annotations`: Отметки из кадра.
+///
+/// returns: Vec<DrawCommand>
+pub fn annotation_commands(view: &ViewState, annotations: &[Annotation]) -> Vec<DrawCommand> {
+    let size = view.texture_size as usize as f32;
+    let center = |cell: (usize, usize)| {
+        let (x, y) = world_to_screen(view.texture_size, cell.0, cell.1);
+        (x + size / 2.0, y + size / 2.0)
+    };
+
+    annotations
+        .iter()
+        .map(|annotation| match annotation {
+            Annotation::CellHighlight { x, y, color } => {
+                let (x, y) = world_to_screen(view.texture_size, *x, *y);
+                DrawCommand::Bar { x, y, width: size, height: size, tint: *color }
+            }
+            Annotation::Text { x, y, string } => {
+                let (x, y) = world_to_screen(view.texture_size, *x, *y);
+                DrawCommand::Text { x, y, text: string.clone(), tint: ANNOTATION_TEXT_COLOR }
+            }
+            Annotation::Line { from, to, color } => {
+                DrawCommand::Line { from: center(*from), to: center(*to), tint: *color }
+            }
+        })
+        .collect()
+}
+
+/// Команды рисования рамок вокруг ячеек, которые воспринимает выбранное животное.
+/// Цвет рамки зависит от области восприятия.
+///
+/// # Arguments
+///
+/// * `view`: Состояние окна.
+/// * `footprint`: Ячейки областей восприятия из кадра.
+///
+/// returns: Vec<DrawCommand>
+pub fn perception_commands(view: &ViewState, footprint: &PerceptionFootprint) -> Vec<DrawCommand> {
+    let regions = [
+        (&footprint.front, PERCEPTION_FRONT_COLOR),
+        (&footprint.left, PERCEPTION_LEFT_COLOR),
+        (&footprint.right, PERCEPTION_RIGHT_COLOR),
+        (&footprint.proximity, PERCEPTION_PROXIMITY_COLOR),
+    ];
+
+    regions
+        .iter()
+        .flat_map(|(cells, tint)| cells.iter().map(move |&(x, y)| {
+            let (x, y) = world_to_screen(view.texture_size, x, y);
+            DrawCommand::Outline { x, y, tint: *tint }
+        }))
+        .collect()
+}
+
+/// Ячейки, через которые проходит линия между двумя ячейками (алгоритм Брезенхэма),
+/// включая начальную и конечную. Нужны драйверам, которые рисуют ячейками.
+///
+/// # Arguments
+///
+/// * `from`: Начальная ячейка.
+/// * `to`: Конечная ячейка.
+///
+/// returns: Vec<(usize, usize)>
+pub fn line_cells(from: (usize, usize), to: (usize, usize)) -> Vec<(usize, usize)> {
+    let (mut x, mut y) = (from.0 as isize, from.1 as isize);
+    let (x1, y1) = (to.0 as isize, to.1 as isize);
+    let (dx, dy) = ((x1 - x).abs(), -(y1 - y).abs());
+    let (sx, sy) = ((x1 - x).signum(), (y1 - y).signum());
+    let mut error = dx + dy;
+
+    let mut cells = Vec::with_capacity((dx - dy) as usize + 1);
+    loop {
+        cells.push((x as usize, y as usize));
+        if x == x1 && y == y1 {
+            return cells;
+        }
+
+        let doubled = 2 * error;
+        if doubled >= dy {
+            error += dy;
+            x += sx;
+        }
+        if doubled <= dx {
+            error += dx;
+            y += sy;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CellMarks;
+
+    const MONITOR_4K: Monitor = Monitor { width: 3840, height: 2160, dpi_scale: 1.0 };
+    const MONITOR_4K_HIGH_DPI: Monitor = Monitor { width: 3840, height: 2160, dpi_scale: 2.0 };
+    const MONITOR_LAPTOP: Monitor = Monitor { width: 1366, height: 768, dpi_scale: 1.0 };
+
+    #[test]
+    fn largest_fitting_texture_is_selected() {
+        let cases = [
+            // Мир по умолчанию помещается на монитор 1920x1080 только с мелкими текстурами.
+            (96, 54, DEFAULT_MONITOR, Size20, (1920, 1080)),
+            (30, 17, DEFAULT_MONITOR, Size63, (1890, 1071)),
+            (40, 27, DEFAULT_MONITOR, Size40, (1600, 1080)),
+            (96, 54, MONITOR_4K, Size40, (3840, 2160)),
+            // На мониторе высокой плотности окно измеряется в логических единицах.
+            (96, 54, MONITOR_4K_HIGH_DPI, Size20, (1920, 1080)),
+            (60, 30, MONITOR_4K, Size63, (3780, 1890)),
+            (60, 30, MONITOR_LAPTOP, Size20, (1200, 600)),
+        ];
+
+        for (width, height, monitor, size, window) in cases {
+            let layout = window_layout(width, height, monitor, None, None).unwrap();
+            assert_eq!(layout.texture_size, size, "мир {}x{}, монитор {:?}", width, height, monitor);
+            assert_eq!((layout.width, layout.height), window, "мир {}x{}, монитор {:?}", width, height, monitor);
+            assert_eq!(layout.scale, 1.0);
+        }
+    }
+
+    #[test]
+    fn too_large_world_is_an_error() {
+        assert!(window_layout(200, 100, DEFAULT_MONITOR, None, None).is_err());
+        assert!(window_layout(96, 54, MONITOR_LAPTOP, None, None).is_err());
+    }
+
+    #[test]
+    fn unknown_dpi_scale_is_ignored() {
+        let monitor = Monitor { width: 1920, height: 1080, dpi_scale: 0.0 };
+
+        assert_eq!(
+            window_layout(96, 54, monitor, None, None),
+            window_layout(96, 54, DEFAULT_MONITOR, None, None)
+        );
+    }
+
+    #[test]
+    fn overrides_are_used_as_is() {
+        // Заданные размер текстур и масштаб не зависят от монитора.
+        let layout = window_layout(96, 54, MONITOR_LAPTOP, Some(Size40), Some(2.0)).unwrap();
+
+        assert_eq!(layout, WindowLayout { width: 7680, height: 4320, texture_size: Size40, scale: 2.0 });
+    }
+
+    #[test]
+    fn scale_applies_to_selection() {
+        // С двукратным масштабом мир занимает вдвое больше места и получает текстуры мельче.
+        let layout = window_layout(40, 27, MONITOR_4K, None, Some(2.0)).unwrap();
+
+        assert_eq!(layout.texture_size, Size40);
+        assert_eq!((layout.width, layout.height), (3200, 2160));
+    }
+
+    #[test]
+    fn non_positive_or_nan_scale_is_rejected() {
+        for scale in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            assert!(validate_scale(Some(scale)).is_err(), "масштаб {}", scale);
+            assert!(window_layout(10, 10, DEFAULT_MONITOR, None, Some(scale)).is_err(), "масштаб {}", scale);
+        }
+        assert_eq!(validate_scale(None), Ok(1.0));
+    }
+
+    #[test]
+    fn downsample_averages_buckets() {
+        assert_eq!(downsample(&[1, 3, 5, 7], 2), vec![2.0, 6.0]);
+        // Неравные столбцы: 5 значений в 2 столбца.
+        assert_eq!(downsample(&[1, 1, 4, 4, 4], 2), vec![1.0, 4.0]);
+    }
+
+    #[test]
+    fn short_series_is_not_downsampled() {
+        assert_eq!(downsample(&[1, 2, 3], 5), vec![1.0, 2.0, 3.0]);
+        assert_eq!(downsample(&[1, 2, 3], 0), vec![1.0, 2.0, 3.0]);
+        assert_eq!(downsample(&[], 4), Vec::<f32>::new());
+    }
+
+    #[test]
+    fn downsample_produces_requested_columns() {
+        let values: Vec<usize> = (0..1000).collect();
+        let columns = downsample(&values, 200);
+
+        assert_eq!(columns.len(), 200);
+        assert_eq!(columns[0], 2.0);
+        assert_eq!(columns[199], 997.0);
+    }
+
+    fn view(texture_size: TextureSize) -> ViewState {
+        ViewState {
+            texture_size,
+            world_size: (10, 10),
+            topology: Topology::Torus,
+            spawn_margin: 0,
+            show_border: false,
+            show_seam: false,
+            show_latency: false,
+            show_history: false,
+        }
+    }
+
+    #[test]
+    fn world_edges_follow_topology() {
+        let edges = |topology, show_border, show_seam| {
+            edge_commands(&ViewState { topology, show_border, show_seam, ..view(Size20) })
+        };
+        let border = |axis| DrawCommand::Border { axis, tint: BORDER_COLOR };
+        let seam = |axis| DrawCommand::Seam { axis, tint: SEAM_COLOR };
+
+        assert_eq!(edges(Topology::Torus, true, true), vec![seam(Axis::X), seam(Axis::Y)]);
+        assert_eq!(edges(Topology::Bounded, true, true), vec![border(Axis::X), border(Axis::Y)]);
+        let cylinder = Topology::Cylinder { wrap_x: true, wrap_y: false };
+        assert_eq!(edges(cylinder, true, true), vec![seam(Axis::X), border(Axis::Y)]);
+
+        // Выключенные шов и стена не рисуются.
+        assert!(edges(Topology::Torus, true, false).is_empty());
+        assert!(edges(Topology::Bounded, false, true).is_empty());
+        assert_eq!(edges(cylinder, false, true), vec![seam(Axis::X)]);
+    }
+
+    #[test]
+    fn frame_builds_golden_command_list() {
+        // Ограниченный мир 4x3 с полосой у краев, задержкой и историей численности.
+        let view = ViewState {
+            world_size: (4, 3),
+            topology: Topology::Bounded,
+            spawn_margin: 1,
+            show_border: true,
+            show_seam: true,
+            show_latency: true,
+            show_history: true,
+            ..view(Size20)
+        };
+        let marks = |dynasty, juvenile| CellMarks { dynasty, juvenile };
+        let map = [
+            (1, 1, CellStuff::HerbFront, marks(true, true)),
+            (2, 1, CellStuff::Plant, marks(false, false)),
+            (3, 2, CellStuff::None, marks(true, false)),
+            (0, 0, CellStuff::Obstacle, marks(false, false)),
+        ];
+
+        let commands = build(&view, &map, &[2, 4], &[(2, 0, 1)]);
+
+        let bar = |x, y, width, height, tint| DrawCommand::Bar { x, y, width, height, tint };
+        assert_eq!(commands, vec![
+            // Полоса у краев мира.
+            bar(0.0, 0.0, 80.0, 20.0, SPAWN_MARGIN_COLOR),
+            bar(0.0, 40.0, 80.0, 20.0, SPAWN_MARGIN_COLOR),
+            bar(0.0, 20.0, 20.0, 20.0, SPAWN_MARGIN_COLOR),
+            bar(60.0, 20.0, 20.0, 20.0, SPAWN_MARGIN_COLOR),
+            // Ячейки карты с отметками.
+            DrawCommand::Sprite { sprite: Sprite::Herbivore(Facing::Front), x: 20.0, y: 20.0 },
+            bar(20.0, 20.0, 5.0, 5.0, JUVENILE_COLOR),
+            DrawCommand::Outline { x: 20.0, y: 20.0, tint: DYNASTY_COLOR },
+            DrawCommand::Sprite { sprite: Sprite::Plant, x: 40.0, y: 20.0 },
+            DrawCommand::Outline { x: 60.0, y: 40.0, tint: DYNASTY_COLOR },
+            DrawCommand::Sprite { sprite: Sprite::Obstacle, x: 0.0, y: 0.0 },
+            // Края мира.
+            DrawCommand::Border { axis: Axis::X, tint: BORDER_COLOR },
+            DrawCommand::Border { axis: Axis::Y, tint: BORDER_COLOR },
+            // Гистограмма задержки.
+            bar(0.0, 50.0, 20.0, 50.0, LATENCY_COLOR),
+            bar(22.0, 0.0, 20.0, 100.0, LATENCY_COLOR),
+            // История численности.
+            bar(0.0, -2.0, 200.0, 62.0, HISTORY_BACKGROUND_COLOR),
+            bar(0.0, -2.0, 200.0, 2.0, HISTORY_HERBIVORE_COLOR),
+            bar(0.0, 58.0, 200.0, 2.0, HISTORY_CARNIVORE_COLOR),
+            bar(0.0, 28.0, 200.0, 2.0, HISTORY_OMNIVORE_COLOR),
+        ]);
+    }
+
+    #[test]
+    fn annotations_become_commands_in_cell_pixels() {
+        let red = Rgba(1.0, 0.0, 0.0, 1.0);
+        let annotations = [
+            Annotation::CellHighlight { x: 1, y: 2, color: red },
+            Annotation::Text { x: 3, y: 0, string: "метка".to_string() },
+            Annotation::Line { from: (0, 0), to: (2, 1), color: red },
+        ];
+
+        let commands = annotation_commands(&view(Size20), &annotations);
+
+        assert_eq!(commands, vec![
+            DrawCommand::Bar { x: 20.0, y: 40.0, width: 20.0, height: 20.0, tint: red },
+            DrawCommand::Text { x: 60.0, y: 0.0, text: "метка".to_string(), tint: ANNOTATION_TEXT_COLOR },
+            DrawCommand::Line { from: (10.0, 10.0), to: (50.0, 30.0), tint: red },
+        ]);
+    }
+
+    #[test]
+    fn no_annotations_no_commands() {
+        assert!(annotation_commands(&view(Size63), &[]).is_empty());
+    }
+
+    #[test]
+    fn perception_regions_are_outlined_in_their_colors() {
+        let footprint = PerceptionFootprint {
+            front: vec![(1, 0)],
+            left: vec![(0, 1)],
+            right: vec![(2, 1)],
+            proximity: vec![(1, 1)],
+        };
+
+        let commands = perception_commands(&view(Size40), &footprint);
+
+        assert_eq!(commands, vec![
+            DrawCommand::Outline { x: 40.0, y: 0.0, tint: PERCEPTION_FRONT_COLOR },
+            DrawCommand::Outline { x: 0.0, y: 40.0, tint: PERCEPTION_LEFT_COLOR },
+            DrawCommand::Outline { x: 80.0, y: 40.0, tint: PERCEPTION_RIGHT_COLOR },
+            DrawCommand::Outline { x: 40.0, y: 40.0, tint: PERCEPTION_PROXIMITY_COLOR },
+        ]);
+    }
+
+    #[test]
+    fn killed_animal_has_its_own_sprite() {
+        assert_eq!(sprite(CellStuff::KilledAnimal), Some(Sprite::KilledAnimal));
+        assert_ne!(fallback_color(Sprite::KilledAnimal), fallback_color(Sprite::DeadAnimal));
+    }
+
+    #[test]
+    fn event_effect_lasts_effect_frames() {
+        let mut effects = Effects::new(3);
+        effects.add(&[(2, 1, EventKind::Kill)]);
+        assert_eq!(effects.effects(), &[Effect { x: 2, y: 1, kind: EventKind::Kill, frames_left: 3 }]);
+
+        // Эффект отображается три кадра и затухает.
+        for (frames_left, alpha) in [(3, 0.8), (2, 0.8 * 2.0 / 3.0), (1, 0.8 / 3.0)] {
+            assert_eq!(effects.effects()[0].frames_left, frames_left);
+            match effects.commands(Size20).as_slice() {
+                [DrawCommand::Bar { x, y, width, height, tint }] => {
+                    assert_eq!((*x, *y, *width, *height), (40.0, 20.0, 20.0, 20.0));
+                    assert_eq!((tint.0, tint.1, tint.2), (KILL_EFFECT_COLOR.0, KILL_EFFECT_COLOR.1, KILL_EFFECT_COLOR.2));
+                    assert!((tint.3 - alpha).abs() < 1e-6, "{}", tint.3);
+                }
+                commands => panic!("ожидалась одна полоса, получено {:?}", commands),
+            }
+            effects.advance();
+        }
+
+        assert!(effects.effects().is_empty());
+        assert!(effects.commands(Size20).is_empty());
+    }
+
+    #[test]
+    fn effects_expire_independently() {
+        let mut effects = Effects::new(2);
+        effects.add(&[(0, 0, EventKind::Birth)]);
+        effects.advance();
+        effects.add(&[(1, 1, EventKind::Kill), (2, 2, EventKind::Birth)]);
+
+        effects.advance();
+        assert_eq!(effects.effects(), &[
+            Effect { x: 1, y: 1, kind: EventKind::Kill, frames_left: 1 },
+            Effect { x: 2, y: 2, kind: EventKind::Birth, frames_left: 1 },
+        ]);
+
+        effects.advance();
+        assert!(effects.effects().is_empty());
+    }
+
+    #[test]
+    fn zero_effect_frames_last_one_frame() {
+        let mut effects = Effects::new(0);
+        effects.add(&[(0, 0, EventKind::Birth)]);
+        assert_eq!(effects.commands(Size20).len(), 1);
+
+        effects.advance();
+        assert!(effects.effects().is_empty());
+    }
+}
@@ -1,14 +1,19 @@
-use std::sync::mpsc::Receiver;
-use crate::{CellStuff, Map};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::sync::mpsc::{Receiver, Sender};
+use crate::{now_ms, ControlMessage, Frame, FrameRequest, Point, Population, Topology};
+use crate::histogram::Histogram;
+use crate::rate::RateMeter;
+use crate::render_model::{self, Axis, DrawCommand, Effects, Facing, Monitor, Rgba, OMNIVORE_TINT, Sprite, TextureSize, ViewState, WindowLayout, DEFAULT_MONITOR};
 
-use tetra::graphics::{self, Color, Texture};
+use tetra::graphics::{self, Color, DrawParams, Rectangle, Texture};
+use tetra::graphics::mesh::{GeometryBuilder, Mesh, ShapeStyle};
+use tetra::graphics::text::{Font, Text};
+use tetra::input::{self, Key, MouseButton};
 use tetra::math::Vec2;
-use tetra::{Context, ContextBuilder, State};
-use tetra::error::Result as TetraResult;
-
-const MAX_WIDTH_SIZE: usize = 1920;
-
-const MAX_HEIGHT_SIZE: usize = 1080;
+use tetra::{time, window, Context, ContextBuilder, State};
+use tetra::error::{Result as TetraResult, TetraError};
 
 const CARNIVORE_NAME: &str = "wolf";
 
@@ -16,127 +21,392 @@ const HERBIVORE_NAME: &str = "sheep";
 
 const ANIMAL_DIRECTIONS: [&str; 4] = ["left", "right", "front", "back"];
 
+// Изображения без направлений (см. `EvolutionView::build`): убитое и умершее животное,
+// растение, туша, скала.
+const TEXTURE_NAMES: [&str; 5] = ["blood", "ghost", "plant", "carcass", "rock"];
+
 const BACKGROUND_COLOR:Color = Color::rgb(0.392, 0.584, 0.929);
 
-/// Возможные варианты размера текстур.
-#[derive(Copy, Clone)]
-enum TextureSize {
-    Size63 = 63,
-    Size40 = 40,
-    Size20 = 20,
+const BORDER_WIDTH: f32 = 6.0;
+
+const SEAM_WIDTH: f32 = 1.0;
+
+// Толщина линии отметки пользователя (пиксели).
+const ANNOTATION_LINE_WIDTH: f32 = 2.0;
+
+// Шрифт надписей отметок пользователя в ресурсах и его размер (пиксели).
+const ANNOTATION_FONT: &str = "font.ttf";
+const ANNOTATION_FONT_SIZE: f32 = 14.0;
+
+// Данные шрифта надписей, загруженные при открытии первого окна.
+static FONT_DATA: OnceLock<Box<[u8]>> = OnceLock::new();
+
+// Границы корзин гистограммы возраста отображаемого кадра (миллисекунды).
+const LATENCY_BOUNDS: [u64; 8] = [5, 10, 20, 50, 100, 200, 500, 1000];
+
+// Изменение энергии выбранного животного клавишами "вверх" и "вниз".
+const ENERGY_STEP: f32 = 5.0;
+
+// Сколько последних итераций показывает график истории численности (клавиша G)
+// и как часто (миллисекунды) он обновляется.
+const HISTORY_TICKS: usize = 500;
+const HISTORY_REFRESH_MS: u64 = 1000;
+
+// Ширина окна (миллисекунды), по которому оценивается скорость мира.
+const TPS_WINDOW_MS: u64 = 2000;
+
+// Размеры окна до выбора размеров по монитору, если мир не помещается на монитор
+// по умолчанию.
+const INITIAL_WIDTH: i32 = 640;
+const INITIAL_HEIGHT: i32 = 480;
+
+/// Объем сведений о производительности в заглавии окна (клавиша H).
+#[derive(Copy, Clone, PartialEq)]
+enum HudMode {
+    /// Сведения не выводятся.
+    Off,
+    /// Частота кадров отображения и скорость мира.
+    Basic,
+    /// Дополнительно количество пропущенных кадров и гистограмма задержки.
+    Detailed,
 }
 
-use self::TextureSize::*;
-const TEXTURE_SIZES:[TextureSize; 3] = [Size63, Size40, Size20];
+impl HudMode {
+    /// Следующий режим по кругу.
+    fn next(self) -> HudMode {
+        match self {
+            HudMode::Off => HudMode::Basic,
+            HudMode::Basic => HudMode::Detailed,
+            HudMode::Detailed => HudMode::Off,
+        }
+    }
+}
 
-pub struct Window {
-    // Канал для получения данных о состоянии мира.
-    receiver: Receiver<Map>,
+/// Источник изображений текстур.
+pub trait AssetSource {
+    /// Возвращает содержимое изображения (PNG) или шрифта (TTF).
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: Путь к файлу относительно ресурсов, например `plant/40.png`.
+    ///
+    /// returns: Option<Vec<u8>> - None, если файла нет.
+    fn load(&self, name: &str) -> Option<Vec<u8>>;
+}
+
+/// Изображения из директории ресурсов `resources` на диске.
+pub struct DirectoryAssets {
+    // Директория ресурсов.
+    root: PathBuf,
+}
+
+impl DirectoryAssets {
+    /// Конструктор.
+    ///
+    /// # Arguments
+    ///
+    /// * `base_path`: Директория, в которой лежит директория ресурсов `resources`.
+    ///
+    /// returns: DirectoryAssets
+    pub fn new(base_path: &str) -> DirectoryAssets {
+        DirectoryAssets { root: PathBuf::from(base_path).join("resources") }
+    }
+}
+
+impl AssetSource for DirectoryAssets {
+    fn load(&self, name: &str) -> Option<Vec<u8>> {
+        fs::read(self.root.join(name)).ok()
+    }
+}
+
+/// Настройки отображения мира.
+pub struct ViewConfig {
+    /// Размеры мира в ячейках.
+    pub width: usize,
+    pub height: usize,
+    /// Топология мира.
+    pub topology: Topology,
+    /// Ширина полосы вдоль краев мира, в которой агенты не размещаются.
+    pub spawn_margin: usize,
+    /// Продолжительность эффектов событий в кадрах отображения.
+    pub effect_frames: u32,
+    /// Канал для отправки управляющих сообщений миру.
+    pub control: Sender<ControlMessage>,
+    /// Размер текстур. None - наибольший, при котором мир помещается на монитор
+    /// окна.
+    pub texture_size: Option<TextureSize>,
+    /// Источник изображений текстур.
+    pub assets: Box<dyn AssetSource>,
+    /// Заглавие окна, в котором выводятся сведения о производительности (клавиша H).
+    /// None - отображение встроено в чужое окно, заглавие не меняется.
+    pub title: Option<String>,
+    /// Масштаб изображения мира в отдельном окне (`launch_screen`). None - без
+    /// масштабирования. Встроенное отображение масштабирует программа-хозяин.
+    pub scale: Option<f32>,
+}
+
+impl ViewConfig {
+    /// Проверяет настройки отображения.
+    ///
+    /// returns: Result<(), String> - ошибка, если мир пуст или масштаб не положителен.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.width == 0 || self.height == 0 {
+            return Err(format!("Размеры мира ({}, {}) должны быть больше нуля", self.width, self.height));
+        }
+
+        render_model::validate_scale(self.scale).map(|_| ())
+    }
+}
+
+/// Отображение мира. Может работать как отдельное окно (`launch_screen`) или быть
+/// встроенным в другую программу на tetra: программа вызывает `update` и `draw` из
+/// своих методов типажа `State` и задает область окна, в которой рисуется мир.
+pub struct EvolutionView {
+    // Канал для отправки управляющих сообщений миру.
+    control: Sender<ControlMessage>,
+
+    // Размеры мира в ячейках.
+    world_size: (usize, usize),
+
+    // Топология мира.
+    topology: Topology,
+
+    // Ширина полосы вдоль краев мира, в которой агенты не размещаются.
+    spawn_margin: usize,
+
+    // Продолжительность эффектов событий в кадрах отображения.
+    effect_frames: u32,
 
-    // Путь до файлов с изображениями текстур.
-    asset_path: String,
+    // Отображать стену по краю ограниченного мира (клавиша B).
+    show_border: bool,
+
+    // Отображать шов тора по линиям x = 0 и y = 0 (клавиша S).
+    show_seam: bool,
+
+    // Размеры изображения мира без масштабирования.
+    natural_size: (f32, f32),
+
+    // Область окна, в которой мир был нарисован в последний раз.
+    dest: Rectangle,
 
     // Размер текстур.
     texture_size: TextureSize,
 
-    // Поля, для хранения текстур.
-    killed_animal_texture: Texture,
-    dead_animal_texture: Texture,
-    herbivore_texture: Vec<Texture>,
-    carnivore_texture: Vec<Texture>,
-    plant_texture: Texture,
+    // Поля, для хранения текстур. None - файла текстуры нет, изображение рисуется
+    // цветным прямоугольником.
+    killed_animal_texture: Option<Texture>,
+    dead_animal_texture: Option<Texture>,
+    herbivore_texture: Vec<Option<Texture>>,
+    carnivore_texture: Vec<Option<Texture>>,
+    plant_texture: Option<Texture>,
+    carcass_texture: Option<Texture>,
+    obstacle_texture: Option<Texture>,
+
+    // Шрифт надписей отметок пользователя. None - файла шрифта нет, вместо надписи
+    // ячейка обводится рамкой.
+    annotation_font: Option<Font>,
+
+    // Прямоугольник размером с ячейку вместо отсутствующей текстуры.
+    plain_cell: Mesh,
+
+    // Рамка вокруг животных отслеживаемой династии.
+    dynasty_outline: Mesh,
+
+    // Стены по краям мира вдоль осей "x" (левый и правый) и "y" (верхний и нижний).
+    border: (Mesh, Mesh),
+
+    // Швы по осям "x" (линия x = 0) и "y" (линия y = 0).
+    seam: (Mesh, Mesh),
+
+    // Гистограмма возраста отображаемого кадра (задержка между миром и отображением).
+    latency: Histogram,
 
-    map: Map,
+    // Отображать гистограмму задержки (клавиша L).
+    show_latency: bool,
+
+    // Столбец гистограммы (единичный квадрат, масштабируется при отображении).
+    latency_bar: Mesh,
+
+    // Отображаемый кадр.
+    frame: Option<Frame>,
+
+    // Численность видов на последних итерациях из последнего ответа мира.
+    history: Vec<Population>,
+
+    // Отображать график истории численности (клавиша G).
+    show_history: bool,
+
+    // Отображать ячейки, которые воспринимает выбранное животное (клавиша P).
+    show_perception: bool,
+
+    // Когда (миллисекунды) история численности запрашивалась последний раз.
+    history_requested_ms: u64,
+
+    // Эффекты событий в ячейках (затухают с каждым кадром отображения).
+    effects: Effects,
+
+    // Заглавие окна программы. None - заглавие не меняется.
+    title: Option<String>,
+
+    // Заглавие, установленное окну последним (вместе со сведениями о производительности).
+    shown_title: String,
+
+    // Режим сведений о производительности.
+    hud: HudMode,
+
+    // Скорость мира (итераций в секунду) по номерам итераций в полученных кадрах.
+    tps: RateMeter,
+
+    // Количество кадров, которые были вытеснены более новыми и не отображались.
+    skipped_frames: u64,
 }
 
-impl Window {
-    /// Создает новый экземпляр драйвера.
+impl EvolutionView {
+    /// Создает отображение мира в контексте tetra.
     ///
     /// # Arguments
     ///
-    /// * `width`: Шрина мира.
-    /// * `height`: Высота мира.
-    /// * `receiver`: Канал для получения данных.
-    /// * `asset_path`: Путь к файлам изображений.
-    /// * `title`: Заглавие окна программы.
+    /// * `ctx`: Контекст tetra.
+    /// * `config`: Настройки отображения.
     ///
-    /// returns: Result<(), String>
-    pub(crate) fn new(
-        width: usize,
-        height: usize,
-        receiver: Receiver<Map>,
-        base_path: &str,
-        title: &str
-    ) -> Result<(), String> {
-        let sizes = Self::get_window_size(width, height)?;
-
-        // Создаем контекст
-        let mut ctx = ContextBuilder::new(title, sizes.0, sizes.1)
-            .high_dpi(true)
-            .show_mouse(true)
-            .quit_on_escape(true)
-            .build()
-            .expect("Создание контекста тетра пало");
+    /// returns: Result<EvolutionView, String> - ошибка, если настройки неверны, мир
+    /// слишком велик или не удалось создать текстуры.
+    pub fn new(ctx: &mut Context, config: ViewConfig) -> Result<EvolutionView, String> {
+        let layout = Self::layout(&config, current_monitor(ctx))?;
 
-        let mut asset_path = base_path.to_owned();
-        asset_path.push_str("/resources/");
+        Self::build(ctx, config, (layout.width, layout.height, layout.texture_size)).map_err(|e| e.to_string())
+    }
 
-        ctx.run(move |ctx| {
-            let killed_animal_texture = Self::load_texture(
-                ctx, &asset_path, sizes.2, "blood"
-            )?;
-
-            let dead_animal_texture = Self::load_texture(
-                ctx, &asset_path, sizes.2, "ghost"
-            )?;
-
-            let plant_texture = Self::load_texture(
-                ctx, &asset_path, sizes.2, "plant"
-            )?;
-
-            let herbivore_texture = Self::load_animal_texture(
-                ctx,  &asset_path, sizes.2, HERBIVORE_NAME
-            )?;
-
-            let carnivore_texture = Self::load_animal_texture(
-                ctx,  &asset_path, sizes.2, CARNIVORE_NAME
-            )?;
-
-            Ok(Window {
-                receiver,
-                asset_path,
-                texture_size: sizes.2,
-                killed_animal_texture,
-                dead_animal_texture,
-                herbivore_texture,
-                carnivore_texture,
-                plant_texture,
-                map: vec![],
-            })
-        }).expect("Тетра пала!");
+    /// Проверяет настройки отображения и выбирает размеры изображения мира и текстур.
+    ///
+    /// # Arguments
+    ///
+    /// * `config`: Настройки отображения.
+    /// * `monitor`: Монитор окна.
+    ///
+    /// returns: Result<WindowLayout, String> - ошибка, если мир пуст, масштаб не
+    /// положителен или мир не помещается на монитор.
+    fn layout(config: &ViewConfig, monitor: Monitor) -> Result<WindowLayout, String> {
+        config.validate()?;
 
-        Ok(())
+        // Встроенное отображение рисуется без масштабирования, масштаб задает
+        // программа-хозяин при вызове `draw`.
+        render_model::window_layout(config.width, config.height, monitor, config.texture_size, None)
     }
 
-    /// Возвращает актуальные размеры окна и тексур для данного размера мира.
+    /// Создает текстуры и фигуры отображения.
     ///
     /// # Arguments
     ///
-    /// * `width`: Шрина мира.
-    /// * `height`: Высота мира.
+    /// * `ctx`: Контекст tetra.
+    /// * `config`: Настройки отображения.
+    /// * `sizes`: Размеры изображения мира и текстур.
     ///
-    /// returns: Result<(i32, i32, TextureSize), String>
-    fn get_window_size(width: usize,  height: usize) -> Result<(i32, i32, TextureSize), String> {
-        for size in TEXTURE_SIZES {
-            let window_with = width * size as usize;
-            let window_height = height * size as usize;
+    /// returns: Result<EvolutionView, TetraError>
+    fn build(
+        ctx: &mut Context,
+        config: ViewConfig,
+        sizes: (i32, i32, TextureSize)
+    ) -> TetraResult<EvolutionView> {
+        let assets = config.assets.as_ref();
 
-            if window_with <= MAX_WIDTH_SIZE && window_height <= MAX_HEIGHT_SIZE {
-                return Ok((window_with as i32, window_height as i32, size));
-            }
+        let missing = Self::missing_textures(assets, sizes.2);
+        if !missing.is_empty() {
+            eprintln!("Нет изображений текстур {}, они заменяются прямоугольниками", missing.join(", "));
         }
 
-        Err("Мир слишком велик ".to_string())
+        let killed_animal_texture = Self::load_texture(ctx, assets, sizes.2, "blood")?;
+        let dead_animal_texture = Self::load_texture(ctx, assets, sizes.2, "ghost")?;
+        let plant_texture = Self::load_texture(ctx, assets, sizes.2, "plant")?;
+        let carcass_texture = Self::load_texture(ctx, assets, sizes.2, "carcass")?;
+        let obstacle_texture = Self::load_texture(ctx, assets, sizes.2, "rock")?;
+        let annotation_font = Self::load_font(ctx, assets)?;
+        let herbivore_texture = Self::load_animal_texture(ctx, assets, sizes.2, HERBIVORE_NAME)?;
+        let carnivore_texture = Self::load_animal_texture(ctx, assets, sizes.2, CARNIVORE_NAME)?;
+
+        let size = sizes.2 as usize as f32;
+        let dynasty_outline = Mesh::rectangle(
+            ctx, ShapeStyle::Stroke(2.0), Rectangle::new(0.0, 0.0, size, size)
+        )?;
+        let plain_cell = Mesh::rectangle(
+            ctx, ShapeStyle::Fill, Rectangle::new(0.0, 0.0, size, size)
+        )?;
+
+        // Стена заходит внутрь мира на половину своей ширины.
+        let wall = BORDER_WIDTH / 2.0;
+        let (screen_width, screen_height) = (sizes.0 as f32, sizes.1 as f32);
+        let border = (
+            GeometryBuilder::new()
+                .rectangle(ShapeStyle::Fill, Rectangle::new(0.0, 0.0, wall, screen_height))?
+                .rectangle(ShapeStyle::Fill, Rectangle::new(screen_width - wall, 0.0, wall, screen_height))?
+                .build_mesh(ctx)?,
+            GeometryBuilder::new()
+                .rectangle(ShapeStyle::Fill, Rectangle::new(0.0, 0.0, screen_width, wall))?
+                .rectangle(ShapeStyle::Fill, Rectangle::new(0.0, screen_height - wall, screen_width, wall))?
+                .build_mesh(ctx)?,
+        );
+
+        // Шов проходит по левому (x = 0) и верхнему (y = 0) краю сетки.
+        let seam = (
+            Mesh::rectangle(ctx, ShapeStyle::Fill, Rectangle::new(0.0, 0.0, SEAM_WIDTH, screen_height))?,
+            Mesh::rectangle(ctx, ShapeStyle::Fill, Rectangle::new(0.0, 0.0, screen_width, SEAM_WIDTH))?,
+        );
+
+        let latency_bar = Mesh::rectangle(
+            ctx, ShapeStyle::Fill, Rectangle::new(0.0, 0.0, 1.0, 1.0)
+        )?;
+
+        // Отображению нужны все слои карты на каждой итерации.
+        let _ = config.control.send(ControlMessage::FrameRequest(FrameRequest::default()));
+
+        Ok(EvolutionView {
+            control: config.control,
+            world_size: (config.width, config.height),
+            topology: config.topology,
+            spawn_margin: config.spawn_margin,
+            effect_frames: config.effect_frames,
+            show_border: true,
+            show_seam: false,
+            natural_size: (screen_width, screen_height),
+            dest: Rectangle::new(0.0, 0.0, screen_width, screen_height),
+            texture_size: sizes.2,
+            killed_animal_texture,
+            dead_animal_texture,
+            herbivore_texture,
+            carnivore_texture,
+            plant_texture,
+            carcass_texture,
+            obstacle_texture,
+            annotation_font,
+            plain_cell,
+            dynasty_outline,
+            border,
+            seam,
+            latency: Histogram::new(&LATENCY_BOUNDS),
+            show_latency: false,
+            latency_bar,
+            frame: None,
+            history: Vec::new(),
+            show_history: false,
+            show_perception: false,
+            history_requested_ms: 0,
+            effects: Effects::new(config.effect_frames),
+            shown_title: config.title.clone().unwrap_or_default(),
+            title: config.title,
+            hud: HudMode::Off,
+            tps: RateMeter::new(TPS_WINDOW_MS),
+            skipped_frames: 0,
+        })
+    }
+
+    /// Размеры изображения мира без масштабирования: ширина и высота в пикселях.
+    pub fn natural_size(&self) -> (f32, f32) {
+        self.natural_size
+    }
+
+    /// Гистограмма возраста отображаемого кадра (задержки между миром и отображением).
+    pub fn latency(&self) -> &Histogram {
+        &self.latency
     }
 
     /// Загружает текстуру из ресурсов.
@@ -144,25 +414,75 @@ impl Window {
     /// # Arguments
     ///
     /// * `ctx`: Контекст tetra.
-    /// * `asset_path`: Путь к изображениям текстур.
+    /// * `assets`: Источник изображений текстур.
     /// * `texture_size`: Размер загружаемых текстур.
     /// * `target`: Имя загружаемого объекта.
     ///
-    /// returns: Result<Texture, TetraError>
+    /// returns: Result<Option<Texture>, TetraError> - None, если изображения нет.
     fn load_texture(
         ctx: &mut Context,
-        asset_path: &String,
+        assets: &dyn AssetSource,
         texture_size: TextureSize,
         target: &str
-    ) -> TetraResult<Texture> {
-        let mut path = asset_path.clone();
+    ) -> TetraResult<Option<Texture>> {
+        Self::load_optional(ctx, assets, texture_path(target, None, texture_size))
+    }
+
+    /// Изображения текстур, которых нет в источнике: агенты с такими текстурами
+    /// рисуются цветными прямоугольниками.
+    ///
+    /// # Arguments
+    ///
+    /// * `assets`: Источник изображений текстур.
+    /// * `texture_size`: Размер текстур.
+    ///
+    /// returns: Vec<String> - пути к недостающим изображениям относительно ресурсов.
+    fn missing_textures(assets: &dyn AssetSource, texture_size: TextureSize) -> Vec<String> {
+        let agents = TEXTURE_NAMES.iter().map(|name| texture_path(name, None, texture_size));
+        let animals = [HERBIVORE_NAME, CARNIVORE_NAME].into_iter().flat_map(|name| {
+            ANIMAL_DIRECTIONS.iter().map(move |direction| texture_path(name, Some(direction), texture_size))
+        });
 
-        path.push_str(target);
-        path.push('/');
-        path.push_str((texture_size as usize).to_string().as_str());
-        path.push_str(".png");
+        agents.chain(animals).filter(|path| assets.load(path).is_none()).collect()
+    }
+
+    /// Загружает текстуру, если изображение есть. Без изображения агент рисуется
+    /// цветным прямоугольником; чтобы проверить такое отображение, достаточно указать
+    /// путь к ресурсам, где изображений нет.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`: Контекст tetra.
+    /// * `assets`: Источник изображений текстур.
+    /// * `path`: Путь к изображению относительно ресурсов.
+    ///
+    /// returns: Result<Option<Texture>, TetraError> - None, если изображения нет.
+    fn load_optional(ctx: &mut Context, assets: &dyn AssetSource, path: String) -> TetraResult<Option<Texture>> {
+        match assets.load(&path) {
+            Some(data) => Texture::from_encoded(ctx, &data).map(Some),
+            None => Ok(None),
+        }
+    }
 
-        Texture::new(ctx, path)
+    /// Загружает шрифт надписей отметок пользователя из ресурсов.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`: Контекст tetra.
+    /// * `assets`: Источник ресурсов.
+    ///
+    /// returns: Result<Option<Font>, TetraError> - None, если файла шрифта нет.
+    fn load_font(ctx: &mut Context, assets: &dyn AssetSource) -> TetraResult<Option<Font>> {
+        match assets.load(ANNOTATION_FONT) {
+            // Данные шрифта должны жить до конца программы: tetra читает их при
+            // отрисовке каждого нового символа. Повторно открытое окно использует
+            // уже загруженные данные.
+            Some(data) => {
+                let data = FONT_DATA.get_or_init(|| data.into_boxed_slice());
+                Font::from_vector_file_data(ctx, data, ANNOTATION_FONT_SIZE).map(Some)
+            }
+            None => Ok(None),
+        }
     }
 
     /// Загружает текстуры животного соотвествующие четырем направлениям
@@ -171,30 +491,21 @@ impl Window {
     /// # Arguments
     ///
     /// * `ctx`: Контекст tetra.
-    /// * `asset_path`: Путь к изображениям текстур.
+    /// * `assets`: Источник изображений текстур.
     /// * `texture_size`: Размер загружаемых текстур.
     /// * `target`: Имя загружаемого объекта.
     ///
-    /// returns: Result<Texture, TetraError>
+    /// returns: Result<Vec<Option<Texture>>, TetraError>
     fn load_animal_texture(
         ctx: &mut Context,
-        asset_path: &String,
+        assets: &dyn AssetSource,
         texture_size: TextureSize,
         target: &str
-    ) -> TetraResult<Vec<Texture>> {
+    ) -> TetraResult<Vec<Option<Texture>>> {
         let mut tetxtures = Vec::with_capacity(4);
 
         for direct in ANIMAL_DIRECTIONS {
-            let mut path = asset_path.clone();
-
-            path.push_str(target);
-            path.push('/');
-            path.push_str(direct);
-            path.push('_');
-            path.push_str((texture_size as usize).to_string().as_str());
-            path.push_str(".png");
-
-            match Texture::new(ctx, path) {
+            match Self::load_optional(ctx, assets, texture_path(target, Some(direct), texture_size)) {
                 Ok(t) => {
                     tetxtures.push(t);
                 }
@@ -207,82 +518,512 @@ impl Window {
         Ok(tetxtures)
     }
 
-    /// Преобразует координаты мира в экранные координаты.
+    /// Возвращает состояние окна для модели отображения.
+    fn view_state(&self) -> ViewState {
+        ViewState {
+            texture_size: self.texture_size,
+            world_size: self.world_size,
+            topology: self.topology,
+            spawn_margin: self.spawn_margin,
+            show_border: self.show_border,
+            show_seam: self.show_seam,
+            show_latency: self.show_latency || self.hud == HudMode::Detailed,
+            show_history: self.show_history,
+        }
+    }
+
+    /// Выводит сведения о производительности в заглавие окна. Заглавие меняется,
+    /// только если изменился его текст.
     ///
     /// # Arguments
     ///
-    /// * `width`:
-    /// * `height`:
+    /// * `ctx`: Контекст tetra.
     ///
-    /// returns: Vec2<f32>
-    fn get_window_coords(&self, width: usize,  height: usize) -> Vec2<f32> {
-        let width = (width * (self.texture_size as usize)) as f32;
-        let height = (height * (self.texture_size as usize)) as f32;
+    /// returns: ()
+    fn show_hud(&mut self, ctx: &mut Context) {
+        let base = match self.title {
+            Some(ref title) => title,
+            None => return,
+        };
+
+        let tps = match self.tps.rate() {
+            Some(rate) => format!("{:.0}", rate),
+            None => "-".to_string(),
+        };
+
+        let title = match self.hud {
+            HudMode::Off => base.clone(),
+            HudMode::Basic => format!(
+                "{} | FPS {:.0} | TPS {}", base, time::get_fps(ctx), tps
+            ),
+            HudMode::Detailed => format!(
+                "{} | FPS {:.0} | TPS {} | пропущено кадров {}",
+                base, time::get_fps(ctx), tps, self.skipped_frames
+            ),
+        };
 
-        Vec2::new(width, height)
+        if title != self.shown_title {
+            window::set_title(ctx, &title);
+            self.shown_title = title;
+        }
     }
-}
 
-impl State for Window {
-    /// Обрабатывает ввод данных от пользователя (клавиатура, мыщ, и т.д.)
-    fn update(&mut self, ctx: &mut Context) -> tetra::Result {
-        match self.receiver.try_recv() {
-            Ok(map) => {
-                self.map = map;
+    /// Возвращает текстуру изображения.
+    ///
+    /// # Arguments
+    ///
+    /// * `sprite`: Изображение.
+    ///
+    /// returns: Option<&Texture> - None, если файла текстуры не было.
+    fn texture(&self, sprite: Sprite) -> Option<&Texture> {
+        match sprite {
+            Sprite::KilledAnimal => self.killed_animal_texture.as_ref(),
+            Sprite::DeadAnimal => self.dead_animal_texture.as_ref(),
+            Sprite::Herbivore(facing) => self.herbivore_texture[facing_index(facing)].as_ref(),
+            Sprite::Carnivore(facing) | Sprite::Omnivore(facing) => {
+                self.carnivore_texture[facing_index(facing)].as_ref()
+            }
+            Sprite::Carcass => self.carcass_texture.as_ref(),
+            Sprite::Plant => self.plant_texture.as_ref(),
+            Sprite::Obstacle => self.obstacle_texture.as_ref(),
+        }
+    }
+
+    /// Исполняет команду рисования.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`: Контекст tetra.
+    /// * `command`: Команда рисования.
+    ///
+    /// returns: ()
+    fn execute(&self, ctx: &mut Context, command: &DrawCommand) {
+        match *command {
+            DrawCommand::Text { x, y, ref text, tint } => {
+                let position = DrawParams::new().position(Vec2::new(x, y)).color(color(tint));
+                match self.annotation_font {
+                    Some(ref font) => Text::new(text.as_str(), font.clone()).draw(ctx, self.place(position)),
+                    None => self.dynasty_outline.draw(ctx, self.place(position)),
+                }
             }
-            Err(_) => {
-                // В канал не передали данные.
+            DrawCommand::Line { from, to, tint } => {
+                let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+                self.latency_bar.draw(
+                    ctx,
+                    self.place(
+                        DrawParams::new()
+                            .position(Vec2::new(from.0, from.1))
+                            .origin(Vec2::new(0.0, 0.5))
+                            .rotation(dy.atan2(dx))
+                            .scale(Vec2::new(dx.hypot(dy), ANNOTATION_LINE_WIDTH))
+                            .color(color(tint))
+                    )
+                );
+            }
+            DrawCommand::Sprite { sprite, x, y } => {
+                let position = DrawParams::new().position(Vec2::new(x, y));
+                match self.texture(sprite) {
+                    Some(texture) if matches!(sprite, Sprite::Omnivore(_)) => {
+                        texture.draw(ctx, self.place(position.color(color(OMNIVORE_TINT))));
+                    }
+                    Some(texture) => texture.draw(ctx, self.place(position)),
+                    None => {
+                        let tint = color(render_model::fallback_color(sprite));
+                        self.plain_cell.draw(ctx, self.place(position.color(tint)));
+                    }
+                }
+            }
+            DrawCommand::Outline { x, y, tint } => {
+                self.dynasty_outline.draw(
+                    ctx,
+                    self.place(DrawParams::new().position(Vec2::new(x, y)).color(color(tint)))
+                );
+            }
+            DrawCommand::Border { axis, tint } => {
+                let border = match axis {
+                    Axis::X => &self.border.0,
+                    Axis::Y => &self.border.1,
+                };
+                border.draw(ctx, self.place(DrawParams::new().color(color(tint))));
+            }
+            DrawCommand::Seam { axis, tint } => {
+                let seam = match axis {
+                    Axis::X => &self.seam.0,
+                    Axis::Y => &self.seam.1,
+                };
+                seam.draw(ctx, self.place(DrawParams::new().color(color(tint))));
+            }
+            DrawCommand::Bar { x, y, width, height, tint } => {
+                self.latency_bar.draw(
+                    ctx,
+                    self.place(
+                        DrawParams::new()
+                            .position(Vec2::new(x, y))
+                            .scale(Vec2::new(width, height))
+                            .color(color(tint))
+                    )
+                );
             }
         }
+    }
 
-        Ok(())
+    /// Масштаб изображения мира в области окна, где он рисуется.
+    fn scale(&self) -> Vec2<f32> {
+        Vec2::new(self.dest.width / self.natural_size.0, self.dest.height / self.natural_size.1)
     }
 
-    /// Отображает мир.
-    fn draw(&mut self, ctx: &mut Context) -> tetra::Result {
-        graphics::clear(ctx, BACKGROUND_COLOR);
+    /// Переносит параметры рисования из координат изображения мира в область окна.
+    ///
+    /// # Arguments
+    ///
+    /// * `params`: Параметры рисования в координатах изображения мира.
+    ///
+    /// returns: DrawParams
+    fn place(&self, params: DrawParams) -> DrawParams {
+        let scale = self.scale();
+        let position = Vec2::new(self.dest.x, self.dest.y) + params.position * scale;
 
-        for p in &self.map {
-            match p.2 {
-                CellStuff::KilledAnimal => {
-                    self.killed_animal_texture.draw(ctx, self.get_window_coords(p.0, p.1));
-                }
-                CellStuff::DeadAnimal => {
-                    self.dead_animal_texture.draw(ctx, self.get_window_coords(p.0, p.1));
-                }
-                CellStuff::HerbLeft => {
-                    self.herbivore_texture[0].draw(ctx, self.get_window_coords(p.0, p.1));
-                }
-                CellStuff::HerbRight => {
-                    self.herbivore_texture[1].draw(ctx, self.get_window_coords(p.0, p.1));
-                }
-                CellStuff::HerbFront => {
-                    self.herbivore_texture[2].draw(ctx, self.get_window_coords(p.0, p.1));
-                }
-                CellStuff::HerbBack => {
-                    self.herbivore_texture[3].draw(ctx, self.get_window_coords(p.0, p.1));
-                }
-                CellStuff::CarnLeft => {
-                    self.carnivore_texture[0].draw(ctx, self.get_window_coords(p.0, p.1));
-                }
-                CellStuff::CarnRight => {
-                    self.carnivore_texture[1].draw(ctx, self.get_window_coords(p.0, p.1));
-                }
-                CellStuff::CarnFront => {
-                    self.carnivore_texture[2].draw(ctx, self.get_window_coords(p.0, p.1));
-                }
-                CellStuff::CarnBack => {
-                    self.carnivore_texture[3].draw(ctx, self.get_window_coords(p.0, p.1));
-                }
-                CellStuff::Plant => {
-                    self.plant_texture.draw(ctx, self.get_window_coords(p.0, p.1));
+        DrawParams { position, scale: params.scale * scale, ..params }
+    }
+
+
+    /// Обрабатывает ввод данных от пользователя (клавиатура, мыщ, и т.д.) и забирает
+    /// из канала самый свежий кадр мира.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`: Контекст tetra.
+    /// * `receiver`: Канал для получения данных о состоянии мира.
+    ///
+    /// returns: ()
+    pub fn update(&mut self, ctx: &mut Context, receiver: &Receiver<Frame>) {
+        // Перезапуск мира: забываем текущее состояние и кадры, которые мир
+        // успел отправить до перезапуска.
+        if input::is_key_pressed(ctx, Key::R) {
+            self.frame = None;
+            self.history.clear();
+            self.effects = Effects::new(self.effect_frames);
+            while receiver.try_recv().is_ok() {}
+
+            // Если мир уже завершил работу, перезапускать нечего.
+            let _ = self.control.send(ControlMessage::Restart);
+        }
+
+        // Переключение отображения краев мира.
+        if input::is_key_pressed(ctx, Key::B) {
+            self.show_border = !self.show_border;
+        }
+        if input::is_key_pressed(ctx, Key::S) {
+            self.show_seam = !self.show_seam;
+        }
+        if input::is_key_pressed(ctx, Key::L) {
+            self.show_latency = !self.show_latency;
+        }
+        if input::is_key_pressed(ctx, Key::H) {
+            self.hud = self.hud.next();
+        }
+        if input::is_key_pressed(ctx, Key::G) {
+            self.show_history = !self.show_history;
+            // График обновляется сразу после включения.
+            self.history_requested_ms = 0;
+        }
+
+        // График истории численности перерисовывается раз в секунду по ответу мира.
+        if self.show_history && now_ms().saturating_sub(self.history_requested_ms) >= HISTORY_REFRESH_MS {
+            self.history_requested_ms = now_ms();
+            let _ = self.control.send(ControlMessage::GetHistory(HISTORY_TICKS));
+        }
+
+        // Ячейки, которые воспринимает выбранное животное, передает мир.
+        if input::is_key_pressed(ctx, Key::P) {
+            self.show_perception = !self.show_perception;
+            let _ = self.control.send(ControlMessage::ShowPerception(self.show_perception));
+        }
+
+        // Ручное изменение энергии выбранного животного.
+        if input::is_key_pressed(ctx, Key::Up) {
+            let _ = self.control.send(ControlMessage::AdjustEnergy(ENERGY_STEP));
+        }
+        if input::is_key_pressed(ctx, Key::Down) {
+            let _ = self.control.send(ControlMessage::AdjustEnergy(-ENERGY_STEP));
+        }
+
+        // Сохранение текущего состояния мира в сценарий.
+        if input::is_key_pressed(ctx, Key::F5) {
+            let _ = self.control.send(ControlMessage::ExportScenario);
+        }
+
+        // Выбор животного и его династии для отслеживания: щелчок по животному.
+        if input::is_mouse_button_pressed(ctx, MouseButton::Left) {
+            let position = input::get_mouse_position(ctx);
+
+            // Щелчок вне области, где нарисован мир, не выбирает животное.
+            if self.dest.contains_point(position) {
+                let position = (position - Vec2::new(self.dest.x, self.dest.y)) / self.scale();
+                if let Some((x, y)) = render_model::screen_to_world(
+                    self.texture_size, position.x, position.y
+                ) {
+                    let _ = self.control.send(ControlMessage::FollowDynasty(x, y));
                 }
-                CellStuff::None => {}
             }
         }
 
+        // Отображаем только самый свежий кадр, более старые пропускаем.
+        // События пропущенных кадров тоже отображаются.
+        let mut latest = None;
+        while let Ok(frame) = receiver.try_recv() {
+            self.effects.add(&frame.transient_events);
+            if let Some(ref history) = frame.history {
+                self.history = history.populations.clone();
+            }
+            if latest.replace(frame).is_some() {
+                self.skipped_frames += 1;
+            }
+        }
+
+        if let Some(frame) = latest {
+            self.tps.record(now_ms(), frame.tick);
+            self.frame = Some(frame);
+        }
+    }
+
+    /// Рисует мир в заданной области окна. Изображение масштабируется до размеров
+    /// области (пропорции не сохраняются, см. `natural_size`).
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`: Контекст tetra.
+    /// * `dest`: Область окна.
+    ///
+    /// returns: ()
+    pub fn draw(&mut self, ctx: &mut Context, dest: Rectangle) {
+        self.dest = dest;
+
+        let map: &[Point] = match &self.frame {
+            Some(frame) => {
+                // Насколько устарел отображаемый кадр.
+                self.latency.record(frame.age_ms());
+                &frame.map
+            }
+            None => &[],
+        };
+
+        let annotations = match &self.frame {
+            Some(frame) => render_model::annotation_commands(&self.view_state(), &frame.annotations),
+            None => Vec::new(),
+        };
+
+        let perception = match self.frame.as_ref().and_then(|frame| frame.perception.as_ref()) {
+            Some(footprint) => render_model::perception_commands(&self.view_state(), footprint),
+            None => Vec::new(),
+        };
+
+        // Отметки пользователя рисуются поверх всего остального.
+        let commands = render_model::build(&self.view_state(), map, self.latency.counts(), &self.history);
+        for command in commands.iter()
+            .chain(&self.effects.commands(self.texture_size))
+            .chain(&perception)
+            .chain(&annotations) {
+            self.execute(ctx, command);
+        }
+        self.effects.advance();
+
+        self.show_hud(ctx);
+    }
+}
+
+/// Окно программы: отображение мира на все окно.
+pub(crate) struct Window {
+    // Отображение мира.
+    view: EvolutionView,
+
+    // Канал для получения данных о состоянии мира.
+    receiver: Receiver<Frame>,
+
+    // Масштаб изображения мира в окне.
+    scale: f32,
+}
+
+impl Window {
+    /// Создает окно и отображает в нем мир, пока окно не закроют. Размеры окна и
+    /// текстур выбираются по монитору, на котором открылось окно.
+    ///
+    /// # Arguments
+    ///
+    /// * `config`: Настройки отображения.
+    /// * `receiver`: Канал для получения данных.
+    ///
+    /// returns: Result<(), String>
+    pub(crate) fn run(mut config: ViewConfig, receiver: Receiver<Frame>) -> Result<(), String> {
+        let scale = config.scale;
+
+        // Монитор становится известен только после создания окна: окно создается по
+        // монитору по умолчанию и затем меняет размер.
+        let initial = render_model::window_layout(
+            config.width, config.height, DEFAULT_MONITOR, config.texture_size, scale
+        ).map_or((INITIAL_WIDTH, INITIAL_HEIGHT), |layout| (layout.width, layout.height));
+        let title = config.title.clone().unwrap_or_default();
+
+        // Создаем контекст
+        let mut ctx = ContextBuilder::new(title, initial.0, initial.1)
+            .high_dpi(true)
+            .show_mouse(true)
+            .quit_on_escape(true)
+            .build()
+            .expect("Создание контекста тетра пало");
+
+        ctx.run(move |ctx| {
+            let layout = render_model::window_layout(
+                config.width, config.height, current_monitor(ctx), config.texture_size, scale
+            ).map_err(TetraError::PlatformError)?;
+
+            if window::get_size(ctx) != (layout.width, layout.height) {
+                window::set_size(ctx, layout.width, layout.height)?;
+            }
+
+            config.texture_size = Some(layout.texture_size);
+            let view = EvolutionView::new(ctx, config).map_err(TetraError::PlatformError)?;
+
+            Ok(Window { view, receiver, scale: layout.scale })
+        }).expect("Тетра пала!");
+
         Ok(())
     }
 }
 
+impl State for Window {
+    fn update(&mut self, ctx: &mut Context) -> tetra::Result {
+        self.view.update(ctx, &self.receiver);
+
+        Ok(())
+    }
 
+    fn draw(&mut self, ctx: &mut Context) -> tetra::Result {
+        graphics::clear(ctx, BACKGROUND_COLOR);
+
+        let (width, height) = self.view.natural_size();
+        self.view.draw(ctx, Rectangle::new(0.0, 0.0, width * self.scale, height * self.scale));
+
+        Ok(())
+    }
+}
+
+impl Drop for Window {
+    /// При закрытии окна выводит гистограмму задержки в stderr, чтобы она не смешивалась
+    /// с выводом мира.
+    fn drop(&mut self) {
+        let latency = self.view.latency();
+        if latency.total() > 0 {
+            eprintln!("Возраст отображаемого кадра, мс (количество кадров):");
+            eprint!("{}", latency);
+        }
+    }
+}
+
+/// Возвращает монитор, на котором находится окно. Если монитор определить не
+/// удалось, возвращается монитор по умолчанию.
+fn current_monitor(ctx: &Context) -> Monitor {
+    match window::get_current_monitor_size(ctx) {
+        Ok((width, height)) => Monitor { width, height, dpi_scale: window::get_dpi_scale(ctx) },
+        Err(_) => DEFAULT_MONITOR,
+    }
+}
+
+/// Путь к изображению текстуры относительно ресурсов, например `plant/40.png` или
+/// `sheep/left_40.png`.
+///
+/// # Arguments
+///
+/// * `target`: Имя загружаемого объекта.
+/// * `direction`: Направление животного (см. `ANIMAL_DIRECTIONS`), None - у
+///   изображения нет направлений.
+/// * `texture_size`: Размер текстур.
+///
+/// returns: String
+fn texture_path(target: &str, direction: Option<&str>, texture_size: TextureSize) -> String {
+    match direction {
+        Some(direction) => format!("{}/{}_{}.png", target, direction, texture_size as usize),
+        None => format!("{}/{}.png", target, texture_size as usize),
+    }
+}
+
+/// Индекс текстуры животного для направления (порядок `ANIMAL_DIRECTIONS`).
+fn facing_index(facing: Facing) -> usize {
+    match facing {
+        Facing::Left => 0,
+        Facing::Right => 1,
+        Facing::Front => 2,
+        Facing::Back => 3,
+    }
+}
+
+/// Преобразует цвет модели отображения в цвет tetra.
+fn color(tint: Rgba) -> Color {
+    Color::rgba(tint.0, tint.1, tint.2, tint.3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    /// Ресурсы в памяти: есть только файлы с перечисленными путями.
+    struct MemoryAssets(Vec<String>);
+
+    impl AssetSource for MemoryAssets {
+        fn load(&self, name: &str) -> Option<Vec<u8>> {
+            self.0.iter().any(|path| path == name).then(Vec::new)
+        }
+    }
+
+    fn config(width: usize, height: usize, scale: Option<f32>) -> ViewConfig {
+        ViewConfig {
+            width,
+            height,
+            topology: Topology::Torus,
+            spawn_margin: 0,
+            effect_frames: 1,
+            control: mpsc::channel().0,
+            texture_size: None,
+            assets: Box::new(MemoryAssets(Vec::new())),
+            title: None,
+            scale,
+        }
+    }
+
+    #[test]
+    fn missing_assets_are_listed_but_not_an_error() {
+        let layout = EvolutionView::layout(&config(10, 10, None), DEFAULT_MONITOR).unwrap();
+        assert_eq!(layout.texture_size, TextureSize::Size63);
+
+        let missing = EvolutionView::missing_textures(&MemoryAssets(Vec::new()), TextureSize::Size40);
+        assert_eq!(missing.len(), TEXTURE_NAMES.len() + 2 * ANIMAL_DIRECTIONS.len());
+        assert!(missing.contains(&"plant/40.png".to_string()));
+        assert!(missing.contains(&"sheep/left_40.png".to_string()));
+        assert!(missing.contains(&"wolf/back_40.png".to_string()));
+
+        let assets = MemoryAssets(vec!["plant/40.png".to_string(), "wolf/back_40.png".to_string()]);
+        let missing = EvolutionView::missing_textures(&assets, TextureSize::Size40);
+        assert_eq!(missing.len(), TEXTURE_NAMES.len() + 2 * ANIMAL_DIRECTIONS.len() - 2);
+        assert!(!missing.contains(&"plant/40.png".to_string()) && !missing.contains(&"wolf/back_40.png".to_string()));
+    }
+
+    #[test]
+    fn invalid_scale_is_an_error() {
+        for scale in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            let error = EvolutionView::layout(&config(10, 10, Some(scale)), DEFAULT_MONITOR).err();
+            assert!(error.is_some_and(|error| error.contains("Масштаб")), "масштаб {}", scale);
+        }
+        assert!(EvolutionView::layout(&config(10, 10, Some(2.0)), DEFAULT_MONITOR).is_ok());
+    }
+
+    #[test]
+    fn world_too_large_for_region_is_an_error() {
+        // Даже с наименьшими текстурами (20 пикселей) мир шире монитора 1920x1080.
+        let error = EvolutionView::layout(&config(97, 10, None), DEFAULT_MONITOR).err();
+        assert!(error.is_some_and(|error| error.contains("слишком велик")));
+        assert!(EvolutionView::layout(&config(96, 54, None), DEFAULT_MONITOR).is_ok());
+
+        assert!(EvolutionView::layout(&config(0, 10, None), DEFAULT_MONITOR).is_err());
+    }
+}
@@ -0,0 +1,103 @@
+//! Пример собственного мозга животного: мозг реализует типаж `AnimalBrain` вне
+//! библиотеки и подключается к животному как `Animal<GreedyBrain>`.
+//!
+//! Запуск: `cargo run --example custom_brain`.
+
+use evolution::animal::brains::AnimalBrain;
+use evolution::animal::species::simple::{Animal, AnimalParams};
+use evolution::animal::{AnimalAction, AnimalAlive, AnimalDirection, AnimalInputSignal, AnimaType};
+use evolution::config::*;
+use evolution::landscape::{Landscape, PlacementStrategy};
+use evolution::plant::PlantAlive;
+use evolution::plant::simple::{Plant, PlantParams};
+use rand::RngCore;
+
+/// "Жадный" мозг: ест, если еда рядом, иначе идет вперед и изредка поворачивает.
+#[derive(Default)]
+struct GreedyBrain {
+    // Количество шагов, сделанных с последнего поворота.
+    steps: usize,
+}
+
+impl AnimalBrain for GreedyBrain {
+    fn action(&mut self, inputs: &AnimalInputSignal) -> AnimalAction {
+        if inputs.plant_proximity > 0 || inputs.herbivore_proximity > 0 {
+            return AnimalAction::Eat;
+        }
+
+        self.steps += 1;
+        if self.steps % 5 == 0 {
+            return AnimalAction::TurnLeft;
+        }
+
+        AnimalAction::Move
+    }
+
+    fn clone_with_mutation(&self, _rng: &mut dyn RngCore, _rate: f64) -> Self {
+        GreedyBrain::default()
+    }
+}
+
+fn main() {
+    let mut world = Landscape::new(20, 20, 10, 10, 20, MAX_PLANT_GROW_ENERGY, SEED)
+        .expect("Ошибка создания мира!");
+
+    let mut new_plant = || -> Box<dyn PlantAlive> {
+        Plant::new(PlantParams {
+            energy: MAX_PLANT_ENERGY,
+            max_energy: MAX_PLANT_ENERGY,
+            eaten_energy: PLANT_EATEN_ENERGY,
+            bite_mode: PLANT_BITE_MODE,
+            reproduce_energy_rate: PLANT_REPRODUCE_ENERGY_RATE,
+            no_repro: PLANT_NO_REPRO,
+            regrowth_delay: PLANT_REGROWTH_DELAY,
+            seed_energy_cost: PLANT_SEED_ENERGY_COST,
+        }).expect("Ошибка настройки растений!")
+    };
+
+    let mut new_animal = |animal_type: AnimaType, rng: &mut dyn RngCore| -> Box<dyn AnimalAlive> {
+        Animal::<GreedyBrain>::new(
+            animal_type,
+            AnimalParams {
+                energy: ANIMAL_BIRTH_ENERGY,
+                max_energy: MAX_ANIMAL_ENERGY,
+                live_energy: ANIMAL_LIVE_ENERGY,
+                energy_profile: ANIMAL_ENERGY_PROFILE,
+                eaten_energy_rate: ANIMAL_EATEN_ENERGY_RATE,
+                reproduce_energy_rate: ANIMAL_REPRODUCE_ENERGY_RATE,
+                no_repro: ANIMAL_NO_REPRO,
+                inherit_direction: ANIMAL_INHERIT_DIRECTION,
+                input_mask: &[],
+                vision_radius: ANIMAL_VISION_RADIUS,
+                max_age: ANIMAL_MAX_AGE,
+            },
+            AnimalDirection::North,
+            0,
+            rng,
+        ).expect("Ошибка настройки животных!")
+    };
+
+    world.populate_random(
+        (10, PlacementStrategy::Uniform),
+        (3, PlacementStrategy::Uniform),
+        (1, PlacementStrategy::Uniform),
+        &mut new_plant,
+        &mut new_animal,
+    ).expect("Ошибка заселения мира!");
+
+    for _ in 0..200 {
+        world.tick();
+    }
+
+    let stats = world.get_world_stats();
+    println!("Итерация: {}", stats.iteration);
+    println!("Травоядных: {}, хищников: {}", stats.animal_count.0, stats.animal_count.1);
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn example_runs_to_completion() {
+        super::main();
+    }
+}
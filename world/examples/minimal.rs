@@ -0,0 +1,79 @@
+//! Минимальный пример использования библиотеки "Эволюция": мир 20x20, 5 растений,
+//! 3 травоядных и 1 хищник, 200 итераций без отображения.
+//!
+//! Запуск: `cargo run --example minimal`.
+
+use evolution::animal::brains::simple::Brain;
+use evolution::animal::species::simple::{Animal, AnimalParams};
+use evolution::animal::{AnimalAlive, AnimalDirection, AnimaType};
+use evolution::config::*;
+use evolution::landscape::{Landscape, PlacementStrategy};
+use evolution::plant::PlantAlive;
+use evolution::plant::simple::{Plant, PlantParams};
+use rand::RngCore;
+
+fn main() {
+    let mut world = Landscape::new(20, 20, 10, 10, 20, MAX_PLANT_GROW_ENERGY, SEED)
+        .expect("Ошибка создания мира!");
+
+    let mut new_plant = || -> Box<dyn PlantAlive> {
+        Plant::new(PlantParams {
+            energy: MAX_PLANT_ENERGY,
+            max_energy: MAX_PLANT_ENERGY,
+            eaten_energy: PLANT_EATEN_ENERGY,
+            bite_mode: PLANT_BITE_MODE,
+            reproduce_energy_rate: PLANT_REPRODUCE_ENERGY_RATE,
+            no_repro: PLANT_NO_REPRO,
+            regrowth_delay: PLANT_REGROWTH_DELAY,
+            seed_energy_cost: PLANT_SEED_ENERGY_COST,
+        }).expect("Ошибка настройки растений!")
+    };
+
+    let mut new_animal = |animal_type: AnimaType, rng: &mut dyn RngCore| -> Box<dyn AnimalAlive> {
+        Animal::<Brain>::new(
+            animal_type,
+            AnimalParams {
+                energy: ANIMAL_BIRTH_ENERGY,
+                max_energy: MAX_ANIMAL_ENERGY,
+                live_energy: ANIMAL_LIVE_ENERGY,
+                energy_profile: ANIMAL_ENERGY_PROFILE,
+                eaten_energy_rate: ANIMAL_EATEN_ENERGY_RATE,
+                reproduce_energy_rate: ANIMAL_REPRODUCE_ENERGY_RATE,
+                // Размножение отключено: пример показывает только жизнь первого поколения.
+                no_repro: true,
+                inherit_direction: ANIMAL_INHERIT_DIRECTION,
+                input_mask: &[],
+                vision_radius: ANIMAL_VISION_RADIUS,
+                max_age: ANIMAL_MAX_AGE,
+            },
+            AnimalDirection::North,
+            0,
+            rng,
+        ).expect("Ошибка настройки животных!")
+    };
+
+    world.populate_random(
+        (5, PlacementStrategy::Uniform),
+        (3, PlacementStrategy::Uniform),
+        (1, PlacementStrategy::Uniform),
+        &mut new_plant,
+        &mut new_animal,
+    ).expect("Ошибка заселения мира!");
+
+    for _ in 0..200 {
+        world.tick();
+    }
+
+    let stats = world.get_world_stats();
+    println!("Итерация: {}", stats.iteration);
+    println!("Растений: {}", stats.plant_count);
+    println!("Травоядных: {}, хищников: {}", stats.animal_count.0, stats.animal_count.1);
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn example_runs_to_completion() {
+        super::main();
+    }
+}
@@ -0,0 +1,477 @@
+//! Сравнение прогонов мира по статистике, сохраненной в CSV файлах.
+
+use std::fmt;
+
+use crate::errors::RecoverableError;
+use crate::landscape::WorldStats;
+
+/// Столбцы CSV файла статистики (в порядке записи).
+const COLUMNS: [&str; 10] = [
+    "iteration",
+    "plants",
+    "herbivores",
+    "carnivores",
+    "herbivore_max_generation",
+    "carnivore_max_generation",
+    "herbivore_eat_attempts",
+    "herbivore_eat_successes",
+    "carnivore_eat_attempts",
+    "carnivore_eat_successes",
+];
+
+/// Строка статистики мира, прочитанная из CSV файла. Первый элемент кортежей -
+/// травоядные, второй хищники.
+#[derive(Copy, Clone, Default)]
+pub struct StatsRow {
+    pub iteration: usize,
+    pub plants: usize,
+    pub animals: (usize, usize),
+    pub max_generation: (usize, usize),
+    pub eat_attempts: (usize, usize),
+    pub eat_successes: (usize, usize),
+}
+
+/// Сравнение одного вида животных в двух прогонах: первый элемент кортежей -
+/// прогон A, второй прогон B.
+#[derive(Clone, Default)]
+pub struct SpeciesComparison {
+    // Средняя численность вида.
+    pub mean_population: (f64, f64),
+    // Итерация окончательного вымирания (вид не восстановился до конца ряда).
+    pub extinction: (Option<usize>, Option<usize>),
+    // Максимальное поколение.
+    pub max_generation: (usize, usize),
+    // Доля удачных попыток поесть. None - попыток не было.
+    pub hunt_efficiency: (Option<f64>, Option<f64>),
+}
+
+/// Сравнение двух прогонов.
+#[derive(Clone, Default)]
+pub struct Comparison {
+    // Количество строк в каждом из прогонов.
+    pub rows: (usize, usize),
+    // Количество сравниваемых строк (ряды обрезаются до более короткого).
+    pub compared: usize,
+    pub herbivore: SpeciesComparison,
+    pub carnivore: SpeciesComparison,
+}
+
+/// Столбцы энергетического баланса видов (добавляются по запросу, для каждого вида).
+const BUDGET_COLUMNS: [&str; 4] = ["energy_gained", "energy_spent", "energy_offspring", "energy_net"];
+
+/// Представляет историю статистики мира в виде CSV.
+///
+/// # Arguments
+///
+/// * `history`: Статистика мира, собранная после каждой итерации.
+/// * `energy_budget`: Добавить столбцы энергетического баланса видов.
+///
+/// returns: String
+pub fn to_csv(history: &[WorldStats], energy_budget: bool) -> String {
+    let mut csv = csv_header(energy_budget);
+    for stats in history {
+        csv.push_str(&csv_row(stats, energy_budget));
+    }
+
+    csv
+}
+
+/// Возвращает строку заголовка CSV файла статистики (вместе с переводом строки).
+///
+/// # Arguments
+///
+/// * `energy_budget`: Добавить столбцы энергетического баланса видов.
+///
+/// returns: String
+pub fn csv_header(energy_budget: bool) -> String {
+    let mut csv = COLUMNS.join(",");
+    csv.push_str(",herbivore_diversity,carnivore_diversity");
+    csv.push_str(",omnivores,omnivore_max_generation,omnivore_eat_attempts,omnivore_eat_successes,omnivore_diversity");
+    csv.push_str(",plant_deaths");
+    if energy_budget {
+        for species in ["herbivore", "carnivore", "omnivore"] {
+            for column in BUDGET_COLUMNS {
+                csv.push_str(&format!(",{}_{}", species, column));
+            }
+        }
+    }
+    csv.push('\n');
+
+    csv
+}
+
+/// Представляет статистику мира за одну итерацию строкой CSV файла статистики
+/// (вместе с переводом строки). Столбцы совпадают с `csv_header`.
+///
+/// # Arguments
+///
+/// * `stats`: Статистика мира после итерации.
+/// * `energy_budget`: Добавить столбцы энергетического баланса видов.
+///
+/// returns: String
+pub fn csv_row(stats: &WorldStats, energy_budget: bool) -> String {
+    // Разнообразие замеряется не на каждой итерации, пропуск - замера еще не было.
+    let diversity = |value: Option<f64>| value.map_or(String::new(), |value| value.to_string());
+
+    let mut csv = format!(
+        "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+        stats.iteration,
+        stats.plant_count,
+        stats.animal_count.0,
+        stats.animal_count.1,
+        stats.max_generation.0,
+        stats.max_generation.1,
+        stats.hunt.0.attempts,
+        stats.hunt.0.successes,
+        stats.hunt.1.attempts,
+        stats.hunt.1.successes,
+        diversity(stats.genetic_diversity.0),
+        diversity(stats.genetic_diversity.1),
+        stats.animal_count.2,
+        stats.max_generation.2,
+        stats.hunt.2.attempts,
+        stats.hunt.2.successes,
+        diversity(stats.genetic_diversity.2),
+        stats.plant_deaths
+    );
+    if energy_budget {
+        for budget in [&stats.energy_budget.0, &stats.energy_budget.1, &stats.energy_budget.2] {
+            csv.push_str(&format!(
+                ",{},{},{},{}", budget.gained, budget.spent, budget.offspring, budget.net()
+            ));
+        }
+    }
+    csv.push('\n');
+
+    csv
+}
+
+/// Разбирает CSV файл статистики. Порядок столбцов не важен, лишние столбцы
+/// пропускаются.
+///
+/// # Arguments
+///
+/// * `text`: Содержимое CSV файла.
+///
+/// returns: Result<Vec<StatsRow>, RecoverableError>
+pub fn parse_csv(text: &str) -> Result<Vec<StatsRow>, RecoverableError> {
+    let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+
+    let header: Vec<&str> = match lines.next() {
+        Some(header) => header.split(',').map(|name| name.trim()).collect(),
+        None => return Err(RecoverableError::new("Файл статистики пуст".to_string())),
+    };
+
+    // Номер столбца в файле для каждого из известных столбцов.
+    let mut positions = [0usize; COLUMNS.len()];
+    for (position, column) in positions.iter_mut().zip(COLUMNS) {
+        *position = match header.iter().position(|name| *name == column) {
+            Some(index) => index,
+            None => return Err(RecoverableError::new(fmt::format(format_args!(
+                "В файле статистики нет столбца \"{}\"", column
+            )))),
+        };
+    }
+
+    let mut rows = Vec::new();
+
+    for (index, line) in lines.enumerate() {
+        let fields: Vec<&str> = line.split(',').map(|field| field.trim()).collect();
+
+        let mut values = [0usize; COLUMNS.len()];
+        for (value, (&position, column)) in values.iter_mut().zip(positions.iter().zip(COLUMNS)) {
+            *value = fields.get(position)
+                .and_then(|field| field.parse().ok())
+                .ok_or_else(|| RecoverableError::new(fmt::format(format_args!(
+                    "Строка {} файла статистики: неверное значение столбца \"{}\"",
+                    index + 2, column
+                ))))?;
+        }
+
+        rows.push(StatsRow {
+            iteration: values[0],
+            plants: values[1],
+            animals: (values[2], values[3]),
+            max_generation: (values[4], values[5]),
+            eat_attempts: (values[6], values[8]),
+            eat_successes: (values[7], values[9]),
+        });
+    }
+
+    if rows.is_empty() {
+        return Err(RecoverableError::new("В файле статистики нет ни одной итерации".to_string()));
+    }
+
+    Ok(rows)
+}
+
+/// Сравнивает два прогона. Ряды выравниваются по началу и обрезаются до более
+/// короткого.
+///
+/// # Arguments
+///
+/// * `a`: Статистика прогона A.
+/// * `b`: Статистика прогона B.
+///
+/// returns: Comparison
+pub fn compare(a: &[StatsRow], b: &[StatsRow]) -> Comparison {
+    let compared = a.len().min(b.len());
+    let rows = (a.len(), b.len());
+    let (a, b) = (&a[..compared], &b[..compared]);
+
+    let herbivore = |row: &StatsRow| {
+        (row.animals.0, row.max_generation.0, row.eat_attempts.0, row.eat_successes.0)
+    };
+    let carnivore = |row: &StatsRow| {
+        (row.animals.1, row.max_generation.1, row.eat_attempts.1, row.eat_successes.1)
+    };
+
+    Comparison {
+        rows,
+        compared,
+        herbivore: compare_species(a, b, herbivore),
+        carnivore: compare_species(a, b, carnivore),
+    }
+}
+
+/// Сравнивает один вид животных в двух прогонах одинаковой длины.
+///
+/// # Arguments
+///
+/// * `a`: Статистика прогона A.
+/// * `b`: Статистика прогона B.
+/// * `species`: Выбирает из строки значения вида: численность, максимальное
+///   поколение, попытки поесть, удачные попытки.
+///
+/// returns: SpeciesComparison
+fn compare_species(
+    a: &[StatsRow],
+    b: &[StatsRow],
+    species: impl Fn(&StatsRow) -> (usize, usize, usize, usize),
+) -> SpeciesComparison {
+    let a = species_metrics(a, &species);
+    let b = species_metrics(b, &species);
+
+    SpeciesComparison {
+        mean_population: (a.mean_population.0, b.mean_population.0),
+        extinction: (a.extinction.0, b.extinction.0),
+        max_generation: (a.max_generation.0, b.max_generation.0),
+        hunt_efficiency: (a.hunt_efficiency.0, b.hunt_efficiency.0),
+    }
+}
+
+/// Вычисляет показатели вида в одном прогоне (заполняется первый элемент кортежей).
+///
+/// # Arguments
+///
+/// * `run`: Статистика прогона.
+/// * `species`: Выбирает из строки значения вида.
+///
+/// returns: SpeciesComparison
+fn species_metrics(
+    run: &[StatsRow],
+    species: &impl Fn(&StatsRow) -> (usize, usize, usize, usize),
+) -> SpeciesComparison {
+    let values: Vec<(usize, usize, usize, usize)> = run.iter().map(species).collect();
+    let mut metrics = SpeciesComparison::default();
+
+    if !values.is_empty() {
+        metrics.mean_population.0 =
+            values.iter().map(|v| v.0 as f64).sum::<f64>() / values.len() as f64;
+    }
+
+    // Вымирание - начало хвоста ряда с нулевой численностью.
+    metrics.extinction.0 = match values.iter().rposition(|v| v.0 > 0) {
+        Some(index) => run.get(index + 1).map(|row| row.iteration),
+        None => run.first().map(|row| row.iteration),
+    };
+
+    metrics.max_generation.0 = values.iter().map(|v| v.1).max().unwrap_or(0);
+
+    let attempts: usize = values.iter().map(|v| v.2).sum();
+    let successes: usize = values.iter().map(|v| v.3).sum();
+    if attempts > 0 {
+        metrics.hunt_efficiency.0 = Some(successes as f64 / attempts as f64);
+    }
+
+    metrics
+}
+
+/// Форматирует необязательное значение для таблицы сравнения.
+fn optional<T: fmt::Display>(value: Option<T>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => "-".to_string(),
+    }
+}
+
+/// Форматирует разность B - A для таблицы сравнения.
+fn difference(a: Option<f64>, b: Option<f64>) -> String {
+    match (a, b) {
+        (Some(a), Some(b)) => format!("{:+.4}", b - a),
+        _ => "-".to_string(),
+    }
+}
+
+impl fmt::Display for SpeciesComparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows = [
+            (
+                "средняя численность",
+                format!("{:.2}", self.mean_population.0),
+                format!("{:.2}", self.mean_population.1),
+                difference(Some(self.mean_population.0), Some(self.mean_population.1)),
+            ),
+            (
+                "итерация вымирания",
+                optional(self.extinction.0),
+                optional(self.extinction.1),
+                difference(self.extinction.0.map(|i| i as f64), self.extinction.1.map(|i| i as f64)),
+            ),
+            (
+                "максимальное поколение",
+                self.max_generation.0.to_string(),
+                self.max_generation.1.to_string(),
+                difference(Some(self.max_generation.0 as f64), Some(self.max_generation.1 as f64)),
+            ),
+            (
+                "доля удачных попыток поесть",
+                optional(self.hunt_efficiency.0.map(|rate| format!("{:.4}", rate))),
+                optional(self.hunt_efficiency.1.map(|rate| format!("{:.4}", rate))),
+                difference(self.hunt_efficiency.0, self.hunt_efficiency.1),
+            ),
+        ];
+
+        for (name, a, b, diff) in rows {
+            writeln!(f, "  {:<28} {:>12} {:>12} {:>12}", name, a, b, diff)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Сравнение прогонов ({} итераций)", self.compared)?;
+        if self.rows.0 != self.rows.1 {
+            writeln!(
+                f,
+                "Длины рядов различаются (A: {}, B: {}), сравниваются первые {} итераций",
+                self.rows.0, self.rows.1, self.compared
+            )?;
+        }
+        writeln!(f, "  {:<28} {:>12} {:>12} {:>12}", "", "A", "B", "B - A")?;
+        writeln!(f, "Травоядные:")?;
+        write!(f, "{}", self.herbivore)?;
+        writeln!(f, "Хищники:")?;
+        write!(f, "{}", self.carnivore)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    /// Строка статистики с заданной итерацией и численностью видов.
+    fn row(iteration: usize, animals: (usize, usize)) -> StatsRow {
+        StatsRow { iteration, animals, ..StatsRow::default() }
+    }
+
+    #[test]
+    fn parsed_csv_matches_written_rows() {
+        let mut world = testing::populated_world(20, 20, (30, 8, 3));
+        let mut history = vec![];
+        for _ in 0..30 {
+            world.tick();
+            history.push(world.get_world_stats());
+        }
+
+        for energy_budget in [false, true] {
+            let rows = parse_csv(&to_csv(&history, energy_budget)).unwrap();
+
+            assert_eq!(rows.len(), history.len());
+            for (row, stats) in rows.iter().zip(&history) {
+                assert_eq!(row.iteration, stats.iteration);
+                assert_eq!(row.plants, stats.plant_count);
+                assert_eq!(row.animals, (stats.animal_count.0, stats.animal_count.1));
+                assert_eq!(row.max_generation, (stats.max_generation.0, stats.max_generation.1));
+                assert_eq!(row.eat_attempts, (stats.hunt.0.attempts, stats.hunt.1.attempts));
+                assert_eq!(row.eat_successes, (stats.hunt.0.successes, stats.hunt.1.successes));
+            }
+        }
+    }
+
+    #[test]
+    fn parse_ignores_column_order_and_extra_columns() {
+        let mut columns: Vec<&str> = COLUMNS.iter().rev().copied().collect();
+        columns.insert(3, "comment");
+        let text = format!("{}\n9,8,7,x,6,5,4,3,2,1,0\n", columns.join(","));
+
+        let rows = parse_csv(&text).unwrap();
+
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].iteration, 0);
+        assert_eq!(rows[0].plants, 1);
+        assert_eq!(rows[0].animals, (2, 3));
+        assert_eq!(rows[0].eat_successes, (7, 9));
+    }
+
+    #[test]
+    fn compare_truncates_to_shorter_run() {
+        let a: Vec<StatsRow> = [(3, 1), (3, 1), (0, 1), (0, 1), (0, 1)].iter()
+            .enumerate()
+            .map(|(index, &animals)| row(index + 1, animals))
+            .collect();
+        let b: Vec<StatsRow> = [(1, 0), (1, 0), (1, 0)].iter()
+            .enumerate()
+            .map(|(index, &animals)| row(index + 1, animals))
+            .collect();
+
+        let comparison = compare(&a, &b);
+
+        assert_eq!(comparison.rows, (5, 3));
+        assert_eq!(comparison.compared, 3);
+        assert_eq!(comparison.herbivore.mean_population, (2.0, 1.0));
+        // Травоядные A вымерли на третьей итерации, хищники B - с самого начала.
+        assert_eq!(comparison.herbivore.extinction, (Some(3), None));
+        assert_eq!(comparison.carnivore.extinction, (None, Some(1)));
+        assert_eq!(comparison.herbivore.hunt_efficiency, (None, None));
+        assert!(comparison.to_string().contains("Длины рядов различаются (A: 5, B: 3)"));
+
+        // Порядок прогонов важен только для порядка значений в кортежах.
+        let reversed = compare(&b, &a);
+        assert_eq!(reversed.rows, (3, 5));
+        assert_eq!(reversed.herbivore.extinction, (None, Some(3)));
+    }
+
+    #[test]
+    fn malformed_csv_is_rejected() {
+        let header = COLUMNS.join(",");
+        let cases = [
+            ("".to_string(), "Файл статистики пуст"),
+            ("\n  \n".to_string(), "Файл статистики пуст"),
+            (header.replace("plants", "trees"), "В файле статистики нет столбца \"plants\""),
+            (format!("{}\n", header), "В файле статистики нет ни одной итерации"),
+            (
+                format!("{}\n1,2,3,4,5,6,7,8,9,10\n1,2,3,x,5,6,7,8,9,10\n", header),
+                "Строка 3 файла статистики: неверное значение столбца \"carnivores\"",
+            ),
+            (
+                format!("{}\n1,2,3,4,5,6,7,8\n", header),
+                "Строка 2 файла статистики: неверное значение столбца \"carnivore_eat_attempts\"",
+            ),
+            (
+                format!("{}\n1,-2,3,4,5,6,7,8,9,10\n", header),
+                "Строка 2 файла статистики: неверное значение столбца \"plants\"",
+            ),
+        ];
+
+        for (text, message) in cases {
+            match parse_csv(&text) {
+                Ok(_) => panic!("файл разобран: {:?}", text),
+                Err(error) => assert_eq!(error.to_string(), message, "{:?}", text),
+            }
+        }
+    }
+}
@@ -1,14 +1,109 @@
 //! Модуль, реализующий общие методы мозга животного.
 
-pub(crate) mod simple;
+pub mod simple;
 
+use rand::RngCore;
 use crate::animal::{AnimalAction, AnimalInputSignal};
+use crate::errors::RecoverableError;
+use crate::rng::RngState;
+
+/// След решения мозга на одном ходу животного: что мозг получил на входе, какие
+/// значения получились на выходе и какое действие выбрано.
+#[derive(Clone, Default)]
+pub struct ActionTrace {
+    // Итерация мира.
+    pub iteration: usize,
+    // Идентификатор животного.
+    pub animal_id: u64,
+    // Имена и значения входов мозга (после маски входов).
+    pub inputs: Vec<(&'static str, f64)>,
+    // Имена действий и значения выходных нейронов до выбора действия.
+    pub outputs: Vec<(&'static str, f64)>,
+    // Выбранное действие.
+    pub action: Option<AnimalAction>,
+}
+
+impl ActionTrace {
+    /// Представляет след решения в виде JSON (одной строкой, для файлов JSONL).
+    pub fn to_json(&self) -> String {
+        let values = |values: &[(&str, f64)]| -> String {
+            values.iter()
+                .map(|(name, value)| format!("\"{}\": {}", name, value))
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+
+        format!(
+            "{{\"iteration\": {}, \"animal_id\": {}, \"inputs\": {{{}}}, \"outputs\": {{{}}}, \"action\": \"{}\"}}\n",
+            self.iteration,
+            self.animal_id,
+            values(&self.inputs),
+            values(&self.outputs),
+            self.action.map_or("", |action| action.name())
+        )
+    }
+}
 
 /// Типаж, определяющий мозг животного.
-pub trait AnimalBrain : Default {
+pub trait AnimalBrain : Default + Send {
     /// Действие агента (основной метод, определяющий поведение агента).
     fn action(&mut self, inputs: &AnimalInputSignal) -> AnimalAction;
 
-    /// Клонирует мозг агента (со случайными мутациями).
-    fn clone_with_mutation(& self) -> Self;
+    /// Действие агента с записью следа решения (для отладки одного животного).
+    /// По умолчанию след не заполняется.
+    ///
+    /// # Arguments
+    ///
+    /// * `inputs`: Входные сигналы.
+    /// * `trace`: След решения, который заполняет мозг.
+    ///
+    /// returns: AnimalAction
+    fn action_traced(&mut self, inputs: &AnimalInputSignal, _trace: &mut ActionTrace) -> AnimalAction {
+        self.action(inputs)
+    }
+
+    /// Создает мозг со случайными значениями из генератора `rng` (с генератором мира
+    /// прогон воспроизводим). По умолчанию генератор не используется.
+    fn from_rng(_rng: &mut dyn RngCore) -> Self {
+        Self::default()
+    }
+
+    /// Клонирует мозг агента (со случайными мутациями из генератора `rng`).
+    /// `rate` - частота мутаций: среднее количество изменяемых генов.
+    fn clone_with_mutation(&self, rng: &mut dyn RngCore, rate: f64) -> Self;
+
+    /// Возвращает геном мозга в виде вектора чисел (для сравнения геномов). None -
+    /// мозг не поддерживает представление генома.
+    fn to_genome(&self) -> Option<Vec<f64>> {
+        None
+    }
+
+    /// Возвращает геном мозга в JSON для сохранения в файл. None - мозг не
+    /// поддерживает сохранение генома.
+    fn genome_json(&self) -> Option<String> {
+        None
+    }
+
+    /// Создает мозг из генома в JSON, сохраненного методом `genome_json`.
+    /// По умолчанию мозг не поддерживает загрузку генома.
+    ///
+    /// # Arguments
+    ///
+    /// * `json`: Геном мозга в JSON.
+    /// * `rng`: Генератор случайных чисел мира (см. `from_rng`).
+    ///
+    /// returns: Result<Self, RecoverableError>
+    fn from_genome_json(_json: &str, _rng: &mut dyn RngCore) -> Result<Self, RecoverableError> {
+        Err(RecoverableError::new("Мозг не поддерживает загрузку генома".to_string()))
+    }
+
+    /// Возвращает состояние генератора случайных чисел мозга (для контрольной точки
+    /// мира). None - мозг не использует случайных чисел.
+    fn rng_state(&self) -> Option<RngState> {
+        None
+    }
+
+    /// Восстанавливает состояние генератора случайных чисел мозга из контрольной
+    /// точки мира. По умолчанию ничего не делает.
+    fn set_rng_state(&mut self, _state: &RngState) {}
 }
\ No newline at end of file
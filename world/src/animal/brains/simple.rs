@@ -1,23 +1,142 @@
 //! "Простой мозг" животного.
 
 extern crate nalgebra;
+use std::fmt;
 use nalgebra::{SVector, SMatrix};
-use crate::animal::brains::AnimalBrain;
-use crate::animal::{AnimalAction, AnimalInputSignal, MAX_ACTIONS};
-use rand::Rng;
+use crate::animal::brains::{ActionTrace, AnimalBrain};
+use crate::animal::{AnimalAction, AnimalInputSignal, InputMask, MAX_ACTIONS};
+use crate::errors::RecoverableError;
+use crate::rng::{CheckpointRng, RngState};
+use rand::{Rng, RngCore, SeedableRng};
+use rand::rngs::SmallRng;
 
-type WeightType = f32;
+pub type WeightType = f32;
 
 /// Константа, определяющая размер "вектора" входных сигналов.
-const INPUT_VECTOR_SIZE: usize = 12;
+const INPUT_VECTOR_SIZE: usize = 18;
+
+/// Количество входов контекста мира (фаза сезона, плотность растений, доля возраста).
+/// Входы контекста идут последними; геномы, сохраненные до их появления, загружаются
+/// с нулевыми весами этих входов.
+const CONTEXT_INPUTS: usize = 3;
 
 /// Константа, определяющая размер "вектора" выходных сигналов (по числу возможных действий).
-const OUTPUT_VECTOR_SIZE: usize = 4;
+const OUTPUT_VECTOR_SIZE: usize = 5;
+
+/// Количество выходов, добавленных вместе с нападением (см. `AnimalAction::Attack`).
+/// Выход нападения идет последним; геномы, сохраненные до его появления, загружаются
+/// с нулевыми весами и смещением этого выхода, поэтому животное с таким геномом
+/// никогда не нападает.
+const ATTACK_OUTPUTS: usize = 1;
+
+/// Функция получения значения входа мозга из восприятия животного.
+type InputValue = fn(&AnimalInputSignal) -> WeightType;
+
+/// Входы мозга: имя входа и функция получения его значения из восприятия животного.
+/// Индекс в массиве совпадает с индексом во входном векторе, поэтому таблица является
+/// единственным источником соответствия имени входа и его позиции.
+const INPUTS: [(&str, InputValue); INPUT_VECTOR_SIZE] = [
+    ("plant_front", |p| p.plant_front as WeightType),
+    ("plant_left", |p| p.plant_left as WeightType),
+    ("plant_right", |p| p.plant_right as WeightType),
+    ("plant_proximity", |p| p.plant_proximity as WeightType),
+
+    ("herbivore_front", |p| p.herbivore_front as WeightType),
+    ("herbivore_left", |p| p.herbivore_left as WeightType),
+    ("herbivore_right", |p| p.herbivore_right as WeightType),
+    ("herbivore_proximity", |p| p.herbivore_proximity as WeightType),
+
+    ("carnivore_front", |p| p.carnivore_front as WeightType),
+    ("carnivore_left", |p| p.carnivore_left as WeightType),
+    ("carnivore_right", |p| p.carnivore_right as WeightType),
+    ("carnivore_proximity", |p| p.carnivore_proximity as WeightType),
+
+    ("repro_blocked", |p| p.repro_blocked as WeightType),
+    ("wall_front", |p| p.wall_front as WeightType),
+
+    ("own_energy", |p| p.own_energy),
+
+    ("season_phase", |p| p.season_phase),
+    ("plant_density", |p| p.plant_density),
+    ("age_fraction", |p| p.age_fraction),
+];
+
+/// Имена выходов мозга (действий), индекс совпадает с индексом в выходном векторе.
+const OUTPUT_NAMES: [&str; OUTPUT_VECTOR_SIZE] = ["turn_left", "turn_right", "move", "eat", "attack"];
 
 /// Генерация случайного веса для нейросети.
 /// Результат принадлежит диапазону [-1, 1].
-fn generate_weight() -> WeightType {
-    rand::thread_rng().gen_range(-1.0..=1.0)
+///
+/// # Arguments
+///
+/// * `rng`: Генератор случайных чисел.
+///
+/// returns: WeightType
+fn generate_weight(rng: &mut dyn RngCore) -> WeightType {
+    rng.gen_range(-1.0..=1.0)
+}
+
+/// Действие, соответствующее выходному нейрону.
+///
+/// # Arguments
+///
+/// * `output`: Индекс выходного нейрона.
+///
+/// returns: AnimalAction
+fn output_action(output: usize) -> AnimalAction {
+    match output {
+        0 => AnimalAction::TurnLeft,
+        1 => AnimalAction::TurnRight,
+        2 => AnimalAction::Move,
+        3 => AnimalAction::Eat,
+        4 => AnimalAction::Attack,
+        _ => AnimalAction::None,
+    }
+}
+
+/// Розыгрыш действия: находит интервал, в который попало случайное значение. Интервалы
+/// идут подряд от нуля, длина каждого равна активации нейрона.
+///
+/// # Arguments
+///
+/// * `ranges`: Положительные активации нейронов, участвующих в розыгрыше.
+/// * `total`: Сумма активаций.
+/// * `choose`: Случайное значение в диапазоне `0..=total`.
+///
+/// returns: индекс выигравшего нейрона в `ranges`
+fn spin(ranges: &[WeightType], total: WeightType, choose: WeightType) -> usize {
+    // Разыгрываем случайную величину, в соответствии с распределением активированных
+    // нейронов.
+    let mut x1: WeightType = 0 as WeightType;
+    let mut x2: WeightType = 0 as WeightType;
+
+    for (i, v) in ranges.iter().enumerate() {
+        x2 += v;
+        if choose >= x1 && choose < x2 {
+            return i;
+        };
+        x1 += v;
+    }
+
+    // Из-за накопления ошибки округления накопленная сумма может не дотянуть
+    // до `choose` (например, `choose` равно `total`, а сумма интервалов меньше).
+    // Тогда выбирается нейрон с наибольшей активацией.
+    let mut largest = 0;
+    for (i, v) in ranges.iter().enumerate() {
+        if *v > ranges[largest] {
+            largest = i;
+        }
+    }
+
+    if cfg!(debug_assertions) {
+        eprintln!(
+            "Розыгрыш действия не попал ни в один интервал (значение {}, сумма {}), выбрано действие с наибольшей активацией",
+            choose,
+            total
+        );
+    }
+
+    largest
 }
 
 /// Структура, реализующая мозг агента.
@@ -26,10 +145,124 @@ pub struct Brain {
     weights: SMatrix::<WeightType, OUTPUT_VECTOR_SIZE, INPUT_VECTOR_SIZE>,
     // Вектор смещений.
     bias: SVector::<WeightType, OUTPUT_VECTOR_SIZE>,
+    // Генератор случайных чисел для выбора действия. Создается из генератора, которым
+    // создан мозг, поэтому выбор действий воспроизводим вместе с прогоном.
+    rng: CheckpointRng,
 }
 
 impl Brain {
-    fn choose_action(&self, actions: SVector::<WeightType, OUTPUT_VECTOR_SIZE>) -> AnimalAction {
+    /// Описывает, чему научился мозг: для каждого действия веса входов,
+    /// упорядоченные по убыванию абсолютной величины.
+    ///
+    /// # Arguments
+    ///
+    /// * `mask`: Маска входов животного. Замаскированные входы мозг не видит,
+    ///   поэтому их веса ни на что не влияют; в отчете они отмечаются.
+    ///
+    /// returns: BrainReport
+    pub fn describe(&self, mask: InputMask) -> BrainReport {
+        let mut actions = Vec::with_capacity(OUTPUT_VECTOR_SIZE);
+
+        for (output, action) in OUTPUT_NAMES.iter().enumerate() {
+            let mut weights: Vec<(&'static str, WeightType)> = INPUTS.iter()
+                .enumerate()
+                .map(|(input, (name, _))| (*name, self.weights[(output, input)]))
+                .collect();
+
+            // Сортировка устойчивая: при равных весах сохраняется порядок входов.
+            // Полный порядок (total_cmp) дает одинаковый результат и при NaN.
+            weights.sort_by(|a, b| b.1.abs().total_cmp(&a.1.abs()));
+
+            actions.push(ActionWeights {
+                action,
+                bias: self.bias[output],
+                weights,
+            });
+        }
+
+        BrainReport {
+            masked_inputs: mask.to_vec(),
+            actions,
+        }
+    }
+
+    /// Сохраняет геном (веса и смещения) мозга в JSON. Веса записываются по строкам:
+    /// строка соответствует действию, столбец - входу.
+    ///
+    /// returns: String
+    pub fn to_json(&self) -> String {
+        let mut weights: Vec<String> = Vec::with_capacity(OUTPUT_VECTOR_SIZE * INPUT_VECTOR_SIZE);
+        for output in 0..OUTPUT_VECTOR_SIZE {
+            for input in 0..INPUT_VECTOR_SIZE {
+                weights.push(self.weights[(output, input)].to_string());
+            }
+        }
+
+        let bias: Vec<String> = self.bias.iter().map(|b| b.to_string()).collect();
+
+        format!("{{\"weights\": [{}], \"bias\": [{}]}}\n", weights.join(", "), bias.join(", "))
+    }
+
+    /// Загружает геном мозга из JSON, сохраненного методом `to_json`. Геном без
+    /// входов контекста мира загружается с нулевыми весами этих входов, геном без
+    /// выхода нападения - с нулевыми весами и смещением этого выхода.
+    ///
+    /// # Arguments
+    ///
+    /// * `json`: Текст генома.
+    /// * `rng`: Генератор, из которого создается генератор выбора действий (с генератором мира прогон воспроизводим).
+    ///
+    /// returns: Result<Brain, RecoverableError>
+    pub fn from_json(json: &str, rng: &mut dyn RngCore) -> Result<Brain, RecoverableError> {
+        let legacy_inputs = INPUT_VECTOR_SIZE - CONTEXT_INPUTS;
+        let legacy_outputs = OUTPUT_VECTOR_SIZE - ATTACK_OUTPUTS;
+
+        // Количество выходов генома определяется по смещениям.
+        let mut bias = parse_json_array(json, "bias", &[OUTPUT_VECTOR_SIZE, legacy_outputs])?;
+        let outputs = bias.len();
+
+        let mut weights = parse_json_array(
+            json, "weights", &[outputs * INPUT_VECTOR_SIZE, outputs * legacy_inputs]
+        )?;
+        if weights.len() == outputs * legacy_inputs {
+            weights = weights.chunks(legacy_inputs)
+                .flat_map(|row| row.iter().copied().chain([0 as WeightType; CONTEXT_INPUTS]))
+                .collect();
+        }
+        weights.resize(OUTPUT_VECTOR_SIZE * INPUT_VECTOR_SIZE, 0 as WeightType);
+        bias.resize(OUTPUT_VECTOR_SIZE, 0 as WeightType);
+
+        Ok(Brain {
+            weights: SMatrix::<WeightType, OUTPUT_VECTOR_SIZE, INPUT_VECTOR_SIZE>::from_row_slice(&weights),
+            bias: SVector::<WeightType, OUTPUT_VECTOR_SIZE>::from_column_slice(&bias),
+            rng: CheckpointRng::seed_from_u64(rng.gen()),
+        })
+    }
+
+    /// Вычисляет входной вектор и значения выходных нейронов.
+    ///
+    /// # Arguments
+    ///
+    /// * `percept`: Восприятие животного.
+    ///
+    /// returns: (входной вектор, выходной вектор)
+    fn evaluate(&self, percept: &AnimalInputSignal) -> (
+        SVector::<WeightType, INPUT_VECTOR_SIZE>,
+        SVector::<WeightType, OUTPUT_VECTOR_SIZE>,
+    ) {
+        let mut inputs = SVector::<WeightType, INPUT_VECTOR_SIZE>::zeros();
+        // Конвертируем восприятие животного во входной вектор.
+        for (index, (_, value)) in INPUTS.iter().enumerate() {
+            inputs[index] = value(percept);
+        }
+
+        // Подсчитаем выходные значения.
+        let actions = self.bias + self.weights * inputs;
+
+        (inputs, actions)
+    }
+
+    fn choose_action(&mut self, actions: SVector::<WeightType, OUTPUT_VECTOR_SIZE>) -> AnimalAction {
         // Определяем действие - победитель.
         // Применим функцию активации к выходным нейронам и получим распределение
         // активированных нейронов.
@@ -37,43 +270,24 @@ impl Brain {
         let mut outs: Vec<usize> = Vec::with_capacity(MAX_ACTIONS);
         let mut total: WeightType = 0 as WeightType;
 
+        // Значения NaN и бесконечности не участвуют в выборе: они сломали бы розыгрыш.
         for (index, action) in actions.iter().enumerate() {
-            if *action > 0 as WeightType {
+            if action.is_finite() && *action > 0 as WeightType {
                 outs.push(index);
                 ranges.push(*action);
                 total += *action;
             }
         }
 
-        // Активированных нейронов нет.
-        if ranges.is_empty() {
+        // Активированных нейронов нет, или сумма активаций переполнилась.
+        if ranges.is_empty() || !total.is_finite() {
             return AnimalAction::None;
         }
 
         // Получаем случайное значение в диапазоне суммы всех выходных значений.
-        let choose: WeightType = rand::thread_rng().gen_range(0.0..=total);
-
-        // Разыгрываем случайную величину, в соответствии с распределением активированных
-        // нейронов.
-        let mut x1: WeightType = 0 as WeightType;
-        let mut x2: WeightType = 0 as WeightType;
-
-        for (i, v) in ranges.iter().enumerate() {
-            x2 += v;
-            if choose >= x1 && choose < x2 {
-                return match outs[i] {
-                    0 => AnimalAction::TurnLeft,
-                    1 => AnimalAction::TurnRight,
-                    2 => AnimalAction::Move,
-                    3 => AnimalAction::Eat,
-                    _ => AnimalAction::None,
-                };
-            };
-            x1 += v;
-        }
+        let choose: WeightType = self.rng.gen_range(0.0..=total);
 
-        panic!("Алгоритм выбора действия для животного сработал некорректно. Достигнута точка, \
-                которую мы ну ни как достигнуть не могли.");
+        output_action(outs[spin(&ranges, total, choose)])
     }
 
     // fn choose_largest(&self, actions: SVector::<WeightType, OUTPUT_VECTOR_SIZE>) -> AnimalAction {
@@ -98,57 +312,541 @@ impl Brain {
     // }
 }
 
+/// Извлекает из JSON массив чисел по ключу и проверяет его размер.
+///
+/// # Arguments
+///
+/// * `json`: Текст JSON.
+/// * `key`: Ключ массива.
+/// * `sizes`: Допустимые количества элементов.
+///
+/// returns: Result<Vec<WeightType>, RecoverableError>
+fn parse_json_array(json: &str, key: &str, sizes: &[usize]) -> Result<Vec<WeightType>, RecoverableError> {
+    let error = |message: &str| RecoverableError::new(format!("Геном мозга, ключ \"{}\": {}", key, message));
+
+    let key_position = json.find(&format!("\"{}\"", key)).ok_or_else(|| error("ключ не найден"))?;
+    let rest = &json[key_position..];
+    let start = rest.find('[').ok_or_else(|| error("не найдено начало массива"))?;
+    let end = rest.find(']').ok_or_else(|| error("не найден конец массива"))?;
+
+    let values = rest[start + 1..end]
+        .split(',')
+        .filter(|value| !value.trim().is_empty())
+        .map(|value| value.trim().parse::<WeightType>().map_err(|e| error(&e.to_string())))
+        .collect::<Result<Vec<WeightType>, RecoverableError>>()?;
+
+    if !sizes.contains(&values.len()) {
+        let sizes: Vec<String> = sizes.iter().map(|size| size.to_string()).collect();
+        return Err(error(&format!("ожидалось {} значений, найдено {}", sizes.join(" или "), values.len())));
+    }
+
+    Ok(values)
+}
+
+/// Веса входов для одного действия мозга.
+pub struct ActionWeights {
+    // Имя действия.
+    pub action: &'static str,
+    // Смещение выходного нейрона.
+    pub bias: WeightType,
+    // Имена входов и их веса по убыванию абсолютной величины.
+    pub weights: Vec<(&'static str, WeightType)>,
+}
+
+/// Описание мозга: веса входов для каждого действия.
+pub struct BrainReport {
+    // Входы, которые животное не воспринимает (маска входов).
+    pub masked_inputs: Vec<&'static str>,
+    pub actions: Vec<ActionWeights>,
+}
+
+impl BrainReport {
+    /// Представляет описание мозга в виде JSON.
+    pub fn to_json(&self) -> String {
+        let actions: Vec<String> = self.actions.iter()
+            .map(|action| {
+                let weights: Vec<String> = action.weights.iter()
+                    .map(|(input, weight)| format!("{{\"input\": \"{}\", \"weight\": {}}}", input, weight))
+                    .collect();
+
+                format!(
+                    "    {{\"action\": \"{}\", \"bias\": {}, \"weights\": [{}]}}",
+                    action.action,
+                    action.bias,
+                    weights.join(", ")
+                )
+            })
+            .collect();
+
+        let masked: Vec<String> = self.masked_inputs.iter()
+            .map(|input| format!("\"{}\"", input))
+            .collect();
+
+        format!(
+            "{{\n  \"masked_inputs\": [{}],\n  \"actions\": [\n{}\n  ]\n}}\n",
+            masked.join(", "),
+            actions.join(",\n")
+        )
+    }
+}
+
+impl fmt::Display for BrainReport {
+    /// Простая текстовая таблица весов.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for action in &self.actions {
+            writeln!(f, "{} (смещение {:.4})", action.action, action.bias)?;
+
+            for (input, weight) in &action.weights {
+                if self.masked_inputs.contains(input) {
+                    writeln!(f, "  {:<20} {:>8.4} (замаскирован)", input, weight)?;
+                } else {
+                    writeln!(f, "  {:<20} {:>8.4}", input, weight)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
 impl Default for Brain {
-    /// Мозг по умолчанию (заполняется случайными значениями).
+    /// Мозг по умолчанию (заполняется случайными значениями генератора с постоянным
+    /// зерном, чтобы прогон был воспроизводим; случайные мозги создаются `from_rng`).
     fn default() -> Self {
+        Brain::from_rng(&mut SmallRng::seed_from_u64(0))
+    }
+}
+
+impl AnimalBrain for Brain {
+    /// Мозг, заполненный случайными значениями из генератора `rng`.
+    fn from_rng(rng: &mut dyn RngCore) -> Self {
         let mut weights = SMatrix::<WeightType, OUTPUT_VECTOR_SIZE, INPUT_VECTOR_SIZE>::zeros();
         for i in 0..OUTPUT_VECTOR_SIZE * INPUT_VECTOR_SIZE {
-            weights[i] = generate_weight();
+            weights[i] = generate_weight(rng);
         }
 
         let mut bias = SVector::<WeightType, OUTPUT_VECTOR_SIZE>::zeros();
         for i in 0..OUTPUT_VECTOR_SIZE {
-            bias[i] = generate_weight();
+            bias[i] = generate_weight(rng);
         }
 
         Brain {
             weights,
-            bias: SVector::new_random(),
+            bias: SVector::from_fn(|_, _| rng.gen()),
+            rng: CheckpointRng::seed_from_u64(rng.gen()),
         }
     }
-}
 
-impl AnimalBrain for Brain {
-    /// Действие агента.
-    fn action(&mut self, percept: &AnimalInputSignal) -> AnimalAction {
+    /// Геном мозга: веса по строкам, затем смещения.
+    fn to_genome(&self) -> Option<Vec<f64>> {
+        let genome = self.weights.iter()
+            .chain(self.bias.iter())
+            .map(|value| *value as f64)
+            .collect();
 
-        let mut inputs = SVector::<WeightType, INPUT_VECTOR_SIZE>::zeros();
-        // Конвертируем восприятие животного во входной вектор.
-        inputs[0]  = percept.plant_front as WeightType;
-        inputs[1]  = percept.plant_left as WeightType;
-        inputs[2]  = percept.plant_right as WeightType;
-        inputs[3]  = percept.plant_proximity as WeightType;
+        Some(genome)
+    }
+
+    /// Геном мозга в JSON (см. `Brain::to_json`).
+    fn genome_json(&self) -> Option<String> {
+        Some(self.to_json())
+    }
 
-        inputs[4]  = percept.herbivore_front as WeightType;
-        inputs[5]  = percept.herbivore_left as WeightType;
-        inputs[6]  = percept.herbivore_right as WeightType;
-        inputs[7]  = percept.herbivore_proximity as WeightType;
+    /// Мозг из генома в JSON (см. `Brain::from_json`).
+    fn from_genome_json(json: &str, rng: &mut dyn RngCore) -> Result<Self, RecoverableError> {
+        Brain::from_json(json, rng)
+    }
 
-        inputs[8]  = percept.carnivore_front as WeightType;
-        inputs[9]  = percept.carnivore_left as WeightType;
-        inputs[10] = percept.carnivore_right as WeightType;
-        inputs[11] = percept.carnivore_proximity as WeightType;
+    fn rng_state(&self) -> Option<RngState> {
+        Some(RngState::of(&self.rng))
+    }
 
-        // Подсчитаем выходные значения.
-        let actions: SVector::<WeightType, OUTPUT_VECTOR_SIZE>  = self.bias + self.weights * inputs;
+    fn set_rng_state(&mut self, state: &RngState) {
+        self.rng = state.restore();
+    }
+
+    /// Действие агента.
+    fn action(&mut self, percept: &AnimalInputSignal) -> AnimalAction {
+        let (_, actions) = self.evaluate(percept);
         // Передаем владение actions.
         self.choose_action(actions)
     }
 
-    /// Клонировать мозг с мутацией одного веса. Вес выбирается случайно,
-    /// как и значение.
-    fn clone_with_mutation(&self) -> Self {
-        todo!()
+    /// Действие агента с записью входов и выходов нейросети.
+    fn action_traced(&mut self, percept: &AnimalInputSignal, trace: &mut ActionTrace) -> AnimalAction {
+        let (inputs, actions) = self.evaluate(percept);
+
+        trace.inputs = INPUTS.iter()
+            .zip(inputs.iter())
+            .map(|((name, _), value)| (*name, *value as f64))
+            .collect();
+        trace.outputs = OUTPUT_NAMES.iter()
+            .zip(actions.iter())
+            .map(|(name, value)| (*name, *value as f64))
+            .collect();
+
+        self.choose_action(actions)
+    }
+
+    /// Клонировать мозг с мутацией весов. Изменяется целая часть `rate` весов и еще
+    /// один с вероятностью, равной дробной части. Веса выбираются случайно, как и
+    /// значения.
+    fn clone_with_mutation(&self, rng: &mut dyn RngCore, rate: f64) -> Self {
+        let mut weights = self.weights;
+        let mut bias = self.bias;
+
+        let rate = rate.max(0.0);
+        let mut mutations = rate.trunc() as usize;
+        // При целой частоте генератор для дробной части не используется.
+        if rate.fract() > 0.0 && rng.gen_bool(rate.fract()) {
+            mutations += 1;
+        }
+
+        for _ in 0..mutations {
+            // Индекс изменяемого элемента: сначала веса (по столбцам), затем смещения.
+            let weight_count = OUTPUT_VECTOR_SIZE * INPUT_VECTOR_SIZE;
+            let index = rng.gen_range(0..weight_count + OUTPUT_VECTOR_SIZE);
+            if index < weight_count {
+                weights[index] = generate_weight(rng);
+            } else {
+                bias[index - weight_count] = generate_weight(rng);
+            }
+        }
+
+        Brain {
+            weights,
+            bias,
+            rng: CheckpointRng::seed_from_u64(rng.gen()),
+        }
+    }
+
+}
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use super::*;
+
+    /// Количество отличающихся элементов генома (весов и смещений) двух мозгов.
+    fn differences(a: &Brain, b: &Brain) -> usize {
+        a.weights.iter().chain(a.bias.iter())
+            .zip(b.weights.iter().chain(b.bias.iter()))
+            .filter(|(a, b)| a.to_bits() != b.to_bits())
+            .count()
+    }
+
+    #[test]
+    fn single_mutation_changes_exactly_one_element() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let parent = Brain::from_rng(&mut rng);
+
+        for _ in 0..100 {
+            let child = parent.clone_with_mutation(&mut rng, 1.0);
+            assert_eq!(differences(&parent, &child), 1);
+        }
+    }
+
+    #[test]
+    fn zero_rate_copies_genome_bit_for_bit() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let parent = Brain::from_rng(&mut rng);
+        let child = parent.clone_with_mutation(&mut rng, 0.0);
+
+        assert_eq!(differences(&parent, &child), 0);
+    }
+
+    /// Индекс входа по имени.
+    fn input(name: &str) -> usize {
+        INPUTS.iter().position(|(input, _)| *input == name).unwrap()
+    }
+
+    #[test]
+    fn input_names_match_percept_fields() {
+        // Каждое поле восприятия получает свое значение.
+        let percept = AnimalInputSignal {
+            plant_front: 1,
+            plant_left: 2,
+            plant_right: 3,
+            plant_proximity: 4,
+            herbivore_front: 5,
+            herbivore_left: 6,
+            herbivore_right: 7,
+            herbivore_proximity: 8,
+            carnivore_front: 9,
+            carnivore_left: 10,
+            carnivore_right: 11,
+            carnivore_proximity: 12,
+            repro_blocked: 13,
+            wall_front: 14,
+            own_energy: 15.0,
+            season_phase: 16.0,
+            plant_density: 17.0,
+            age_fraction: 18.0,
+        };
+        let expected = [
+            ("plant_front", 1.0), ("plant_left", 2.0), ("plant_right", 3.0), ("plant_proximity", 4.0),
+            ("herbivore_front", 5.0), ("herbivore_left", 6.0), ("herbivore_right", 7.0), ("herbivore_proximity", 8.0),
+            ("carnivore_front", 9.0), ("carnivore_left", 10.0), ("carnivore_right", 11.0), ("carnivore_proximity", 12.0),
+            ("repro_blocked", 13.0), ("wall_front", 14.0), ("own_energy", 15.0),
+            ("season_phase", 16.0), ("plant_density", 17.0), ("age_fraction", 18.0),
+        ];
+
+        let (inputs, _) = Brain::default().evaluate(&percept);
+
+        assert_eq!(expected.len(), INPUT_VECTOR_SIZE);
+        for (name, value) in expected {
+            assert_eq!(inputs[input(name)], value, "вход {}", name);
+        }
+        // Имена входов не повторяются.
+        for (index, (name, _)) in INPUTS.iter().enumerate() {
+            assert_eq!(input(name), index, "вход {}", name);
+        }
+        // Имена выходов совпадают с именами действий.
+        for (output, name) in OUTPUT_NAMES.iter().enumerate() {
+            assert_eq!(output_action(output).name(), *name);
+        }
+    }
+
+    #[test]
+    fn masked_inputs_are_always_zero() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let all: InputMask = Box::leak(INPUTS.map(|(name, _)| name).into());
+
+        for _ in 0..100 {
+            // Все входы восприятия не равны нулю.
+            let mut count = || rng.gen_range(1..10);
+            let percept = AnimalInputSignal {
+                plant_front: count(),
+                plant_left: count(),
+                plant_right: count(),
+                plant_proximity: count(),
+                herbivore_front: count(),
+                herbivore_left: count(),
+                herbivore_right: count(),
+                herbivore_proximity: count(),
+                carnivore_front: count(),
+                carnivore_left: count(),
+                carnivore_right: count(),
+                carnivore_proximity: count(),
+                repro_blocked: 1,
+                wall_front: 1,
+                own_energy: rng.gen_range(0.1..1.0),
+                season_phase: rng.gen_range(0.1..1.0),
+                plant_density: rng.gen_range(0.1..1.0),
+                age_fraction: rng.gen_range(0.1..1.0),
+            };
+            let (inputs, _) = Brain::default().evaluate(&percept);
+            assert!(inputs.iter().all(|&value| value != 0.0));
+
+            // Замаскированный вход равен нулю, остальные не меняются.
+            for index in 0..INPUT_VECTOR_SIZE {
+                let (masked, _) = Brain::default().evaluate(&percept.masked(&all[index..=index]));
+                for (other, (name, _)) in INPUTS.iter().enumerate() {
+                    let expected = if other == index { 0.0 } else { inputs[other] };
+                    assert_eq!(masked[other], expected, "маска {}, вход {}", all[index], name);
+                }
+            }
+
+            let (masked, _) = Brain::default().evaluate(&percept.masked(all));
+            assert!(masked.iter().all(|&value| value == 0.0));
+        }
+    }
+
+    #[test]
+    fn own_energy_changes_chosen_action() {
+        // Сытое животное идет дальше, голодное ест.
+        let brain = || {
+            let mut brain = Brain::default();
+            brain.weights.fill(0.0);
+            brain.bias.fill(0.0);
+            brain.weights[(2, input("own_energy"))] = 1.0;
+            brain.weights[(3, input("own_energy"))] = -1.0;
+            brain.bias[3] = 1.0;
+            brain
+        };
+        let (mut fed, mut hungry) = (brain(), brain());
+
+        let percept = AnimalInputSignal {
+            plant_front: 2,
+            plant_left: 0,
+            plant_right: 1,
+            plant_proximity: 3,
+            herbivore_front: 0,
+            herbivore_left: 1,
+            herbivore_right: 0,
+            herbivore_proximity: 1,
+            carnivore_front: 0,
+            carnivore_left: 0,
+            carnivore_right: 1,
+            carnivore_proximity: 0,
+            repro_blocked: 0,
+            wall_front: 0,
+            own_energy: 1.0,
+            season_phase: 0.5,
+            plant_density: 0.2,
+            age_fraction: 0.3,
+        };
+        let starving = AnimalInputSignal { own_energy: 0.0, ..percept };
+
+        for _ in 0..100 {
+            assert!(fed.action(&percept) == AnimalAction::Move);
+            assert!(hungry.action(&starving) == AnimalAction::Eat);
+        }
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn traced_action_records_synthetic_inputs() {
+        use crate::animal::species::simple::Animal;
+        use crate::animal::{AnimaType, AnimalDirection};
+        use crate::landscape::Energy;
+        use crate::testing;
+
+        // Движение следует за растениями впереди, поедание - за энергией.
+        let mut brain = Brain::default();
+        brain.weights.fill(0.0);
+        brain.bias.fill(0.0);
+        brain.bias[0] = 0.5;
+        brain.weights[(2, input("plant_front"))] = 1.0;
+        brain.weights[(3, input("own_energy"))] = 2.0;
+        let mut animal = Animal::with_brain(
+            AnimaType::Herbivore, testing::animal_params(), AnimalDirection::North, 0, brain
+        ).unwrap();
+
+        // Заданная последовательность восприятий.
+        for step in 0..5_usize {
+            let percept = AnimalInputSignal {
+                plant_front: step,
+                plant_left: step + 1,
+                plant_right: 2,
+                plant_proximity: 3,
+                herbivore_front: 4,
+                herbivore_left: 5,
+                herbivore_right: 6,
+                herbivore_proximity: 7,
+                carnivore_front: 8,
+                carnivore_left: 9,
+                carnivore_right: 10,
+                carnivore_proximity: step * 2,
+                repro_blocked: step % 2,
+                wall_front: 1 - step % 2,
+                own_energy: step as Energy * 0.25,
+                season_phase: 0.5,
+                plant_density: 0.125,
+                age_fraction: 0.75,
+            };
+            let mut trace = ActionTrace::default();
+            let action = animal.action_traced(&percept, &mut trace);
+
+            assert_eq!(trace.inputs, vec![
+                ("plant_front", step as f64),
+                ("plant_left", (step + 1) as f64),
+                ("plant_right", 2.0),
+                ("plant_proximity", 3.0),
+                ("herbivore_front", 4.0),
+                ("herbivore_left", 5.0),
+                ("herbivore_right", 6.0),
+                ("herbivore_proximity", 7.0),
+                ("carnivore_front", 8.0),
+                ("carnivore_left", 9.0),
+                ("carnivore_right", 10.0),
+                ("carnivore_proximity", (step * 2) as f64),
+                ("repro_blocked", (step % 2) as f64),
+                ("wall_front", (1 - step % 2) as f64),
+                ("own_energy", step as f64 * 0.25),
+                ("season_phase", 0.5),
+                ("plant_density", 0.125),
+                ("age_fraction", 0.75),
+            ]);
+            assert_eq!(trace.outputs, vec![
+                ("turn_left", 0.5),
+                ("turn_right", 0.0),
+                ("move", step as f64),
+                ("eat", step as f64 * 0.5),
+                ("attack", 0.0),
+            ]);
+            assert!(trace.action == Some(action));
+        }
+    }
+
+    #[test]
+    fn describe_sorts_weights_by_magnitude() {
+        let mut brain = Brain::default();
+        brain.weights.fill(0.0);
+        brain.bias[0] = 0.25;
+        brain.weights[(0, input("own_energy"))] = -0.1;
+        brain.weights[(0, input("wall_front"))] = 0.5;
+        brain.weights[(0, input("carnivore_proximity"))] = -0.9;
+        brain.weights[(0, input("plant_front"))] = -0.5;
+
+        let report = brain.describe(&["wall_front"]);
+
+        let actions: Vec<&str> = report.actions.iter().map(|action| action.action).collect();
+        assert_eq!(actions, OUTPUT_NAMES);
+        assert_eq!(report.masked_inputs, vec!["wall_front"]);
+
+        let turn_left = &report.actions[0];
+        assert_eq!(turn_left.bias, 0.25);
+        assert_eq!(turn_left.weights.len(), INPUT_VECTOR_SIZE);
+        // По убыванию абсолютной величины; равные по величине веса - в порядке входов.
+        assert_eq!(&turn_left.weights[..4], &[
+            ("carnivore_proximity", -0.9), ("plant_front", -0.5), ("wall_front", 0.5), ("own_energy", -0.1),
+        ]);
+        let rest: Vec<&str> = turn_left.weights[4..].iter().map(|(name, _)| *name).collect();
+        let zero: Vec<&str> = INPUTS.iter()
+            .map(|(name, _)| *name)
+            .filter(|name| !["carnivore_proximity", "plant_front", "wall_front", "own_energy"].contains(name))
+            .collect();
+        assert_eq!(rest, zero);
+
+        assert!(report.to_string().contains("wall_front             0.5000 (замаскирован)"));
+    }
+
+    /// Сколько раз выбрано каждое действие (в порядке выходов) за `draws` розыгрышей.
+    fn choices(actions: [WeightType; OUTPUT_VECTOR_SIZE], draws: usize) -> [usize; OUTPUT_VECTOR_SIZE] {
+        let mut brain = Brain::from_rng(&mut StdRng::seed_from_u64(7));
+        let actions = SVector::<WeightType, OUTPUT_VECTOR_SIZE>::from_column_slice(&actions);
+
+        let mut counts = [0; OUTPUT_VECTOR_SIZE];
+        for _ in 0..draws {
+            let action = brain.choose_action(actions);
+            let output = (0..OUTPUT_VECTOR_SIZE).find(|&output| output_action(output) == action).unwrap();
+            counts[output] += 1;
+        }
+
+        counts
+    }
+
+    #[test]
+    fn equal_activations_are_chosen_evenly() {
+        assert_eq!(spin(&[1.0, 1.0, 1.0], 3.0, 0.0), 0);
+        assert_eq!(spin(&[1.0, 1.0, 1.0], 3.0, 1.0), 1);
+        assert_eq!(spin(&[1.0, 1.0, 1.0], 3.0, 2.5), 2);
+
+        for count in choices([0.5; OUTPUT_VECTOR_SIZE], 5000) {
+            assert!((800..1200).contains(&count), "{}", count);
+        }
+    }
+
+    #[test]
+    fn tiny_activations_still_take_part() {
+        assert_eq!(choices([1e-30, 0.0, -1.0, 0.0, 0.0], 100), [100, 0, 0, 0, 0]);
+        assert_eq!(choices([0.0, 0.0, f32::MIN_POSITIVE, 0.0, -1e-30], 100), [0, 0, 100, 0, 0]);
+
+        let counts = choices([1e-30; OUTPUT_VECTOR_SIZE], 5000);
+        assert!(counts.iter().all(|&count| count > 0), "{:?}", counts);
+    }
+
+    #[test]
+    fn dominant_activation_wins_almost_always() {
+        let counts = choices([0.001, 0.001, 1000.0, 0.001, 0.001], 1000);
+        assert!(counts[2] >= 995, "{:?}", counts);
+    }
+
+    #[test]
+    fn value_equal_to_total_picks_largest_activation() {
+        // Значение на правой границе не попадает ни в один полуоткрытый интервал.
+        assert_eq!(spin(&[1.0, 3.0, 2.0], 6.0, 6.0), 1);
+        assert_eq!(spin(&[0.5], 0.5, 0.5), 0);
+        // Внутри суммы значение попадает в свой интервал.
+        assert_eq!(spin(&[1.0, 3.0, 2.0], 6.0, 4.0), 2);
+    }
+}
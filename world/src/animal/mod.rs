@@ -1,36 +1,84 @@
+use std::any::Any;
+use rand::{Rng, RngCore};
+use crate::animal::brains::ActionTrace;
 use crate::landscape::Energy;
+use crate::rng::RngState;
 
 pub mod brains;
 pub mod species;
 
 /// Возможные виды животных.
-#[derive(Copy, Clone)]
-#[derive(PartialEq)]
+#[derive(Copy, Clone, PartialEq)]
 pub enum AnimaType {
     Herbivore,
     Carnivore,
+    Omnivore,     // Ест растения, а если их нет поблизости - травоядных.
+}
+
+impl AnimaType {
+    /// Имя вида (для сценариев, отчетов и журналов событий).
+    pub fn name(&self) -> &'static str {
+        match self {
+            AnimaType::Herbivore => "herbivore",
+            AnimaType::Carnivore => "carnivore",
+            AnimaType::Omnivore => "omnivore",
+        }
+    }
 }
 
 /// Возможные действия для животного.
 /// Действия, связанные с конфликтами внутри группы пока не рассматриваем!
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq)]
 pub enum AnimalAction {
     TurnLeft,     // Повернуть на лево (агент остается на месте).
     TurnRight,    // Повернуть на право (агент остается на месте).
     Move,         // Сделать шаг вперед.
     Eat,          // Попытаться съесть агента в области близости.
+    Attack,       // Попытаться убить животное в области близости.
     Reproduce,    // Размножение.
     None,         // Нет действия (животное что-то ждет).
 }
-const MAX_ACTIONS: usize = 6;
+const MAX_ACTIONS: usize = 7;
+
+impl AnimalAction {
+    /// Имя действия (для отчетов и следов решений).
+    pub fn name(&self) -> &'static str {
+        match self {
+            AnimalAction::TurnLeft => "turn_left",
+            AnimalAction::TurnRight => "turn_right",
+            AnimalAction::Move => "move",
+            AnimalAction::Eat => "eat",
+            AnimalAction::Attack => "attack",
+            AnimalAction::Reproduce => "reproduce",
+            AnimalAction::None => "none",
+        }
+    }
+}
 
 /// Перечисление, определяющее текущие направление животного.
-#[derive(Copy, Clone)]
-#[derive(PartialEq)]
+#[derive(Copy, Clone, PartialEq)]
 pub enum AnimalDirection {
     North, South, West, East
 }
 
+impl AnimalDirection {
+    /// Возвращает случайное направление (равновероятно).
+    ///
+    /// # Arguments
+    ///
+    /// * `rng`: Генератор случайных чисел.
+    ///
+    /// returns: AnimalDirection
+    pub fn random(rng: &mut dyn RngCore) -> AnimalDirection {
+        match rng.gen_range(0..4) {
+            0 => AnimalDirection::North,
+            1 => AnimalDirection::South,
+            2 => AnimalDirection::West,
+            _ => AnimalDirection::East,
+        }
+    }
+}
+
 /// Структура для передачи значений входных ячеек сенсоров.
 #[derive(Copy, Clone)]
 pub struct AnimalInputSignal {
@@ -48,24 +96,106 @@ pub struct AnimalInputSignal {
     pub carnivore_left: usize,        // Хищник слева.
     pub carnivore_right: usize,       // Хищник справа.
     pub carnivore_proximity: usize,   // Хищник поблизости.
+
+    pub repro_blocked: usize,         // На прошлом ходу не хватило места для размножения.
+    pub wall_front: usize,            // Прямо впереди стена (край мира без склейки).
+
+    pub own_energy: Energy,           // Собственная энергия животного, доля от максимальной [0, 1].
+
+    // Контекст мира (см. `Landscape::set_brain_context`), без контекста равен нулю.
+    pub season_phase: Energy,         // Фаза сезона роста растений [0, 1).
+    pub plant_density: Energy,        // Доля ячеек мира с растениями [0, 1].
+    pub age_fraction: Energy,         // Возраст животного, доля от возраста нормировки [0, 1].
+}
+
+/// Маска входов мозга: имена входов (совпадают с именами полей `AnimalInputSignal`),
+/// значения которых животное не воспринимает.
+pub type InputMask = &'static [&'static str];
+
+impl AnimalInputSignal {
+    /// Возвращает копию входных сигналов, в которой замаскированные входы равны нулю.
+    /// Размер входного вектора не меняется, поэтому геномы остаются совместимыми.
+    ///
+    /// # Arguments
+    ///
+    /// * `mask`: Маска входов.
+    ///
+    /// returns: AnimalInputSignal
+    pub fn masked(&self, mask: InputMask) -> AnimalInputSignal {
+        let mut inputs = *self;
+
+        for name in mask {
+            match *name {
+                "plant_front" => inputs.plant_front = 0,
+                "plant_left" => inputs.plant_left = 0,
+                "plant_right" => inputs.plant_right = 0,
+                "plant_proximity" => inputs.plant_proximity = 0,
+                "herbivore_front" => inputs.herbivore_front = 0,
+                "herbivore_left" => inputs.herbivore_left = 0,
+                "herbivore_right" => inputs.herbivore_right = 0,
+                "herbivore_proximity" => inputs.herbivore_proximity = 0,
+                "carnivore_front" => inputs.carnivore_front = 0,
+                "carnivore_left" => inputs.carnivore_left = 0,
+                "carnivore_right" => inputs.carnivore_right = 0,
+                "carnivore_proximity" => inputs.carnivore_proximity = 0,
+                "repro_blocked" => inputs.repro_blocked = 0,
+                "wall_front" => inputs.wall_front = 0,
+                "own_energy" => inputs.own_energy = 0 as Energy,
+                "season_phase" => inputs.season_phase = 0 as Energy,
+                "plant_density" => inputs.plant_density = 0 as Energy,
+                "age_fraction" => inputs.age_fraction = 0 as Energy,
+                _ => panic!("Маска входов мозга содержит неизвестный вход: {}", name),
+            }
+        }
+
+        inputs
+    }
+}
+
+/// Состояние животного, которое не задается параметрами нового животного и
+/// сохраняется в контрольной точке мира (см. `Landscape::to_checkpoint`).
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct AnimalState {
+    /// Возраст в итерациях.
+    pub age: usize,
+    /// Поколение.
+    pub generation: usize,
+    /// На прошлом ходу не хватило места для размножения.
+    pub reproduce_blocked: bool,
+    /// Запрет на размножение.
+    pub no_repro: bool,
+    /// Состояние генератора случайных чисел мозга. None - мозг не использует случайных чисел.
+    pub brain_rng: Option<RngState>,
 }
 
 /// Типаж, определяющий животное.
-pub trait AnimalAlive {
+pub trait AnimalAlive: Send {
     // Методы получения состояния животного.
 
     /// Мертвое ли?
     fn is_dead(&self) -> bool;
 
+    /// Прожило ли животное больше отпущенного ему срока (умерло от старости)?
+    fn is_old(&self) -> bool;
+
     /// Было ли животное съедено?
     fn is_eaten(&self) -> bool;
 
-    /// Признак того, что на текущей итерации животное уже "совершило свой ход".
-    fn is_processed(&self) -> bool;
+    /// Было ли животное убито хищником (см. `be_killed`)?
+    fn is_killed(&self) -> bool;
+
+    /// Признак того, что на итерации `tick` животное уже "совершило свой ход".
+    fn is_processed(&self, tick: u64) -> bool;
 
     /// Возвращает тип животного.
     fn get_type(&self) -> AnimaType;
 
+    /// Возвращает текущую энергию животного.
+    fn get_energy(&self) -> Energy;
+
+    /// Возвращает максимальную энергию, которую может иметь животное.
+    fn get_max_energy(&self) -> Energy;
+
     /// Возвращает текущее направление движения животного.
     fn get_direction(&self) -> AnimalDirection;
 
@@ -75,9 +205,72 @@ pub trait AnimalAlive {
     /// Возвращает поколение животного.
     fn get_generation(&self) -> usize;
 
+    /// Не удалось ли животному размножиться на прошлом ходу из-за тесноты?
+    fn is_reproduce_blocked(&self) -> bool;
+
+    /// Возвращает энергию тела животного, которая остается в мире в виде падали,
+    /// если животное умерло не будучи съеденным.
+    fn get_carcass_energy(&self) -> Energy;
+
+    /// Возвращает геном мозга животного в виде вектора чисел. None - мозг не
+    /// поддерживает представление генома.
+    fn get_genome(&self) -> Option<Vec<f64>>;
+
+    /// Возвращает геном мозга животного в JSON. None - мозг не поддерживает
+    /// сохранение генома.
+    fn get_genome_json(&self) -> Option<String>;
+
+    /// Возвращает идентификатор животного, уникальный в пределах мира.
+    fn get_id(&self) -> u64;
+
+    /// Возвращает радиус зрения животного: животное видит ячейки, удаленные от
+    /// него не более чем на радиус по каждой из осей. 0 - животное слепое.
+    fn get_vision_radius(&self) -> usize;
+
+    /// Изменяет энергию животного "вручную" (в обход правил мира). Энергия
+    /// ограничивается диапазоном [0, максимальная энергия].
+    ///
+    /// # Arguments
+    ///
+    /// * `delta`: Изменение энергии.
+    ///
+    /// returns: Energy - новая энергия животного.
+    fn adjust_energy(&mut self, delta: Energy) -> Energy;
+
+    /// Задает энергию животного. Энергия ограничивается диапазоном
+    /// [0, максимальная энергия].
+    fn set_energy(&mut self, energy: Energy);
+
+    /// Задает идентификатор животного. Идентификатор назначает мир при добавлении
+    /// животного в мир.
+    fn set_id(&mut self, id: u64);
+
+    /// Возвращает координаты последней удачно съеденной цели, если животное их помнит.
+    fn recall_food(&self) -> Option<(usize, usize)>;
+
+    /// Запоминает координаты удачно съеденной цели (None - забыть).
+    fn remember_food(&mut self, coord: Option<(usize, usize)>);
+
+    /// Возвращает состояние животного для контрольной точки мира.
+    fn get_state(&self) -> AnimalState;
+
+    /// Восстанавливает состояние животного из контрольной точки мира.
+    fn set_state(&mut self, state: &AnimalState);
+
+    /// Прошла еще одна итерация мира. Мир вызывает метод для каждого живого животного
+    /// ровно один раз за итерацию перед методом action.
+    fn tick_passed(&mut self) {}
+
+    /// Отмечает, что на итерации `tick` животное "совершило свой ход". Сравнение с
+    /// номером итерации не требует сброса признака в конце итерации, поэтому признак
+    /// остается верным при любом порядке обхода ячеек. Новорожденное животное мир
+    /// отмечает на итерации рождения: как и животные, заселенные до начала прогона,
+    /// оно впервые ходит (и стареет) на следующей итерации после появления в мире.
+    fn mark_processed(&mut self, tick: u64);
+
     /// Очищает состояние животное. Метод следует вызвать после прохода всех
     /// ячеек на текущей итерации.
-    fn clear(&mut self);
+    fn clear(&mut self) {}
 
     // Метод Action
 
@@ -87,6 +280,10 @@ pub trait AnimalAlive {
     /// разделяемая ссылка на которую передается в качестве параметров.
     fn action(&mut self, inputs: &AnimalInputSignal) -> AnimalAction;
 
+    /// То же, что `action`, но с записью следа решения мозга (входов, выходов и
+    /// выбранного действия). Используется для отслеживаемого животного.
+    fn action_traced(&mut self, inputs: &AnimalInputSignal, trace: &mut ActionTrace) -> AnimalAction;
+
     // Действия, которые реализуют "желания" животного. Эти методы лишь
     // изменяют внутреннее состояние животного и сами не влияют на мир.
     // Удовлетворится ли желание решает Мир по объективным причинам.
@@ -102,8 +299,31 @@ pub trait AnimalAlive {
     /// energy - энергия полученная от съедания.
     fn eat_action(&mut self, energy: Energy);
 
-    /// Реализует желание размножаться.
-    fn reproduce_action(&mut self) -> Box<dyn AnimalAlive>;
+    /// Реализует желание напасть на другое животное. Животное тратит энергию на
+    /// нападение независимо от его исхода.
+    fn attack_action(&mut self);
+
+    /// Реализует желание размножаться. Случайности потомка (мутация мозга,
+    /// направление) берутся из генератора `rng` мира; `mutation_rate` - частота
+    /// мутаций мозга потомка.
+    fn reproduce_action(&mut self, rng: &mut dyn RngCore, mutation_rate: f64) -> Box<dyn AnimalAlive>;
+
+    /// Реализует желание размножаться, переиспользуя память умершего животного:
+    /// `child` переинициализируется на месте как потомок. Возвращает false, если
+    /// `child` не того же конкретного типа, что и родитель; тогда состояние
+    /// родителя не меняется и следует вызвать `reproduce_action`.
+    fn reproduce_into(&mut self, child: &mut dyn AnimalAlive, rng: &mut dyn RngCore, mutation_rate: f64) -> bool;
+
+    /// Возвращает животное как `Any` для приведения к конкретному типу.
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    /// Возвращает животное как `Any` (с передачей владения) для приведения к
+    /// конкретному типу.
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+
+    /// Желание размножаться не удалось реализовать: рядом нет свободного места.
+    /// Животное тратит энергию как при бездействии и узнает о неудаче на следующем ходу.
+    fn reproduce_blocked(&mut self);
 
     /// Действие - "нет действия". Животное может предпочесть оставаться на месте
     /// и ждать когда еда сама придет, экономя энергию.
@@ -113,6 +333,12 @@ pub trait AnimalAlive {
 
     /// Попытка съедения животного.
     fn be_eaten(&mut self) -> Energy;
+
+    /// Животное убито, но не съедено: его тело остается в ячейке тушей, которую
+    /// можно съесть на этой или следующих итерациях.
+    ///
+    /// returns: Energy - энергия туши (0, если животное убить нельзя).
+    fn be_killed(&mut self) -> Energy;
 }
 
 
@@ -0,0 +1,403 @@
+//! Животное любого вида для хранилища мира.
+//!
+//! Вызовы методов `AnimalAlive` у объекта трейта проходят через таблицу виртуальных
+//! методов и указатель на кучу. Набор видов в крейте невелик, поэтому с возможностью
+//! `enum-dispatch` мир хранит животных в перечислении `AnyAnimal`: животные известных
+//! видов лежат в хранилище без указателя, а вызов метода - это сопоставление с
+//! образцом, которое компилятор может встроить. Животные сторонних реализаций
+//! (например, с собственным мозгом) хранятся как объекты трейта.
+
+use std::any::Any;
+use std::mem;
+use rand::RngCore;
+use crate::animal::brains::ActionTrace;
+use crate::animal::brains::simple::Brain;
+use crate::animal::species::simple::Animal;
+use crate::animal::{AnimalAction, AnimalAlive, AnimalDirection, AnimalInputSignal, AnimalState, AnimaType};
+use crate::landscape::Energy;
+
+/// Животное любого вида.
+// Простое животное намеренно хранится без указателя: в этом весь смысл перечисления.
+#[allow(clippy::large_enum_variant)]
+pub enum AnyAnimal {
+    // Простое животное с простым мозгом.
+    Simple(Animal<Brain>),
+    // Животное сторонней реализации.
+    Dyn(Box<dyn AnimalAlive>),
+}
+
+/// Вызывает метод у животного любого варианта `AnyAnimal`.
+macro_rules! delegate {
+    ($self:ident, $animal:ident => $call:expr) => {
+        match $self {
+            AnyAnimal::Simple($animal) => $call,
+            AnyAnimal::Dyn($animal) => $call,
+        }
+    };
+}
+
+/// Способ хранения животных в хранилище мира.
+pub trait AnimalStorage: Sized {
+    /// Размещает животное в хранилище.
+    ///
+    /// # Arguments
+    ///
+    /// * `animal`: Животное.
+    ///
+    /// returns: Self
+    fn store(animal: Box<dyn AnimalAlive>) -> Self;
+
+    /// Возвращает хранимое животное как объект трейта.
+    fn as_animal(&self) -> &dyn AnimalAlive;
+
+    /// Возвращает изменяемое хранимое животное как объект трейта.
+    fn as_animal_mut(&mut self) -> &mut dyn AnimalAlive;
+
+    /// Размер памяти хранимого животного (байты).
+    fn memory_size(&self) -> usize {
+        mem::size_of_val(self.as_animal())
+    }
+}
+
+impl AnimalStorage for Box<dyn AnimalAlive> {
+    fn store(animal: Box<dyn AnimalAlive>) -> Self {
+        animal
+    }
+
+    fn as_animal(&self) -> &dyn AnimalAlive {
+        self.as_ref()
+    }
+
+    fn as_animal_mut(&mut self) -> &mut dyn AnimalAlive {
+        self.as_mut()
+    }
+}
+
+impl AnimalStorage for AnyAnimal {
+    /// Животное простого вида переносится из кучи в перечисление, животное сторонней
+    /// реализации остается объектом трейта.
+    fn store(mut animal: Box<dyn AnimalAlive>) -> Self {
+        if !animal.as_any_mut().is::<Animal<Brain>>() {
+            return AnyAnimal::Dyn(animal);
+        }
+
+        let animal = animal.into_any().downcast::<Animal<Brain>>()
+            .unwrap_or_else(|_| panic!("Внутренняя ошибка программы: животное изменило тип"));
+        AnyAnimal::Simple(*animal)
+    }
+
+    fn as_animal(&self) -> &dyn AnimalAlive {
+        match self {
+            AnyAnimal::Simple(animal) => animal,
+            AnyAnimal::Dyn(animal) => animal.as_ref(),
+        }
+    }
+
+    fn as_animal_mut(&mut self) -> &mut dyn AnimalAlive {
+        match self {
+            AnyAnimal::Simple(animal) => animal,
+            AnyAnimal::Dyn(animal) => animal.as_mut(),
+        }
+    }
+}
+
+impl AnimalAlive for AnyAnimal {
+    fn is_dead(&self) -> bool {
+        delegate!(self, animal => animal.is_dead())
+    }
+
+    fn is_old(&self) -> bool {
+        delegate!(self, animal => animal.is_old())
+    }
+
+    fn is_eaten(&self) -> bool {
+        delegate!(self, animal => animal.is_eaten())
+    }
+
+    fn is_killed(&self) -> bool {
+        delegate!(self, animal => animal.is_killed())
+    }
+
+    fn is_processed(&self, tick: u64) -> bool {
+        delegate!(self, animal => animal.is_processed(tick))
+    }
+
+    fn get_type(&self) -> AnimaType {
+        delegate!(self, animal => animal.get_type())
+    }
+
+    fn get_energy(&self) -> Energy {
+        delegate!(self, animal => animal.get_energy())
+    }
+
+    fn get_max_energy(&self) -> Energy {
+        delegate!(self, animal => animal.get_max_energy())
+    }
+
+    fn get_direction(&self) -> AnimalDirection {
+        delegate!(self, animal => animal.get_direction())
+    }
+
+    fn get_age(&self) -> usize {
+        delegate!(self, animal => animal.get_age())
+    }
+
+    fn get_generation(&self) -> usize {
+        delegate!(self, animal => animal.get_generation())
+    }
+
+    fn is_reproduce_blocked(&self) -> bool {
+        delegate!(self, animal => animal.is_reproduce_blocked())
+    }
+
+    fn get_carcass_energy(&self) -> Energy {
+        delegate!(self, animal => animal.get_carcass_energy())
+    }
+
+    fn get_genome(&self) -> Option<Vec<f64>> {
+        delegate!(self, animal => animal.get_genome())
+    }
+
+    fn get_genome_json(&self) -> Option<String> {
+        delegate!(self, animal => animal.get_genome_json())
+    }
+
+    fn get_id(&self) -> u64 {
+        delegate!(self, animal => animal.get_id())
+    }
+
+    fn get_vision_radius(&self) -> usize {
+        delegate!(self, animal => animal.get_vision_radius())
+    }
+
+    fn adjust_energy(&mut self, delta: Energy) -> Energy {
+        delegate!(self, animal => animal.adjust_energy(delta))
+    }
+
+    fn set_energy(&mut self, energy: Energy) {
+        delegate!(self, animal => animal.set_energy(energy))
+    }
+
+    fn set_id(&mut self, id: u64) {
+        delegate!(self, animal => animal.set_id(id))
+    }
+
+    fn recall_food(&self) -> Option<(usize, usize)> {
+        delegate!(self, animal => animal.recall_food())
+    }
+
+    fn remember_food(&mut self, coord: Option<(usize, usize)>) {
+        delegate!(self, animal => animal.remember_food(coord))
+    }
+
+    fn get_state(&self) -> AnimalState {
+        delegate!(self, animal => animal.get_state())
+    }
+
+    fn set_state(&mut self, state: &AnimalState) {
+        delegate!(self, animal => animal.set_state(state))
+    }
+
+    fn tick_passed(&mut self) {
+        delegate!(self, animal => animal.tick_passed())
+    }
+
+    fn mark_processed(&mut self, tick: u64) {
+        delegate!(self, animal => animal.mark_processed(tick))
+    }
+
+    fn clear(&mut self) {
+        delegate!(self, animal => animal.clear())
+    }
+
+    fn action(&mut self, inputs: &AnimalInputSignal) -> AnimalAction {
+        delegate!(self, animal => animal.action(inputs))
+    }
+
+    fn action_traced(&mut self, inputs: &AnimalInputSignal, trace: &mut ActionTrace) -> AnimalAction {
+        delegate!(self, animal => animal.action_traced(inputs, trace))
+    }
+
+    fn turn_action(&mut self, turn_left: bool) {
+        delegate!(self, animal => animal.turn_action(turn_left))
+    }
+
+    fn move_action(&mut self, realized: bool) {
+        delegate!(self, animal => animal.move_action(realized))
+    }
+
+    fn eat_action(&mut self, energy: Energy) {
+        delegate!(self, animal => animal.eat_action(energy))
+    }
+
+    fn attack_action(&mut self) {
+        delegate!(self, animal => animal.attack_action())
+    }
+
+    fn reproduce_action(&mut self, rng: &mut dyn RngCore, mutation_rate: f64) -> Box<dyn AnimalAlive> {
+        delegate!(self, animal => animal.reproduce_action(rng, mutation_rate))
+    }
+
+    fn reproduce_into(&mut self, child: &mut dyn AnimalAlive, rng: &mut dyn RngCore, mutation_rate: f64) -> bool {
+        delegate!(self, animal => animal.reproduce_into(child, rng, mutation_rate))
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        delegate!(self, animal => animal.as_any_mut())
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        match *self {
+            AnyAnimal::Simple(animal) => Box::new(animal),
+            AnyAnimal::Dyn(animal) => animal.into_any(),
+        }
+    }
+
+    fn reproduce_blocked(&mut self) {
+        delegate!(self, animal => animal.reproduce_blocked())
+    }
+
+    fn inactivity_action(&mut self) {
+        delegate!(self, animal => animal.inactivity_action())
+    }
+
+    fn be_eaten(&mut self) -> Energy {
+        delegate!(self, animal => animal.be_eaten())
+    }
+
+    fn be_killed(&mut self) -> Energy {
+        delegate!(self, animal => animal.be_killed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use crate::arena::{Arena, Handle};
+    use crate::testing;
+
+    /// Входы мозга животного, которое видит растение впереди и хищника поблизости.
+    fn inputs(own_energy: Energy) -> AnimalInputSignal {
+        AnimalInputSignal {
+            plant_front: 1,
+            plant_left: 0,
+            plant_right: 0,
+            plant_proximity: 1,
+            herbivore_front: 0,
+            herbivore_left: 0,
+            herbivore_right: 0,
+            herbivore_proximity: 0,
+            carnivore_front: 0,
+            carnivore_left: 0,
+            carnivore_right: 0,
+            carnivore_proximity: 1,
+            repro_blocked: 0,
+            wall_front: 0,
+            own_energy,
+            season_phase: 0.0,
+            plant_density: 0.0,
+            age_fraction: 0.0,
+        }
+    }
+
+    /// Плотное хранилище животных со случайными мозгами из генератора с зерном и
+    /// дескрипторы животных.
+    fn dense_arena<S: AnimalStorage>(count: usize) -> (Arena<S>, Vec<Handle>) {
+        let mut rng = StdRng::seed_from_u64(testing::TEST_SEED);
+        let mut arena = Arena::new();
+        let handles = (0..count)
+            .map(|index| {
+                let animal_type = if index % 4 == 0 { AnimaType::Carnivore } else { AnimaType::Herbivore };
+                arena.insert(S::store(testing::animal(animal_type, &mut rng)))
+            })
+            .collect();
+
+        (arena, handles)
+    }
+
+    /// Ход животного: то, что мир делает с каждым животным на итерации (без восприятия
+    /// и изменения мира).
+    trait Turn {
+        fn turn(&mut self, tick: u64) -> Option<AnimalAction>;
+    }
+
+    /// Реализует `Turn` одинаково для всех способов хранения: вызовы идут напрямую к
+    /// хранимому значению.
+    macro_rules! impl_turn {
+        ($storage:ty) => {
+            impl Turn for $storage {
+                fn turn(&mut self, tick: u64) -> Option<AnimalAction> {
+                    if self.is_dead() || self.is_processed(tick) {
+                        return None;
+                    }
+
+                    self.tick_passed();
+                    let action = self.action(&inputs(self.get_energy() / self.get_max_energy()));
+                    match action {
+                        AnimalAction::TurnLeft => self.turn_action(true),
+                        AnimalAction::TurnRight => self.turn_action(false),
+                        AnimalAction::Move => self.move_action(true),
+                        AnimalAction::Eat => self.eat_action(0.0),
+                        _ => self.inactivity_action(),
+                    }
+                    self.mark_processed(tick);
+                    self.clear();
+
+                    Some(action)
+                }
+            }
+        };
+    }
+
+    impl_turn!(Box<dyn AnimalAlive>);
+    impl_turn!(AnyAnimal);
+
+    /// Выполняет ходы всех животных хранилища за `ticks` итераций. Возвращает
+    /// выбранные действия и время ходов.
+    fn run_ticks<S: AnimalStorage + Turn>(
+        (arena, handles): &mut (Arena<S>, Vec<Handle>),
+        ticks: u64
+    ) -> (Vec<Option<AnimalAction>>, Duration) {
+        let mut actions = Vec::with_capacity(handles.len() * ticks as usize);
+        let started = Instant::now();
+        for tick in 0..ticks {
+            for &handle in handles.iter() {
+                actions.push(arena.get_mut(handle).unwrap().turn(tick));
+            }
+        }
+
+        (actions, started.elapsed())
+    }
+
+    #[test]
+    fn simple_animal_is_stored_without_box() {
+        let mut rng = StdRng::seed_from_u64(testing::TEST_SEED);
+        let animal = AnyAnimal::store(testing::animal(AnimaType::Herbivore, &mut rng));
+
+        assert!(matches!(animal, AnyAnimal::Simple(_)));
+        assert!(animal.as_animal().get_type() == AnimaType::Herbivore);
+        assert!(animal.memory_size() >= mem::size_of::<Animal<Brain>>());
+    }
+
+    #[test]
+    fn enum_dispatch_matches_trait_objects() {
+        let (count, ticks) = (2_000, 50);
+        let mut boxed = dense_arena::<Box<dyn AnimalAlive>>(count);
+        let mut inline = dense_arena::<AnyAnimal>(count);
+
+        let (boxed_actions, boxed_time) = run_ticks(&mut boxed, ticks);
+        let (inline_actions, inline_time) = run_ticks(&mut inline, ticks);
+
+        assert!(inline_actions == boxed_actions);
+        let boxed_energy: Vec<Energy> = boxed.0.iter().map(|animal| animal.get_energy()).collect();
+        let inline_energy: Vec<Energy> = inline.0.iter().map(|animal| animal.get_energy()).collect();
+        assert_eq!(inline_energy, boxed_energy);
+        eprintln!(
+            "ходы {} животных за {} итераций: объекты трейта {:?}, перечисление {:?}",
+            count, ticks, boxed_time, inline_time
+        );
+    }
+}
@@ -1,3 +1,4 @@
 //! Виды животных.
 
-pub mod simple;
\ No newline at end of file
+pub mod any;
+pub mod simple;
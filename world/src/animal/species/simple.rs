@@ -1,18 +1,128 @@
 //! Простое животное.
 
-use crate::animal::brains::AnimalBrain;
-use crate::animal::{AnimalAction, AnimalAlive, AnimalDirection, AnimalInputSignal, AnimaType};
+use std::any::Any;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use rand::RngCore;
+use crate::animal::brains::{ActionTrace, AnimalBrain};
+use crate::animal::{AnimalAction, AnimalAlive, AnimalDirection, AnimalInputSignal, AnimalState, AnimaType, InputMask};
+use crate::errors::RecoverableError;
 use crate::landscape::Energy;
 
-const TURN_ACTION_ENERGY_RATE: f64 = 1.0;
+/// Затраты энергии на действия животного в долях энергии гомеостаза.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct AnimalEnergyProfile {
+    /// Поворот.
+    pub turn: f64,
+    /// Перемещение.
+    pub movement: f64,
+    /// Попытка поесть.
+    pub eat: f64,
+    /// Нападение (удачное или нет).
+    pub attack: f64,
+    /// Размножение (не считая энергии, переданной потомку).
+    pub reproduce: f64,
+    /// Бездействие и неудавшееся из-за тесноты размножение.
+    pub none: f64,
+    /// Восприятие за итерацию независимо от радиуса зрения.
+    pub perception_base: f64,
+    /// Восприятие за итерацию на квадрат радиуса зрения: всего животное тратит на
+    /// восприятие `perception_base + perception_radius * радиус²`.
+    pub perception_radius: f64,
+}
 
-const MOVE_ACTION_ENERGY_RATE: f64 = 1.0;
+impl AnimalEnergyProfile {
+    /// Все действия стоят энергию гомеостаза, восприятие бесплатно: затраты на
+    /// восприятие задаются явно (см. `perception_base` и `perception_radius`).
+    pub const UNIFORM: AnimalEnergyProfile = AnimalEnergyProfile {
+        turn: 1.0,
+        movement: 1.0,
+        eat: 1.0,
+        attack: 1.0,
+        reproduce: 1.0,
+        none: 1.0,
+        perception_base: 0.0,
+        perception_radius: 0.0,
+    };
+
+    /// Проверяет затраты энергии: каждая затрата должна быть конечным
+    /// неотрицательным числом. Отрицательная затрата превратила бы действие в
+    /// источник энергии.
+    ///
+    /// returns: Result<(), RecoverableError>
+    pub fn validate(&self) -> Result<(), RecoverableError> {
+        let costs = [
+            ("turn", self.turn),
+            ("movement", self.movement),
+            ("eat", self.eat),
+            ("attack", self.attack),
+            ("reproduce", self.reproduce),
+            ("none", self.none),
+            ("perception_base", self.perception_base),
+            ("perception_radius", self.perception_radius),
+        ];
+
+        match costs.iter().find(|(_, cost)| !cost.is_finite() || *cost < 0.0) {
+            Some((name, cost)) => Err(RecoverableError::new(fmt::format(format_args!(
+                "Затрата энергии {} должна быть конечным неотрицательным числом, задано {}", name, cost
+            )))),
+            None => Ok(()),
+        }
+    }
+}
 
-const EAT_ACTION_ENERGY_RATE: f64 = 1.0;
+impl Default for AnimalEnergyProfile {
+    fn default() -> Self {
+        AnimalEnergyProfile::UNIFORM
+    }
+}
 
-const REPRODUCE_ACTION_ENERGY_RATE: f64 = 1.0;
+/// Откуда потомок получает начальное направление.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum DirectionInheritance {
+    /// Направление выбирается случайно, что исключает систематический перекос
+    /// направлений в популяции, основанной одним животным.
+    Random,
+    /// Потомок смотрит туда же, куда родитель в момент размножения.
+    Parent,
+    /// Направление закодировано геномом (весами мозга): потомки с одинаковым геномом
+    /// рождаются с одинаковым направлением, а мутация мозга выбирает направление
+    /// заново. Так предпочтение направления может эволюционировать вместе с мозгом.
+    /// Мозг без генома дает случайное направление.
+    Genome,
+}
 
-const NONE_ACTION_ENERGY_RATE: f64 = 1.0;
+/// Параметры нового животного. Все параметры, кроме запрета на размножение,
+/// наследуются потомками.
+#[derive(Copy, Clone, Debug)]
+pub struct AnimalParams {
+    /// Начальная энергия животного. Столько же энергии родитель передает потомку.
+    pub energy: Energy,
+    /// Максимальная энергия, которую может иметь животное.
+    pub max_energy: Energy,
+    /// Энергия, которую животное теряет на каждой итерации независимо от типа его
+    /// действия (энергия гомеостаза). На основе этой величины вычисляются потери
+    /// энергии для других действий (движение, поворот, и т.д.).
+    pub live_energy: Energy,
+    /// Затраты энергии на действия в долях энергии гомеостаза.
+    pub energy_profile: AnimalEnergyProfile,
+    /// Доля энергии, которую получит хищник, когда съест животное.
+    pub eaten_energy_rate: f64,
+    /// Критерий готовности к размножению (доля максимальной энергии).
+    pub reproduce_energy_rate: f64,
+    /// Запретить размножение животного.
+    pub no_repro: bool,
+    /// Откуда потомки получают начальное направление.
+    pub inherit_direction: DirectionInheritance,
+    /// Входы мозга, значения которых перед передачей мозгу обнуляются.
+    pub input_mask: InputMask,
+    /// Радиус зрения. Чем больше радиус, тем дороже животному обходится восприятие.
+    pub vision_radius: usize,
+    /// Наибольший возраст в итерациях: прожившее дольше животное умирает от старости
+    /// независимо от энергии. 0 - без ограничения.
+    pub max_age: usize,
+}
 
 /// Структура, описывающая состояние агента.
 pub struct Animal<B: AnimalBrain> {
@@ -22,21 +132,32 @@ pub struct Animal<B: AnimalBrain> {
     energy: Energy,              // Энергия животного.
     max_energy: Energy,          // Максимальная энергия которую может иметь животное.
     live_energy: Energy,         // Базовая энергия гомеостаза.
+    energy_profile: AnimalEnergyProfile, // Затраты энергии на действия.
     birth_energy: Energy,        // Энергия, которую получит новое животное при размножении.
     eaten_energy_rate: f64,      // Доля собственная энергия животного, которую получает
                                  // животное съевшее текущее животное.
 
     reproduce_energy_rate: f64,  // Критерий готовности к размножению.
     no_repro: bool,              // Запрет на размножение.
+    inherit_direction: DirectionInheritance, // Откуда потомок получает направление.
+    input_mask: InputMask,       // Входы мозга, которые животное не воспринимает.
+    vision_radius: usize,        // Радиус зрения (0 - животное слепое).
+    max_age: usize,              // Наибольший возраст (0 - без ограничения).
 
     direction: AnimalDirection,  // Текущее направление движения животного (север,
                                  // юг, восток, запад).
 
     // Статистика
+    id: u64,                     // Идентификатор животного (назначается миром).
     age: usize,                  // Возраст животного в "прожитых" итерациях.
     generation: usize,           // Поколение животного (количество его предков).
     is_eaten: bool,              // Признак того, что животное съели.
-    processed: bool,             // Животное совершило "свой ход" на текущей итерации.
+    is_killed: bool,             // Признак того, что животное убили (туша не съедена).
+    repro_blocked: bool,         // На прошлом ходу не хватило места для размножения.
+    last_processed_tick: Option<u64>, // Итерация, на которой животное совершило "свой ход".
+
+    // Память
+    food: Option<(usize, usize)>, // Координаты последней удачно съеденной цели.
 
     // Мозг
     brain: B,
@@ -52,52 +173,173 @@ impl<B: AnimalBrain + 'static> Animal<B> {
     /// # Arguments
     ///
     /// * `animal_type`: Тип животного (травоядное, хищник).
-    /// * `energy`: Начальная энергия животного.
-    /// * `max_energy`: Максимальная энергия, которую мождет иметь животное.
-    /// * `live_energy`: Энергия, которую животное теряет на каждой итерации не
-    /// зависимо от типа его действия (энергия гомеостаза). На основе этой величины
-    /// вычисляются потери энергии для других действий (движение, поворот, и т.д.).
-    /// * `eaten_energy_rate`: Доля энергии которую получит хищник, когда съест
-    /// текущее животное.
-    /// * `reproduce_energy_rate`: Критерий готовности к размножению.
-    /// * `no_repro`: Запретить размножение животного.
+    /// * `params`: Параметры животного (энергия, затраты на действия, зрение и т.д.).
     /// * `direction`: Текущее направление движения.
     /// * `generation`: Поколение. Для животных созданных с самого начала мира -
     /// должно равняться нулю.
+    /// * `rng`: Генератор случайных чисел, из которого создается мозг.
     ///
-    /// Returns: `Box<dyn(AnimalAlive)>`
+    /// Returns: `Result<Box<dyn AnimalAlive>, RecoverableError>` - ошибка, если затраты
+    /// энергии неверны (см. `AnimalEnergyProfile::validate`).
     pub fn new(
         animal_type: AnimaType,
-        energy: Energy,
-        max_energy: Energy,
-        live_energy: Energy,
-        eaten_energy_rate: f64,
-        reproduce_energy_rate: f64,
-        no_repro: bool,
+        params: AnimalParams,
+        direction: AnimalDirection,
+        generation: usize,
+        rng: &mut dyn RngCore,
+    ) -> Result<Box<dyn AnimalAlive>, RecoverableError> {
+        Self::with_brain(animal_type, params, direction, generation, B::from_rng(rng))
+    }
+
+    /// Создает новое животное с заданным мозгом (например, загруженным из файла).
+    /// Параметры, кроме мозга, такие же, как у конструктора `new`.
+    ///
+    /// # Arguments
+    ///
+    /// * `brain`: Мозг животного.
+    ///
+    /// Returns: `Result<Box<dyn AnimalAlive>, RecoverableError>` - ошибка, если затраты
+    /// энергии неверны (см. `AnimalEnergyProfile::validate`).
+    pub fn with_brain(
+        animal_type: AnimaType,
+        params: AnimalParams,
         direction: AnimalDirection,
         generation: usize,
-    ) -> Box<dyn(AnimalAlive)> {
-        let brain = B::default();
+        brain: B,
+    ) -> Result<Box<dyn AnimalAlive>, RecoverableError> {
+        params.energy_profile.validate().map_err(|e| RecoverableError::new(fmt::format(format_args!(
+            "Неверные параметры животного: {}", e
+        ))))?;
 
-        // Рождение, это уже "действие" животного, по этому processed = true.
-        // В противном случае - некоторые животные совершили бы еще один ход
-        // на текущей итерации, а некоторые нет.
-        Box::new(Animal {
+        Ok(Box::new(Animal {
             animal_type,
-            energy,
-            max_energy,
-            live_energy,
-            birth_energy: energy,
-            eaten_energy_rate,
-            reproduce_energy_rate,
-            no_repro,
+            energy: params.energy,
+            max_energy: params.max_energy,
+            live_energy: params.live_energy,
+            energy_profile: params.energy_profile,
+            birth_energy: params.energy,
+            eaten_energy_rate: params.eaten_energy_rate,
+            reproduce_energy_rate: params.reproduce_energy_rate,
+            no_repro: params.no_repro,
+            inherit_direction: params.inherit_direction,
+            input_mask: params.input_mask,
+            vision_radius: params.vision_radius,
+            max_age: params.max_age,
             direction,
+            id: 0,
             age: 0,
             generation,
             is_eaten: false,
-            processed: true,
+            is_killed: false,
+            repro_blocked: false,
+            last_processed_tick: None,
+            food: None,
             brain,
-        })
+        }))
+    }
+
+    /// Создает потомка животного. Родитель тратит энергию на размножение и часть
+    /// своей энергии передает потомку.
+    ///
+    /// # Arguments
+    ///
+    /// * `rng`: Генератор случайных чисел мира.
+    /// * `mutation_rate`: Частота мутаций мозга потомка.
+    ///
+    /// returns: Animal<B>
+    fn child(&mut self, rng: &mut dyn RngCore, mutation_rate: f64) -> Animal<B> {
+        self.energy -= (self.energy_profile.reproduce * self.live_energy as f64) as Energy;
+        // Часть своей энергии передает потомку.
+        self.energy -= self.birth_energy;
+        self.check_energy();
+
+        let brain = self.brain.clone_with_mutation(rng, mutation_rate);
+
+        let direction = match self.inherit_direction {
+            DirectionInheritance::Random => AnimalDirection::random(rng),
+            DirectionInheritance::Parent => self.direction,
+            DirectionInheritance::Genome => match brain.to_genome() {
+                Some(genome) => genome_direction(&genome),
+                None => AnimalDirection::random(rng),
+            },
+        };
+
+        Animal {
+            animal_type: self.animal_type,
+            energy: self.birth_energy,
+            max_energy: self.max_energy,
+            live_energy: self.live_energy,
+            energy_profile: self.energy_profile,
+            birth_energy: self.birth_energy,
+            eaten_energy_rate: self.eaten_energy_rate,
+            reproduce_energy_rate: self.reproduce_energy_rate,
+            no_repro: false, // Если текущее размножилось, то потомки тоже могут.
+            inherit_direction: self.inherit_direction,
+            input_mask: self.input_mask,
+            vision_radius: self.vision_radius,
+            max_age: self.max_age,
+            direction,
+            id: 0,
+            age: 0,
+            generation: self.generation + 1,
+            is_eaten: false,
+            is_killed: false,
+            repro_blocked: false,
+            last_processed_tick: None,
+            food: None,
+            brain,
+        }
+    }
+
+    /// Энергия, которую животное тратит на восприятие за одну итерацию.
+    ///
+    /// returns: Energy
+    fn perception_energy(&self) -> Energy {
+        let radius = self.vision_radius as f64;
+        let rate = self.energy_profile.perception_base + self.energy_profile.perception_radius * radius * radius;
+
+        (rate * self.live_energy as f64) as Energy
+    }
+
+    /// Проверяет, что энергия животного - конечное число. Проверка выполняется только
+    /// в отладочной сборке; в выпускной сборке мир может обнулять такую энергию
+    /// (см. `Landscape::set_energy_sanitizing`).
+    fn check_energy(&self) {
+        debug_assert!(
+            self.energy.is_finite(),
+            "Энергия животного {} не является конечным числом: {}", self.id, self.energy
+        );
+    }
+
+    /// Выбирает действие животного.
+    ///
+    /// # Arguments
+    ///
+    /// * `inputs`: Входные сигналы.
+    /// * `trace`: След решения мозга. None - след не записывается.
+    ///
+    /// returns: AnimalAction
+    fn decide(&mut self, inputs: &AnimalInputSignal, trace: Option<&mut ActionTrace>) -> AnimalAction {
+        // О неудачном размножении животное узнает только на следующем ходу.
+        self.repro_blocked = false;
+
+        // Восприятие требует энергии независимо от выбранного действия.
+        self.energy -= self.perception_energy();
+        self.check_energy();
+
+        // Размножение животного не зависит от решения его мозга.
+        if !self.no_repro
+            && self.energy > (self.reproduce_energy_rate * self.max_energy as f64) as Energy {
+            return AnimalAction::Reproduce;
+        }
+
+        // Передаем вектор входных сигналов в мозг животного. Замаскированные входы
+        // мозг не видит.
+        let inputs = inputs.masked(self.input_mask);
+        match trace {
+            Some(trace) => self.brain.action_traced(&inputs, trace),
+            None => self.brain.action(&inputs),
+        }
     }
 }
 
@@ -109,21 +351,41 @@ impl<B: AnimalBrain + 'static> AnimalAlive for Animal<B> {
             return true;
         }
 
-        false
+        self.is_old()
+    }
+
+    fn is_old(&self) -> bool {
+        self.max_age > 0 && self.age > self.max_age
     }
 
     fn is_eaten(&self) -> bool {
         self.is_eaten
     }
 
-    fn is_processed(&self) -> bool {
-        self.processed
+    fn is_killed(&self) -> bool {
+        self.is_killed
+    }
+
+    fn is_reproduce_blocked(&self) -> bool {
+        self.repro_blocked
+    }
+
+    fn is_processed(&self, tick: u64) -> bool {
+        self.last_processed_tick == Some(tick)
     }
 
     fn get_type(&self) -> AnimaType {
         self.animal_type
     }
 
+    fn get_energy(&self) -> Energy {
+        self.energy
+    }
+
+    fn get_max_energy(&self) -> Energy {
+        self.max_energy
+    }
+
     fn get_direction(&self) -> AnimalDirection {
         self.direction
     }
@@ -136,27 +398,89 @@ impl<B: AnimalBrain + 'static> AnimalAlive for Animal<B> {
         self.generation
     }
 
-    fn clear(&mut self) {
-        self.processed = false;
+    fn get_id(&self) -> u64 {
+        self.id
     }
 
-    // Метод Action
+    fn get_genome(&self) -> Option<Vec<f64>> {
+        self.brain.to_genome()
+    }
 
-    fn action(&mut self, inputs: &AnimalInputSignal) -> AnimalAction {
+    fn get_genome_json(&self) -> Option<String> {
+        self.brain.genome_json()
+    }
+
+    fn get_carcass_energy(&self) -> Energy {
+        // Тело животного стоит столько же, сколько хищник получил бы, съев
+        // новорожденное животное.
+        (self.eaten_energy_rate * self.birth_energy as f64) as Energy
+    }
+
+    fn get_vision_radius(&self) -> usize {
+        self.vision_radius
+    }
+
+    fn set_id(&mut self, id: u64) {
+        self.id = id;
+    }
+
+    fn set_energy(&mut self, energy: Energy) {
+        self.energy = energy.clamp(0 as Energy, self.max_energy);
+    }
+
+    fn adjust_energy(&mut self, delta: Energy) -> Energy {
+        self.energy = (self.energy + delta).clamp(0 as Energy, self.max_energy);
+        self.energy
+    }
+
+    fn recall_food(&self) -> Option<(usize, usize)> {
+        self.food
+    }
+
+    fn remember_food(&mut self, coord: Option<(usize, usize)>) {
+        self.food = coord;
+    }
+
+    fn get_state(&self) -> AnimalState {
+        AnimalState {
+            age: self.age,
+            generation: self.generation,
+            reproduce_blocked: self.repro_blocked,
+            no_repro: self.no_repro,
+            brain_rng: self.brain.rng_state(),
+        }
+    }
+
+    fn set_state(&mut self, state: &AnimalState) {
+        self.age = state.age;
+        self.generation = state.generation;
+        self.repro_blocked = state.reproduce_blocked;
+        self.no_repro = state.no_repro;
+        if let Some(brain_rng) = &state.brain_rng {
+            self.brain.set_rng_state(brain_rng);
+        }
+    }
+
+    fn tick_passed(&mut self) {
         // Животное прожило еще одну итерацию.
         self.age += 1;
+    }
 
-        // Животное совершило "свой ход".
-        self.processed = true;
+    fn mark_processed(&mut self, tick: u64) {
+        self.last_processed_tick = Some(tick);
+    }
 
-        // Размножение животного не зависит от решения его мозга.
-        if !self.no_repro
-            && self.energy > (self.reproduce_energy_rate * self.max_energy as f64) as Energy {
-            return AnimalAction::Reproduce;
-        }
+    // Метод Action
 
-        // Передаем вектор входных сигналов в мозг животного.
-        self.brain.action(inputs)
+    fn action(&mut self, inputs: &AnimalInputSignal) -> AnimalAction {
+        self.decide(inputs, None)
+    }
+
+    fn action_traced(&mut self, inputs: &AnimalInputSignal, trace: &mut ActionTrace) -> AnimalAction {
+        let action = self.decide(inputs, Some(trace));
+        trace.action = Some(action);
+
+        action
     }
 
     // Действия, которые реализуют "желания" животного.
@@ -169,7 +493,8 @@ impl<B: AnimalBrain + 'static> AnimalAlive for Animal<B> {
     /// поворота: `true` - поворот налево, `false` - поворот направо.
     fn turn_action(&mut self, turn_left: bool) {
         // Любое действие животного сопровождается потреблением энергии.
-        self.energy -= (TURN_ACTION_ENERGY_RATE * self.live_energy as f64) as Energy;
+        self.energy -= (self.energy_profile.turn * self.live_energy as f64) as Energy;
+        self.check_energy();
 
         match self.direction {
             AnimalDirection::North => {
@@ -205,76 +530,328 @@ impl<B: AnimalBrain + 'static> AnimalAlive for Animal<B> {
 
     /// Движение животного в перед. Мир должен вызвать это действие - тем самым разрешив его.
     fn move_action(&mut self, _realized: bool) {
-        self.energy -= (MOVE_ACTION_ENERGY_RATE * self.live_energy as f64) as Energy;
+        self.energy -= (self.energy_profile.movement * self.live_energy as f64) as Energy;
+        self.check_energy();
     }
 
     fn eat_action(&mut self, energy: Energy) {
-        self.energy -= (EAT_ACTION_ENERGY_RATE * self.live_energy as f64) as Energy;
+        self.energy -= (self.energy_profile.eat * self.live_energy as f64) as Energy;
         self.energy += energy;
 
         if self.energy > self.max_energy {
             self.energy = self.max_energy;
         }
+        self.check_energy();
     }
 
-    fn reproduce_action(&mut self) -> Box<dyn AnimalAlive> {
-        self.energy -= (REPRODUCE_ACTION_ENERGY_RATE * self.live_energy as f64) as Energy;
-        // Часть своей энергии передает потомку.
-        self.energy -= self.birth_energy;
+    fn attack_action(&mut self) {
+        self.energy -= (self.energy_profile.attack * self.live_energy as f64) as Energy;
+        self.check_energy();
+    }
 
-        let brain = self.brain.clone_with_mutation();
+    fn reproduce_action(&mut self, rng: &mut dyn RngCore, mutation_rate: f64) -> Box<dyn AnimalAlive> {
+        Box::new(self.child(rng, mutation_rate))
+    }
 
-        Box::new(Animal {
-            animal_type: self.animal_type,
-            energy: self.birth_energy,
-            max_energy: self.max_energy,
-            live_energy: self.live_energy,
-            birth_energy: self.birth_energy,
-            eaten_energy_rate: self.eaten_energy_rate,
-            reproduce_energy_rate: self.reproduce_energy_rate,
-            no_repro: false, // Если текущее размножилось, то потомки тоже могут.
-            direction: self.direction,
-            age: 0,
-            generation: self.generation + 1,
-            is_eaten: false,
-            processed: false,
-            brain,
-        })
+    fn reproduce_into(&mut self, child: &mut dyn AnimalAlive, rng: &mut dyn RngCore, mutation_rate: f64) -> bool {
+        match child.as_any_mut().downcast_mut::<Animal<B>>() {
+            Some(child) => {
+                // Переинициализация на месте, память не выделяется.
+                *child = self.child(rng, mutation_rate);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+
+    fn reproduce_blocked(&mut self) {
+        self.energy -= (self.energy_profile.none * self.live_energy as f64) as Energy;
+        self.check_energy();
+        self.repro_blocked = true;
     }
 
     fn inactivity_action(&mut self) {
-        self.energy -= (NONE_ACTION_ENERGY_RATE * self.live_energy as f64) as Energy;
+        self.energy -= (self.energy_profile.none * self.live_energy as f64) as Energy;
+        self.check_energy();
     }
 
     // Действия, которые можно совершить с животным против его воли.
 
     fn be_eaten(&mut self) -> Energy {
-        // TODO: Пока мы просто съедаем травоядное, в последующих реализациях
-        // TODO: можно съедать только убитое животное. Получается хищник сможет
-        // TODO: съедать другого хищника, травоядное сможет реализовывать
-        // TODO: стратегии с атакой и убийством хищников (для обороны). Тем
-        // TODO: не менее, травоядное не может съесть хищника, но эти правила
-        // TODO: закладываются не в этом методе, а в общих правилах мира и мозга,
-        // TODO: тем более мы эти правила можем и изменить введя в рассмотрение
-        // TODO: всеядных животных.
-
-        if self.animal_type == AnimaType::Herbivore {
-            // Частично съесть травоядное нельзя. Найдем энергию которую получит хищник.
+        // Одношаговая охота: хищник убивает и съедает травоядное или всеядное за один
+        // ход. При двухшаговой охоте (см. `be_killed`) жертву сначала убивают, а съедают
+        // оставшуюся тушу.
+        // TODO: Хищник мог бы съедать другого хищника, а травоядное - реализовывать
+        // TODO: стратегии с атакой и убийством хищников (для обороны). Эти правила
+        // TODO: закладываются не в этом методе, а в общих правилах мира и мозга.
+
+        if self.animal_type != AnimaType::Carnivore {
+            // Частично съесть жертву нельзя. Найдем энергию которую получит хищник.
             let energy =  (self.eaten_energy_rate * self.energy as f64) as Energy;
 
-            // Обнуляем энергию (травоядное погибло).
+            // Обнуляем энергию (жертва погибла).
             self.energy = 0 as Energy;
 
             // Показываем от чего именно умерло животное.
             self.is_eaten = true;
 
-            // Съеденное животное теряет возможность совершать действия, т.к. мертво.
-            self.processed = true;
-
             energy
         } else {
             // Хищника вообще съесть нельзя.
             0 as Energy
         }
     }
-}
\ No newline at end of file
+
+    fn be_killed(&mut self) -> Energy {
+        if self.animal_type != AnimaType::Carnivore {
+            // Туша стоит столько же, сколько хищник получил бы, съев жертву сразу.
+            let energy = (self.eaten_energy_rate * self.energy as f64) as Energy;
+
+            // Обнуляем энергию (жертва погибла).
+            self.energy = 0 as Energy;
+
+            // Показываем от чего именно умерло животное.
+            self.is_killed = true;
+
+            energy
+        } else {
+            // Хищника вообще убить нельзя.
+            0 as Energy
+        }
+    }
+}
+
+/// Направление, закодированное геномом: одинаковые геномы дают одно и то же
+/// направление, а разные - равновероятно любое.
+///
+/// # Arguments
+///
+/// * `genome`: Геном мозга (см. `AnimalBrain::to_genome`).
+///
+/// returns: AnimalDirection
+fn genome_direction(genome: &[f64]) -> AnimalDirection {
+    let mut hasher = DefaultHasher::new();
+    for weight in genome {
+        weight.to_bits().hash(&mut hasher);
+    }
+
+    match hasher.finish() % 4 {
+        0 => AnimalDirection::North,
+        1 => AnimalDirection::South,
+        2 => AnimalDirection::West,
+        _ => AnimalDirection::East,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use crate::animal::brains::simple::Brain;
+    use super::*;
+
+    fn params(energy_profile: AnimalEnergyProfile, vision_radius: usize) -> AnimalParams {
+        AnimalParams {
+            energy: 25.0,
+            max_energy: 60.0,
+            live_energy: 0.5,
+            energy_profile,
+            eaten_energy_rate: 0.3,
+            reproduce_energy_rate: 0.9,
+            no_repro: true,
+            inherit_direction: DirectionInheritance::Random,
+            input_mask: &[],
+            vision_radius,
+            max_age: 0,
+        }
+    }
+
+    fn animal(energy_profile: AnimalEnergyProfile, vision_radius: usize) -> Box<dyn AnimalAlive> {
+        let mut rng = StdRng::seed_from_u64(1);
+        Animal::<Brain>::new(AnimaType::Herbivore, params(energy_profile, vision_radius), AnimalDirection::North, 0, &mut rng)
+            .unwrap()
+    }
+
+    fn perception_energy(animal: &mut dyn AnimalAlive) -> Energy {
+        animal.as_any_mut().downcast_mut::<Animal<Brain>>().unwrap().perception_energy()
+    }
+
+    #[test]
+    fn larger_vision_radius_costs_more_energy() {
+        let profile = AnimalEnergyProfile {
+            perception_base: 0.1,
+            perception_radius: 0.05,
+            ..AnimalEnergyProfile::UNIFORM
+        };
+        let costs: Vec<Energy> = (0..4).map(|radius| perception_energy(animal(profile, radius).as_mut())).collect();
+
+        assert!(costs[0] > 0 as Energy, "заданное базовое восприятие не бесплатно");
+        for pair in costs.windows(2) {
+            assert!(pair[1] > pair[0], "затраты на восприятие не растут с радиусом: {:?}", costs);
+        }
+    }
+
+    /// Восприятие животного, вокруг которого ничего нет.
+    fn silence() -> AnimalInputSignal {
+        AnimalInputSignal {
+            plant_front: 0,
+            plant_left: 0,
+            plant_right: 0,
+            plant_proximity: 0,
+            herbivore_front: 0,
+            herbivore_left: 0,
+            herbivore_right: 0,
+            herbivore_proximity: 0,
+            carnivore_front: 0,
+            carnivore_left: 0,
+            carnivore_right: 0,
+            carnivore_proximity: 0,
+            repro_blocked: 0,
+            wall_front: 0,
+            own_energy: 0.5,
+            season_phase: 0.0,
+            plant_density: 0.0,
+            age_fraction: 0.0,
+        }
+    }
+
+    #[test]
+    fn perception_cost_is_exact_at_each_radius() {
+        // (0.5 + 0.25·r²) · 0.5 энергии гомеостаза.
+        let profile = AnimalEnergyProfile {
+            perception_base: 0.5,
+            perception_radius: 0.25,
+            ..AnimalEnergyProfile::UNIFORM
+        };
+
+        for (radius, cost) in [(0, 0.25), (1, 0.375), (2, 0.75), (3, 1.375), (4, 2.25)] {
+            let mut animal = animal(profile, radius);
+            assert_eq!(perception_energy(animal.as_mut()), cost, "радиус {}", radius);
+
+            // Восприятие оплачивается при каждом решении.
+            let energy = animal.get_energy();
+            animal.action(&silence());
+            assert_eq!(animal.get_energy(), energy - cost, "радиус {}", radius);
+        }
+    }
+
+    #[test]
+    fn perception_is_free_by_default() {
+        for radius in 0..4 {
+            assert_eq!(perception_energy(animal(AnimalEnergyProfile::default(), radius).as_mut()), 0 as Energy);
+        }
+    }
+
+    #[test]
+    fn move_cost_changes_energy_after_same_actions() {
+        let cheap = AnimalEnergyProfile { movement: 0.5, ..AnimalEnergyProfile::UNIFORM };
+        let expensive = AnimalEnergyProfile { movement: 2.0, ..AnimalEnergyProfile::UNIFORM };
+        let mut animals = [cheap, expensive].map(|profile| animal(profile, 2));
+
+        for animal in animals.iter_mut() {
+            for _ in 0..10 {
+                animal.move_action(true);
+                animal.turn_action(true);
+                animal.inactivity_action();
+            }
+        }
+
+        // Поворот и бездействие стоят одинаково, разница только в перемещении:
+        // 10 перемещений * (2.0 - 0.5) * 0.5 энергии гомеостаза.
+        let difference = animals[0].get_energy() - animals[1].get_energy();
+        assert!((difference - 7.5).abs() < 1e-3, "разница энергии {}", difference);
+    }
+
+    #[test]
+    fn negative_or_nan_costs_are_rejected() {
+        assert!(AnimalEnergyProfile::UNIFORM.validate().is_ok());
+
+        let negative = AnimalEnergyProfile { eat: -1.0, ..AnimalEnergyProfile::UNIFORM };
+        assert!(negative.validate().is_err());
+
+        let nan = AnimalEnergyProfile { perception_radius: f64::NAN, ..AnimalEnergyProfile::UNIFORM };
+        assert!(nan.validate().is_err());
+    }
+
+    #[test]
+    fn animal_with_negative_cost_is_not_created() {
+        let profile = AnimalEnergyProfile { movement: -1.0, ..AnimalEnergyProfile::UNIFORM };
+        let mut rng = StdRng::seed_from_u64(1);
+
+        let result = Animal::<Brain>::new(AnimaType::Herbivore, params(profile, 2), AnimalDirection::North, 0, &mut rng);
+        let error = result.err().expect("животное с отрицательной затратой создано").to_string();
+        assert!(error.contains("movement"), "{}", error);
+
+        let brain = Brain::from_rng(&mut rng);
+        assert!(Animal::with_brain(AnimaType::Herbivore, params(profile, 2), AnimalDirection::North, 0, brain).is_err());
+    }
+
+    const DIRECTIONS: [AnimalDirection; 4] = [
+        AnimalDirection::North, AnimalDirection::South, AnimalDirection::West, AnimalDirection::East
+    ];
+
+    /// Родитель с заданным наследованием направления и направления его потомков.
+    fn children_directions(
+        inherit_direction: DirectionInheritance,
+        direction: AnimalDirection,
+        mutation_rate: f64,
+        seed: u64,
+    ) -> Vec<AnimalDirection> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let params = AnimalParams { inherit_direction, ..params(AnimalEnergyProfile::UNIFORM, 2) };
+        let mut parent = Animal::<Brain>::new(AnimaType::Herbivore, params, direction, 0, &mut rng).unwrap();
+
+        (0..40).map(|_| parent.reproduce_action(&mut rng, mutation_rate).get_direction()).collect()
+    }
+
+    /// Номера направлений (направление не сравнивается в `assert_eq!`).
+    fn indices(directions: &[AnimalDirection]) -> Vec<usize> {
+        directions.iter()
+            .map(|direction| DIRECTIONS.iter().position(|d| d == direction).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn inherited_children_face_parent_direction() {
+        for direction in DIRECTIONS {
+            let children = children_directions(DirectionInheritance::Parent, direction, 0.5, 1);
+            assert!(children.iter().all(|child| *child == direction));
+        }
+    }
+
+    #[test]
+    fn randomized_children_use_rng() {
+        let children = children_directions(DirectionInheritance::Random, AnimalDirection::North, 0.0, 1);
+
+        // Встречаются все направления, а не только направление родителя.
+        for direction in DIRECTIONS {
+            assert!(children.contains(&direction));
+        }
+        // Направления определяются генератором: то же зерно дает те же направления,
+        // другое - другие.
+        let same = children_directions(DirectionInheritance::Random, AnimalDirection::North, 0.0, 1);
+        let other = children_directions(DirectionInheritance::Random, AnimalDirection::North, 0.0, 2);
+        assert_eq!(indices(&children), indices(&same));
+        assert_ne!(indices(&children), indices(&other));
+    }
+
+    #[test]
+    fn genome_children_share_direction_until_mutation() {
+        // Без мутаций геном потомков совпадает с геномом родителя, как и направление.
+        let children = children_directions(DirectionInheritance::Genome, AnimalDirection::North, 0.0, 1);
+        assert!(children.iter().all(|child| *child == children[0]));
+
+        // Мутации мозга меняют и направление.
+        let mutated = children_directions(DirectionInheritance::Genome, AnimalDirection::North, 1.0, 1);
+        let distinct: std::collections::BTreeSet<usize> = indices(&mutated).into_iter().collect();
+        assert!(distinct.len() > 1);
+    }
+}
@@ -0,0 +1,249 @@
+//! Хранилище агентов мира. Агенты (или указатели на них - объекты трейтов) лежат в
+//! слотах вектора, а ячейки и списки мира ссылаются на них дескрипторами. Дескриптор содержит поколение слота: после
+//! удаления агента слот получает новое поколение, и старый дескриптор больше ничего
+//! не находит, даже если слот занят другим агентом.
+
+/// Дескриптор агента в хранилище.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct Handle {
+    // Индекс слота.
+    index: usize,
+    // Поколение слота на момент размещения агента.
+    generation: u32,
+}
+
+/// Слот хранилища.
+struct Slot<T> {
+    // Поколение слота, увеличивается при удалении агента.
+    generation: u32,
+    // Агент. None - слот свободен или агент временно извлечен (`take`).
+    agent: Option<T>,
+}
+
+/// Хранилище агентов.
+pub struct Arena<T> {
+    // Слоты.
+    slots: Vec<Slot<T>>,
+    // Индексы свободных слотов (агенты удалены).
+    free: Vec<usize>,
+    // Количество агентов, включая временно извлеченных.
+    len: usize,
+}
+
+impl<T> Arena<T> {
+    /// Конструктор.
+    pub fn new() -> Arena<T> {
+        Arena {
+            slots: Vec::new(),
+            free: Vec::new(),
+            len: 0,
+        }
+    }
+
+    /// Размещает агента в хранилище.
+    ///
+    /// # Arguments
+    ///
+    /// * `agent`: Агент.
+    ///
+    /// returns: Handle
+    pub fn insert(&mut self, agent: T) -> Handle {
+        self.len += 1;
+
+        match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index];
+                slot.agent = Some(agent);
+
+                Handle { index, generation: slot.generation }
+            }
+            None => {
+                self.slots.push(Slot { generation: 0, agent: Some(agent) });
+
+                Handle { index: self.slots.len() - 1, generation: 0 }
+            }
+        }
+    }
+
+    /// Возвращает агента по дескриптору. None - агент удален или временно извлечен.
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        self.slots.get(handle.index)
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.agent.as_ref())
+    }
+
+    /// Возвращает изменяемого агента по дескриптору. None - агент удален или временно
+    /// извлечен.
+    pub fn get_mut(&mut self, handle: Handle) -> Option<&mut T> {
+        self.slots.get_mut(handle.index)
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.agent.as_mut())
+    }
+
+    /// Временно извлекает агента, чтобы работать с ним, пока изменяется остальной мир.
+    /// Слот остается за агентом; агента следует вернуть методом `restore`.
+    ///
+    /// # Arguments
+    ///
+    /// * `handle`: Дескриптор агента.
+    ///
+    /// returns: Option<T> - None, если агент удален или уже извлечен.
+    pub fn take(&mut self, handle: Handle) -> Option<T> {
+        self.slots.get_mut(handle.index)
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.agent.take())
+    }
+
+    /// Возвращает агента, извлеченного методом `take`.
+    ///
+    /// # Arguments
+    ///
+    /// * `handle`: Дескриптор агента.
+    /// * `agent`: Агент.
+    ///
+    /// returns: ()
+    pub fn restore(&mut self, handle: Handle, agent: T) {
+        let slot = &mut self.slots[handle.index];
+        assert!(
+            slot.generation == handle.generation && slot.agent.is_none(),
+            "Агент возвращается в чужой слот хранилища"
+        );
+
+        slot.agent = Some(agent);
+    }
+
+    /// Удаляет агента. Дескриптор (и все его копии) после этого ничего не находит.
+    ///
+    /// # Arguments
+    ///
+    /// * `handle`: Дескриптор агента.
+    ///
+    /// returns: Option<T> - удаленный агент.
+    pub fn remove(&mut self, handle: Handle) -> Option<T> {
+        let slot = self.slots.get_mut(handle.index)
+            .filter(|slot| slot.generation == handle.generation)?;
+        let agent = slot.agent.take()?;
+
+        slot.generation += 1;
+        self.free.push(handle.index);
+        self.len -= 1;
+
+        Some(agent)
+    }
+
+    /// Освобождает слот агента, извлеченного методом `take` и не возвращенного.
+    /// Дескриптор (и все его копии) после этого ничего не находит.
+    ///
+    /// # Arguments
+    ///
+    /// * `handle`: Дескриптор агента.
+    ///
+    /// returns: ()
+    pub fn discard(&mut self, handle: Handle) {
+        let slot = &mut self.slots[handle.index];
+        assert!(
+            slot.generation == handle.generation && slot.agent.is_none(),
+            "Освобождается чужой слот хранилища"
+        );
+
+        slot.generation += 1;
+        self.free.push(handle.index);
+        self.len -= 1;
+    }
+
+    /// Количество агентов в хранилище.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Пусто ли хранилище.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Перебирает агентов хранилища (кроме временно извлеченных).
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.slots.iter().filter_map(|slot| slot.agent.as_ref())
+    }
+}
+
+impl<T> Default for Arena<T> {
+    fn default() -> Self {
+        Arena::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_and_remove_track_len() {
+        let mut arena = Arena::new();
+        let first = arena.insert(1);
+        let second = arena.insert(2);
+
+        assert_eq!(arena.len(), 2);
+        assert_eq!(arena.get(first), Some(&1));
+        assert_eq!(arena.get(second), Some(&2));
+
+        assert_eq!(arena.remove(first), Some(1));
+        assert_eq!(arena.len(), 1);
+        assert_eq!(arena.get(first), None);
+        assert_eq!(arena.remove(first), None);
+        assert_eq!(arena.len(), 1);
+    }
+
+    #[test]
+    fn removed_slot_is_reused() {
+        let mut arena = Arena::new();
+        let old = arena.insert(1);
+        arena.insert(2);
+        arena.remove(old);
+
+        let new = arena.insert(3);
+
+        assert_eq!(new.index, old.index);
+        assert_ne!(new, old);
+        assert_eq!(arena.get(new), Some(&3));
+        assert_eq!(arena.len(), 2);
+    }
+
+    #[test]
+    fn stale_handle_to_reused_slot_finds_nothing() {
+        let mut arena = Arena::new();
+        let old = arena.insert(1);
+        arena.remove(old);
+        let new = arena.insert(2);
+
+        assert_eq!(arena.get(old), None);
+        assert_eq!(arena.get_mut(old), None);
+        assert_eq!(arena.take(old), None);
+        assert_eq!(arena.remove(old), None);
+        assert_eq!(arena.get(new), Some(&2));
+        assert_eq!(arena.len(), 1);
+    }
+
+    #[test]
+    fn taken_agent_keeps_its_slot() {
+        let mut arena = Arena::new();
+        let handle = arena.insert(1);
+
+        let agent = arena.take(handle).unwrap();
+        assert_eq!(arena.get(handle), None);
+        assert_eq!(arena.len(), 1);
+
+        // Пока агент извлечен, слот не отдается новому агенту.
+        let other = arena.insert(2);
+        assert_ne!(other.index, handle.index);
+
+        arena.restore(handle, agent);
+        assert_eq!(arena.get(handle), Some(&1));
+
+        arena.take(handle).unwrap();
+        arena.discard(handle);
+        assert_eq!(arena.len(), 1);
+        assert_eq!(arena.insert(3).index, handle.index);
+        assert_eq!(arena.get(handle), None);
+    }
+}
@@ -1,5 +1,148 @@
 //! Функции инициализации.
 
+use std::fmt;
+use std::fs;
+use rand::RngCore;
+use rand::seq::SliceRandom;
+
+use crate::animal::brains::AnimalBrain;
+use crate::animal::{AnimalAlive, AnimaType};
+use crate::errors::RecoverableError;
+use crate::landscape::{Landscape, PlacementStrategy};
+
+/// Создает новое животное заданного типа с заданным мозгом из генератора случайных
+/// чисел мира.
+pub type NewAnimalWithBrain<'a, B> = dyn FnMut(AnimaType, B, &mut dyn RngCore) -> Box<dyn AnimalAlive> + 'a;
+
+/// Читает мозги лучших животных из файла, сохраненного `Landscape::export_best_brains`.
+/// Мозги всеядных пропускаются: всеядные заселяются отдельно.
+///
+/// # Arguments
+///
+/// * `path`: Путь к файлу.
+/// * `rng`: Генератор случайных чисел мира (мозги создаются из него, чтобы прогон был воспроизводим).
+///
+/// returns: Result<Vec<(AnimaType, B)>, RecoverableError> - вид животного и его мозг.
+pub fn read_champions<B: AnimalBrain>(path: &str, rng: &mut dyn RngCore) -> Result<Vec<(AnimaType, B)>, RecoverableError> {
+    let text = fs::read_to_string(path).map_err(|e| RecoverableError::new(fmt::format(format_args!(
+        "Не удалось прочитать мозги лучших животных из файла {}: {}", path, e
+    ))))?;
+
+    let mut champions = Vec::new();
+    for (index, line) in text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        let error = |message: String| RecoverableError::new(fmt::format(format_args!(
+            "Файл {}, строка {}: {}", path, index + 1, message
+        )));
+
+        // Вид, состояние и приспособленность; геном - остаток строки.
+        let mut fields = line.splitn(4, ' ');
+        let species = fields.next().unwrap_or_default();
+        let json = match fields.nth(2) {
+            Some(json) => json,
+            None => return Err(error("ожидается \"<вид> <alive|dead> <приспособленность> <геном>\"".to_string())),
+        };
+
+        let animal_type = match species {
+            "herbivore" => AnimaType::Herbivore,
+            "carnivore" => AnimaType::Carnivore,
+            "omnivore" => continue,
+            _ => return Err(error(format!("неизвестный вид \"{}\"", species))),
+        };
+
+        let brain = B::from_genome_json(json, rng).map_err(|e| error(e.to_string()))?;
+        champions.push((animal_type, brain));
+    }
+
+    Ok(champions)
+}
+
+/// Заселяет мир травоядными и хищниками, мозги которых - клоны лучших животных
+/// своего вида с мутациями (клон каждого животного делается со случайного лучшего
+/// животного). Так отбор можно продолжать в следующих прогонах. Виды, появление
+/// которых отложено, при появлении тоже следует заселять клонами (`clone_champion`).
+///
+/// # Arguments
+///
+/// * `world`: Мир.
+/// * `champions`: Лучшие животные: вид и мозг (см. `read_champions`).
+/// * `herbivores`: Количество травоядных и стратегия их размещения.
+/// * `carnivores`: Количество хищников и стратегия их размещения.
+/// * `mutation_rate`: Частота мутаций клонов: травоядные, хищники.
+/// * `new_animal`: Создает новое животное заданного типа с заданным мозгом из
+///   генератора случайных чисел мира.
+///
+/// returns: Result<(), RecoverableError> - ошибка, если для заселяемого вида нет
+/// лучших животных или животных не удалось разместить.
+pub fn seed_population<B: AnimalBrain>(
+    world: &mut Landscape,
+    champions: &[(AnimaType, B)],
+    herbivores: (usize, PlacementStrategy),
+    carnivores: (usize, PlacementStrategy),
+    mutation_rate: (f64, f64),
+    new_animal: &mut NewAnimalWithBrain<B>,
+) -> Result<(), RecoverableError> {
+    for (count, animal_type, name) in [
+        (herbivores.0, AnimaType::Herbivore, "травоядных"),
+        (carnivores.0, AnimaType::Carnivore, "хищников"),
+    ] {
+        if count > 0 && !champions.iter().any(|(champion_type, _)| *champion_type == animal_type) {
+            return Err(RecoverableError::new(fmt::format(format_args!(
+                "Нет лучших {} для заселения мира", name
+            ))));
+        }
+    }
+
+    // Отложенные виды (см. `Landscape::set_start_tick`) заселяются клонами позже, при
+    // вызове `Landscape::introduce_species` с `clone_champion`.
+    let mut clone = |animal_type: AnimaType, rng: &mut dyn RngCore| -> Box<dyn AnimalAlive> {
+        clone_champion(champions, animal_type, mutation_rate, rng, new_animal)
+            .expect("Внутренняя ошибка программы: нет лучших животных для заселения")
+    };
+
+    // Растения заселяются отдельно.
+    world.populate_random(
+        (0, PlacementStrategy::Uniform),
+        herbivores,
+        carnivores,
+        &mut || unreachable!("Растения не заселяются"),
+        &mut clone,
+    )
+}
+
+/// Создает клон случайного лучшего животного заданного вида с мутациями (см.
+/// `seed_population`).
+///
+/// # Arguments
+///
+/// * `champions`: Лучшие животные: вид и мозг (см. `read_champions`).
+/// * `animal_type`: Вид животного.
+/// * `mutation_rate`: Частота мутаций клонов: травоядные, хищники.
+/// * `rng`: Генератор случайных чисел мира.
+/// * `new_animal`: Создает новое животное заданного типа с заданным мозгом из генератора случайных чисел мира.
+///
+/// returns: Option<Box<dyn AnimalAlive>> - None, если лучших животных этого вида нет.
+pub fn clone_champion<B: AnimalBrain>(
+    champions: &[(AnimaType, B)],
+    animal_type: AnimaType,
+    mutation_rate: (f64, f64),
+    rng: &mut dyn RngCore,
+    new_animal: &mut NewAnimalWithBrain<B>,
+) -> Option<Box<dyn AnimalAlive>> {
+    let brains: Vec<&B> = champions.iter()
+        .filter(|(champion_type, _)| *champion_type == animal_type)
+        .map(|(_, brain)| brain)
+        .collect();
+    let rate = match animal_type {
+        AnimaType::Herbivore => mutation_rate.0,
+        _ => mutation_rate.1,
+    };
+
+    let champion = brains.choose(rng)?;
+    let brain = champion.clone_with_mutation(rng, rate);
+
+    Some(new_animal(animal_type, brain, rng))
+}
+
 /*
 /*
  *  init()
@@ -109,4 +252,69 @@ void initAgent( agentType *agent )
 
   return;
 }
-*/
\ No newline at end of file
+*/
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use crate::animal::AnimalDirection;
+    use crate::animal::brains::simple::Brain;
+    use crate::animal::species::simple::Animal;
+    use crate::testing;
+
+    /// Количество отличающихся генов двух геномов.
+    fn differences(a: &[f64], b: &[f64]) -> usize {
+        a.iter().zip(b).filter(|(a, b)| a.to_bits() != b.to_bits()).count()
+    }
+
+    #[test]
+    fn seeded_and_deferred_animals_are_mutated_champions() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let champions = vec![
+            (AnimaType::Herbivore, Brain::from_rng(&mut rng)),
+            (AnimaType::Carnivore, Brain::from_rng(&mut rng)),
+        ];
+        let genome = |animal_type: AnimaType| champions.iter()
+            .find(|(champion_type, _)| *champion_type == animal_type)
+            .and_then(|(_, brain)| brain.to_genome())
+            .unwrap();
+
+        // Геномы созданных животных по видам.
+        let mut children: Vec<(AnimaType, Vec<f64>)> = Vec::new();
+        let mut new_animal = |animal_type: AnimaType, brain: Brain, _: &mut dyn RngCore| -> Box<dyn AnimalAlive> {
+            children.push((animal_type, brain.to_genome().unwrap()));
+            Animal::with_brain(animal_type, testing::animal_params(), AnimalDirection::North, 0, brain).unwrap()
+        };
+
+        // Хищники появляются на третьей итерации.
+        let mut world = testing::world(10, 10);
+        world.set_start_tick(0, 3, 0);
+        seed_population(
+            &mut world,
+            &champions,
+            (4, PlacementStrategy::Uniform),
+            (3, PlacementStrategy::Uniform),
+            (1.0, 1.0),
+            &mut new_animal,
+        ).unwrap();
+        assert_eq!(world.get_world_stats().animal_count.1, 0);
+
+        while world.get_world_stats().iteration < 3 {
+            world.tick();
+        }
+        let introduced = world.introduce_species(&mut |animal_type, rng| {
+            clone_champion(&champions, animal_type, (1.0, 1.0), rng, &mut new_animal).unwrap()
+        }).unwrap();
+
+        assert!(introduced == vec![AnimaType::Carnivore]);
+        assert_eq!(world.get_world_stats().animal_count.1, 3);
+        assert_eq!(children.iter().filter(|(animal_type, _)| *animal_type == AnimaType::Herbivore).count(), 4);
+        assert_eq!(children.iter().filter(|(animal_type, _)| *animal_type == AnimaType::Carnivore).count(), 3);
+        // Частота мутаций 1: клон отличается от лучшего животного ровно одним геном.
+        for (animal_type, child) in &children {
+            assert_eq!(differences(child, &genome(*animal_type)), 1);
+        }
+    }
+}
@@ -1,5 +1,11 @@
-use display::ScreenType;
-use crate::landscape::Energy;
+use display::{ScreenType, Topology};
+use crate::animal::InputMask;
+use crate::animal::species::simple::{AnimalEnergyProfile, DirectionInheritance};
+use crate::deadline::CheapMode;
+use crate::fitness::{Age, Fitness};
+use crate::writer::OverflowPolicy;
+use crate::plant::BiteMode;
+use crate::landscape::{EatTargeting, Energy, InitialEnergy, PlacementStrategy, ReproductionPlacement};
 
 pub mod init;
 
@@ -8,15 +14,211 @@ pub mod init;
 /// Рабочая директория
 //pub const WORKING_DIR: &str = "D:/Projects/RustProjects/evolution";
 
-/// Драйвер отображения: console, window, none.
+/// Директория, в которой лежит директория ресурсов `resources`. None - ищется рядом
+/// с исполняемым файлом, иначе берется корень исходников. Переменная окружения
+/// EVOLUTION_BASE_PATH важнее этой настройки. Если изображений по пути нет, агенты
+/// рисуются цветными прямоугольниками.
+pub const BASE_PATH: Option<&str> = None;
+
+/// Драйвер отображения: Tetra - окно, Console - символы в терминале.
 pub const SCREEN_TYPE: ScreenType = ScreenType::Tetra;
 
+/// Размер текстур (сторона ячейки в пикселях): 63, 40 или 20. None - наибольший,
+/// при котором окно помещается на монитор.
+pub const TEXTURE_SIZE: Option<usize> = None;
+
+/// Масштаб изображения мира в окне, например 2.0 - ячейки вдвое крупнее текстур.
+/// None - без масштабирования.
+pub const WINDOW_SCALE: Option<f32> = None;
+
+/// Сколько кадров отображения длится эффект события в ячейке (убийство, рождение).
+pub const EVENT_EFFECT_FRAMES: u32 = 20;
+
 /// Не отображать мир на экране. Должно быть true для реальных расчетов.
 pub const HEADLESS_MODE: bool = false;
 
+/// Период (в итерациях) вывода строки статистики мира в режиме без отображения.
+/// 0 - статистика не выводится.
+pub const STATS_PRINT_PERIOD: usize = 0;
+
+/// Период (в итерациях) замеров временного ряда мира, который записывается в режиме
+/// без отображения с ключом `--record out.csv`.
+pub const RECORD_PERIOD: usize = 1;
+
+/// Количество итераций проверки выносливости (подкоманда `soak`), если оно не задано
+/// в командной строке.
+pub const SOAK_TICKS: usize = 5_000_000;
+
+/// Период (в итерациях) замеров памяти и сверки счетчиков при проверке выносливости.
+pub const SOAK_SAMPLE_PERIOD: usize = 10_000;
+
+/// Допустимый рост памяти процесса при проверке выносливости (байт за итерацию,
+/// после прогрева).
+pub const SOAK_MAX_MEMORY_SLOPE: f64 = 1.0;
+
+// Файл отчета проверки выносливости.
+pub const SOAK_REPORT_FILE: &str = "soak_report.txt";
+
+/// Время (миллисекунды), отведенное на итерацию мира. Если 95-й процентиль
+/// продолжительности итераций за TICK_DEADLINE_WINDOW итераций его превышает, по
+/// очереди включаются режимы DEGRADATION_ORDER; когда он становится ниже доли
+/// TICK_DEADLINE_HEADROOM отведенного времени, режимы выключаются в обратном порядке.
+/// 0 - итерации не ограничены по времени.
+pub const TICK_BUDGET_MS: u64 = 0;
+pub const TICK_DEADLINE_WINDOW: usize = 50;
+pub const TICK_DEADLINE_HEADROOM: f64 = 0.5;
+pub const DEGRADATION_ORDER: [CheapMode; 3] = [
+    CheapMode::SparsePlants,
+    CheapMode::SparseStats,
+    CheapMode::NoValidation,
+];
+
+/// Период обработки растений (PLANT_UPDATE_INTERVAL) в режиме редкой обработки растений.
+pub const DEGRADED_PLANT_UPDATE_INTERVAL: usize = 4;
+
+/// Во сколько раз реже собирается статистика (DIVERSITY_PERIOD, STATS_PRINT_PERIOD)
+/// в режиме редкого сбора статистики.
+pub const DEGRADED_STATS_FACTOR: usize = 10;
+
+/// Период (в итерациях) сверки счетчиков статистики с их пересчетом по сетке мира.
+/// 0 - сверка не выполняется.
+pub const STATS_VALIDATION_PERIOD: usize = 0;
+
+/// Обнулять энергию агентов, которая перестала быть конечным числом (NaN, бесконечность),
+/// с сообщением об этом. В отладочной сборке такая энергия останавливает программу.
+pub const SANITIZE_ENERGY: bool = false;
+
+/// Приблизительный бюджет памяти прогона в байтах. При превышении мир освобождает
+/// память умерших животных. 0 - не ограничен.
+pub const MEMORY_BUDGET: usize = 0;
+
+/// Период (в итерациях) замера генетического разнообразия видов. 0 - замер не выполняется.
+/// Замер сравнивает геномы выборки попарно (DIVERSITY_SAMPLE² сравнений), поэтому по
+/// умолчанию выключен; регулятор частоты мутаций (MUTATION_CONTROLLER) требует замера.
+pub const DIVERSITY_PERIOD: usize = 0;
+
+/// Максимальное количество животных одного вида в выборке для замера разнообразия.
+pub const DIVERSITY_SAMPLE: usize = 50;
+
+/// Возраст (итерации), до которого животные защищены от хищников: травоядные,
+/// хищники, всеядные. Защищенные животные отмечаются на экране. 0 - защиты нет.
+pub const JUVENILE_PROTECTION_AGE: (usize, usize, usize) = (0, 0, 0);
+
+/// Передавать мозгу контекст мира (фазу сезона роста растений PLANT_SEASON, долю
+/// ячеек с растениями, долю возраста животного): возраст, которому соответствует
+/// доля возраста 1 (итерации). None - входы контекста равны нулю.
+pub const BRAIN_CONTEXT: Option<usize> = None;
+
+/// Частота мутаций мозга потомков (травоядные, хищники, всеядные): среднее количество
+/// генов, изменяемых при размножении.
+pub const MUTATION_RATE: (f64, f64, f64) = (1.0, 1.0, 1.0);
+
+/// Регулятор частоты мутаций (target_diversity, gain, bounds): после каждого замера
+/// разнообразия (DIVERSITY_PERIOD) частота сдвигается на
+/// gain * (target_diversity - разнообразие) в пределах bounds (min, max).
+/// None - частота постоянна.
+pub const MUTATION_CONTROLLER: Option<(f64, f64, (f64, f64))> = None;
+
+/// Оценка приспособленности, по которой выбираются лучшие животные: Age (возраст),
+/// Offspring (потомки), EnergyLifetime (энергия за жизнь), Kills (жертвы хищника).
+pub const FITNESS: &dyn Fitness = &Age;
+
+/// Размер окна (в итерациях) скользящих средних эффективности поедания.
+pub const HUNT_STATS_WINDOW: usize = 100;
+
+/// Количество последних итераций, за которые в строке статистики (STATS_PRINT_PERIOD)
+/// выводится энергетический баланс видов.
+pub const ENERGY_BUDGET_WINDOW: usize = 100;
+
+/// Добавлять в файл статистики (STATS_CSV_FILE) столбцы энергетического баланса видов.
+pub const STATS_CSV_ENERGY_BUDGET: bool = false;
+
+/// Сколько последних итераций статистики мир хранит для графиков численности
+/// в окне отображения.
+pub const RECENT_HISTORY: usize = 1000;
+
+/// Файл, в который сохраняются итоги прогона (JSON).
+pub const RUN_SUMMARY_FILE: &str = "run_summary.json";
+
+// Файл, в который сохраняется статистика мира по итерациям (для сравнения прогонов).
+pub const STATS_CSV_FILE: &str = "stats.csv";
+
+/// Имя файла (без зерна и расширения), в который в конце прогона сохраняются мозги
+/// лучших животных: `<RUN_DIR>/best_brains_<зерно>.txt`. Следующий прогон можно
+/// заселить их клонами: `evolution --seed-agents run/best_brains_<зерно>.txt`.
+pub const BEST_BRAINS_FILE: &str = "best_brains";
+
+/// Учитывать занятость ячеек (сколько итераций в ячейке было растение, травоядное,
+/// хищник и всеядное) и сохранять ее в конце прогона в файлы
+/// `<OCCUPANCY_FILE_PREFIX>_*.csv` и тепловые карты `<OCCUPANCY_FILE_PREFIX>_*.png`.
+pub const OCCUPANCY_TRACKING: bool = false;
+
+/// Путь к файлам занятости ячеек без суффикса вида и расширения.
+pub const OCCUPANCY_FILE_PREFIX: &str = "occupancy";
+
+/// Сколько записей о жизни последних умерших животных хранит мир (для медианы
+/// продолжительности жизни в итогах прогона). 0 - записи не хранятся.
+pub const POSTMORTEM_HISTORY: usize = 1000;
+
+/// Количество травоядных и хищников, которыми заселяется мир с `--seed-agents`
+/// (вместо INITIAL_HERBIVORES и INITIAL_CARNIVORES).
+pub const SEED_HERBIVORES: usize = 20;
+pub const SEED_CARNIVORES: usize = 10;
+
+// Файл сценария, в который сохраняется состояние мира по команде из окна отображения
+// (клавиша F5). Геномы мозгов сохраняются в файлы рядом с ним.
+pub const SCENARIO_FILE: &str = "scenario.txt";
+
+// Файл контрольной точки, которая сохраняется вместе со сценарием: с нее прогон
+// продолжается так же, как продолжился бы без остановки.
+pub const CHECKPOINT_FILE: &str = "checkpoint.txt";
+
+// Файл манифеста прогона: версия, настройки, зерно и итоговая статистика. По манифесту
+// подкоманда `verify <директория прогона>` повторяет прогон и проверяет результат.
+pub const RUN_MANIFEST_FILE: &str = "run_manifest.txt";
+
+// Директория прогона, в которую сохраняется манифест прогона.
+pub const RUN_DIR: &str = "run";
+
+// Файл (JSONL), в который записываются решения мозга выбранного щелчком животного.
+pub const ACTION_TRACE_FILE: &str = "action_trace.jsonl";
+
+// Решения мозга записываются в файл отдельным потоком. Сколько итераций решений может
+// ждать записи, и что делать, если поток записи не успевает: Block - ждать,
+// Drop - отбросить решения итерации.
+pub const ACTION_TRACE_QUEUE: usize = 64;
+pub const ACTION_TRACE_OVERFLOW: OverflowPolicy = OverflowPolicy::Block;
+
+// Файл (JSONL), в который записываются события мира (см. WorldEvent). None - события
+// не записываются.
+pub const EVENTS_FILE: Option<&str> = None;
+
+// Файл (CSV) родословной: кто от кого и на какой итерации родился. None - родословная
+// не записывается.
+pub const LINEAGE_FILE: Option<&str> = Some("lineage.csv");
+
+// Статистика по итерациям (STATS_CSV_FILE), события и родословная записываются в файлы
+// отдельными потоками. Сколько итераций строк может ждать записи, и что делать, если
+// поток записи не успевает (см. ACTION_TRACE_OVERFLOW).
+pub const OUTPUT_QUEUE: usize = 256;
+pub const OUTPUT_OVERFLOW: OverflowPolicy = OverflowPolicy::Block;
+
+// Раз в сколько итераций строки событий и родословной передаются потокам записи. Между
+// передачами строки копятся в памяти и учитываются бюджетом памяти (MEMORY_BUDGET):
+// если он превышен, самые старые строки отбрасываются.
+pub const OUTPUT_BATCH_TICKS: usize = 1;
+
 
 // Настройки среды
 
+/// Зерно генератора случайных чисел мира. С одинаковым зерном и настройками прогоны
+/// совпадают. None - зерно случайное.
+pub const SEED: Option<u64> = None;
+
+/// Топология мира: Torus (края склеены), Bounded (края - стена) или
+/// Cylinder { wrap_x, wrap_y } (края склеены только по выбранным осям).
+pub const TOPOLOGY: Topology = Topology::Torus;
+
 /// Максимальное количество итераций мира.
 pub const MAX_STEPS: usize = 1000; // 1000000
 
@@ -24,10 +226,22 @@ pub const MAX_STEPS: usize = 1000; // 1000000
 //pub const STEP: bool = false;
 
 /// Размеры сетки мира.
-/// (96, 54) максимальный размер мира в текущей реализации, соответствует разрешению 1920x1080.
+/// (96, 54) максимальный размер мира на мониторе 1920x1080 (больше - с TEXTURE_SIZE и WINDOW_SCALE).
 pub const GRID_WIDTH: usize = 96;
 pub const GRID_HEIGHT: usize = 54;
 
+/// Ширина полосы (в ячейках) вдоль краев мира, в которой агенты не размещаются ни при
+/// заселении, ни при размножении. 0 - полосы нет.
+pub const SPAWN_MARGIN: usize = 0;
+
+/// Начальное заселение мира: количество агентов и стратегия их размещения. Животные
+/// смотрят в случайных направлениях.
+pub const INITIAL_PLANTS: (usize, PlacementStrategy) = (20, PlacementStrategy::Uniform);
+pub const INITIAL_HERBIVORES: (usize, PlacementStrategy) = (10, PlacementStrategy::Uniform);
+pub const INITIAL_CARNIVORES: (usize, PlacementStrategy) = (5, PlacementStrategy::Uniform);
+/// Всеядных не больше `MAX_OMNIVORE`.
+pub const INITIAL_OMNIVORES: (usize, PlacementStrategy) = (0, PlacementStrategy::Uniform);
+
 /// Максимальное количество растений.
 /// 0 - не ограничено.
 pub const MAX_PLANTS: usize = 35;
@@ -40,9 +254,38 @@ pub const MAX_HERBIVORE: usize = 18;
 /// 0 - не ограничено.
 pub const MAX_CARNIVORE: usize = 18;
 
+/// Максимальное количество всеядных. 0 - всеядных в мире нет.
+pub const MAX_OMNIVORE: usize = 0;
+
+/// Доля энергии съеденного растения, которую получает всеядное.
+pub const OMNIVORE_PLANT_ENERGY_RATE: f64 = 0.5;
+
+/// Доля энергии съеденного травоядного, которую получает всеядное.
+pub const OMNIVORE_MEAT_ENERGY_RATE: f64 = 0.5;
+
 /// Максимальная энергия которую может получить растение на каждой итерации.
 pub const MAX_PLANT_GROW_ENERGY: Energy = 5.;
 
+/// Сезонность роста растений: длина сезона (итерации) и амплитуда из [0, 1]. Энергия
+/// роста умножается на 1 + амплитуда * sin(2π * фаза сезона). None - сезонов нет.
+pub const PLANT_SEASON: Option<(usize, f64)> = None;
+
+/// Строгий режим поедания: животное ест только то, что находится прямо перед ним.
+/// По умолчанию животное может съесть агента в любой ячейке области близости.
+pub const STRICT_EATING: bool = false;
+
+/// Травоядное сначала пытается доесть растение, которое ело в прошлый раз.
+pub const HERBIVORE_FOOD_FOCUS: bool = false;
+
+/// Способ выбора растения травоядным, если в области близости их несколько.
+pub const HERBIVORE_EAT_TARGETING: EatTargeting = EatTargeting::Random;
+
+/// Способ выбора жертвы хищником, если в области близости их несколько.
+pub const CARNIVORE_EAT_TARGETING: EatTargeting = EatTargeting::Random;
+
+/// Способ выбора растения или жертвы всеядным, если в области близости их несколько.
+pub const OMNIVORE_EAT_TARGETING: EatTargeting = EatTargeting::Random;
+
 
 
 // Настройки растений
@@ -59,6 +302,36 @@ pub const PLANT_REPRODUCE_ENERGY_RATE: f64 = 0.5;
 /// Запрещает размножение растений.
 pub const PLANT_NO_REPRO: bool = true;
 
+/// Растения обрабатываются раз в столько итераций (энергия роста растет пропорционально).
+/// 1 - на каждой итерации. Сокращает работу итерации в мирах с большим количеством растений.
+pub const PLANT_UPDATE_INTERVAL: usize = 1;
+
+/// Способ вычисления энергии, которую растение отдает за один укус.
+pub const PLANT_BITE_MODE: BiteMode = BiteMode::Fixed;
+
+/// Количество итераций, в течение которых полностью съеденное растение не растет.
+pub const PLANT_REGROWTH_DELAY: usize = 0;
+
+/// Возраст (итерации), по достижении которого растение умирает и освобождает ячейку.
+/// 0 - растения не умирают от старости.
+pub const PLANT_MAX_AGE: usize = 0;
+
+/// Количество семян, которые растение пытается рассыпать за одно размножение.
+pub const PLANT_SEEDS_PER_REPRODUCTION: usize = 1;
+
+/// Энергия, которую растение тратит на каждое семечко.
+pub const PLANT_SEED_ENERGY_COST: Energy = 0.;
+
+/// Где прорастает семечко: Anywhere (в любой свободной ячейке мира) или
+/// Adjacent { radius } (сначала рядом с растением).
+pub const PLANT_REPRODUCTION_PLACEMENT: ReproductionPlacement = ReproductionPlacement::Anywhere;
+
+// Начальная энергия растений при заселении мира.
+pub const PLANT_INITIAL_ENERGY: InitialEnergy = InitialEnergy::Full;
+
+// Начальная энергия животных при заселении мира.
+pub const ANIMAL_INITIAL_ENERGY: InitialEnergy = InitialEnergy::Full;
+
 
 // Настройки животных
 
@@ -71,9 +344,29 @@ pub const ANIMAL_BIRTH_ENERGY: Energy = 25.;
 // Энергия, которую теряет животное, что-бы жить.
 pub const ANIMAL_LIVE_ENERGY: Energy = 0.005;
 
+// Затраты энергии животных на действия (поворот, перемещение, попытку поесть,
+// нападение, размножение, бездействие) и на восприятие в долях энергии гомеостаза
+// ANIMAL_LIVE_ENERGY. В профиле UNIFORM восприятие бесплатно.
+pub const ANIMAL_ENERGY_PROFILE: AnimalEnergyProfile = AnimalEnergyProfile::UNIFORM;
+
 // Какую часть от энергии съеденного животного получит хищник.
 pub const ANIMAL_EATEN_ENERGY_RATE: f64 = 0.3;
 
+// Двухшаговая охота: хищник сначала убивает травоядное нападением, а затем съедает
+// оставшуюся в ячейке тушу (ее может съесть и другое животное). false - хищник
+// убивает и съедает травоядное одним действием "есть". Геномы, сохраненные до
+// появления действия "напасть", не умеют нападать: их хищники при двухшаговой
+// охоте голодают.
+pub const TWO_STEP_PREDATION: bool = false;
+
+// Вероятность удачного нападения на жертву с равной нападающему энергией (более
+// сильная жертва отбивается чаще).
+pub const ATTACK_SUCCESS_RATE: f64 = 1.0;
+
+// Сколько итераций туша убитого животного лежит в ячейке, после чего становится
+// падалью (или пропадает, если падаль никто не ест). 0 - без ограничения.
+pub const CORPSE_LIFETIME: usize = 5;
+
 // Константа определяет благоприятные условия для размножения животного. Т.е. как только,
 // энергия животного достигнет величины, равной этой доли от максимальной энергии животного,
 // животное размножится.
@@ -81,3 +374,104 @@ pub const ANIMAL_REPRODUCE_ENERGY_RATE: f64 = 0.9;
 
 // No reproduction
 pub const ANIMAL_NO_REPRO: bool = false;
+
+// Где появляется потомок животного: Anywhere (в любой свободной ячейке мира) или
+// Adjacent { radius } (сначала рядом с родителем).
+pub const ANIMAL_REPRODUCTION_PLACEMENT: ReproductionPlacement = ReproductionPlacement::Anywhere;
+
+// Сколько итераций животное вынашивает потомка. 0 - потомок появляется сразу.
+pub const GESTATION_TICKS: usize = 0;
+
+// Энергия, которую родитель дополнительно теряет на каждой итерации вынашивания.
+pub const GESTATION_ENERGY_DRAIN: Energy = 0.;
+
+// Сколько потомков животное может вынашивать одновременно. 0 - без ограничения.
+pub const MAX_PENDING_BIRTHS: usize = 1;
+
+// Итерация, на которой в мире появляются травоядные. 0 - сразу.
+pub const HERBIVORE_START_TICK: usize = 0;
+
+// Итерация, на которой в мире появляются хищники. 0 - сразу. Травоядным полезно
+// дать время расселиться до появления хищников.
+pub const CARNIVORE_START_TICK: usize = 0;
+
+// Итерация, на которой в мире появляются всеядные. 0 - сразу.
+pub const OMNIVORE_START_TICK: usize = 0;
+
+// Максимальное количество рождений травоядных за одну итерацию. 0 - не ограничено.
+pub const MAX_HERBIVORE_BIRTHS_PER_TICK: usize = 0;
+
+// Максимальное количество рождений хищников за одну итерацию. 0 - не ограничено.
+pub const MAX_CARNIVORE_BIRTHS_PER_TICK: usize = 0;
+
+// Максимальное количество рождений всеядных за одну итерацию. 0 - не ограничено.
+pub const MAX_OMNIVORE_BIRTHS_PER_TICK: usize = 0;
+
+// Травоядные поедают падаль (останки умерших от голода животных), когда поблизости
+// нет растений.
+pub const HERBIVORE_SCAVENGING: bool = false;
+
+// Хищники поедают падаль, когда поблизости нет травоядных.
+pub const CARNIVORE_SCAVENGING: bool = false;
+
+// Всеядные поедают падаль, когда поблизости нет ни растений, ни травоядных.
+pub const OMNIVORE_SCAVENGING: bool = false;
+
+// Доля энергии съеденной падали, которую получает животное.
+pub const CARRION_ENERGY_RATE: f64 = 0.5;
+
+// Сколько энергии падали животное съедает за раз.
+pub const CARRION_BITE_ENERGY: Energy = 10.;
+
+// Сколько итераций падаль лежит в ячейке, после чего разлагается полностью.
+// 0 - без ограничения.
+pub const CARRION_LIFETIME: usize = 0;
+
+// Доля энергии падали, которую она теряет за итерацию (разложение). 0 - падаль не разлагается.
+pub const CARRION_DECAY_RATE: f64 = 0.0;
+
+// Доля энергии разложившейся падали, которая переходит в плодородие почвы (дополнительную
+// энергию роста растения в ячейке).
+pub const CARRION_FERTILITY_RATE: f64 = 0.5;
+
+// Доля плодородия почвы, которую она теряет за итерацию.
+pub const FERTILITY_DECAY_RATE: f64 = 0.1;
+
+// Минимальное количество растений в области близости, при котором травоядное может
+// размножиться. 0 - без проверки.
+pub const HERBIVORE_REPRODUCE_MIN_FOOD: usize = 0;
+
+// Минимальное количество травоядных в области близости, при котором хищник может
+// размножиться. 0 - без проверки.
+pub const CARNIVORE_REPRODUCE_MIN_FOOD: usize = 0;
+
+// Минимальное количество растений и травоядных в области близости, при котором
+// всеядное может размножиться. 0 - без проверки.
+pub const OMNIVORE_REPRODUCE_MIN_FOOD: usize = 0;
+
+// Откуда потомок получает начальное направление: случайное, направление родителя
+// или направление, закодированное геномом.
+pub const ANIMAL_INHERIT_DIRECTION: DirectionInheritance = DirectionInheritance::Random;
+
+// Радиус зрения животных. Области восприятия не выходят за две ячейки от животного,
+// поэтому радиус больше двух не расширяет обзор, но обходится дороже.
+pub const ANIMAL_VISION_RADIUS: usize = 2;
+
+// Наибольший возраст животных (итерации): прожившее дольше животное умирает от
+// старости независимо от энергии. 0 - без ограничения.
+pub const ANIMAL_MAX_AGE: usize = 0;
+
+// Входы мозга, которые не воспринимает травоядное (значения обнуляются).
+pub const HERBIVORE_INPUT_MASK: InputMask = &[];
+
+// Входы мозга, которые не воспринимает хищник (значения обнуляются). Растения
+// хищнику не нужны.
+pub const CARNIVORE_INPUT_MASK: InputMask = &[
+    "plant_front",
+    "plant_left",
+    "plant_right",
+    "plant_proximity",
+];
+
+// Входы мозга, которые не воспринимает всеядное (значения обнуляются).
+pub const OMNIVORE_INPUT_MASK: InputMask = &[];
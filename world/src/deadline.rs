@@ -0,0 +1,246 @@
+//! Контроль продолжительности итераций мира: если итерации перестают укладываться
+//! в отведенное время, по очереди включаются более дешевые режимы мира, а когда
+//! появляется запас времени, они выключаются в обратном порядке. Контроль - чистый
+//! автомат над замерами продолжительности итераций и от часов не зависит.
+
+use std::collections::VecDeque;
+
+/// Более дешевый режим мира, который можно включать и выключать во время прогона.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum CheapMode {
+    /// Растения обрабатываются реже.
+    SparsePlants,
+    /// Статистика (генетическое разнообразие, строка статистики) собирается реже.
+    SparseStats,
+    /// Сверка счетчиков статистики с сеткой мира не выполняется.
+    NoValidation,
+}
+
+impl CheapMode {
+    /// Описание режима для сообщений.
+    pub fn describe(&self) -> &'static str {
+        match self {
+            CheapMode::SparsePlants => "редкая обработка растений",
+            CheapMode::SparseStats => "редкий сбор статистики",
+            CheapMode::NoValidation => "без сверки статистики",
+        }
+    }
+}
+
+/// Шаг деградации: какой режим включить или выключить.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum DegradationStep {
+    /// Итерации не укладываются в отведенное время: режим включается.
+    Enable(CheapMode),
+    /// Появился запас времени: режим выключается.
+    Disable(CheapMode),
+}
+
+/// Контроль продолжительности итераций. Решение принимается по 95-му процентилю
+/// продолжительности за окно последних итераций; после каждого шага окно
+/// заполняется заново, чтобы следующий шаг учитывал только замеры в новом режиме.
+pub struct DeadlineMonitor {
+    // Отведенное на итерацию время (миллисекунды).
+    budget_ms: f64,
+    // Доля отведенного времени, ниже которой режимы выключаются.
+    headroom: f64,
+    // Размер окна замеров.
+    window: usize,
+    // Замеры продолжительности итераций в окне (миллисекунды).
+    samples: VecDeque<f64>,
+    // Режимы в порядке включения.
+    order: Vec<CheapMode>,
+    // Количество включенных режимов (первые в порядке включения).
+    level: usize,
+}
+
+impl DeadlineMonitor {
+    /// Конструктор. Все режимы выключены.
+    ///
+    /// # Arguments
+    ///
+    /// * `budget_ms`: Отведенное на итерацию время в миллисекундах.
+    /// * `window`: Количество последних итераций, по которым принимается решение, не
+    ///   может быть меньше единицы.
+    /// * `headroom`: Доля отведенного времени (от 0 до 1): если 95-й процентиль
+    ///   ниже нее, последний включенный режим выключается.
+    /// * `order`: Режимы в порядке включения.
+    ///
+    /// returns: DeadlineMonitor
+    pub fn new(budget_ms: f64, window: usize, headroom: f64, order: &[CheapMode]) -> DeadlineMonitor {
+        let window = window.max(1);
+
+        DeadlineMonitor {
+            budget_ms,
+            headroom: headroom.clamp(0.0, 1.0),
+            window,
+            samples: VecDeque::with_capacity(window),
+            order: order.to_vec(),
+            level: 0,
+        }
+    }
+
+    /// Учитывает продолжительность итерации и решает, нужно ли изменить режим мира.
+    ///
+    /// # Arguments
+    ///
+    /// * `duration_ms`: Продолжительность итерации в миллисекундах.
+    ///
+    /// returns: Option<DegradationStep> - None, если режим менять не нужно.
+    pub fn record(&mut self, duration_ms: f64) -> Option<DegradationStep> {
+        if self.samples.len() == self.window {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(duration_ms);
+
+        if self.samples.len() < self.window {
+            return None;
+        }
+
+        let p95 = self.p95()?;
+        let step = if p95 > self.budget_ms && self.level < self.order.len() {
+            self.level += 1;
+            DegradationStep::Enable(self.order[self.level - 1])
+        } else if p95 < self.budget_ms * self.headroom && self.level > 0 {
+            self.level -= 1;
+            DegradationStep::Disable(self.order[self.level])
+        } else {
+            return None;
+        };
+
+        self.samples.clear();
+
+        Some(step)
+    }
+
+    /// 95-й процентиль продолжительности итераций в окне (миллисекунды). None -
+    /// замеров нет.
+    pub fn p95(&self) -> Option<f64> {
+        percentile(self.samples.iter().copied().collect(), 0.95)
+    }
+
+    /// Отведенное на итерацию время (миллисекунды).
+    pub fn budget_ms(&self) -> f64 {
+        self.budget_ms
+    }
+
+    /// Включенные режимы в порядке включения.
+    pub fn enabled(&self) -> &[CheapMode] {
+        &self.order[..self.level]
+    }
+
+    /// Забывает замеры и считает все режимы выключенными (например, после
+    /// перезапуска мира).
+    pub fn reset(&mut self) {
+        self.samples.clear();
+        self.level = 0;
+    }
+}
+
+/// Процентиль значений по ближайшему рангу.
+///
+/// # Arguments
+///
+/// * `values`: Значения.
+/// * `rank`: Процентиль, доля от 0 до 1.
+///
+/// returns: Option<f64> - None, если значений нет.
+pub(crate) fn percentile(mut values: Vec<f64>, rank: f64) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+
+    values.sort_by(|a, b| a.total_cmp(b));
+    let index = ((rank.clamp(0.0, 1.0) * values.len() as f64).ceil() as usize).clamp(1, values.len()) - 1;
+
+    Some(values[index])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORDER: [CheapMode; 3] = [CheapMode::SparsePlants, CheapMode::SparseStats, CheapMode::NoValidation];
+
+    /// Записывает замеры и возвращает принятые решения.
+    fn record_all(monitor: &mut DeadlineMonitor, samples: &[f64]) -> Vec<DegradationStep> {
+        samples.iter().filter_map(|sample| monitor.record(*sample)).collect()
+    }
+
+    #[test]
+    fn no_decision_until_window_is_full() {
+        let mut monitor = DeadlineMonitor::new(10.0, 4, 0.5, &ORDER);
+
+        assert_eq!(record_all(&mut monitor, &[50.0, 50.0, 50.0]), vec![]);
+        assert_eq!(monitor.record(50.0), Some(DegradationStep::Enable(CheapMode::SparsePlants)));
+    }
+
+    #[test]
+    fn modes_are_enabled_in_order_and_disabled_in_reverse() {
+        let mut monitor = DeadlineMonitor::new(10.0, 2, 0.5, &ORDER);
+
+        let slow = record_all(&mut monitor, &[20.0; 8]);
+        assert_eq!(slow, ORDER.map(DegradationStep::Enable).to_vec());
+        assert_eq!(monitor.enabled(), &ORDER);
+
+        let fast = record_all(&mut monitor, &[1.0; 8]);
+        assert_eq!(fast, vec![
+            DegradationStep::Disable(CheapMode::NoValidation),
+            DegradationStep::Disable(CheapMode::SparseStats),
+            DegradationStep::Disable(CheapMode::SparsePlants),
+        ]);
+        assert!(monitor.enabled().is_empty());
+    }
+
+    #[test]
+    fn within_budget_and_above_headroom_keeps_modes() {
+        let mut monitor = DeadlineMonitor::new(10.0, 2, 0.5, &ORDER);
+        record_all(&mut monitor, &[20.0, 20.0]);
+
+        assert_eq!(record_all(&mut monitor, &[7.0; 10]), vec![]);
+        assert_eq!(monitor.enabled(), &ORDER[..1]);
+    }
+
+    #[test]
+    fn decision_uses_95th_percentile() {
+        // Одна медленная итерация из двадцати не превышает 95-й процентиль.
+        let mut samples = vec![5.0; 19];
+        samples.push(100.0);
+        let mut monitor = DeadlineMonitor::new(10.0, 20, 0.1, &ORDER);
+        assert_eq!(record_all(&mut monitor, &samples), vec![]);
+        assert_eq!(monitor.p95(), Some(5.0));
+
+        // Две медленные итерации - уже превышают.
+        samples[0] = 100.0;
+        let mut monitor = DeadlineMonitor::new(10.0, 20, 0.1, &ORDER);
+        assert_eq!(record_all(&mut monitor, &samples), vec![DegradationStep::Enable(CheapMode::SparsePlants)]);
+    }
+
+    #[test]
+    fn fractional_milliseconds_are_measured() {
+        let mut monitor = DeadlineMonitor::new(0.5, 3, 0.5, &ORDER);
+
+        assert_eq!(record_all(&mut monitor, &[0.6, 0.7, 0.8]), vec![DegradationStep::Enable(CheapMode::SparsePlants)]);
+        assert_eq!(record_all(&mut monitor, &[0.1, 0.2, 0.2]), vec![DegradationStep::Disable(CheapMode::SparsePlants)]);
+    }
+
+    #[test]
+    fn reset_forgets_samples_and_modes() {
+        let mut monitor = DeadlineMonitor::new(10.0, 2, 0.5, &ORDER);
+        record_all(&mut monitor, &[20.0, 20.0, 20.0]);
+
+        monitor.reset();
+
+        assert!(monitor.enabled().is_empty());
+        assert_eq!(monitor.p95(), None);
+        assert_eq!(monitor.record(20.0), None);
+    }
+
+    #[test]
+    fn percentile_by_nearest_rank() {
+        assert_eq!(percentile(vec![], 0.95), None);
+        assert_eq!(percentile(vec![3.0, 1.0, 2.0], 0.5), Some(2.0));
+        assert_eq!(percentile(vec![3.0, 1.0, 2.0], 0.0), Some(1.0));
+        assert_eq!(percentile(vec![3.0, 1.0, 2.0], 1.0), Some(3.0));
+    }
+}
@@ -30,6 +30,7 @@ impl Error for RecoverableError {}
 pub enum AddAgentError {
     TakenCell((usize, usize)),
     OutOfBounds((usize, usize)),
+    BlockedCell((usize, usize)),
     //Overpopulated,
 }
 
@@ -42,6 +43,9 @@ impl fmt::Display for AddAgentError {
             AddAgentError::OutOfBounds(point) => write!(
                 f, "Точка с координатами ({}, {}) выходит за границы мира", point.0, point.1
             ),
+            AddAgentError::BlockedCell(point) => write!(
+                f, "Ячейка с координатами ({}, {}) непроходима", point.0, point.1
+            ),
             // AddAgentError::Overpopulated =>  write!(
             //     f, "Мир перенаселен, в нем закончилось место для новых животных"
             // ),
@@ -0,0 +1,212 @@
+//! События мира для анализа динамики популяций: рождения, смерти, поедание,
+//! прорастание растений и ручные изменения энергии животных. Мир отправляет события в канал, заданный методом
+//! `Landscape::set_event_sink`; без канала события не создаются. Здесь же описана
+//! запись о жизни умершего животного, которую мир хранит для итогов прогона.
+
+use crate::animal::AnimaType;
+use crate::landscape::Energy;
+
+/// Причина смерти животного.
+#[derive(Copy, Clone, PartialEq)]
+pub enum DeathCause {
+    /// Животное умерло от голода (энергия закончилась).
+    Starved,
+    /// Животное съедено.
+    Eaten,
+    /// Животное убито хищником (см. `AnimalAlive::be_killed`), его туша осталась
+    /// в ячейке.
+    Killed,
+    /// Животное прожило отпущенный ему срок (см. `AnimalAlive::is_old`).
+    OldAge,
+}
+
+impl DeathCause {
+    /// Имя причины смерти (для журнала событий).
+    pub fn name(&self) -> &'static str {
+        match self {
+            DeathCause::Starved => "starved",
+            DeathCause::Eaten => "eaten",
+            DeathCause::Killed => "killed",
+            DeathCause::OldAge => "old_age",
+        }
+    }
+}
+
+/// Чем питалось животное.
+#[derive(Copy, Clone, PartialEq)]
+pub enum PreyKind {
+    /// Растение.
+    Plant,
+    /// Животное заданного вида.
+    Animal(AnimaType),
+    /// Падаль.
+    Carrion,
+    /// Туша убитого животного.
+    Corpse,
+}
+
+impl PreyKind {
+    /// Имя еды (для журнала событий): вид съеденного животного или вид еды.
+    pub fn name(&self) -> &'static str {
+        match self {
+            PreyKind::Plant => "plant",
+            PreyKind::Animal(species) => species.name(),
+            PreyKind::Carrion => "carrion",
+            PreyKind::Corpse => "corpse",
+        }
+    }
+}
+
+/// Запись о жизни умершего животного (см. `Landscape::set_postmortem_history`).
+#[derive(Copy, Clone, PartialEq)]
+pub struct Postmortem {
+    /// Идентификатор животного.
+    pub id: u64,
+    /// Вид животного.
+    pub species: AnimaType,
+    /// Поколение животного.
+    pub generation: usize,
+    /// Возраст животного при смерти (итерации).
+    pub age: usize,
+    /// Причина смерти.
+    pub cause: DeathCause,
+    /// Номер итерации смерти (начиная с единицы, как в `WorldStats`).
+    pub tick: usize,
+    /// Координаты ячейки, в которой животное умерло.
+    pub at: (usize, usize),
+    /// Количество съеденных животных (для хищника - убитые жертвы).
+    pub kills: usize,
+    /// Сколько раз животное поело (растения, животные и падаль).
+    pub meals: usize,
+    /// Количество потомков.
+    pub offspring: usize,
+    /// Хэш генома мозга, позволяет найти умерших с одинаковым мозгом. None - мозг
+    /// не предоставляет геном.
+    pub genome_hash: Option<u64>,
+}
+
+/// Событие мира. Каждое событие помечено номером итерации (начиная с единицы, как
+/// в `WorldStats`) и координатами ячейки.
+#[derive(Copy, Clone, PartialEq)]
+pub enum WorldEvent {
+    /// Родилось животное (координаты новорожденного) с идентификатором `id` от
+    /// родителя с идентификатором `parent`.
+    Born { tick: usize, species: AnimaType, generation: usize, at: (usize, usize), id: u64, parent: u64 },
+    /// Умерло животное (координаты умершего).
+    Died { tick: usize, species: AnimaType, age: usize, cause: DeathCause, at: (usize, usize) },
+    /// Животное убило другое животное (координаты убитого).
+    Killed { tick: usize, predator: AnimaType, prey: AnimaType, at: (usize, usize) },
+    /// Животное поело (координаты съеденного).
+    Ate { tick: usize, predator: AnimaType, prey: PreyKind, at: (usize, usize) },
+    /// Проросло семя растения (координаты нового растения).
+    PlantSprouted { tick: usize, at: (usize, usize) },
+    /// Запись о жизни умершего животного, отправляется вслед за `Died`, если
+    /// включена `Landscape::set_postmortem_events`.
+    Postmortem(Postmortem),
+    /// Энергия животного с идентификатором `agent_id` изменена вручную на `delta`
+    /// (см. `Landscape::adjust_energy`), `energy` - новая энергия животного. По этому
+    /// событию прогоны с вмешательством исключаются из анализа.
+    EnergyAdjusted { tick: usize, agent_id: u64, delta: Energy, energy: Energy },
+}
+
+/// Заголовок CSV файла родословной (см. `WorldEvent::to_lineage_csv`).
+pub const LINEAGE_CSV_HEADER: &str = "tick,parent,child,species,generation\n";
+
+impl WorldEvent {
+    /// Представляет событие строкой JSON (вместе с переводом строки) для журнала
+    /// событий.
+    pub fn to_json(&self) -> String {
+        let line = match self {
+            WorldEvent::Born { tick, species, generation, at, id, parent } => format!(
+                "{{\"event\": \"born\", \"tick\": {}, \"species\": \"{}\", \"generation\": {}, \"x\": {}, \"y\": {}, \"id\": {}, \"parent\": {}}}",
+                tick, species.name(), generation, at.0, at.1, id, parent
+            ),
+            WorldEvent::Died { tick, species, age, cause, at } => format!(
+                "{{\"event\": \"died\", \"tick\": {}, \"species\": \"{}\", \"age\": {}, \"cause\": \"{}\", \"x\": {}, \"y\": {}}}",
+                tick, species.name(), age, cause.name(), at.0, at.1
+            ),
+            WorldEvent::Killed { tick, predator, prey, at } => format!(
+                "{{\"event\": \"killed\", \"tick\": {}, \"predator\": \"{}\", \"prey\": \"{}\", \"x\": {}, \"y\": {}}}",
+                tick, predator.name(), prey.name(), at.0, at.1
+            ),
+            WorldEvent::Ate { tick, predator, prey, at } => format!(
+                "{{\"event\": \"ate\", \"tick\": {}, \"predator\": \"{}\", \"prey\": \"{}\", \"x\": {}, \"y\": {}}}",
+                tick, predator.name(), prey.name(), at.0, at.1
+            ),
+            WorldEvent::PlantSprouted { tick, at } => format!(
+                "{{\"event\": \"sprouted\", \"tick\": {}, \"x\": {}, \"y\": {}}}",
+                tick, at.0, at.1
+            ),
+            WorldEvent::Postmortem(record) => format!(
+                "{{\"event\": \"postmortem\", \"tick\": {}, \"id\": {}, \"species\": \"{}\", \"generation\": {}, \"age\": {}, \"cause\": \"{}\", \"kills\": {}, \"meals\": {}, \"offspring\": {}}}",
+                record.tick,
+                record.id,
+                record.species.name(),
+                record.generation,
+                record.age,
+                record.cause.name(),
+                record.kills,
+                record.meals,
+                record.offspring
+            ),
+            WorldEvent::EnergyAdjusted { tick, agent_id, delta, energy } => format!(
+                "{{\"event\": \"energy_adjusted\", \"tick\": {}, \"id\": {}, \"delta\": {}, \"energy\": {}}}",
+                tick, agent_id, delta, energy
+            ),
+        };
+
+        line + "\n"
+    }
+
+    /// Представляет рождение строкой CSV файла родословной (вместе с переводом строки).
+    ///
+    /// returns: Option<String> - None, если событие не рождение.
+    pub fn to_lineage_csv(&self) -> Option<String> {
+        match self {
+            WorldEvent::Born { tick, species, generation, id, parent, .. } => {
+                Some(format!("{},{},{},{},{}\n", tick, parent, id, species.name(), generation))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn birth_is_logged_with_its_parent() {
+        let born = WorldEvent::Born {
+            tick: 3, species: AnimaType::Carnivore, generation: 2, at: (1, 4), id: 17, parent: 5
+        };
+
+        assert_eq!(
+            born.to_json(),
+            "{\"event\": \"born\", \"tick\": 3, \"species\": \"carnivore\", \"generation\": 2, \"x\": 1, \"y\": 4, \"id\": 17, \"parent\": 5}\n"
+        );
+        assert_eq!(born.to_lineage_csv(), Some("3,5,17,carnivore,2\n".to_string()));
+    }
+
+    #[test]
+    fn only_births_enter_lineage() {
+        let died = WorldEvent::Died { tick: 3, species: AnimaType::Herbivore, age: 9, cause: DeathCause::Eaten, at: (0, 0) };
+        let ate = WorldEvent::Ate { tick: 3, predator: AnimaType::Omnivore, prey: PreyKind::Animal(AnimaType::Herbivore), at: (0, 0) };
+
+        assert!(died.to_json().contains("\"cause\": \"eaten\""));
+        assert!(ate.to_json().contains("\"prey\": \"herbivore\""));
+        assert_eq!(died.to_lineage_csv(), None);
+        assert_eq!(ate.to_lineage_csv(), None);
+    }
+
+    #[test]
+    fn energy_adjustment_is_logged() {
+        let adjusted = WorldEvent::EnergyAdjusted { tick: 4, agent_id: 12, delta: -2.5, energy: 7.5 };
+
+        assert_eq!(
+            adjusted.to_json(),
+            "{\"event\": \"energy_adjusted\", \"tick\": 4, \"id\": 12, \"delta\": -2.5, \"energy\": 7.5}\n"
+        );
+        assert_eq!(adjusted.to_lineage_csv(), None);
+    }
+}
@@ -0,0 +1,137 @@
+//! Приспособленность животного: оценка, по которой мир выбирает лучших живых
+//! животных для статистики.
+
+use crate::animal::{AnimalAlive, AnimaType};
+
+/// Статистика жизни животного, которую мир собирает для оценки приспособленности.
+#[derive(Copy, Clone, Default)]
+pub struct PerAnimalStats {
+    // Количество потомков.
+    pub offspring: usize,
+    // Количество съеденных животных (для хищника - убитые жертвы).
+    pub kills: usize,
+    // Сколько раз животное поело (растения, животные и падаль).
+    pub meals: usize,
+    // Сумма энергии животного по прожитым итерациям.
+    pub energy_lifetime: f64,
+}
+
+/// Типаж, определяющий приспособленность животного. Чем больше оценка, тем
+/// приспособленнее животное.
+pub trait Fitness: Sync {
+    /// Имя оценки (записывается в итоги прогона).
+    fn name(&self) -> &'static str;
+
+    /// Оценивает приспособленность животного.
+    ///
+    /// # Arguments
+    ///
+    /// * `animal`: Животное.
+    /// * `stats`: Статистика жизни животного.
+    ///
+    /// returns: f64
+    fn score(&self, animal: &dyn AnimalAlive, stats: &PerAnimalStats) -> f64;
+}
+
+/// Возраст животного в итерациях.
+pub struct Age;
+
+impl Fitness for Age {
+    fn name(&self) -> &'static str {
+        "age"
+    }
+
+    fn score(&self, animal: &dyn AnimalAlive, _stats: &PerAnimalStats) -> f64 {
+        animal.get_age() as f64
+    }
+}
+
+/// Количество потомков.
+pub struct Offspring;
+
+impl Fitness for Offspring {
+    fn name(&self) -> &'static str {
+        "offspring"
+    }
+
+    fn score(&self, _animal: &dyn AnimalAlive, stats: &PerAnimalStats) -> f64 {
+        stats.offspring as f64
+    }
+}
+
+/// Энергия, накопленная за жизнь: сумма энергии животного по прожитым итерациям.
+/// Учитывает не только продолжительность жизни, но и то, насколько сытой она была.
+pub struct EnergyLifetime;
+
+impl Fitness for EnergyLifetime {
+    fn name(&self) -> &'static str {
+        "energy_lifetime"
+    }
+
+    fn score(&self, _animal: &dyn AnimalAlive, stats: &PerAnimalStats) -> f64 {
+        stats.energy_lifetime
+    }
+}
+
+/// Количество убитых жертв для хищника и всеядного; травоядные оцениваются по возрасту.
+pub struct Kills;
+
+impl Fitness for Kills {
+    fn name(&self) -> &'static str {
+        "kills"
+    }
+
+    fn score(&self, animal: &dyn AnimalAlive, stats: &PerAnimalStats) -> f64 {
+        match animal.get_type() {
+            AnimaType::Herbivore => animal.get_age() as f64,
+            AnimaType::Carnivore | AnimaType::Omnivore => stats.kills as f64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    /// Животное заданного типа и возраста.
+    fn aged(animal_type: AnimaType, age: usize) -> Box<dyn AnimalAlive> {
+        let mut animal = testing::animal(animal_type, &mut StdRng::seed_from_u64(testing::TEST_SEED));
+        for _ in 0..age {
+            animal.tick_passed();
+        }
+
+        animal
+    }
+
+    /// Индексы животных по убыванию оценки.
+    fn rank(fitness: &dyn Fitness, animals: &[(Box<dyn AnimalAlive>, PerAnimalStats)]) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..animals.len()).collect();
+        order.sort_by(|&a, &b| {
+            let score = |index: usize| fitness.score(animals[index].0.as_ref(), &animals[index].1);
+            score(b).total_cmp(&score(a))
+        });
+
+        order
+    }
+
+    #[test]
+    fn fitness_functions_rank_animals_differently() {
+        let animals = [
+            // Старое травоядное без потомков.
+            (aged(AnimaType::Herbivore, 10), PerAnimalStats { offspring: 0, kills: 0, meals: 4, energy_lifetime: 50.0 }),
+            // Молодой хищник, убивший многих.
+            (aged(AnimaType::Carnivore, 3), PerAnimalStats { offspring: 2, kills: 12, meals: 12, energy_lifetime: 20.0 }),
+            // Травоядное средних лет с большим потомством и сытой жизнью.
+            (aged(AnimaType::Herbivore, 6), PerAnimalStats { offspring: 5, kills: 0, meals: 9, energy_lifetime: 90.0 }),
+        ];
+
+        assert_eq!(rank(&Age, &animals), vec![0, 2, 1]);
+        assert_eq!(rank(&Offspring, &animals), vec![2, 1, 0]);
+        assert_eq!(rank(&EnergyLifetime, &animals), vec![2, 0, 1]);
+        // Травоядные оцениваются по возрасту, хищник - по убитым жертвам.
+        assert_eq!(rank(&Kills, &animals), vec![1, 0, 2]);
+    }
+}
@@ -1,26 +1,460 @@
 //! Среда.
 
 use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::collections::hash_map::DefaultHasher;
 use std::fmt;
-use rand::{Rng, thread_rng};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::mem;
+use std::path::Path;
+use std::sync::mpsc::Sender;
+use rand::{Rng, RngCore, SeedableRng};
+use rand::distributions::{Distribution, WeightedIndex};
 use rand::seq::SliceRandom;
 
+use crate::arena::{Arena, Handle};
 use crate::errors::{RecoverableError, AddAgentError};
-use crate::animal::{AnimalAction, AnimalAlive, AnimalDirection, AnimalInputSignal, AnimaType};
-use crate::plant::{PlantAction, PlantAlive};
-
-use display::{CellStuff, Map};
+use crate::events::{DeathCause, Postmortem, PreyKind, WorldEvent};
+use crate::fitness::{Age, Fitness, PerAnimalStats};
+use crate::memory::MemoryTracker;
+use crate::mutation::{MutationAdjustment, MutationController};
+use crate::policy::{ActionPolicy, PolicyCtx, MAX_ACTION_POLICIES};
+use crate::animal::brains::ActionTrace;
+#[cfg(feature = "enum-dispatch")]
+use crate::animal::species::any::AnyAnimal;
+use crate::animal::species::any::AnimalStorage;
+use crate::animal::{AnimalAction, AnimalAlive, AnimalDirection, AnimalInputSignal, AnimalState, AnimaType};
+use crate::plant::{PlantAction, PlantAlive, PlantState};
+use crate::rng::{CheckpointRng, RngState};
+use crate::stats::{mean_pairwise_distance, RingBuffer, RollingMean};
+
+use display::{CellEvent, CellMarks, CellStuff, EventKind, FrameRequest, LayerSet, Map, PerceptionFootprint, Topology};
 
 
 /// Тип представляющий энергию живого существа
 pub type Energy = f32;
 
+/// Полный порядок на значениях энергии, одинаковый на всех платформах: в отличие
+/// от `partial_cmp`, сравнение определено и для NaN (NaN больше любого числа).
+/// Везде, где от сравнения энергий зависит поведение мира, следует использовать
+/// эту функцию.
+///
+/// # Arguments
+///
+/// * `a`, `b`: Сравниваемые значения энергии.
+///
+/// returns: Ordering
+pub fn cmp_energy(a: Energy, b: Energy) -> Ordering {
+    a.total_cmp(&b)
+}
+
 /// Тип агента.
 #[derive(Copy, Clone)]
 pub enum AgentType {
     Plant,
     Herbivore,
     Carnivore,
+    Omnivore,
+}
+
+impl From<AnimaType> for AgentType {
+    fn from(animal_type: AnimaType) -> Self {
+        match animal_type {
+            AnimaType::Herbivore => AgentType::Herbivore,
+            AnimaType::Carnivore => AgentType::Carnivore,
+            AnimaType::Omnivore => AgentType::Omnivore,
+        }
+    }
+}
+
+/// Способ выбора цели для поедания, если в области близости животного
+/// несколько подходящих агентов.
+#[derive(Copy, Clone, PartialEq)]
+pub enum EatTargeting {
+    Random,        // Случайный агент (равновероятно).
+    RichestFirst,  // Агент с максимальной энергией (растение с наибольшим запасом).
+    WeakestFirst,  // Агент с минимальной энергией (самая слабая жертва).
+}
+
+/// Где появляется потомок размножающегося агента.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ReproductionPlacement {
+    // Сначала в свободной ячейке не дальше `radius` ячеек от родителя (по каждой оси);
+    // если все они заняты - в любой свободной ячейке мира.
+    Adjacent { radius: usize },
+    // В любой свободной ячейке мира.
+    Anywhere,
+}
+
+/// Ось координат мира.
+#[derive(Copy, Clone, PartialEq)]
+pub enum Axis {
+    X,
+    Y,
+}
+
+/// Половина мира вдоль оси: первая - меньшие координаты (запад или север),
+/// вторая - большие (восток или юг).
+#[derive(Copy, Clone, PartialEq)]
+pub enum Half {
+    First,
+    Second,
+}
+
+/// Стратегия начального размещения агентов в мире.
+#[derive(Copy, Clone, PartialEq)]
+pub enum PlacementStrategy {
+    // Равномерно по всему миру.
+    Uniform,
+    // В одной половине мира вдоль заданной оси.
+    HalfSplit { axis: Axis, half: Half },
+    // В `k` случайных квадратных участках радиуса `radius` (с учетом тороидальности).
+    Patches { k: usize, radius: usize },
+}
+
+/// Начальная энергия агентов при заселении мира, относительно энергии, с которой
+/// агент создан (для растений обычно максимальная энергия).
+#[derive(Copy, Clone, PartialEq)]
+pub enum InitialEnergy {
+    // Энергия, с которой агент создан.
+    Full,
+    // Случайная энергия, равномерно распределенная в [доля * полная, полная].
+    Uniform(f64),
+    // Заданная доля энергии, с которой агент создан.
+    Fraction(f64),
+}
+
+impl InitialEnergy {
+    /// Вычисляет начальную энергию агента.
+    ///
+    /// # Arguments
+    ///
+    /// * `full`: Энергия, с которой агент создан.
+    /// * `rng`: Генератор случайных чисел мира.
+    ///
+    /// returns: Energy
+    fn draw(&self, full: Energy, rng: &mut CheckpointRng) -> Energy {
+        match *self {
+            InitialEnergy::Full => full,
+            InitialEnergy::Uniform(low) => {
+                let low = low.clamp(0.0, 1.0);
+                (rng.gen_range(low..=1.0) * full as f64) as Energy
+            }
+            InitialEnergy::Fraction(fraction) => (fraction * full as f64) as Energy,
+        }
+    }
+}
+
+/// Заселение вида животных: количество животных и стратегия их размещения.
+type Introduction = (usize, PlacementStrategy);
+
+/// Количество итераций, в течение которых ячейка ([x][y]) была занята.
+type OccupancyGrid = Vec<Vec<u32>>;
+
+/// Животное в хранилище мира. С возможностью `enum-dispatch` - перечисление видов
+/// (см. `AnyAnimal`), иначе - объект трейта.
+#[cfg(feature = "enum-dispatch")]
+type StoredAnimal = AnyAnimal;
+#[cfg(not(feature = "enum-dispatch"))]
+type StoredAnimal = Box<dyn AnimalAlive>;
+
+/// Источник мозга животного сценария.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum BrainSource<'a> {
+    // Случайный мозг.
+    Random,
+    // Мозг из файла генома.
+    Genome(&'a str),
+    // Клон случайного лучшего животного своего вида из файла лучших животных (см.
+    // `Landscape::export_best_brains` и `config::init::read_champions`).
+    Champions(&'a str),
+}
+
+/// Создает животное сценария (см. `Landscape::load_scenario`) по виду, направлению и
+/// источнику мозга из генератора случайных чисел мира. Ошибка - файл мозга не
+/// читается или не подходит мозгу животного.
+pub type ScenarioAnimal<'a> = dyn FnMut(
+    AnimaType, AnimalDirection, BrainSource, &mut dyn RngCore
+) -> Result<Box<dyn AnimalAlive>, RecoverableError> + 'a;
+
+/// Направление животных случайного блока сценария.
+enum ScenarioFacing {
+    // Все животные смотрят в одну сторону.
+    Fixed(AnimalDirection),
+    // Направления равновероятны.
+    Uniform,
+    // Направления с весами: север, юг, запад, восток.
+    Weighted(WeightedIndex<u32>),
+}
+
+/// Снимок статистики мира после итерации.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct WorldStats {
+    // Номер итерации (начиная с единицы).
+    pub iteration: usize,
+    // Количество растений.
+    pub plant_count: usize,
+    // Количество живых животных: первый элемент - травоядные, второй хищники, третий всеядные.
+    pub animal_count: (usize, usize, usize),
+    // Количество размножений, отложенных на итерации из-за лимита рождений.
+    pub deferred_births: (usize, usize, usize),
+    // Количество размножений, не состоявшихся на итерации из-за тесноты.
+    pub blocked_births: (usize, usize, usize),
+    // Сколько раз на итерации каждое правило цепочки (см. `Landscape::add_action_policy`)
+    // запретило или заменило действие животного, в порядке правил.
+    pub policy_vetoes: [usize; MAX_ACTION_POLICIES],
+    // Сколько раз на итерации хищник пропустил добычу, защищенную по возрасту
+    // (см. `Landscape::set_protection_age`), по виду добычи.
+    pub protected_skips: (usize, usize, usize),
+    // Количество вынашиваемых потомков (рождение отложено, см. `Landscape::set_gestation`).
+    pub pending_births: usize,
+    // Количество вынашиваемых потомков, которые не родились на итерации: родитель
+    // умер или рядом с ним не нашлось места.
+    pub failed_births: usize,
+    // Энергия, полученная животными от поедания падали на итерации.
+    pub carrion_energy: (Energy, Energy, Energy),
+    // Генетическое разнообразие (среднее попарное расстояние между геномами выборки
+    // животных) по последнему замеру: первый элемент - травоядные, второй хищники, третий всеядные.
+    // None - замеров не было или в выборке меньше двух животных.
+    pub genetic_diversity: (Option<f64>, Option<f64>, Option<f64>),
+    // Количество семян растений: первый элемент - попытки рассыпать, второй - проросшие.
+    pub seeds: (usize, usize),
+    // Количество растений, умерших на итерации от старости (см. `Landscape::set_plant_max_age`).
+    pub plant_deaths: usize,
+    // Общее количество ручных изменений энергии животных с начала прогона.
+    pub energy_adjustments: usize,
+    // Имя оценки приспособленности, по которой выбираются лучшие животные.
+    pub fitness: &'static str,
+    // Максимальное поколение животных с начала прогона: первый элемент - травоядные,
+    // второй хищники, третий всеядные.
+    pub max_generation: (usize, usize, usize),
+    // Эффективность поедания: первый элемент - травоядные, второй хищники, третий всеядные.
+    pub hunt: (HuntStats, HuntStats, HuntStats),
+    // Энергетический баланс видов на итерации: первый элемент - травоядные, второй
+    // хищники, третий всеядные.
+    pub energy_budget: (EnergyBudget, EnergyBudget, EnergyBudget),
+}
+
+/// Сводная статистика мира с начала прогона.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct LandscapeStats {
+    // Номер итерации (начиная с единицы).
+    pub tick: usize,
+    // Количество растений.
+    pub plant_count: usize,
+    // Количество живых животных: первый элемент - травоядные, второй хищники, третий всеядные.
+    pub animal_count: (usize, usize, usize),
+    // Количество рождений животных с начала прогона.
+    pub births: (usize, usize, usize),
+    // Количество смертей животных с начала прогона.
+    pub deaths: (usize, usize, usize),
+    // Сколько из них умерли от старости.
+    pub old_age_deaths: (usize, usize, usize),
+    // Максимальное поколение животных с начала прогона.
+    pub max_generation: (usize, usize, usize),
+    // Возраст самого старого живого животного. None - живых животных вида нет.
+    pub oldest_alive_age: (Option<usize>, Option<usize>, Option<usize>),
+    // Наибольший возраст животного с начала прогона (среди живых и умерших).
+    pub oldest_ever_age: (usize, usize, usize),
+    // Энергетический баланс видов за последние итерации (см. `Landscape::set_energy_budget_window`).
+    pub energy_budget: (EnergyBudget, EnergyBudget, EnergyBudget),
+}
+
+/// Состояние мира в виде редактируемого сценария: текст сценария и геномы мозгов
+/// животных, на которые сценарий ссылается по имени файла.
+pub struct Scenario {
+    // Текст сценария.
+    pub text: String,
+    // Имена файлов геномов и сами геномы в JSON.
+    pub genomes: Vec<(String, String)>,
+}
+
+/// Вынашиваемый потомок: родится рядом с родителем на заданной итерации, если
+/// родитель будет жив.
+struct PendingBirth {
+    // Идентификатор родителя.
+    parent_id: u64,
+    // Дескриптор родителя.
+    parent: Handle,
+    // Потомок (геном и энергия определены в момент размножения).
+    child: Box<dyn AnimalAlive>,
+    // Итерация, на которой потомок появляется в мире.
+    hatch_at: usize,
+}
+
+/// Снимок состояния животного для статистики. Статистика хранит копию, а не
+/// дескриптор, поэтому снимок остается верным после смерти животного и
+/// переиспользования его памяти.
+#[derive(Clone)]
+pub struct AnimalSnapshot {
+    // Идентификатор животного.
+    pub id: u64,
+    // Возраст животного в итерациях на момент снимка.
+    pub age: usize,
+    // Поколение животного.
+    pub generation: usize,
+    // Приспособленность животного на момент снимка.
+    pub fitness: f64,
+    // Геном мозга животного (если мозг поддерживает представление генома).
+    pub genome: Option<Vec<f64>>,
+    // Геном мозга в JSON (если мозг поддерживает сохранение генома).
+    pub genome_json: Option<String>,
+}
+
+impl AnimalSnapshot {
+    /// Создает снимок животного.
+    ///
+    /// # Arguments
+    ///
+    /// * `animal`: Животное.
+    /// * `fitness`: Приспособленность животного.
+    ///
+    /// returns: AnimalSnapshot
+    fn new(animal: &dyn AnimalAlive, fitness: f64) -> AnimalSnapshot {
+        AnimalSnapshot {
+            id: animal.get_id(),
+            age: animal.get_age(),
+            generation: animal.get_generation(),
+            fitness,
+            genome: animal.get_genome(),
+            genome_json: animal.get_genome_json(),
+        }
+    }
+}
+
+/// Растение в ячейке (см. `Landscape::get_cell_info` и `Landscape::plants_iter`).
+#[derive(Copy, Clone)]
+pub struct PlantInfo {
+    // Энергия растения.
+    pub energy: Energy,
+    // Возраст растения в итерациях.
+    pub age: usize,
+}
+
+/// Животное в ячейке (см. `Landscape::get_cell_info` и `Landscape::animals_iter`).
+#[derive(Copy, Clone)]
+pub struct AnimalInfo {
+    // Идентификатор животного.
+    pub id: u64,
+    // Вид животного.
+    pub species: AnimaType,
+    // Направление движения животного.
+    pub direction: AnimalDirection,
+    // Энергия животного.
+    pub energy: Energy,
+    // Возраст животного в итерациях.
+    pub age: usize,
+    // Поколение животного.
+    pub generation: usize,
+}
+
+impl PlantInfo {
+    /// Создает снимок растения.
+    ///
+    /// # Arguments
+    ///
+    /// * `plant`: Растение.
+    ///
+    /// returns: PlantInfo
+    fn new(plant: &dyn PlantAlive) -> PlantInfo {
+        PlantInfo {
+            energy: plant.get_energy(),
+            age: plant.get_age(),
+        }
+    }
+}
+
+impl AnimalInfo {
+    /// Создает снимок животного.
+    ///
+    /// # Arguments
+    ///
+    /// * `animal`: Животное.
+    ///
+    /// returns: AnimalInfo
+    fn new(animal: &dyn AnimalAlive) -> AnimalInfo {
+        AnimalInfo {
+            id: animal.get_id(),
+            species: animal.get_type(),
+            direction: animal.get_direction(),
+            energy: animal.get_energy(),
+            age: animal.get_age(),
+            generation: animal.get_generation(),
+        }
+    }
+}
+
+/// Содержимое ячейки мира для отладки и осмотра ячейки в отображении.
+#[derive(Copy, Clone)]
+pub struct CellInfo {
+    // Местность.
+    pub terrain: Terrain,
+    // Растение. None - растения нет.
+    pub plant: Option<PlantInfo>,
+    // Животное. None - животного нет.
+    pub animal: Option<AnimalInfo>,
+    // Энергия падали.
+    pub carrion: Energy,
+    // Энергия туши убитого, но не съеденного животного.
+    pub corpse: Energy,
+    // Плодородие почвы.
+    pub fertility: Energy,
+}
+
+/// Энергетический баланс одного вида животных: куда уходит энергия, полученная
+/// от поедания. Учитываются только ходы животных и вынашивание потомков; ручные
+/// изменения энергии и энергия убитых животных в баланс не входят.
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+pub struct EnergyBudget {
+    // Энергия, полученная от поедания.
+    pub gained: Energy,
+    // Энергия, потраченная на гомеостаз, восприятие, действия и вынашивание, включая
+    // энергию еды, которая не поместилась в животное.
+    pub spent: Energy,
+    // Энергия, переданная потомкам при размножении.
+    pub offspring: Energy,
+}
+
+impl EnergyBudget {
+    /// Изменение суммарной энергии животных вида без учета рождений и смертей:
+    /// полученная энергия за вычетом потраченной и переданной потомкам.
+    pub fn net(&self) -> Energy {
+        self.gained - self.spent - self.offspring
+    }
+
+    /// Добавляет к балансу баланс другого периода.
+    ///
+    /// # Arguments
+    ///
+    /// * `other`: Баланс другого периода.
+    ///
+    /// returns: ()
+    pub fn add(&mut self, other: &EnergyBudget) {
+        self.gained += other.gained;
+        self.spent += other.spent;
+        self.offspring += other.offspring;
+    }
+}
+
+/// Эффективность поедания для одного вида животных.
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+pub struct HuntStats {
+    // Количество попыток поесть на итерации.
+    pub attempts: usize,
+    // Количество удачных попыток на итерации (для хищника - убитые жертвы, при
+    // двухшаговой охоте - съеденные туши).
+    pub successes: usize,
+    // Энергия, полученная от поедания на итерации.
+    pub energy: Energy,
+    // Скользящее среднее доли удачных попыток (по итерациям, в которых были попытки).
+    pub success_rate: Option<f64>,
+    // Скользящее среднее энергии, полученной за одну попытку.
+    pub energy_per_attempt: Option<f64>,
+    // Количество нападений на итерации (см. `Landscape::set_two_step_predation`).
+    pub attack_attempts: usize,
+    // Количество жертв, убитых нападениями на итерации.
+    pub kills: usize,
 }
 
 // Константы смещений, в зависимости от "взгляда" животного. Каждая константа хранят
@@ -54,23 +488,76 @@ const NORTH_LEFT: [(i8, i8); 2] = [(-2, 0), (-2, -1)];
 const NORTH_RIGHT: [(i8, i8); 2] = [(2, 0), (2, -1)];
 const NORTH_PROXIMITY: [(i8, i8); 5] = [(-1, 0), (-1, -1), (0, -1), (1, -1), (1, 0)];
 
+// Остальные направления получаются поворотом таблиц для севера, поэтому таблицы
+// не могут разойтись друг с другом.
+
 // Grid offsets for Front/Left/Right/Proximity (South facing).
-const SOUTH_FRONT: [(i8, i8); 5] = [(2, 2), (1, 2), (0, 2), (-1, 2), (-2, 2)];
-const SOUTH_LEFT: [(i8, i8); 2] = [(2, 0), (2, 1)];
-const SOUTH_RIGHT: [(i8, i8); 2] = [(-2, 0), (-2, 1)];
-const SOUTH_PROXIMITY: [(i8, i8); 5] = [(1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0)];
+const SOUTH_FRONT: [(i8, i8); 5] = rotate(NORTH_FRONT, AnimalDirection::South);
+const SOUTH_LEFT: [(i8, i8); 2] = rotate(NORTH_LEFT, AnimalDirection::South);
+const SOUTH_RIGHT: [(i8, i8); 2] = rotate(NORTH_RIGHT, AnimalDirection::South);
+const SOUTH_PROXIMITY: [(i8, i8); 5] = rotate(NORTH_PROXIMITY, AnimalDirection::South);
 
 // Grid offsets for Front/Left/Right/Proximity (West facing).
-const WEST_FRONT: [(i8, i8); 5] = [(-2, 2), (-2, 1), (-2, 0), (-2, -1), (-2, -2)];
-const WEST_LEFT: [(i8, i8); 2] = [(0, 2), (-1, 2)];
-const WEST_RIGHT: [(i8, i8); 2] = [(0, -2), (-1, -2)];
-const WEST_PROXIMITY: [(i8, i8); 5] = [(0, 1), (-1, 1), (-1, 0), (-1, 1), (0, 1)];
+const WEST_FRONT: [(i8, i8); 5] = rotate(NORTH_FRONT, AnimalDirection::West);
+const WEST_LEFT: [(i8, i8); 2] = rotate(NORTH_LEFT, AnimalDirection::West);
+const WEST_RIGHT: [(i8, i8); 2] = rotate(NORTH_RIGHT, AnimalDirection::West);
+const WEST_PROXIMITY: [(i8, i8); 5] = rotate(NORTH_PROXIMITY, AnimalDirection::West);
 
 // Grid offsets for Front/Left/Right/Proximity (East facing).
-const EAST_FRONT: [(i8, i8); 5] = [(-2, 2), (-2, 1), (-2, 0), (-2, -1), (-2, -2)];
-const EAST_LEFT: [(i8, i8); 2] = [(0, 2), (-1, 2)];
-const EAST_RIGHT: [(i8, i8); 2] = [(0, -2), (-1, -2)];
-const EAST_PROXIMITY: [(i8, i8); 5] = [(0, 1), (-1, 1), (-1, 0), (-1, 1), (0, 1)];
+const EAST_FRONT: [(i8, i8); 5] = rotate(NORTH_FRONT, AnimalDirection::East);
+const EAST_LEFT: [(i8, i8); 2] = rotate(NORTH_LEFT, AnimalDirection::East);
+const EAST_RIGHT: [(i8, i8); 2] = rotate(NORTH_RIGHT, AnimalDirection::East);
+const EAST_PROXIMITY: [(i8, i8); 5] = rotate(NORTH_PROXIMITY, AnimalDirection::East);
+
+// Кого едят хищники и всеядные. Хищника не ест никто.
+const CARNIVORE_PREY: [AnimaType; 2] = [AnimaType::Herbivore, AnimaType::Omnivore];
+const OMNIVORE_PREY: [AnimaType; 1] = [AnimaType::Herbivore];
+
+/// Поворачивает смещения, заданные для животного, смотрящего на север, так, как
+/// их видит животное, смотрящее в направлении `direction`. Ось "y" направлена на юг.
+///
+/// # Arguments
+///
+/// * `offsets`: Смещения для направления на север.
+/// * `direction`: Направление животного.
+///
+/// returns: [(i8, i8); N]
+const fn rotate<const N: usize>(offsets: [(i8, i8); N], direction: AnimalDirection) -> [(i8, i8); N] {
+    let mut rotated = offsets;
+
+    let mut i = 0;
+    while i < N {
+        let (dx, dy) = offsets[i];
+        rotated[i] = match direction {
+            AnimalDirection::North => (dx, dy),
+            AnimalDirection::South => (-dx, -dy),
+            AnimalDirection::West => (dy, -dx),
+            AnimalDirection::East => (-dy, dx),
+        };
+        i += 1;
+    }
+
+    rotated
+}
+
+// Смещение ячейки непосредственно перед животным (куда оно шагнет при движении).
+const NORTH_AHEAD: [(i8, i8); 1] = [(0, -1)];
+const SOUTH_AHEAD: [(i8, i8); 1] = [(0, 1)];
+const WEST_AHEAD: [(i8, i8); 1] = [(-1, 0)];
+const EAST_AHEAD: [(i8, i8); 1] = [(1, 0)];
+
+// Символы текстового представления мира (см. Landscape::to_ascii). Для животных
+// регистр определяет вид (строчные - травоядные, прописные - хищники), а буква -
+// направление взгляда в порядке: север, юг, запад, восток. Всеядные обозначаются
+// стрелками в том же порядке.
+const ASCII_EMPTY: char = '.';
+const ASCII_OBSTACLE: char = '#';
+const ASCII_PLANT: char = '*';
+const ASCII_EATEN_PLANT: char = ',';
+const ASCII_DEAD_ANIMAL: char = 'x';
+const ASCII_HERBIVORE: [char; 4] = ['n', 's', 'w', 'e'];
+const ASCII_CARNIVORE: [char; 4] = ['N', 'S', 'W', 'E'];
+const ASCII_OMNIVORE: [char; 4] = ['^', 'v', '<', '>'];
 
 /// Создает матрицу среды ячейками которой являются значения C типа.
 ///
@@ -100,31 +587,39 @@ fn create_landscape_matrix<C>(width: usize, height: usize) -> Vec<Vec<C>>
     width_container
 }
 
-/// Сортирует вектор координат (кортеж (i8, i8)) случайным образом.
+/// Наибольшее количество смещений в области, которую можно перемешать.
+const MAX_SHUFFLED_AREA: usize = 8;
+
+/// Перемешивает смещения области случайным образом (тасование Фишера - Йетса, все
+/// перестановки равновероятны). Смещения копируются в массив на стеке, поэтому
+/// память не выделяется.
 ///
 /// # Arguments
 ///
-/// * `array`: Вектор кортежей координат точек.
+/// * `area`: Смещения области, не больше MAX_SHUFFLED_AREA.
+/// * `rng`: Генератор случайных чисел мира.
 ///
-/// returns: Vec<(i8, i8)>
-fn randomize_coord_vector(mut array: Vec<(i8, i8)>) -> Vec<(i8, i8)> {
-    array.sort_unstable_by(|_, _| {
-        let num = thread_rng().gen_range(0..2);
-        if num == 1 {
-            Ordering::Greater
-        } else {
-            Ordering::Less
-        }
-    });
-
-    array
+/// returns: ([(i8, i8); MAX_SHUFFLED_AREA], usize) - массив, в начале которого
+/// перемешанные смещения, и их количество.
+fn shuffle_area(area: &[(i8, i8)], rng: &mut CheckpointRng) -> ([(i8, i8); MAX_SHUFFLED_AREA], usize) {
+    assert!(
+        area.len() <= MAX_SHUFFLED_AREA,
+        "Внутренняя ошибка программы: область из {} смещений больше {}", area.len(), MAX_SHUFFLED_AREA
+    );
+
+    let mut shuffled = [(0, 0); MAX_SHUFFLED_AREA];
+    shuffled[..area.len()].copy_from_slice(area);
+    shuffled[..area.len()].shuffle(rng);
+
+    (shuffled, area.len())
 }
 
-/// Растение в ячейке. Растения не умираю, а теряют энергию до нуля.
-/// На последующих итерациях растение может вырасти снова.
+/// Растение в ячейке. Съеденное растение теряет энергию до нуля и на последующих
+/// итерациях может вырасти снова. Растение, достигшее наибольшего возраста (см.
+/// `Landscape::set_plant_max_age`), умирает, и ячейка освобождается.
 #[derive(Copy, Clone)]
 enum PlantInCell {
-    Plant(*mut dyn PlantAlive),
+    Plant(Handle),
     None,
 }
 impl Default for PlantInCell {
@@ -135,12 +630,11 @@ impl Default for PlantInCell {
 
 /// Животное в ячейке. Животное может погибнуть и может передвигаться.
 /// Каждое такое действие сопровождается освобождением занимаемой ячейки.
-/// В случае смерти животного можно было бы освобождать ячейку, но для
-/// статистики и пока это не вызывает проблем с производительностью будем
-/// переносить указатель на умершее животное в специальный массив.
+/// В случае смерти животного ячейка освобождается, а память животного
+/// переносится в пул для новорожденных.
 #[derive(Copy, Clone)]
 enum AnimalInCell {
-    Animal(*mut dyn AnimalAlive),
+    Animal(Handle),
     None,
 }
 impl Default for AnimalInCell {
@@ -149,42 +643,77 @@ impl Default for AnimalInCell {
     }
 }
 
-/// Ячейка среды. В ячейке хранятся указатели на агенты.
+/// Местность в ячейке.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub enum Terrain {
+    /// Открытая ячейка.
+    #[default]
+    Open,
+    /// Непроходимая ячейка (скала, вода): в ней не могут находиться ни растения, ни
+    /// животные.
+    Blocked,
+}
+
+/// Ячейка среды. В ячейке хранятся дескрипторы агентов.
 #[derive(Default)]
 struct Cell {
     // Текущее растение в точке.
     plant: PlantInCell,
     // Текущее животное в точке.
     animal: AnimalInCell,
+    // Энергия падали (останков умерших от голода животных) в точке.
+    carrion: Energy,
+    // Итерация, на которой в точке последний раз появилась падаль.
+    carrion_since: usize,
+    // Энергия туши убитого, но еще не съеденного животного в точке (двухшаговая охота).
+    corpse: Energy,
+    // Итерация, на которой в точке последний раз появилась туша.
+    corpse_since: usize,
+    // Плодородие почвы: дополнительная энергия роста растения в точке. Появляется при
+    // разложении падали и со временем убывает.
+    fertility: Energy,
+    // Местность.
+    terrain: Terrain,
 }
 
 /// Структурой, объединяющей все вместе является среда - двухмерная структура, на
 /// пересечении координат которой находится ячейка. Среда имеет два массива: растения
-/// и животные. Напрямую с этим массивом мы не работаем, они лишь контейнеры. Перед
-/// переносом в эти контейнеры мы получаем изменяемый *указатель* на сущность и
-/// храним их в ячейке в каждой точке.
+/// и животные. Напрямую с этими хранилищами мы не работаем, они лишь контейнеры. При
+/// размещении агента в хранилище мы получаем его *дескриптор* и храним его в ячейке
+/// в каждой точке.
 pub struct Landscape {
     // Агенты.
 
-    // Массив животных.
-    animals: Vec<Box<dyn AnimalAlive>>,
-    // Массив растений.
-    plants: Vec<Box<dyn PlantAlive>>,
-    // Умершие животные. Растение погибнуть не может - оно может вырасти заново.
-    // TODO: Возможно стоит рассмотреть варианты с погибшими растениями, восстановление
-    // TODO: популяции которых происходит только при размножении.
-    dead_animals: Vec<*mut dyn AnimalAlive>,
+    // Хранилище животных.
+    animals: Arena<StoredAnimal>,
+    // Хранилище растений.
+    plants: Arena<Box<dyn PlantAlive>>,
+    // Память умерших животных, удаленных из хранилища, переиспользуется для
+    // новорожденных (пул животных). История умерших - записи `Postmortem` (см.
+    // `set_postmortem_history`).
+    spare_animals: Vec<StoredAnimal>,
 
     // Среда. Точки среды - ячейки.
     landscape: Vec<Vec<Cell>>,
 
     // Вспомогательный массив, содержит элементы позволяющие отобразить текущую ячейку.
     view_state: Map,
+    // Запрос отображения: какие слои карты собирать в view_state и как часто.
+    frame_request: FrameRequest,
+    // Карта в view_state собрана на последней итерации.
+    view_ready: bool,
+    // События в ячейках (убийства, рождения) с момента последней собранной карты.
+    transient_events: Vec<CellEvent>,
 
     // Вспомогательные массивы для случайного размещения агентов в мире.
     shuffle_width: Vec<usize>,
     shuffle_height: Vec<usize>,
 
+    // Генератор случайных чисел мира. С заданным зерном прогон воспроизводим.
+    rng: CheckpointRng,
+    // Зерно генератора случайных чисел мира.
+    seed: u64,
+
     // Настройки мира.
 
     // Ширина мира.
@@ -197,29 +726,217 @@ pub struct Landscape {
     max_herbivore: usize,
     // Максимальное количество хищных животных.
     max_carnivore: usize,
-    // Энергия, которую получает растение на каждой итерации.
-    // В дальнейшим можно создавать карту энергии.
-    plant_grow_energy: Energy,
+    // Максимальное количество всеядных. По умолчанию 0 - всеядных в мире нет.
+    max_omnivore: usize,
+    // Карта энергии роста (климатические зоны): энергия, которую получает растение
+    // в ячейке [x][y] на каждой итерации.
+    growth_map: Vec<Vec<Energy>>,
+    // Ширина полосы вдоль краев мира, в которой агенты не размещаются (ни при заселении,
+    // ни при размножении). 0 - полосы нет.
+    spawn_margin: usize,
+    // Растения обрабатываются раз в столько итераций (энергия роста умножается на
+    // это число). 1 - на каждой итерации.
+    plant_update_interval: usize,
+    // Способ выбора цели для поедания: первый элемент - травоядное, второй хищное,
+    // третий всеядное.
+    eat_targeting: (EatTargeting, EatTargeting, EatTargeting),
+    // Где появляется потомок: первый элемент - у растений, второй - у животных.
+    reproduction_placement: (ReproductionPlacement, ReproductionPlacement),
+    // Сколько итераций животное вынашивает потомка. 0 - потомок появляется сразу.
+    gestation_ticks: usize,
+    // Энергия, которую родитель дополнительно теряет на каждой итерации вынашивания.
+    gestation_drain: Energy,
+    // Сколько потомков одно животное может вынашивать одновременно. 0 - без ограничения.
+    max_pending_births: usize,
+    // Вынашиваемые потомки.
+    pending_births: Vec<PendingBirth>,
+    // Ячейки родителей, вынашивающих потомков, по идентификатору родителя (обновляются
+    // при перемещении родителя, чтобы не искать его по всей сетке).
+    pending_parents: BTreeMap<u64, (usize, usize)>,
+    // Количество вынашиваемых потомков, которые не родились на итерации.
+    failed_births: usize,
+    // Травоядное сначала пытается доесть растение, которое ело в прошлый раз.
+    food_focus: bool,
+    // Количество семян, которые растение пытается рассыпать за одно размножение.
+    seeds_per_reproduction: usize,
+    // Возраст (итерации), по достижении которого растение умирает. 0 - растения не
+    // умирают от старости.
+    plant_max_age: usize,
+    // Начальная энергия агентов при заселении мира: растения и животные.
+    initial_energy: (InitialEnergy, InitialEnergy),
+    // Максимальное количество рождений за одну итерацию: первый элемент - травоядные,
+    // второй хищные, третий всеядные. 0 - не ограничено.
+    birth_budget: (usize, usize, usize),
+    // Правила мира, которые проверяют действия животных, в порядке применения.
+    action_policies: Vec<Box<dyn ActionPolicy>>,
+    // Доля энергии съеденного, которую получает всеядное: первый элемент - растения,
+    // второй - травоядные.
+    omnivore_energy_rate: (f64, f64),
+    // Какие виды поедают падаль, когда поблизости нет другой еды: травоядные,
+    // хищники, всеядные.
+    scavenging: (bool, bool, bool),
+    // Доля энергии падали, которую получает поедающее ее животное.
+    carrion_energy_rate: f64,
+    // Сколько энергии падали животное съедает за раз.
+    carrion_bite: Energy,
+    // Доля энергии падали, которую она теряет за итерацию (разложение). 0 - падаль
+    // не разлагается.
+    carrion_decay_rate: f64,
+    // Доля потерянной при разложении энергии, которая переходит в плодородие почвы.
+    carrion_fertility_rate: f64,
+    // Сколько итераций падаль лежит в ячейке, после чего разлагается полностью.
+    // 0 - без ограничения.
+    carrion_lifetime: usize,
+    // Доля плодородия, которую почва теряет за итерацию.
+    fertility_decay_rate: f64,
+    // Двухшаговая охота: хищник сначала убивает жертву нападением, а затем съедает
+    // ее тушу. Иначе хищник убивает и съедает жертву одним действием "есть".
+    two_step_predation: bool,
+    // Вероятность удачного нападения на жертву с равной нападающему энергией.
+    attack_success_rate: f64,
+    // Сколько итераций туша лежит в ячейке, после чего становится падалью (если
+    // падаль кто-то ест) или пропадает. 0 - без ограничения.
+    corpse_lifetime: usize,
+    // Итерация, на которой вид появляется в мире: первый элемент - травоядные,
+    // второй хищные, третий всеядные. 0 - вид заселяется сразу.
+    start_tick: (usize, usize, usize),
+    // Отложенное заселение вида (количество и стратегия размещения). None - вид уже
+    // заселен или заселять нечего.
+    pending_introduction: (Option<Introduction>, Option<Introduction>, Option<Introduction>),
+    // Строгий режим поедания: животное ест только то, что находится прямо перед ним.
+    strict_eating: bool,
+    // Топология мира: по каким осям склеены края сетки.
+    topology: Topology,
+    // Период (в итерациях) сверки счетчиков статистики с пересчетом по сетке.
+    // 0 - сверка не выполняется.
+    stats_validation_period: usize,
+    // Последнее расхождение счетчиков, найденное сверкой: итерация и описание.
+    stats_mismatch: Option<(usize, String)>,
+    // Период (в итерациях) замера генетического разнообразия. 0 - замер не выполняется.
+    diversity_period: usize,
+    // Максимальный размер выборки животных одного вида для замера разнообразия.
+    diversity_sample: usize,
 
     // Статистика мира.
-    // В случае кортежа: первый элемент - травоядное, второй хищное.
+
+    // Количество выполненных итераций мира.
+    iteration: usize,
+    // Идентификатор, который получит следующее добавленное в мир животное.
+    next_animal_id: u64,
+    // Количество ручных изменений энергии животных.
+    energy_adjustments: usize,
+    // Идентификаторы живых членов отслеживаемой династии (предка и его потомков).
+    dynasty: BTreeSet<u64>,
+    // Обнулять энергию агента, если она перестала быть конечным числом.
+    sanitize_energy: bool,
+    // Адреса агентов, об обнулении энергии которых уже сообщено (сообщаем один раз).
+    sanitized_agents: (BTreeSet<Handle>, BTreeSet<Handle>),
+    // Животное, решения мозга которого записываются. None - запись выключена.
+    traced_animal: Option<u64>,
+    // Ячейка животного, решения которого записываются (обновляется при его перемещении),
+    // чтобы не искать его по всей сетке на каждом кадре.
+    traced_position: Option<(usize, usize)>,
+    // Следы решений, записанные с момента последнего `take_action_traces`.
+    action_traces: Vec<ActionTrace>,
+    // Частота мутаций мозга потомков: среднее количество изменяемых генов.
+    mutation_rate: (f64, f64, f64),
+    // Регулятор частоты мутаций по генетическому разнообразию. None - частота постоянна.
+    mutation_controller: Option<MutationController>,
+    // Изменения частоты мутаций с момента последнего `take_mutation_adjustments`.
+    mutation_adjustments: Vec<MutationAdjustment>,
+    // В случае кортежа: первый элемент - травоядное, второй хищное, третий всеядное.
 
     // Общее количество растений (не съеденных) в мире.
     plant_count: usize,
     // Количество живых животных в мире.
-    animal_count: (usize, usize),
-    // Текущие, живые долгожители (имеющие максимальный срок жизни в итерациях).
-    best_animal: (AnimalInCell, AnimalInCell),
-    // Указатель на лучшее умершее животное (прожившее дольше всех в итерациях).
-    best_death_animal: (AnimalInCell, AnimalInCell),
+    animal_count: (usize, usize, usize),
+    // Снимки лучших (самых приспособленных) живых животных.
+    best_animal: (Option<AnimalSnapshot>, Option<AnimalSnapshot>, Option<AnimalSnapshot>),
+    // Оценка приспособленности, по которой выбираются лучшие животные.
+    fitness: &'static dyn Fitness,
+    // Статистика жизни живых животных для оценки приспособленности.
+    animal_stats: BTreeMap<u64, PerAnimalStats>,
+    // Записи о жизни последних умерших животных. None - записи не ведутся.
+    postmortems: Option<RingBuffer<Postmortem>>,
+    // Отправлять записи о жизни умерших животных в канал событий.
+    postmortem_events: bool,
     // Количество размножений животных.
-    animal_reproductions: (usize, usize),
+    animal_reproductions: (usize, usize, usize),
     // Количество смертей животных.
-    animal_deaths: (usize, usize),
+    animal_deaths: (usize, usize, usize),
+    // Количество животных, добавленных в мир не рождением (заселение, сценарий,
+    // `add_animal`). Вместе с рождениями и смертями дает численность видов.
+    animal_arrivals: (usize, usize, usize),
+    // Количество смертей животных от старости.
+    old_age_deaths: (usize, usize, usize),
+    // Наибольший возраст умершего животного.
+    oldest_death_age: (usize, usize, usize),
     // Максимальное достигнутое поколение животных.
-    animal_max_generation: (usize, usize),
+    animal_max_generation: (usize, usize, usize),
+    // Энергия, фактически полученная животными от поедания на текущей итерации.
+    eaten_energy: (Energy, Energy, Energy),
+    // Количество рождений на текущей итерации.
+    births: (usize, usize, usize),
+    // Количество зачатий (потомков, которых начали вынашивать) на текущей итерации.
+    conceptions: (usize, usize, usize),
+    // Количество размножений, отложенных на текущей итерации из-за лимита рождений.
+    deferred_births: (usize, usize, usize),
+    // Количество размножений, не состоявшихся на текущей итерации из-за тесноты.
+    blocked_births: (usize, usize, usize),
+    // Количество запретов действий на текущей итерации по правилам цепочки.
+    policy_vetoes: [usize; MAX_ACTION_POLICIES],
+    // Возраст, до которого животные каждого вида не могут быть съедены. 0 - защиты нет.
+    protection_age: (usize, usize, usize),
+    // Контекст мира на входах мозга: возраст нормировки доли возраста (итерации).
+    // None - входы контекста равны нулю.
+    brain_context: Option<usize>,
+    // Сезонность роста растений: длина сезона (итерации) и амплитуда. None - энергия
+    // роста постоянна.
+    season: Option<(usize, f64)>,
+    // Сколько итераций в каждой ячейке ([x][y]) было растение, травоядное, хищник и
+    // всеядное. None - занятость ячеек не учитывается.
+    occupancy: Option<(OccupancyGrid, OccupancyGrid, OccupancyGrid, OccupancyGrid)>,
+    // Сколько раз на текущей итерации хищник пропустил защищенную добычу, по виду добычи.
+    protected_skips: (usize, usize, usize),
+    // Энергия, полученная животными от поедания падали на текущей итерации.
+    carrion_energy: (Energy, Energy, Energy),
+    // Генетическое разнообразие видов по последнему замеру.
+    genetic_diversity: (Option<f64>, Option<f64>, Option<f64>),
+    // Количество семян растений на текущей итерации: попытки и проросшие.
+    seeds: (usize, usize),
+    // Количество растений, умерших от старости на текущей итерации.
+    plant_deaths: usize,
+    // Количество попыток поесть на текущей итерации.
+    eat_attempts: (usize, usize, usize),
+    // Количество удачных попыток поесть на текущей итерации.
+    eat_successes: (usize, usize, usize),
+    // Количество нападений на текущей итерации.
+    attack_attempts: (usize, usize, usize),
+    // Количество жертв, убитых нападениями на текущей итерации.
+    kills: (usize, usize, usize),
+    // Скользящие средние доли удачных попыток поесть.
+    success_rate: (RollingMean, RollingMean, RollingMean),
+    // Скользящие средние энергии, полученной за одну попытку поесть.
+    energy_per_attempt: (RollingMean, RollingMean, RollingMean),
+    // Энергетический баланс видов на текущей итерации. До завершения итерации в
+    // `spent` накапливается убыль энергии животных за их ходы (см. `tick`).
+    energy_budget: (EnergyBudget, EnergyBudget, EnergyBudget),
+    // Энергетические балансы видов за последние итерации.
+    energy_budgets: VecDeque<(EnergyBudget, EnergyBudget, EnergyBudget)>,
+    // Количество итераций, за которые хранятся энергетические балансы.
+    energy_budget_window: usize,
+    // Канал для событий мира. None - события не отправляются.
+    event_sink: Option<Sender<WorldEvent>>,
+    // Действующие настройки случайных блоков загруженных сценариев (см. `load_scenario`).
+    scenario_blocks: Vec<String>,
 }
 
+// Мир можно прогонять в отдельном потоке: проверка на этапе компиляции.
+const _: fn() = || {
+    fn assert_send<T: Send>() {}
+    assert_send::<Landscape>();
+};
+
 impl Landscape {
     /// Конструктор. Условно считаем, что мир простирается слева на право и с верху вниз:
     /// (0, 0) - левый, верхний угол; (width, height) - правый, нижний угол. Значение
@@ -235,10 +952,11 @@ impl Landscape {
     /// * `max_carnivore`: Максимальное количество хищников.
     /// * `plant_grow_energy`: Энергия которую среда будет передавать растению на каждой итерации.
     /// Этим самым мы как-бы эмулируем солнечный свет.
+    /// * `seed`: Зерно генератора случайных чисел мира. С одинаковым зерном и настройками
+    ///   прогоны совпадают. None - зерно случайное.
     ///
-    /// TODO: Сделать сезонность на основе параметра plant_grow_energy, а так-же неоднородность по среде.
-    /// TODO: Это позволит эмулировать "изменение климата", "времена года" и разные климатические зоны.
-    /// TODO: В идеале это должно привести к тому, что разные области будут населять разные животные.
+    /// Энергия роста одинакова во всех ячейках; климатические зоны задаются методами
+    /// `set_growth_map` и `set_growth_fn`.
     ///
     /// returns: Result<World, CreatingWorldError>
     pub fn new(
@@ -247,7 +965,8 @@ impl Landscape {
         max_plants: usize,
         max_herbivore: usize,
         max_carnivore: usize,
-        plant_grow_energy: Energy
+        plant_grow_energy: Energy,
+        seed: Option<u64>
     ) -> Result<Landscape, RecoverableError> {
         if width > isize::MAX.try_into().unwrap() ||  height > isize::MAX.try_into().unwrap() {
             return Err(RecoverableError::new(
@@ -263,20 +982,28 @@ impl Landscape {
         // для поиска случайных не занятых мест.
         let mut shuffle_width: Vec<usize> = (0..width).collect();
         let mut shuffle_height: Vec<usize> = (0..height).collect();
-        shuffle_width.shuffle(&mut thread_rng());
-        shuffle_height.shuffle(&mut thread_rng());
+        // Случайное зерно тоже запоминается, чтобы прогон можно было повторить.
+        let seed = seed.unwrap_or_else(|| CheckpointRng::from_entropy().gen());
+        let mut rng = CheckpointRng::seed_from_u64(seed);
+        shuffle_width.shuffle(&mut rng);
+        shuffle_height.shuffle(&mut rng);
 
         Ok(Landscape {
             // Агенты.
-            animals: vec![],
-            plants: vec![],
-            dead_animals: vec![],
+            animals: Arena::new(),
+            plants: Arena::new(),
+            spare_animals: vec![],
 
             // Среда.
             landscape: create_landscape_matrix(width, height),
             view_state: Vec::with_capacity(max_plants * max_herbivore * max_carnivore),
+            frame_request: FrameRequest::default(),
+            view_ready: false,
+            transient_events: Vec::new(),
             shuffle_width,
             shuffle_height,
+            rng,
+            seed,
 
             // Параметры мира.
             width,
@@ -284,1028 +1011,7883 @@ impl Landscape {
             max_plants,
             max_herbivore,
             max_carnivore,
-            plant_grow_energy,
+            max_omnivore: 0,
+            growth_map: vec![vec![plant_grow_energy; height]; width],
+            eat_targeting: (EatTargeting::Random, EatTargeting::Random, EatTargeting::Random),
+            reproduction_placement: (ReproductionPlacement::Anywhere, ReproductionPlacement::Anywhere),
+            gestation_ticks: 0,
+            gestation_drain: 0 as Energy,
+            max_pending_births: 0,
+            pending_births: Vec::new(),
+            pending_parents: BTreeMap::new(),
+            failed_births: 0,
+            food_focus: false,
+            seeds_per_reproduction: 1,
+            plant_max_age: 0,
+            plant_update_interval: 1,
+            spawn_margin: 0,
+            initial_energy: (InitialEnergy::Full, InitialEnergy::Full),
+            birth_budget: (0, 0, 0),
+            action_policies: Vec::new(),
+            scavenging: (false, false, false),
+            omnivore_energy_rate: (0.5, 0.5),
+            carrion_energy_rate: 0.0,
+            carrion_bite: 0 as Energy,
+            carrion_decay_rate: 0.0,
+            carrion_fertility_rate: 0.0,
+            carrion_lifetime: 0,
+            fertility_decay_rate: 0.0,
+            two_step_predation: false,
+            attack_success_rate: 1.0,
+            corpse_lifetime: 0,
+            start_tick: (0, 0, 0),
+            pending_introduction: (None, None, None),
+            strict_eating: false,
+            topology: Topology::Torus,
+            stats_validation_period: 0,
+            stats_mismatch: None,
+            diversity_period: 0,
+            diversity_sample: 0,
+            genetic_diversity: (None, None, None),
 
             // Статистика.
+            iteration: 0,
+            next_animal_id: 0,
+            energy_adjustments: 0,
+            dynasty: BTreeSet::new(),
+            sanitize_energy: false,
+            sanitized_agents: (BTreeSet::new(), BTreeSet::new()),
+            traced_animal: None,
+            traced_position: None,
+            action_traces: Vec::new(),
+            mutation_rate: (1.0, 1.0, 1.0),
+            mutation_controller: None,
+            mutation_adjustments: Vec::new(),
             plant_count: 0,
-            animal_count: (0, 0),
-            best_animal: (AnimalInCell::None, AnimalInCell::None),
-            best_death_animal: (AnimalInCell::None, AnimalInCell::None),
-            animal_reproductions: (0, 0),
-            animal_deaths: (0, 0),
-            animal_max_generation: (0, 0),
+            animal_count: (0, 0, 0),
+            best_animal: (None, None, None),
+            fitness: &Age,
+            animal_stats: BTreeMap::new(),
+            postmortems: None,
+            postmortem_events: false,
+            animal_reproductions: (0, 0, 0),
+            animal_deaths: (0, 0, 0),
+            animal_arrivals: (0, 0, 0),
+            old_age_deaths: (0, 0, 0),
+            oldest_death_age: (0, 0, 0),
+            animal_max_generation: (0, 0, 0),
+            eaten_energy: (0 as Energy, 0 as Energy, 0 as Energy),
+            births: (0, 0, 0),
+            conceptions: (0, 0, 0),
+            deferred_births: (0, 0, 0),
+            blocked_births: (0, 0, 0),
+            policy_vetoes: [0; MAX_ACTION_POLICIES],
+            protection_age: (0, 0, 0),
+            brain_context: None,
+            season: None,
+            occupancy: None,
+            protected_skips: (0, 0, 0),
+            carrion_energy: (0 as Energy, 0 as Energy, 0 as Energy),
+            seeds: (0, 0),
+            plant_deaths: 0,
+            eat_attempts: (0, 0, 0),
+            eat_successes: (0, 0, 0),
+            attack_attempts: (0, 0, 0),
+            kills: (0, 0, 0),
+            success_rate: (RollingMean::new(1), RollingMean::new(1), RollingMean::new(1)),
+            energy_per_attempt: (RollingMean::new(1), RollingMean::new(1), RollingMean::new(1)),
+            energy_budget: Default::default(),
+            energy_budgets: VecDeque::new(),
+            energy_budget_window: 1,
+            event_sink: None,
+            scenario_blocks: vec![],
         })
     }
 
-    /// Обрезает координаты, что-бы обеспечить тороидальность мира.
+    /// Задает способ выбора цели для поедания для каждого вида животных.
     ///
     /// # Arguments
     ///
-    /// * `coord`: Координата местоположения (x или y). Может быть отрицательной.
-    /// * `max_size`: Максимальный размер мира по соответствующей координате.
+    /// * `herbivore`: Способ выбора растения травоядным.
+    /// * `carnivore`: Способ выбора жертвы хищником.
+    /// * `omnivore`: Способ выбора растения или жертвы всеядным.
     ///
-    /// Returns: Координата в границах мира.
-    fn clip(coord: isize, max_size: usize) -> usize {
-        // Конвертируем параметры в тип со знаком.
-        let max_size: isize = max_size as isize;
-
-        if coord < 0 {
-            // В процессе вычисления получилась отрицательная координата.
-            // В этом случае берем смещение от максимальной границы мира,
-            // на размер отрицательной координаты.
-            return (max_size + coord) as usize;
-        } else if coord > (max_size - 1) {
-            // Если-же координата выходит за максимальные границы мира, то
-            // берем остаток от деления координаты на размер мира в этом
-            // направлении.
-            return (coord % max_size) as usize;
-        }
+    /// returns: ()
+    pub fn set_eat_targeting(&mut self, herbivore: EatTargeting, carnivore: EatTargeting, omnivore: EatTargeting) {
+        self.eat_targeting = (herbivore, carnivore, omnivore);
+    }
 
-        // По умолчанию возвращаем переданную координату.
-        coord as usize
+    /// Включает вынашивание потомков животными. Размножившееся животное не
+    /// размещает потомка сразу, а вынашивает его заданное количество итераций,
+    /// теряя дополнительную энергию. Потом потомок появляется рядом с текущим
+    /// положением родителя; если родитель умер или места нет, потомок не рождается.
+    /// Животное, которое уже вынашивает наибольшее количество потомков, откладывает
+    /// размножение (как при исчерпанном лимите рождений) и сохраняет энергию.
+    ///
+    /// # Arguments
+    ///
+    /// * `ticks`: Продолжительность вынашивания в итерациях. 0 - потомок появляется сразу.
+    /// * `drain`: Энергия, которую родитель теряет на каждой итерации вынашивания каждого потомка.
+    /// * `max_pending`: Сколько потомков животное может вынашивать одновременно. 0 - без ограничения.
+    ///
+    /// returns: ()
+    pub fn set_gestation(&mut self, ticks: usize, drain: Energy, max_pending: usize) {
+        self.gestation_ticks = ticks;
+        self.gestation_drain = drain;
+        self.max_pending_births = max_pending;
     }
 
-    /// Метод - обертка, конвертирует изменяемый указатель в разделяемую ссылку.
-    /// Метод универсален, добавлен для сокращения unsafe блоков.
+    /// Задает, где появляется потомок размножающегося агента. По умолчанию потомок
+    /// появляется в любой свободной ячейке мира; размещение рядом с родителем сохраняет
+    /// пространственные группы (заросли растений, стада).
     ///
     /// # Arguments
     ///
-    /// * `t`: Изменяемый указатель на тип T.
+    /// * `plants`: Размещение потомков растений.
+    /// * `animals`: Размещение потомков животных.
     ///
-    /// Returns: &T
-    fn get_agent_ref<'a, T: ?Sized>(t: *mut T) -> &'a T {
-        unsafe {
-            t.as_ref().expect("Обнаружен нулевой указатель на агента")
-        }
+    /// returns: ()
+    pub fn set_reproduction_placement(&mut self, plants: ReproductionPlacement, animals: ReproductionPlacement) {
+        self.reproduction_placement = (plants, animals);
     }
 
-    /// Метод - обертка, конвертирует изменяемый указатель в изменяемую ссылку.
-    /// Метод универсален, добавлен для сокращения unsafe блоков.
+    /// Включает "сосредоточенное" поедание: травоядное запоминает растение, которое
+    /// удачно съело, и на следующих итерациях сначала пытается доесть его, а к поиску
+    /// в области переходит, только если растение съедено или вне досягаемости.
     ///
     /// # Arguments
     ///
-    /// * `t`: Изменяемый указатель на тип T.
+    /// * `focus`: Включить сосредоточенное поедание.
     ///
-    /// Returns: &mut T
-    fn get_agent_mut<'a, T: ?Sized>(t: *mut T) -> &'a mut T {
-        unsafe {
-            t.as_mut().expect("Обнаружен нулевой указатель на агента")
-        }
+    /// returns: ()
+    pub fn set_food_focus(&mut self, focus: bool) {
+        self.food_focus = focus;
     }
 
-    // /// Возвращает ширину мира.
-    // pub fn get_width(&self) -> usize {
-    //     self.width
-    // }
-    //
-    // /// Возвращает высоту мира.
-    // pub fn get_height(&self) -> usize {
-    //     self.height
-    // }
+    /// Задает количество семян, которые растение пытается рассыпать за одно
+    /// размножение. Каждое семечко стоит растению энергии; рассыпание прекращается,
+    /// как только не находится свободного места или растению не хватает энергии.
+    ///
+    /// # Arguments
+    ///
+    /// * `seeds`: Количество семян (не меньше одного).
+    ///
+    /// returns: ()
+    pub fn set_seeds_per_reproduction(&mut self, seeds: usize) {
+        self.seeds_per_reproduction = seeds.max(1);
+    }
 
-    /// Возвращает состояние ячейки, т.е. информацию, которую можно отобразить
-    /// для данной ячейки.
+    /// Задает наибольший возраст растений. Растение, достигшее его, умирает: оно
+    /// удаляется из мира, а ячейка освобождается для новых растений (и место
+    /// освобождается в пределах максимального количества растений).
     ///
     /// # Arguments
     ///
-    /// * `x`: Координата "x" местоположения.
-    /// * `y`: Координата "y" местоположения.
+    /// * `max_age`: Возраст в итерациях, 0 - растения не умирают от старости.
     ///
-    /// returns: Vec<CellStuff, Global>
-    pub fn get_view_state(&self) -> Map {
-        self.view_state.clone()
+    /// returns: ()
+    pub fn set_plant_max_age(&mut self, max_age: usize) {
+        self.plant_max_age = max_age;
     }
 
-    /// Find an empty spot for the agent within its particular type.
+    /// Задает ширину полосы вдоль краев мира, в которой агенты не размещаются: ни при
+    /// заселении, ни при размножении. Животные могут заходить в полосу сами. Полоса
+    /// уменьшает количество ячеек, доступных для размещения, поэтому максимальное
+    /// количество агентов каждого типа должно помещаться в оставшуюся часть мира.
     ///
     /// # Arguments
     ///
-    /// * `agent_type`: Тип агента для которого пытаемся найти "место в мире".
+    /// * `margin`: Ширина полосы в ячейках. 0 - полосы нет.
     ///
-    /// returns: Result<(usize, usize), RecoverableError>
-    pub fn find_empty_spot(&self, agent_type: AgentType) -> Result<(usize, usize), RecoverableError> {
-        match agent_type {
-            AgentType::Plant => {
-                if self.plant_count >= self.max_plants {
-                    return Err(RecoverableError::new(
-                        fmt::format(format_args!(
-                            "Достигнуто максимальное количество ({}) растений в мире",
-                            self.max_plants,
-                        ))
-                    ))
-                }
-            }
-            AgentType::Herbivore => {
-                if self.animal_count.0 >= self.max_herbivore {
-                    return Err(RecoverableError::new(
-                        fmt::format(format_args!(
-                            "Достигнуто максимальное количество ({}) травоядных в мире",
-                            self.max_herbivore,
-                        ))
-                    ))
-                }
-            }
-            AgentType::Carnivore => {
-                if self.animal_count.1 >= self.max_carnivore {
-                    return Err(RecoverableError::new(
-                        fmt::format(format_args!(
-                            "Достигнуто максимальное количество ({}) хищников в мире",
-                            self.max_carnivore,
-                        ))
-                    ))
-                }
-            }
+    /// returns: Result<(), RecoverableError>
+    pub fn set_spawn_margin(&mut self, margin: usize) -> Result<(), RecoverableError> {
+        if margin * 2 >= self.width || margin * 2 >= self.height {
+            return Err(RecoverableError::new(fmt::format(format_args!(
+                "Полоса шириной {} не оставляет места в мире {}x{}", margin, self.width, self.height
+            ))));
         }
 
-        match agent_type {
-            AgentType::Plant => {
-                // Просматриваем все ячейки, но в случайном порядке.
-                for test_x in &self.shuffle_width {
-                    for test_y in &self.shuffle_height {
-                        if let PlantInCell::None = self.landscape[*test_x][*test_y].plant {
-                            // Точка свободна, берем ее.
-                            return Ok((*test_x, *test_y));
-                        }
-                    }
-                }
-
-                // Вряд ли это случится, но если все ячейки заняты...
+        let capacity = (self.width - margin * 2) * (self.height - margin * 2);
+        let limits = [
+            (self.max_plants, "растений"),
+            (self.max_herbivore, "травоядных"),
+            (self.max_carnivore, "хищников"),
+            (self.max_omnivore, "всеядных"),
+        ];
+        for (max, name) in limits {
+            if max > capacity {
                 return Err(RecoverableError::new(fmt::format(format_args!(
-                    "Не удалось найти свободное место для растения"
+                    "Максимальное количество {} ({}) больше количества ячеек вне полосы ({})",
+                    name, max, capacity
                 ))));
             }
+        }
 
-            AgentType::Herbivore | AgentType::Carnivore => {
-                for test_x in &self.shuffle_width {
-                    for test_y in &self.shuffle_height {
-                        if let AnimalInCell::None = self.landscape[*test_x][*test_y].animal {
-                            return Ok((*test_x, *test_y));
-                        }
-                    }
-                }
+        self.spawn_margin = margin;
 
-                // Вряд ли жто случится, но если все ячейки заняты...
-                panic!("По каким-то причинам, в мире закончилось место для новых животных!");
-            }
-        }
+        Ok(())
     }
 
-    /// Добавляет растение в мир.
+    /// Возвращает ширину полосы вдоль краев мира, в которой агенты не размещаются.
+    pub fn get_spawn_margin(&self) -> usize {
+        self.spawn_margin
+    }
+
+    /// Находится ли ячейка в полосе вдоль краев мира, где агенты не размещаются.
     ///
     /// # Arguments
     ///
-    /// * `x`: Координата "x" местоположения.
-    /// * `y`: Координата "y" местоположения.
-    /// * `plant`: Растение: тип должен реализовывать типаж PlantAlive и быть обернут в Box.
+    /// * `x`: Координата "x" ячейки.
+    /// * `y`: Координата "y" ячейки.
     ///
-    /// returns: Result<(), WorldError>
-    pub fn add_plant(
-        &mut self,
-        mut x: usize,
-        mut y: usize,
-        mut plant: Box<dyn PlantAlive>
-    ) -> Result<(), AddAgentError> {
-        // Если переданная точка выходит за "границы" мира.
-        if x >= self.width || y >= self.height {
-            return Err(
-                AddAgentError::OutOfBounds((x, y))
-            );
-        }
-
-        x = Self::clip(x as isize, self.width);
-        y = Self::clip(y as isize, self.height);
-
-        // Нужно проверить, не занято ли место в ячейке.
-        if let PlantInCell::None = self.landscape[x][y].plant {
-            // С начала в cell мы помещаем изменяемый указать на растение.
-            self.landscape[x][y].plant = PlantInCell::Plant(plant.as_mut());
+    /// returns: bool
+    fn in_spawn_margin(&self, x: usize, y: usize) -> bool {
+        let margin = self.spawn_margin;
 
-            // Затем переносим "бокс" с растением, в общий массив растений.
-            // Порядок важен, если мы сделаем наоборот, то попытаемся получить
-            // изменяемую ссылку у перемещенного объекта.
-            self.plants.push(plant);
-            self.plant_count += 1;
-        } else {
-            return Err(
-                AddAgentError::TakenCell((x, y))
-            );
-        }
+        x < margin || y < margin || x + margin >= self.width || y + margin >= self.height
+    }
 
-        Ok(())
+    /// Задает период обработки растений. Растения обрабатываются только на итерациях,
+    /// кратных периоду, и получают энергию роста сразу за весь период, поэтому в среднем
+    /// растут так же, как при обработке на каждой итерации. Между обработками растения
+    /// остаются на месте, животные их видят и едят как обычно. Возраст растений и время
+    /// до возобновления роста отсчитываются на каждой итерации.
+    ///
+    /// # Arguments
+    ///
+    /// * `interval`: Период в итерациях (не меньше одного). 1 - на каждой итерации.
+    ///
+    /// returns: ()
+    pub fn set_plant_update_interval(&mut self, interval: usize) {
+        self.plant_update_interval = interval.max(1);
     }
 
-    /// Добавляет животное в мир.
+    /// Задает распределение начальной энергии агентов, размещаемых методами
+    /// `populate_random` и `introduce_species`. Одинаковая полная энергия всех
+    /// растений в начале прогона делает первые поколения нетипичными.
     ///
     /// # Arguments
     ///
-    /// * `x`: Координата "x" местоположения.
-    /// * `y`: Координата "y" местоположения.
-    /// * `animal`: Животное: тип должен реализовывать типаж AnimalAlive и быть обернут в Box.
+    /// * `plants`: Начальная энергия растений.
+    /// * `animals`: Начальная энергия животных.
     ///
-    /// returns: Result<(), WorldError>
-    pub fn add_animal(
-        &mut self,
-        mut x: usize,
-        mut y: usize,
-        mut animal: Box<dyn AnimalAlive>
-    ) -> Result<(), AddAgentError> {
-        // Если переданная точка выходит за "границы" мира.
-        if x >= self.width || y >= self.height {
-            return Err(
-                AddAgentError::OutOfBounds((x, y))
-            );
-        }
-
-        x = Self::clip(x as isize, self.width);
-        y = Self::clip(y as isize, self.height);
-
-        // Нужно проверить, не занято ли место в ячейке.
-        if let AnimalInCell::None = self.landscape[x][y].animal {
-            // Изменяемая ссылка на животное.
-            let animal_ref = animal.as_mut();
-            let animal_type = animal_ref.get_type();
-
-            // С начала в cell мы помещаем изменяемый указать на животное
-            // (изменяемая ссылка конвертируется в изменяемый указатель,
-            // с внутренней точки зрения это одно и тоже).
-            self.landscape[x][y].animal = AnimalInCell::Animal(animal_ref);
+    /// returns: ()
+    pub fn set_initial_energy(&mut self, plants: InitialEnergy, animals: InitialEnergy) {
+        self.initial_energy = (plants, animals);
+    }
 
-            // Затем переносим "бокс" с животным, в общий массив животных.
-            // Порядок важен, если мы сделаем наоборот, то попытаемся получить
-            // изменяемую ссылку у перемещенного объекта.
-            self.animals.push(animal);
+    /// Задает максимальное количество рождений за одну итерацию для каждого вида
+    /// животных. Лимит проверяется раньше ограничения на количество животных в мире
+    /// и расходуется только состоявшимися рождениями. Животное, которому не хватило
+    /// лимита, сохраняет энергию и может попытаться размножиться на следующей итерации.
+    /// При вынашивании (см. `set_gestation`) лимит ограничивает зачатия за итерацию.
+    ///
+    /// # Arguments
+    ///
+    /// * `herbivore`: Лимит рождений травоядных, 0 - не ограничено.
+    /// * `carnivore`: Лимит рождений хищников, 0 - не ограничено.
+    /// * `omnivore`: Лимит рождений всеядных, 0 - не ограничено.
+    ///
+    /// returns: ()
+    pub fn set_birth_budget(&mut self, herbivore: usize, carnivore: usize, omnivore: usize) {
+        self.birth_budget = (herbivore, carnivore, omnivore);
+    }
 
-            match animal_type {
-                AnimaType::Herbivore => {
-                    self.animal_count.0 += 1;
-                }
-                AnimaType::Carnivore => {
-                    self.animal_count.1 += 1;
-                }
-            }
-        } else {
-            return Err(
-                AddAgentError::TakenCell((x, y))
-            );
+    /// Добавляет правило в конец цепочки правил, которые проверяют действие,
+    /// выбранное мозгом животного, до его выполнения (см. `policy::ActionPolicy`).
+    /// Запреты каждого правила считаются в `WorldStats::policy_vetoes`.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy`: Правило.
+    ///
+    /// returns: Result<(), RecoverableError> - ошибка, если в цепочке уже
+    /// MAX_ACTION_POLICIES правил.
+    pub fn add_action_policy(&mut self, policy: Box<dyn ActionPolicy>) -> Result<(), RecoverableError> {
+        if self.action_policies.len() >= MAX_ACTION_POLICIES {
+            return Err(RecoverableError::new(fmt::format(format_args!(
+                "Не удалось добавить правило {}: в цепочке не может быть больше {} правил",
+                policy.name(), MAX_ACTION_POLICIES
+            ))));
         }
 
+        self.action_policies.push(policy);
+
         Ok(())
     }
 
-    /// Одна симуляция всего мира.
-    pub fn tick(&mut self) {
-        // Перед каждой итерацией тасуем вектора координат. Т.к. сложность алгоритма тасовки
-        // составляет 2*N, то это не представляет особых проблем с производительностью.
-        self.shuffle_width.shuffle(&mut thread_rng());
-        self.shuffle_height.shuffle(&mut thread_rng());
-
-        // Перебираем ячейки в случайном порядке!
-        for x in &self.shuffle_width.clone() {
-            for y in &self.shuffle_height.clone() {
-                // Симуляция травы.
-                match self.landscape[*x][*y].plant {
-                    // В точке есть растение.
-                    PlantInCell::Plant(ptr) => {
-                        // Получим изменяемую ссылку на значение на которое "указывает" указатель.
-                        // Непосредственно работать с указателем мы не можем. Если ссылка получена,
-                        // то это уже безопасный код.
-                        let plant = Self::get_agent_mut(ptr);
-                        self.simulate_plant(plant, *x, *y);
-                    },
-                    // Нет растения - ничего не делать.
-                    PlantInCell::None => {},
-                }
-
-                // Симуляция животных.
-                match self.landscape[*x][*y].animal {
-                    // В точке есть животное.
-                    AnimalInCell::Animal(ptr) => {
-                        // Изменяемая ссылка на животное.
-                        let animal = Self::get_agent_mut(ptr);
-
-                        // Проверяем обработанность животного.
-                        // Возможно животное уже сделало "свой ход". Как такое возможно, что в новь
-                        // обрабатываемая точка уже содержит животное сделавшее свой ход? Рассмотрим
-                        // пример: текущая итерация обрабатывает точку (1, 1). Животное перемещается
-                        // в точку (1, 2). Когда итерация дойдет до точки (1, 2) животное повторно
-                        // совершит свое действие, что неверно.
-                        if animal.is_processed() == true {
-                            continue;
-                        }
-
-                        // К этому моменту мертвого животного в точке быть не может (исключается
-                        // параметром is_processed).
-                        if animal.is_dead() {
-                            panic!("Попытка симуляции мертвого животного в ячейке {}, {}.", x, y);
-                        };
-
-                        // Даем животному, своими активными действиями, шанс выжить.
-                        self.simulate_animal(animal, *x, *y);
-                    },
-                    // Нет животного - ничего не делать.
-                    AnimalInCell::None => {},
-                }
-            }
-        }
-
-        // Завершающая обработка.
-        self.final_processing();
+    /// Имена правил цепочки в порядке применения (соответствуют счетчикам
+    /// `WorldStats::policy_vetoes`).
+    pub fn action_policy_names(&self) -> Vec<&'static str> {
+        self.action_policies.iter().map(|policy| policy.name()).collect()
     }
 
-    /// Симуляция травы в указанной точке.
+    /// Задает долю энергии съеденного, которую получает всеядное. Остальная энергия
+    /// пропадает: всеядное усваивает пищу хуже специализированных видов.
     ///
     /// # Arguments
     ///
-    /// * `plant`: Изменяемый указатель на текущее, симулируемое растение.
-    /// * `x`: "x" координата симулируемого растения.
-    /// * `y`: "y" координата симулируемого растения.
+    /// * `plant_rate`: Доля энергии съеденного растения.
+    /// * `meat_rate`: Доля энергии съеденного травоядного.
     ///
-    /// Returns: ()
-    fn simulate_plant(&mut self, plant: &mut dyn PlantAlive, x: usize, y: usize) {
-        // Получаем то, что хочет растение.
-        let action = plant.action();
-
-        match action {
-            // Растение ничего не хочет (кроме гомеостаза).
-            PlantAction::None => {
-                self.inactivity_plant_action(plant, x, y);
-            }
-            // Растение хочет расти.
-            PlantAction::Grow => {
-                self.grow_plant_action(plant, x, y);
-            }
-            // Растение решило размножиться (рассыпать семена).
-            PlantAction::Reproduce => {
-                self.reproduce_plant_action(plant, x, y);
-            }
-        }
+    /// returns: ()
+    pub fn set_omnivore_energy_rate(&mut self, plant_rate: f64, meat_rate: f64) {
+        self.omnivore_energy_rate = (plant_rate, meat_rate);
     }
 
-    /// Действие - нет действия.
-    fn inactivity_plant_action(&mut self, plant: &mut dyn PlantAlive, x: usize, y: usize) {
-        self.landscape[x][y].plant = self.landscape[x][y].plant;
-        plant.inactivity_action();
+    /// Задает максимальное количество всеядных в мире. По умолчанию 0 - всеядные
+    /// не заселяются и не рождаются.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_omnivore`: Максимальное количество всеядных.
+    ///
+    /// returns: ()
+    pub fn set_max_omnivore(&mut self, max_omnivore: usize) {
+        self.max_omnivore = max_omnivore;
     }
 
-    /// Реализует рост растения.
-    fn grow_plant_action(&mut self, plant: &mut dyn PlantAlive, x: usize, y: usize) {
-        self.landscape[x][y].plant = self.landscape[x][y].plant;
-        plant.grow_action(self.plant_grow_energy);
+    /// Включает поедание падали. Если падаль ест хотя бы один вид, животное, умершее
+    /// от голода, оставляет в ячейке падаль, и энергия его тела не пропадает. Животное
+    /// ест падаль действием "есть", если в области поедания нет другой еды (растений
+    /// для травоядных, жертв для хищников, ни того, ни другого для всеядных); несколько
+    /// животных могут есть одну падаль, пока она не закончится.
+    ///
+    /// # Arguments
+    ///
+    /// * `species`: Какие виды поедают падаль: травоядные, хищники, всеядные.
+    /// * `energy_rate`: Доля энергии съеденной падали, которую получает животное.
+    /// * `bite`: Сколько энергии падали животное съедает за раз.
+    ///
+    /// returns: ()
+    pub fn set_scavenging(&mut self, species: (bool, bool, bool), energy_rate: f64, bite: Energy) {
+        self.scavenging = species;
+        self.carrion_energy_rate = energy_rate;
+        self.carrion_bite = bite;
     }
 
-    /// Реализует размножение растения.
+    /// Включает разложение падали. Каждую итерацию падаль теряет часть энергии, и часть
+    /// потерянного переходит в плодородие почвы ячейки. Плодородие добавляется к энергии
+    /// роста растения в ячейке и постепенно убывает.
     ///
     /// # Arguments
     ///
-    /// * `plant`: Изменяемая ссылка на текущее, симулируемое растение.
-    /// * `_x`: "x" координата симулируемого растения.
-    /// * `_y`: "y" координата симулируемого растения.
+    /// * `decay_rate`: Доля энергии падали, которую она теряет за итерацию. 0 - падаль
+    ///   не разлагается.
+    /// * `fertility_rate`: Доля потерянной энергии, которая переходит в плодородие.
+    /// * `fertility_decay_rate`: Доля плодородия, которую почва теряет за итерацию.
     ///
-    /// Returns: ()
-    fn reproduce_plant_action(&mut self, plant: &mut dyn PlantAlive, _x: usize, _y: usize) {
-        let spot = self.find_empty_spot(AgentType::Plant);
+    /// returns: ()
+    pub fn set_carrion_decay(&mut self, decay_rate: f64, fertility_rate: f64, fertility_decay_rate: f64) {
+        self.carrion_decay_rate = decay_rate.clamp(0.0, 1.0);
+        self.carrion_fertility_rate = fertility_rate.clamp(0.0, 1.0);
+        self.fertility_decay_rate = fertility_decay_rate.clamp(0.0, 1.0);
+    }
 
-        match spot {
-            // Ячейка нашлась.
-            Ok(coord) => {
-                let new_plant = plant.reproduce_action();
-                self.add_plant(coord.0, coord.1, new_plant)
-                    .expect("Не удалось добавить растение");
-            }
-            // Не удалось найти свободную ячейку... Пропускаем...
-            Err(_) => {}
-        }
+    /// Задает, сколько итераций падаль лежит в ячейке. Когда срок истекает, оставшаяся
+    /// падаль разлагается полностью (часть ее энергии переходит в плодородие почвы, см.
+    /// `set_carrion_decay`). Новое тело в той же ячейке продлевает срок.
+    ///
+    /// # Arguments
+    ///
+    /// * `lifetime`: Срок в итерациях, 0 - без ограничения.
+    ///
+    /// returns: ()
+    pub fn set_carrion_lifetime(&mut self, lifetime: usize) {
+        self.carrion_lifetime = lifetime;
     }
 
-    /// Симуляция животного в точке.
+    /// Включает двухшаговую охоту. Хищник (и всеядное) сначала убивает травоядное
+    /// действием "напасть", и в ячейке жертвы остается туша; затем любое хищное или
+    /// всеядное животное может съесть тушу действием "есть" на этой же или следующих
+    /// итерациях. Живых животных при двухшаговой охоте не едят.
     ///
-    /// This is the main animal simulation routine. This function performs
-    /// the perception phase which fills in the input cells for the animal's
-    /// brain. This is based upon the particular direction of the agent.
-    /// The agent brain determines the action to be taken. A function is
-    /// then called based upon the action selected.
+    /// Вероятность удачного нападения равна `success_rate`, умноженной на удвоенную
+    /// долю энергии нападающего в суммарной энергии нападающего и жертвы (и не больше
+    /// единицы): жертва с запасом энергии отбивается чаще.
     ///
     /// # Arguments
     ///
-    /// * `animal`: Изменяемая ссылка на текущее, симулируемое животное.
-    /// * `x`: "x" координата симулируемого животного.
-    /// * `y`: "y" координата симулируемого животного.
+    /// * `success_rate`: Вероятность удачного нападения на жертву с равной энергией.
+    /// * `corpse_lifetime`: Сколько итераций туша лежит в ячейке, после чего становится
+    ///   падалью (если падаль ест хотя бы один вид, см. `set_scavenging`) или пропадает.
+    ///   0 - без ограничения.
     ///
     /// returns: ()
-    fn simulate_animal(&mut self, animal: &mut dyn AnimalAlive, x: usize, y: usize) {
-        // Determine inputs for the agent brain.
-        let inputs = self.percept(animal, x, y);
-        let action = animal.action(&inputs);
-
-        // Perform Action
-        match action {
-            AnimalAction::TurnLeft => {
-                self.turn_left_animal_action(animal, x, y);
-            }
-            AnimalAction::TurnRight => {
-                self.turn_right_animal_action(animal, x, y);
-            }
-            AnimalAction::Move => {
-                self.movement_animal_action(animal, x, y);
-            }
-            AnimalAction::Eat => {
-                self.eating_animal_action(animal, x, y);
-            }
-            AnimalAction::Reproduce => {
-                self.reproduce_animal_action(animal)
-            }
-            AnimalAction::None => {
-                self.inactivity_animal_action(animal)
-            }
-        }
+    pub fn set_two_step_predation(&mut self, success_rate: f64, corpse_lifetime: usize) {
+        self.two_step_predation = true;
+        self.attack_success_rate = success_rate.clamp(0.0, 1.0);
+        self.corpse_lifetime = corpse_lifetime;
     }
 
-    /// Животное "должно посмотреть по сторонам" (по соответствующим областям в зависимости
-    /// от направления) и заполнить структуру содержащую переменные входных сигналов для
-    /// мозга животного. Животное видит текущее состояние мира, т.е. остальные агенты
-    /// могли у этому моменту сделать свой шаг, а некоторые еще ждут своей очереди.
+    /// Задает итерацию, на которой каждый вид животных появляется в мире. Виды с
+    /// ненулевой итерацией не заселяются методами `populate_random` и
+    /// `populate_omnivores` сразу, а запоминаются и заселяются методом
+    /// `introduce_species` с той же стратегией размещения, когда мир достигнет
+    /// заданной итерации.
     ///
-    /// TODO: В дальнейшем планирую использовать "карту восприятия", матрицу
-    /// TODO: где заполнены соответствующие позиции с информацией о агентах
-    /// TODO: (их наличие).
+    /// # Arguments
+    ///
+    /// * `herbivore`: Итерация появления травоядных.
+    /// * `carnivore`: Итерация появления хищников.
+    /// * `omnivore`: Итерация появления всеядных.
+    ///
+    /// returns: ()
+    pub fn set_start_tick(&mut self, herbivore: usize, carnivore: usize, omnivore: usize) {
+        self.start_tick = (herbivore, carnivore, omnivore);
+    }
+
+    /// Делает ячейку непроходимой (скала, вода): в ней не могут находиться ни
+    /// растения, ни животные, животные упираются в нее.
     ///
     /// # Arguments
     ///
-    /// * `animal`: Изменяемая ссылка на животное.
-    /// * `x`: Положение животного по "x".
-    /// * `y`: Положение животного по "y".
+    /// * `x`, `y`: Координаты ячейки.
     ///
-    /// returns: AnimalInputSignal
-    fn percept(&self, animal: &mut dyn AnimalAlive, x: usize, y: usize) -> AnimalInputSignal {
-        let mut inputs =  AnimalInputSignal {
-            plant_front: 0,
-            plant_left: 0,
-            plant_right: 0,
-            plant_proximity: 0,
-            herbivore_front: 0,
-            herbivore_left: 0,
-            herbivore_right: 0,
-            herbivore_proximity: 0,
-            carnivore_front: 0,
-            carnivore_left: 0,
-            carnivore_right: 0,
-            carnivore_proximity: 0,
-        };
+    /// returns: Result<(), RecoverableError> - ошибка, если ячейка вне мира или в ней
+    /// есть агент.
+    pub fn set_blocked(&mut self, x: usize, y: usize) -> Result<(), RecoverableError> {
+        if x >= self.width || y >= self.height {
+            return Err(RecoverableError::new(AddAgentError::OutOfBounds((x, y)).to_string()));
+        }
 
-        match animal.get_direction() {
-            // Животное смотрит на север
-            AnimalDirection::North => {
-                let count = self.count_agents_in_area(&NORTH_FRONT, x, y);
-                inputs.plant_front = count.0;
-                inputs.herbivore_front = count.1;
-                inputs.carnivore_front = count.2;
-
-                let count = self.count_agents_in_area(&NORTH_LEFT, x, y);
-                inputs.plant_left = count.0;
-                inputs.herbivore_left = count.1;
-                inputs.carnivore_left = count.2;
-
-                let count = self.count_agents_in_area(&NORTH_RIGHT, x, y);
-                inputs.plant_right = count.0;
-                inputs.herbivore_right = count.1;
-                inputs.carnivore_right = count.2;
-
-                let count = self.count_agents_in_area(&NORTH_PROXIMITY, x, y);
-                inputs.plant_proximity = count.0;
-                inputs.herbivore_proximity = count.1;
-                inputs.carnivore_proximity = count.2;
-            }
-            // Животное смотри на юг
-            AnimalDirection::South => {
-                let count = self.count_agents_in_area(&SOUTH_FRONT, x, y);
-                inputs.plant_front = count.0;
-                inputs.herbivore_front = count.1;
-                inputs.carnivore_front = count.2;
-
-                let count = self.count_agents_in_area(&SOUTH_LEFT, x, y);
-                inputs.plant_left = count.0;
-                inputs.herbivore_left = count.1;
-                inputs.carnivore_left = count.2;
-
-                let count = self.count_agents_in_area(&SOUTH_RIGHT, x, y);
-                inputs.plant_right = count.0;
-                inputs.herbivore_right = count.1;
-                inputs.carnivore_right = count.2;
-
-                let count = self.count_agents_in_area(&SOUTH_PROXIMITY, x, y);
-                inputs.plant_proximity = count.0;
-                inputs.herbivore_proximity = count.1;
-                inputs.carnivore_proximity = count.2;
-            }
-            // Животное смотрит на запад
-            AnimalDirection::West => {
-                let count = self.count_agents_in_area(&WEST_FRONT, x, y);
-                inputs.plant_front = count.0;
-                inputs.herbivore_front = count.1;
-                inputs.carnivore_front = count.2;
-
-                let count = self.count_agents_in_area(&WEST_LEFT, x, y);
-                inputs.plant_left = count.0;
-                inputs.herbivore_left = count.1;
-                inputs.carnivore_left = count.2;
-
-                let count = self.count_agents_in_area(&WEST_RIGHT, x, y);
-                inputs.plant_right = count.0;
-                inputs.herbivore_right = count.1;
-                inputs.carnivore_right = count.2;
-
-                let count = self.count_agents_in_area(&WEST_PROXIMITY, x, y);
-                inputs.plant_proximity = count.0;
-                inputs.herbivore_proximity = count.1;
-                inputs.carnivore_proximity = count.2;
-            }
-            // Животное смотрит на восток
-            AnimalDirection::East => {
-                let count = self.count_agents_in_area(&EAST_FRONT, x, y);
-                inputs.plant_front = count.0;
-                inputs.herbivore_front = count.1;
-                inputs.carnivore_front = count.2;
-
-                let count = self.count_agents_in_area(&EAST_LEFT, x, y);
-                inputs.plant_left = count.0;
-                inputs.herbivore_left = count.1;
-                inputs.carnivore_left = count.2;
-
-                let count = self.count_agents_in_area(&EAST_RIGHT, x, y);
-                inputs.plant_right = count.0;
-                inputs.herbivore_right = count.1;
-                inputs.carnivore_right = count.2;
-
-                let count = self.count_agents_in_area(&EAST_PROXIMITY, x, y);
-                inputs.plant_proximity = count.0;
-                inputs.herbivore_proximity = count.1;
-                inputs.carnivore_proximity = count.2;
-            }
+        if !matches!(self.landscape[x][y].plant, PlantInCell::None)
+            || !matches!(self.landscape[x][y].animal, AnimalInCell::None)
+        {
+            return Err(RecoverableError::new(AddAgentError::TakenCell((x, y)).to_string()));
         }
 
-        inputs
+        self.landscape[x][y].terrain = Terrain::Blocked;
+
+        Ok(())
     }
 
-    /// Метод вычисляет количество агентов в точках которые переданы срезом.
+    /// Задает местность всего мира по маске непроходимых ячеек: ячейка [x][y]
+    /// непроходима, если `mask[x][y]` истинно, иначе открыта.
     ///
     /// # Arguments
     ///
-    /// * `offsets`: Срез смещений относительно заданной точки.
-    /// * `x`: Координата "x" точки относительно которой ищутся агенты.
-    /// * `y`: Координата "y" точки относительно которой ищутся агенты.
+    /// * `mask`: Маска непроходимых ячеек: `width` столбцов по `height` ячеек.
     ///
-    /// Returns: (usize, usize, usize) - количество растений, травоядных, хищников.
-    fn count_agents_in_area(&self, offsets: &[(i8, i8)], x: usize, y: usize) -> (usize, usize, usize) {
-        let mut plants: usize = 0;
-        let mut herbivores: usize = 0;
-        let mut carnivores: usize = 0;
-
-        for coord in offsets {
-            let x_off = Self::clip(
-                x as isize + coord.0 as isize,
-                self.width
-            );
-
-            let y_off = Self::clip(
-                y as isize + coord.1 as isize,
+    /// returns: Result<(), RecoverableError> - ошибка, если размеры маски не совпадают
+    /// с размерами мира или в непроходимой ячейке есть агент. При ошибке местность
+    /// не меняется.
+    pub fn set_terrain_mask(&mut self, mask: &[Vec<bool>]) -> Result<(), RecoverableError> {
+        if mask.len() != self.width || mask.iter().any(|column| column.len() != self.height) {
+            return Err(RecoverableError::new(fmt::format(format_args!(
+                "Размеры маски местности не совпадают с размерами мира ({}, {})",
+                self.width,
                 self.height
-            );
-
-            if let PlantInCell::Plant(plant) = self.landscape[x_off][y_off].plant {
-                let plant = Self::get_agent_ref(plant);
+            ))));
+        }
 
-                if !plant.is_eaten() {
-                    plants += 1;
+        for (x, column) in mask.iter().enumerate() {
+            for (y, blocked) in column.iter().enumerate() {
+                let cell = &self.landscape[x][y];
+                if *blocked && (!matches!(cell.plant, PlantInCell::None) || !matches!(cell.animal, AnimalInCell::None)) {
+                    return Err(RecoverableError::new(AddAgentError::TakenCell((x, y)).to_string()));
                 }
             }
+        }
 
-            if let AnimalInCell::Animal(animal) = self.landscape[x_off][y_off].animal {
-                let animal = Self::get_agent_ref(animal);
-
-                if !animal.is_dead() {
-                    match animal.get_type() {
-                        AnimaType::Herbivore => {
-                            herbivores += 1;
-                        }
-                        AnimaType::Carnivore => {
-                            carnivores += 1;
-                        }
-                    }
-                }
+        for (x, column) in mask.iter().enumerate() {
+            for (y, blocked) in column.iter().enumerate() {
+                self.landscape[x][y].terrain = if *blocked { Terrain::Blocked } else { Terrain::Open };
             }
-
         }
 
-        (plants, herbivores, carnivores)
-    }
-
-    /// Реализует поворот животного на лево.
-    fn turn_left_animal_action(&mut self, animal: &mut dyn AnimalAlive, _x: usize, _y: usize) {
-        animal.turn_action(true);
+        Ok(())
     }
 
-    /// Реализует поворот животного на право.
-    fn turn_right_animal_action(&mut self, animal: &mut dyn AnimalAlive, _x: usize, _y: usize) {
-        animal.turn_action(false);
+    /// Непроходима ли ячейка.
+    ///
+    /// # Arguments
+    ///
+    /// * `x`, `y`: Координаты ячейки.
+    ///
+    /// returns: bool
+    pub fn is_blocked(&self, x: usize, y: usize) -> bool {
+        self.landscape[x][y].terrain == Terrain::Blocked
     }
 
-    /// Implements the move function.
+    /// Задает карту энергии роста растений (климатические зоны): растение в ячейке
+    /// [x][y] получает на каждой итерации энергию `map[x][y]`. Так можно создать,
+    /// например, плодородную полосу и пустыню.
     ///
     /// # Arguments
     ///
-    /// * `animal`: Изменяемая ссылка на животное.
-    /// * `x`: Положение животного по "x".
-    /// * `y`: Положение животного по "y".
+    /// * `map`: Карта энергии роста: `width` столбцов по `height` ячеек.
     ///
-    /// returns: ()
-    fn movement_animal_action(&mut self, animal: &mut dyn AnimalAlive, x: usize, y: usize) {
-        // Определим координаты новой точки местоположения животного.
-        let coords = match animal.get_direction() {
-            AnimalDirection::North => {
-                (x, Self::clip(y as isize - 1, self.height))
-            }
-            AnimalDirection::South => {
-                (x, Self::clip(y as isize + 1, self.height))
-            }
-            AnimalDirection::West => {
-                (Self::clip(x as isize - 1, self.width), y)
-            }
-            AnimalDirection::East => {
-                (Self::clip(x as isize + 1, self.width), y)
-            }
-        };
+    /// returns: Result<(), RecoverableError> - ошибка, если размеры карты не совпадают
+    /// с размерами мира или энергия роста в ячейке отрицательна (или не число).
+    pub fn set_growth_map(&mut self, map: Vec<Vec<Energy>>) -> Result<(), RecoverableError> {
+        if map.len() != self.width || map.iter().any(|column| column.len() != self.height) {
+            return Err(RecoverableError::new(fmt::format(format_args!(
+                "Размеры карты энергии роста не совпадают с размерами мира ({}, {})",
+                self.width,
+                self.height
+            ))));
+        }
 
-        // Проверить возможность движения.
+        for (x, column) in map.iter().enumerate() {
+            if let Some(y) = column.iter().position(|energy| energy.is_nan() || *energy < 0 as Energy) {
+                return Err(RecoverableError::new(fmt::format(format_args!(
+                    "Энергия роста в ячейке ({}, {}) должна быть неотрицательным числом: {}",
+                    x,
+                    y,
+                    column[y]
+                ))));
+            }
+        }
+
+        self.growth_map = map;
+
+        Ok(())
+    }
+
+    /// Задает карту энергии роста растений функцией координат ячейки (см.
+    /// `set_growth_map`).
+    ///
+    /// # Arguments
+    ///
+    /// * `growth`: Возвращает энергию роста в ячейке (x, y).
+    ///
+    /// returns: Result<(), RecoverableError> - ошибка, если энергия роста в ячейке
+    /// отрицательна (или не число).
+    pub fn set_growth_fn(&mut self, growth: impl Fn(usize, usize) -> Energy) -> Result<(), RecoverableError> {
+        let map = (0..self.width)
+            .map(|x| (0..self.height).map(|y| growth(x, y)).collect())
+            .collect();
+
+        self.set_growth_map(map)
+    }
+
+    /// Задает возраст, до которого животные каждого вида защищены от хищников: хищник
+    /// не выбирает такое животное целью и пропускает его. Новорожденные иначе часто
+    /// съедаются на той же итерации среди сородичей родителя.
+    ///
+    /// # Arguments
+    ///
+    /// * `herbivore`: Возраст защиты травоядных (итерации). 0 - защиты нет.
+    /// * `carnivore`: Возраст защиты хищников.
+    /// * `omnivore`: Возраст защиты всеядных.
+    ///
+    /// returns: ()
+    pub fn set_protection_age(&mut self, herbivore: usize, carnivore: usize, omnivore: usize) {
+        self.protection_age = (herbivore, carnivore, omnivore);
+    }
+
+    /// Включает контекст мира на входах мозга: фазу сезона роста растений (см.
+    /// `set_season`, без сезонности фаза равна нулю), долю ячеек мира с растениями и
+    /// долю возраста животного. Все значения нормированы в [0, 1]; без контекста
+    /// входы равны нулю.
+    ///
+    /// # Arguments
+    ///
+    /// * `context`: Возраст (итерации), которому соответствует доля возраста 1 (более
+    ///   старые животные тоже получают 1). None - контекста нет.
+    ///
+    /// returns: ()
+    pub fn set_brain_context(&mut self, context: Option<usize>) {
+        self.brain_context = context;
+    }
+
+    /// Включает сезонность роста растений. Энергия роста в ячейке (см.
+    /// `set_growth_map`) умножается на `1 + amplitude * sin(2π * фаза)`, где фаза -
+    /// доля прошедшего сезона: в первой половине сезона растения растут быстрее, во
+    /// второй - медленнее.
+    ///
+    /// # Arguments
+    ///
+    /// * `season`: Длина сезона (итерации) и амплитуда из [0, 1]. None или нулевая длина - энергия роста постоянна.
+    ///
+    /// returns: ()
+    pub fn set_season(&mut self, season: Option<(usize, f64)>) {
+        self.season = season
+            .filter(|&(length, _)| length > 0)
+            .map(|(length, amplitude)| (length, amplitude.clamp(0.0, 1.0)));
+    }
+
+    /// Фаза сезона роста растений на текущей итерации: доля прошедшего сезона из
+    /// [0, 1). Без сезонности фаза равна нулю.
+    fn season_phase(&self) -> f64 {
+        match self.season {
+            Some((length, _)) => (self.iteration % length) as f64 / length as f64,
+            None => 0.0,
+        }
+    }
+
+    /// Множитель энергии роста растений на текущей итерации (см. `set_season`).
+    fn season_factor(&self) -> f64 {
+        match self.season {
+            Some((_, amplitude)) => 1.0 + amplitude * (2.0 * std::f64::consts::PI * self.season_phase()).sin(),
+            None => 1.0,
+        }
+    }
+
+    /// Задает размер истории умерших животных: мир хранит записи о жизни (причина
+    /// смерти, возраст, поедание, потомки и т.д.) только последних умерших, поэтому
+    /// память не растет с продолжительностью прогона.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity`: Количество хранимых записей, 0 - записи не хранятся.
+    ///
+    /// returns: ()
+    pub fn set_postmortem_history(&mut self, capacity: usize) {
+        self.postmortems = if capacity > 0 { Some(RingBuffer::new(capacity)) } else { None };
+    }
+
+    /// Включает отправку записей о жизни умерших животных (`WorldEvent::Postmortem`)
+    /// в канал событий (см. `set_event_sink`) вслед за событием смерти.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled`: Отправлять записи.
+    ///
+    /// returns: ()
+    pub fn set_postmortem_events(&mut self, enabled: bool) {
+        self.postmortem_events = enabled;
+    }
+
+    /// Включает или выключает учет занятости ячеек: сколько итераций в каждой ячейке
+    /// было растение, травоядное, хищник и всеядное (см. `export_occupancy`).
+    /// Включение обнуляет счетчики.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled`: Учитывать ли занятость ячеек.
+    ///
+    /// returns: ()
+    pub fn set_occupancy_tracking(&mut self, enabled: bool) {
+        self.occupancy = if enabled {
+            let grid = vec![vec![0u32; self.height]; self.width];
+            Some((grid.clone(), grid.clone(), grid.clone(), grid))
+        } else {
+            None
+        };
+    }
+
+    /// Защищено ли животное от хищников по возрасту.
+    ///
+    /// # Arguments
+    ///
+    /// * `animal`: Животное.
+    ///
+    /// returns: bool
+    fn is_protected(&self, animal: &StoredAnimal) -> bool {
+        let protection_age = match animal.get_type() {
+            AnimaType::Herbivore => self.protection_age.0,
+            AnimaType::Carnivore => self.protection_age.1,
+            AnimaType::Omnivore => self.protection_age.2,
+        };
+
+        animal.get_age() < protection_age
+    }
+
+    /// Учитывает добычу, пропущенную хищником из-за защиты по возрасту.
+    ///
+    /// # Arguments
+    ///
+    /// * `animal_type`: Вид добычи.
+    ///
+    /// returns: ()
+    fn record_protected_skip(&mut self, animal_type: AnimaType) {
+        match animal_type {
+            AnimaType::Herbivore => self.protected_skips.0 += 1,
+            AnimaType::Carnivore => self.protected_skips.1 += 1,
+            AnimaType::Omnivore => self.protected_skips.2 += 1,
+        }
+    }
+
+    /// Задает размер окна (в итерациях) скользящих средних эффективности поедания.
+    /// Накопленные значения сбрасываются.
+    ///
+    /// # Arguments
+    ///
+    /// * `window`: Размер окна.
+    ///
+    /// returns: ()
+    pub fn set_hunt_stats_window(&mut self, window: usize) {
+        self.success_rate = (RollingMean::new(window), RollingMean::new(window), RollingMean::new(window));
+        self.energy_per_attempt = (RollingMean::new(window), RollingMean::new(window), RollingMean::new(window));
+    }
+
+    /// Задает количество итераций, за которые подводится энергетический баланс видов
+    /// (см. `get_statistics`). Накопленные балансы сбрасываются.
+    ///
+    /// # Arguments
+    ///
+    /// * `window`: Количество итераций, не может быть меньше единицы.
+    ///
+    /// returns: ()
+    pub fn set_energy_budget_window(&mut self, window: usize) {
+        self.energy_budget_window = window.max(1);
+        self.energy_budgets.clear();
+    }
+
+    /// Задает канал, в который мир отправляет события: рождения, смерти, поедание,
+    /// прорастание растений и ручные изменения энергии (см. `WorldEvent`). Если получатель закрыт, события
+    /// больше не отправляются. По умолчанию канала нет.
+    ///
+    /// # Arguments
+    ///
+    /// * `sink`: Канал для событий. None - события не отправляются.
+    ///
+    /// returns: ()
+    pub fn set_event_sink(&mut self, sink: Option<Sender<WorldEvent>>) {
+        self.event_sink = sink;
+    }
+
+    /// Задает запрос отображения: какие слои карты собирать и на каких итерациях.
+    /// Карта собирается только из запрошенных слоев и только на каждой
+    /// `every_nth_tick` итерации (см. `is_view_ready`). По умолчанию собираются
+    /// все слои на каждой итерации.
+    ///
+    /// # Arguments
+    ///
+    /// * `request`: Запрос отображения.
+    ///
+    /// returns: ()
+    pub fn set_frame_request(&mut self, request: FrameRequest) {
+        self.frame_request = request;
+    }
+
+    /// Включает строгий режим поедания, в котором животное может съесть только агента
+    /// в ячейке прямо перед собой. По умолчанию животное ест в области близости.
+    ///
+    /// # Arguments
+    ///
+    /// * `strict`: Включить строгий режим.
+    ///
+    /// returns: ()
+    pub fn set_strict_eating(&mut self, strict: bool) {
+        self.strict_eating = strict;
+    }
+
+    /// Задает топологию мира. По оси со склейкой краев агент, вышедший за край сетки,
+    /// появляется с другой стороны; по оси без склейки край - стена: животное не может
+    /// сделать шаг за нее и ничего за ней не видит. По умолчанию мир является тором.
+    ///
+    /// # Arguments
+    ///
+    /// * `topology`: Топология мира.
+    ///
+    /// returns: ()
+    pub fn set_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+    }
+
+    /// Задает период сверки счетчиков статистики с их полным пересчетом по сетке мира.
+    /// Сверка дорогая, но позволяет обнаружить расхождение инкрементальных счетчиков
+    /// в долгих прогонах.
+    ///
+    /// # Arguments
+    ///
+    /// * `period`: Период в итерациях, 0 - не выполнять сверку.
+    ///
+    /// returns: ()
+    pub fn set_stats_validation_period(&mut self, period: usize) {
+        self.stats_validation_period = period;
+    }
+
+    /// Возвращает и забывает последнее расхождение счетчиков статистики, найденное
+    /// периодической сверкой (см. `set_stats_validation_period`).
+    ///
+    /// returns: Option<(usize, String)> - итерация сверки и описание расхождения.
+    pub fn take_stats_mismatch(&mut self) -> Option<(usize, String)> {
+        self.stats_mismatch.take()
+    }
+
+    /// Задает замер генетического разнообразия видов: каждые `period` итераций из
+    /// живых животных вида случайно выбирается не больше `sample` животных и
+    /// вычисляется среднее попарное расстояние между геномами их мозгов. Сложность
+    /// замера - O(sample²).
+    ///
+    /// # Arguments
+    ///
+    /// * `period`: Период в итерациях, 0 - не выполнять замер.
+    /// * `sample`: Максимальный размер выборки животных одного вида.
+    ///
+    /// returns: ()
+    pub fn set_diversity_sampling(&mut self, period: usize, sample: usize) {
+        self.diversity_period = period;
+        self.diversity_sample = sample;
+    }
+
+    /// Обрезает координаты, что-бы обеспечить склейку краев мира по оси.
+    ///
+    /// # Arguments
+    ///
+    /// * `coord`: Координата местоположения (x или y). Может быть отрицательной и
+    ///   выходить за границы мира больше, чем на размер мира.
+    /// * `max_size`: Максимальный размер мира по соответствующей координате.
+    ///
+    /// Returns: Координата в границах мира.
+    fn clip(coord: isize, max_size: usize) -> usize {
+        // Евклидов остаток от деления всегда неотрицателен, поэтому координата
+        // попадает в границы мира, на сколько бы размеров мира она ни выходила за них.
+        coord.rem_euclid(max_size as isize) as usize
+    }
+
+    /// Возвращает координаты ячейки, смещенной относительно заданной точки, с учетом
+    /// топологии мира: по оси со склейкой координата переносится на другой край, по
+    /// оси без склейки смещение за край мира упирается в стену.
+    ///
+    /// # Arguments
+    ///
+    /// * `x`, `y`: Координаты точки.
+    /// * `dx`, `dy`: Смещение.
+    ///
+    /// returns: Option<(usize, usize)> - None, если ячейка находится за стеной.
+    fn shift(&self, x: usize, y: usize, dx: isize, dy: isize) -> Option<(usize, usize)> {
+        let axis = |coord: isize, max_size: usize, wraps: bool| {
+            if wraps {
+                Some(Self::clip(coord, max_size))
+            } else if coord < 0 || coord >= max_size as isize {
+                None
+            } else {
+                Some(coord as usize)
+            }
+        };
+
+        Some((
+            axis(x as isize + dx, self.width, self.topology.wraps_x())?,
+            axis(y as isize + dy, self.height, self.topology.wraps_y())?,
+        ))
+    }
+
+    /// Возвращает растение в ячейке. None - растения нет (или оно временно извлечено
+    /// из хранилища, пока совершает свой ход).
+    ///
+    /// # Arguments
+    ///
+    /// * `x`, `y`: Координаты ячейки.
+    ///
+    /// returns: Option<&dyn PlantAlive>
+    fn plant_at(&self, x: usize, y: usize) -> Option<&dyn PlantAlive> {
+        match self.landscape[x][y].plant {
+            PlantInCell::Plant(handle) => self.plants.get(handle).map(|plant| plant.as_ref()),
+            PlantInCell::None => None,
+        }
+    }
+
+    /// Возвращает животное в ячейке. None - животного нет (или оно временно извлечено
+    /// из хранилища, пока совершает свой ход).
+    ///
+    /// # Arguments
+    ///
+    /// * `x`, `y`: Координаты ячейки.
+    ///
+    /// returns: Option<&StoredAnimal>
+    fn animal_at(&self, x: usize, y: usize) -> Option<&StoredAnimal> {
+        match self.landscape[x][y].animal {
+            AnimalInCell::Animal(handle) => self.animals.get(handle),
+            AnimalInCell::None => None,
+        }
+    }
+
+    /// Возвращает содержимое ячейки: местность, растение, животное, падаль и
+    /// плодородие почвы.
+    ///
+    /// # Arguments
+    ///
+    /// * `x`, `y`: Координаты ячейки.
+    ///
+    /// returns: Option<CellInfo> - None, если координаты за пределами мира.
+    pub fn get_cell_info(&self, x: usize, y: usize) -> Option<CellInfo> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+
+        let cell = &self.landscape[x][y];
+
+        Some(CellInfo {
+            terrain: cell.terrain,
+            plant: self.plant_at(x, y).map(PlantInfo::new),
+            animal: self.animal_at(x, y).map(|animal| AnimalInfo::new(animal.as_animal())),
+            carrion: cell.carrion,
+            corpse: cell.corpse,
+            fertility: cell.fertility,
+        })
+    }
+
+    /// Перебирает живых животных мира вместе с их координатами. Животные
+    /// перебираются по ячейкам, мозги не копируются.
+    ///
+    /// returns: impl Iterator<Item = (usize, usize, AnimalInfo)>
+    pub fn animals_iter(&self) -> impl Iterator<Item = (usize, usize, AnimalInfo)> + '_ {
+        self.cells()
+            .filter_map(|(x, y)| match self.animal_at(x, y) {
+                Some(animal) if !animal.is_dead() => Some((x, y, AnimalInfo::new(animal.as_animal()))),
+                _ => None,
+            })
+    }
+
+    /// Перебирает растения мира вместе с их координатами, в том числе съеденные
+    /// растения, которые ждут отрастания.
+    ///
+    /// returns: impl Iterator<Item = (usize, usize, PlantInfo)>
+    pub fn plants_iter(&self) -> impl Iterator<Item = (usize, usize, PlantInfo)> + '_ {
+        self.cells()
+            .filter_map(|(x, y)| self.plant_at(x, y).map(|plant| (x, y, PlantInfo::new(plant))))
+    }
+
+    /// Перебирает координаты всех ячеек мира.
+    ///
+    /// returns: impl Iterator<Item = (usize, usize)>
+    fn cells(&self) -> impl Iterator<Item = (usize, usize)> {
+        let height = self.height;
+        (0..self.width).flat_map(move |x| (0..height).map(move |y| (x, y)))
+    }
+
+    /// Возвращает топологию мира.
+    pub fn get_topology(&self) -> Topology {
+        self.topology
+    }
+
+    // /// Возвращает ширину мира.
+    // pub fn get_width(&self) -> usize {
+    //     self.width
+    // }
+    //
+    // /// Возвращает высоту мира.
+    // pub fn get_height(&self) -> usize {
+    //     self.height
+    // }
+
+    /// Возвращает состояние ячейки, т.е. информацию, которую можно отобразить
+    /// для данной ячейки.
+    ///
+    /// # Arguments
+    ///
+    /// * `x`: Координата "x" местоположения.
+    /// * `y`: Координата "y" местоположения.
+    ///
+    /// returns: Vec<CellStuff, Global>
+    pub fn get_view_state(&self) -> Map {
+        self.view_state.clone()
+    }
+
+    /// Действующие настройки случайных блоков загруженных сценариев (по строке на
+    /// блок, см. `load_scenario`) для манифеста прогона.
+    pub fn get_scenario_blocks(&self) -> &[String] {
+        &self.scenario_blocks
+    }
+
+    /// Зерно генератора случайных чисел мира (заданное или выбранное случайно).
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Генератор случайных чисел мира. Агенты, созданные вне мира из этого генератора,
+    /// воспроизводятся вместе с прогоном.
+    pub fn rng(&mut self) -> &mut dyn RngCore {
+        &mut self.rng
+    }
+
+    /// Собрана ли карта на последней итерации (итерация входит в запрошенную
+    /// отображением частоту кадров).
+    pub fn is_view_ready(&self) -> bool {
+        self.view_ready
+    }
+
+    /// Возвращает события в ячейках (убийства, рождения), произошедшие с момента
+    /// предыдущей собранной карты. Отображение показывает их кратковременными эффектами.
+    pub fn get_transient_events(&self) -> Vec<CellEvent> {
+        self.transient_events.clone()
+    }
+
+    /// Начинает отслеживать династию животного в заданной ячейке: само животное и
+    /// всех его потомков, рожденных с этого момента. Ранее отслеживаемая династия
+    /// забывается.
+    ///
+    /// # Arguments
+    ///
+    /// * `x`: Координата "x" ячейки.
+    /// * `y`: Координата "y" ячейки.
+    ///
+    /// returns: Result<u64, RecoverableError> - идентификатор животного или ошибка, если
+    /// в ячейке нет живого животного.
+    pub fn follow_dynasty(&mut self, x: usize, y: usize) -> Result<u64, RecoverableError> {
+        if x >= self.width || y >= self.height {
+            return Err(RecoverableError::new(fmt::format(format_args!(
+                "Ячейка ({}, {}) вне мира", x, y
+            ))));
+        }
+
+        match self.animal_at(x, y) {
+            Some(animal) if !animal.is_dead() => {
+                let id = animal.get_id();
+
+                self.dynasty.clear();
+                self.dynasty.insert(id);
+
+                Ok(id)
+            }
+            _ => Err(RecoverableError::new(fmt::format(format_args!(
+                "В ячейке ({}, {}) нет живого животного", x, y
+            )))),
+        }
+    }
+
+    /// Включает обнуление энергии агентов, которая перестала быть конечным числом
+    /// (NaN или бесконечность). Такая энергия ломает сравнения: животное с энергией
+    /// NaN никогда не умирает. В отладочной сборке агенты сами проверяют энергию при
+    /// каждом изменении и останавливают программу.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled`: Обнулять энергию (о каждом агенте сообщается один раз).
+    ///
+    /// returns: ()
+    pub fn set_energy_sanitizing(&mut self, enabled: bool) {
+        self.sanitize_energy = enabled;
+    }
+
+    /// Обнуляет энергию растения, если она не является конечным числом.
+    ///
+    /// # Arguments
+    ///
+    /// * `plant`: Растение.
+    /// * `handle`: Дескриптор растения (о растении сообщается один раз).
+    /// * `x`: "x" координата растения.
+    /// * `y`: "y" координата растения.
+    ///
+    /// returns: ()
+    fn sanitize_plant_energy(&mut self, plant: &mut dyn PlantAlive, handle: Handle, x: usize, y: usize) {
+        let energy = plant.get_energy();
+        if energy.is_finite() {
+            return;
+        }
+
+        plant.set_energy(0 as Energy);
+
+        if self.sanitized_agents.0.insert(handle) {
+            eprintln!(
+                "Итерация {}: энергия растения в ячейке ({}, {}) равна {}, обнулена",
+                self.iteration, x, y, energy
+            );
+        }
+    }
+
+    /// Обнуляет энергию животного, если она не является конечным числом.
+    ///
+    /// # Arguments
+    ///
+    /// * `animal`: Животное.
+    /// * `handle`: Дескриптор животного (о животном сообщается один раз).
+    ///
+    /// returns: ()
+    fn sanitize_animal_energy(&mut self, animal: &mut StoredAnimal, handle: Handle) {
+        let energy = animal.get_energy();
+        if energy.is_finite() {
+            return;
+        }
+
+        animal.set_energy(0 as Energy);
+
+        if self.sanitized_agents.1.insert(handle) {
+            eprintln!(
+                "Итерация {}: энергия животного {} равна {}, обнулена",
+                self.iteration, animal.get_id(), energy
+            );
+        }
+    }
+
+    /// Включает запись решений мозга животного: на каждом его ходу сохраняются входы
+    /// и выходы мозга и выбранное действие. Записывается только одно животное.
+    ///
+    /// # Arguments
+    ///
+    /// * `id`: Идентификатор животного. None - выключить запись.
+    ///
+    /// returns: ()
+    pub fn set_traced_animal(&mut self, id: Option<u64>) {
+        self.traced_animal = None;
+        self.traced_position = id.and_then(|id| self.animal_cell(id));
+        self.traced_animal = id;
+    }
+
+    /// Забирает следы решений, записанные с прошлого вызова.
+    ///
+    /// returns: Vec<ActionTrace>
+    pub fn take_action_traces(&mut self) -> Vec<ActionTrace> {
+        mem::take(&mut self.action_traces)
+    }
+
+    /// Задает частоту мутаций мозга потомков каждого вида: среднее количество генов,
+    /// изменяемых при размножении. По умолчанию изменяется один ген.
+    ///
+    /// # Arguments
+    ///
+    /// * `herbivore`: Частота мутаций травоядных.
+    /// * `carnivore`: Частота мутаций хищников.
+    /// * `omnivore`: Частота мутаций всеядных.
+    ///
+    /// returns: ()
+    pub fn set_mutation_rate(&mut self, herbivore: f64, carnivore: f64, omnivore: f64) {
+        self.mutation_rate = (herbivore, carnivore, omnivore);
+    }
+
+    /// Возвращает частоту мутаций: травоядные, хищники, всеядные.
+    pub fn get_mutation_rate(&self) -> (f64, f64, f64) {
+        self.mutation_rate
+    }
+
+    /// Задает регулятор частоты мутаций. Регулятор срабатывает после каждого замера
+    /// генетического разнообразия (см. `set_diversity_sampling`), новая частота
+    /// действует на потомков, рожденных после этого. По умолчанию регулятора нет.
+    ///
+    /// # Arguments
+    ///
+    /// * `controller`: Регулятор. None - частота мутаций постоянна.
+    ///
+    /// returns: ()
+    pub fn set_mutation_controller(&mut self, controller: Option<MutationController>) {
+        self.mutation_controller = controller;
+    }
+
+    /// Забирает изменения частоты мутаций, сделанные регулятором с прошлого вызова.
+    pub fn take_mutation_adjustments(&mut self) -> Vec<MutationAdjustment> {
+        mem::take(&mut self.mutation_adjustments)
+    }
+
+    /// Изменяет энергию живого животного "вручную" (для отладки). Энергия
+    /// ограничивается диапазоном [0, максимальная энергия]; животное с нулевой
+    /// энергией умирает по обычным правилам в конце итерации. Каждое изменение
+    /// учитывается в статистике и отправляется в канал событий
+    /// (`WorldEvent::EnergyAdjusted`), чтобы такие прогоны можно было исключить из анализа.
+    ///
+    /// # Arguments
+    ///
+    /// * `agent_id`: Идентификатор животного.
+    /// * `delta`: Изменение энергии.
+    ///
+    /// returns: Result<Energy, RecoverableError> - новая энергия животного или ошибка,
+    /// если живого животного с таким идентификатором нет.
+    pub fn adjust_energy(&mut self, agent_id: u64, delta: Energy) -> Result<Energy, RecoverableError> {
+        let handle = self.find_animal(agent_id).ok_or_else(|| {
+            RecoverableError::new(fmt::format(format_args!(
+                "Животного с идентификатором {} нет в мире", agent_id
+            )))
+        })?;
+
+        self.energy_adjustments += 1;
+
+        let animal = self.animals.get_mut(handle)
+            .expect("Внутренняя ошибка программы: дескриптор животного в ячейке устарел");
+        let energy = animal.adjust_energy(delta);
+
+        // Умершее животное не делает хода: как и убитая жертва, оно только
+        // убирается из мира в конце итерации.
+        if animal.is_dead() {
+            animal.mark_processed(self.iteration as u64);
+        }
+
+        self.emit(|tick| WorldEvent::EnergyAdjusted { tick, agent_id, delta, energy });
+
+        Ok(energy)
+    }
+
+    /// Ищет живое животное по идентификатору.
+    ///
+    /// # Arguments
+    ///
+    /// * `agent_id`: Идентификатор животного.
+    ///
+    /// returns: Option<Handle>
+    fn find_animal(&self, agent_id: u64) -> Option<Handle> {
+        let (x, y) = self.animal_cell(agent_id)?;
+
+        match self.landscape[x][y].animal {
+            AnimalInCell::Animal(handle) => Some(handle),
+            AnimalInCell::None => None,
+        }
+    }
+
+    /// Ищет ячейку живого животного по идентификатору. Ячейка животного, решения
+    /// которого записываются, известна; остальные животные ищутся по всей сетке.
+    ///
+    /// # Arguments
+    ///
+    /// * `agent_id`: Идентификатор животного.
+    ///
+    /// returns: Option<(usize, usize)>
+    fn animal_cell(&self, agent_id: u64) -> Option<(usize, usize)> {
+        let is_agent = |(x, y): (usize, usize)| self.animal_at(x, y).is_some_and(|animal| animal.get_id() == agent_id);
+
+        if self.traced_animal == Some(agent_id) {
+            self.traced_position.filter(|&cell| is_agent(cell))
+        } else {
+            self.cells().find(|&cell| is_agent(cell))
+        }
+    }
+
+    /// Возвращает снимок статистики мира на текущий момент.
+    pub fn get_world_stats(&self) -> WorldStats {
+        WorldStats {
+            iteration: self.iteration,
+            plant_count: self.plant_count,
+            animal_count: self.animal_count,
+            deferred_births: self.deferred_births,
+            blocked_births: self.blocked_births,
+            policy_vetoes: self.policy_vetoes,
+            protected_skips: self.protected_skips,
+            pending_births: self.pending_births.len(),
+            failed_births: self.failed_births,
+            carrion_energy: self.carrion_energy,
+            genetic_diversity: self.genetic_diversity,
+            seeds: self.seeds,
+            plant_deaths: self.plant_deaths,
+            energy_adjustments: self.energy_adjustments,
+            fitness: self.fitness.name(),
+            max_generation: self.animal_max_generation,
+            hunt: (
+                HuntStats {
+                    attempts: self.eat_attempts.0,
+                    successes: self.eat_successes.0,
+                    energy: self.eaten_energy.0,
+                    success_rate: self.success_rate.0.mean(),
+                    energy_per_attempt: self.energy_per_attempt.0.mean(),
+                    attack_attempts: self.attack_attempts.0,
+                    kills: self.kills.0,
+                },
+                HuntStats {
+                    attempts: self.eat_attempts.1,
+                    successes: self.eat_successes.1,
+                    energy: self.eaten_energy.1,
+                    success_rate: self.success_rate.1.mean(),
+                    energy_per_attempt: self.energy_per_attempt.1.mean(),
+                    attack_attempts: self.attack_attempts.1,
+                    kills: self.kills.1,
+                },
+                HuntStats {
+                    attempts: self.eat_attempts.2,
+                    successes: self.eat_successes.2,
+                    energy: self.eaten_energy.2,
+                    success_rate: self.success_rate.2.mean(),
+                    energy_per_attempt: self.energy_per_attempt.2.mean(),
+                    attack_attempts: self.attack_attempts.2,
+                    kills: self.kills.2,
+                },
+            ),
+            energy_budget: self.energy_budget,
+        }
+    }
+
+    /// Возвращает сводную статистику мира с начала прогона: численность видов,
+    /// рождения и смерти, максимальное поколение и возраст самых старых животных.
+    pub fn get_statistics(&self) -> LandscapeStats {
+        let mut oldest_alive_age: (Option<usize>, Option<usize>, Option<usize>) = (None, None, None);
+        for animal in self.animals.iter().filter(|animal| !animal.is_dead()) {
+            let oldest = match animal.get_type() {
+                AnimaType::Herbivore => &mut oldest_alive_age.0,
+                AnimaType::Carnivore => &mut oldest_alive_age.1,
+                AnimaType::Omnivore => &mut oldest_alive_age.2,
+            };
+            *oldest = Some(oldest.map_or(animal.get_age(), |age| age.max(animal.get_age())));
+        }
+
+        LandscapeStats {
+            tick: self.iteration,
+            plant_count: self.plant_count,
+            animal_count: self.animal_count,
+            births: self.animal_reproductions,
+            deaths: self.animal_deaths,
+            old_age_deaths: self.old_age_deaths,
+            max_generation: self.animal_max_generation,
+            oldest_alive_age,
+            oldest_ever_age: (
+                self.oldest_death_age.0.max(oldest_alive_age.0.unwrap_or(0)),
+                self.oldest_death_age.1.max(oldest_alive_age.1.unwrap_or(0)),
+                self.oldest_death_age.2.max(oldest_alive_age.2.unwrap_or(0)),
+            ),
+            energy_budget: self.get_energy_budget(),
+        }
+    }
+
+    /// Возвращает энергетический баланс видов за последние итерации (не больше
+    /// заданного окна, см. `set_energy_budget_window`): травоядные, хищники, всеядные.
+    pub fn get_energy_budget(&self) -> (EnergyBudget, EnergyBudget, EnergyBudget) {
+        let mut total: (EnergyBudget, EnergyBudget, EnergyBudget) = Default::default();
+        for budget in &self.energy_budgets {
+            total.0.add(&budget.0);
+            total.1.add(&budget.1);
+            total.2.add(&budget.2);
+        }
+
+        total
+    }
+
+    /// Возвращает энергию, которую животные получили от поедания на последней
+    /// итерации: травоядные, хищники, всеядные.
+    pub fn get_eaten_energy(&self) -> (Energy, Energy, Energy) {
+        self.eaten_energy
+    }
+
+    /// Возвращает текущее состояние мира в виде редактируемого сценария: по строке
+    /// на каждого агента. Сохраняется только то, что нужно для воссоздания мира
+    /// (положение, вид, энергия, направление, мозг); признаки текущей итерации
+    /// (съедено, сделан ход, неудачное размножение, память о еде) намеренно
+    /// отбрасываются, мертвые животные не сохраняются. Сценарий загружается методом
+    /// `load_scenario`.
+    ///
+    /// Формат строк:
+    /// `size <ширина> <высота>`,
+    /// `plant <x> <y> <энергия>`,
+    /// `herbivore|carnivore|omnivore <id> <x> <y> <энергия> <north|south|west|east> <файл генома|->`,
+    /// `pending <herbivore|carnivore|omnivore> <id родителя> <итераций до рождения> <энергия> <направление> <файл генома|->`
+    /// (вынашиваемый потомок; строка следует за строкой родителя).
+    ///
+    /// returns: Scenario
+    pub fn to_scenario(&self) -> Scenario {
+        let mut text = format!("# Сценарий мира, итерация {}\n", self.iteration);
+        text.push_str(&format!("size {} {}\n", self.width, self.height));
+
+        let mut genomes = Vec::new();
+
+        for x in 0..self.width {
+            for y in 0..self.height {
+                if let Some(plant) = self.plant_at(x, y) {
+                    text.push_str(&format!("plant {} {} {}\n", x, y, plant.get_energy()));
+                }
+
+                if let Some(animal) = self.animal_at(x, y) {
+                    if animal.is_dead() {
+                        continue;
+                    }
+
+                    let species = Self::species_name(animal.get_type());
+                    let direction = Self::direction_name(animal.get_direction());
+
+                    let genome = match animal.get_genome_json() {
+                        Some(json) => {
+                            let file = format!("genome_{}.json", animal.get_id());
+                            genomes.push((file.clone(), json));
+                            file
+                        }
+                        None => "-".to_string(),
+                    };
+
+                    text.push_str(&format!(
+                        "{} {} {} {} {} {} {}\n", species, animal.get_id(), x, y, animal.get_energy(), direction, genome
+                    ));
+                }
+            }
+        }
+
+        // Идентификатор потомку еще не присвоен: файл генома называется по родителю.
+        for (index, birth) in self.pending_births.iter().enumerate() {
+            let genome = match birth.child.get_genome_json() {
+                Some(json) => {
+                    let file = format!("genome_pending_{}_{}.json", birth.parent_id, index);
+                    genomes.push((file.clone(), json));
+                    file
+                }
+                None => "-".to_string(),
+            };
+
+            text.push_str(&format!(
+                "pending {} {} {} {} {} {}\n",
+                Self::species_name(birth.child.get_type()),
+                birth.parent_id,
+                birth.hatch_at.saturating_sub(self.iteration),
+                birth.child.get_energy(),
+                Self::direction_name(birth.child.get_direction()),
+                genome
+            ));
+        }
+
+        Scenario { text, genomes }
+    }
+
+    /// Возвращает контрольную точку мира: сценарий (см. `to_scenario`) и состояние,
+    /// от которого зависит продолжение прогона. Мир тех же размеров и настроек,
+    /// заселенный из контрольной точки методом `load_scenario`, продолжает прогон
+    /// так же, как продолжил бы исходный мир: совпадают агенты, статистика
+    /// (`WorldStats`) и генераторы случайных чисел мира и мозгов животных.
+    ///
+    /// Не сохраняются статистика приспособленности и лучшие животные, записи о жизни
+    /// умерших, занятость ячеек, отложенное заселение видов и отслеживание животных.
+    ///
+    /// Строки, которые добавляются к сценарию:
+    /// `checkpoint <итерация> <следующий идентификатор животного> <зерно мира>` (вторая
+    /// строка; животные сохраняют идентификаторы из сценария),
+    /// `animal_state <x> <y> <возраст> <поколение> <неудачное размножение 0|1> <запрет размножения 0|1> <еда x,y|-> <генератор мозга|->`,
+    /// `plant_state <x> <y> <возраст> <итераций до возобновления роста> <запрет размножения 0|1>`,
+    /// `cell <x> <y> <падаль> <итерация падали> <туша> <итерация туши> <плодородие>`,
+    /// `order x|y <порядок обхода>`,
+    /// `counters <размножения> <смерти> <смерти от старости> <наибольший возраст умершего> <наибольшее поколение> <ручные изменения энергии>`
+    /// (по три числа, кроме последнего),
+    /// `mutation_rate <травоядные> <хищники> <всеядные>`,
+    /// `diversity <травоядные|-> <хищники|-> <всеядные|->`,
+    /// `hunt <вид> success_rate|energy_per_attempt <сумма> <значения окна>`,
+    /// `budget <полученная> <потраченная> <переданная потомкам энергия>` (по три числа на вид, по строке на итерацию окна),
+    /// `rng <генератор мира>` (восстанавливается после загрузки агентов).
+    ///
+    /// returns: Scenario
+    pub fn to_checkpoint(&self) -> Scenario {
+        let scenario = self.to_scenario();
+        let mut lines = scenario.text.lines();
+        let mut text = String::new();
+        for line in lines.by_ref().take(2) {
+            text.push_str(line);
+            text.push('\n');
+        }
+        text.push_str(&format!("checkpoint {} {} {}\n", self.iteration, self.next_animal_id, self.seed));
+        for line in lines {
+            text.push_str(line);
+            text.push('\n');
+        }
+
+        let flag = |value: bool| if value { 1 } else { 0 };
+        for (x, y) in self.cells() {
+            if let Some(animal) = self.animal_at(x, y).filter(|animal| !animal.is_dead()) {
+                let state = animal.get_state();
+                text.push_str(&format!(
+                    "animal_state {} {} {} {} {} {} {} {}\n",
+                    x,
+                    y,
+                    state.age,
+                    state.generation,
+                    flag(state.reproduce_blocked),
+                    flag(state.no_repro),
+                    animal.recall_food().map_or("-".to_string(), |(x, y)| format!("{},{}", x, y)),
+                    state.brain_rng.map_or("-".to_string(), |rng| rng.to_string())
+                ));
+            }
+
+            if let Some(plant) = self.plant_at(x, y) {
+                let state = plant.get_state();
+                text.push_str(&format!(
+                    "plant_state {} {} {} {} {}\n", x, y, state.age, state.regrowth_cooldown, flag(state.no_repro)
+                ));
+            }
+
+            let cell = &self.landscape[x][y];
+            if cell.carrion != 0 as Energy || cell.corpse != 0 as Energy || cell.fertility != 0 as Energy {
+                text.push_str(&format!(
+                    "cell {} {} {} {} {} {} {}\n",
+                    x, y, cell.carrion, cell.carrion_since, cell.corpse, cell.corpse_since, cell.fertility
+                ));
+            }
+        }
+
+        let join = |values: &[usize]| values.iter().map(usize::to_string).collect::<Vec<String>>().join(" ");
+        text.push_str(&format!("order x {}\n", join(&self.shuffle_width)));
+        text.push_str(&format!("order y {}\n", join(&self.shuffle_height)));
+
+        let triples = [
+            self.animal_reproductions,
+            self.animal_deaths,
+            self.old_age_deaths,
+            self.oldest_death_age,
+            self.animal_max_generation,
+            self.animal_arrivals,
+        ];
+        let mut counters: Vec<usize> = triples.iter().flat_map(|&(h, c, o)| [h, c, o]).collect();
+        counters.push(self.energy_adjustments);
+        text.push_str(&format!("counters {}\n", join(&counters)));
+
+        text.push_str(&format!(
+            "mutation_rate {} {} {}\n", self.mutation_rate.0, self.mutation_rate.1, self.mutation_rate.2
+        ));
+
+        let diversity = |value: Option<f64>| value.map_or("-".to_string(), |value| value.to_string());
+        text.push_str(&format!(
+            "diversity {} {} {}\n",
+            diversity(self.genetic_diversity.0),
+            diversity(self.genetic_diversity.1),
+            diversity(self.genetic_diversity.2)
+        ));
+
+        let hunt = [
+            (AnimaType::Herbivore, &self.success_rate.0, &self.energy_per_attempt.0),
+            (AnimaType::Carnivore, &self.success_rate.1, &self.energy_per_attempt.1),
+            (AnimaType::Omnivore, &self.success_rate.2, &self.energy_per_attempt.2),
+        ];
+        for (animal_type, success_rate, energy_per_attempt) in hunt {
+            for (name, mean) in [("success_rate", success_rate), ("energy_per_attempt", energy_per_attempt)] {
+                let (values, sum) = mean.state();
+                let values: Vec<String> = values.iter().map(f64::to_string).collect();
+                text.push_str(&format!(
+                    "hunt {} {} {} {}\n", Self::species_name(animal_type), name, sum, values.join(" ")
+                ));
+            }
+        }
+
+        for budgets in &self.energy_budgets {
+            text.push_str("budget");
+            for budget in [&budgets.0, &budgets.1, &budgets.2] {
+                text.push_str(&format!(" {} {} {}", budget.gained, budget.spent, budget.offspring));
+            }
+            text.push('\n');
+        }
+
+        text.push_str(&format!("rng {}\n", RngState::of(&self.rng)));
+
+        Scenario { text, genomes: scenario.genomes }
+    }
+
+    /// Заселяет мир агентами сценария, сохраненного методом `to_scenario`. Мир должен
+    /// быть того же размера; животные получают новые идентификаторы, вынашиваемые
+    /// потомки - новых (загруженных) родителей. Строки, начинающиеся с `#`, пропускаются.
+    /// Контрольная точка (см. `to_checkpoint`) загружается так же, но в новый мир:
+    /// восстанавливаются номер итерации, идентификаторы и состояние агентов, статистика
+    /// и генератор случайных чисел мира.
+    ///
+    /// Кроме строк `to_scenario`, сценарий может заселять мир случайными блоками:
+    /// `random <вид> <количество> <энергия> <направление> <мозг>`, где энергия - `full`
+    /// (с которой животное создано), число или диапазон `<от>..<до>`; направление -
+    /// `north|south|west|east`, `random` (равновероятно) или веса `<с>,<ю>,<з>,<в>`;
+    /// мозг - `random`, `genome:<файл генома>` или `champions:<файл лучших животных>`.
+    /// Животные блока размещаются равномерно в свободных ячейках генератором мира: с тем
+    /// же зерном сценарий заселяет мир так же. Файлы мозгов проверяются до размещения
+    /// блока, действующие настройки блоков доступны через `get_scenario_blocks`.
+    ///
+    /// # Arguments
+    ///
+    /// * `text`: Текст сценария.
+    /// * `new_plant`: Создает новое растение.
+    /// * `new_animal`: Создает животное сценария (см. `ScenarioAnimal`).
+    ///
+    /// returns: Result<(), RecoverableError> - ошибка с номером строки сценария.
+    pub fn load_scenario(
+        &mut self,
+        text: &str,
+        new_plant: &mut dyn FnMut() -> Box<dyn PlantAlive>,
+        new_animal: &mut ScenarioAnimal,
+    ) -> Result<(), RecoverableError> {
+        // Ячейки загруженных животных по их идентификаторам в сценарии.
+        let mut loaded: BTreeMap<u64, (usize, usize)> = BTreeMap::new();
+        // Контрольная точка: следующий идентификатор животного (животные сохраняют
+        // идентификаторы) и состояние генератора мира, которое восстанавливается после
+        // загрузки агентов (создание животных расходует генератор).
+        let mut next_animal_id: Option<u64> = None;
+        let mut rng_state: Option<RngState> = None;
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let error = |message: String| RecoverableError::new(fmt::format(format_args!(
+                "Сценарий, строка {}: {}", index + 1, message
+            )));
+            let fields: Vec<&str> = line.split_whitespace().collect();
+
+            match fields.as_slice() {
+                ["size", width, height] => {
+                    let width: usize = Self::scenario_field(width).map_err(error)?;
+                    let height: usize = Self::scenario_field(height).map_err(error)?;
+                    let size = (width, height);
+                    if size != (self.width, self.height) {
+                        return Err(error(format!(
+                            "размер сценария {}x{} не совпадает с размером мира {}x{}", size.0, size.1, self.width, self.height
+                        )));
+                    }
+                }
+                ["plant", x, y, energy] => {
+                    let mut plant = new_plant();
+                    plant.set_energy(Self::scenario_field(energy).map_err(error)?);
+
+                    let (x, y) = (Self::scenario_field(x).map_err(error)?, Self::scenario_field(y).map_err(error)?);
+                    self.add_plant(x, y, plant).map_err(|e| error(e.to_string()))?;
+                }
+                ["pending", species, parent_id, ticks, energy, direction, genome] => {
+                    let parent_id: u64 = Self::scenario_field(parent_id).map_err(error)?;
+                    let (x, y) = *loaded.get(&parent_id)
+                        .ok_or_else(|| error(format!("родителя {} нет в сценарии", parent_id)))?;
+                    let parent = match self.landscape[x][y].animal {
+                        AnimalInCell::Animal(handle) => handle,
+                        AnimalInCell::None => return Err(error(format!("родителя {} нет в мире", parent_id))),
+                    };
+                    let ticks: usize = Self::scenario_field(ticks).map_err(error)?;
+
+                    let mut child = self.scenario_animal(species, direction, genome, new_animal).map_err(error)?;
+                    child.set_energy(Self::scenario_field(energy).map_err(error)?);
+
+                    let parent_id = self.animals.get(parent).map_or(parent_id, |parent| parent.get_id());
+                    self.pending_births.push(PendingBirth {
+                        parent_id,
+                        parent,
+                        child,
+                        hatch_at: self.iteration + ticks,
+                    });
+                    self.pending_parents.insert(parent_id, (x, y));
+                }
+                ["checkpoint", iteration, next_id, seed] => {
+                    self.iteration = Self::scenario_field(iteration).map_err(error)?;
+                    next_animal_id = Some(Self::scenario_field(next_id).map_err(error)?);
+                    self.seed = Self::scenario_field(seed).map_err(error)?;
+                }
+                ["animal_state", x, y, age, generation, blocked, no_repro, food, brain_rng @ ..] => {
+                    let (x, y) = self.scenario_cell(x, y).map_err(error)?;
+                    let food = match *food {
+                        "-" => None,
+                        food => {
+                            let (food_x, food_y) = food.split_once(',')
+                                .ok_or_else(|| error(format!("неверная ячейка еды \"{}\"", food)))?;
+                            Some(self.scenario_cell(food_x, food_y).map_err(error)?)
+                        }
+                    };
+                    let state = AnimalState {
+                        age: Self::scenario_field(age).map_err(error)?,
+                        generation: Self::scenario_field(generation).map_err(error)?,
+                        reproduce_blocked: Self::scenario_flag(blocked).map_err(error)?,
+                        no_repro: Self::scenario_flag(no_repro).map_err(error)?,
+                        brain_rng: match brain_rng {
+                            ["-"] => None,
+                            _ => Some(brain_rng.join(" ").parse().map_err(error)?),
+                        },
+                    };
+
+                    let animal = match self.landscape[x][y].animal {
+                        AnimalInCell::Animal(handle) => self.animals.get_mut(handle),
+                        AnimalInCell::None => None,
+                    }.ok_or_else(|| error(format!("в ячейке ({}, {}) нет животного", x, y)))?;
+                    animal.set_state(&state);
+                    animal.remember_food(food);
+                }
+                ["plant_state", x, y, age, regrowth_cooldown, no_repro] => {
+                    let (x, y) = self.scenario_cell(x, y).map_err(error)?;
+                    let state = PlantState {
+                        age: Self::scenario_field(age).map_err(error)?,
+                        regrowth_cooldown: Self::scenario_field(regrowth_cooldown).map_err(error)?,
+                        no_repro: Self::scenario_flag(no_repro).map_err(error)?,
+                    };
+
+                    match self.landscape[x][y].plant {
+                        PlantInCell::Plant(handle) => self.plants.get_mut(handle),
+                        PlantInCell::None => None,
+                    }.ok_or_else(|| error(format!("в ячейке ({}, {}) нет растения", x, y)))?.set_state(&state);
+                }
+                ["cell", x, y, carrion, carrion_since, corpse, corpse_since, fertility] => {
+                    let (x, y) = self.scenario_cell(x, y).map_err(error)?;
+                    let cell = Cell {
+                        carrion: Self::scenario_field(carrion).map_err(error)?,
+                        carrion_since: Self::scenario_field(carrion_since).map_err(error)?,
+                        corpse: Self::scenario_field(corpse).map_err(error)?,
+                        corpse_since: Self::scenario_field(corpse_since).map_err(error)?,
+                        fertility: Self::scenario_field(fertility).map_err(error)?,
+                        ..self.landscape[x][y]
+                    };
+                    self.landscape[x][y] = cell;
+                }
+                ["order", axis, order @ ..] => {
+                    let order: Vec<usize> = Self::scenario_fields(order).map_err(error)?;
+                    let (shuffle, size) = match *axis {
+                        "x" => (&mut self.shuffle_width, self.width),
+                        "y" => (&mut self.shuffle_height, self.height),
+                        _ => return Err(error(format!("неизвестная ось \"{}\"", axis))),
+                    };
+
+                    let mut sorted = order.clone();
+                    sorted.sort_unstable();
+                    if sorted != (0..size).collect::<Vec<usize>>() {
+                        return Err(error(format!("порядок обхода по оси {} не является перестановкой 0..{}", axis, size)));
+                    }
+                    *shuffle = order;
+                }
+                ["counters", counters @ ..] => {
+                    let counters: Vec<usize> = Self::scenario_fields(counters).map_err(error)?;
+                    if counters.len() != 19 {
+                        return Err(error(format!("ожидается 19 счетчиков, задано {}", counters.len())));
+                    }
+
+                    let triple = |index: usize| (counters[index], counters[index + 1], counters[index + 2]);
+                    self.animal_reproductions = triple(0);
+                    self.animal_deaths = triple(3);
+                    self.old_age_deaths = triple(6);
+                    self.oldest_death_age = triple(9);
+                    self.animal_max_generation = triple(12);
+                    self.animal_arrivals = triple(15);
+                    self.energy_adjustments = counters[18];
+                }
+                ["mutation_rate", herbivore, carnivore, omnivore] => {
+                    self.mutation_rate = (
+                        Self::scenario_field(herbivore).map_err(error)?,
+                        Self::scenario_field(carnivore).map_err(error)?,
+                        Self::scenario_field(omnivore).map_err(error)?,
+                    );
+                }
+                ["diversity", herbivore, carnivore, omnivore] => {
+                    let diversity = |field: &str| match field {
+                        "-" => Ok(None),
+                        field => Self::scenario_field(field).map(Some),
+                    };
+                    self.genetic_diversity = (
+                        diversity(herbivore).map_err(error)?,
+                        diversity(carnivore).map_err(error)?,
+                        diversity(omnivore).map_err(error)?,
+                    );
+                }
+                ["hunt", species, name, sum, values @ ..] => {
+                    let (success_rate, energy_per_attempt) = match *species {
+                        "herbivore" => (&mut self.success_rate.0, &mut self.energy_per_attempt.0),
+                        "carnivore" => (&mut self.success_rate.1, &mut self.energy_per_attempt.1),
+                        "omnivore" => (&mut self.success_rate.2, &mut self.energy_per_attempt.2),
+                        _ => return Err(error(format!("неизвестный вид \"{}\"", species))),
+                    };
+                    let mean = match *name {
+                        "success_rate" => success_rate,
+                        "energy_per_attempt" => energy_per_attempt,
+                        _ => return Err(error(format!("неизвестное скользящее среднее \"{}\"", name))),
+                    };
+
+                    let values: Vec<f64> = Self::scenario_fields(values).map_err(error)?;
+                    mean.restore(&values, Self::scenario_field(sum).map_err(error)?);
+                }
+                ["budget", budget @ ..] => {
+                    let budget: Vec<Energy> = Self::scenario_fields(budget).map_err(error)?;
+                    if budget.len() != 9 {
+                        return Err(error(format!("ожидается 9 значений баланса, задано {}", budget.len())));
+                    }
+
+                    let species = |index: usize| EnergyBudget {
+                        gained: budget[index],
+                        spent: budget[index + 1],
+                        offspring: budget[index + 2],
+                    };
+                    if self.energy_budgets.len() == self.energy_budget_window {
+                        self.energy_budgets.pop_front();
+                    }
+                    self.energy_budgets.push_back((species(0), species(3), species(6)));
+                }
+                ["rng", state @ ..] => {
+                    rng_state = Some(state.join(" ").parse().map_err(error)?);
+                }
+                ["random", block @ ..] => {
+                    let block = self.scenario_random_block(block, new_animal).map_err(error)?;
+                    self.scenario_blocks.push(block);
+                }
+                [species, id, x, y, energy, direction, genome] => {
+                    let mut animal = self.scenario_animal(species, direction, genome, new_animal).map_err(error)?;
+                    animal.set_energy(Self::scenario_field(energy).map_err(error)?);
+
+                    let id = Self::scenario_field(id).map_err(error)?;
+                    if next_animal_id.is_some() {
+                        self.next_animal_id = id;
+                    }
+
+                    let (x, y) = (Self::scenario_field(x).map_err(error)?, Self::scenario_field(y).map_err(error)?);
+                    self.add_animal(x, y, animal).map_err(|e| error(e.to_string()))?;
+                    loaded.insert(id, (x, y));
+                }
+                _ => return Err(error(format!("неизвестная строка \"{}\"", line))),
+            }
+        }
+
+        if let Some(next_animal_id) = next_animal_id {
+            self.next_animal_id = next_animal_id;
+        }
+        if let Some(rng_state) = rng_state {
+            self.rng = rng_state.restore();
+        }
+
+        Ok(())
+    }
+
+    /// Создает животное по полям строки сценария.
+    ///
+    /// # Arguments
+    ///
+    /// * `species`: Вид животного.
+    /// * `direction`: Направление животного.
+    /// * `genome`: Файл генома (`-` - мозг случайный).
+    /// * `new_animal`: Создает животное сценария.
+    ///
+    /// returns: Result<Box<dyn AnimalAlive>, String>
+    fn scenario_animal(
+        &mut self,
+        species: &str,
+        direction: &str,
+        genome: &str,
+        new_animal: &mut ScenarioAnimal,
+    ) -> Result<Box<dyn AnimalAlive>, String> {
+        let animal_type = Self::scenario_species(species)?;
+        let direction = Self::scenario_direction(direction)?;
+        let brain = if genome == "-" { BrainSource::Random } else { BrainSource::Genome(genome) };
+
+        new_animal(animal_type, direction, brain, &mut self.rng).map_err(|e| e.to_string())
+    }
+
+    /// Заселяет мир случайным блоком сценария (строка `random`, см. `load_scenario`):
+    /// животные размещаются равномерно в свободных ячейках, направление и энергия
+    /// выбираются генератором случайных чисел мира, поэтому с тем же зерном блок
+    /// заселяется так же.
+    ///
+    /// # Arguments
+    ///
+    /// * `fields`: Поля строки после `random`: вид, количество, энергия, направление, мозг.
+    /// * `new_animal`: Создает животное сценария.
+    ///
+    /// returns: Result<String, String> - действующие настройки блока (для манифеста прогона).
+    fn scenario_random_block(&mut self, fields: &[&str], new_animal: &mut ScenarioAnimal) -> Result<String, String> {
+        let [species, count, energy, facing, brain] = fields else {
+            return Err("ожидается \"random <вид> <количество> <энергия> <направление> <мозг>\"".to_string());
+        };
+
+        let animal_type = Self::scenario_species(species)?;
+        let count: usize = Self::scenario_field(count)?;
+        let energy: Option<(Energy, Energy)> = match *energy {
+            "full" => None,
+            energy => match energy.split_once("..") {
+                Some((min, max)) => {
+                    let (min, max) = (Self::scenario_field(min)?, Self::scenario_field(max)?);
+                    if !(0.0..=max).contains(&min) {
+                        return Err(format!("неверный диапазон энергии \"{}\"", energy));
+                    }
+                    Some((min, max))
+                }
+                None => {
+                    let energy = Self::scenario_field(energy)?;
+                    Some((energy, energy))
+                }
+            },
+        };
+        let facing = match *facing {
+            "random" => ScenarioFacing::Uniform,
+            facing if facing.contains(',') => {
+                let weights: Vec<u32> = Self::scenario_fields(&facing.split(',').collect::<Vec<&str>>())?;
+                match WeightedIndex::new(&weights) {
+                    Ok(index) if weights.len() == 4 => ScenarioFacing::Weighted(index),
+                    _ => return Err(format!("ожидаются 4 веса направлений с ненулевой суммой, задано \"{}\"", facing)),
+                }
+            }
+            facing => ScenarioFacing::Fixed(Self::scenario_direction(facing)?),
+        };
+        let brain = match brain.split_once(':') {
+            None if *brain == "random" => BrainSource::Random,
+            Some(("genome", file)) => BrainSource::Genome(file),
+            Some(("champions", file)) => BrainSource::Champions(file),
+            _ => return Err(format!("неизвестный источник мозга \"{}\"", brain)),
+        };
+        if let BrainSource::Genome(file) | BrainSource::Champions(file) = brain {
+            if !Path::new(file).is_file() {
+                return Err(format!("файл мозга \"{}\" не найден", file));
+            }
+        }
+
+        // Все животные блока создаются до размещения: неподходящий мозг останавливает
+        // загрузку, пока блок еще не заселен.
+        let spots = self.placement_spots(AgentType::from(animal_type), count, PlacementStrategy::Uniform)
+            .map_err(|e| e.to_string())?;
+        let mut animals = Vec::with_capacity(count);
+        for _ in 0..count {
+            let direction = match &facing {
+                ScenarioFacing::Fixed(direction) => *direction,
+                ScenarioFacing::Uniform => AnimalDirection::random(&mut self.rng),
+                ScenarioFacing::Weighted(weights) => [
+                    AnimalDirection::North, AnimalDirection::South, AnimalDirection::West, AnimalDirection::East
+                ][weights.sample(&mut self.rng)],
+            };
+
+            let mut animal = new_animal(animal_type, direction, brain, &mut self.rng).map_err(|e| e.to_string())?;
+            if let Some((min, max)) = energy {
+                let energy = if min < max { self.rng.gen_range(min..=max) } else { min };
+                animal.set_energy(energy);
+            }
+            animals.push(animal);
+        }
+
+        for ((x, y), animal) in spots.into_iter().zip(animals) {
+            self.add_animal(x, y, animal).map_err(|e| e.to_string())?;
+        }
+
+        Ok(fields.join(" "))
+    }
+
+    /// Разбирает вид животного строки сценария.
+    fn scenario_species(species: &str) -> Result<AnimaType, String> {
+        match species {
+            "herbivore" => Ok(AnimaType::Herbivore),
+            "carnivore" => Ok(AnimaType::Carnivore),
+            "omnivore" => Ok(AnimaType::Omnivore),
+            _ => Err(format!("неизвестный вид \"{}\"", species)),
+        }
+    }
+
+    /// Разбирает направление животного строки сценария.
+    fn scenario_direction(direction: &str) -> Result<AnimalDirection, String> {
+        match direction {
+            "north" => Ok(AnimalDirection::North),
+            "south" => Ok(AnimalDirection::South),
+            "west" => Ok(AnimalDirection::West),
+            "east" => Ok(AnimalDirection::East),
+            _ => Err(format!("неизвестное направление \"{}\"", direction)),
+        }
+    }
+
+    /// Разбирает числовое поле строки сценария.
+    fn scenario_field<T: std::str::FromStr>(field: &str) -> Result<T, String> {
+        field.parse().map_err(|_| format!("неверное число \"{}\"", field))
+    }
+
+    /// Разбирает числовые поля строки сценария.
+    fn scenario_fields<T: std::str::FromStr>(fields: &[&str]) -> Result<Vec<T>, String> {
+        fields.iter().map(|field| Self::scenario_field(field)).collect()
+    }
+
+    /// Разбирает признак строки сценария (`0` или `1`).
+    fn scenario_flag(field: &str) -> Result<bool, String> {
+        match field {
+            "0" => Ok(false),
+            "1" => Ok(true),
+            _ => Err(format!("неверный признак \"{}\"", field)),
+        }
+    }
+
+    /// Разбирает координаты ячейки строки сценария и проверяет, что ячейка в мире.
+    fn scenario_cell(&self, x: &str, y: &str) -> Result<(usize, usize), String> {
+        let (x, y) = (Self::scenario_field(x)?, Self::scenario_field(y)?);
+        if x >= self.width || y >= self.height {
+            return Err(format!("ячейка ({}, {}) вне мира", x, y));
+        }
+
+        Ok((x, y))
+    }
+
+    /// Имя вида животных в сценарии.
+    fn species_name(animal_type: AnimaType) -> &'static str {
+        animal_type.name()
+    }
+
+    /// Имя направления животного в сценарии.
+    fn direction_name(direction: AnimalDirection) -> &'static str {
+        match direction {
+            AnimalDirection::North => "north",
+            AnimalDirection::South => "south",
+            AnimalDirection::West => "west",
+            AnimalDirection::East => "east",
+        }
+    }
+
+    /// Возвращает мир в виде текста: по строке на каждую координату "y", по символу
+    /// на каждую ячейку. Если в ячейке есть и животное и растение, отображается
+    /// животное (мертвое животное отображается символом трупа).
+    ///
+    /// Символы: `.` - пустая ячейка, `#` - непроходимая ячейка, `*` - растение,
+    /// `,` - полностью съеденное растение, `x` - мертвое животное, `n`/`s`/`w`/`e` - травоядное, смотрящее
+    /// на север, юг, запад или восток, `N`/`S`/`W`/`E` - хищник.
+    ///
+    /// returns: String
+    pub fn to_ascii(&self) -> String {
+        let mut ascii = String::with_capacity((self.width + 1) * self.height);
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let glyph = match (self.animal_at(x, y), self.plant_at(x, y)) {
+                    (Some(animal), _) => {
+                        if animal.is_dead() {
+                            ASCII_DEAD_ANIMAL
+                        } else {
+                            let glyphs = match animal.get_type() {
+                                AnimaType::Herbivore => &ASCII_HERBIVORE,
+                                AnimaType::Carnivore => &ASCII_CARNIVORE,
+                                AnimaType::Omnivore => &ASCII_OMNIVORE,
+                            };
+
+                            match animal.get_direction() {
+                                AnimalDirection::North => glyphs[0],
+                                AnimalDirection::South => glyphs[1],
+                                AnimalDirection::West => glyphs[2],
+                                AnimalDirection::East => glyphs[3],
+                            }
+                        }
+                    }
+                    (None, Some(plant)) => {
+                        if plant.is_eaten() {
+                            ASCII_EATEN_PLANT
+                        } else {
+                            ASCII_PLANT
+                        }
+                    }
+                    (None, None) if self.is_blocked(x, y) => ASCII_OBSTACLE,
+                    (None, None) => ASCII_EMPTY,
+                };
+
+                ascii.push(glyph);
+            }
+
+            ascii.push('\n');
+        }
+
+        ascii
+    }
+
+    /// Find an empty spot for the agent within its particular type.
+    ///
+    /// # Arguments
+    ///
+    /// * `agent_type`: Тип агента для которого пытаемся найти "место в мире".
+    ///
+    /// returns: Result<(usize, usize), RecoverableError>
+    pub fn find_empty_spot(&self, agent_type: AgentType) -> Result<(usize, usize), RecoverableError> {
+        self.check_agent_limit(agent_type)?;
+
+        match agent_type {
+            AgentType::Plant => {
+                // Просматриваем все ячейки, но в случайном порядке.
+                for test_x in &self.shuffle_width {
+                    for test_y in &self.shuffle_height {
+                        if self.is_spot_free(agent_type, *test_x, *test_y) {
+                            // Точка свободна, берем ее.
+                            return Ok((*test_x, *test_y));
+                        }
+                    }
+                }
+
+                // Вряд ли это случится, но если все ячейки заняты...
+                Err(RecoverableError::new(fmt::format(format_args!(
+                    "Не удалось найти свободное место для растения"
+                ))))
+            }
+
+            AgentType::Herbivore | AgentType::Carnivore | AgentType::Omnivore => {
+                for test_x in &self.shuffle_width {
+                    for test_y in &self.shuffle_height {
+                        if self.is_spot_free(agent_type, *test_x, *test_y) {
+                            return Ok((*test_x, *test_y));
+                        }
+                    }
+                }
+
+                // Без полосы вряд ли жто случится, но если все ячейки заняты...
+                if self.spawn_margin > 0 {
+                    return Err(RecoverableError::new(
+                        "Вне полосы у краев мира не осталось места для животного".to_string()
+                    ));
+                }
+                panic!("По каким-то причинам, в мире закончилось место для новых животных!");
+            }
+        }
+    }
+
+    /// Находит место для потомка агента, размножающегося в заданной точке.
+    ///
+    /// # Arguments
+    ///
+    /// * `agent_type`: Тип потомка.
+    /// * `x`, `y`: Координаты родителя.
+    /// * `placement`: Где искать место для потомка.
+    ///
+    /// returns: Result<(usize, usize), RecoverableError>
+    pub fn find_offspring_spot(
+        &mut self,
+        agent_type: AgentType,
+        x: usize,
+        y: usize,
+        placement: ReproductionPlacement
+    ) -> Result<(usize, usize), RecoverableError> {
+        if let ReproductionPlacement::Adjacent { radius } = placement {
+            self.check_agent_limit(agent_type)?;
+
+            // Соседние ячейки просматриваются в случайном порядке.
+            let mut area = self.square_area(x, y, radius);
+            area.shuffle(&mut self.rng);
+
+            for (test_x, test_y) in area {
+                if (test_x, test_y) != (x, y) && self.is_spot_free(agent_type, test_x, test_y) {
+                    return Ok((test_x, test_y));
+                }
+            }
+        }
+
+        // Рядом с родителем места нет: ищем по всему миру.
+        self.find_empty_spot(agent_type)
+    }
+
+    /// Свободна ли ячейка для агента: она проходима, вне полосы у краев мира и в ней
+    /// нет агента того же рода (растения или животного).
+    ///
+    /// # Arguments
+    ///
+    /// * `agent_type`: Тип агента.
+    /// * `x`, `y`: Координаты ячейки.
+    ///
+    /// returns: bool
+    fn is_spot_free(&self, agent_type: AgentType, x: usize, y: usize) -> bool {
+        if self.in_spawn_margin(x, y) || self.is_blocked(x, y) {
+            return false;
+        }
+
+        match agent_type {
+            AgentType::Plant => matches!(self.landscape[x][y].plant, PlantInCell::None),
+            AgentType::Herbivore | AgentType::Carnivore | AgentType::Omnivore => {
+                matches!(self.landscape[x][y].animal, AnimalInCell::None)
+            }
+        }
+    }
+
+    /// Проверяет, не достигнуто ли максимальное количество агентов данного типа.
+    ///
+    /// # Arguments
+    ///
+    /// * `agent_type`: Тип агента.
+    ///
+    /// returns: Result<(), RecoverableError>
+    fn check_agent_limit(&self, agent_type: AgentType) -> Result<(), RecoverableError> {
+        match agent_type {
+            AgentType::Plant => {
+                if self.plant_count >= self.max_plants {
+                    return Err(RecoverableError::new(
+                        fmt::format(format_args!(
+                            "Достигнуто максимальное количество ({}) растений в мире",
+                            self.max_plants,
+                        ))
+                    ))
+                }
+            }
+            AgentType::Herbivore => {
+                if self.animal_count.0 >= self.max_herbivore {
+                    return Err(RecoverableError::new(
+                        fmt::format(format_args!(
+                            "Достигнуто максимальное количество ({}) травоядных в мире",
+                            self.max_herbivore,
+                        ))
+                    ))
+                }
+            }
+            AgentType::Carnivore => {
+                if self.animal_count.1 >= self.max_carnivore {
+                    return Err(RecoverableError::new(
+                        fmt::format(format_args!(
+                            "Достигнуто максимальное количество ({}) хищников в мире",
+                            self.max_carnivore,
+                        ))
+                    ))
+                }
+            }
+            AgentType::Omnivore => {
+                if self.animal_count.2 >= self.max_omnivore {
+                    return Err(RecoverableError::new(
+                        fmt::format(format_args!(
+                            "Достигнуто максимальное количество ({}) всеядных в мире",
+                            self.max_omnivore,
+                        ))
+                    ))
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Добавляет растение в мир.
+    ///
+    /// # Arguments
+    ///
+    /// * `x`: Координата "x" местоположения.
+    /// * `y`: Координата "y" местоположения.
+    /// * `plant`: Растение: тип должен реализовывать типаж PlantAlive и быть обернут в Box.
+    ///
+    /// returns: Result<(), WorldError>
+    pub fn add_plant(
+        &mut self,
+        mut x: usize,
+        mut y: usize,
+        plant: Box<dyn PlantAlive>
+    ) -> Result<(), AddAgentError> {
+        // Если переданная точка выходит за "границы" мира.
+        if x >= self.width || y >= self.height {
+            return Err(
+                AddAgentError::OutOfBounds((x, y))
+            );
+        }
+
+        x = Self::clip(x as isize, self.width);
+        y = Self::clip(y as isize, self.height);
+
+        if self.is_blocked(x, y) {
+            return Err(AddAgentError::BlockedCell((x, y)));
+        }
+
+        // Нужно проверить, не занято ли место в ячейке.
+        if let PlantInCell::None = self.landscape[x][y].plant {
+            // Переносим "бокс" с растением в хранилище растений, а в ячейку помещаем
+            // дескриптор растения.
+            let handle = self.plants.insert(plant);
+            self.landscape[x][y].plant = PlantInCell::Plant(handle);
+            self.plant_count += 1;
+        } else {
+            return Err(
+                AddAgentError::TakenCell((x, y))
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Удаляет умершее растение из мира: из хранилища и из ячейки.
+    ///
+    /// # Arguments
+    ///
+    /// * `handle`: Дескриптор растения.
+    /// * `x`: Координата "x" ячейки растения.
+    /// * `y`: Координата "y" ячейки растения.
+    ///
+    /// returns: ()
+    fn remove_plant(&mut self, handle: Handle, x: usize, y: usize) {
+        self.plants.remove(handle)
+            .expect("Внутренняя ошибка программы: дескриптор растения в ячейке устарел");
+        self.landscape[x][y].plant = PlantInCell::None;
+        self.plant_count -= 1;
+        self.plant_deaths += 1;
+    }
+
+    /// Добавляет животное в мир.
+    ///
+    /// # Arguments
+    ///
+    /// * `x`: Координата "x" местоположения.
+    /// * `y`: Координата "y" местоположения.
+    /// * `animal`: Животное: тип должен реализовывать типаж AnimalAlive и быть обернут в Box.
+    ///
+    /// returns: Result<(), WorldError>
+    pub fn add_animal(
+        &mut self,
+        x: usize,
+        y: usize,
+        animal: Box<dyn AnimalAlive>
+    ) -> Result<(), AddAgentError> {
+        let animal_type = animal.get_type();
+        self.add_stored_animal(x, y, StoredAnimal::store(animal))?;
+
+        match animal_type {
+            AnimaType::Herbivore => self.animal_arrivals.0 += 1,
+            AnimaType::Carnivore => self.animal_arrivals.1 += 1,
+            AnimaType::Omnivore => self.animal_arrivals.2 += 1,
+        }
+
+        Ok(())
+    }
+
+    /// Добавляет в мир животное, уже подготовленное к хранению (см. `add_animal`).
+    ///
+    /// # Arguments
+    ///
+    /// * `x`, `y`: Координаты местоположения.
+    /// * `animal`: Животное.
+    ///
+    /// returns: Result<(), AddAgentError>
+    fn add_stored_animal(&mut self, mut x: usize, mut y: usize, animal: StoredAnimal) -> Result<(), AddAgentError> {
+        // Если переданная точка выходит за "границы" мира.
+        if x >= self.width || y >= self.height {
+            return Err(
+                AddAgentError::OutOfBounds((x, y))
+            );
+        }
+
+        x = Self::clip(x as isize, self.width);
+        y = Self::clip(y as isize, self.height);
+
+        if self.is_blocked(x, y) {
+            return Err(AddAgentError::BlockedCell((x, y)));
+        }
+
+        // Нужно проверить, не занято ли место в ячейке.
+        if let AnimalInCell::None = self.landscape[x][y].animal {
+            // Переносим животное в хранилище животных и получаем дескриптор.
+            let handle = self.animals.insert(animal);
+
+            self.settle_animal(x, y, handle);
+        } else {
+            return Err(
+                AddAgentError::TakenCell((x, y))
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Помещает животное в свободную ячейку: назначает животному идентификатор и
+    /// обновляет счетчики. Животное должно находиться в хранилище животных мира.
+    ///
+    /// # Arguments
+    ///
+    /// * `x`, `y`: Координаты свободной ячейки.
+    /// * `handle`: Дескриптор животного.
+    ///
+    /// returns: ()
+    fn settle_animal(&mut self, x: usize, y: usize, handle: Handle) {
+        let animal = self.animals.get_mut(handle)
+            .expect("Внутренняя ошибка программы: заселяемого животного нет в хранилище");
+
+        animal.set_id(self.next_animal_id);
+        self.next_animal_id += 1;
+
+        self.landscape[x][y].animal = AnimalInCell::Animal(handle);
+
+        match animal.get_type() {
+            AnimaType::Herbivore => {
+                self.animal_count.0 += 1;
+            }
+            AnimaType::Carnivore => {
+                self.animal_count.1 += 1;
+            }
+            AnimaType::Omnivore => {
+                self.animal_count.2 += 1;
+            }
+        }
+    }
+
+    /// Заселяет мир заданным количеством растений и животных, размещая их случайно
+    /// в соответствии со стратегией размещения для каждого вида агентов. Запросы,
+    /// которые невозможно выполнить (агентов больше, чем свободных ячеек в области,
+    /// или превышено максимальное количество агентов в мире), отклоняются. Вид
+    /// животных, итерация появления которого еще не наступила, не размещается, а
+    /// откладывается до вызова `introduce_species`. Всеядные заселяются методом
+    /// `populate_omnivores`.
+    ///
+    /// # Arguments
+    ///
+    /// * `plants`: Количество растений и стратегия их размещения.
+    /// * `herbivores`: Количество травоядных и стратегия их размещения.
+    /// * `carnivores`: Количество хищников и стратегия их размещения.
+    /// * `new_plant`: Создает новое растение.
+    /// * `new_animal`: Создает новое животное заданного типа из генератора случайных
+    ///   чисел мира.
+    ///
+    /// returns: Result<(), RecoverableError>
+    pub fn populate_random(
+        &mut self,
+        plants: (usize, PlacementStrategy),
+        herbivores: (usize, PlacementStrategy),
+        carnivores: (usize, PlacementStrategy),
+        new_plant: &mut dyn FnMut() -> Box<dyn PlantAlive>,
+        new_animal: &mut dyn FnMut(AnimaType, &mut dyn RngCore) -> Box<dyn AnimalAlive>,
+    ) -> Result<(), RecoverableError> {
+        for (x, y) in self.placement_spots(AgentType::Plant, plants.0, plants.1)? {
+            let mut plant = new_plant();
+            let energy = self.initial_energy.0.draw(plant.get_energy(), &mut self.rng);
+            plant.set_energy(energy);
+
+            self.add_plant(x, y, plant)
+                .map_err(|e| RecoverableError::new(e.to_string()))?;
+        }
+
+        if self.start_tick.0 > self.iteration {
+            self.pending_introduction.0 = Some(herbivores);
+        } else {
+            self.place_animals(AnimaType::Herbivore, herbivores.0, herbivores.1, new_animal)?;
+        }
+
+        if self.start_tick.1 > self.iteration {
+            self.pending_introduction.1 = Some(carnivores);
+        } else {
+            self.place_animals(AnimaType::Carnivore, carnivores.0, carnivores.1, new_animal)?;
+        }
+
+        Ok(())
+    }
+
+    /// Заселяет мир заданным количеством всеядных, размещая их случайно в соответствии
+    /// со стратегией размещения. Если итерация появления всеядных (см.
+    /// `set_start_tick`) еще не наступила, всеядные заселяются позже.
+    ///
+    /// # Arguments
+    ///
+    /// * `omnivores`: Количество всеядных и стратегия их размещения.
+    /// * `new_animal`: Создает новое животное заданного типа из генератора случайных
+    ///   чисел мира.
+    ///
+    /// returns: Result<(), RecoverableError>
+    pub fn populate_omnivores(
+        &mut self,
+        omnivores: (usize, PlacementStrategy),
+        new_animal: &mut dyn FnMut(AnimaType, &mut dyn RngCore) -> Box<dyn AnimalAlive>,
+    ) -> Result<(), RecoverableError> {
+        if self.start_tick.2 > self.iteration {
+            self.pending_introduction.2 = Some(omnivores);
+            return Ok(());
+        }
+
+        self.place_animals(AnimaType::Omnivore, omnivores.0, omnivores.1, new_animal)
+    }
+
+    /// Заселяет отложенные виды животных, итерация появления которых наступила.
+    /// Метод следует вызывать перед каждой итерацией мира. Каждый вид заселяется
+    /// только один раз.
+    ///
+    /// # Arguments
+    ///
+    /// * `new_animal`: Создает новое животное заданного типа из генератора случайных
+    ///   чисел мира.
+    ///
+    /// returns: Result<Vec<AnimaType>, RecoverableError> - виды, заселенные при этом вызове.
+    pub fn introduce_species(
+        &mut self,
+        new_animal: &mut dyn FnMut(AnimaType, &mut dyn RngCore) -> Box<dyn AnimalAlive>,
+    ) -> Result<Vec<AnimaType>, RecoverableError> {
+        let mut introduced = Vec::new();
+
+        if self.start_tick.0 <= self.iteration {
+            if let Some((count, strategy)) = self.pending_introduction.0.take() {
+                self.place_animals(AnimaType::Herbivore, count, strategy, new_animal)?;
+                introduced.push(AnimaType::Herbivore);
+            }
+        }
+
+        if self.start_tick.1 <= self.iteration {
+            if let Some((count, strategy)) = self.pending_introduction.1.take() {
+                self.place_animals(AnimaType::Carnivore, count, strategy, new_animal)?;
+                introduced.push(AnimaType::Carnivore);
+            }
+        }
+
+        if self.start_tick.2 <= self.iteration {
+            if let Some((count, strategy)) = self.pending_introduction.2.take() {
+                self.place_animals(AnimaType::Omnivore, count, strategy, new_animal)?;
+                introduced.push(AnimaType::Omnivore);
+            }
+        }
+
+        Ok(introduced)
+    }
+
+    /// Размещает животных одного вида в случайных свободных ячейках.
+    ///
+    /// # Arguments
+    ///
+    /// * `animal_type`: Вид животных.
+    /// * `count`: Количество животных.
+    /// * `strategy`: Стратегия размещения.
+    /// * `new_animal`: Создает новое животное заданного типа из генератора случайных
+    ///   чисел мира.
+    ///
+    /// returns: Result<(), RecoverableError>
+    fn place_animals(
+        &mut self,
+        animal_type: AnimaType,
+        count: usize,
+        strategy: PlacementStrategy,
+        new_animal: &mut dyn FnMut(AnimaType, &mut dyn RngCore) -> Box<dyn AnimalAlive>,
+    ) -> Result<(), RecoverableError> {
+        let agent_type = AgentType::from(animal_type);
+
+        for (x, y) in self.placement_spots(agent_type, count, strategy)? {
+            let mut animal = new_animal(animal_type, &mut self.rng);
+            let energy = animal.get_energy();
+            let initial = self.initial_energy.1.draw(energy, &mut self.rng);
+            // Животное не может начать жизнь мертвым.
+            if initial > 0 as Energy {
+                animal.adjust_energy(initial - energy);
+            }
+
+            self.add_animal(x, y, animal)
+                .map_err(|e| RecoverableError::new(e.to_string()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Выбирает случайные свободные ячейки для размещения агентов в соответствии
+    /// со стратегией размещения.
+    ///
+    /// # Arguments
+    ///
+    /// * `agent_type`: Тип размещаемых агентов.
+    /// * `count`: Количество размещаемых агентов.
+    /// * `strategy`: Стратегия размещения.
+    ///
+    /// returns: Result<Vec<(usize, usize)>, RecoverableError>
+    fn placement_spots(
+        &mut self,
+        agent_type: AgentType,
+        count: usize,
+        strategy: PlacementStrategy
+    ) -> Result<Vec<(usize, usize)>, RecoverableError> {
+        let (current, max, name) = match agent_type {
+            AgentType::Plant => (self.plant_count, self.max_plants, "растений"),
+            AgentType::Herbivore => (self.animal_count.0, self.max_herbivore, "травоядных"),
+            AgentType::Carnivore => (self.animal_count.1, self.max_carnivore, "хищников"),
+            AgentType::Omnivore => (self.animal_count.2, self.max_omnivore, "всеядных"),
+        };
+
+        if max > 0 && current + count > max {
+            return Err(RecoverableError::new(fmt::format(format_args!(
+                "Невозможно разместить {} {}: максимальное количество в мире {}, уже размещено {}",
+                count,
+                name,
+                max,
+                current
+            ))));
+        }
+
+        let mut free: Vec<(usize, usize)> = self.placement_region(strategy)
+            .into_iter()
+            .filter(|&(x, y)| !self.in_spawn_margin(x, y) && !self.is_blocked(x, y))
+            .filter(|&(x, y)| match agent_type {
+                AgentType::Plant => matches!(self.landscape[x][y].plant, PlantInCell::None),
+                AgentType::Herbivore | AgentType::Carnivore | AgentType::Omnivore => {
+                    matches!(self.landscape[x][y].animal, AnimalInCell::None)
+                }
+            })
+            .collect();
+
+        if free.len() < count {
+            return Err(RecoverableError::new(fmt::format(format_args!(
+                "Невозможно разместить {} {}: в области размещения только {} свободных ячеек",
+                count,
+                name,
+                free.len()
+            ))));
+        }
+
+        free.shuffle(&mut self.rng);
+        free.truncate(count);
+
+        Ok(free)
+    }
+
+    /// Возвращает ячейки области размещения (без повторов).
+    ///
+    /// # Arguments
+    ///
+    /// * `strategy`: Стратегия размещения.
+    ///
+    /// returns: Vec<(usize, usize)>
+    fn placement_region(&mut self, strategy: PlacementStrategy) -> Vec<(usize, usize)> {
+        let mut region: Vec<(usize, usize)> = vec![];
+
+        match strategy {
+            PlacementStrategy::Uniform => {
+                for x in 0..self.width {
+                    for y in 0..self.height {
+                        region.push((x, y));
+                    }
+                }
+            }
+            PlacementStrategy::HalfSplit { axis, half } => {
+                for x in 0..self.width {
+                    for y in 0..self.height {
+                        let (coord, size) = match axis {
+                            Axis::X => (x, self.width),
+                            Axis::Y => (y, self.height),
+                        };
+
+                        let first = coord < size / 2;
+                        if first == (half == Half::First) {
+                            region.push((x, y));
+                        }
+                    }
+                }
+            }
+            PlacementStrategy::Patches { k, radius } => {
+                // Участки могут пересекаться, отмечаем уже включенные ячейки.
+                let mut included: Vec<Vec<bool>> = create_landscape_matrix(self.width, self.height);
+
+                for _ in 0..k {
+                    let center_x = self.rng.gen_range(0..self.width);
+                    let center_y = self.rng.gen_range(0..self.height);
+
+                    for (x, y) in self.square_area(center_x, center_y, radius) {
+                        if !included[x][y] {
+                            included[x][y] = true;
+                            region.push((x, y));
+                        }
+                    }
+                }
+            }
+        }
+
+        region
+    }
+
+    /// Возвращает ячейки квадрата с центром в заданной точке с учетом тороидальности
+    /// мира. Если радиус больше половины размера мира по оси, квадрат покрывает мир
+    /// по этой оси целиком.
+    ///
+    /// # Arguments
+    ///
+    /// * `x`, `y`: Координаты центра квадрата.
+    /// * `radius`: Радиус (половина стороны квадрата без центральной ячейки).
+    ///
+    /// returns: Vec<(usize, usize)>
+    fn square_area(&self, x: usize, y: usize, radius: usize) -> Vec<(usize, usize)> {
+        let radius_x = radius.min(self.width / 2) as isize;
+        let radius_y = radius.min(self.height / 2) as isize;
+
+        let mut area: Vec<(usize, usize)> = vec![];
+        for dx in -radius_x..=radius_x {
+            for dy in -radius_y..=radius_y {
+                // Ячейки за стеной в квадрат не входят.
+                if let Some(coord) = self.shift(x, y, dx, dy) {
+                    area.push(coord);
+                }
+            }
+        }
+
+        area
+    }
+
+    /// Одна симуляция всего мира.
+    pub fn tick(&mut self) {
+        // Перед каждой итерацией тасуем вектора координат. Т.к. сложность алгоритма тасовки
+        // составляет 2*N, то это не представляет особых проблем с производительностью.
+        self.shuffle_width.shuffle(&mut self.rng);
+        self.shuffle_height.shuffle(&mut self.rng);
+
+        // События, отправленные с предыдущей картой, больше не нужны; иначе они
+        // накапливаются до следующей собранной карты.
+        if self.view_ready {
+            self.transient_events.clear();
+        }
+
+        // Статистика текущей итерации.
+        self.eaten_energy = (0 as Energy, 0 as Energy, 0 as Energy);
+        self.births = (0, 0, 0);
+        self.conceptions = (0, 0, 0);
+        self.deferred_births = (0, 0, 0);
+        self.blocked_births = (0, 0, 0);
+        self.policy_vetoes = [0; MAX_ACTION_POLICIES];
+        self.protected_skips = (0, 0, 0);
+        self.carrion_energy = (0 as Energy, 0 as Energy, 0 as Energy);
+        self.seeds = (0, 0);
+        self.plant_deaths = 0;
+        self.eat_attempts = (0, 0, 0);
+        self.eat_successes = (0, 0, 0);
+        self.attack_attempts = (0, 0, 0);
+        self.kills = (0, 0, 0);
+        self.failed_births = 0;
+        self.energy_budget = Default::default();
+
+        // Обрабатываются ли растения на этой итерации.
+        let plant_phase = self.iteration.is_multiple_of(self.plant_update_interval);
+
+        // Перебираем ячейки в случайном порядке!
+        for x in &self.shuffle_width.clone() {
+            for y in &self.shuffle_height.clone() {
+                // Разложение падали удобряет почву.
+                if self.carrion_decay_rate > 0.0 || self.carrion_lifetime > 0 {
+                    self.decay_carrion(*x, *y);
+                }
+
+                // Несъеденная туша со временем становится падалью.
+                if self.corpse_lifetime > 0 {
+                    self.rot_corpse(*x, *y);
+                }
+
+                // Симуляция травы.
+                match self.landscape[*x][*y].plant {
+                    // В точке есть растение.
+                    PlantInCell::Plant(handle) => {
+                        // На время хода растение извлекается из хранилища: растение
+                        // изменяется вместе с остальным миром.
+                        let mut plant = self.plants.take(handle)
+                            .expect("Внутренняя ошибка программы: дескриптор растения в ячейке устарел");
+
+                        // Для растения прошла еще одна итерация, даже если на этой
+                        // итерации растения не обрабатываются.
+                        plant.tick_passed();
+
+                        if plant_phase {
+                            self.simulate_plant(plant.as_mut(), *x, *y);
+
+                            if self.sanitize_energy {
+                                self.sanitize_plant_energy(plant.as_mut(), handle, *x, *y);
+                            }
+                        }
+
+                        // Растение, достигшее наибольшего возраста, умирает.
+                        let expired = self.plant_max_age > 0 && plant.get_age() >= self.plant_max_age;
+
+                        self.plants.restore(handle, plant);
+
+                        if expired {
+                            self.remove_plant(handle, *x, *y);
+                        }
+                    },
+                    // Нет растения.
+                    PlantInCell::None => {},
+                }
+
+                // Симуляция животных.
+                match self.landscape[*x][*y].animal {
+                    // В точке есть животное.
+                    AnimalInCell::Animal(handle) => {
+                        // На время хода животное извлекается из хранилища: животное
+                        // изменяется вместе с остальным миром.
+                        let mut animal = self.animals.take(handle)
+                            .expect("Внутренняя ошибка программы: дескриптор животного в ячейке устарел");
+
+                        // Проверяем обработанность животного.
+                        // Возможно животное уже сделало "свой ход". Как такое возможно, что в новь
+                        // обрабатываемая точка уже содержит животное сделавшее свой ход? Рассмотрим
+                        // пример: текущая итерация обрабатывает точку (1, 1). Животное перемещается
+                        // в точку (1, 2). Когда итерация дойдет до точки (1, 2) животное повторно
+                        // совершит свое действие, что неверно.
+                        if animal.is_processed(self.iteration as u64) {
+                            self.animals.restore(handle, animal);
+                            continue;
+                        }
+
+                        // К этому моменту мертвого животного в точке быть не может (исключается
+                        // параметром is_processed).
+                        if animal.is_dead() {
+                            panic!("Попытка симуляции мертвого животного в ячейке {}, {}.", x, y);
+                        };
+
+                        // Даем животному, своими активными действиями, шанс выжить.
+                        self.simulate_animal(&mut animal, *x, *y);
+
+                        if self.sanitize_energy {
+                            self.sanitize_animal_energy(&mut animal, handle);
+                        }
+
+                        self.animals.restore(handle, animal);
+                    },
+                    // Нет животного - ничего не делать.
+                    AnimalInCell::None => {},
+                }
+            }
+        }
+
+        // Вынашиваемые потомки.
+        if !self.pending_births.is_empty() {
+            self.hatch_pending_births();
+        }
+
+        // Завершающая обработка.
+        self.final_processing();
+
+        // Энергетический баланс видов за итерацию.
+        self.close_energy_budget();
+
+        // Скользящие средние эффективности поедания учитывают только итерации с попытками.
+        if self.eat_attempts.0 > 0 {
+            let attempts = self.eat_attempts.0 as f64;
+            self.success_rate.0.push(self.eat_successes.0 as f64 / attempts);
+            self.energy_per_attempt.0.push(self.eaten_energy.0 as f64 / attempts);
+        }
+        if self.eat_attempts.1 > 0 {
+            let attempts = self.eat_attempts.1 as f64;
+            self.success_rate.1.push(self.eat_successes.1 as f64 / attempts);
+            self.energy_per_attempt.1.push(self.eaten_energy.1 as f64 / attempts);
+        }
+        if self.eat_attempts.2 > 0 {
+            let attempts = self.eat_attempts.2 as f64;
+            self.success_rate.2.push(self.eat_successes.2 as f64 / attempts);
+            self.energy_per_attempt.2.push(self.eaten_energy.2 as f64 / attempts);
+        }
+
+        self.iteration += 1;
+
+        // Замер генетического разнообразия.
+        if self.diversity_period > 0 && self.iteration.is_multiple_of(self.diversity_period) {
+            self.genetic_diversity = (
+                self.measure_diversity(AnimaType::Herbivore),
+                self.measure_diversity(AnimaType::Carnivore),
+                self.measure_diversity(AnimaType::Omnivore),
+            );
+            self.adjust_mutation_rate();
+        }
+
+        // Сверка счетчиков статистики.
+        if self.stats_validation_period > 0 && self.iteration.is_multiple_of(self.stats_validation_period) {
+            if let Err(e) = self.validate_statistics() {
+                eprintln!("Итерация {}: {}", self.iteration, e);
+                self.stats_mismatch = Some((self.iteration, e.to_string()));
+            }
+        }
+    }
+
+    /// Сдвигает частоту мутаций видов по последнему замеру генетического разнообразия,
+    /// если регулятор задан. Каждое изменение частоты записывается.
+    fn adjust_mutation_rate(&mut self) {
+        let controller = match self.mutation_controller {
+            Some(controller) => controller,
+            None => return,
+        };
+
+        let species = [
+            (AnimaType::Herbivore, self.genetic_diversity.0, &mut self.mutation_rate.0),
+            (AnimaType::Carnivore, self.genetic_diversity.1, &mut self.mutation_rate.1),
+            (AnimaType::Omnivore, self.genetic_diversity.2, &mut self.mutation_rate.2),
+        ];
+
+        for (animal_type, diversity, rate) in species {
+            // Разнообразие вида не замерено (в выборке меньше двух животных).
+            let diversity = match diversity {
+                Some(diversity) => diversity,
+                None => continue,
+            };
+
+            let next = controller.next_rate(*rate, diversity);
+            if next != *rate {
+                self.mutation_adjustments.push(MutationAdjustment {
+                    iteration: self.iteration,
+                    animal_type,
+                    diversity,
+                    from: *rate,
+                    to: next,
+                });
+                *rate = next;
+            }
+        }
+    }
+
+    /// Вычисляет генетическое разнообразие вида по случайной выборке живых животных.
+    ///
+    /// # Arguments
+    ///
+    /// * `animal_type`: Вид животных.
+    ///
+    /// returns: Option<f64> - None, если в выборке меньше двух геномов.
+    fn measure_diversity(&mut self, animal_type: AnimaType) -> Option<f64> {
+        let mut handles: Vec<Handle> = Vec::new();
+
+        for column in &self.landscape {
+            for cell in column {
+                if let AnimalInCell::Animal(handle) = cell.animal {
+                    let animal = self.animals.get(handle)
+                        .expect("Внутренняя ошибка программы: дескриптор животного в ячейке устарел");
+
+                    if animal.get_type() == animal_type && !animal.is_dead() {
+                        handles.push(handle);
+                    }
+                }
+            }
+        }
+
+        // Геномы получаем только для выборки: стоимость сравнения геномов не зависит
+        // от численности вида.
+        let (sample, _) = handles.partial_shuffle(&mut self.rng, self.diversity_sample);
+        let genomes: Vec<Vec<f64>> = sample.iter()
+            .filter_map(|&handle| self.animals.get(handle).and_then(|animal| animal.get_genome()))
+            .collect();
+
+        mean_pairwise_distance(&genomes)
+    }
+
+    /// Пересчитывает счетчики агентов по сетке мира и сравнивает их со значениями,
+    /// которые мир ведет инкрементально. Численность каждого вида должна совпадать
+    /// с количеством добавленных в мир и рожденных животных за вычетом умерших, а
+    /// суммарная энергия растений, животных, падали, туш и плодородия почвы должна
+    /// быть конечным неотрицательным числом, энергетический баланс видов - конечным.
+    ///
+    /// returns: Result<(), RecoverableError> - ошибка содержит все расхождения
+    /// с указанием счетчика и разницы.
+    pub fn validate_statistics(&self) -> Result<(), RecoverableError> {
+        let mut plant_count: usize = 0;
+        let mut animal_count: (usize, usize, usize) = (0, 0, 0);
+        // Суммарная энергия: растения, животные, падаль, туши, плодородие.
+        let mut energy = [0 as Energy; 5];
+
+        for column in &self.landscape {
+            for cell in column {
+                if let PlantInCell::Plant(handle) = cell.plant {
+                    plant_count += 1;
+                    energy[0] += self.plants.get(handle).map_or(0 as Energy, |plant| plant.get_energy());
+                }
+
+                // Устаревший дескриптор не учитывается и проявится расхождением счетчиков.
+                if let AnimalInCell::Animal(handle) = cell.animal {
+                    if let Some(animal) = self.animals.get(handle) {
+                        match animal.get_type() {
+                            AnimaType::Herbivore => animal_count.0 += 1,
+                            AnimaType::Carnivore => animal_count.1 += 1,
+                            AnimaType::Omnivore => animal_count.2 += 1,
+                        }
+                        energy[1] += animal.get_energy().max(0 as Energy);
+                    }
+                }
+
+                energy[2] += cell.carrion;
+                energy[3] += cell.corpse;
+                energy[4] += cell.fertility;
+            }
+        }
+
+        // Численность по рождениям и смертям: добавленные и рожденные за вычетом умерших.
+        let population = |arrivals: usize, births: usize, deaths: usize| (arrivals + births).wrapping_sub(deaths);
+
+        let checks = [
+            ("plant_count", self.plant_count, plant_count),
+            ("animal_count.0 (травоядные)", self.animal_count.0, animal_count.0),
+            ("animal_count.1 (хищники)", self.animal_count.1, animal_count.1),
+            ("animal_count.2 (всеядные)", self.animal_count.2, animal_count.2),
+            (
+                "births.0 - deaths.0 (травоядные)",
+                population(self.animal_arrivals.0, self.animal_reproductions.0, self.animal_deaths.0),
+                animal_count.0,
+            ),
+            (
+                "births.1 - deaths.1 (хищники)",
+                population(self.animal_arrivals.1, self.animal_reproductions.1, self.animal_deaths.1),
+                animal_count.1,
+            ),
+            (
+                "births.2 - deaths.2 (всеядные)",
+                population(self.animal_arrivals.2, self.animal_reproductions.2, self.animal_deaths.2),
+                animal_count.2,
+            ),
+        ];
+
+        // Энергия в мире не может быть отрицательной. Баланс видов может: потраченная
+        // энергия учитывается за вычетом съеденной.
+        let budget = self.get_energy_budget();
+        let energy_checks = [
+            ("энергия растений", energy[0], true),
+            ("энергия животных", energy[1], true),
+            ("энергия падали", energy[2], true),
+            ("энергия туш", energy[3], true),
+            ("плодородие почвы", energy[4], true),
+            ("energy_budget.0 (травоядные)", budget.0.gained + budget.0.spent + budget.0.offspring, false),
+            ("energy_budget.1 (хищники)", budget.1.gained + budget.1.spent + budget.1.offspring, false),
+            ("energy_budget.2 (всеядные)", budget.2.gained + budget.2.spent + budget.2.offspring, false),
+        ];
+
+        let mut mismatches: Vec<String> = vec![];
+        for (name, total, non_negative) in energy_checks {
+            if !total.is_finite() || (non_negative && total < 0 as Energy) {
+                mismatches.push(fmt::format(format_args!("{}: {}", name, total)));
+            }
+        }
+        for (name, counted, actual) in checks {
+            if counted != actual {
+                mismatches.push(fmt::format(format_args!(
+                    "{}: учтено {}, фактически {} (разница {})",
+                    name,
+                    counted,
+                    actual,
+                    counted as isize - actual as isize
+                )));
+            }
+        }
+
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(RecoverableError::new(fmt::format(format_args!(
+                "Расхождение счетчиков статистики: {}",
+                mismatches.join("; ")
+            ))))
+        }
+    }
+
+    /// Разложение падали в ячейке: падаль теряет часть энергии (по истечении срока -
+    /// всю), часть потерянного переходит в плодородие почвы, накопленное плодородие
+    /// убывает.
+    ///
+    /// # Arguments
+    ///
+    /// * `x`: "x" координата ячейки.
+    /// * `y`: "y" координата ячейки.
+    ///
+    /// returns: ()
+    fn decay_carrion(&mut self, x: usize, y: usize) {
+        let cell = &mut self.landscape[x][y];
+
+        cell.fertility -= (self.fertility_decay_rate * cell.fertility as f64) as Energy;
+
+        if cell.carrion > 0 as Energy {
+            let expired = self.carrion_lifetime > 0 && self.iteration >= cell.carrion_since + self.carrion_lifetime;
+            let decayed = if expired {
+                cell.carrion
+            } else {
+                (self.carrion_decay_rate * cell.carrion as f64) as Energy
+            };
+            cell.carrion -= decayed;
+            cell.fertility += (self.carrion_fertility_rate * decayed as f64) as Energy;
+        }
+    }
+
+    /// Туша, пролежавшая в ячейке дольше срока (см. `set_two_step_predation`),
+    /// становится падалью, если падаль ест хотя бы один вид, иначе пропадает.
+    ///
+    /// # Arguments
+    ///
+    /// * `x`: "x" координата ячейки.
+    /// * `y`: "y" координата ячейки.
+    ///
+    /// returns: ()
+    fn rot_corpse(&mut self, x: usize, y: usize) {
+        let scavenging = self.scavenging.0 || self.scavenging.1 || self.scavenging.2;
+        let cell = &mut self.landscape[x][y];
+
+        if cell.corpse > 0 as Energy && self.iteration >= cell.corpse_since + self.corpse_lifetime {
+            if scavenging {
+                cell.carrion += cell.corpse;
+                cell.carrion_since = self.iteration;
+            }
+            cell.corpse = 0 as Energy;
+        }
+    }
+
+    /// Симуляция травы в указанной точке.
+    ///
+    /// # Arguments
+    ///
+    /// * `plant`: Изменяемая ссылка на текущее, симулируемое растение.
+    /// * `x`: "x" координата симулируемого растения.
+    /// * `y`: "y" координата симулируемого растения.
+    ///
+    /// Returns: ()
+    fn simulate_plant(&mut self, plant: &mut dyn PlantAlive, x: usize, y: usize) {
+        // Получаем то, что хочет растение.
+        let action = plant.action();
+
+        match action {
+            // Растение ничего не хочет (кроме гомеостаза).
+            PlantAction::None => {
+                self.inactivity_plant_action(plant, x, y);
+            }
+            // Растение хочет расти.
+            PlantAction::Grow => {
+                self.grow_plant_action(plant, x, y);
+            }
+            // Растение решило размножиться (рассыпать семена).
+            PlantAction::Reproduce => {
+                self.reproduce_plant_action(plant, x, y);
+            }
+        }
+    }
+
+    /// Действие - нет действия.
+    fn inactivity_plant_action(&mut self, plant: &mut dyn PlantAlive, x: usize, y: usize) {
+        self.landscape[x][y].plant = self.landscape[x][y].plant;
+        plant.inactivity_action();
+    }
+
+    /// Реализует рост растения.
+    fn grow_plant_action(&mut self, plant: &mut dyn PlantAlive, x: usize, y: usize) {
+        self.landscape[x][y].plant = self.landscape[x][y].plant;
+        // Растение получает энергию роста за все итерации с прошлой обработки.
+        let growth = self.growth_map[x][y] as f64 * self.season_factor();
+        let energy = growth as Energy + self.landscape[x][y].fertility;
+        plant.grow_action(energy * self.plant_update_interval as Energy);
+    }
+
+    /// Реализует размножение растения.
+    ///
+    /// # Arguments
+    ///
+    /// * `plant`: Изменяемая ссылка на текущее, симулируемое растение.
+    /// * `x`: "x" координата симулируемого растения.
+    /// * `y`: "y" координата симулируемого растения.
+    ///
+    /// Returns: ()
+    fn reproduce_plant_action(&mut self, plant: &mut dyn PlantAlive, x: usize, y: usize) {
+        for seed in 0..self.seeds_per_reproduction {
+            // Растение истощилось: следующее семечко уже не по силам.
+            if seed > 0 && plant.get_energy() <= 0 as Energy {
+                break;
+            }
+
+            self.seeds.0 += 1;
+
+            let placement = self.reproduction_placement.0;
+            match self.find_offspring_spot(AgentType::Plant, x, y, placement) {
+                // Ячейка нашлась.
+                Ok(coord) => {
+                    let new_plant = plant.reproduce_action();
+                    self.add_plant(coord.0, coord.1, new_plant)
+                        .expect("Не удалось добавить растение");
+
+                    self.seeds.1 += 1;
+                    self.emit(|tick| WorldEvent::PlantSprouted { tick, at: coord });
+                }
+                // Не удалось найти свободную ячейку или достигнут лимит растений.
+                Err(_) => break,
+            }
+        }
+    }
+
+    /// Симуляция животного в точке.
+    ///
+    /// This is the main animal simulation routine. This function performs
+    /// the perception phase which fills in the input cells for the animal's
+    /// brain. This is based upon the particular direction of the agent.
+    /// The agent brain determines the action to be taken. A function is
+    /// then called based upon the action selected.
+    ///
+    /// # Arguments
+    ///
+    /// * `animal`: Изменяемая ссылка на текущее, симулируемое животное.
+    /// * `x`: "x" координата симулируемого животного.
+    /// * `y`: "y" координата симулируемого животного.
+    ///
+    /// returns: ()
+    fn simulate_animal(&mut self, animal: &mut StoredAnimal, x: usize, y: usize) {
+        let energy = animal.get_energy();
+
+        // Для животного прошла еще одна итерация.
+        animal.tick_passed();
+        // Животное совершает "свой ход".
+        animal.mark_processed(self.iteration as u64);
+
+        // Животное, прожившее отпущенный срок, умирает, не совершая действий.
+        if animal.is_old() {
+            return;
+        }
+
+        // Determine inputs for the agent brain.
+        let inputs = self.percept(animal, x, y);
+        let action = if self.traced_animal == Some(animal.get_id()) {
+            let mut trace = ActionTrace {
+                iteration: self.iteration,
+                animal_id: animal.get_id(),
+                ..ActionTrace::default()
+            };
+            let action = animal.action_traced(&inputs, &mut trace);
+            self.action_traces.push(trace);
+
+            action
+        } else {
+            animal.action(&inputs)
+        };
+
+        // Perform Action
+        let action = self.review_action(animal.as_animal(), x, y, action, &inputs);
+        self.apply_animal_action(animal, x, y, action);
+
+        self.species_budget(animal.get_type()).spent += energy - animal.get_energy();
+    }
+
+    /// Возвращает энергетический баланс вида на текущей итерации.
+    ///
+    /// # Arguments
+    ///
+    /// * `animal_type`: Вид животного.
+    ///
+    /// returns: &mut EnergyBudget
+    fn species_budget(&mut self, animal_type: AnimaType) -> &mut EnergyBudget {
+        match animal_type {
+            AnimaType::Herbivore => &mut self.energy_budget.0,
+            AnimaType::Carnivore => &mut self.energy_budget.1,
+            AnimaType::Omnivore => &mut self.energy_budget.2,
+        }
+    }
+
+    /// Отправляет событие мира, если канал для событий задан. Событие создается
+    /// только при наличии канала.
+    ///
+    /// # Arguments
+    ///
+    /// * `event`: Создает событие по номеру текущей итерации (начиная с единицы).
+    ///
+    /// returns: ()
+    fn emit(&mut self, event: impl FnOnce(usize) -> WorldEvent) {
+        if let Some(ref sink) = self.event_sink {
+            if sink.send(event(self.iteration + 1)).is_err() {
+                // Получатель закрыт.
+                self.event_sink = None;
+            }
+        }
+    }
+
+    /// Подводит энергетический баланс видов за итерацию и сохраняет его в окне
+    /// последних балансов. За ходы животных накоплена убыль их энергии: потраченная
+    /// энергия и энергия потомков за вычетом полученной.
+    fn close_energy_budget(&mut self) {
+        for (budget, gained) in [
+            (&mut self.energy_budget.0, self.eaten_energy.0),
+            (&mut self.energy_budget.1, self.eaten_energy.1),
+            (&mut self.energy_budget.2, self.eaten_energy.2),
+        ] {
+            budget.gained = gained;
+            budget.spent += gained - budget.offspring;
+        }
+
+        if self.energy_budgets.len() == self.energy_budget_window {
+            self.energy_budgets.pop_front();
+        }
+        self.energy_budgets.push_back(self.energy_budget);
+    }
+
+    /// Проверяет действие животного цепочкой правил мира и считает запреты.
+    ///
+    /// # Arguments
+    ///
+    /// * `animal`: Ссылка на животное.
+    /// * `x`, `y`: Положение животного.
+    /// * `action`: Действие, выбранное мозгом.
+    /// * `inputs`: Входные сигналы, которые животное восприняло на текущем ходу.
+    ///
+    /// returns: AnimalAction - действие, оставленное правилами.
+    fn review_action(
+        &mut self,
+        animal: &dyn AnimalAlive,
+        x: usize,
+        y: usize,
+        action: AnimalAction,
+        inputs: &AnimalInputSignal
+    ) -> AnimalAction {
+        let ctx = PolicyCtx { tick: self.iteration + 1, at: (x, y) };
+
+        let mut action = action;
+        for (index, policy) in self.action_policies.iter().enumerate() {
+            let reviewed = policy.review(animal, inputs, action, &ctx);
+            if reviewed != action {
+                self.policy_vetoes[index] += 1;
+                action = reviewed;
+            }
+        }
+
+        action
+    }
+
+    /// Выполняет действие животного в точке. Животное должно быть извлечено из
+    /// хранилища на время действия.
+    ///
+    /// # Arguments
+    ///
+    /// * `animal`: Изменяемая ссылка на животное.
+    /// * `x`, `y`: Положение животного.
+    /// * `action`: Действие.
+    ///
+    /// returns: ()
+    fn apply_animal_action(&mut self, animal: &mut StoredAnimal, x: usize, y: usize, action: AnimalAction) {
+        match action {
+            AnimalAction::TurnLeft => {
+                self.turn_left_animal_action(animal, x, y);
+            }
+            AnimalAction::TurnRight => {
+                self.turn_right_animal_action(animal, x, y);
+            }
+            AnimalAction::Move => {
+                self.movement_animal_action(animal, x, y);
+            }
+            AnimalAction::Eat => {
+                self.eating_animal_action(animal, x, y);
+            }
+            AnimalAction::Attack => {
+                self.attack_animal_action(animal, x, y);
+            }
+            AnimalAction::Reproduce => {
+                self.reproduce_animal_action(animal, x, y)
+            }
+            AnimalAction::None => {
+                self.inactivity_animal_action(animal)
+            }
+        }
+    }
+
+    /// Заставляет животное в ячейке выполнить заданное действие в обход его мозга.
+    /// Позволяет проверять правила мира (движение, поедание и т.д.) на подготовленном
+    /// мире без полной итерации. Животное воспринимает обстановку, но не тратит ход:
+    /// возраст и отметка обработки не меняются. Действие проверяется цепочкой правил
+    /// мира, как и действие, выбранное мозгом.
+    ///
+    /// # Arguments
+    ///
+    /// * `x`, `y`: Положение животного.
+    /// * `action`: Действие.
+    ///
+    /// returns: Result<(), RecoverableError> - ошибка, если в ячейке нет живого животного.
+    pub fn perform_animal_action(
+        &mut self,
+        x: usize,
+        y: usize,
+        action: AnimalAction
+    ) -> Result<(), RecoverableError> {
+        let handle = match self.landscape.get(x).and_then(|column| column.get(y)) {
+            Some(Cell { animal: AnimalInCell::Animal(handle), .. }) => *handle,
+            _ => {
+                return Err(RecoverableError::new(fmt::format(format_args!(
+                    "В ячейке ({}, {}) нет животного", x, y
+                ))));
+            }
+        };
+
+        let mut animal = self.animals.take(handle)
+            .expect("Внутренняя ошибка программы: дескриптор животного в ячейке устарел");
+
+        if animal.is_dead() {
+            self.animals.restore(handle, animal);
+            return Err(RecoverableError::new(fmt::format(format_args!(
+                "Животное в ячейке ({}, {}) мертво", x, y
+            ))));
+        }
+
+        let inputs = self.percept(&mut animal, x, y);
+        let action = self.review_action(animal.as_animal(), x, y, action, &inputs);
+        self.apply_animal_action(&mut animal, x, y, action);
+        self.animals.restore(handle, animal);
+
+        Ok(())
+    }
+
+    /// Животное "должно посмотреть по сторонам" (по соответствующим областям в зависимости
+    /// от направления) и заполнить структуру содержащую переменные входных сигналов для
+    /// мозга животного. Животное видит текущее состояние мира, т.е. остальные агенты
+    /// могли у этому моменту сделать свой шаг, а некоторые еще ждут своей очереди.
+    /// Само животное в свои области не попадает, даже если смещение области (например,
+    /// в маленьком мире со склейкой краев) приходится на его собственную ячейку.
+    ///
+    /// TODO: В дальнейшем планирую использовать "карту восприятия", матрицу
+    /// TODO: где заполнены соответствующие позиции с информацией о агентах
+    /// TODO: (их наличие).
+    ///
+    /// # Arguments
+    ///
+    /// * `animal`: Изменяемая ссылка на животное.
+    /// * `x`: Положение животного по "x".
+    /// * `y`: Положение животного по "y".
+    ///
+    /// returns: AnimalInputSignal
+    fn percept(&self, animal: &mut StoredAnimal, x: usize, y: usize) -> AnimalInputSignal {
+        let mut inputs =  AnimalInputSignal {
+            plant_front: 0,
+            plant_left: 0,
+            plant_right: 0,
+            plant_proximity: 0,
+            herbivore_front: 0,
+            herbivore_left: 0,
+            herbivore_right: 0,
+            herbivore_proximity: 0,
+            carnivore_front: 0,
+            carnivore_left: 0,
+            carnivore_right: 0,
+            carnivore_proximity: 0,
+            repro_blocked: animal.is_reproduce_blocked() as usize,
+            wall_front: 0,
+            own_energy: if animal.get_max_energy() > 0 as Energy {
+                (animal.get_energy() / animal.get_max_energy()).clamp(0 as Energy, 1 as Energy)
+            } else {
+                0 as Energy
+            },
+            season_phase: 0 as Energy,
+            plant_density: 0 as Energy,
+            age_fraction: 0 as Energy,
+        };
+
+        // Контекст мира.
+        if let Some(max_age) = self.brain_context {
+            inputs.season_phase = self.season_phase() as Energy;
+            inputs.plant_density = (self.plant_count as Energy / (self.width * self.height).max(1) as Energy)
+                .clamp(0 as Energy, 1 as Energy);
+            if max_age > 0 {
+                inputs.age_fraction = (animal.get_age() as Energy / max_age as Energy).min(1 as Energy);
+            }
+        }
+
+        // Стену животное замечает, только если уперлось в нее: по оси со склейкой
+        // краев стены нет. Непроходимая ячейка впереди тоже воспринимается как стена.
+        let ahead = Self::ahead_area(animal.get_direction())[0];
+        inputs.wall_front = match self.shift(x, y, ahead.0 as isize, ahead.1 as isize) {
+            Some((ahead_x, ahead_y)) => self.is_blocked(ahead_x, ahead_y) as usize,
+            None => 1,
+        };
+
+        // Ячейки дальше радиуса зрения животное не видит.
+        let radius = animal.get_vision_radius();
+        // Себя животное не считает.
+        let id = animal.get_id();
+
+        let [front, left, right, proximity] = Self::perception_areas(animal.get_direction());
+
+        let count = self.count_agents_in_area(front, radius, x, y, id);
+        inputs.plant_front = count.0;
+        inputs.herbivore_front = count.1;
+        inputs.carnivore_front = count.2;
+
+        let count = self.count_agents_in_area(left, radius, x, y, id);
+        inputs.plant_left = count.0;
+        inputs.herbivore_left = count.1;
+        inputs.carnivore_left = count.2;
+
+        let count = self.count_agents_in_area(right, radius, x, y, id);
+        inputs.plant_right = count.0;
+        inputs.herbivore_right = count.1;
+        inputs.carnivore_right = count.2;
+
+        let count = self.count_agents_in_area(proximity, radius, x, y, id);
+        inputs.plant_proximity = count.0;
+        inputs.herbivore_proximity = count.1;
+        inputs.carnivore_proximity = count.2;
+
+        inputs
+    }
+
+    /// Возвращает смещения областей восприятия животного: впереди, слева, справа и
+    /// область близости.
+    ///
+    /// # Arguments
+    ///
+    /// * `direction`: Направление животного.
+    ///
+    /// returns: [&[(i8, i8)]; 4]
+    fn perception_areas(direction: AnimalDirection) -> [&'static [(i8, i8)]; 4] {
+        match direction {
+            AnimalDirection::North => [&NORTH_FRONT, &NORTH_LEFT, &NORTH_RIGHT, &NORTH_PROXIMITY],
+            AnimalDirection::South => [&SOUTH_FRONT, &SOUTH_LEFT, &SOUTH_RIGHT, &SOUTH_PROXIMITY],
+            AnimalDirection::West => [&WEST_FRONT, &WEST_LEFT, &WEST_RIGHT, &WEST_PROXIMITY],
+            AnimalDirection::East => [&EAST_FRONT, &EAST_LEFT, &EAST_RIGHT, &EAST_PROXIMITY],
+        }
+    }
+
+    /// Ячейки области восприятия: смещения в пределах радиуса зрения, переведенные
+    /// в координаты мира. Ячейки за стеной в область не входят.
+    ///
+    /// # Arguments
+    ///
+    /// * `offsets`: Срез смещений относительно заданной точки.
+    /// * `radius`: Радиус зрения: смещения, превышающие радиус хотя бы по одной оси,
+    ///   не учитываются.
+    /// * `x`, `y`: Координаты точки, относительно которой заданы смещения.
+    ///
+    /// returns: impl Iterator<Item = (usize, usize)>
+    fn area_cells<'a>(
+        &'a self,
+        offsets: &'a [(i8, i8)],
+        radius: usize,
+        x: usize,
+        y: usize
+    ) -> impl Iterator<Item = (usize, usize)> + 'a {
+        offsets.iter()
+            .filter(move |coord| {
+                coord.0.unsigned_abs() as usize <= radius && coord.1.unsigned_abs() as usize <= radius
+            })
+            .filter_map(move |coord| self.shift(x, y, coord.0 as isize, coord.1 as isize))
+    }
+
+    /// Возвращает ячейки, которые воспринимает животное в заданной ячейке с заданными
+    /// направлением и радиусом зрения, по областям восприятия. Те же ячейки
+    /// использует восприятие животных (`percept`).
+    ///
+    /// # Arguments
+    ///
+    /// * `x`, `y`: Координаты животного.
+    /// * `direction`: Направление животного.
+    /// * `radius`: Радиус зрения.
+    ///
+    /// returns: PerceptionFootprint
+    pub fn perception_footprint(
+        &self,
+        x: usize,
+        y: usize,
+        direction: AnimalDirection,
+        radius: usize
+    ) -> PerceptionFootprint {
+        let [front, left, right, proximity] = Self::perception_areas(direction);
+
+        PerceptionFootprint {
+            front: self.area_cells(front, radius, x, y).collect(),
+            left: self.area_cells(left, radius, x, y).collect(),
+            right: self.area_cells(right, radius, x, y).collect(),
+            proximity: self.area_cells(proximity, radius, x, y).collect(),
+        }
+    }
+
+    /// Возвращает ячейки, которые воспринимает живое животное с заданным
+    /// идентификатором (см. `perception_footprint`). Ячейку животного, решения
+    /// которого записываются (см. `set_traced_animal`), мир знает и не ищет.
+    ///
+    /// # Arguments
+    ///
+    /// * `agent_id`: Идентификатор животного.
+    ///
+    /// returns: Option<PerceptionFootprint> - None, если живого животного нет.
+    pub fn animal_perception(&self, agent_id: u64) -> Option<PerceptionFootprint> {
+        let (x, y) = self.animal_cell(agent_id)?;
+
+        match self.animal_at(x, y) {
+            Some(animal) if !animal.is_dead() => Some(
+                self.perception_footprint(x, y, animal.get_direction(), animal.get_vision_radius())
+            ),
+            _ => None,
+        }
+    }
+
+    /// Метод вычисляет количество агентов в точках которые переданы срезом.
+    ///
+    /// # Arguments
+    ///
+    /// * `offsets`: Срез смещений относительно заданной точки.
+    /// * `radius`: Радиус зрения: смещения, превышающие радиус хотя бы по одной оси,
+    ///   не учитываются.
+    /// * `x`: Координата "x" точки относительно которой ищутся агенты.
+    /// * `y`: Координата "y" точки относительно которой ищутся агенты.
+    /// * `perceiver`: Идентификатор воспринимающего животного, оно не учитывается.
+    ///
+    /// Returns: (usize, usize, usize) - количество растений, травоядных, хищников.
+    fn count_agents_in_area(
+        &self,
+        offsets: &[(i8, i8)],
+        radius: usize,
+        x: usize,
+        y: usize,
+        perceiver: u64
+    ) -> (usize, usize, usize) {
+        let mut plants: usize = 0;
+        let mut herbivores: usize = 0;
+        let mut carnivores: usize = 0;
+
+        for (x_off, y_off) in self.area_cells(offsets, radius, x, y) {
+            if let Some(plant) = self.plant_at(x_off, y_off) {
+                if !plant.is_eaten() {
+                    plants += 1;
+                }
+            }
+
+            if let Some(animal) = self.animal_at(x_off, y_off) {
+                if !animal.is_dead() && animal.get_id() != perceiver {
+                    match animal.get_type() {
+                        AnimaType::Herbivore => {
+                            herbivores += 1;
+                        }
+                        // Для восприятия всеядное так же опасно, как хищник.
+                        AnimaType::Carnivore | AnimaType::Omnivore => {
+                            carnivores += 1;
+                        }
+                    }
+                }
+            }
+
+        }
+
+        (plants, herbivores, carnivores)
+    }
+
+    /// Реализует поворот животного на лево.
+    fn turn_left_animal_action(&mut self, animal: &mut StoredAnimal, _x: usize, _y: usize) {
+        animal.turn_action(true);
+    }
+
+    /// Реализует поворот животного на право.
+    fn turn_right_animal_action(&mut self, animal: &mut StoredAnimal, _x: usize, _y: usize) {
+        animal.turn_action(false);
+    }
+
+    /// Implements the move function.
+    ///
+    /// # Arguments
+    ///
+    /// * `animal`: Изменяемая ссылка на животное.
+    /// * `x`: Положение животного по "x".
+    /// * `y`: Положение животного по "y".
+    ///
+    /// returns: ()
+    fn movement_animal_action(&mut self, animal: &mut StoredAnimal, x: usize, y: usize) {
+        // Определим координаты новой точки местоположения животного.
+        let offset = Self::ahead_area(animal.get_direction())[0];
+        let coords = match self.shift(x, y, offset.0 as isize, offset.1 as isize) {
+            Some(coords) => coords,
+            None => {
+                // Впереди стена.
+                animal.move_action(false);
+                return;
+            }
+        };
+
+        // Непроходимая ячейка: животное упирается в нее, как в другое животное.
+        if self.is_blocked(coords.0, coords.1) {
+            animal.move_action(false);
+            return;
+        }
+
+        // Проверить возможность движения.
         match self.landscape[coords.0][coords.1].animal {
             AnimalInCell::Animal(_) => {
                 // В точке есть другое животное.
                 animal.move_action(false);
             },
-            AnimalInCell::None => {
-                // Точка свободна, перемещаемся.
-                self.landscape[coords.0][coords.1].animal = self.landscape[x][y].animal;
-                self.landscape[x][y].animal = AnimalInCell::None;
+            AnimalInCell::None => {
+                // Точка свободна, перемещаемся.
+                self.landscape[coords.0][coords.1].animal = self.landscape[x][y].animal;
+                self.landscape[x][y].animal = AnimalInCell::None;
+                if self.traced_animal == Some(animal.get_id()) {
+                    self.traced_position = Some(coords);
+                }
+                if let Some(cell) = self.pending_parents.get_mut(&animal.get_id()) {
+                    *cell = coords;
+                }
+
+                animal.move_action(true);
+            },
+        }
+    }
+
+    /// Реализует функцию поедания у животного. Возможность съесть что-то определяется ранее,
+    /// в методе Self::percept, где животно анализирует текущую обстановку.
+    ///
+    /// # Arguments
+    ///
+    /// * `animal`: Изменяемая ссылка на животное.
+    /// * `x`: Положение животного по "x".
+    /// * `y`: Положение животного по "y".
+    ///
+    /// Returns: ()
+    fn eating_animal_action(&mut self, animal: &mut StoredAnimal, x: usize, y: usize) {
+        match animal.get_type() {
+            // Травоядное ест траву
+            AnimaType::Herbivore => {
+                self.eat_attempts.0 += 1;
+                let targeting = self.eat_targeting.0;
+
+                let area = self.eat_area(animal.get_direction());
+                let coord = match self.recall_plant(animal, x, y, area) {
+                    Some(coord) => Some(coord),
+                    None => self.choose_plant(x, y, area, targeting),
+                };
+
+                match coord {
+                    Some(coord) => {
+                        // Получить растение по координатам
+                        if let PlantInCell::Plant(handle) = self.landscape[coord.0][coord.1].plant {
+                            let plant = self.plants.get_mut(handle)
+                                .expect("Внутренняя ошибка программы: дескриптор растения в ячейке устарел");
+
+                            let energy = plant.be_eaten();
+                            self.eaten_energy.0 += energy;
+                            self.eat_successes.0 += 1;
+                            self.animal_stats.entry(animal.get_id()).or_default().meals += 1;
+
+                            animal.eat_action(energy);
+
+                            if self.food_focus && !plant.is_eaten() {
+                                animal.remember_food(Some(coord));
+                            }
+
+                            self.emit(|tick| WorldEvent::Ate {
+                                tick, predator: AnimaType::Herbivore, prey: PreyKind::Plant, at: coord
+                            });
+                        }
+                    }
+                    None if self.scavenging.0 => {
+                        // Растений нет, но травоядное может съесть падаль.
+                        self.scavenge(animal, x, y, area);
+                    }
+                    None => {
+                        // Есть нечего: животное ошиблось.
+                    }
+                }
+
+            }
+            // Хищник поедает травоядное или всеядное
+            AnimaType::Carnivore => {
+                self.eat_attempts.1 += 1;
+                let targeting = self.eat_targeting.1;
+
+                let area = self.eat_area(animal.get_direction());
+
+                // При двухшаговой охоте хищник ест только туши убитых животных.
+                if self.two_step_predation {
+                    match self.choose_corpse(x, y, area) {
+                        Some(coord) => self.eat_corpse(animal, coord, 1.0),
+                        None if self.scavenging.1 => self.scavenge(animal, x, y, area),
+                        None => {}
+                    }
+                    return;
+                }
+
+                let coord = self.choose_animal(&CARNIVORE_PREY, x, y, area, targeting);
+
+                match coord {
+                    Some(coord) => {
+                        // Получить жертву по координатам
+                        if let AnimalInCell::Animal(handle) = self.landscape[coord.0][coord.1].animal {
+                            let herb = self.animals.get_mut(handle)
+                                .expect("Внутренняя ошибка программы: дескриптор животного в ячейке устарел");
+
+                            let prey = herb.get_type();
+                            if prey == AnimaType::Carnivore {
+                                panic!("Хищник хочет съесть хищника!");
+                            }
+
+                            let energy = herb.be_eaten();
+                            // Съеденное животное теряет возможность совершать действия, т.к. мертво.
+                            herb.mark_processed(self.iteration as u64);
+                            self.eaten_energy.1 += energy;
+                            self.eat_successes.1 += 1;
+                            let stats = self.animal_stats.entry(animal.get_id()).or_default();
+                            stats.kills += 1;
+                            stats.meals += 1;
+                            self.transient_events.push((coord.0, coord.1, EventKind::Kill));
+
+                            animal.eat_action(energy);
+
+                            self.emit(|tick| WorldEvent::Ate {
+                                tick,
+                                predator: AnimaType::Carnivore,
+                                prey: PreyKind::Animal(prey),
+                                at: coord,
+                            });
+                        }
+                    }
+                    None if self.scavenging.1 => {
+                        // Жертв нет, но хищник может съесть падаль.
+                        self.scavenge(animal, x, y, area);
+                    }
+                    None => {
+                        // Есть нечего: животное ошиблось.
+                    }
+                }
+            }
+            // Всеядное ест растение, а если растений рядом нет - травоядное.
+            AnimaType::Omnivore => {
+                self.eat_attempts.2 += 1;
+                let (plant_rate, meat_rate) = self.omnivore_energy_rate;
+
+                let area = self.eat_area(animal.get_direction());
+                if let Some(coord) = self.choose_plant(x, y, area, self.eat_targeting.2) {
+                    if let PlantInCell::Plant(handle) = self.landscape[coord.0][coord.1].plant {
+                        let plant = self.plants.get_mut(handle)
+                            .expect("Внутренняя ошибка программы: дескриптор растения в ячейке устарел");
+
+                        let energy = (plant_rate * plant.be_eaten() as f64) as Energy;
+                        self.eaten_energy.2 += energy;
+                        self.eat_successes.2 += 1;
+                        self.animal_stats.entry(animal.get_id()).or_default().meals += 1;
+
+                        animal.eat_action(energy);
+
+                        self.emit(|tick| WorldEvent::Ate {
+                            tick, predator: AnimaType::Omnivore, prey: PreyKind::Plant, at: coord
+                        });
+                    }
+                    return;
+                }
+
+                // При двухшаговой охоте всеядное ест только туши убитых животных.
+                if self.two_step_predation {
+                    match self.choose_corpse(x, y, area) {
+                        Some(coord) => self.eat_corpse(animal, coord, meat_rate),
+                        None if self.scavenging.2 => self.scavenge(animal, x, y, area),
+                        None => {}
+                    }
+                    return;
+                }
+
+                let coord = self.choose_animal(&OMNIVORE_PREY, x, y, area, self.eat_targeting.2);
+                if let Some(coord) = coord {
+                    if let AnimalInCell::Animal(handle) = self.landscape[coord.0][coord.1].animal {
+                        let herb = self.animals.get_mut(handle)
+                            .expect("Внутренняя ошибка программы: дескриптор животного в ячейке устарел");
+
+                        let energy = (meat_rate * herb.be_eaten() as f64) as Energy;
+                        herb.mark_processed(self.iteration as u64);
+                        self.eaten_energy.2 += energy;
+                        self.eat_successes.2 += 1;
+                        let stats = self.animal_stats.entry(animal.get_id()).or_default();
+                        stats.kills += 1;
+                        stats.meals += 1;
+                        self.transient_events.push((coord.0, coord.1, EventKind::Kill));
+
+                        animal.eat_action(energy);
+
+                        self.emit(|tick| WorldEvent::Ate {
+                            tick,
+                            predator: AnimaType::Omnivore,
+                            prey: PreyKind::Animal(AnimaType::Herbivore),
+                            at: coord,
+                        });
+                    }
+                } else if self.scavenging.2 {
+                    // Ни растений, ни жертв нет, но всеядное может съесть падаль.
+                    self.scavenge(animal, x, y, area);
+                }
+            }
+        }
+    }
+
+    /// Реализует нападение животного при двухшаговой охоте (см.
+    /// `set_two_step_predation`). Хищник и всеядное нападают на травоядное в области
+    /// поедания; удачное нападение убивает жертву, и в ее ячейке остается туша.
+    /// Травоядное нападать не умеет, а без двухшаговой охоты хищник убивает жертву
+    /// действием "есть": в обоих случаях нападение тратит энергию впустую.
+    ///
+    /// # Arguments
+    ///
+    /// * `animal`: Изменяемая ссылка на животное.
+    /// * `x`, `y`: Положение животного.
+    ///
+    /// returns: ()
+    fn attack_animal_action(&mut self, animal: &mut StoredAnimal, x: usize, y: usize) {
+        animal.attack_action();
+
+        let predator = animal.get_type();
+        match predator {
+            AnimaType::Herbivore => {
+                self.attack_attempts.0 += 1;
+                return;
+            }
+            AnimaType::Carnivore => self.attack_attempts.1 += 1,
+            AnimaType::Omnivore => self.attack_attempts.2 += 1,
+        }
+
+        if !self.two_step_predation {
+            return;
+        }
+
+        let area = self.eat_area(animal.get_direction());
+        let (prey_types, targeting) = match predator {
+            AnimaType::Omnivore => (&OMNIVORE_PREY[..], self.eat_targeting.2),
+            _ => (&CARNIVORE_PREY[..], self.eat_targeting.1),
+        };
+        let coord = match self.choose_animal(prey_types, x, y, area, targeting) {
+            Some(coord) => coord,
+            None => return,
+        };
+
+        if let AnimalInCell::Animal(handle) = self.landscape[coord.0][coord.1].animal {
+            let prey = self.animals.get_mut(handle)
+                .expect("Внутренняя ошибка программы: дескриптор животного в ячейке устарел");
+
+            // Жертва с запасом энергии отбивается чаще.
+            let attacker_energy = animal.get_energy().max(0 as Energy) as f64;
+            let total_energy = attacker_energy + prey.get_energy().max(0 as Energy) as f64;
+            let chance = if total_energy > 0.0 {
+                (self.attack_success_rate * 2.0 * attacker_energy / total_energy).min(1.0)
+            } else {
+                0.0
+            };
+            if !self.rng.gen_bool(chance) {
+                return;
+            }
+
+            let prey_type = prey.get_type();
+            let energy = prey.be_killed();
+            // Убитое животное теряет возможность совершать действия, т.к. мертво.
+            prey.mark_processed(self.iteration as u64);
+
+            let cell = &mut self.landscape[coord.0][coord.1];
+            cell.corpse += energy;
+            cell.corpse_since = self.iteration;
+
+            match predator {
+                AnimaType::Herbivore => {}
+                AnimaType::Carnivore => self.kills.1 += 1,
+                AnimaType::Omnivore => self.kills.2 += 1,
+            }
+            self.animal_stats.entry(animal.get_id()).or_default().kills += 1;
+            self.transient_events.push((coord.0, coord.1, EventKind::Kill));
+
+            self.emit(|tick| WorldEvent::Killed { tick, predator, prey: prey_type, at: coord });
+        }
+    }
+
+    /// Возвращает координаты первой (в порядке смещений области) ячейки с тушей.
+    ///
+    /// # Arguments
+    ///
+    /// * `x`, `y`: Положение животного.
+    /// * `area`: Область поедания.
+    ///
+    /// returns: Option<(usize, usize)>
+    fn choose_corpse(&self, x: usize, y: usize, area: &[(i8, i8)]) -> Option<(usize, usize)> {
+        area.iter()
+            .filter_map(|offset| self.shift(x, y, offset.0 as isize, offset.1 as isize))
+            .find(|&(x_off, y_off)| self.landscape[x_off][y_off].corpse > 0 as Energy)
+    }
+
+    /// Животное съедает тушу в ячейке целиком. Тушу может съесть любое хищное или
+    /// всеядное животное, а не только убившее жертву.
+    ///
+    /// # Arguments
+    ///
+    /// * `animal`: Изменяемая ссылка на животное.
+    /// * `coord`: Координаты ячейки с тушей.
+    /// * `energy_rate`: Доля энергии туши, которую получает животное.
+    ///
+    /// returns: ()
+    fn eat_corpse(&mut self, animal: &mut StoredAnimal, coord: (usize, usize), energy_rate: f64) {
+        let corpse = mem::replace(&mut self.landscape[coord.0][coord.1].corpse, 0 as Energy);
+        let energy = (energy_rate * corpse as f64) as Energy;
+
+        let (eaten_energy, eat_successes) = match animal.get_type() {
+            AnimaType::Herbivore => (&mut self.eaten_energy.0, &mut self.eat_successes.0),
+            AnimaType::Carnivore => (&mut self.eaten_energy.1, &mut self.eat_successes.1),
+            AnimaType::Omnivore => (&mut self.eaten_energy.2, &mut self.eat_successes.2),
+        };
+        *eaten_energy += energy;
+        *eat_successes += 1;
+        self.animal_stats.entry(animal.get_id()).or_default().meals += 1;
+
+        animal.eat_action(energy);
+
+        let predator = animal.get_type();
+        self.emit(|tick| WorldEvent::Ate { tick, predator, prey: PreyKind::Corpse, at: coord });
+    }
+
+    /// Животное поедает падаль в области поедания. Падаль убывает на съеденную
+    /// энергию, животное получает ее часть.
+    ///
+    /// # Arguments
+    ///
+    /// * `animal`: Изменяемая ссылка на животное.
+    /// * `x`, `y`: Положение животного.
+    /// * `area`: Область поедания.
+    ///
+    /// returns: ()
+    fn scavenge(&mut self, animal: &mut StoredAnimal, x: usize, y: usize, area: &[(i8, i8)]) {
+        for offset in area {
+            let (x_off, y_off) = match self.shift(x, y, offset.0 as isize, offset.1 as isize) {
+                Some(coord) => coord,
+                None => continue,
+            };
+
+            let carrion = &mut self.landscape[x_off][y_off].carrion;
+            if *carrion > 0 as Energy {
+                let bite = carrion.min(self.carrion_bite);
+                *carrion -= bite;
+
+                let energy = (self.carrion_energy_rate * bite as f64) as Energy;
+                let (carrion_energy, eaten_energy, eat_successes) = match animal.get_type() {
+                    AnimaType::Herbivore => (&mut self.carrion_energy.0, &mut self.eaten_energy.0, &mut self.eat_successes.0),
+                    AnimaType::Carnivore => (&mut self.carrion_energy.1, &mut self.eaten_energy.1, &mut self.eat_successes.1),
+                    AnimaType::Omnivore => (&mut self.carrion_energy.2, &mut self.eaten_energy.2, &mut self.eat_successes.2),
+                };
+                *carrion_energy += energy;
+                *eaten_energy += energy;
+                *eat_successes += 1;
+                self.animal_stats.entry(animal.get_id()).or_default().meals += 1;
+
+                animal.eat_action(energy);
+
+                let predator = animal.get_type();
+                self.emit(|tick| WorldEvent::Ate {
+                    tick, predator, prey: PreyKind::Carrion, at: (x_off, y_off)
+                });
+
+                return;
+            }
+        }
+    }
+
+    /// Возвращает координаты растения, которое животное ело в прошлый раз, если
+    /// сосредоточенное поедание включено, растение не съедено полностью и находится
+    /// в области, где животное может есть. Иначе животное забывает растение.
+    ///
+    /// # Arguments
+    ///
+    /// * `animal`: Изменяемая ссылка на животное.
+    /// * `x`, `y`: Положение животного.
+    /// * `area`: Область, в которой животное может есть.
+    ///
+    /// returns: Option<(usize, usize)>
+    fn recall_plant(
+        &self,
+        animal: &mut StoredAnimal,
+        x: usize,
+        y: usize,
+        area: &[(i8, i8)]
+    ) -> Option<(usize, usize)> {
+        if !self.food_focus {
+            return None;
+        }
+
+        let coord = animal.recall_food()?;
+        animal.remember_food(None);
+
+        let in_range = area.iter()
+            .any(|offset| self.shift(x, y, offset.0 as isize, offset.1 as isize) == Some(coord));
+
+        if let Some(plant) = self.plant_at(coord.0, coord.1) {
+            if in_range && !plant.is_eaten() {
+                return Some(coord);
+            }
+        }
+
+        None
+    }
+
+    /// Возвращает смещение ячейки непосредственно перед животным.
+    ///
+    /// # Arguments
+    ///
+    /// * `direction`: Направление животного.
+    ///
+    /// returns: &[(i8, i8)] - срез из одного смещения.
+    fn ahead_area(direction: AnimalDirection) -> &'static [(i8, i8)] {
+        match direction {
+            AnimalDirection::North => &NORTH_AHEAD,
+            AnimalDirection::South => &SOUTH_AHEAD,
+            AnimalDirection::West => &WEST_AHEAD,
+            AnimalDirection::East => &EAST_AHEAD,
+        }
+    }
+
+    /// Возвращает область, в которой животное может найти себе еду: область близости,
+    /// или, в строгом режиме, только ячейку перед животным.
+    ///
+    /// # Arguments
+    ///
+    /// * `direction`: Направление животного.
+    ///
+    /// returns: &[(i8, i8)]
+    fn eat_area(&self, direction: AnimalDirection) -> &'static [(i8, i8)] {
+        if self.strict_eating {
+            return Self::ahead_area(direction);
+        }
+
+        match direction {
+            AnimalDirection::North => &NORTH_PROXIMITY,
+            AnimalDirection::South => &SOUTH_PROXIMITY,
+            AnimalDirection::West => &WEST_PROXIMITY,
+            AnimalDirection::East => &EAST_PROXIMITY,
+        }
+    }
+
+    /// Метод находит растение в области, точки которой переданы срезом.
+    ///
+    /// # Arguments
+    ///
+    /// * `x`, `y`: Координаты относительно которой берутся смещения из области.
+    /// * `area`: Область смещения.
+    /// * `targeting`: Способ выбора растения, если их в области несколько.
+    ///
+    /// returns: Option<(usize, usize)>
+    fn choose_plant(
+        &mut self,
+        x: usize,
+        y: usize,
+        area: &[(i8, i8)],
+        targeting: EatTargeting
+    ) -> Option<(usize, usize)> {
+        if targeting == EatTargeting::Random {
+            // Перемешаем смещения области, что бы получить случайное растение,
+            // если их несколько в ближайшей области.
+            let (shuffled, count) = shuffle_area(area, &mut self.rng);
+
+            for offset in &shuffled[..count] {
+                let (x_off, y_off) = match self.shift(x, y, offset.0 as isize, offset.1 as isize) {
+                    Some(coord) => coord,
+                    None => continue,
+                };
+
+                // Растение, полностью съеденное ранее на этой итерации, недоступно.
+                if let Some(plant) = self.plant_at(x_off, y_off) {
+                    if !plant.is_eaten() {
+                        return Some((x_off, y_off));
+                    }
+                }
+            }
+
+            return None;
+        }
+
+        // Просматриваем всех кандидатов в порядке следования смещений в области,
+        // при равенстве энергий побеждает первый найденный.
+        let mut best: Option<((usize, usize), Energy)> = None;
+
+        for offset in area {
+            let (x_off, y_off) = match self.shift(x, y, offset.0 as isize, offset.1 as isize) {
+                Some(coord) => coord,
+                None => continue,
+            };
+
+            if let Some(plant) = self.plant_at(x_off, y_off) {
+                if plant.is_eaten() {
+                    continue;
+                }
+
+                let energy = plant.get_energy();
+                if Self::is_better_target(targeting, energy, best) {
+                    best = Some(((x_off, y_off), energy));
+                }
+            }
+        }
+
+        best.map(|(coord, _)| coord)
+    }
+
+    /// Метод находит животное в области, точки которой переданы срезом.
+    ///
+    /// # Arguments
+    ///
+    /// * `prey`: Виды животных, которых мы ищем.
+    /// * `x`, `y`: Координаты относительно которой берутся смещения из области.
+    /// * `area`: Область смещения.
+    /// * `targeting`: Способ выбора жертвы, если их в области несколько.
+    ///
+    /// returns: Option<(usize, usize)>
+    fn choose_animal(
+        &mut self,
+        prey: &[AnimaType],
+        x: usize,
+        y: usize,
+        area: &[(i8, i8)],
+        targeting: EatTargeting
+    ) -> Option<(usize, usize)> {
+        if targeting == EatTargeting::Random {
+            // Перемешаем смещения области, что бы получить случайное животное,
+            // если их несколько в ближайшей области.
+            let (shuffled, count) = shuffle_area(area, &mut self.rng);
+
+            for offset in &shuffled[..count] {
+                let (x_off, y_off) = match self.shift(x, y, offset.0 as isize, offset.1 as isize) {
+                    Some(coord) => coord,
+                    None => continue,
+                };
+
+                // В точке есть животное
+                if let Some(animal) = self.animal_at(x_off, y_off) {
+                    // Проверим тип животного. Животное, съеденное ранее на этой
+                    // итерации, недоступно.
+                    if !prey.contains(&animal.get_type()) || animal.is_dead() {
+                        continue;
+                    }
+
+                    // Молодое животное защищено: хищник его пропускает.
+                    if self.is_protected(animal) {
+                        self.record_protected_skip(animal.get_type());
+                        continue;
+                    }
+
+                    return Some((x_off, y_off));
+                }
+            }
+
+            return None;
+        }
+
+        // Просматриваем всех кандидатов в порядке следования смещений в области,
+        // при равенстве энергий побеждает первый найденный.
+        let mut best: Option<((usize, usize), Energy)> = None;
+
+        for offset in area {
+            let (x_off, y_off) = match self.shift(x, y, offset.0 as isize, offset.1 as isize) {
+                Some(coord) => coord,
+                None => continue,
+            };
+
+            if let Some(animal) = self.animal_at(x_off, y_off) {
+                if !prey.contains(&animal.get_type()) || animal.is_dead() {
+                    continue;
+                }
+
+                if self.is_protected(animal) {
+                    self.record_protected_skip(animal.get_type());
+                    continue;
+                }
+
+                let energy = animal.get_energy();
+                if Self::is_better_target(targeting, energy, best) {
+                    best = Some(((x_off, y_off), energy));
+                }
+            }
+        }
+
+        best.map(|(coord, _)| coord)
+    }
+
+    /// Сравнивает энергию кандидата с лучшим найденным ранее в соответствии со
+    /// способом выбора цели. Равенство энергий не считается улучшением.
+    ///
+    /// # Arguments
+    ///
+    /// * `targeting`: Способ выбора цели.
+    /// * `energy`: Энергия кандидата.
+    /// * `best`: Лучший кандидат, найденный ранее.
+    ///
+    /// returns: bool
+    fn is_better_target(
+        targeting: EatTargeting,
+        energy: Energy,
+        best: Option<((usize, usize), Energy)>
+    ) -> bool {
+        // Цель с неопределенной энергией никогда не выбирается.
+        if energy.is_nan() {
+            return false;
+        }
+
+        match best {
+            None => true,
+            Some((_, best_energy)) => match targeting {
+                EatTargeting::RichestFirst => cmp_energy(energy, best_energy) == Ordering::Greater,
+                EatTargeting::WeakestFirst => cmp_energy(energy, best_energy) == Ordering::Less,
+                EatTargeting::Random => false,
+            },
+        }
+    }
+
+    /// Метод реализует размножение животного.
+    ///
+    /// An animal has reached the energy level needed for reproduction. An animal
+    /// is only permitted to reproduce if space is available for the new animal.
+    /// The child animal is a copy of the parent, except that one of the weights
+    /// of the neural network of his brain is mutated.
+    ///
+    /// # Arguments
+    ///
+    /// * `animal`: Изменяемая ссылка на животное.
+    /// * `x`, `y`: Положение животного.
+    ///
+    /// returns: ()
+    fn reproduce_animal_action(&mut self, animal: &mut StoredAnimal, x: usize, y: usize) {
+        let agent_type = AgentType::from(animal.get_type());
+
+        // Лимит рождений на итерации исчерпан: размножение откладывается,
+        // животное сохраняет энергию. При вынашивании лимит расходуют зачатия:
+        // потомки, зачатые на одной итерации, рождаются на одной итерации.
+        let used = if self.gestation_ticks > 0 { self.conceptions } else { self.births };
+        let (births, budget, deferred) = match animal.get_type() {
+            AnimaType::Herbivore => (used.0, self.birth_budget.0, &mut self.deferred_births.0),
+            AnimaType::Carnivore => (used.1, self.birth_budget.1, &mut self.deferred_births.1),
+            AnimaType::Omnivore => (used.2, self.birth_budget.2, &mut self.deferred_births.2),
+        };
+        if budget > 0 && births >= budget {
+            *deferred += 1;
+            return;
+        }
+
+        let mutation_rate = match animal.get_type() {
+            AnimaType::Herbivore => self.mutation_rate.0,
+            AnimaType::Carnivore => self.mutation_rate.1,
+            AnimaType::Omnivore => self.mutation_rate.2,
+        };
+
+        // Потомок вынашивается: место для него ищется при рождении.
+        if self.gestation_ticks > 0 {
+            let parent_id = animal.get_id();
+            let pending = self.pending_births.iter().filter(|birth| birth.parent_id == parent_id).count();
+            if self.max_pending_births > 0 && pending >= self.max_pending_births {
+                *deferred += 1;
+                return;
+            }
+
+            if let AnimalInCell::Animal(parent) = self.landscape[x][y].animal {
+                let child = animal.reproduce_action(&mut self.rng, mutation_rate);
+                self.species_budget(child.get_type()).offspring += child.get_energy();
+                self.pending_births.push(PendingBirth {
+                    parent_id,
+                    parent,
+                    child,
+                    hatch_at: self.iteration + self.gestation_ticks,
+                });
+                self.pending_parents.insert(parent_id, (x, y));
+
+                match animal.get_type() {
+                    AnimaType::Herbivore => self.conceptions.0 += 1,
+                    AnimaType::Carnivore => self.conceptions.1 += 1,
+                    AnimaType::Omnivore => self.conceptions.2 += 1,
+                }
+            }
+            return;
+        }
+
+        let placement = self.reproduction_placement.1;
+        let spot = self.find_offspring_spot(agent_type, x, y, placement);
+
+        match spot {
+            // Нашлось место для размножения.
+            Ok(coord) => {
+                let child_id = self.next_animal_id;
+
+                // Новорожденный по возможности занимает память умершего животного.
+                let child = match self.spare_animals.pop() {
+                    Some(mut spare) => {
+                        if animal.reproduce_into(spare.as_animal_mut(), &mut self.rng, mutation_rate) {
+                            spare
+                        } else {
+                            // Память животного другого типа возвращается в пул.
+                            self.spare_animals.push(spare);
+                            StoredAnimal::store(animal.reproduce_action(&mut self.rng, mutation_rate))
+                        }
+                    }
+                    None => StoredAnimal::store(animal.reproduce_action(&mut self.rng, mutation_rate)),
+                };
+                let generation = child.get_generation();
+                self.species_budget(child.get_type()).offspring += child.get_energy();
+
+                self.add_stored_animal(coord.0, coord.1, child)
+                    .expect("Внутренняя ошибка программы: найденное место для животного уже занято");
+
+                self.record_birth(animal.get_id(), animal.get_type(), coord, child_id, generation);
+            }
+            // Нет места для размножения: животное тратит ход впустую и узнает об этом.
+            Err(_) => {
+                animal.reproduce_blocked();
+
+                match animal.get_type() {
+                    AnimaType::Herbivore => self.blocked_births.0 += 1,
+                    AnimaType::Carnivore => self.blocked_births.1 += 1,
+                    AnimaType::Omnivore => self.blocked_births.2 += 1,
+                }
+            }
+        }
+    }
+
+    /// Учитывает рождение потомка животного в статистике. Потомок, уже помещенный
+    /// в ячейку, отмечается как совершивший ход на итерации рождения (см.
+    /// `AnimalAlive::mark_processed`).
+    ///
+    /// # Arguments
+    ///
+    /// * `parent_id`: Идентификатор родителя.
+    /// * `animal_type`: Вид животного.
+    /// * `coord`: Положение потомка.
+    /// * `child_id`: Идентификатор потомка.
+    /// * `generation`: Поколение потомка.
+    ///
+    /// returns: ()
+    fn record_birth(
+        &mut self,
+        parent_id: u64,
+        animal_type: AnimaType,
+        coord: (usize, usize),
+        child_id: u64,
+        generation: usize
+    ) {
+        // Рождение - ход потомка: иначе потомок, оказавшийся в еще не обработанной
+        // ячейке, сделал бы ход на итерации рождения.
+        if let AnimalInCell::Animal(handle) = self.landscape[coord.0][coord.1].animal {
+            if let Some(child) = self.animals.get_mut(handle) {
+                child.mark_processed(self.iteration as u64);
+            }
+        }
+
+        self.animal_stats.entry(parent_id).or_default().offspring += 1;
+        self.transient_events.push((coord.0, coord.1, EventKind::Birth));
+        self.emit(|tick| WorldEvent::Born {
+            tick, species: animal_type, generation, at: coord, id: child_id, parent: parent_id
+        });
+
+        // Потомок члена отслеживаемой династии входит в династию.
+        if self.dynasty.contains(&parent_id) {
+            self.dynasty.insert(child_id);
+        }
+
+        match animal_type {
+            AnimaType::Herbivore => {
+                self.animal_reproductions.0 += 1;
+                self.births.0 += 1;
+                if self.animal_max_generation.0 < generation {
+                    self.animal_max_generation.0 = generation;
+                }
+            }
+            AnimaType::Carnivore => {
+                self.animal_reproductions.1 += 1;
+                self.births.1 += 1;
+                if self.animal_max_generation.1 < generation {
+                    self.animal_max_generation.1 = generation;
+                }
+            }
+            AnimaType::Omnivore => {
+                self.animal_reproductions.2 += 1;
+                self.births.2 += 1;
+                if self.animal_max_generation.2 < generation {
+                    self.animal_max_generation.2 = generation;
+                }
+            }
+        }
+    }
+
+    /// Вынашивание потомков: живые родители теряют энергию вынашивания, потомки,
+    /// срок которых наступил, появляются рядом с родителем.
+    fn hatch_pending_births(&mut self) {
+        for birth in mem::take(&mut self.pending_births) {
+            // Память умершего родителя могла достаться другому животному.
+            let alive = self.animals.get(birth.parent)
+                .is_some_and(|parent| parent.get_id() == birth.parent_id && !parent.is_dead());
+            let position = if alive { self.pending_parents.get(&birth.parent_id).copied() } else { None };
+
+            let (x, y) = match position {
+                Some(position) => position,
+                None => {
+                    self.failed_births += 1;
+                    continue;
+                }
+            };
+
+            if birth.hatch_at > self.iteration {
+                if let Some(parent) = self.animals.get_mut(birth.parent) {
+                    let energy = parent.get_energy();
+                    let drained = energy - parent.adjust_energy(-self.gestation_drain);
+                    let animal_type = parent.get_type();
+                    self.species_budget(animal_type).spent += drained;
+                }
+                self.pending_births.push(birth);
+                continue;
+            }
+
+            let animal_type = birth.child.get_type();
+            let placement = self.reproduction_placement.1;
+            match self.find_offspring_spot(AgentType::from(animal_type), x, y, placement) {
+                Ok(coord) => {
+                    let child_id = self.next_animal_id;
+                    let generation = birth.child.get_generation();
+
+                    self.add_stored_animal(coord.0, coord.1, StoredAnimal::store(birth.child))
+                        .expect("Внутренняя ошибка программы: найденное место для животного уже занято");
+                    self.record_birth(birth.parent_id, animal_type, coord, child_id, generation);
+                }
+                Err(_) => self.failed_births += 1,
+            }
+        }
+
+        // Родители, которые больше никого не вынашивают.
+        let parents: BTreeSet<u64> = self.pending_births.iter().map(|birth| birth.parent_id).collect();
+        self.pending_parents.retain(|parent_id, _| parents.contains(parent_id));
+    }
+
+    /// Действие - нет действия.
+    fn inactivity_animal_action(&mut self, animal: &mut StoredAnimal) {
+        animal.inactivity_action();
+    }
+
+    /// Завершающая обработка.
+    /// Удаляем мертвых животных из среды обитания, обновляем статистику,
+    /// определяем элементы для отображения, очищаем состояние животных.
+    fn final_processing(&mut self) {
+        // Очистим текущее состояние ячейки.
+        self.view_state.clear();
+
+        // Карта собирается только на итерациях, кадры которых запросило отображение,
+        // и только из запрошенных слоев.
+        let period = self.frame_request.every_nth_tick.max(1) as usize;
+        self.view_ready = (self.iteration + 1).is_multiple_of(period);
+        let layers = if self.view_ready {
+            self.frame_request.layers
+        } else {
+            LayerSet { plants: false, animals: false, corpses: false, dynasty: false }
+        };
+
+        for x in 0..self.width {
+            for y in 0..self.height {
+                let mut tmp_view: Vec<CellStuff> = Vec::with_capacity(CellStuff::None as usize);
+                // Отметки животного в ячейке: династия и защита по возрасту.
+                let mut marks = CellMarks::default();
+
+                // Непроходимая ячейка.
+                if self.view_ready && self.is_blocked(x, y) {
+                    tmp_view.push(CellStuff::Obstacle);
+                }
+
+                // Падаль.
+                if layers.corpses && self.landscape[x][y].carrion > 0 as Energy {
+                    tmp_view.push(CellStuff::Carcass);
+                }
+
+                // Туша убитого, но не съеденного животного.
+                if layers.corpses && self.landscape[x][y].corpse > 0 as Energy {
+                    tmp_view.push(CellStuff::KilledAnimal);
+                }
+
+                // Если в точке есть растение
+                if let PlantInCell::Plant(_) = self.landscape[x][y].plant {
+                    if layers.plants {
+                        tmp_view.push(CellStuff::Plant);
+                    }
+                    if let Some(ref mut occupancy) = self.occupancy {
+                        occupancy.0[x][y] = occupancy.0[x][y].saturating_add(1);
+                    }
+                }
+
+                // Если в точке есть животное.
+                if let AnimalInCell::Animal(handle) = self.landscape[x][y].animal {
+                    let mut animal = self.animals.take(handle)
+                        .expect("Внутренняя ошибка программы: дескриптор животного в ячейке устарел");
+                    marks.dynasty = layers.dynasty && self.dynasty.contains(&animal.get_id());
+
+                    // Мир жестокое место, и если животное не справилось его место в раю.
+                    // If energy falls to or below zero, the animal dies. Otherwise, we
+                    // check to see if the agent has lived longer than any other agent
+                    // of the particular type.
+                    let dead = animal.is_dead();
+                    if dead {
+                        // Отправляем животное в рай.
+                        self.send_to_heaven(&mut animal, x, y);
+
+                        if layers.corpses {
+                            if animal.is_eaten() || animal.is_killed() {
+                                tmp_view.push(CellStuff::KilledAnimal);
+                            } else {
+                                tmp_view.push(CellStuff::DeadAnimal);
+                            }
+                        }
+                    } else {
+                        // Очищаем состояние животного.
+                        animal.clear();
+                        // Обновляем статистику.
+                        self.update_best_animal(animal.as_animal());
+                        if let Some(ref mut occupancy) = self.occupancy {
+                            let grid = match animal.get_type() {
+                                AnimaType::Herbivore => &mut occupancy.1,
+                                AnimaType::Carnivore => &mut occupancy.2,
+                                AnimaType::Omnivore => &mut occupancy.3,
+                            };
+                            grid[x][y] = grid[x][y].saturating_add(1);
+                        }
+
+                        let stuff = match animal.get_type() {
+                            AnimaType::Herbivore => match animal.get_direction() {
+                                AnimalDirection::North => CellStuff::HerbBack,
+                                AnimalDirection::South => CellStuff::HerbFront,
+                                AnimalDirection::West => CellStuff::HerbLeft,
+                                AnimalDirection::East => CellStuff::HerbRight,
+                            },
+                            AnimaType::Carnivore => match animal.get_direction() {
+                                AnimalDirection::North => CellStuff::CarnBack,
+                                AnimalDirection::South => CellStuff::CarnFront,
+                                AnimalDirection::West => CellStuff::CarnLeft,
+                                AnimalDirection::East => CellStuff::CarnRight,
+                            },
+                            AnimaType::Omnivore => match animal.get_direction() {
+                                AnimalDirection::North => CellStuff::OmniBack,
+                                AnimalDirection::South => CellStuff::OmniFront,
+                                AnimalDirection::West => CellStuff::OmniLeft,
+                                AnimalDirection::East => CellStuff::OmniRight,
+                            },
+                        };
+
+                        if layers.animals {
+                            tmp_view.push(stuff);
+                            marks.juvenile = self.is_protected(&animal);
+                        }
+                    }
+
+                    if dead {
+                        // Умершее животное удаляется из хранилища (его дескриптор больше
+                        // ничего не находит), память достается пулу для новорожденных.
+                        self.spare_animals.push(animal);
+                        self.animals.discard(handle);
+                    } else {
+                        self.animals.restore(handle, animal);
+                    }
+                }
+
+                // После сбора того, что могло произойти в ячейке
+                // следует упорядочить события по важности.
+                tmp_view.sort();
+                // Добавляем состояние ячейки в массив отображения.
+                match tmp_view.first() {
+                    Some(stuff) => {
+                        self.view_state.push((x, y, *stuff, marks));
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Метод "очищает" мир от умершего животного.
+    ///
+    /// # Arguments
+    ///
+    /// * `animal`: Изменяемая ссылка на умершее животное.
+    /// * `x`, `y`: Координаты умершего животного.
+    ///
+    /// returns: ()
+    fn send_to_heaven(&mut self, animal: &mut StoredAnimal, x: usize, y: usize) {
+        // Death came to this animal (or it was eaten)...
+        // Удаляем животное из ячейки.
+        self.landscape[x][y].animal = AnimalInCell::None;
+
+        // Животное, умершее от голода, оставляет падаль. Тело убитого животного уже
+        // лежит в ячейке тушей.
+        let scavenging = self.scavenging.0 || self.scavenging.1 || self.scavenging.2;
+        if scavenging && !animal.is_eaten() && !animal.is_killed() {
+            self.landscape[x][y].carrion += animal.get_carcass_energy();
+            self.landscape[x][y].carrion_since = self.iteration;
+        }
+
+        // Умерший член династии из нее выбывает; когда умрет последний, династия
+        // перестанет отслеживаться.
+        self.dynasty.remove(&animal.get_id());
+
+        let stats = self.animal_stats.remove(&animal.get_id()).unwrap_or_default();
+
+        let cause = if animal.is_eaten() {
+            DeathCause::Eaten
+        } else if animal.is_killed() {
+            DeathCause::Killed
+        } else if animal.is_old() {
+            DeathCause::OldAge
+        } else {
+            DeathCause::Starved
+        };
+        let (species, age) = (animal.get_type(), animal.get_age());
+        self.emit(|tick| WorldEvent::Died { tick, species, age, cause, at: (x, y) });
+        self.record_postmortem(animal.as_animal(), &stats, cause, (x, y));
+
+        match animal.get_type() {
+            AnimaType::Herbivore => {
+                self.animal_count.0 -= 1;
+                self.animal_deaths.0 += 1;
+                if cause == DeathCause::OldAge {
+                    self.old_age_deaths.0 += 1;
+                }
+                self.oldest_death_age.0 = self.oldest_death_age.0.max(animal.get_age());
+
+                // Лучшим живым животным станет другое животное на следующей итерации.
+                if self.best_animal.0.as_ref().is_some_and(|best| best.id == animal.get_id()) {
+                    self.best_animal.0 = None;
+                }
+            }
+            AnimaType::Carnivore => {
+                self.animal_count.1 -= 1;
+                self.animal_deaths.1 += 1;
+                if cause == DeathCause::OldAge {
+                    self.old_age_deaths.1 += 1;
+                }
+                self.oldest_death_age.1 = self.oldest_death_age.1.max(animal.get_age());
+
+                // Лучшим живым животным станет другое животное на следующей итерации.
+                if self.best_animal.1.as_ref().is_some_and(|best| best.id == animal.get_id()) {
+                    self.best_animal.1 = None;
+                }
+            }
+            AnimaType::Omnivore => {
+                self.animal_count.2 -= 1;
+                self.animal_deaths.2 += 1;
+                if cause == DeathCause::OldAge {
+                    self.old_age_deaths.2 += 1;
+                }
+                self.oldest_death_age.2 = self.oldest_death_age.2.max(animal.get_age());
+
+                // Лучшим живым животным станет другое животное на следующей итерации.
+                if self.best_animal.2.as_ref().is_some_and(|best| best.id == animal.get_id()) {
+                    self.best_animal.2 = None;
+                }
+            }
+        }
+    }
+
+    /// Сохраняет запись о жизни умершего животного в истории умерших и, если
+    /// включено, отправляет ее в канал событий.
+    ///
+    /// # Arguments
+    ///
+    /// * `animal`: Умершее животное.
+    /// * `stats`: Статистика жизни животного.
+    /// * `cause`: Причина смерти.
+    /// * `at`: Координаты ячейки, в которой животное умерло.
+    ///
+    /// returns: ()
+    fn record_postmortem(&mut self, animal: &dyn AnimalAlive, stats: &PerAnimalStats, cause: DeathCause, at: (usize, usize)) {
+        if self.postmortems.is_none() && !(self.postmortem_events && self.event_sink.is_some()) {
+            return;
+        }
+
+        let genome_hash = animal.get_genome().map(|genome| {
+            let mut hasher = DefaultHasher::new();
+            for weight in genome {
+                weight.to_bits().hash(&mut hasher);
+            }
+            hasher.finish()
+        });
+
+        let record = Postmortem {
+            id: animal.get_id(),
+            species: animal.get_type(),
+            generation: animal.get_generation(),
+            age: animal.get_age(),
+            cause,
+            tick: self.iteration + 1,
+            at,
+            kills: stats.kills,
+            meals: stats.meals,
+            offspring: stats.offspring,
+            genome_hash,
+        };
+
+        if let Some(ref mut postmortems) = self.postmortems {
+            postmortems.push(record);
+        }
+        if self.postmortem_events {
+            self.emit(|_| WorldEvent::Postmortem(record));
+        }
+    }
+
+    /// Записи о жизни последних умерших животных (от старых к новым), см.
+    /// `set_postmortem_history`.
+    pub fn get_postmortems(&self) -> Vec<Postmortem> {
+        match self.postmortems {
+            Some(ref postmortems) => postmortems.last(usize::MAX).copied().collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Обновляет статистику жизни животного и информацию о лучшем (самом
+    /// приспособленном) животном.
+    fn update_best_animal(&mut self, animal: &dyn AnimalAlive) {
+
+        let stats = self.animal_stats.entry(animal.get_id()).or_default();
+        stats.energy_lifetime += animal.get_energy() as f64;
+        let fitness = self.fitness.score(animal, stats);
+
+        match animal.get_type() {
+            AnimaType::Herbivore => Self::keep_fittest(&mut self.best_animal.0, animal, fitness),
+            AnimaType::Carnivore => Self::keep_fittest(&mut self.best_animal.1, animal, fitness),
+            AnimaType::Omnivore => Self::keep_fittest(&mut self.best_animal.2, animal, fitness),
+        }
+    }
+
+    /// Заменяет снимок лучшего животного снимком животного, если оно приспособленнее
+    /// (или это то же животное, и снимок нужно обновить).
+    ///
+    /// # Arguments
+    ///
+    /// * `best`: Снимок текущего лучшего животного.
+    /// * `animal`: Животное.
+    /// * `fitness`: Приспособленность животного.
+    ///
+    /// returns: ()
+    fn keep_fittest(best: &mut Option<AnimalSnapshot>, animal: &dyn AnimalAlive, fitness: f64) {
+        let replace = match best {
+            Some(best) => fitness > best.fitness || animal.get_id() == best.id,
+            None => true,
+        };
+
+        if replace {
+            *best = Some(AnimalSnapshot::new(animal, fitness));
+        }
+    }
+
+    /// Задает оценку приспособленности, по которой выбираются лучшие животные.
+    ///
+    /// # Arguments
+    ///
+    /// * `fitness`: Оценка приспособленности.
+    ///
+    /// returns: ()
+    pub fn set_fitness(&mut self, fitness: &'static dyn Fitness) {
+        self.fitness = fitness;
+    }
+
+    /// Возвращает снимки живых лучших животных: первый элемент - травоядное, второй
+    /// хищник, третий всеядное.
+    pub fn get_best_animal(&self) -> (Option<&AnimalSnapshot>, Option<&AnimalSnapshot>, Option<&AnimalSnapshot>) {
+        (self.best_animal.0.as_ref(), self.best_animal.1.as_ref(), self.best_animal.2.as_ref())
+    }
+
+    /// Сохраняет в файл мозги лучших живых животных каждого вида, чтобы
+    /// заселить ими новый мир (см. `config::init::seed_population`).
+    ///
+    /// Формат строк: `<вид> alive <приспособленность> <геном мозга в JSON>` (загрузчик
+    /// принимает и строки `dead` из файлов прежних прогонов).
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: Путь к файлу.
+    ///
+    /// returns: Result<usize, RecoverableError> - количество сохраненных мозгов.
+    pub fn export_best_brains(&self, path: &str) -> Result<usize, RecoverableError> {
+        let champions = [
+            (AnimaType::Herbivore, "alive", &self.best_animal.0),
+            (AnimaType::Carnivore, "alive", &self.best_animal.1),
+            (AnimaType::Omnivore, "alive", &self.best_animal.2),
+        ];
+
+        let mut text = String::new();
+        let mut count = 0;
+        for (animal_type, state, snapshot) in champions {
+            if let Some(snapshot) = snapshot {
+                if let Some(ref json) = snapshot.genome_json {
+                    text.push_str(&format!(
+                        "{} {} {} {}\n", Self::species_name(animal_type), state, snapshot.fitness, json.trim()
+                    ));
+                    count += 1;
+                }
+            }
+        }
+
+        fs::write(path, text).map_err(|e| RecoverableError::new(fmt::format(format_args!(
+            "Не удалось сохранить мозги лучших животных в файл {}: {}", path, e
+        ))))?;
+
+        Ok(count)
+    }
+
+    /// Сохраняет занятость ячеек (см. `set_occupancy_tracking`) для каждого вида
+    /// (`plants`, `herbivores`, `carnivores`, `omnivores`) в файл CSV
+    /// `<prefix>_<вид>.csv` и тепловую карту `<prefix>_<вид>.png`. Файл CSV - матрица
+    /// количества итераций, строка матрицы соответствует координате y, столбец -
+    /// координате x. Тепловая карта - изображение в оттенках серого, пиксель на
+    /// ячейку: чем ярче, тем дольше ячейка была занята (самая занятая ячейка белая).
+    ///
+    /// # Arguments
+    ///
+    /// * `prefix`: Путь к файлам без суффикса вида и расширения.
+    ///
+    /// returns: Result<Vec<String>, RecoverableError> - пути к сохраненным файлам.
+    pub fn export_occupancy(&self, prefix: &str) -> Result<Vec<String>, RecoverableError> {
+        let occupancy = match self.occupancy {
+            Some(ref occupancy) => occupancy,
+            None => return Err(RecoverableError::new(
+                "Учет занятости ячеек не включен".to_string()
+            )),
+        };
+
+        let grids = [
+            ("plants", &occupancy.0),
+            ("herbivores", &occupancy.1),
+            ("carnivores", &occupancy.2),
+            ("omnivores", &occupancy.3),
+        ];
+        let mut paths = Vec::with_capacity(grids.len() * 2);
+        for (name, grid) in grids {
+            let mut text = String::new();
+            for y in 0..self.height {
+                let row: Vec<String> = grid.iter().map(|column| column[y].to_string()).collect();
+                text.push_str(&row.join(","));
+                text.push('\n');
+            }
+
+            let path = format!("{}_{}.csv", prefix, name);
+            fs::write(&path, text).map_err(|e| RecoverableError::new(fmt::format(format_args!(
+                "Не удалось сохранить занятость ячеек в файл {}: {}", path, e
+            ))))?;
+            paths.push(path);
+
+            let path = format!("{}_{}.png", prefix, name);
+            self.write_heatmap(&path, grid).map_err(|e| RecoverableError::new(fmt::format(format_args!(
+                "Не удалось сохранить тепловую карту в файл {}: {}", path, e
+            ))))?;
+            paths.push(path);
+        }
+
+        Ok(paths)
+    }
+
+    /// Сохраняет занятость ячеек одного вида тепловой картой PNG (см. `export_occupancy`).
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: Путь к файлу.
+    /// * `grid`: Занятость ячеек ([x][y]).
+    ///
+    /// returns: Result<(), String>
+    fn write_heatmap(&self, path: &str, grid: &OccupancyGrid) -> Result<(), String> {
+        let max = grid.iter().flatten().copied().max().unwrap_or(0).max(1) as u64;
+        let pixels: Vec<u8> = (0..self.height)
+            .flat_map(|y| grid.iter().map(move |column| (column[y] as u64 * 255 / max) as u8))
+            .collect();
+
+        let file = fs::File::create(path).map_err(|e| e.to_string())?;
+        let mut encoder = png::Encoder::new(std::io::BufWriter::new(file), self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        encoder.write_header()
+            .and_then(|mut writer| writer.write_image_data(&pixels))
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl MemoryTracker for Landscape {
+    fn tracker_name(&self) -> &'static str {
+        "пул умерших животных"
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.spare_animals.iter()
+            .map(|spare| spare.memory_size())
+            .sum()
+    }
+
+    /// Освобождает память самых давно умерших животных. Умершие животные уже удалены
+    /// из хранилища, поэтому освобождение безопасно.
+    fn shed_memory(&mut self, bytes: usize) -> usize {
+        let mut freed = 0;
+        let mut count = 0;
+
+        // Пул расходуется с конца, в начале пула - самые давно умершие животные.
+        for spare in &self.spare_animals {
+            if freed >= bytes {
+                break;
+            }
+
+            freed += spare.memory_size();
+            count += 1;
+        }
+
+        self.spare_animals.drain(..count);
+
+        freed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use crate::animal::brains::AnimalBrain;
+    use crate::animal::brains::simple::Brain;
+    use crate::animal::species::simple::{Animal, AnimalEnergyProfile, AnimalParams};
+    use crate::config::{
+        ANIMAL_BIRTH_ENERGY, ANIMAL_EATEN_ENERGY_RATE, ANIMAL_LIVE_ENERGY, CARRION_BITE_ENERGY, CARRION_ENERGY_RATE,
+        MAX_ANIMAL_ENERGY, MAX_PLANT_ENERGY, MAX_PLANT_GROW_ENERGY, PLANT_EATEN_ENERGY
+    };
+    use crate::plant::BiteMode;
+    use crate::policy::ReproductionFoodPolicy;
+    use crate::plant::simple::{Plant, PlantParams};
+    use crate::testing;
+    use crate::{analysis, summary};
+
+    /// Помещает в ячейку мира животное с параметрами по умолчанию, заданными
+    /// направлением и энергией.
+    fn place(
+        world: &mut Landscape,
+        (x, y): (usize, usize),
+        animal_type: AnimaType,
+        direction: AnimalDirection,
+        energy: Energy
+    ) {
+        let mut animal = testing::animal_facing(animal_type, testing::animal_params(), direction);
+        animal.set_energy(energy);
+
+        world.add_animal(x, y, animal).expect("Ячейка тестового мира занята");
+    }
+
+    const DIRECTIONS: [AnimalDirection; 4] = [
+        AnimalDirection::North, AnimalDirection::South, AnimalDirection::West, AnimalDirection::East
+    ];
+
+    /// Восприятие животного в ячейке. Животное не тратит ход.
+    fn percept_at(world: &mut Landscape, x: usize, y: usize) -> AnimalInputSignal {
+        let handle = match world.landscape[x][y].animal {
+            AnimalInCell::Animal(handle) => handle,
+            AnimalInCell::None => panic!("В ячейке ({}, {}) нет животного", x, y),
+        };
+
+        let mut animal = world.animals.take(handle).unwrap();
+        let inputs = world.percept(&mut animal, x, y);
+        world.animals.restore(handle, animal);
+
+        inputs
+    }
+
+    /// Показания растений в восприятии: впереди, слева, справа и поблизости.
+    fn plant_inputs(inputs: &AnimalInputSignal) -> (usize, usize, usize, usize) {
+        (inputs.plant_front, inputs.plant_left, inputs.plant_right, inputs.plant_proximity)
+    }
+
+    /// Смещения на одну ячейку вперед и влево для направления животного (ось "y"
+    /// направлена на юг).
+    fn forward_and_left(direction: AnimalDirection) -> ((isize, isize), (isize, isize)) {
+        match direction {
+            AnimalDirection::North => ((0, -1), (-1, 0)),
+            AnimalDirection::South => ((0, 1), (1, 0)),
+            AnimalDirection::West => ((-1, 0), (0, 1)),
+            AnimalDirection::East => ((1, 0), (0, -1)),
+        }
+    }
+
+    /// Помещает растение в ячейку, смещенную относительно заданной.
+    fn plant_at_offset(world: &mut Landscape, (x, y): (usize, usize), (dx, dy): (isize, isize)) {
+        world.add_plant((x as isize + dx) as usize, (y as isize + dy) as usize, testing::plant()).unwrap();
+    }
+
+    /// Мозг, который всегда выбирает движение вперед.
+    #[derive(Default)]
+    struct MovingBrain;
+
+    impl AnimalBrain for MovingBrain {
+        fn action(&mut self, _inputs: &AnimalInputSignal) -> AnimalAction {
+            AnimalAction::Move
+        }
+
+        fn clone_with_mutation(&self, _rng: &mut dyn RngCore, _rate: f64) -> Self {
+            MovingBrain
+        }
+    }
+
+    /// Мозг, который всегда выбирает поедание.
+    #[derive(Default)]
+    struct EatingBrain;
+
+    impl AnimalBrain for EatingBrain {
+        fn action(&mut self, _inputs: &AnimalInputSignal) -> AnimalAction {
+            AnimalAction::Eat
+        }
+
+        fn clone_with_mutation(&self, _rng: &mut dyn RngCore, _rate: f64) -> Self {
+            EatingBrain
+        }
+    }
+
+    /// Растение, которое считает вызовы `tick_passed`, остальное делает простое растение.
+    struct CountingPlant {
+        plant: Box<dyn PlantAlive>,
+        ticks: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    impl PlantAlive for CountingPlant {
+        fn is_eaten(&self) -> bool { self.plant.is_eaten() }
+        fn get_energy(&self) -> Energy { self.plant.get_energy() }
+        fn get_age(&self) -> usize { self.plant.get_age() }
+        fn set_energy(&mut self, energy: Energy) { self.plant.set_energy(energy) }
+        fn get_state(&self) -> PlantState { self.plant.get_state() }
+        fn set_state(&mut self, state: &PlantState) { self.plant.set_state(state) }
+        fn tick_passed(&mut self) {
+            self.ticks.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            self.plant.tick_passed();
+        }
+        fn action(&mut self) -> PlantAction { self.plant.action() }
+        fn grow_action(&mut self, energy: Energy) { self.plant.grow_action(energy) }
+        fn reproduce_action(&mut self) -> Box<dyn PlantAlive> { self.plant.reproduce_action() }
+        fn inactivity_action(&mut self) { self.plant.inactivity_action() }
+        fn be_eaten(&mut self) -> Energy { self.plant.be_eaten() }
+    }
+
+    #[test]
+    fn plant_tick_hook_is_called_once_per_tick() {
+        let mut world = testing::world(10, 10);
+        let counters: Vec<_> = (0..10).map(|_| std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0))).collect();
+        for (x, ticks) in counters.iter().enumerate() {
+            world.add_plant(x, x, Box::new(CountingPlant { plant: testing::plant(), ticks: ticks.clone() })).unwrap();
+        }
+
+        for _ in 0..25 {
+            world.tick();
+        }
+
+        for ticks in &counters {
+            assert_eq!(ticks.load(std::sync::atomic::Ordering::Relaxed), 25);
+        }
+    }
+
+    #[test]
+    fn plant_clock_does_not_depend_on_update_interval() {
+        let states: Vec<PlantState> = [1, 4].into_iter().map(|interval| {
+            let mut world = testing::world(10, 10);
+            world.set_plant_update_interval(interval);
+
+            // Съеденное растение ждет возобновления роста.
+            let mut plant = Plant::new(PlantParams { energy: 0 as Energy, regrowth_delay: 50, no_repro: true, ..testing::plant_params() }).unwrap();
+            plant.be_eaten();
+            world.add_plant(5, 5, plant).unwrap();
+
+            for _ in 0..4 * 5 {
+                world.tick();
+            }
+
+            world.plant_at(5, 5).unwrap().get_state()
+        }).collect();
+
+        assert_eq!(states[0].age, 20);
+        assert_eq!(states[0].regrowth_cooldown, 30);
+        assert_eq!(states[1].age, states[0].age);
+        assert_eq!(states[1].regrowth_cooldown, states[0].regrowth_cooldown);
+    }
+
+    #[test]
+    fn animal_tick_hook_is_called_once_per_tick() {
+        // Возраст животного увеличивает только `tick_passed`. Животные перемещаются,
+        // в том числе в ячейки, которые мир обойдет на этой же итерации позже.
+        let mut world = testing::populated_world(20, 20, (80, 20, 5));
+        let founders: Vec<u64> = world.animals_iter().map(|(_, _, animal)| animal.id).collect();
+
+        for _ in 0..20 {
+            world.tick();
+        }
+
+        let survivors: Vec<AnimalInfo> = world.animals_iter()
+            .map(|(_, _, animal)| animal)
+            .filter(|animal| founders.contains(&animal.id))
+            .collect();
+        assert!(!survivors.is_empty());
+        assert!(survivors.iter().all(|animal| animal.age == 20), "{:?}", survivors.iter().map(|a| a.age).collect::<Vec<_>>());
+        // Родившиеся во время прогона животные моложе.
+        assert!(world.animals_iter().all(|(_, _, animal)| animal.age <= 20));
+    }
+
+    #[test]
+    fn sanitizing_zeroes_non_finite_energy_once_per_agent() {
+        let mut world = testing::world(5, 5);
+        world.set_energy_sanitizing(true);
+        place(&mut world, (2, 2), AnimaType::Herbivore, AnimalDirection::North, 30.0);
+        world.add_plant(1, 1, testing::plant()).unwrap();
+
+        let handle = world.find_animal(world.animal_at(2, 2).unwrap().get_id()).unwrap();
+        for _ in 0..2 {
+            let mut animal = world.animals.take(handle).unwrap();
+            animal.set_energy(Energy::NAN);
+            world.sanitize_animal_energy(&mut animal, handle);
+            assert_eq!(animal.get_energy(), 0 as Energy);
+            world.animals.restore(handle, animal);
+        }
+
+        let handle = match world.landscape[1][1].plant {
+            PlantInCell::Plant(handle) => handle,
+            PlantInCell::None => unreachable!(),
+        };
+        for _ in 0..2 {
+            let mut plant = world.plants.take(handle).unwrap();
+            plant.set_energy(Energy::NAN);
+            world.sanitize_plant_energy(plant.as_mut(), handle, 1, 1);
+            assert_eq!(plant.get_energy(), 0 as Energy);
+            world.plants.restore(handle, plant);
+        }
+
+        // О каждом агенте сообщается один раз.
+        assert_eq!((world.sanitized_agents.0.len(), world.sanitized_agents.1.len()), (1, 1));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "не является конечным числом")]
+    fn non_finite_energy_stops_debug_build() {
+        let mut world = testing::world(5, 5);
+        let animal = Animal::with_brain(AnimaType::Herbivore, testing::animal_params(), AnimalDirection::North, 0, MovingBrain).unwrap();
+        world.add_animal(2, 2, animal).unwrap();
+        let id = world.animal_at(2, 2).unwrap().get_id();
+
+        // Энергия NaN не делает животное мертвым, и на своем ходу оно проверяет энергию.
+        world.adjust_energy(id, Energy::NAN).unwrap();
+        world.tick();
+    }
+
+    #[test]
+    fn animal_never_moves_into_wall() {
+        let mut world = testing::world(9, 9);
+        world.set_blocked(4, 3).unwrap();
+        let mut animal = Animal::with_brain(AnimaType::Herbivore, testing::animal_params(), AnimalDirection::North, 0, MovingBrain).unwrap();
+        animal.set_energy(MAX_ANIMAL_ENERGY);
+        world.add_animal(4, 4, animal).unwrap();
+
+        for tick in 1..=100 {
+            world.tick();
+
+            assert!(world.animal_at(4, 3).is_none(), "итерация {}", tick);
+            assert!(world.landscape[4][3].terrain == Terrain::Blocked);
+            let (x, y, _) = world.animals_iter().next().unwrap();
+            assert_eq!((x, y), (4, 4), "итерация {}", tick);
+        }
+    }
+
+    #[test]
+    fn populate_random_skips_blocked_cells() {
+        let strategies = [
+            PlacementStrategy::Uniform,
+            PlacementStrategy::HalfSplit { axis: Axis::X, half: Half::First },
+            PlacementStrategy::Patches { k: 3, radius: 2 },
+        ];
+
+        for strategy in strategies {
+            // Каждый второй столбец непроходим.
+            let mut world = testing::world(10, 10);
+            for x in (0..10).step_by(2) {
+                for y in 0..10 {
+                    world.set_blocked(x, y).unwrap();
+                }
+            }
+
+            world.populate_random((10, strategy), (8, strategy), (4, strategy), &mut testing::plant, &mut testing::animal)
+                .unwrap();
+
+            let stats = world.get_world_stats();
+            assert_eq!((stats.plant_count, stats.animal_count), (10, (8, 4, 0)));
+            let blocked = |x: usize, y: usize| world.landscape[x][y].terrain == Terrain::Blocked;
+            assert!(world.plants_iter().all(|(x, y, _)| !blocked(x, y)));
+            assert!(world.animals_iter().all(|(x, y, _)| !blocked(x, y)));
+        }
+    }
+
+    #[test]
+    fn animal_moving_against_traversal_order_acts_once_per_tick() {
+        let size = 8;
+        let cost = (AnimalEnergyProfile::UNIFORM.movement * ANIMAL_LIVE_ENERGY as f64) as Energy;
+        let mut backwards = 0;
+
+        for direction in DIRECTIONS {
+            let mut world = testing::world(size, size);
+            let animal = Animal::with_brain(AnimaType::Herbivore, testing::animal_params(), direction, 0, MovingBrain).unwrap();
+            world.add_animal(3, 3, animal).unwrap();
+            let (dx, dy) = forward_and_left(direction).0;
+            let (mut position, mut energy) = ((3, 3), ANIMAL_BIRTH_ENERGY);
+
+            for tick in 1..=2 * size {
+                world.tick();
+
+                let expected = (
+                    (position.0 as isize + dx).rem_euclid(size as isize) as usize,
+                    (position.1 as isize + dy).rem_euclid(size as isize) as usize,
+                );
+                energy -= cost;
+                let (x, y, animal) = world.animals_iter().next().unwrap();
+                assert_eq!((x, y), expected, "итерация {}", tick);
+                assert_eq!(animal.energy, energy, "итерация {}", tick);
+                assert_eq!(animal.age, tick);
+
+                // Порядок обхода: сначала столбцы, внутри столбца - строки.
+                let order = |(x, y): (usize, usize)| (
+                    world.shuffle_width.iter().position(|&v| v == x).unwrap(),
+                    world.shuffle_height.iter().position(|&v| v == y).unwrap(),
+                );
+                if order(expected) < order(position) {
+                    backwards += 1;
+                }
+                position = expected;
+            }
+        }
+
+        // Животные перемещались и в уже обойденные ячейки, и в еще не обойденные.
+        assert!(backwards > 0 && backwards < 4 * 2 * size, "{}", backwards);
+    }
+
+    #[test]
+    fn dynasty_tracks_descendants_over_three_generations() {
+        let mut world = testing::world(9, 9);
+        place(&mut world, (4, 4), AnimaType::Herbivore, AnimalDirection::North, MAX_ANIMAL_ENERGY);
+        place(&mut world, (0, 0), AnimaType::Herbivore, AnimalDirection::North, MAX_ANIMAL_ENERGY);
+        let ancestor = world.follow_dynasty(4, 4).unwrap();
+        assert!(world.follow_dynasty(2, 2).is_err());
+
+        // Рождает потомка животного в ячейке и возвращает его идентификатор и
+        // координаты. Потомку хватает энергии для своего размножения.
+        let mut born: BTreeSet<u64> = world.animals_iter().map(|(_, _, animal)| animal.id).collect();
+        let mut reproduce = |world: &mut Landscape, (x, y): (usize, usize)| -> (u64, (usize, usize)) {
+            world.perform_animal_action(x, y, AnimalAction::Reproduce).unwrap();
+            let (cx, cy, child) = world.animals_iter().find(|(_, _, animal)| !born.contains(&animal.id)).unwrap();
+            born.insert(child.id);
+            world.adjust_energy(child.id, MAX_ANIMAL_ENERGY).unwrap();
+            (child.id, (cx, cy))
+        };
+
+        let (child, child_cell) = reproduce(&mut world, (4, 4));
+        let (grandchild, _) = reproduce(&mut world, child_cell);
+        // Потомок животного вне династии в нее не входит.
+        let (stranger, stranger_cell) = reproduce(&mut world, (0, 0));
+        assert_eq!(world.dynasty, BTreeSet::from([ancestor, child, grandchild]));
+        assert!(!world.dynasty.contains(&stranger));
+
+        // Умершие члены выбывают из династии, с последним она перестает отслеживаться.
+        let kill = |world: &mut Landscape, id: u64| {
+            let handle = world.find_animal(id).unwrap();
+            world.animals.get_mut(handle).unwrap().set_energy(0 as Energy);
+        };
+        kill(&mut world, ancestor);
+        kill(&mut world, child);
+        world.final_processing();
+        assert_eq!(world.dynasty, BTreeSet::from([grandchild]));
+
+        kill(&mut world, grandchild);
+        world.final_processing();
+        assert!(world.dynasty.is_empty());
+        assert_eq!(world.animals_iter().count(), 2);
+
+        reproduce(&mut world, stranger_cell);
+        assert!(world.dynasty.is_empty());
+    }
+
+    #[test]
+    fn herbivore_reproduces_in_tiny_world() {
+        let mut world = testing::world(3, 3);
+        place(&mut world, (1, 1), AnimaType::Herbivore, AnimalDirection::North, MAX_ANIMAL_ENERGY);
+
+        world.perform_animal_action(1, 1, AnimalAction::Reproduce).unwrap();
+
+        let animals: Vec<AnimalInfo> = world.animals_iter().map(|(_, _, animal)| animal).collect();
+        assert_eq!(animals.len(), 2);
+        assert_eq!(animals.iter().map(|animal| animal.generation).max(), Some(1));
+
+        // Мир с потомком продолжает жить.
+        for _ in 0..10 {
+            world.tick();
+        }
+    }
+
+    #[test]
+    fn clip_wraps_any_offset_into_world() {
+        let max = 7usize;
+        let m = max as isize;
+        let cases = [
+            (-1, max - 1),
+            (-m, 0),
+            (-m - 3, max - 3),
+            (2 * m + 1, 1),
+            (m - 1, max - 1),
+            (m, 0),
+            (0, 0),
+        ];
+
+        for (coord, expected) in cases {
+            assert_eq!(Landscape::clip(coord, max), expected, "координата {}", coord);
+        }
+    }
+
+    #[test]
+    fn plant_in_each_cardinal_cell_is_perceived_in_right_area() {
+        let center = (4, 4);
+
+        for direction in DIRECTIONS {
+            let ((fx, fy), (lx, ly)) = forward_and_left(direction);
+            let cases = [
+                ((2 * fx, 2 * fy), (1, 0, 0, 0)),
+                ((2 * lx, 2 * ly), (0, 1, 0, 0)),
+                ((-2 * lx, -2 * ly), (0, 0, 1, 0)),
+                ((fx, fy), (0, 0, 0, 1)),
+                ((lx, ly), (0, 0, 0, 1)),
+                ((-lx, -ly), (0, 0, 0, 1)),
+                // Позади себя животное ничего не видит.
+                ((-fx, -fy), (0, 0, 0, 0)),
+                ((-2 * fx, -2 * fy), (0, 0, 0, 0)),
+            ];
+
+            for (offset, expected) in cases {
+                let mut world = testing::world(9, 9);
+                place(&mut world, center, AnimaType::Herbivore, direction, 25.0);
+                plant_at_offset(&mut world, center, offset);
+
+                let inputs = percept_at(&mut world, center.0, center.1);
+                assert_eq!(plant_inputs(&inputs), expected, "смещение {:?}", offset);
+            }
+        }
+    }
+
+    #[test]
+    fn turns_rotate_direction_by_quarter() {
+        // Начальное направление, направление после поворота налево и направо.
+        let cases = [
+            (AnimalDirection::North, AnimalDirection::West, AnimalDirection::East),
+            (AnimalDirection::West, AnimalDirection::South, AnimalDirection::North),
+            (AnimalDirection::South, AnimalDirection::East, AnimalDirection::West),
+            (AnimalDirection::East, AnimalDirection::North, AnimalDirection::South),
+        ];
+        let center = (4, 4);
+
+        for (start, left, right) in cases {
+            let (_, (lx, ly)) = forward_and_left(start);
+
+            for (action, expected, offset) in [
+                (AnimalAction::TurnLeft, left, (2 * lx, 2 * ly)),
+                (AnimalAction::TurnRight, right, (-2 * lx, -2 * ly)),
+            ] {
+                let mut world = testing::world(9, 9);
+                place(&mut world, center, AnimaType::Herbivore, start, 25.0);
+                // Растение сбоку после поворота в его сторону оказывается впереди.
+                plant_at_offset(&mut world, center, offset);
+
+                world.perform_animal_action(center.0, center.1, action).unwrap();
+
+                let animal = world.get_cell_info(center.0, center.1).unwrap().animal.unwrap();
+                assert!(animal.direction == expected, "поворот {}", action.name());
+                let inputs = percept_at(&mut world, center.0, center.1);
+                assert_eq!(plant_inputs(&inputs), (1, 0, 0, 0), "поворот {}", action.name());
+            }
+        }
+    }
+
+    /// Мир из текстового представления: обратное `Landscape::to_ascii` для пустых
+    /// ячеек, скал, растений и живых животных.
+    fn world_from_ascii(ascii: &str) -> Landscape {
+        let rows: Vec<&str> = ascii.lines().collect();
+        let mut world = testing::world(rows[0].chars().count(), rows.len());
+
+        for (y, row) in rows.iter().enumerate() {
+            for (x, glyph) in row.chars().enumerate() {
+                let species = [
+                    (AnimaType::Herbivore, &ASCII_HERBIVORE),
+                    (AnimaType::Carnivore, &ASCII_CARNIVORE),
+                    (AnimaType::Omnivore, &ASCII_OMNIVORE),
+                ];
+                let animal = species.iter().find_map(|(animal_type, glyphs)| {
+                    glyphs.iter().position(|g| *g == glyph).map(|index| (*animal_type, DIRECTIONS[index]))
+                });
+
+                match (glyph, animal) {
+                    (_, Some((animal_type, direction))) => place(&mut world, (x, y), animal_type, direction, 25.0),
+                    (ASCII_PLANT, _) => world.add_plant(x, y, testing::plant()).unwrap(),
+                    (ASCII_OBSTACLE, _) => world.set_blocked(x, y).unwrap(),
+                    (ASCII_EMPTY, _) => {}
+                    _ => panic!("неизвестный символ {:?}", glyph),
+                }
+            }
+        }
+
+        world
+    }
+
+    #[test]
+    fn ascii_round_trips_small_world() {
+        let ascii = "\
+            .*n.#\n\
+            S..^*\n\
+            #w.E.\n\
+            <.s*N\n";
+
+        let world = world_from_ascii(ascii);
+
+        assert_eq!(world.to_ascii(), ascii);
+        assert_eq!(world.get_world_stats().animal_count, (3, 3, 2));
+        assert_eq!(world.get_world_stats().plant_count, 3);
+    }
+
+    #[test]
+    fn adjusted_energy_is_clamped() {
+        let mut world = testing::world(5, 5);
+        place(&mut world, (2, 2), AnimaType::Herbivore, AnimalDirection::North, 30.0);
+        let id = world.animal_at(2, 2).unwrap().get_id();
+
+        assert_eq!(world.adjust_energy(id, 1000.0).unwrap(), MAX_ANIMAL_ENERGY);
+        assert_eq!(world.adjust_energy(id, -5.0).unwrap(), MAX_ANIMAL_ENERGY - 5.0);
+        assert_eq!(world.adjust_energy(id, -1000.0).unwrap(), 0 as Energy);
+        assert_eq!(world.get_world_stats().energy_adjustments, 3);
+    }
+
+    #[test]
+    fn adjusting_unknown_animal_is_an_error() {
+        let mut world = testing::world(5, 5);
+        place(&mut world, (2, 2), AnimaType::Herbivore, AnimalDirection::North, 30.0);
+        let id = world.animal_at(2, 2).unwrap().get_id();
+
+        let error = world.adjust_energy(id + 1, 1.0).unwrap_err();
+        assert_eq!(error.to_string(), format!("Животного с идентификатором {} нет в мире", id + 1));
+        assert_eq!(world.get_world_stats().energy_adjustments, 0);
+        assert_eq!(world.animal_at(2, 2).unwrap().get_energy(), 30.0);
+    }
+
+    #[test]
+    fn animal_dies_when_energy_is_adjusted_to_zero() {
+        let mut world = testing::world(5, 5);
+        place(&mut world, (2, 2), AnimaType::Herbivore, AnimalDirection::North, 30.0);
+        place(&mut world, (0, 0), AnimaType::Herbivore, AnimalDirection::North, 30.0);
+        let id = world.animal_at(2, 2).unwrap().get_id();
+
+        world.adjust_energy(id, -30.0).unwrap();
+        // Мертвое животное не делает хода и убирается из мира в конце итерации.
+        world.tick();
+
+        assert!(world.animal_at(2, 2).is_none());
+        assert_eq!(world.get_world_stats().animal_count.0, 1);
+        assert_eq!(world.get_statistics().deaths, (1, 0, 0));
+        assert!(world.adjust_energy(id, 1.0).is_err());
+    }
+
+    #[test]
+    fn event_counts_match_statistics() {
+        // Растения размножаются, чтобы в прогоне прорастали семена. Численность
+        // животных ограничена, чтобы им хватало места.
+        let mut world = Landscape::new(12, 12, 144, 40, 20, MAX_PLANT_GROW_ENERGY, Some(testing::TEST_SEED)).unwrap();
+        let mut new_plant = || -> Box<dyn PlantAlive> {
+            Plant::new(PlantParams { no_repro: false, ..testing::plant_params() }).unwrap()
+        };
+        let uniform = |count: usize| (count, PlacementStrategy::Uniform);
+        world.populate_random(uniform(40), uniform(20), uniform(6), &mut new_plant, &mut testing::animal).unwrap();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        world.set_event_sink(Some(sender));
+
+        let (mut born, mut died, mut ate, mut sprouted) = (0, 0, 0, 0);
+        let (mut eat_successes, mut seeds) = (0, 0);
+        for _ in 0..50 {
+            world.tick();
+
+            let stats = world.get_world_stats();
+            eat_successes += stats.hunt.0.successes + stats.hunt.1.successes + stats.hunt.2.successes;
+            seeds += stats.seeds.1;
+
+            for event in receiver.try_iter() {
+                match event {
+                    WorldEvent::Born { .. } => born += 1,
+                    WorldEvent::Died { .. } => died += 1,
+                    WorldEvent::Ate { .. } => ate += 1,
+                    WorldEvent::PlantSprouted { .. } => sprouted += 1,
+                    _ => {}
+                }
+            }
+        }
+
+        let statistics = world.get_statistics();
+        let total = |counts: (usize, usize, usize)| counts.0 + counts.1 + counts.2;
+        assert_eq!(born, total(statistics.births));
+        assert_eq!(died, total(statistics.deaths));
+        assert_eq!(ate, eat_successes);
+        assert_eq!(sprouted, seeds);
+        // Прогон не вырожден: события каждого вида происходили.
+        assert!(born > 0 && died > 0 && ate > 0 && sprouted > 0, "{} {} {} {}", born, died, ate, sprouted);
+    }
+
+    #[test]
+    fn spawn_margin_keeps_new_agents_out_of_band() {
+        let mut world = Landscape::new(16, 16, 100, 40, 20, MAX_PLANT_GROW_ENERGY, Some(testing::TEST_SEED)).unwrap();
+        world.set_spawn_margin(2).unwrap();
+        let mut new_plant = || -> Box<dyn PlantAlive> {
+            Plant::new(PlantParams { no_repro: false, ..testing::plant_params() }).unwrap()
+        };
+        let uniform = |count: usize| (count, PlacementStrategy::Uniform);
+        world.populate_random(uniform(60), uniform(30), uniform(8), &mut new_plant, &mut testing::animal).unwrap();
+        assert!(world.cells().all(|(x, y)| {
+            !world.in_spawn_margin(x, y) || (world.plant_at(x, y).is_none() && world.animal_at(x, y).is_none())
+        }));
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        world.set_event_sink(Some(sender));
+
+        let (mut born, mut sprouted) = (0, 0);
+        for _ in 0..500 {
+            world.tick();
+
+            for event in receiver.try_iter() {
+                let at = match event {
+                    WorldEvent::Born { at, .. } => {
+                        born += 1;
+                        at
+                    }
+                    WorldEvent::PlantSprouted { at, .. } => {
+                        sprouted += 1;
+                        at
+                    }
+                    _ => continue,
+                };
+                assert!(!world.in_spawn_margin(at.0, at.1), "агент появился в полосе: {:?}", at);
+            }
+            // Растения не двигаются, поэтому в полосе их нет никогда.
+            assert!(world.cells().all(|(x, y)| !world.in_spawn_margin(x, y) || world.plant_at(x, y).is_none()));
+        }
+        assert!(born > 0 && sprouted > 0, "{} {}", born, sprouted);
+    }
+
+    #[test]
+    fn spawn_margin_rejects_limits_exceeding_free_cells() {
+        // Вне полосы шириной 2 в мире 10x10 остается 36 ячеек.
+        let mut world = Landscape::new(10, 10, 36, 36, 37, MAX_PLANT_GROW_ENERGY, Some(testing::TEST_SEED)).unwrap();
+        assert!(world.set_spawn_margin(2).is_err());
+        assert_eq!(world.get_spawn_margin(), 0);
+
+        world.set_max_omnivore(36);
+        world.set_spawn_margin(1).unwrap();
+        // Полоса, занимающая весь мир, тоже отвергается.
+        assert!(world.set_spawn_margin(5).is_err());
+        assert_eq!(world.get_spawn_margin(), 1);
+    }
+
+    #[test]
+    fn energy_budget_matches_hand_computed_tick() {
+        // Каждое действие стоит 1, восприятие с радиусом 2 стоит 0.5 + 0.25·2² = 1.5.
+        let params = AnimalParams {
+            live_energy: 1.0,
+            energy_profile: AnimalEnergyProfile {
+                perception_base: 0.5,
+                perception_radius: 0.25,
+                ..AnimalEnergyProfile::UNIFORM
+            },
+            ..testing::animal_params()
+        };
+        let mut world = testing::world(7, 7);
+        let mut put = |(x, y): (usize, usize), mut animal: Box<dyn AnimalAlive>, energy: Energy| {
+            animal.set_energy(energy);
+            world.add_animal(x, y, animal).unwrap();
+        };
+        // Травоядное съедает растение перед собой: 30 - 1.5 - 1 + 15 = 42.5.
+        put((1, 1), Animal::with_brain(AnimaType::Herbivore, params, AnimalDirection::North, 0, EatingBrain).unwrap(), 30.0);
+        // Травоядное идет вперед: 30 - 1.5 - 1 = 27.5.
+        put((5, 1), Animal::with_brain(AnimaType::Herbivore, params, AnimalDirection::North, 0, MovingBrain).unwrap(), 30.0);
+        // Сытый хищник размножается: 58 - 1.5 - 1 - 25 = 30.5, потомку достается 25.
+        put((3, 5), Animal::with_brain(AnimaType::Carnivore, params, AnimalDirection::North, 0, MovingBrain).unwrap(), 58.0);
+        world.add_plant(1, 0, testing::plant()).unwrap();
+
+        world.tick();
+
+        let (herbivores, carnivores, omnivores) = world.get_energy_budget();
+        assert_eq!(herbivores, EnergyBudget { gained: 15.0, spent: 5.0, offspring: 0.0 });
+        assert_eq!(herbivores.net(), 10.0);
+        assert_eq!(carnivores, EnergyBudget { gained: 0.0, spent: 2.5, offspring: 25.0 });
+        assert_eq!(carnivores.net(), -27.5);
+        assert_eq!(omnivores, EnergyBudget::default());
+        assert_eq!(omnivores.net(), 0.0);
+
+        // Чистое изменение - это изменение энергии животных вида без учета потомков.
+        assert_eq!(world.animal_at(1, 1).unwrap().get_energy(), 42.5);
+        assert_eq!(world.animal_at(5, 0).unwrap().get_energy(), 27.5);
+        assert_eq!(world.animal_at(3, 5).unwrap().get_energy(), 30.5);
+    }
+
+    #[test]
+    fn decaying_carrion_fertilizes_its_cell() {
+        let mut world = testing::world(5, 5);
+        world.set_carrion_decay(0.25, 0.5, 0.5);
+        world.landscape[2][2].carrion = 64.0;
+
+        // Падаль теряет четверть энергии, половина потерянного переходит в плодородие,
+        // плодородие до пополнения убывает вдвое.
+        let mut fertility_lost = 0 as Energy;
+        for (carrion, fertility) in [(48.0, 8.0), (36.0, 10.0), (27.0, 9.5)] {
+            fertility_lost += world.landscape[2][2].fertility * 0.5;
+            world.tick();
+
+            assert_eq!(world.landscape[2][2].carrion, carrion);
+            assert_eq!(world.landscape[2][2].fertility, fertility);
+            world.validate_statistics().unwrap();
+        }
+
+        // Баланс: энергия падали осталась в ячейке, ушла в плодородие (и убыла из
+        // него) или рассеялась.
+        let cell = &world.landscape[2][2];
+        let dissipated = (64.0 - cell.carrion) * 0.5;
+        assert_eq!(cell.carrion + cell.fertility + fertility_lost + dissipated, 64.0);
+
+        // Без падали плодородие убывает к нулю.
+        world.landscape[2][2].carrion = 0 as Energy;
+        for fertility in [4.75, 2.375, 1.1875] {
+            world.tick();
+            assert_eq!(world.landscape[2][2].fertility, fertility);
+        }
+        // Соседние ячейки не удобряются.
+        assert!(world.cells().all(|(x, y)| (x, y) == (2, 2) || world.landscape[x][y].fertility == 0 as Energy));
+    }
+
+    #[test]
+    fn fertility_boosts_plant_growth() {
+        let mut world = testing::world(5, 5);
+        let plant = Plant::new(PlantParams { energy: 1.0, ..testing::plant_params() }).unwrap();
+        world.add_plant(2, 2, plant).unwrap();
+        let plant = Plant::new(PlantParams { energy: 1.0, ..testing::plant_params() }).unwrap();
+        world.add_plant(3, 3, plant).unwrap();
+        world.landscape[2][2].fertility = 2.0;
+
+        world.tick();
+
+        let boost = world.plant_at(2, 2).unwrap().get_energy() - world.plant_at(3, 3).unwrap().get_energy();
+        assert_eq!(boost, 2.0);
+    }
+
+    /// Энергии растений и животных мира 10x10 с зерном `seed`, заселенного с заданной
+    /// начальной энергией.
+    fn initial_energies(seed: u64, plants: InitialEnergy, animals: InitialEnergy) -> (Vec<Energy>, Vec<Energy>) {
+        let mut world = Landscape::new(10, 10, 100, 100, 100, MAX_PLANT_GROW_ENERGY, Some(seed)).unwrap();
+        world.set_initial_energy(plants, animals);
+        let uniform = |count: usize| (count, PlacementStrategy::Uniform);
+        world.populate_random(uniform(40), uniform(20), uniform(10), &mut testing::plant, &mut testing::animal).unwrap();
+
+        let cells: Vec<(usize, usize)> = world.cells().collect();
+        (
+            cells.iter().filter_map(|&(x, y)| world.plant_at(x, y)).map(|plant| plant.get_energy()).collect(),
+            cells.iter().filter_map(|&(x, y)| world.animal_at(x, y)).map(|animal| animal.get_energy()).collect(),
+        )
+    }
+
+    #[test]
+    fn initial_energy_follows_mode() {
+        let (plants, animals) = initial_energies(testing::TEST_SEED, InitialEnergy::Full, InitialEnergy::Full);
+        assert!(plants.len() == 40 && plants.iter().all(|&energy| energy == MAX_PLANT_ENERGY));
+        assert!(animals.len() == 30 && animals.iter().all(|&energy| energy == ANIMAL_BIRTH_ENERGY));
+
+        let mode = InitialEnergy::Fraction(0.5);
+        let (plants, animals) = initial_energies(testing::TEST_SEED, mode, mode);
+        assert!(plants.iter().all(|&energy| energy == MAX_PLANT_ENERGY * 0.5));
+        assert!(animals.iter().all(|&energy| energy == ANIMAL_BIRTH_ENERGY * 0.5));
+
+        // Случайная энергия не ниже заданной доли и не выше полной.
+        let mode = InitialEnergy::Uniform(0.25);
+        let (plants, animals) = initial_energies(testing::TEST_SEED, mode, mode);
+        assert!(plants.iter().all(|&energy| (MAX_PLANT_ENERGY * 0.25..=MAX_PLANT_ENERGY).contains(&energy)));
+        assert!(animals.iter().all(|&energy| (ANIMAL_BIRTH_ENERGY * 0.25..=ANIMAL_BIRTH_ENERGY).contains(&energy)));
+        assert!(plants.iter().any(|&energy| energy != plants[0]));
+        assert!(animals.iter().any(|&energy| energy != animals[0]));
+
+        // Животное не может начать жизнь мертвым: нулевая доля оставляет полную энергию.
+        let (_, animals) = initial_energies(testing::TEST_SEED, InitialEnergy::Full, InitialEnergy::Fraction(0.0));
+        assert!(animals.iter().all(|&energy| energy == ANIMAL_BIRTH_ENERGY));
+    }
+
+    #[test]
+    fn initial_energy_is_reproducible_with_seed() {
+        let modes = (InitialEnergy::Uniform(0.2), InitialEnergy::Uniform(0.2));
+        let first = initial_energies(testing::TEST_SEED, modes.0, modes.1);
+
+        assert!(first == initial_energies(testing::TEST_SEED, modes.0, modes.1));
+        assert!(first != initial_energies(testing::TEST_SEED + 1, modes.0, modes.1));
+    }
+
+    #[test]
+    fn energy_adjustments_are_sent_as_events() {
+        let mut world = testing::world(5, 5);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        world.set_event_sink(Some(sender));
+        place(&mut world, (2, 2), AnimaType::Herbivore, AnimalDirection::North, 30.0);
+        let id = world.animal_at(2, 2).unwrap().get_id();
+
+        world.adjust_energy(id, -5.0).unwrap();
+        // Животное, энергия которого доведена до нуля, умирает в конце итерации.
+        world.adjust_energy(id, -100.0).unwrap();
+        world.tick();
+
+        let events: Vec<WorldEvent> = receiver.try_iter().collect();
+        let adjusted: Vec<WorldEvent> = events.iter()
+            .filter(|event| matches!(event, WorldEvent::EnergyAdjusted { .. }))
+            .copied()
+            .collect();
+        assert!(adjusted == [
+            WorldEvent::EnergyAdjusted { tick: 1, agent_id: id, delta: -5.0, energy: 25.0 },
+            WorldEvent::EnergyAdjusted { tick: 1, agent_id: id, delta: -100.0, energy: 0 as Energy },
+        ]);
+        assert!(events.iter().any(|event| matches!(
+            event, WorldEvent::Died { tick: 1, at: (2, 2), .. }
+        )));
+    }
+
+    #[test]
+    fn ascii_shows_animal_over_plant_and_eaten_or_dead_agents() {
+        let mut world = world_from_ascii("*.*\n...\n");
+        // Животное закрывает растение в той же ячейке.
+        place(&mut world, (0, 0), AnimaType::Herbivore, AnimalDirection::East, 25.0);
+        // Полностью съеденное растение и мертвое животное.
+        let handle = match world.landscape[2][0].plant {
+            PlantInCell::Plant(handle) => handle,
+            PlantInCell::None => unreachable!(),
+        };
+        world.plants.get_mut(handle).unwrap().be_eaten();
+        place(&mut world, (1, 1), AnimaType::Carnivore, AnimalDirection::North, 25.0);
+        world.adjust_energy(world.get_cell_info(1, 1).unwrap().animal.unwrap().id, -25.0).unwrap();
+
+        assert_eq!(world.to_ascii(), "e.,\n.x.\n");
+    }
+
+    #[test]
+    fn strict_eating_takes_only_cell_ahead() {
+        let center = (4, 4);
+
+        for direction in DIRECTIONS {
+            let ((fx, fy), (lx, ly)) = forward_and_left(direction);
+            // Смещение растения и съест ли его животное в строгом и обычном режимах.
+            let cases = [
+                ((fx, fy), true, true),
+                ((fx + lx, fy + ly), false, true),
+                ((fx - lx, fy - ly), false, true),
+                ((lx, ly), false, true),
+                ((-lx, -ly), false, true),
+                ((2 * fx, 2 * fy), false, false),
+                ((-fx, -fy), false, false),
+            ];
+
+            for (offset, strict_eats, proximity_eats) in cases {
+                for (strict, eats) in [(true, strict_eats), (false, proximity_eats)] {
+                    let mut world = testing::world(9, 9);
+                    world.set_strict_eating(strict);
+                    place(&mut world, center, AnimaType::Herbivore, direction, 25.0);
+                    plant_at_offset(&mut world, center, offset);
+
+                    world.perform_animal_action(center.0, center.1, AnimalAction::Eat).unwrap();
+
+                    assert_eq!(
+                        world.eat_successes.0, eats as usize,
+                        "вперед {:?}, смещение {:?}, строгий режим {}", (fx, fy), offset, strict
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn strict_eating_wraps_around_world_edge() {
+        // Животное у края мира смотрит наружу: ячейка перед ним - на другом краю.
+        let cases = [
+            ((2, 0), AnimalDirection::North, (2, 4)),
+            ((2, 4), AnimalDirection::South, (2, 0)),
+            ((0, 2), AnimalDirection::West, (4, 2)),
+            ((4, 2), AnimalDirection::East, (0, 2)),
+        ];
+
+        for (at, direction, ahead) in cases {
+            let mut world = testing::world(5, 5);
+            world.set_strict_eating(true);
+            place(&mut world, at, AnimaType::Herbivore, direction, 25.0);
+            world.add_plant(ahead.0, ahead.1, testing::plant()).unwrap();
+
+            world.perform_animal_action(at.0, at.1, AnimalAction::Eat).unwrap();
+
+            assert_eq!(world.eat_successes.0, 1, "растение в ячейке {:?}", ahead);
+        }
+    }
+
+    /// Травоядное в ячейке (4, 4) смотрит на север и ест растения области близости
+    /// (по 2 единицы энергии за укус, по 5 укусов на растение). Возвращает ячейки
+    /// растений, от которых был откушен каждый укус.
+    fn bitten_plants(focus: bool, bites: usize) -> Vec<(usize, usize)> {
+        let mut world = testing::world(9, 9);
+        world.set_food_focus(focus);
+        place(&mut world, (4, 4), AnimaType::Herbivore, AnimalDirection::North, 25.0);
+        let cells = NORTH_PROXIMITY.map(|(dx, dy)| ((4 + dx as isize) as usize, (4 + dy as isize) as usize));
+        for (x, y) in cells {
+            let plant = Plant::new(PlantParams {
+                energy: 10.0, eaten_energy: 2.0, bite_mode: BiteMode::Fixed, ..testing::plant_params()
+            }).unwrap();
+            world.add_plant(x, y, plant).unwrap();
+        }
+        let energies = |world: &Landscape| cells.map(|(x, y)| world.get_cell_info(x, y).unwrap().plant.unwrap().energy);
+
+        let mut bitten = vec![];
+        for _ in 0..bites {
+            let before = energies(&world);
+            world.perform_animal_action(4, 4, AnimalAction::Eat).unwrap();
+            let after = energies(&world);
+
+            let index = (0..cells.len()).find(|&i| after[i] < before[i]).expect("Травоядное ничего не съело");
+            bitten.push(cells[index]);
+        }
+
+        bitten
+    }
+
+    #[test]
+    fn focused_herbivore_eats_same_plant_until_depleted() {
+        let bitten = bitten_plants(true, 10);
+
+        // Первое растение съедено за пять ходов подряд, затем так же второе.
+        assert!(bitten[..5].iter().all(|cell| *cell == bitten[0]), "{:?}", bitten);
+        assert!(bitten[5..].iter().all(|cell| *cell == bitten[5]), "{:?}", bitten);
+        assert_ne!(bitten[0], bitten[5]);
+
+        // Без сосредоточенности травоядное переходит от растения к растению.
+        let scattered: BTreeSet<(usize, usize)> = bitten_plants(false, 10).into_iter().collect();
+        assert!(scattered.len() > 2, "{:?}", scattered);
+    }
+
+    #[test]
+    fn seeds_fill_free_cells_and_report_shortfall() {
+        // В мире 3x3 рядом с растением в центре свободны только три ячейки.
+        let mut world = testing::world(3, 3);
+        for cell in [(1, 1), (0, 0), (0, 1), (0, 2), (1, 0), (2, 0)] {
+            world.add_plant(cell.0, cell.1, testing::plant()).unwrap();
+        }
+        world.set_seeds_per_reproduction(5);
+
+        let mut parent = Plant::new(PlantParams { seed_energy_cost: 1.0, ..testing::plant_params() }).unwrap();
+        world.reproduce_plant_action(parent.as_mut(), 1, 1);
+
+        let stats = world.get_world_stats();
+        assert_eq!(stats.plant_count, 9);
+        // Рассыпание прекращается на первом семечке, которому не нашлось места.
+        assert_eq!(stats.seeds, (4, 3));
+        // Растение заплатило только за проросшие семена.
+        assert_eq!(parent.get_energy(), MAX_PLANT_ENERGY - 3.0);
+        for cell in [(1, 2), (2, 1), (2, 2)] {
+            assert!(world.get_cell_info(cell.0, cell.1).unwrap().plant.is_some(), "ячейка {:?}", cell);
+        }
+    }
+
+    #[test]
+    fn single_bite_plant_feeds_only_one_of_two_herbivores() {
+        let mut world = testing::world(9, 9);
+        place(&mut world, (4, 5), AnimaType::Herbivore, AnimalDirection::North, 25.0);
+        place(&mut world, (4, 3), AnimaType::Herbivore, AnimalDirection::South, 25.0);
+        let plant = Plant::new(PlantParams { energy: 4.0, eaten_energy: 4.0, bite_mode: BiteMode::Fixed, ..testing::plant_params() }).unwrap();
+        world.add_plant(4, 4, plant).unwrap();
+
+        world.perform_animal_action(4, 5, AnimalAction::Eat).unwrap();
+        world.perform_animal_action(4, 3, AnimalAction::Eat).unwrap();
+
+        // Первое травоядное съело растение целиком, второму ничего не досталось
+        // (неудачная попытка поесть бесплатна).
+        let cost = (AnimalEnergyProfile::UNIFORM.eat * ANIMAL_LIVE_ENERGY as f64) as Energy;
+        let energy = |world: &Landscape, (x, y)| world.get_cell_info(x, y).unwrap().animal.unwrap().energy;
+        assert_eq!(energy(&world, (4, 5)), 25.0 + 4.0 - cost);
+        assert_eq!(energy(&world, (4, 3)), 25.0);
+        assert_eq!(world.eat_successes.0, 1);
+        assert_eq!(world.get_cell_info(4, 4).unwrap().plant.unwrap().energy, 0.0);
+    }
+
+    #[test]
+    fn single_bite_moves_plant_energy_to_herbivore() {
+        let center = (4, 4);
+        let mut world = testing::world(9, 9);
+        place(&mut world, center, AnimaType::Herbivore, AnimalDirection::North, 25.0);
+        let plant = Plant::new(PlantParams { energy: 10.0, eaten_energy: 4.0, bite_mode: BiteMode::Fixed, ..testing::plant_params() }).unwrap();
+        world.add_plant(center.0, center.1 - 1, plant).unwrap();
+
+        world.perform_animal_action(center.0, center.1, AnimalAction::Eat).unwrap();
+
+        let plant = world.get_cell_info(center.0, center.1 - 1).unwrap().plant.unwrap();
+        assert_eq!(plant.energy, 6.0);
+        // Животное получает укус за вычетом затрат на поедание.
+        let animal = world.get_cell_info(center.0, center.1).unwrap().animal.unwrap();
+        let cost = (AnimalEnergyProfile::UNIFORM.eat * ANIMAL_LIVE_ENERGY as f64) as Energy;
+        assert_eq!(animal.energy, 25.0 + 4.0 - cost);
+    }
+
+    /// Мир 10x10 с 20 травоядными, готовыми к размножению, и заданным наибольшим
+    /// количеством травоядных. Возвращает мир и координаты родителей.
+    fn world_with_ready_parents(max_herbivore: usize) -> (Landscape, Vec<(usize, usize)>) {
+        let mut world = Landscape::new(10, 10, 100, max_herbivore, 100, MAX_PLANT_GROW_ENERGY, Some(testing::TEST_SEED))
+            .unwrap();
+        let parents: Vec<(usize, usize)> = (0..10).step_by(2)
+            .flat_map(|x| (0..8).step_by(2).map(move |y| (x, y)))
+            .collect();
+        for &at in &parents {
+            place(&mut world, at, AnimaType::Herbivore, AnimalDirection::North, MAX_ANIMAL_ENERGY);
+        }
+
+        (world, parents)
+    }
+
+    /// Мир 3x3, заполненный травоядными, которые всегда идут вперед: размножиться
+    /// и сделать шаг здесь некуда.
+    fn crowded_world() -> Landscape {
+        let mut world = testing::world(3, 3);
+        for (x, y) in (0..3).flat_map(|x| (0..3).map(move |y| (x, y))) {
+            let animal = Animal::with_brain(AnimaType::Herbivore, testing::animal_params(), AnimalDirection::North, 0, MovingBrain).unwrap();
+            world.add_animal(x, y, animal).unwrap();
+        }
+
+        world
+    }
+
+    #[test]
+    fn blocked_reproduction_costs_inactivity_energy() {
+        let mut world = crowded_world();
+        let cost = (AnimalEnergyProfile::UNIFORM.none * ANIMAL_LIVE_ENERGY as f64) as Energy;
+
+        world.perform_animal_action(1, 1, AnimalAction::Reproduce).unwrap();
+
+        let animal = world.get_cell_info(1, 1).unwrap().animal.unwrap();
+        assert_eq!(animal.energy, ANIMAL_BIRTH_ENERGY - cost);
+        assert_eq!(world.get_world_stats().animal_count.0, 9);
+    }
+
+    #[test]
+    fn reproduce_blocked_flag_lasts_until_next_decision() {
+        let mut world = crowded_world();
+        world.perform_animal_action(1, 1, AnimalAction::Reproduce).unwrap();
+        assert!(world.animal_at(1, 1).unwrap().is_reproduce_blocked());
+        assert_eq!(percept_at(&mut world, 1, 1).repro_blocked, 1);
+
+        // Другие действия вне решения мозга флаг не сбрасывают.
+        world.perform_animal_action(1, 1, AnimalAction::Move).unwrap();
+        assert_eq!(percept_at(&mut world, 1, 1).repro_blocked, 1);
+
+        // На следующем ходу животное узнает о неудаче и решает идти, флаг сброшен.
+        world.tick();
+        assert!(!world.animal_at(1, 1).unwrap().is_reproduce_blocked());
+        assert_eq!(percept_at(&mut world, 1, 1).repro_blocked, 0);
+    }
+
+    #[test]
+    fn blocked_reproductions_are_counted_per_tick() {
+        let mut world = crowded_world();
+        world.perform_animal_action(1, 1, AnimalAction::Reproduce).unwrap();
+        world.perform_animal_action(0, 0, AnimalAction::Reproduce).unwrap();
+        assert_eq!(world.get_world_stats().blocked_births, (2, 0, 0));
+
+        world.perform_animal_action(1, 1, AnimalAction::Reproduce).unwrap();
+        assert_eq!(world.get_world_stats().blocked_births, (3, 0, 0));
+
+        // Счетчик относится к итерации: на следующей животные только пытаются идти.
+        world.tick();
+        assert_eq!(world.get_world_stats().blocked_births, (0, 0, 0));
+    }
+
+    #[test]
+    fn birth_budget_limits_births_per_tick() {
+        let (mut world, parents) = world_with_ready_parents(100);
+        world.set_birth_budget(3, 0, 0);
+        assert_eq!(parents.len(), 20);
+
+        for &(x, y) in &parents {
+            world.perform_animal_action(x, y, AnimalAction::Reproduce).unwrap();
+        }
+
+        let stats = world.get_world_stats();
+        assert_eq!(stats.animal_count.0, 23);
+        assert_eq!(stats.deferred_births.0, 17);
+        assert_eq!(stats.blocked_births.0, 0);
+
+        // Отложившие размножение животные сохранили энергию.
+        let full = parents.iter()
+            .filter(|&&(x, y)| world.get_cell_info(x, y).unwrap().animal.unwrap().energy == MAX_ANIMAL_ENERGY)
+            .count();
+        assert_eq!(full, 17);
+    }
+
+    #[test]
+    fn birth_budget_limits_conceptions_under_gestation() {
+        let (mut world, parents) = world_with_ready_parents(100);
+        world.set_birth_budget(3, 0, 0);
+        world.set_gestation(2, 0 as Energy, 1);
+
+        for &(x, y) in &parents {
+            world.perform_animal_action(x, y, AnimalAction::Reproduce).unwrap();
+        }
+
+        let stats = world.get_world_stats();
+        assert_eq!(stats.pending_births, 3);
+        assert_eq!(stats.deferred_births.0, 17);
+
+        // Зачатые на одной итерации потомки рождаются вместе, не больше лимита.
+        world.iteration += 2;
+        world.hatch_pending_births();
+        let stats = world.get_world_stats();
+        assert_eq!(stats.pending_births, 0);
+        assert_eq!(stats.animal_count.0, 23);
+    }
+
+    #[test]
+    fn birth_budget_is_checked_before_species_limit() {
+        // Лимит рождений и наибольшее количество травоядных допускают по 3 рождения:
+        // лишние размножения откладываются лимитом, до ограничения вида дело не доходит.
+        let (mut world, parents) = world_with_ready_parents(23);
+        world.set_birth_budget(3, 0, 0);
+        for &(x, y) in &parents {
+            world.perform_animal_action(x, y, AnimalAction::Reproduce).unwrap();
+        }
+
+        let stats = world.get_world_stats();
+        assert_eq!(stats.animal_count.0, 23);
+        assert_eq!(stats.deferred_births.0, 17);
+        assert_eq!(stats.blocked_births.0, 0);
+
+        // Ограничение вида строже лимита: остальные размножения не состоялись из-за тесноты,
+        // а лимит, который расходуется только рождениями, не исчерпан.
+        let (mut world, parents) = world_with_ready_parents(21);
+        world.set_birth_budget(3, 0, 0);
+        for &(x, y) in &parents {
+            world.perform_animal_action(x, y, AnimalAction::Reproduce).unwrap();
+        }
+
+        let stats = world.get_world_stats();
+        assert_eq!(stats.animal_count.0, 21);
+        assert_eq!(stats.deferred_births.0, 0);
+        assert_eq!(stats.blocked_births.0, 19);
+    }
+
+    #[test]
+    fn omnivore_births_respect_budget() {
+        let mut world = testing::world(9, 9);
+        world.set_max_omnivore(81);
+        world.set_birth_budget(0, 0, 1);
+        let parents = [(1, 1), (4, 4), (7, 7)];
+        for &at in &parents {
+            place(&mut world, at, AnimaType::Omnivore, AnimalDirection::North, MAX_ANIMAL_ENERGY);
+        }
+
+        for &(x, y) in &parents {
+            world.perform_animal_action(x, y, AnimalAction::Reproduce).unwrap();
+        }
+
+        let stats = world.get_world_stats();
+        assert_eq!(stats.animal_count.2, 4);
+        assert_eq!(stats.deferred_births.2, 2);
+    }
+
+    #[test]
+    fn ready_herbivore_reproduces_only_among_plants() {
+        let (poor, rich) = ((4, 4), (15, 15));
+        let mut world = testing::world(20, 20);
+        world.add_action_policy(Box::new(ReproductionFoodPolicy::new(3, 0))).unwrap();
+        for (dx, dy) in [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)] {
+            plant_at_offset(&mut world, rich, (dx, dy));
+        }
+        // Животные с наибольшей энергией размножаются независимо от решения мозга.
+        place(&mut world, poor, AnimaType::Herbivore, AnimalDirection::North, MAX_ANIMAL_ENERGY);
+        place(&mut world, rich, AnimaType::Herbivore, AnimalDirection::North, MAX_ANIMAL_ENERGY);
+        assert_eq!(percept_at(&mut world, poor.0, poor.1).plant_proximity, 0);
+        assert!(percept_at(&mut world, rich.0, rich.1).plant_proximity >= 3);
+
+        world.tick();
+
+        let stats = world.get_world_stats();
+        assert_eq!(stats.animal_count.0, 3);
+        assert_eq!(stats.policy_vetoes[0], 1);
+        // Размножилось животное среди растений, животное без еды поблизости сохранило
+        // энергию для размножения.
+        let energy = |world: &Landscape, (x, y): (usize, usize)| world.get_cell_info(x, y).unwrap().animal.unwrap().energy;
+        assert!(energy(&world, poor) > energy(&world, rich));
+    }
+
+    #[test]
+    fn omnivore_reproduction_needs_food_nearby() {
+        let mut world = testing::world(5, 5);
+        world.set_max_omnivore(25);
+        world.add_action_policy(Box::new(ReproductionFoodPolicy::new(0, 0).with_omnivore(1))).unwrap();
+        place(&mut world, (2, 2), AnimaType::Omnivore, AnimalDirection::North, MAX_ANIMAL_ENERGY);
+
+        world.perform_animal_action(2, 2, AnimalAction::Reproduce).unwrap();
+        assert_eq!(world.get_world_stats().animal_count.2, 1);
+
+        world.add_plant(2, 1, testing::plant()).unwrap();
+        world.perform_animal_action(2, 2, AnimalAction::Reproduce).unwrap();
+        assert_eq!(world.get_world_stats().animal_count.2, 2);
+    }
+
+    #[test]
+    fn populate_omnivores_places_omnivores() {
+        let mut world = testing::world(10, 10);
+        world.set_max_omnivore(5);
+
+        world.populate_omnivores((5, PlacementStrategy::Uniform), &mut testing::animal).unwrap();
+
+        assert_eq!(world.get_world_stats().animal_count.2, 5);
+        assert!(world.populate_omnivores((1, PlacementStrategy::Uniform), &mut testing::animal).is_err());
+    }
+
+    #[test]
+    fn species_counts_change_at_their_start_tick() {
+        let mut world = testing::world(10, 10);
+        world.set_start_tick(2, 4, 0);
+        world.populate_random(
+            (10, PlacementStrategy::Uniform),
+            (8, PlacementStrategy::Uniform),
+            (4, PlacementStrategy::Uniform),
+            &mut testing::plant,
+            &mut testing::animal,
+        ).unwrap();
+        assert_eq!(world.get_world_stats().animal_count, (0, 0, 0));
+
+        let mut introduced_at = vec![];
+        for iteration in 1..=5 {
+            world.tick();
+            let introduced = world.introduce_species(&mut testing::animal).unwrap();
+            let counts = world.get_world_stats().animal_count;
+
+            // Вид появляется в полном составе.
+            for species in introduced {
+                introduced_at.push((species.name(), iteration));
+                match species {
+                    AnimaType::Herbivore => assert_eq!(counts.0, 8),
+                    AnimaType::Carnivore => assert_eq!(counts.1, 4),
+                    AnimaType::Omnivore => unreachable!(),
+                }
+            }
+            if iteration < 2 {
+                assert_eq!((counts.0, counts.1), (0, 0), "итерация {}", iteration);
+            } else if iteration < 4 {
+                assert!(counts.0 > 0, "итерация {}", iteration);
+                assert_eq!(counts.1, 0, "итерация {}", iteration);
+            }
+        }
+
+        assert_eq!(introduced_at, vec![(AnimaType::Herbivore.name(), 2), (AnimaType::Carnivore.name(), 4)]);
+    }
+
+    #[test]
+    fn omnivores_are_introduced_at_their_start_tick() {
+        let mut world = testing::world(10, 10);
+        world.set_max_omnivore(5);
+        world.set_start_tick(0, 0, 2);
+
+        world.populate_omnivores((5, PlacementStrategy::Uniform), &mut testing::animal).unwrap();
+        assert_eq!(world.get_world_stats().animal_count.2, 0);
+
+        world.tick();
+        assert!(world.introduce_species(&mut testing::animal).unwrap().is_empty());
+        world.tick();
+        let introduced = world.introduce_species(&mut testing::animal).unwrap();
+        assert!(introduced == vec![AnimaType::Omnivore]);
+        assert_eq!(world.get_world_stats().animal_count.2, 5);
+    }
+
+    #[test]
+    fn omnivore_survives_on_plants_without_herbivores() {
+        let mut world = testing::world(9, 9);
+        let energy = 2.0 * ANIMAL_LIVE_ENERGY;
+        place(&mut world, (4, 4), AnimaType::Omnivore, AnimalDirection::North, energy);
+
+        // Без еды 50 попыток поесть стоили бы больше, чем запас энергии всеядного.
+        for _ in 0..50 {
+            // Съеденное растение заменяется свежим.
+            if let PlantInCell::Plant(handle) = world.landscape[4][3].plant {
+                if world.plants.get(handle).unwrap().is_eaten() {
+                    world.remove_plant(handle, 4, 3);
+                }
+            }
+            if let PlantInCell::None = world.landscape[4][3].plant {
+                world.add_plant(4, 3, testing::plant()).unwrap();
+            }
+            world.perform_animal_action(4, 4, AnimalAction::Eat).unwrap();
+        }
+
+        assert_eq!(world.get_world_stats().animal_count.0, 0);
+        let omnivore = world.animal_at(4, 4).unwrap();
+        assert!(!omnivore.is_dead());
+        assert!(omnivore.get_energy() > energy);
+        assert_eq!(world.eat_successes.2, 50);
+    }
+
+    #[test]
+    fn omnivore_eats_adjacent_herbivore() {
+        let mut world = testing::world(9, 9);
+        place(&mut world, (4, 4), AnimaType::Omnivore, AnimalDirection::North, 30.0);
+        place(&mut world, (4, 3), AnimaType::Herbivore, AnimalDirection::North, 40.0);
+
+        world.perform_animal_action(4, 4, AnimalAction::Eat).unwrap();
+
+        assert!(world.animal_at(4, 3).unwrap().is_dead());
+        let eaten = (0.5 * ANIMAL_EATEN_ENERGY_RATE * 40.0) as Energy;
+        let cost = (AnimalEnergyProfile::UNIFORM.eat * ANIMAL_LIVE_ENERGY as f64) as Energy;
+        assert!((world.animal_at(4, 4).unwrap().get_energy() - (30.0 - cost + eaten)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn predator_skips_protected_newborn_until_window_expires() {
+        let mut world = testing::world(9, 9);
+        world.set_protection_age(2, 0, 0);
+        place(&mut world, (4, 4), AnimaType::Carnivore, AnimalDirection::North, 30.0);
+        place(&mut world, (4, 3), AnimaType::Herbivore, AnimalDirection::North, 40.0);
+        let newborn = world.animal_at(4, 3).unwrap().get_id();
+
+        // Пока новорожденный моложе возраста защиты, хищник его пропускает.
+        for age in 0..2 {
+            assert_eq!(world.animal_at(4, 3).unwrap().get_age(), age);
+            world.perform_animal_action(4, 4, AnimalAction::Eat).unwrap();
+
+            assert!(!world.animal_at(4, 3).unwrap().is_dead());
+            assert_eq!(world.get_world_stats().protected_skips, (age + 1, 0, 0));
+
+            let handle = world.find_animal(newborn).unwrap();
+            world.animals.get_mut(handle).unwrap().tick_passed();
+        }
+
+        // Защита закончилась: хищник съедает животное, пропусков больше нет.
+        world.perform_animal_action(4, 4, AnimalAction::Eat).unwrap();
+
+        assert!(world.animal_at(4, 3).unwrap().is_dead());
+        assert_eq!(world.eat_successes.1, 1);
+        assert_eq!(world.get_world_stats().protected_skips, (2, 0, 0));
+    }
+
+    #[test]
+    fn carnivore_eats_omnivore() {
+        let mut world = testing::world(9, 9);
+        place(&mut world, (4, 4), AnimaType::Carnivore, AnimalDirection::North, 30.0);
+        place(&mut world, (4, 3), AnimaType::Omnivore, AnimalDirection::North, 40.0);
+
+        world.perform_animal_action(4, 4, AnimalAction::Eat).unwrap();
+
+        let omnivore = world.animal_at(4, 3).unwrap();
+        assert!(omnivore.is_dead() && omnivore.is_eaten());
+        assert_eq!(world.eat_successes.1, 1);
+    }
+
+    #[test]
+    fn populate_random_fills_small_world() {
+        let mut world = testing::populated_world(10, 10, (20, 10, 5));
+        world.set_max_omnivore(5);
+        world.populate_omnivores((5, PlacementStrategy::Uniform), &mut testing::animal).unwrap();
+
+        let stats = world.get_world_stats();
+        assert_eq!(stats.plant_count, 20);
+        assert_eq!(stats.animal_count, (10, 5, 5));
+        assert_eq!(world.plants_iter().count(), 20);
+        assert_eq!(world.animals_iter().count(), 20);
+
+        // В мире 100 ячеек: 81 животное уже не помещается, сколько свободно - в ошибке.
+        let error = world.populate_random(
+            (0, PlacementStrategy::Uniform),
+            (81, PlacementStrategy::Uniform),
+            (0, PlacementStrategy::Uniform),
+            &mut testing::plant,
+            &mut testing::animal,
+        ).unwrap_err();
+        assert!(error.to_string().contains("только 80 свободных ячеек"), "{}", error);
+        assert_eq!(world.get_world_stats().animal_count, (10, 5, 5));
+    }
+
+    /// Ячейки животных заданного вида.
+    fn species_cells(world: &Landscape, species: AnimaType) -> Vec<(usize, usize)> {
+        world.animals_iter()
+            .filter(|(_, _, animal)| animal.species == species)
+            .map(|(x, y, _)| (x, y))
+            .collect()
+    }
+
+    #[test]
+    fn half_split_places_agents_in_their_half() {
+        let mut world = testing::world(10, 8);
+        world.populate_random(
+            (20, PlacementStrategy::HalfSplit { axis: Axis::X, half: Half::First }),
+            (15, PlacementStrategy::HalfSplit { axis: Axis::Y, half: Half::Second }),
+            (20, PlacementStrategy::HalfSplit { axis: Axis::X, half: Half::Second }),
+            &mut testing::plant,
+            &mut testing::animal,
+        ).unwrap();
+
+        let plants: Vec<(usize, usize)> = world.plants_iter().map(|(x, y, _)| (x, y)).collect();
+        assert_eq!(plants.len(), 20);
+        assert!(plants.iter().all(|&(x, _)| x < 5), "{:?}", plants);
+
+        let herbivores = species_cells(&world, AnimaType::Herbivore);
+        assert_eq!(herbivores.len(), 15);
+        assert!(herbivores.iter().all(|&(_, y)| y >= 4), "{:?}", herbivores);
+
+        let carnivores = species_cells(&world, AnimaType::Carnivore);
+        assert_eq!(carnivores.len(), 20);
+        assert!(carnivores.iter().all(|&(x, _)| x >= 5), "{:?}", carnivores);
+
+        // Больше, чем помещается в половине, разместить нельзя.
+        let error = world.populate_random(
+            (21, PlacementStrategy::HalfSplit { axis: Axis::X, half: Half::First }),
+            (0, PlacementStrategy::Uniform),
+            (0, PlacementStrategy::Uniform),
+            &mut testing::plant,
+            &mut testing::animal,
+        ).unwrap_err();
+        assert!(error.to_string().contains("только 20 свободных ячеек"), "{}", error);
+    }
+
+    #[test]
+    fn patches_place_agents_inside_patch() {
+        // Расстояние между ячейками по оси с учетом тороидальности.
+        let distance = |a: usize, b: usize, size: usize| a.abs_diff(b).min(size - a.abs_diff(b));
+
+        for seed in 0..10 {
+            let mut world = Landscape::new(12, 12, 144, 144, 144, MAX_PLANT_GROW_ENERGY, Some(seed)).unwrap();
+            // Единственный участок радиуса 1 - квадрат 3x3, который растения заполняют целиком.
+            world.populate_random(
+                (9, PlacementStrategy::Patches { k: 1, radius: 1 }),
+                (5, PlacementStrategy::Patches { k: 1, radius: 2 }),
+                (0, PlacementStrategy::Uniform),
+                &mut testing::plant,
+                &mut testing::animal,
+            ).unwrap();
+
+            let plants: Vec<(usize, usize)> = world.plants_iter().map(|(x, y, _)| (x, y)).collect();
+            assert_eq!(plants.len(), 9);
+            for a in &plants {
+                for b in &plants {
+                    assert!(distance(a.0, b.0, 12) <= 2 && distance(a.1, b.1, 12) <= 2, "{:?}", plants);
+                }
+            }
+
+            // Травоядные - внутри квадрата 5x5.
+            let herbivores = species_cells(&world, AnimaType::Herbivore);
+            assert_eq!(herbivores.len(), 5);
+            for a in &herbivores {
+                for b in &herbivores {
+                    assert!(distance(a.0, b.0, 12) <= 4 && distance(a.1, b.1, 12) <= 4, "{:?}", herbivores);
+                }
+            }
+
+            // В участке 3x3 не поместится десятое растение.
+            let error = world.populate_random(
+                (10, PlacementStrategy::Patches { k: 1, radius: 1 }),
+                (0, PlacementStrategy::Uniform),
+                (0, PlacementStrategy::Uniform),
+                &mut testing::plant,
+                &mut testing::animal,
+            ).unwrap_err();
+            assert!(error.to_string().contains("свободных ячеек"), "{}", error);
+        }
+    }
+
+    /// Сосед животного в ячейке перед ним.
+    #[derive(Copy, Clone, Debug)]
+    enum Neighbour {
+        Nobody,
+        Plant,
+        Herbivore,
+    }
+
+    /// Ожидаемое изменение энергии животного и сдвинулось ли оно, если животное
+    /// выполняет действие при соседе перед собой. Травоядное-сосед имеет энергию
+    /// `prey_energy`.
+    fn expected_outcome(
+        species: AnimaType,
+        neighbour: Neighbour,
+        action: AnimalAction,
+        prey_energy: Energy
+    ) -> (Energy, bool) {
+        let cost = (ANIMAL_LIVE_ENERGY as f64) as Energy;
+        let meat = (ANIMAL_EATEN_ENERGY_RATE * prey_energy as f64) as Energy;
+
+        match (action, neighbour) {
+            (AnimalAction::Move, Neighbour::Herbivore) => (-cost, false),
+            (AnimalAction::Move, _) => (-cost, true),
+            // Попытка поесть, когда есть нечего, энергии не стоит.
+            (AnimalAction::Eat, Neighbour::Plant) => match species {
+                AnimaType::Herbivore => (PLANT_EATEN_ENERGY - cost, false),
+                AnimaType::Carnivore => (0.0, false),
+                AnimaType::Omnivore => (PLANT_EATEN_ENERGY / 2.0 - cost, false),
+            },
+            (AnimalAction::Eat, Neighbour::Herbivore) => match species {
+                AnimaType::Herbivore => (0.0, false),
+                AnimaType::Carnivore => (meat - cost, false),
+                AnimaType::Omnivore => (meat / 2.0 - cost, false),
+            },
+            (AnimalAction::Eat, Neighbour::Nobody) => (0.0, false),
+            (AnimalAction::Reproduce, _) => (-ANIMAL_BIRTH_ENERGY - cost, false),
+            _ => (-cost, false),
+        }
+    }
+
+    #[test]
+    fn forced_actions_change_energy_and_position() {
+        let actions = [
+            AnimalAction::None,
+            AnimalAction::TurnLeft,
+            AnimalAction::TurnRight,
+            AnimalAction::Move,
+            AnimalAction::Eat,
+            AnimalAction::Reproduce,
+        ];
+        let energy = 30.0;
+        let at = (4, 4);
+        let ahead = (4, 3);
+
+        let species_list = [AnimaType::Herbivore, AnimaType::Carnivore, AnimaType::Omnivore];
+        for (species_index, species) in species_list.into_iter().enumerate() {
+            for neighbour in [Neighbour::Nobody, Neighbour::Plant, Neighbour::Herbivore] {
+                for (action_index, action) in actions.into_iter().enumerate() {
+                    let mut world = testing::world(9, 9);
+                    world.set_max_omnivore(81);
+                    place(&mut world, at, species, AnimalDirection::North, energy);
+                    let id = world.get_cell_info(at.0, at.1).unwrap().animal.unwrap().id;
+                    match neighbour {
+                        Neighbour::Nobody => {}
+                        Neighbour::Plant => world.add_plant(ahead.0, ahead.1, testing::plant()).unwrap(),
+                        Neighbour::Herbivore => {
+                            place(&mut world, ahead, AnimaType::Herbivore, AnimalDirection::North, energy)
+                        }
+                    }
+                    let before = world.get_world_stats().animal_count;
+
+                    world.perform_animal_action(at.0, at.1, action).unwrap();
+
+                    let (delta, moved) = expected_outcome(species, neighbour, action, energy);
+                    let (x, y, animal) = world.animals_iter().find(|(_, _, animal)| animal.id == id).unwrap();
+                    let case = format!("вид {}, сосед {:?}, действие {}", species_index, neighbour, action_index);
+                    assert!((animal.energy - (energy + delta)).abs() < 1e-4, "{}: энергия {}", case, animal.energy);
+                    assert_eq!((x, y), if moved { ahead } else { at }, "{}", case);
+
+                    let after = world.get_world_stats().animal_count;
+                    let born = action == AnimalAction::Reproduce;
+                    let count = |counts: (usize, usize, usize)| match species {
+                        AnimaType::Herbivore => counts.0,
+                        AnimaType::Carnivore => counts.1,
+                        AnimaType::Omnivore => counts.2,
+                    };
+                    assert_eq!(count(after), count(before) + born as usize, "{}", case);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn attack_on_empty_cell_only_costs_energy() {
+        let mut world = testing::world(9, 9);
+        world.set_two_step_predation(1.0, 5);
+        place(&mut world, (4, 4), AnimaType::Carnivore, AnimalDirection::North, 30.0);
+
+        world.perform_animal_action(4, 4, AnimalAction::Attack).unwrap();
+
+        let animal = world.get_cell_info(4, 4).unwrap().animal.unwrap();
+        assert_eq!(animal.energy, 30.0 - (ANIMAL_LIVE_ENERGY as f64) as Energy);
+        assert_eq!(world.get_world_stats().hunt.1.kills, 0);
+        assert!((0..9).all(|x| (0..9).all(|y| world.get_cell_info(x, y).unwrap().corpse == 0.0)));
+    }
+
+    #[test]
+    fn kill_leaves_edible_corpse() {
+        let mut world = testing::world(9, 9);
+        world.set_two_step_predation(1.0, 5);
+        place(&mut world, (4, 4), AnimaType::Carnivore, AnimalDirection::North, 30.0);
+        place(&mut world, (4, 3), AnimaType::Herbivore, AnimalDirection::North, 30.0);
+        let cost = (ANIMAL_LIVE_ENERGY as f64) as Energy;
+
+        // Равные по энергии животные: нападение удается всегда.
+        world.perform_animal_action(4, 4, AnimalAction::Attack).unwrap();
+
+        let corpse = (ANIMAL_EATEN_ENERGY_RATE * 30.0) as Energy;
+        assert_eq!(world.get_cell_info(4, 3).unwrap().corpse, corpse);
+        assert_eq!(world.get_world_stats().hunt.1.kills, 1);
+
+        world.perform_animal_action(4, 4, AnimalAction::Eat).unwrap();
+
+        assert_eq!(world.get_cell_info(4, 3).unwrap().corpse, 0.0);
+        let animal = world.get_cell_info(4, 4).unwrap().animal.unwrap();
+        assert!((animal.energy - (30.0 - cost + corpse - cost)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn killed_animal_is_drawn() {
+        let mut world = testing::world(9, 9);
+        world.set_two_step_predation(1.0, 5);
+        place(&mut world, (4, 4), AnimaType::Carnivore, AnimalDirection::North, 30.0);
+        place(&mut world, (4, 3), AnimaType::Herbivore, AnimalDirection::North, 30.0);
+
+        world.perform_animal_action(4, 4, AnimalAction::Attack).unwrap();
+        world.tick();
+
+        assert!(world.is_view_ready());
+        assert!(world.get_view_state().iter()
+            .any(|&(x, y, stuff, _)| (x, y) == (4, 3) && stuff == CellStuff::KilledAnimal));
+    }
+
+    /// Мир 5x5 со всеми слоями карты: растение (0, 0), падаль (1, 3), живое травоядное
+    /// отслеживаемой династии (2, 2) и мертвое травоядное (4, 4).
+    fn world_with_all_layers() -> Landscape {
+        let mut world = testing::world(5, 5);
+        world.add_plant(0, 0, testing::plant()).unwrap();
+        world.landscape[1][3].carrion = 5.0;
+        place(&mut world, (2, 2), AnimaType::Herbivore, AnimalDirection::North, 30.0);
+        world.follow_dynasty(2, 2).unwrap();
+        place(&mut world, (4, 4), AnimaType::Herbivore, AnimalDirection::North, 0 as Energy);
+
+        world
+    }
+
+    #[test]
+    fn frame_contains_only_requested_layers() {
+        for mask in 0..16 {
+            let layers = LayerSet {
+                plants: mask & 1 != 0,
+                animals: mask & 2 != 0,
+                corpses: mask & 4 != 0,
+                dynasty: mask & 8 != 0,
+            };
+            let mut world = world_with_all_layers();
+            world.set_frame_request(FrameRequest { layers, every_nth_tick: 1 });
+            world.final_processing();
+
+            let map = world.get_view_state();
+            let stuff_at = |cell: (usize, usize)| map.iter()
+                .find(|&&(x, y, _, _)| (x, y) == cell)
+                .map(|&(_, _, stuff, _)| stuff);
+
+            assert!(stuff_at((0, 0)) == layers.plants.then_some(CellStuff::Plant), "{}", mask);
+            assert!(stuff_at((1, 3)) == layers.corpses.then_some(CellStuff::Carcass), "{}", mask);
+            assert!(stuff_at((4, 4)) == layers.corpses.then_some(CellStuff::DeadAnimal), "{}", mask);
+            assert!(stuff_at((2, 2)) == layers.animals.then_some(CellStuff::HerbBack), "{}", mask);
+            // Отметка династии передается вместе с животным.
+            if let Some(&(_, _, _, marks)) = map.iter().find(|&&(x, y, _, _)| (x, y) == (2, 2)) {
+                assert_eq!(marks.dynasty, layers.dynasty, "{}", mask);
+            }
+            let expected = [layers.plants, layers.animals, layers.corpses, layers.corpses];
+            assert_eq!(map.len(), expected.iter().filter(|&&on| on).count(), "{}", mask);
+        }
+    }
+
+    #[test]
+    fn frames_follow_requested_cadence() {
+        let world_with_plant = || {
+            let mut world = testing::world(5, 5);
+            world.add_plant(0, 0, testing::plant()).unwrap();
+            world
+        };
+
+        // По умолчанию карта собирается на каждой итерации.
+        let mut world = world_with_plant();
+        for _ in 0..3 {
+            world.tick();
+            assert!(world.is_view_ready());
+            assert!(!world.get_view_state().is_empty());
+        }
+
+        let mut world = world_with_plant();
+        world.set_frame_request(FrameRequest { layers: LayerSet::ALL, every_nth_tick: 3 });
+        for tick in 1..=9 {
+            world.tick();
+            assert_eq!(world.is_view_ready(), tick % 3 == 0, "итерация {}", tick);
+            assert_eq!(world.get_view_state().is_empty(), tick % 3 != 0, "итерация {}", tick);
+        }
+    }
+
+    /// Мир с фиксированным зерном: растения и животные, мозги которых загружены из
+    /// генома `genome` с генератором мира.
+    fn world_with_genome(genome: &str) -> Landscape {
+        let mut world = testing::populated_world(12, 12, (30, 0, 0));
+        let species = [AnimaType::Herbivore; 6].into_iter().chain([AnimaType::Carnivore; 3]);
+
+        for (index, animal_type) in species.enumerate() {
+            let brain = Brain::from_genome_json(genome, world.rng()).unwrap();
+            let animal = Animal::with_brain(animal_type, testing::animal_params(), AnimalDirection::North, 0, brain).unwrap();
+            world.add_animal(index, index, animal).unwrap();
+        }
+
+        world
+    }
+
+    #[test]
+    fn fixed_seed_worlds_with_loaded_genomes_match() {
+        let genome = Brain::from_rng(&mut StdRng::seed_from_u64(3)).to_json();
+        let (mut a, mut b) = (world_with_genome(&genome), world_with_genome(&genome));
+
+        for tick in 0..50 {
+            a.tick();
+            b.tick();
+            assert!(a.get_view_state() == b.get_view_state(), "Миры разошлись на итерации {}", tick);
+        }
+        assert_eq!(a.get_world_stats().animal_count, b.get_world_stats().animal_count);
+    }
+
+    /// Ячейки области восприятия без учета порядка.
+    fn sorted(mut cells: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+        cells.sort();
+        cells
+    }
+
+    /// Проверяет области восприятия животного в ячейке (5, 5) мира 11x11.
+    fn assert_footprint(
+        direction: AnimalDirection,
+        front: &[(usize, usize)],
+        left: &[(usize, usize)],
+        right: &[(usize, usize)],
+        proximity: &[(usize, usize)]
+    ) {
+        let footprint = testing::world(11, 11).perception_footprint(5, 5, direction, 2);
+
+        assert_eq!(sorted(footprint.front), sorted(front.to_vec()));
+        assert_eq!(sorted(footprint.left), sorted(left.to_vec()));
+        assert_eq!(sorted(footprint.right), sorted(right.to_vec()));
+        assert_eq!(sorted(footprint.proximity), sorted(proximity.to_vec()));
+    }
+
+    #[test]
+    fn footprint_facing_north() {
+        assert_footprint(
+            AnimalDirection::North,
+            &[(3, 3), (4, 3), (5, 3), (6, 3), (7, 3)],
+            &[(3, 5), (3, 4)],
+            &[(7, 5), (7, 4)],
+            &[(4, 5), (4, 4), (5, 4), (6, 4), (6, 5)],
+        );
+    }
+
+    #[test]
+    fn footprint_facing_south() {
+        assert_footprint(
+            AnimalDirection::South,
+            &[(3, 7), (4, 7), (5, 7), (6, 7), (7, 7)],
+            &[(7, 5), (7, 6)],
+            &[(3, 5), (3, 6)],
+            &[(6, 5), (6, 6), (5, 6), (4, 6), (4, 5)],
+        );
+    }
+
+    #[test]
+    fn footprint_facing_west() {
+        assert_footprint(
+            AnimalDirection::West,
+            &[(3, 3), (3, 4), (3, 5), (3, 6), (3, 7)],
+            &[(5, 7), (4, 7)],
+            &[(5, 3), (4, 3)],
+            &[(5, 6), (4, 6), (4, 5), (4, 4), (5, 4)],
+        );
+    }
+
+    #[test]
+    fn footprint_facing_east() {
+        assert_footprint(
+            AnimalDirection::East,
+            &[(7, 3), (7, 4), (7, 5), (7, 6), (7, 7)],
+            &[(5, 3), (6, 3)],
+            &[(5, 7), (6, 7)],
+            &[(5, 4), (6, 4), (6, 5), (6, 6), (5, 6)],
+        );
+    }
+
+    #[test]
+    fn footprint_is_limited_by_vision_radius() {
+        let world = testing::world(11, 11);
+
+        let near = world.perception_footprint(5, 5, AnimalDirection::North, 1);
+        assert!(near.front.is_empty() && near.left.is_empty() && near.right.is_empty());
+        assert_eq!(near.proximity.len(), 5);
+
+        let blind = world.perception_footprint(5, 5, AnimalDirection::North, 0);
+        assert_eq!(blind, PerceptionFootprint::default());
+    }
+
+    #[test]
+    fn footprint_wraps_on_torus_and_stops_at_walls() {
+        let mut world = testing::world(10, 10);
+
+        let torus = world.perception_footprint(0, 0, AnimalDirection::North, 2);
+        assert_eq!(sorted(torus.front), vec![(0, 8), (1, 8), (2, 8), (8, 8), (9, 8)]);
+        assert_eq!(sorted(torus.left), vec![(8, 0), (8, 9)]);
+
+        world.set_topology(Topology::Bounded);
+        let bounded = world.perception_footprint(0, 0, AnimalDirection::North, 2);
+        assert!(bounded.front.is_empty() && bounded.left.is_empty());
+        assert_eq!(bounded.right, vec![(2, 0)]);
+        assert_eq!(bounded.proximity, vec![(1, 0)]);
+    }
+
+    #[test]
+    fn topology_is_reported_for_display() {
+        let mut world = testing::world(10, 10);
+        assert_eq!(world.get_topology(), Topology::Torus);
+
+        for topology in [Topology::Bounded, Topology::Cylinder { wrap_x: false, wrap_y: true }, Topology::Torus] {
+            world.set_topology(topology);
+            assert_eq!(world.get_topology(), topology);
+        }
+    }
+
+    /// Мир 10x10 с травоядным в ячейке (0, 0), смотрящим на север, и агентами у нижнего
+    /// края, которые на торе попадают в его область «впереди».
+    fn world_with_agents_behind_north_edge(topology: Topology) -> Landscape {
+        let mut world = testing::world(10, 10);
+        world.set_topology(topology);
+        place(&mut world, (0, 0), AnimaType::Herbivore, AnimalDirection::North, 30.0);
+        for x in [0, 1, 9] {
+            world.add_plant(x, 8, testing::plant()).unwrap();
+            world.add_plant(x, 9, testing::plant()).unwrap();
+        }
+        place(&mut world, (1, 8), AnimaType::Herbivore, AnimalDirection::South, 30.0);
+        place(&mut world, (9, 8), AnimaType::Carnivore, AnimalDirection::South, 30.0);
+
+        world
+    }
+
+    #[test]
+    fn bounded_world_animal_at_corner_sees_nothing_in_front() {
+        let mut torus = world_with_agents_behind_north_edge(Topology::Torus);
+        let inputs = percept_at(&mut torus, 0, 0);
+        assert!(inputs.plant_front > 0 && inputs.herbivore_front > 0 && inputs.carnivore_front > 0);
+
+        let mut bounded = world_with_agents_behind_north_edge(Topology::Bounded);
+        let inputs = percept_at(&mut bounded, 0, 0);
+        assert_eq!((inputs.plant_front, inputs.herbivore_front, inputs.carnivore_front), (0, 0, 0));
+        assert_eq!(inputs.wall_front, 1);
+    }
+
+    #[test]
+    fn bounded_world_animal_at_corner_stays_put_moving_north() {
+        let mut world = world_with_agents_behind_north_edge(Topology::Bounded);
+        let id = world.animal_at(0, 0).unwrap().get_id();
+
+        world.perform_animal_action(0, 0, AnimalAction::Move).unwrap();
+
+        assert_eq!(world.animal_at(0, 0).map(|animal| animal.get_id()), Some(id));
+        assert!(world.animal_at(0, 9).is_none());
+    }
+
+    #[test]
+    fn blind_animal_pays_base_cost_and_sees_nothing() {
+        // Вокруг ячейки (4, 4) растения и животные обоих видов во всех областях восприятия.
+        let crowd = || {
+            let mut world = testing::world(9, 9);
+            for x in 2..=6 {
+                for y in 2..=6 {
+                    if (x, y) == (4, 4) {
+                        continue;
+                    }
+                    world.add_plant(x, y, testing::plant()).unwrap();
+                    let animal_type = if (x + y) % 2 == 0 { AnimaType::Herbivore } else { AnimaType::Carnivore };
+                    place(&mut world, (x, y), animal_type, AnimalDirection::North, 30.0);
+                }
+            }
+            world
+        };
+        let counts = |inputs: &AnimalInputSignal| [
+            inputs.plant_front, inputs.plant_left, inputs.plant_right, inputs.plant_proximity,
+            inputs.herbivore_front, inputs.herbivore_left, inputs.herbivore_right, inputs.herbivore_proximity,
+            inputs.carnivore_front, inputs.carnivore_left, inputs.carnivore_right, inputs.carnivore_proximity,
+        ];
+
+        // Зрячее животное видит соседей в каждой области.
+        let mut world = crowd();
+        place(&mut world, (4, 4), AnimaType::Herbivore, AnimalDirection::North, 30.0);
+        assert!(counts(&percept_at(&mut world, 4, 4)).iter().all(|&count| count > 0));
+
+        let params = AnimalParams {
+            live_energy: 1.0,
+            energy_profile: AnimalEnergyProfile {
+                perception_base: 0.5,
+                perception_radius: 0.25,
+                ..AnimalEnergyProfile::UNIFORM
+            },
+            vision_radius: 0,
+            ..testing::animal_params()
+        };
+        let mut blind = Animal::with_brain(AnimaType::Herbivore, params, AnimalDirection::North, 0, MovingBrain).unwrap();
+        blind.set_energy(30.0);
+        let mut world = crowd();
+        world.add_animal(4, 4, blind).unwrap();
+
+        let inputs = percept_at(&mut world, 4, 4);
+        assert_eq!(counts(&inputs), [0; 12]);
+
+        // Слепое животное платит за восприятие только базовую цену: 0.5 · 1.0.
+        let handle = world.find_animal(world.animal_at(4, 4).unwrap().get_id()).unwrap();
+        let animal = world.animals.get_mut(handle).unwrap();
+        animal.action(&inputs);
+        assert_eq!(animal.get_energy(), 29.5);
+    }
+
+    #[test]
+    fn cylinder_edges_follow_per_axis_wrapping() {
+        for (wrap_x, wrap_y) in [(false, false), (true, false), (false, true), (true, true)] {
+            // Травоядные смотрят через край мира по оси "x" и по оси "y", за краем - растения.
+            let mut world = testing::world(10, 10);
+            world.set_topology(Topology::Cylinder { wrap_x, wrap_y });
+            place(&mut world, (0, 5), AnimaType::Herbivore, AnimalDirection::West, 30.0);
+            place(&mut world, (5, 0), AnimaType::Herbivore, AnimalDirection::North, 30.0);
+            world.add_plant(8, 5, testing::plant()).unwrap();
+            world.add_plant(5, 8, testing::plant()).unwrap();
+            let case = format!("wrap_x: {}, wrap_y: {}", wrap_x, wrap_y);
+
+            let west = percept_at(&mut world, 0, 5);
+            assert_eq!(west.plant_front > 0, wrap_x, "{}", case);
+            assert_eq!(west.wall_front, !wrap_x as usize, "{}", case);
+
+            let north = percept_at(&mut world, 5, 0);
+            assert_eq!(north.plant_front > 0, wrap_y, "{}", case);
+            assert_eq!(north.wall_front, !wrap_y as usize, "{}", case);
+
+            // Через склеенный край животное переходит, в границу упирается.
+            world.perform_animal_action(0, 5, AnimalAction::Move).unwrap();
+            world.perform_animal_action(5, 0, AnimalAction::Move).unwrap();
+            assert_eq!(world.animal_at(9, 5).is_some(), wrap_x, "{}", case);
+            assert_eq!(world.animal_at(0, 5).is_some(), !wrap_x, "{}", case);
+            assert_eq!(world.animal_at(5, 9).is_some(), wrap_y, "{}", case);
+            assert_eq!(world.animal_at(5, 0).is_some(), !wrap_y, "{}", case);
+        }
+    }
+
+    #[test]
+    fn traced_animal_perception_follows_its_moves() {
+        let mut world = testing::world(11, 11);
+        place(&mut world, (5, 5), AnimaType::Herbivore, AnimalDirection::North, 30.0);
+        let id = world.animal_at(5, 5).unwrap().get_id();
+        world.set_traced_animal(Some(id));
+
+        world.perform_animal_action(5, 5, AnimalAction::Move).unwrap();
+
+        assert_eq!(world.traced_position, Some((5, 4)));
+        assert_eq!(
+            world.animal_perception(id),
+            Some(world.perception_footprint(5, 4, AnimalDirection::North, testing::animal_params().vision_radius))
+        );
+        assert!(world.adjust_energy(id, 1.0).is_ok());
+    }
+
+    #[test]
+    fn occupancy_counts_every_species_each_tick() {
+        let mut world = testing::world(8, 8);
+        world.set_occupancy_tracking(true);
+        world.add_plant(1, 1, testing::plant()).unwrap();
+        for (cell, animal_type) in [((2, 2), AnimaType::Herbivore), ((5, 5), AnimaType::Carnivore), ((6, 2), AnimaType::Omnivore)] {
+            place(&mut world, cell, animal_type, AnimalDirection::North, 30.0);
+        }
+
+        // Сумма по ячейкам - сколько особей вида было в мире на каждой итерации.
+        let mut expected = (0, 0, 0, 0);
+        for _ in 0..10 {
+            world.tick();
+            let stats = world.get_world_stats();
+            expected.0 += stats.plant_count;
+            expected.1 += stats.animal_count.0;
+            expected.2 += stats.animal_count.1;
+            expected.3 += stats.animal_count.2;
+        }
+
+        let total = |grid: &OccupancyGrid| grid.iter().flatten().map(|&count| count as usize).sum::<usize>();
+        let occupancy = world.occupancy.as_ref().unwrap();
+        assert_eq!(
+            (total(&occupancy.0), total(&occupancy.1), total(&occupancy.2), total(&occupancy.3)),
+            expected
+        );
+        assert!(expected.3 > 0);
+        assert_eq!(occupancy.0[1][1], 10);
+
+        let prefix = std::env::temp_dir().join(format!("evolution_occupancy_{}", std::process::id()));
+        let paths = world.export_occupancy(prefix.to_str().unwrap()).unwrap();
+        assert_eq!(paths.len(), 8);
+        let csv = fs::read_to_string(&paths[0]).unwrap();
+        assert_eq!(csv.lines().count(), 8);
+        assert_eq!(csv.lines().nth(1).unwrap().split(',').nth(1), Some("10"));
+        assert!(fs::read(&paths[1]).unwrap().starts_with(b"\x89PNG"));
+        for path in paths {
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn adjacent_offspring_spot_is_within_radius() {
+        let mut world = testing::world(9, 9);
+        place(&mut world, (4, 4), AnimaType::Herbivore, AnimalDirection::North, MAX_ANIMAL_ENERGY);
+
+        for radius in [1, 2] {
+            for _ in 0..50 {
+                let spot = world
+                    .find_offspring_spot(AgentType::Herbivore, 4, 4, ReproductionPlacement::Adjacent { radius })
+                    .unwrap();
+                assert_ne!(spot, (4, 4));
+                assert!(spot.0.abs_diff(4) <= radius && spot.1.abs_diff(4) <= radius, "{:?}", spot);
+            }
+        }
+    }
+
+    #[test]
+    fn adjacent_offspring_spot_falls_back_when_neighbors_are_occupied() {
+        let mut world = testing::world(9, 9);
+        for x in 3..=5 {
+            for y in 3..=5 {
+                place(&mut world, (x, y), AnimaType::Herbivore, AnimalDirection::North, MAX_ANIMAL_ENERGY);
+            }
+        }
+
+        // Соседи заняты: место находится в другой части мира.
+        for _ in 0..50 {
+            let spot = world
+                .find_offspring_spot(AgentType::Herbivore, 4, 4, ReproductionPlacement::Adjacent { radius: 1 })
+                .unwrap();
+            assert!(spot.0.abs_diff(4) > 1 || spot.1.abs_diff(4) > 1, "{:?}", spot);
+        }
+
+        // Мир заполнен целиком: места для потомка нет нигде.
+        let mut world = Landscape::new(5, 5, 26, 25, 25, MAX_PLANT_GROW_ENERGY, Some(testing::TEST_SEED)).unwrap();
+        for (x, y) in world.cells().collect::<Vec<_>>() {
+            world.add_plant(x, y, testing::plant()).unwrap();
+        }
+        let spot = world.find_offspring_spot(AgentType::Plant, 2, 2, ReproductionPlacement::Adjacent { radius: 1 });
+        assert!(spot.is_err());
+    }
+
+    /// Мир 9x9 с вынашиванием потомков (потомок появляется рядом с родителем) и
+    /// травоядным в ячейке (4, 4), которое начало вынашивать потомка.
+    fn gestating_world(ticks: usize) -> Landscape {
+        let mut world = testing::world(9, 9);
+        world.set_gestation(ticks, 0 as Energy, 1);
+        world.set_reproduction_placement(ReproductionPlacement::Anywhere, ReproductionPlacement::Adjacent { radius: 1 });
+        place(&mut world, (4, 4), AnimaType::Herbivore, AnimalDirection::North, MAX_ANIMAL_ENERGY);
+        world.perform_animal_action(4, 4, AnimalAction::Reproduce).unwrap();
+
+        world
+    }
+
+    #[test]
+    fn pending_birth_hatches_next_to_moved_parent() {
+        let mut world = gestating_world(2);
+        world.perform_animal_action(4, 4, AnimalAction::Move).unwrap();
+
+        world.hatch_pending_births();
+        assert_eq!(world.pending_births.len(), 1);
+
+        world.iteration += 2;
+        world.hatch_pending_births();
+
+        assert!(world.pending_births.is_empty() && world.pending_parents.is_empty());
+        assert_eq!(world.failed_births, 0);
+        assert_eq!(world.get_world_stats().animal_count.0, 2);
+        let child = world.cells()
+            .find(|&cell| cell != (4, 3) && world.animal_at(cell.0, cell.1).is_some())
+            .unwrap();
+        assert!(child.0.abs_diff(4) <= 1 && child.1.abs_diff(3) <= 1);
+    }
+
+    #[test]
+    fn pending_birth_is_lost_with_its_parent() {
+        let mut world = gestating_world(2);
+        let parent = world.animal_at(4, 4).unwrap().get_id();
+        world.animals.get_mut(world.find_animal(parent).unwrap()).unwrap().set_energy(0 as Energy);
+
+        world.hatch_pending_births();
+
+        assert!(world.pending_births.is_empty() && world.pending_parents.is_empty());
+        assert_eq!(world.failed_births, 1);
+        assert_eq!(world.get_world_stats().animal_count.0, 1);
+    }
+
+    #[test]
+    fn pending_birth_without_space_is_lost() {
+        let mut world = testing::world(1, 1);
+        world.set_gestation(1, 0 as Energy, 1);
+        place(&mut world, (0, 0), AnimaType::Herbivore, AnimalDirection::North, MAX_ANIMAL_ENERGY);
+        world.perform_animal_action(0, 0, AnimalAction::Reproduce).unwrap();
+
+        world.iteration += 1;
+        world.hatch_pending_births();
+
+        assert!(world.pending_births.is_empty());
+        assert_eq!(world.failed_births, 1);
+        assert_eq!(world.get_world_stats().animal_count.0, 1);
+    }
+
+    #[test]
+    fn parent_carries_limited_number_of_births() {
+        let mut world = gestating_world(5);
+        let energy = world.animal_at(4, 4).unwrap().get_energy();
+
+        world.perform_animal_action(4, 4, AnimalAction::Reproduce).unwrap();
+
+        assert_eq!(world.pending_births.len(), 1);
+        assert_eq!(world.deferred_births.0, 1);
+        assert_eq!(world.animal_at(4, 4).unwrap().get_energy(), energy);
+    }
+
+    #[test]
+    fn scenario_restores_pending_births() {
+        let mut world = gestating_world(3);
+        world.add_plant(1, 2, testing::plant()).unwrap();
+        let scenario = world.to_scenario();
+
+        let mut loaded = testing::world(9, 9);
+        loaded.set_gestation(3, 0 as Energy, 1);
+        loaded.load_scenario(&scenario.text, &mut testing::plant, &mut |animal_type, direction, brain, _rng| {
+            let json = scenario.genomes.iter()
+                .find(|(file, _)| BrainSource::Genome(file) == brain)
+                .map(|(_, json)| json.as_str())
+                .unwrap();
+            let brain = Brain::from_genome_json(json, &mut StdRng::seed_from_u64(1))?;
+            Animal::with_brain(animal_type, testing::animal_params(), direction, 0, brain)
+        }).unwrap();
+
+        assert_eq!(loaded.pending_births.len(), 1);
+        assert_eq!(loaded.pending_parents.get(&loaded.animal_at(4, 4).unwrap().get_id()), Some(&(4, 4)));
+        assert_eq!(loaded.get_world_stats().plant_count, 1);
+        let lines = |text: &str| text.lines().skip(1).map(str::to_string).collect::<Vec<_>>();
+        assert_eq!(lines(&loaded.to_scenario().text), lines(&scenario.text));
+    }
+
+    #[test]
+    fn checkpoint_resumes_run_exactly() {
+        let new_world = || testing::populated_world(20, 20, (40, 12, 4));
+        let mut straight = new_world();
+        for _ in 0..1000 {
+            straight.tick();
+        }
+
+        let mut first_half = new_world();
+        for _ in 0..500 {
+            first_half.tick();
+        }
+        let checkpoint = first_half.to_checkpoint();
+
+        let mut resumed = testing::world(20, 20);
+        resumed.load_scenario(&checkpoint.text, &mut testing::plant, &mut |animal_type, direction, brain, _rng| {
+            let json = checkpoint.genomes.iter()
+                .find(|(file, _)| BrainSource::Genome(file) == brain)
+                .map(|(_, json)| json.as_str())
+                .unwrap();
+            let brain = Brain::from_genome_json(json, &mut StdRng::seed_from_u64(1))?;
+            Animal::with_brain(animal_type, testing::animal_params(), direction, 0, brain)
+        }).unwrap();
+        for _ in 0..500 {
+            resumed.tick();
+        }
+
+        assert!(straight.get_world_stats().animal_count != (0, 0, 0));
+        assert_eq!(resumed.get_world_stats(), straight.get_world_stats());
+        assert_eq!(resumed.to_checkpoint().text, straight.to_checkpoint().text);
+        resumed.validate_statistics().unwrap();
+    }
+
+    #[test]
+    fn corrupted_counters_fail_statistics_validation() {
+        let mut world = testing::populated_world(20, 20, (40, 12, 4));
+        world.set_stats_validation_period(1);
+        for _ in 0..100 {
+            world.tick();
+        }
+        assert!(world.get_statistics().deaths != (0, 0, 0));
+        assert_eq!(world.take_stats_mismatch(), None);
+        world.validate_statistics().unwrap();
+
+        let error = |world: &Landscape| world.validate_statistics().unwrap_err().to_string();
+
+        world.animal_reproductions.1 += 1;
+        assert!(error(&world).contains("births.1"), "{}", error(&world));
+        world.animal_reproductions.1 -= 1;
+
+        world.animal_deaths.0 += 2;
+        assert!(error(&world).contains("deaths.0"), "{}", error(&world));
+        world.animal_deaths.0 -= 2;
+
+        world.landscape[3][3].carrion = -1.0;
+        assert!(error(&world).contains("энергия падали"), "{}", error(&world));
+        world.landscape[3][3].carrion = 0 as Energy;
+
+        world.validate_statistics().unwrap();
+
+        // Сверка во время итерации записывает расхождение, а не останавливает программу.
+        world.animal_deaths.2 += 1;
+        world.tick();
+        let (iteration, mismatch) = world.take_stats_mismatch().unwrap();
+        assert_eq!(iteration, 101);
+        assert!(mismatch.contains("deaths.2"), "{}", mismatch);
+        assert_eq!(world.take_stats_mismatch(), None);
+    }
+
+    /// Хеш FNV-1a текста: в отличие от `DefaultHasher` не зависит от версии Rust.
+    fn fnv1a(text: &str) -> u64 {
+        text.bytes().fold(0xcbf29ce484222325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+    }
+
+    #[test]
+    fn golden_run_matches_recorded_checkpoint() {
+        // Хеш записан прогоном с животными - объектами трейта. Прогон с хранением
+        // животных в перечислении (возможность `enum-dispatch`) должен дать тот же мир.
+        const GOLDEN_HASH: u64 = 179547972890539731;
+        let mut world = testing::populated_world(20, 20, (40, 12, 4));
+        for _ in 0..300 {
+            world.tick();
+        }
+
+        assert_eq!(fnv1a(&world.to_checkpoint().text), GOLDEN_HASH);
+    }
+
+    #[test]
+    fn golden_trace_matches_recorded_hash() {
+        // Хеш следа 1000 итераций (численность видов после каждой итерации и итоговая
+        // контрольная точка) записан на Linux x86_64 и должен совпадать на любой
+        // платформе: порядок обхода коллекций и сравнение энергий в мире определены.
+        const GOLDEN_TRACE_HASH: u64 = 16916318644348765499;
+        let mut world = testing::populated_world(20, 20, (40, 12, 4));
+        let mut trace = String::new();
+        for _ in 0..1000 {
+            world.tick();
+            let stats = world.get_world_stats();
+            trace.push_str(&format!("{} {} {:?}\n", stats.iteration, stats.plant_count, stats.animal_count));
+        }
+        trace.push_str(&world.to_checkpoint().text);
+
+        assert_eq!(fnv1a(&trace), GOLDEN_TRACE_HASH);
+    }
+
+    /// Создает животное сценария с параметрами по умолчанию: мозг случайный, из файла
+    /// генома или клон первого лучшего животного своего вида из файла лучших животных.
+    fn scenario_animal(
+        animal_type: AnimaType,
+        direction: AnimalDirection,
+        brain: BrainSource,
+        rng: &mut dyn RngCore
+    ) -> Result<Box<dyn AnimalAlive>, RecoverableError> {
+        let brain = match brain {
+            BrainSource::Random => return Animal::<Brain>::new(animal_type, testing::animal_params(), direction, 0, rng),
+            BrainSource::Genome(file) => {
+                let json = fs::read_to_string(file).map_err(|e| RecoverableError::new(e.to_string()))?;
+                Brain::from_genome_json(&json, rng)?
+            }
+            BrainSource::Champions(file) => crate::config::init::read_champions::<Brain>(file, rng)?
+                .into_iter()
+                .find(|(champion_type, _)| *champion_type == animal_type)
+                .map(|(_, brain)| brain)
+                .ok_or_else(|| RecoverableError::new("нет лучших животных вида".to_string()))?,
+        };
+
+        Animal::with_brain(animal_type, testing::animal_params(), direction, 0, brain)
+    }
+
+    /// Живые животные мира: координаты, вид, направление и энергия.
+    fn animals_of(world: &Landscape) -> Vec<((usize, usize), AnimaType, AnimalDirection, Energy)> {
+        world.animals_iter()
+            .map(|(x, y, animal)| ((x, y), animal.species, animal.direction, animal.energy))
+            .collect()
+    }
+
+    #[test]
+    fn random_block_places_same_agents_with_same_seed() {
+        let scenario = "size 9 9\nrandom herbivore 10 5..20 random random\nrandom carnivore 5 full 1,0,0,3 random\n";
+        let load = || {
+            let mut world = testing::world(9, 9);
+            world.load_scenario(scenario, &mut testing::plant, &mut scenario_animal).unwrap();
+            world
+        };
+
+        let (first, second) = (load(), load());
+
+        assert_eq!(first.get_statistics().animal_count, (10, 5, 0));
+        assert_eq!(first.to_scenario().text, second.to_scenario().text);
+        assert!(animals_of(&first) == animals_of(&second));
+        assert_eq!(first.get_scenario_blocks(), ["herbivore 10 5..20 random random", "carnivore 5 full 1,0,0,3 random"]);
+    }
+
+    #[test]
+    fn random_block_sets_energy_and_facing() {
+        let mut world = testing::world(9, 9);
+        world.load_scenario(
+            "size 9 9\nrandom herbivore 20 7 east random\nrandom carnivore 20 2..3 0,0,1,0 random\nrandom omnivore 20 full random random\n",
+            &mut testing::plant,
+            &mut scenario_animal,
+        ).unwrap();
+
+        let animals = animals_of(&world);
+        let of = |animal_type: AnimaType| animals.iter().filter(move |animal| animal.1 == animal_type);
+        assert!(of(AnimaType::Herbivore).all(|animal| animal.2 == AnimalDirection::East && animal.3 == 7.0));
+        assert!(of(AnimaType::Carnivore).all(|animal| animal.2 == AnimalDirection::West && (2.0..=3.0).contains(&animal.3)));
+        assert!(of(AnimaType::Omnivore).all(|animal| animal.3 == testing::animal_params().energy));
+        // Случайные направления и энергии не совпадают у всех животных блока.
+        assert!(of(AnimaType::Carnivore).any(|animal| animal.3 != of(AnimaType::Carnivore).next().unwrap().3));
+        assert!(DIRECTIONS.iter().all(|&direction| of(AnimaType::Omnivore).any(|animal| animal.2 == direction)));
+    }
+
+    #[test]
+    fn random_block_loads_brains_from_files() {
+        let dir = std::env::temp_dir();
+        let genome_file = dir.join(format!("evolution_block_genome_{}.json", std::process::id()));
+        let champions_file = dir.join(format!("evolution_block_champions_{}.txt", std::process::id()));
+        let genome = Brain::default();
+        let champion = testing::animal(AnimaType::Carnivore, &mut StdRng::seed_from_u64(2));
+        fs::write(&genome_file, genome.genome_json().unwrap()).unwrap();
+        fs::write(&champions_file, format!("carnivore alive 1 {}\n", champion.get_genome_json().unwrap())).unwrap();
+
+        let mut world = testing::world(9, 9);
+        let scenario = format!(
+            "size 9 9\nrandom herbivore 3 full north genome:{}\nrandom carnivore 3 full north champions:{}\n",
+            genome_file.display(),
+            champions_file.display()
+        );
+        world.load_scenario(&scenario, &mut testing::plant, &mut scenario_animal).unwrap();
+        fs::remove_file(&genome_file).unwrap();
+        fs::remove_file(&champions_file).unwrap();
+
+        let genomes: Vec<(AnimaType, Option<Vec<f64>>)> = world.cells()
+            .filter_map(|(x, y)| world.animal_at(x, y))
+            .map(|animal| (animal.get_type(), animal.get_genome()))
+            .collect();
+        assert_eq!(genomes.len(), 6);
+        for (animal_type, animal_genome) in genomes {
+            let expected = match animal_type {
+                AnimaType::Herbivore => genome.to_genome(),
+                _ => champion.get_genome(),
+            };
+            assert_eq!(animal_genome, expected);
+        }
+    }
+
+    #[test]
+    fn random_block_errors_name_the_line() {
+        for block in [
+            "random herbivore 3 full north genome:/nonexistent/genome.json",
+            "random herbivore 3 full north champions:/nonexistent/champions.txt",
+            "random herbivore 3 full north memory",
+            "random herbivore 3 full 1,1,1 random",
+            "random herbivore 3 full 0,0,0,0 random",
+            "random herbivore 3 5..1 north random",
+            "random herbivore 3 full",
+            "random herbivore 100 full north random",
+        ] {
+            let mut world = testing::world(9, 9);
+            let result = world.load_scenario(&format!("size 9 9\n{}\n", block), &mut testing::plant, &mut scenario_animal);
+
+            assert!(result.unwrap_err().to_string().starts_with("Сценарий, строка 2"), "{}", block);
+            assert_eq!(world.get_statistics().animal_count, (0, 0, 0), "{}", block);
+        }
+    }
+
+    #[test]
+    fn scenario_with_unknown_parent_is_rejected() {
+        let mut world = testing::world(9, 9);
+        let result = world.load_scenario(
+            "size 9 9\npending herbivore 7 1 10 north -\n",
+            &mut testing::plant,
+            &mut |animal_type, _, _, rng| Ok(testing::animal(animal_type, rng)),
+        );
+
+        assert!(result.unwrap_err().to_string().starts_with("Сценарий, строка 2"));
+    }
+
+    #[test]
+    fn postmortem_history_keeps_last_deaths() {
+        let mut world = testing::world(9, 9);
+        world.set_postmortem_history(3);
+        // Животным не хватает энергии даже на восприятие (неудачная попытка поесть
+        // бесплатна, поэтому голод не должен зависеть от выбранного действия).
+        let params = AnimalParams {
+            energy_profile: AnimalEnergyProfile { perception_base: 1.0, ..AnimalEnergyProfile::UNIFORM },
+            ..testing::animal_params()
+        };
+        for x in 0..5 {
+            let mut animal = testing::animal_facing(AnimaType::Herbivore, params, AnimalDirection::South);
+            animal.set_energy(ANIMAL_LIVE_ENERGY / 100.0);
+            world.add_animal(x * 2, 0, animal).unwrap();
+        }
+
+        world.tick();
+
+        let deaths = world.get_postmortems();
+        assert_eq!(world.get_statistics().animal_count.0, 0);
+        assert_eq!(deaths.len(), 3);
+        assert!(deaths.iter().all(|death| death.cause == DeathCause::Starved));
+
+        // Умершие животные удалены из хранилища, их память ушла в пул.
+        assert_eq!(world.animals.len(), 0);
+        assert_eq!(world.spare_animals.len(), 5);
+    }
+
+    /// Животное, которое умрет от голода на следующей итерации: на восприятие
+    /// тратится больше энергии, чем у него осталось.
+    fn place_starving(world: &mut Landscape, (x, y): (usize, usize), energy: Energy) -> Handle {
+        let params = AnimalParams {
+            energy_profile: AnimalEnergyProfile { perception_base: 1.0, ..AnimalEnergyProfile::UNIFORM },
+            ..testing::animal_params()
+        };
+        let mut animal = testing::animal_facing(AnimaType::Herbivore, params, AnimalDirection::North);
+        animal.set_energy(energy);
+        world.add_animal(x, y, animal).unwrap();
 
-                animal.move_action(true);
-            },
+        match world.landscape[x][y].animal {
+            AnimalInCell::Animal(handle) => handle,
+            AnimalInCell::None => unreachable!(),
         }
     }
 
-    /// Реализует функцию поедания у животного. Возможность съесть что-то определяется ранее,
-    /// в методе Self::percept, где животно анализирует текущую обстановку.
-    ///
-    /// # Arguments
-    ///
-    /// * `animal`: Изменяемая ссылка на животное.
-    /// * `x`: Положение животного по "x".
-    /// * `y`: Положение животного по "y".
-    ///
-    /// Returns: ()
-    fn eating_animal_action(&self, animal: &mut dyn AnimalAlive, x: usize, y: usize) {
-        match animal.get_type() {
-            // Травоядное ест траву
-            AnimaType::Herbivore => {
-                let coord = match animal.get_direction() {
-                    AnimalDirection::North => {
-                        self.choose_plant(x, y, &NORTH_PROXIMITY)
-                    }
-                    AnimalDirection::South => {
-                        self.choose_plant(x, y, &SOUTH_PROXIMITY)
-                    }
-                    AnimalDirection::West => {
-                        self.choose_plant(x, y, &WEST_PROXIMITY)
-                    }
-                    AnimalDirection::East => {
-                        self.choose_plant(x, y, &EAST_PROXIMITY)
-                    }
-                };
-
-                match coord {
-                    Some(coord) => {
-                        // Получить растение по координатам
-                        if let PlantInCell::Plant(plant) = self.landscape[coord.0][coord.1].plant {
-                            let plant = Self::get_agent_mut(plant);
+    #[test]
+    fn recycled_slot_does_not_alias_dead_animal() {
+        let mut world = testing::world(5, 5);
+        let victim = place_starving(&mut world, (0, 0), ANIMAL_LIVE_ENERGY / 100.0);
+        let victim_id = world.animals.get(victim).unwrap().get_id();
+
+        world.tick();
+
+        // Умершее животное освободило слот хранилища, его память ушла в пул.
+        assert!(world.animals.get(victim).is_none());
+        assert!(world.animals.is_empty());
+        assert_eq!(world.spare_animals.len(), 1);
+
+        place(&mut world, (2, 2), AnimaType::Herbivore, AnimalDirection::North, MAX_ANIMAL_ENERGY);
+        let parent = world.get_cell_info(2, 2).unwrap().animal.unwrap();
+        world.perform_animal_action(2, 2, AnimalAction::Reproduce).unwrap();
+
+        let (child_x, child_y, child) = world.animals_iter()
+            .find(|(_, _, animal)| animal.id != parent.id)
+            .expect("Потомок не родился");
+        let child_handle = world.find_animal(child.id).unwrap();
+
+        // Родитель занял освободившийся слот, потомок - память умершего животного,
+        // но старый дескриптор по-прежнему ничего не находит.
+        assert!(world.spare_animals.is_empty());
+        assert_ne!(world.find_animal(parent.id).unwrap(), victim);
+        assert_ne!(child_handle, victim);
+        assert!(world.animals.get(victim).is_none());
+        assert!(world.find_animal(victim_id).is_none());
+        assert_ne!(child.id, victim_id);
+        assert_eq!(world.animals.get(child_handle).unwrap().get_id(), child.id);
+        assert_eq!(world.get_cell_info(child_x, child_y).unwrap().animal.unwrap().id, child.id);
+        assert_eq!(world.animals.len(), 2);
+    }
 
-                            animal.eat_action(plant.be_eaten());
-                        }
-                    }
-                    None => {
-                        // Есть нечего: животное ошиблось.
+    #[test]
+    fn recycled_slots_under_heavy_churn_never_alias_live_animals() {
+        let mut world = testing::world(20, 20);
+        let rounds = 50;
+        let mut dead = vec![];
+        let mut births = 0;
+
+        let allocations = testing::allocations();
+        let started = std::time::Instant::now();
+        for _ in 0..rounds {
+            // Заполняем мир голодающими родителями, половина из них рожает, затем все
+            // родители умирают, а потомки занимают их память в следующих поколениях.
+            let mut parents = vec![];
+            for x in (0..20).step_by(2) {
+                for y in (0..20).step_by(2) {
+                    if world.get_cell_info(x, y).unwrap().animal.is_none() {
+                        parents.push((x, y, place_starving(&mut world, (x, y), MAX_ANIMAL_ENERGY)));
                     }
                 }
+            }
+            for &(x, y, _) in parents.iter().step_by(2) {
+                let before = world.animals.len();
+                world.perform_animal_action(x, y, AnimalAction::Reproduce).unwrap();
+                births += world.animals.len() - before;
+            }
+            for &(_, _, handle) in &parents {
+                let parent = world.animals.get_mut(handle).unwrap();
+                parent.set_energy(ANIMAL_LIVE_ENERGY / 100.0);
+                dead.push((parent.get_id(), handle));
+            }
+            world.tick();
 
+            // Ни один дескриптор умершего животного не находит живое животное.
+            let live: Vec<u64> = world.animals_iter().map(|(_, _, animal)| animal.id).collect();
+            for &(id, handle) in &dead {
+                assert!(world.animals.get(handle).is_none(), "животное {}", id);
+                assert!(!live.contains(&id), "животное {}", id);
             }
-            // Хищник поедает травоядное
-            AnimaType::Carnivore => {
-                let coord = match animal.get_direction() {
-                    AnimalDirection::North => {
-                        self.choose_animal(AnimaType::Herbivore, x, y, &NORTH_PROXIMITY)
-                    }
-                    AnimalDirection::South => {
-                        self.choose_animal(AnimaType::Herbivore, x, y, &SOUTH_PROXIMITY)
-                    }
-                    AnimalDirection::West => {
-                        self.choose_animal(AnimaType::Herbivore, x, y, &WEST_PROXIMITY)
-                    }
-                    AnimalDirection::East => {
-                        self.choose_animal(AnimaType::Herbivore, x, y, &EAST_PROXIMITY)
-                    }
-                };
+            assert_eq!(world.animals.len(), live.len());
+        }
+        let elapsed = started.elapsed();
 
-                match coord {
-                    Some(coord) => {
-                        // Получить растение по координатам
-                        if let AnimalInCell::Animal(herb) = self.landscape[coord.0][coord.1].animal {
-                            let herb = Self::get_agent_mut(herb);
+        assert!(births > 0);
+        eprintln!(
+            "рождения и смерти: {} рождений за {:?}, {} выделений памяти",
+            births, elapsed, testing::allocations() - allocations
+        );
+    }
 
-                            if herb.get_type() == AnimaType::Carnivore {
-                                panic!("Хищник хочет съесть хищника!");
-                            }
+    #[test]
+    fn postmortem_records_eaten_and_starved_animals() {
+        let mut world = testing::world(9, 9);
+        world.set_postmortem_history(10);
+        place(&mut world, (4, 4), AnimaType::Carnivore, AnimalDirection::North, 30.0);
+        place(&mut world, (4, 3), AnimaType::Herbivore, AnimalDirection::North, 30.0);
+        place(&mut world, (0, 8), AnimaType::Herbivore, AnimalDirection::North, ANIMAL_LIVE_ENERGY / 100.0);
+        // Голодающее травоядное упирается в скалу и умирает в своей ячейке.
+        world.set_blocked(0, 7).unwrap();
+        let id_at = |world: &Landscape, (x, y): (usize, usize)| world.get_cell_info(x, y).unwrap().animal.unwrap().id;
+        let (prey, starving) = (id_at(&world, (4, 3)), id_at(&world, (0, 8)));
+
+        world.perform_animal_action(4, 4, AnimalAction::Eat).unwrap();
+        world.tick();
+
+        let deaths = world.get_postmortems();
+        let record = |id: u64| *deaths.iter().find(|death| death.id == id).unwrap();
+        let (eaten, starved) = (record(prey), record(starving));
+
+        assert!(eaten.species == AnimaType::Herbivore);
+        assert!(eaten.cause == DeathCause::Eaten);
+        assert_eq!((eaten.at, eaten.tick, eaten.generation), ((4, 3), 1, 0));
+        assert_eq!((eaten.kills, eaten.meals, eaten.offspring), (0, 0, 0));
+
+        assert!(starved.species == AnimaType::Herbivore);
+        assert!(starved.cause == DeathCause::Starved);
+        assert_eq!((starved.at, starved.tick, starved.generation), ((0, 8), 1, 0));
+        assert_eq!((starved.kills, starved.meals, starved.offspring), (0, 0, 0));
+
+        // Мозги обоих травоядных созданы одним генератором: геномы совпадают.
+        assert!(eaten.genome_hash.is_some());
+        assert_eq!(eaten.genome_hash, starved.genome_hash);
+    }
 
-                            animal.eat_action(herb.be_eaten());
-                        }
-                    }
-                    None => {
-                        // Есть нечего: животное ошиблось.
-                    }
-                }
-            }
+    #[test]
+    fn shuffled_area_puts_every_offset_first_equally_often() {
+        let mut rng = CheckpointRng::seed_from_u64(testing::TEST_SEED);
+        let rounds = 50_000;
+        let mut first = [0usize; 5];
+
+        for _ in 0..rounds {
+            let (shuffled, count) = shuffle_area(&NORTH_FRONT, &mut rng);
+            assert_eq!(count, NORTH_FRONT.len());
+
+            let mut sorted = shuffled[..count].to_vec();
+            sorted.sort();
+            assert_eq!(sorted, NORTH_FRONT.to_vec());
+
+            first[NORTH_FRONT.iter().position(|&offset| offset == shuffled[0]).unwrap()] += 1;
+        }
+
+        // Ожидается rounds / 5 = 10000 раз на смещение, стандартное отклонение около 90.
+        let expected = rounds as f64 / NORTH_FRONT.len() as f64;
+        for (index, count) in first.into_iter().enumerate() {
+            assert!((count as f64 - expected).abs() < 0.05 * expected, "смещение {}: {} раз", index, count);
         }
     }
 
-    /// Метод находит растение в области, точки которой переданы срезом.
-    ///
-    /// # Arguments
-    ///
-    /// * `x`, `y`: Координаты относительно которой берутся смещения из области.
-    /// * `area`: Область смещения.
-    ///
-    /// returns: Option<(usize, usize)>
-    fn choose_plant(&self, x: usize, y: usize, area: &[(i8, i8)]) -> Option<(usize, usize)> {
-        // Отсортируем срез случайным образом, что бы получить случайное растение,
-        // если их несколько в ближайшей области.
-        let area = randomize_coord_vector(Vec::from(area));
+    /// Раскладка для проверки выбора еды: животное в ячейке (4, 4) смотрит на север,
+    /// в области близости лежат агенты с заданной энергией. Энергия 8 встречается
+    /// дважды: в ячейках (3, 3) и (4, 3), первая раньше в порядке смещений области.
+    const TARGETING_LAYOUT: [((usize, usize), Energy); 5] = [
+        ((3, 4), 2.0), ((3, 3), 8.0), ((4, 3), 8.0), ((5, 3), 1.0), ((5, 4), 5.0),
+    ];
+
+    #[test]
+    fn plant_targeting_selects_known_cell() {
+        let mut world = testing::world(9, 9);
+        for ((x, y), energy) in TARGETING_LAYOUT {
+            world.add_plant(x, y, Plant::new(PlantParams { energy, ..testing::plant_params() }).unwrap()).unwrap();
+        }
 
-        for offset in area {
-            let x_off = Self::clip(x as isize + offset.0 as isize, self.width);
-            let y_off = Self::clip(y as isize + offset.1 as isize, self.height);
+        assert_eq!(world.choose_plant(4, 4, &NORTH_PROXIMITY, EatTargeting::RichestFirst), Some((3, 3)));
+        assert_eq!(world.choose_plant(4, 4, &NORTH_PROXIMITY, EatTargeting::WeakestFirst), Some((5, 3)));
 
-            if let PlantInCell::Plant(_) = self.landscape[x_off][y_off].plant {
-                return Some((x_off, y_off));
-            }
+        // Случайный выбор со временем находит каждое растение области.
+        let mut chosen = BTreeSet::new();
+        for _ in 0..200 {
+            chosen.insert(world.choose_plant(4, 4, &NORTH_PROXIMITY, EatTargeting::Random).unwrap());
         }
-
-        None
+        assert_eq!(chosen, TARGETING_LAYOUT.iter().map(|(at, _)| *at).collect());
     }
 
-    /// Метод находит животное в области, точки которой переданы срезом.
-    ///
-    /// # Arguments
-    ///
-    /// * `animal_type`: Тип животного которое мы ищем.
-    /// * `x`, `y`: Координаты относительно которой берутся смещения из области.
-    /// * `area`: Область смещения.
-    ///
-    /// returns: Option<(usize, usize)>
-    fn choose_animal(
-        &self,
-        animal_type: AnimaType,
-        x: usize,
-        y: usize,
-        area: &[(i8, i8)]
-    ) -> Option<(usize, usize)> {
-        // Отсортируем срез случайным образом, что бы получить случайное животное,
-        // если их несколько в ближайшей области.
-        let area = randomize_coord_vector(Vec::from(area));
+    #[test]
+    fn prey_targeting_selects_known_cell() {
+        let mut world = testing::world(9, 9);
+        place(&mut world, (4, 4), AnimaType::Carnivore, AnimalDirection::North, 30.0);
+        for ((x, y), energy) in TARGETING_LAYOUT {
+            place(&mut world, (x, y), AnimaType::Herbivore, AnimalDirection::North, energy);
+        }
 
-        for offset in area {
-            let x_off = Self::clip(x as isize + offset.0 as isize, self.width);
-            let y_off = Self::clip(y as isize + offset.1 as isize, self.height);
-
-            // В точке есть животное
-            if let AnimalInCell::Animal(animal) = self.landscape[x_off][y_off].animal {
-                // Проверим тип животного
-                let animal = Self::get_agent_ref(animal);
-                if animal.get_type() == animal_type {
-                    return Some((x_off, y_off));
-                }
-            }
+        let choose = |world: &mut Landscape, targeting| {
+            world.choose_animal(&CARNIVORE_PREY, 4, 4, &NORTH_PROXIMITY, targeting)
+        };
+        assert_eq!(choose(&mut world, EatTargeting::WeakestFirst), Some((5, 3)));
+        assert_eq!(choose(&mut world, EatTargeting::RichestFirst), Some((3, 3)));
+
+        let mut chosen = BTreeSet::new();
+        for _ in 0..200 {
+            chosen.insert(choose(&mut world, EatTargeting::Random).unwrap());
         }
+        assert_eq!(chosen, TARGETING_LAYOUT.iter().map(|(at, _)| *at).collect());
+    }
 
-        None
+    #[test]
+    fn choosing_food_does_not_allocate() {
+        let mut world = testing::world(9, 9);
+        place(&mut world, (4, 4), AnimaType::Carnivore, AnimalDirection::North, 30.0);
+        place(&mut world, (5, 3), AnimaType::Herbivore, AnimalDirection::North, 30.0);
+        world.add_plant(3, 4, testing::plant()).unwrap();
+        world.add_plant(4, 3, testing::plant()).unwrap();
+
+        let allocations = testing::allocations();
+        for _ in 0..1_000 {
+            std::hint::black_box(world.choose_plant(4, 4, &NORTH_PROXIMITY, EatTargeting::Random));
+            std::hint::black_box(world.choose_animal(
+                &CARNIVORE_PREY, 4, 4, &NORTH_PROXIMITY, EatTargeting::Random
+            ));
+        }
+
+        assert_eq!(testing::allocations(), allocations);
     }
 
-    /// Метод реализует размножение животного.
-    ///
-    /// An animal has reached the energy level needed for reproduction. An animal
-    /// is only permitted to reproduce if space is available for the new animal.
-    /// The child animal is a copy of the parent, except that one of the weights
-    /// of the neural network of his brain is mutated.
-    ///
-    /// # Arguments
-    ///
-    /// * `animal`: Изменяемая ссылка на животное.
-    ///
-    /// returns: ()
-    fn reproduce_animal_action(&mut self, animal: &mut dyn AnimalAlive) {
-        let agent_type = if animal.get_type() == AnimaType::Herbivore {
-            AgentType::Herbivore
-        } else {
-            AgentType::Carnivore
-        };
+    /// Мир, в котором хищники едят падаль, а травоядное в ячейке (4, 3) умерло от
+    /// голода на первой итерации и оставило падаль (впереди травоядного скала, так что
+    /// оно умирает в своей ячейке).
+    fn world_with_fresh_carcass() -> Landscape {
+        let mut world = testing::world(9, 9);
+        world.set_scavenging((false, true, false), CARRION_ENERGY_RATE, CARRION_BITE_ENERGY);
+        world.set_blocked(4, 2).unwrap();
+        place(&mut world, (4, 3), AnimaType::Herbivore, AnimalDirection::North, ANIMAL_LIVE_ENERGY / 100.0);
 
-        let spot = self.find_empty_spot(agent_type);
+        world.tick();
+        assert!(world.get_cell_info(4, 3).unwrap().animal.is_none());
 
-        match spot {
-            // Нашлось место для размножения.
-            Ok(coord) => {
-                let child = animal.reproduce_action();
-                let generation = child.get_generation();
+        world
+    }
 
-                self.add_animal(coord.0, coord.1, child)
-                    .expect("Внутренняя ошибка программы: найденное место для животного уже занято");
+    #[test]
+    fn carnivore_next_to_fresh_carcass_gains_energy() {
+        let mut world = world_with_fresh_carcass();
+        let carcass = (ANIMAL_EATEN_ENERGY_RATE * ANIMAL_BIRTH_ENERGY as f64) as Energy;
+        assert_eq!(world.get_cell_info(4, 3).unwrap().carrion, carcass);
+
+        place(&mut world, (4, 4), AnimaType::Carnivore, AnimalDirection::North, 30.0);
+        world.perform_animal_action(4, 4, AnimalAction::Eat).unwrap();
+
+        let bite = carcass.min(CARRION_BITE_ENERGY);
+        let gained = (CARRION_ENERGY_RATE * bite as f64) as Energy;
+        let cost = (ANIMAL_LIVE_ENERGY as f64) as Energy;
+        let animal = world.get_cell_info(4, 4).unwrap().animal.unwrap();
+        assert!(gained > cost);
+        assert!((animal.energy - (30.0 - cost + gained)).abs() < 1e-4, "энергия {}", animal.energy);
+        assert_eq!(world.get_cell_info(4, 3).unwrap().carrion, carcass - bite);
+        assert_eq!(world.get_world_stats().carrion_energy.1, gained);
+    }
 
-                match animal.get_type() {
-                    AnimaType::Herbivore => {
-                        self.animal_reproductions.0 += 1;
-                        if self.animal_max_generation.0 < generation {
-                            self.animal_max_generation.0 = generation;
-                        }
-                    }
-                    AnimaType::Carnivore => {
-                        self.animal_reproductions.1 += 1;
-                        if self.animal_max_generation.1 < generation {
-                            self.animal_max_generation.1 = generation;
-                        }
-                    }
-                }
-            }
-            // Если нет возможности размножится, ничего не делаем.
-            Err(_) => {
-                return;
+    #[test]
+    fn herbivore_eats_carnivore_carcass_only_when_scavenging() {
+        for herbivores_scavenge in [true, false] {
+            // Падаль едят хищники, а травоядные - только с включенным флагом.
+            let mut world = testing::world(9, 9);
+            world.set_scavenging((herbivores_scavenge, true, false), CARRION_ENERGY_RATE, CARRION_BITE_ENERGY);
+            world.set_blocked(4, 2).unwrap();
+            place(&mut world, (4, 3), AnimaType::Carnivore, AnimalDirection::North, ANIMAL_LIVE_ENERGY / 100.0);
+            world.tick();
+            let carcass = world.get_cell_info(4, 3).unwrap().carrion;
+            assert!(carcass > 0.0);
+
+            place(&mut world, (4, 4), AnimaType::Herbivore, AnimalDirection::North, 30.0);
+            world.perform_animal_action(4, 4, AnimalAction::Eat).unwrap();
+
+            let animal = world.get_cell_info(4, 4).unwrap().animal.unwrap();
+            let stats = world.get_world_stats();
+            if herbivores_scavenge {
+                let cost = (ANIMAL_LIVE_ENERGY as f64) as Energy;
+                let bite = carcass.min(CARRION_BITE_ENERGY);
+                let gained = (CARRION_ENERGY_RATE * bite as f64) as Energy;
+                assert!((animal.energy - (30.0 - cost + gained)).abs() < 1e-4, "энергия {}", animal.energy);
+                assert_eq!(world.get_cell_info(4, 3).unwrap().carrion, carcass - bite);
+                assert_eq!(stats.carrion_energy.0, gained);
+            } else {
+                // Неудачная попытка поесть энергии не стоит.
+                assert_eq!(animal.energy, 30.0);
+                assert_eq!(world.get_cell_info(4, 3).unwrap().carrion, carcass);
+                assert_eq!(stats.carrion_energy.0, 0.0);
             }
+            assert_eq!(stats.carrion_energy.1, 0.0);
         }
     }
 
-    /// Действие - нет действия.
-    fn inactivity_animal_action(&mut self, animal: &mut dyn AnimalAlive) {
-        animal.inactivity_action();
-    }
+    #[test]
+    fn carcass_vanishes_after_carrion_lifetime() {
+        let lifetime = 3;
+        let mut world = world_with_fresh_carcass();
+        world.set_carrion_lifetime(lifetime);
 
-    /// Завершающая обработка.
-    /// Удаляем мертвых животных из среды обитания, обновляем статистику,
-    /// определяем элементы для отображения, очищаем состояние животных.
-    fn final_processing(&mut self) {
-        // Очистим текущее состояние ячейки.
-        self.view_state.clear();
+        // Падаль появилась на первой итерации и лежит `lifetime` итераций, включая ее.
+        for _ in 1..lifetime {
+            world.tick();
+            assert!(world.get_cell_info(4, 3).unwrap().carrion > 0.0);
+        }
 
-        for x in 0..self.width {
-            for y in 0..self.height {
-                let mut tmp_view: Vec<CellStuff> = Vec::with_capacity(CellStuff::None as usize);
+        world.tick();
+        assert_eq!(world.get_cell_info(4, 3).unwrap().carrion, 0.0);
+    }
 
-                // Если в точке есть растение
-                if let PlantInCell::Plant(_) = self.landscape[x][y].plant {
-                    tmp_view.push(CellStuff::Plant);
-                }
+    #[test]
+    fn plant_dies_at_max_age_and_frees_its_cell() {
+        let max_age = 3;
+        let mut world = testing::world(5, 5);
+        world.set_plant_max_age(max_age);
+        world.add_plant(2, 2, testing::plant()).unwrap();
+        let mut history = Vec::new();
+
+        // Растение живет до наибольшего возраста и умирает на итерации, когда его достигло.
+        for tick in 1..=max_age {
+            world.tick();
+            history.push(world.get_world_stats());
+
+            let alive = tick < max_age;
+            assert_eq!(world.get_cell_info(2, 2).unwrap().plant.is_some(), alive, "итерация {}", tick);
+            assert_eq!(world.get_world_stats().plant_deaths, !alive as usize, "итерация {}", tick);
+        }
+        assert_eq!(world.get_world_stats().plant_count, 0);
+        assert_eq!(world.plants.len(), 0);
+
+        // Смерти растений попадают в итоги прогона и в файл статистики.
+        assert_eq!(summary::analyze(&history).plant_deaths, 1);
+        let header = analysis::csv_header(false);
+        let column = header.trim_end().split(',').position(|name| name == "plant_deaths").unwrap();
+        let last = analysis::csv_row(history.last().unwrap(), false);
+        assert_eq!(last.trim_end().split(',').nth(column), Some("1"));
+
+        // Освободившаяся ячейка принимает новое растение, которое живет полный срок.
+        world.add_plant(2, 2, testing::plant()).unwrap();
+        assert_eq!(world.get_world_stats().plant_count, 1);
+        for _ in 1..max_age {
+            world.tick();
+            assert!(world.get_cell_info(2, 2).unwrap().plant.is_some());
+        }
+        world.tick();
+        assert!(world.get_cell_info(2, 2).unwrap().plant.is_none());
+    }
 
-                // Если в точке есть животное.
-                if let AnimalInCell::Animal(ptr) = self.landscape[x][y].animal {
-                    let animal = Self::get_agent_mut(ptr);
+    #[test]
+    fn animal_at_full_energy_dies_after_max_age() {
+        let mut world = testing::world(9, 9);
+        let params = AnimalParams { max_age: 10, no_repro: true, ..testing::animal_params() };
+        let mut animal = testing::animal_facing(AnimaType::Herbivore, params, AnimalDirection::North);
+        animal.set_energy(MAX_ANIMAL_ENERGY);
+        world.add_animal(4, 4, animal).unwrap();
+        let id = world.get_cell_info(4, 4).unwrap().animal.unwrap().id;
+        let alive = |world: &Landscape| world.animals_iter().any(|(_, _, animal)| animal.id == id);
+
+        // Животное проживает все десять итераций и удаляется на одиннадцатой.
+        for tick in 1..=10 {
+            world.tick();
+            assert!(alive(&world), "итерация {}", tick);
+        }
 
-                    // Мир жестокое место, и если животное не справилось его место в раю.
-                    // If energy falls to or below zero, the animal dies. Otherwise, we
-                    // check to see if the agent has lived longer than any other agent
-                    // of the particular type.
-                    if animal.is_dead() {
-                        // Отправляем животное в рай.
-                        self.send_to_heaven(ptr, x, y);
+        world.tick();
+        assert!(!alive(&world));
+        assert_eq!(world.get_statistics().old_age_deaths.0, 1);
+    }
 
-                        if animal.is_eaten() {
-                            tmp_view.push(CellStuff::KilledAnimal);
-                        } else {
-                            tmp_view.push(CellStuff::DeadAnimal);
-                        }
-                    } else {
-                        // Очищаем состояние животного.
-                        animal.clear();
-                        // Обновляем статистику.
-                        self.update_best_animal(ptr);
+    #[test]
+    fn lone_animal_perceives_nothing() {
+        for direction in DIRECTIONS {
+            for radius in 0..=3 {
+                let mut world = testing::world(11, 11);
+                let params = AnimalParams { vision_radius: radius, ..testing::animal_params() };
+                world.add_animal(5, 5, testing::animal_facing(AnimaType::Herbivore, params, direction)).unwrap();
+
+                let inputs = percept_at(&mut world, 5, 5);
+
+                // Собственная энергия - единственный ненулевой вход одинокого животного.
+                let counts = [
+                    inputs.plant_front, inputs.plant_left, inputs.plant_right, inputs.plant_proximity,
+                    inputs.herbivore_front, inputs.herbivore_left, inputs.herbivore_right, inputs.herbivore_proximity,
+                    inputs.carnivore_front, inputs.carnivore_left, inputs.carnivore_right, inputs.carnivore_proximity,
+                    inputs.repro_blocked, inputs.wall_front,
+                ];
+                let context = [inputs.season_phase, inputs.plant_density, inputs.age_fraction];
+                assert!(counts.iter().all(|&count| count == 0), "радиус {}: {:?}", radius, counts);
+                assert!(context.iter().all(|&value| value == 0 as Energy), "радиус {}: {:?}", radius, context);
+                assert!(inputs.own_energy > 0 as Energy);
+            }
+        }
+    }
 
-                        let stuff = match animal.get_type() {
-                            AnimaType::Herbivore => match animal.get_direction() {
-                                AnimalDirection::North => CellStuff::HerbBack,
-                                AnimalDirection::South => CellStuff::HerbFront,
-                                AnimalDirection::West => CellStuff::HerbLeft,
-                                AnimalDirection::East => CellStuff::HerbRight,
-                            },
-                            AnimaType::Carnivore => match animal.get_direction() {
-                                AnimalDirection::North => CellStuff::CarnBack,
-                                AnimalDirection::South => CellStuff::CarnFront,
-                                AnimalDirection::West => CellStuff::CarnLeft,
-                                AnimalDirection::East => CellStuff::CarnRight,
-                            },
-                        };
+    #[test]
+    fn iterators_sum_the_same_energy_as_cells() {
+        let mut world = testing::populated_world(12, 12, (40, 10, 4));
+        for _ in 0..5 {
+            world.tick();
+        }
 
-                        tmp_view.push(stuff);
-                    }
+        let (mut plants, mut animals) = ((0, 0.0), (0, 0.0));
+        for x in 0..12 {
+            for y in 0..12 {
+                let cell = world.get_cell_info(x, y).unwrap();
+                if let Some(plant) = cell.plant {
+                    plants = (plants.0 + 1, plants.1 + plant.energy as f64);
                 }
-
-                // После сбора того, что могло произойти в ячейке
-                // следует упорядочить события по важности.
-                tmp_view.sort();
-                // Добавляем состояние ячейки в массив отображения.
-                match tmp_view.first() {
-                    Some(stuff) => {
-                        self.view_state.push((x, y, *stuff));
-                    }
-                    _ => {}
+                if let Some(animal) = cell.animal {
+                    animals = (animals.0 + 1, animals.1 + animal.energy as f64);
                 }
             }
         }
+
+        let plant_energy: f64 = world.plants_iter().map(|(_, _, plant)| plant.energy as f64).sum();
+        let animal_energy: f64 = world.animals_iter().map(|(_, _, animal)| animal.energy as f64).sum();
+        assert!(plants.0 > 0 && animals.0 > 0);
+        assert_eq!(world.plants_iter().count(), plants.0);
+        assert_eq!(world.animals_iter().count(), animals.0);
+        assert!((plant_energy - plants.1).abs() < 1e-6, "{} != {}", plant_energy, plants.1);
+        assert!((animal_energy - animals.1).abs() < 1e-6, "{} != {}", animal_energy, animals.1);
     }
 
-    /// Метод "очищает" мир от умершего животного.
-    ///
-    /// # Arguments
-    ///
-    /// * `animal_ptr`: Изменяемый *указатель* на умершее животное.
-    /// * `x`, `y`: Координаты умершего животного.
-    ///
-    /// returns: ()
-    fn send_to_heaven(&mut self, animal_ptr: *mut dyn AnimalAlive, x: usize, y: usize) {
-        // Death came to this animal (or it was eaten)...
-        // Удаляем животное из ячейки.
-        self.landscape[x][y].animal = AnimalInCell::None;
-        // Помещаем указатель на животное в "рай". Указатель копируемый тип.
-        self.dead_animals.push(animal_ptr);
+    /// Правило, по которому животные первого поколения размножаются на итерации
+    /// `tick` и бездействуют на остальных.
+    struct ReproduceOn {
+        tick: usize,
+    }
 
-        // Получим изменяемую ссылку на агента.
-        let animal = Self::get_agent_mut(animal_ptr);
+    impl ActionPolicy for ReproduceOn {
+        fn name(&self) -> &'static str {
+            "reproduce_on"
+        }
 
-        match animal.get_type() {
-            AnimaType::Herbivore => {
-                self.animal_count.0 -= 1;
-                self.animal_deaths.0 += 1;
+        fn review(&self, animal: &dyn AnimalAlive, _: &AnimalInputSignal, _: AnimalAction, ctx: &PolicyCtx) -> AnimalAction {
+            if animal.get_generation() == 0 && ctx.tick == self.tick {
+                AnimalAction::Reproduce
+            } else {
+                AnimalAction::None
+            }
+        }
+    }
 
-                match self.best_death_animal.0 {
-                    AnimalInCell::Animal(best_death_animal_ptr) => {
-                        // Т.к. в этой ячейке точно не может быть текущего агента,
-                        // текущий только что умер... Получим ссылку на лучшего агента.
-                        let best_death_animal =  Self::get_agent_ref(best_death_animal_ptr);
+    /// Правило, которое заменяет действие `from` действием `to`.
+    struct Replace {
+        from: AnimalAction,
+        to: AnimalAction,
+    }
 
-                        // Только что умерший агент жил дольше всех.
-                        if animal.get_age() > best_death_animal.get_age() {
-                            self.best_death_animal.0 = AnimalInCell::Animal(animal_ptr);
-                        }
-                    }
-                    _ => {}
-                }
+    impl ActionPolicy for Replace {
+        fn name(&self) -> &'static str {
+            "replace"
+        }
+
+        fn review(&self, _: &dyn AnimalAlive, _: &AnimalInputSignal, action: AnimalAction, _: &PolicyCtx) -> AnimalAction {
+            if action == self.from {
+                self.to
+            } else {
+                action
             }
-            AnimaType::Carnivore => {
-                self.animal_count.1 -= 1;
-                self.animal_deaths.1 += 1;
+        }
+    }
+
+    /// Мир 5x5 с травоядным в центре и цепочкой правил.
+    fn world_with_policies(policies: Vec<Box<dyn ActionPolicy>>) -> Landscape {
+        let mut world = testing::world(5, 5);
+        for policy in policies {
+            world.add_action_policy(policy).unwrap();
+        }
+        place(&mut world, (2, 2), AnimaType::Herbivore, AnimalDirection::North, MAX_ANIMAL_ENERGY);
+        world
+    }
 
-                match self.best_death_animal.1 {
-                    AnimalInCell::Animal(best_death_animal_ptr) => {
-                        // Т.к. в этой ячейке точно не может быть текущего агента,
-                        // текущий только что умер... Получим ссылку на лучшего агента.
-                        let best_death_animal =  Self::get_agent_ref(best_death_animal_ptr);
+    #[test]
+    fn action_policies_apply_in_order() {
+        let move_to_turn = || Box::new(Replace { from: AnimalAction::Move, to: AnimalAction::TurnLeft });
+        let turn_to_reproduce = || Box::new(Replace { from: AnimalAction::TurnLeft, to: AnimalAction::Reproduce });
+
+        // Второе правило получает действие, замененное первым.
+        let mut world = world_with_policies(vec![move_to_turn(), turn_to_reproduce()]);
+        world.perform_animal_action(2, 2, AnimalAction::Move).unwrap();
+        let stats = world.get_world_stats();
+        assert_eq!(stats.animal_count.0, 2);
+        assert_eq!(stats.policy_vetoes[..2], [1, 1]);
+
+        // В обратном порядке второе правило уже не видит действия, которое заменяет.
+        let mut world = world_with_policies(vec![turn_to_reproduce(), move_to_turn()]);
+        world.perform_animal_action(2, 2, AnimalAction::Move).unwrap();
+        let stats = world.get_world_stats();
+        assert_eq!(stats.animal_count.0, 1);
+        assert_eq!(stats.policy_vetoes[..2], [0, 1]);
+        assert!(world.animal_at(2, 2).is_some_and(|animal| animal.get_direction() == AnimalDirection::West));
+    }
 
-                        // Только что умерший агент жил дольше всех.
-                        if animal.get_age() > best_death_animal.get_age() {
-                            self.best_death_animal.1 = AnimalInCell::Animal(animal_ptr);
-                        }
-                    }
-                    _ => {}
-                }
-            }
+    #[test]
+    fn later_policy_overrides_conflicting_earlier_one() {
+        let force_reproduce = || Box::new(Replace { from: AnimalAction::None, to: AnimalAction::Reproduce });
+        let need_plants = || Box::new(ReproductionFoodPolicy::new(1, 0));
+
+        // Размножение, навязанное первым правилом, запрещает второе: поблизости нет растений.
+        let mut world = world_with_policies(vec![force_reproduce(), need_plants()]);
+        world.perform_animal_action(2, 2, AnimalAction::None).unwrap();
+        let stats = world.get_world_stats();
+        assert_eq!(stats.animal_count.0, 1);
+        assert_eq!(stats.policy_vetoes[..2], [1, 1]);
+
+        // Правило еды проверяет бездействие и пропускает его, размножение навязывается после.
+        let mut world = world_with_policies(vec![need_plants(), force_reproduce()]);
+        world.perform_animal_action(2, 2, AnimalAction::None).unwrap();
+        let stats = world.get_world_stats();
+        assert_eq!(stats.animal_count.0, 2);
+        assert_eq!(stats.policy_vetoes[..2], [0, 1]);
+    }
+
+    #[test]
+    fn newborn_ages_from_the_tick_after_birth() {
+        let birth_tick = 2;
+        let mut world = testing::world(5, 5);
+        world.add_action_policy(Box::new(ReproduceOn { tick: birth_tick })).unwrap();
+        place(&mut world, (2, 2), AnimaType::Herbivore, AnimalDirection::North, MAX_ANIMAL_ENERGY);
+        let child_age = |world: &Landscape| world.animals_iter()
+            .find(|(_, _, animal)| animal.generation == 1)
+            .map(|(_, _, animal)| animal.age);
+
+        world.tick();
+        assert_eq!(child_age(&world), None);
+
+        // Потомок, родившийся на итерации T, после нее имеет возраст 0, после T + 1 - 1.
+        world.tick();
+        assert_eq!(world.get_world_stats().iteration, birth_tick);
+        assert_eq!(child_age(&world), Some(0));
+
+        world.tick();
+        assert_eq!(child_age(&world), Some(1));
+    }
+
+    #[test]
+    fn growth_map_sets_plant_growth_per_cell() {
+        let mut world = testing::world(3, 2);
+        world.set_growth_fn(|x, y| (x + 10 * y) as Energy / 100.0).unwrap();
+        assert!(world.growth_map == vec![vec![0.0, 0.1], vec![0.01, 0.11], vec![0.02, 0.12]]);
+
+        // Растение в ячейке получает энергию роста этой ячейки.
+        for (x, y) in [(0, 0), (2, 1)] {
+            let mut plant = testing::plant();
+            plant.set_energy(0 as Energy);
+            world.grow_plant_action(plant.as_mut(), x, y);
+            assert!((plant.get_energy() - world.growth_map[x][y]).abs() < 1e-6, "({}, {})", x, y);
         }
+
+        // Карта другого размера и отрицательная или нечисловая энергия отклоняются,
+        // прежняя карта остается.
+        assert!(world.set_growth_map(vec![vec![0.1; 2]; 2]).is_err());
+        assert!(world.set_growth_map(vec![vec![0.1; 3]; 3]).is_err());
+        assert!(world.set_growth_fn(|x, _| if x == 1 { -0.1 } else { 0.1 }).is_err());
+        assert!(world.set_growth_fn(|_, y| if y == 1 { Energy::NAN } else { 0.1 }).is_err());
+        assert!((world.growth_map[2][1] - 0.12).abs() < 1e-6);
     }
 
-    /// Обновляет информацию о лучшем животном (живущем дольше всех).
-    fn update_best_animal(&mut self, animal_ptr: *mut dyn AnimalAlive) {
-        let animal =  Self::get_agent_ref(animal_ptr);
-        match animal.get_type() {
-            AnimaType::Herbivore => {
-                // Получим текущее лучшее животное
-                if let AnimalInCell::Animal(ptr) = self.best_animal.0 {
-                    let best_animal = Self::get_agent_ref(ptr);
+    #[test]
+    fn season_scales_plant_growth() {
+        let mut world = testing::world(3, 3);
+        assert_eq!(world.season_factor(), 1.0);
 
-                    if animal.get_age() > best_animal.get_age() {
-                        self.best_animal.0 = AnimalInCell::Animal(animal_ptr);
-                    }
-                }
-            }
-            AnimaType::Carnivore => {
-                // Получим текущее лучшее животное
-                if let AnimalInCell::Animal(ptr) = self.best_animal.1 {
-                    let best_animal = Self::get_agent_ref(ptr);
+        world.set_season(Some((4, 1.0)));
+        let factors: Vec<f64> = (0..4).map(|iteration| {
+            world.iteration = iteration;
+            world.season_factor()
+        }).collect();
 
-                    if animal.get_age() > best_animal.get_age() {
-                        self.best_animal.1 = AnimalInCell::Animal(animal_ptr);
-                    }
-                }
+        for (factor, expected) in factors.into_iter().zip([1.0, 2.0, 1.0, 0.0]) {
+            assert!((factor - expected).abs() < 1e-9, "{} != {}", factor, expected);
+        }
+    }
+
+    #[test]
+    fn context_inputs_stay_in_unit_range() {
+        let (season, max_age) = (7, 5);
+        let mut world = testing::world(9, 9);
+        world.set_season(Some((season, 0.5)));
+        world.set_brain_context(Some(max_age));
+        place(&mut world, (4, 4), AnimaType::Herbivore, AnimalDirection::North, MAX_ANIMAL_ENERGY);
+        world.add_plant(0, 0, testing::plant()).unwrap();
+
+        for tick in 0..20 {
+            let (x, y, animal) = world.animals_iter().next().unwrap();
+            let inputs = percept_at(&mut world, x, y);
+
+            let phase = (tick % season) as Energy / season as Energy;
+            let age = (animal.age as Energy / max_age as Energy).min(1.0);
+            assert!((inputs.season_phase - phase).abs() < 1e-6, "итерация {}", tick);
+            assert!((inputs.age_fraction - age).abs() < 1e-6, "итерация {}", tick);
+            for value in [inputs.season_phase, inputs.plant_density, inputs.age_fraction] {
+                assert!((0.0..=1.0).contains(&value), "итерация {}: {}", tick, value);
             }
+
+            world.tick();
+        }
+    }
+
+    #[test]
+    fn context_inputs_are_zero_without_context() {
+        let mut world = testing::world(9, 9);
+        world.set_season(Some((7, 0.5)));
+        place(&mut world, (4, 4), AnimaType::Herbivore, AnimalDirection::North, MAX_ANIMAL_ENERGY);
+        world.add_plant(0, 0, testing::plant()).unwrap();
+
+        for tick in 0..10 {
+            let (x, y, _) = world.animals_iter().next().unwrap();
+            let inputs = percept_at(&mut world, x, y);
+
+            let context = [inputs.season_phase, inputs.plant_density, inputs.age_fraction];
+            assert_eq!(context, [0.0; 3], "итерация {}", tick);
+
+            world.tick();
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,27 @@
+//! Библиотека моделирования эволюции "Эволюция": мир, растения, животные и их мозг.
+//! Программа `evolution` и примеры используют мир только через эту библиотеку.
+
+pub mod animal;
+pub mod plant;
+pub mod config;
+pub mod landscape;
+pub mod events;
+pub mod errors;
+pub mod summary;
+pub mod analysis;
+pub mod memory;
+pub mod stats;
+pub mod fitness;
+pub mod run_loop;
+pub mod deadline;
+pub mod policy;
+pub mod manifest;
+pub mod arena;
+pub mod mutation;
+pub mod writer;
+pub mod recorder;
+pub mod soak;
+pub mod rng;
+
+#[cfg(test)]
+mod testing;
@@ -1,116 +1,1183 @@
 //! Программа моделирование эволюции "Эволюция".
 
-use crate::animal::brains::simple::Brain as AnimalBrain;
-use crate::animal::species::simple::Animal;
+use evolution::animal::brains::AnimalBrain as _;
+use evolution::animal::brains::simple::Brain as AnimalBrain;
+use evolution::animal::species::simple::{Animal, AnimalParams};
 // Настройки
-use crate::config::*;
+use evolution::config::*;
 
-use std::sync::mpsc::channel;
+use std::env;
+use rand::{RngCore, SeedableRng};
+use rand::rngs::SmallRng;
+use std::fs;
+use std::path::Path;
+use std::process;
+use std::sync::mpsc::{channel, Receiver};
 use std::thread::spawn;
-use crate::animal::{AnimalDirection, AnimaType};
-use crate::landscape::Landscape;
+use std::time::Instant;
+use evolution::animal::{AnimalAlive, AnimalDirection, AnimaType, InputMask};
+use evolution::landscape::{Landscape, PlacementStrategy, WorldStats};
+use evolution::plant::PlantAlive;
+use evolution::plant::simple::{Plant, PlantParams};
+use evolution::run_loop::{self, RunIo, RunReport, Simulation, SystemClock};
+use evolution::deadline::{CheapMode, DeadlineMonitor};
+use evolution::policy::ReproductionFoodPolicy;
+use evolution::mutation::MutationController;
+use evolution::events::{Postmortem, WorldEvent, LINEAGE_CSV_HEADER};
+use evolution::{analysis, summary};
+use evolution::memory::MemoryBudget;
+use evolution::manifest::{RunManifest, Verdict};
+use evolution::writer::{AsyncWriter, LineBacklog};
+use evolution::recorder::Recorder;
+use evolution::memory::MemoryTracker;
+use evolution::soak::{self, SoakMonitor};
 
-use display::{launch_screen, Map};
-
-mod animal;
-mod plant;
-mod config;
-mod landscape;
-mod errors;
+use display::{default_base_path, launch_screen, Annotation, ControlMessage, DirectoryAssets, Frame, ViewConfig};
+use display::render_model::TextureSize;
+use round::round;
 
 fn main() {
     println!("Программа \"Эволюция\"");
 
+    // Подкоманды.
+    let mut args: Vec<String> = env::args().collect();
+
+    // Заселение мира клонами лучших животных прошлого прогона.
+    let seed_agents = match args.iter().position(|arg| arg == "--seed-agents") {
+        Some(index) if index + 1 < args.len() => {
+            let path = args.remove(index + 1);
+            args.remove(index);
+            Some(path)
+        }
+        Some(_) => {
+            eprintln!("Использование: evolution --seed-agents {}/{}_<зерно>.txt", RUN_DIR, BEST_BRAINS_FILE);
+            return;
+        }
+        None => None,
+    };
+
+    // Запись временного ряда мира в CSV файл (только без отображения).
+    let record = match args.iter().position(|arg| arg == "--record") {
+        Some(index) if index + 1 < args.len() => {
+            let path = args.remove(index + 1);
+            args.remove(index);
+            Some(path)
+        }
+        Some(_) => {
+            eprintln!("Использование: evolution --record out.csv");
+            return;
+        }
+        None => None,
+    };
+    if record.is_some() && !HEADLESS_MODE {
+        eprintln!("Временной ряд записывается только без отображения (HEADLESS_MODE)");
+    }
+
+    if args.len() > 1 {
+        match (args[1].as_str(), args.get(2)) {
+            ("inspect-brain", Some(path)) => {
+                let options = &args[3..];
+                let mask = if options.iter().any(|o| o == "--carnivore") {
+                    CARNIVORE_INPUT_MASK
+                } else if options.iter().any(|o| o == "--herbivore") {
+                    HERBIVORE_INPUT_MASK
+                } else {
+                    &[]
+                };
+
+                inspect_brain(path, options.iter().any(|o| o == "--json"), mask)
+            }
+            ("compare", Some(path_a)) if args.len() > 3 => compare_runs(path_a, &args[3]),
+            ("verify", Some(run_dir)) => verify_run(run_dir),
+            ("soak", ticks) => match ticks.map_or(Ok(SOAK_TICKS), |ticks| ticks.parse::<usize>()) {
+                Ok(ticks) => soak(ticks),
+                Err(e) => eprintln!("Неверное количество итераций: {}", e),
+            },
+            _ => eprintln!(
+                "Использование: evolution [--seed-agents run/best_brains_<seed>.txt] [--record out.csv] \
+                 | [inspect-brain genome.json [--json] [--herbivore|--carnivore] \
+                 | compare runA/stats.csv runB/stats.csv | verify run-dir | soak [ticks]]"
+            ),
+        }
+
+        return;
+    }
+
     // Создаем мир.
+    let (world, champions) = create_world(SEED, seed_agents.as_deref());
+    let mut run = Run {
+        world,
+        champions,
+        budget: MemoryBudget::new(MEMORY_BUDGET),
+        seed: SEED,
+        seed_agents,
+        traces: None,
+        output: None,
+        realtime: !HEADLESS_MODE,
+        stats_period_factor: 1,
+        recorder: if HEADLESS_MODE && record.is_some() { Some(Recorder::new(RECORD_PERIOD)) } else { None },
+        max_steps: MAX_STEPS,
+    };
+    run.output = Some(RunOutput::open(&mut run.world));
+    let clock = SystemClock::new();
+
+    let report = if HEADLESS_MODE == false {
+        // Канал для пересылки сообщений о состоянии мира.
+        let (mut sender, receiver) = channel::<Frame>();
+        // Канал для управляющих сообщений от окна отображения.
+        let (control_sender, mut control_receiver) = channel::<ControlMessage>();
+
+        // Топология и полоса у краев мира передаются отображению при запуске.
+        let topology = run.world.get_topology();
+        let spawn_margin = run.world.get_spawn_margin();
+        let base_path = base_path();
+
+        // Запуск отображения мира в отдельном потоке.
+        let handler = spawn(move || {
+            let config = ViewConfig {
+                width: GRID_WIDTH,
+                height: GRID_HEIGHT,
+                topology,
+                spawn_margin,
+                effect_frames: EVENT_EFFECT_FRAMES,
+                control: control_sender,
+                texture_size: TEXTURE_SIZE.map(|pixels| {
+                    TextureSize::from_pixels(pixels).expect("Нет текстур заданного размера!")
+                }),
+                assets: Box::new(DirectoryAssets::new(&base_path)),
+                title: Some("Программа эволюция".to_string()),
+                scale: WINDOW_SCALE,
+            };
+
+            launch_screen(SCREEN_TYPE, config, receiver).expect("Ошибка создания экрана!");
+        });
+
+        let report = run_loop::run(&mut run, &mut RunIo {
+            frames: Some(&mut sender),
+            control: Some(&mut control_receiver),
+            clock: &clock,
+        });
+
+        // Окно закрывается после того, как мир перестал отправлять кадры.
+        drop(sender);
+        handler.join().unwrap();
+
+        report
+    } else {
+        run_loop::run(&mut run, &mut RunIo {
+            frames: None,
+            control: None,
+            clock: &clock,
+        })
+    };
+
+    println!("Программа проработала {} минут(ы)", round(report.elapsed_ms as f64 / 60000.0, 4));
+
+    finish_action_traces(run.traces.take());
+    if let Some(output) = run.output.take() {
+        output.finish();
+    }
+    report_summary(&report.history, &run.world.get_postmortems());
+    write_manifest(&run, &report);
+    export_best_brains(&run.world);
+    if OCCUPANCY_TRACKING {
+        export_occupancy(&run.world);
+    }
+    if let (Some(recorder), Some(path)) = (&run.recorder, &record) {
+        match recorder.write_csv(path) {
+            Ok(()) => println!("Временной ряд ({} замеров) сохранен в файл {}", recorder.samples().len(), path),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+}
+
+/// Возвращает директорию, в которой лежит директория ресурсов: из переменной
+/// окружения EVOLUTION_BASE_PATH, из настроек или путь по умолчанию.
+///
+/// returns: String
+fn base_path() -> String {
+    env::var("EVOLUTION_BASE_PATH")
+        .ok()
+        .or_else(|| BASE_PATH.map(str::to_string))
+        .unwrap_or_else(default_base_path)
+}
+
+/// Лучшие животные прошлого прогона: вид и мозг (см. `init::read_champions`).
+type Champions = Vec<(AnimaType, AnimalBrain)>;
+
+/// Прогоняемая программой модель: мир и лучшие животные, клонами которых заселяются
+/// отложенные виды, бюджет памяти прогона, зерно генератора случайных чисел и файл
+/// лучших животных, с которыми мир создается заново, запись
+/// решений мозга (открывается при первом решении). Итерации прогона с отображением
+/// ограничены по времени (см. TICK_BUDGET_MS); множитель периода статистики больше
+/// единицы в режиме редкого сбора статистики; запись временного ряда (`--record`);
+/// наибольшее количество итераций (повтор по манифесту выполняет записанное количество);
+/// запись статистики, событий и родословной (нет при повторе по манифесту и проверке
+/// выносливости).
+struct Run {
+    world: Landscape,
+    champions: Champions,
+    budget: MemoryBudget,
+    seed: Option<u64>,
+    seed_agents: Option<String>,
+    traces: Option<AsyncWriter>,
+    output: Option<RunOutput>,
+    realtime: bool,
+    stats_period_factor: usize,
+    recorder: Option<Recorder>,
+    max_steps: usize,
+}
+
+impl Simulation for Run {
+    fn world(&mut self) -> &mut Landscape {
+        &mut self.world
+    }
+
+    fn restart(&mut self) {
+        (self.world, self.champions) = create_world(self.seed, self.seed_agents.as_deref());
+        self.stats_period_factor = 1;
+        if self.output.is_some() {
+            // Файлы прежнего мира дописываются и закрываются, новый мир пишет их заново.
+            self.output = None;
+            self.output = Some(RunOutput::open(&mut self.world));
+        }
+    }
+
+    fn step(&mut self) {
+        introduce_species(&mut self.world, &self.champions);
+        self.world.tick();
+        write_action_traces(&mut self.world, &mut self.traces);
+        if let Some(ref mut output) = self.output {
+            output.write(&self.world);
+        }
+        report_mutation_adjustments(&mut self.world);
+        if HEADLESS_MODE {
+            print_statistics(&self.world, STATS_PRINT_PERIOD * self.stats_period_factor);
+        }
+        if let Some(ref mut recorder) = self.recorder {
+            recorder.sample(&self.world);
+        }
+        enforce_memory_budget(&self.budget, &mut self.world, self.recorder.as_mut(), self.output.as_mut());
+    }
+
+    fn export_scenario(&mut self) {
+        export_scenario(&self.world);
+    }
+
+    fn max_steps(&self) -> usize {
+        self.max_steps
+    }
+
+    fn recent_history(&self) -> usize {
+        RECENT_HISTORY
+    }
+
+    fn deadline_monitor(&self) -> Option<DeadlineMonitor> {
+        // Повтор прогона по манифесту должен совпасть с исходным прогоном.
+        if !self.realtime || TICK_BUDGET_MS == 0 {
+            return None;
+        }
+
+        Some(DeadlineMonitor::new(
+            TICK_BUDGET_MS as f64, TICK_DEADLINE_WINDOW, TICK_DEADLINE_HEADROOM, &DEGRADATION_ORDER
+        ))
+    }
+
+    fn set_cheap_mode(&mut self, mode: CheapMode, enabled: bool) {
+        let iteration = self.world.get_world_stats().iteration;
+        if enabled {
+            println!(
+                "Итерация {}: итерации не укладываются в {} мс, включен режим \"{}\"",
+                iteration, TICK_BUDGET_MS, mode.describe()
+            );
+        } else {
+            println!(
+                "Итерация {}: итерации укладываются в {} мс с запасом, выключен режим \"{}\"",
+                iteration, TICK_BUDGET_MS, mode.describe()
+            );
+        }
+
+        match mode {
+            CheapMode::SparsePlants => {
+                let interval = if enabled { DEGRADED_PLANT_UPDATE_INTERVAL } else { PLANT_UPDATE_INTERVAL };
+                self.world.set_plant_update_interval(interval);
+            }
+            CheapMode::SparseStats => {
+                self.stats_period_factor = if enabled { DEGRADED_STATS_FACTOR } else { 1 };
+                self.world.set_diversity_sampling(DIVERSITY_PERIOD * self.stats_period_factor, DIVERSITY_SAMPLE);
+            }
+            CheapMode::NoValidation => {
+                let period = if enabled { 0 } else { STATS_VALIDATION_PERIOD };
+                self.world.set_stats_validation_period(period);
+            }
+        }
+    }
+
+    fn annotations(&mut self) -> Vec<Annotation> {
+        // Программа своих отметок не добавляет.
+        Vec::new()
+    }
+}
+
+/// Выводит веса мозга, сохраненного в файле генома, упорядоченные по значимости.
+///
+/// # Arguments
+///
+/// * `path`: Путь к файлу генома.
+/// * `json`: Вывести описание в формате JSON, иначе в виде текстовой таблицы.
+/// * `mask`: Маска входов вида животного, которому принадлежит мозг.
+///
+/// returns: ()
+fn inspect_brain(path: &str, json: bool, mask: InputMask) {
+    let brain = fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|json| AnimalBrain::from_json(&json, &mut SmallRng::seed_from_u64(0)).map_err(|e| e.to_string()));
+
+    match brain {
+        Ok(brain) if json => print!("{}", brain.describe(mask).to_json()),
+        Ok(brain) => print!("{}", brain.describe(mask)),
+        Err(e) => eprintln!("Не удалось загрузить геном мозга из файла {}: {}", path, e),
+    }
+}
+
+/// Сравнивает два прогона по сохраненной статистике и выводит таблицу сравнения.
+///
+/// # Arguments
+///
+/// * `path_a`: Путь к CSV файлу статистики прогона A.
+/// * `path_b`: Путь к CSV файлу статистики прогона B.
+///
+/// returns: ()
+fn compare_runs(path_a: &str, path_b: &str) {
+    let load = |path: &str| {
+        fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|csv| analysis::parse_csv(&csv).map_err(|e| e.to_string()))
+            .map_err(|e| eprintln!("Не удалось загрузить статистику из файла {}: {}", path, e))
+    };
+
+    if let (Ok(a), Ok(b)) = (load(path_a), load(path_b)) {
+        print!("{}", analysis::compare(&a, &b));
+    }
+}
+
+/// Сохраняет текущее состояние мира в файлы сценария и контрольной точки, а геномы
+/// мозгов животных - в файлы рядом с ними.
+///
+/// # Arguments
+///
+/// * `world`: Мир.
+///
+/// returns: ()
+fn export_scenario(world: &Landscape) {
+    let scenario = world.to_scenario();
+    let checkpoint = world.to_checkpoint();
+
+    let result = scenario.genomes.iter()
+        .try_for_each(|(file, json)| fs::write(file, json))
+        .and_then(|_| fs::write(SCENARIO_FILE, &scenario.text))
+        .and_then(|_| fs::write(CHECKPOINT_FILE, &checkpoint.text));
+
+    match result {
+        Ok(_) => println!(
+            "Состояние мира сохранено в сценарий {} и контрольную точку {} (геномов: {})",
+            SCENARIO_FILE,
+            CHECKPOINT_FILE,
+            scenario.genomes.len()
+        ),
+        Err(e) => eprintln!("Не удалось сохранить сценарий в файл {}: {}", SCENARIO_FILE, e),
+    }
+}
+
+/// Передает потоку записи решения мозга выбранного животного, записанные за итерацию.
+/// Файл открывается при первом решении; если запись не удалась, файл будет открыт
+/// заново при следующем решении.
+///
+/// # Arguments
+///
+/// * `world`: Мир.
+/// * `writer`: Запись решений в файл.
+///
+/// returns: ()
+fn write_action_traces(world: &mut Landscape, writer: &mut Option<AsyncWriter>) {
+    let traces = world.take_action_traces();
+    if traces.is_empty() {
+        return;
+    }
+
+    if writer.is_none() {
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(ACTION_TRACE_FILE);
+
+        match file {
+            Ok(file) => {
+                *writer = Some(AsyncWriter::new(Box::new(file), ACTION_TRACE_QUEUE, ACTION_TRACE_OVERFLOW));
+            }
+            Err(e) => {
+                eprintln!("Не удалось открыть файл решений мозга {}: {}", ACTION_TRACE_FILE, e);
+                return;
+            }
+        }
+    }
+
+    let lines = traces.iter().map(|trace| trace.to_json()).collect();
+    if let Some(Err(e)) = writer.as_mut().map(|writer| writer.write_lines(lines)) {
+        eprintln!("Не удалось записать решения мозга в файл {}: {}", ACTION_TRACE_FILE, e);
+        *writer = None;
+    }
+}
+
+/// Дожидается записи всех решений мозга и сообщает, сколько решений было отброшено.
+///
+/// # Arguments
+///
+/// * `writer`: Запись решений в файл.
+///
+/// returns: ()
+fn finish_action_traces(writer: Option<AsyncWriter>) {
+    match writer.map(AsyncWriter::finish) {
+        Some(Ok(dropped)) if dropped > 0 => {
+            println!("Решений мозга не записано из-за переполнения очереди: {}", dropped)
+        }
+        Some(Err(e)) => eprintln!("Не удалось записать решения мозга в файл {}: {}", ACTION_TRACE_FILE, e),
+        _ => {}
+    }
+}
+
+/// Запись в файлы по ходу прогона: статистика по итерациям, события мира и родословная.
+/// Каждый файл пишется своим потоком (см. `AsyncWriter`), чтобы ввод-вывод не
+/// задерживал итерации. Если запись в файл не удалась, файл больше не пишется. Строки
+/// событий и родословной копятся в памяти и передаются потокам записи раз в
+/// OUTPUT_BATCH_TICKS итераций.
+struct RunOutput {
+    stats: Option<AsyncWriter>,
+    events: Option<Receiver<WorldEvent>>,
+    event_log: Option<AsyncWriter>,
+    lineage: Option<AsyncWriter>,
+    event_backlog: LineBacklog,
+    lineage_backlog: LineBacklog,
+}
+
+impl RunOutput {
+    /// Открывает файлы и, если записываются события или родословная, задает миру
+    /// канал событий.
+    ///
+    /// # Arguments
+    ///
+    /// * `world`: Мир.
+    ///
+    /// returns: RunOutput
+    fn open(world: &mut Landscape) -> RunOutput {
+        let stats = Self::open_file(STATS_CSV_FILE, analysis::csv_header(STATS_CSV_ENERGY_BUDGET));
+        let event_log = EVENTS_FILE.and_then(|path| Self::open_file(path, String::new()));
+        let lineage = LINEAGE_FILE.and_then(|path| Self::open_file(path, LINEAGE_CSV_HEADER.to_string()));
+
+        let events = if event_log.is_some() || lineage.is_some() {
+            let (sender, receiver) = channel::<WorldEvent>();
+            world.set_event_sink(Some(sender));
+            Some(receiver)
+        } else {
+            None
+        };
+
+        RunOutput {
+            stats,
+            events,
+            event_log,
+            lineage,
+            event_backlog: LineBacklog::new("строки событий"),
+            lineage_backlog: LineBacklog::new("строки родословной"),
+        }
+    }
+
+    /// Создает файл и запускает поток записи в него.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: Путь к файлу.
+    /// * `header`: Первая строка файла (пустая - без заголовка).
+    ///
+    /// returns: Option<AsyncWriter> - None, если файл не удалось создать.
+    fn open_file(path: &str, header: String) -> Option<AsyncWriter> {
+        let file = match fs::File::create(path) {
+            Ok(file) => file,
+            Err(e) => {
+                eprintln!("Не удалось создать файл {}: {}", path, e);
+                return None;
+            }
+        };
+
+        let mut writer = AsyncWriter::new(Box::new(file), OUTPUT_QUEUE, OUTPUT_OVERFLOW);
+        if !header.is_empty() && !Self::send(Some(&mut writer), path, vec![header]) {
+            return None;
+        }
+
+        Some(writer)
+    }
+
+    /// Передает потокам записи статистику итерации и накопленные события мира.
+    ///
+    /// # Arguments
+    ///
+    /// * `world`: Мир.
+    ///
+    /// returns: ()
+    fn write(&mut self, world: &Landscape) {
+        let stats = world.get_world_stats();
+        let row = analysis::csv_row(&stats, STATS_CSV_ENERGY_BUDGET);
+        if !Self::send(self.stats.as_mut(), STATS_CSV_FILE, vec![row]) {
+            self.stats = None;
+        }
+
+        if let Some(ref receiver) = self.events {
+            for event in receiver.try_iter() {
+                if self.event_log.is_some() {
+                    self.event_backlog.push(Some(event.to_json()));
+                }
+                if self.lineage.is_some() {
+                    self.lineage_backlog.push(event.to_lineage_csv());
+                }
+            }
+        }
+
+        if stats.iteration.is_multiple_of(OUTPUT_BATCH_TICKS.max(1)) {
+            self.flush();
+        }
+    }
+
+    /// Передает потокам записи накопленные строки событий и родословной.
+    ///
+    /// returns: ()
+    fn flush(&mut self) {
+        let lines = self.event_backlog.take();
+        if !lines.is_empty() && !Self::send(self.event_log.as_mut(), EVENTS_FILE.unwrap_or_default(), lines) {
+            self.event_log = None;
+        }
+
+        let lines = self.lineage_backlog.take();
+        if !lines.is_empty() && !Self::send(self.lineage.as_mut(), LINEAGE_FILE.unwrap_or_default(), lines) {
+            self.lineage = None;
+        }
+    }
+
+    /// Передает строки потоку записи файла.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer`: Запись в файл (None - файл не пишется).
+    /// * `path`: Путь к файлу (для сообщения об ошибке).
+    /// * `lines`: Строки (вместе с переводами строк).
+    ///
+    /// returns: bool - false, если запись не удалась.
+    fn send(writer: Option<&mut AsyncWriter>, path: &str, lines: Vec<String>) -> bool {
+        match writer.map(|writer| writer.write_lines(lines)) {
+            Some(Err(e)) => {
+                eprintln!("Не удалось записать в файл {}: {}", path, e);
+                false
+            }
+            _ => true,
+        }
+    }
+
+    /// Передает потокам записи оставшиеся строки, дожидается записи всех файлов и
+    /// сообщает, сколько строк было отброшено.
+    ///
+    /// returns: ()
+    fn finish(mut self) {
+        self.flush();
+
+        let files = [
+            (STATS_CSV_FILE, self.stats, 0),
+            (EVENTS_FILE.unwrap_or_default(), self.event_log, self.event_backlog.dropped()),
+            (LINEAGE_FILE.unwrap_or_default(), self.lineage, self.lineage_backlog.dropped()),
+        ];
+
+        for (path, writer, shed) in files {
+            if shed > 0 {
+                println!("Строк не записано в файл {} из-за нехватки памяти: {}", path, shed);
+            }
+            match writer.map(AsyncWriter::finish) {
+                Some(Ok(dropped)) if dropped > 0 => {
+                    println!("Строк не записано в файл {} из-за переполнения очереди: {}", path, dropped)
+                }
+                Some(Err(e)) => eprintln!("Не удалось записать в файл {}: {}", path, e),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Выводит итоги прогона и сохраняет их в файл.
+///
+/// # Arguments
+///
+/// * `history`: Статистика мира, собранная после каждой итерации.
+/// * `deaths`: Записи о жизни последних умерших животных.
+///
+/// returns: ()
+fn report_summary(history: &[WorldStats], deaths: &[Postmortem]) {
+    let mut summary = summary::analyze(history);
+    summary.add_deaths(deaths);
+
+    print!("{}", summary);
+
+    if let Err(e) = fs::write(RUN_SUMMARY_FILE, summary.to_json()) {
+        eprintln!("Не удалось сохранить итоги прогона в файл {}: {}", RUN_SUMMARY_FILE, e);
+    }
+}
+
+/// Сохраняет мозги лучших животных, чтобы заселить ими следующий прогон
+/// (`--seed-agents`).
+///
+/// # Arguments
+///
+/// * `world`: Мир.
+///
+/// returns: ()
+fn export_best_brains(world: &Landscape) {
+    // Файл прогона с другим зерном (например, файл, которым заселен этот мир) не
+    // перезаписывается.
+    let path = Path::new(RUN_DIR).join(format!("{}_{}.txt", BEST_BRAINS_FILE, world.get_seed()));
+    let path = path.to_string_lossy();
+
+    match fs::create_dir_all(RUN_DIR).map_err(|e| e.to_string())
+        .and_then(|_| world.export_best_brains(&path).map_err(|e| e.to_string()))
+    {
+        Ok(count) => println!(
+            "Мозги лучших животных ({}) сохранены в файл {} (следующий прогон: --seed-agents {})", count, path, path
+        ),
+        Err(e) => eprintln!("{}", e),
+    }
+}
+
+/// Сохраняет занятость ячеек мира в файлы CSV для тепловых карт.
+///
+/// # Arguments
+///
+/// * `world`: Мир.
+///
+/// returns: ()
+fn export_occupancy(world: &Landscape) {
+    match world.export_occupancy(OCCUPANCY_FILE_PREFIX) {
+        Ok(paths) => println!("Занятость ячеек сохранена в файлы {}", paths.join(", ")),
+        Err(e) => eprintln!("{}", e),
+    }
+}
+
+/// Сохраняет в директорию прогона манифест, по которому прогон можно повторить и
+/// проверить (подкоманда `verify`).
+///
+/// # Arguments
+///
+/// * `run`: Прогон.
+/// * `report`: Итоги прогона.
+///
+/// returns: ()
+fn write_manifest(run: &Run, report: &RunReport) {
+    let degradation = run.deadline_monitor().map(|_| report.degradations.len());
+    // Случайные блоки загруженного сценария записываются вместе с настройками.
+    let mut config = config_snapshot();
+    config.extend(run.world.get_scenario_blocks().iter()
+        .enumerate()
+        .map(|(index, block)| (format!("SCENARIO_RANDOM_{}", index + 1), block.clone())));
+    let manifest = RunManifest::new(run.world.get_seed(), config, &report.history, degradation);
+    let path = Path::new(RUN_DIR).join(RUN_MANIFEST_FILE);
+
+    match fs::create_dir_all(RUN_DIR).and_then(|_| fs::write(&path, manifest.to_text())) {
+        Ok(()) => println!("Манифест прогона сохранен в файл {} (проверка: verify {})", path.display(), RUN_DIR),
+        Err(e) => eprintln!("Не удалось сохранить манифест прогона в файл {}: {}", path.display(), e),
+    }
+}
+
+/// Повторяет прогон по манифесту из директории прогона без отображения (ровно столько
+/// итераций, сколько записано в манифесте) и сравнивает итоговую статистику
+/// с записанной. Выводит PASS или FAIL с первым расхождением; при расхождении
+/// программа завершается с кодом 1.
+///
+/// # Arguments
+///
+/// * `run_dir`: Директория прогона с файлом манифеста.
+///
+/// returns: ()
+fn verify_run(run_dir: &str) {
+    let path = Path::new(run_dir).join(RUN_MANIFEST_FILE);
+    let manifest = match fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| RunManifest::parse(&text).map_err(|e| e.to_string()))
+    {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            eprintln!("Не удалось загрузить манифест прогона из файла {}: {}", path.display(), e);
+            process::exit(1);
+        }
+    };
+
+    let mut run = Run {
+        world: create_world(Some(manifest.seed), None).0,
+        champions: Vec::new(),
+        budget: MemoryBudget::new(MEMORY_BUDGET),
+        seed: Some(manifest.seed),
+        seed_agents: None,
+        traces: None,
+        output: None,
+        realtime: false,
+        stats_period_factor: 1,
+        recorder: None,
+        max_steps: manifest.steps,
+    };
+    let report = run_loop::run(&mut run, &mut RunIo {
+        frames: None,
+        control: None,
+        clock: &SystemClock::new(),
+    });
+
+    let rerun = RunManifest::new(run.world.get_seed(), config_snapshot(), &report.history, None);
+    match manifest.verify(&rerun) {
+        Verdict::Pass => println!("PASS: прогон {} воспроизведен (зерно {})", run_dir, manifest.seed),
+        Verdict::Fail(difference) => {
+            println!("FAIL: прогон {} не воспроизведен: {}", run_dir, difference);
+            process::exit(1);
+        }
+    }
+}
+
+/// Проверка выносливости: прогоняет мир без отображения заданное количество итераций,
+/// периодически замеряя память процесса, продолжительность итераций и сверяя счетчики
+/// статистики. Отчет сохраняется в файл; если проверка не пройдена, программа
+/// завершается с кодом 1.
+///
+/// # Arguments
+///
+/// * `ticks`: Количество итераций.
+///
+/// returns: ()
+fn soak(ticks: usize) {
+    let mut run = Run {
+        world: create_world(SEED, None).0,
+        champions: Vec::new(),
+        budget: MemoryBudget::new(MEMORY_BUDGET),
+        seed: SEED,
+        seed_agents: None,
+        traces: None,
+        output: None,
+        realtime: false,
+        stats_period_factor: 1,
+        recorder: None,
+        max_steps: ticks,
+    };
+    let mut monitor = SoakMonitor::new(SOAK_MAX_MEMORY_SLOPE);
+    let period = SOAK_SAMPLE_PERIOD.max(1);
+
+    println!("Проверка выносливости: {} итераций, зерно {}", ticks, run.world.get_seed());
+
+    for tick in 1..=ticks {
+        let start = Instant::now();
+        run.step();
+        monitor.record_tick(start.elapsed().as_secs_f64() * 1000.0);
+
+        if tick % period == 0 || tick == ticks {
+            if let Err(e) = run.world.validate_statistics() {
+                monitor.record_failure(tick, e.to_string());
+            }
+            monitor.sample(tick, soak::resident_memory(), run.world.memory_usage());
+
+            if let Some(sample) = monitor.samples().last() {
+                println!(
+                    "Итерация {}: память {} байт, итерация p95 {} мс",
+                    tick,
+                    sample.rss_bytes.unwrap_or(sample.tracked_bytes),
+                    round(sample.tick_ms.1, 3)
+                );
+            }
+        }
+    }
+
+    print!("{}", monitor.summary());
+    if let Err(e) = fs::write(SOAK_REPORT_FILE, monitor.report()) {
+        eprintln!("Не удалось сохранить отчет проверки выносливости в файл {}: {}", SOAK_REPORT_FILE, e);
+    }
+
+    if !monitor.passed() {
+        process::exit(1);
+    }
+}
+
+/// Настройки, от которых зависит результат прогона. Записываются в манифест прогона:
+/// повтор с другими настройками не считается воспроизведением.
+///
+/// returns: Vec<(String, String)>
+fn config_snapshot() -> Vec<(String, String)> {
+    [
+        ("MAX_STEPS", MAX_STEPS.to_string()),
+        ("GRID_WIDTH", GRID_WIDTH.to_string()),
+        ("GRID_HEIGHT", GRID_HEIGHT.to_string()),
+        ("TOPOLOGY", format!("{:?}", TOPOLOGY)),
+        ("SPAWN_MARGIN", SPAWN_MARGIN.to_string()),
+        ("MAX_PLANTS", MAX_PLANTS.to_string()),
+        ("MAX_HERBIVORE", MAX_HERBIVORE.to_string()),
+        ("MAX_CARNIVORE", MAX_CARNIVORE.to_string()),
+        ("MAX_OMNIVORE", MAX_OMNIVORE.to_string()),
+        ("OMNIVORE_PLANT_ENERGY_RATE", OMNIVORE_PLANT_ENERGY_RATE.to_string()),
+        ("OMNIVORE_MEAT_ENERGY_RATE", OMNIVORE_MEAT_ENERGY_RATE.to_string()),
+        ("MAX_PLANT_GROW_ENERGY", MAX_PLANT_GROW_ENERGY.to_string()),
+        ("PLANT_SEASON", format!("{:?}", PLANT_SEASON)),
+        ("MAX_PLANT_ENERGY", MAX_PLANT_ENERGY.to_string()),
+        ("PLANT_EATEN_ENERGY", PLANT_EATEN_ENERGY.to_string()),
+        ("PLANT_UPDATE_INTERVAL", PLANT_UPDATE_INTERVAL.to_string()),
+        ("PLANT_MAX_AGE", PLANT_MAX_AGE.to_string()),
+        ("MAX_ANIMAL_ENERGY", MAX_ANIMAL_ENERGY.to_string()),
+        ("ANIMAL_BIRTH_ENERGY", ANIMAL_BIRTH_ENERGY.to_string()),
+        ("ANIMAL_LIVE_ENERGY", ANIMAL_LIVE_ENERGY.to_string()),
+        ("ANIMAL_ENERGY_PROFILE", format!("{:?}", ANIMAL_ENERGY_PROFILE)),
+        ("ANIMAL_EATEN_ENERGY_RATE", ANIMAL_EATEN_ENERGY_RATE.to_string()),
+        ("TWO_STEP_PREDATION", TWO_STEP_PREDATION.to_string()),
+        ("ATTACK_SUCCESS_RATE", ATTACK_SUCCESS_RATE.to_string()),
+        ("CORPSE_LIFETIME", CORPSE_LIFETIME.to_string()),
+        ("ANIMAL_REPRODUCE_ENERGY_RATE", ANIMAL_REPRODUCE_ENERGY_RATE.to_string()),
+        ("ANIMAL_VISION_RADIUS", ANIMAL_VISION_RADIUS.to_string()),
+        ("ANIMAL_MAX_AGE", ANIMAL_MAX_AGE.to_string()),
+        ("PLANT_REPRODUCTION_PLACEMENT", format!("{:?}", PLANT_REPRODUCTION_PLACEMENT)),
+        ("ANIMAL_REPRODUCTION_PLACEMENT", format!("{:?}", ANIMAL_REPRODUCTION_PLACEMENT)),
+        ("GESTATION_TICKS", GESTATION_TICKS.to_string()),
+        ("GESTATION_ENERGY_DRAIN", GESTATION_ENERGY_DRAIN.to_string()),
+        ("MAX_PENDING_BIRTHS", MAX_PENDING_BIRTHS.to_string()),
+        ("CARRION_DECAY_RATE", CARRION_DECAY_RATE.to_string()),
+        ("CARRION_LIFETIME", CARRION_LIFETIME.to_string()),
+        ("FITNESS", FITNESS.name().to_string()),
+        ("MUTATION_RATE", format!("{:?}", MUTATION_RATE)),
+        ("MUTATION_CONTROLLER", format!("{:?}", MUTATION_CONTROLLER)),
+    ]
+    .into_iter()
+    .map(|(name, value)| (name.to_string(), value))
+    .collect()
+}
+
+/// Создает и заселяет мир в соответствии с настройками.
+///
+/// # Arguments
+///
+/// * `seed`: Зерно генератора случайных чисел мира. None - зерно случайное.
+/// * `seed_agents`: Файл лучших животных прошлого прогона. None - животные случайные.
+///
+/// returns: (Landscape, Champions) - мир и лучшие животные, клонами которых заселяются
+/// отложенные виды (см. `introduce_species`).
+fn create_world(seed: Option<u64>, seed_agents: Option<&str>) -> (Landscape, Champions) {
+    PLANT_BITE_MODE.validate().expect("Ошибка настройки растений!");
+    ANIMAL_ENERGY_PROFILE.validate().expect("Ошибка настройки животных!");
+
     let mut world = Landscape::new(
         GRID_WIDTH,
         GRID_HEIGHT,
+        MAX_PLANTS,
         MAX_HERBIVORE,
         MAX_CARNIVORE,
-        MAX_PLANTS,
-        MAX_PLANT_GROW_ENERGY
+        MAX_PLANT_GROW_ENERGY,
+        seed
     ).expect("Ошибка создания мира!");
 
-    // // Заселение мира растениями и животными.
-    // let mut plant = Plant::new(
-    //     MAX_PLANT_ENERGY,
-    //     MAX_PLANT_ENERGY,
-    //     PLANT_EATEN_ENERGY,
-    //     PLANT_REPRODUCE_ENERGY_RATE,
-    //     PLANT_NO_REPRO
-    // );
-    // world.add_plant(0, 0, plant).expect("Ячейка занята!");
-    //
-    // let mut herb = Animal::<AnimalBrain>::new(
-    //     AnimaType::Herbivore,
-    //     ANIMAL_BIRTH_ENERGY,
-    //     MAX_ANIMAL_ENERGY,
-    //     ANIMAL_LIVE_ENERGY,
-    //     ANIMAL_EATEN_ENERGY_RATE,
-    //     ANIMAL_REPRODUCE_ENERGY_RATE,
-    //     ANIMAL_NO_REPRO,
-    //     AnimalDirection::North,
-    //     0,
-    // );
-    // world.add_animal(0, 1, herb).expect("Ячейка занята!");
-
-    let mut carn = Animal::<AnimalBrain>::new(
-        AnimaType::Carnivore,
-        ANIMAL_BIRTH_ENERGY,
-        MAX_ANIMAL_ENERGY,
-        ANIMAL_LIVE_ENERGY,
-        ANIMAL_EATEN_ENERGY_RATE,
-        ANIMAL_REPRODUCE_ENERGY_RATE,
-        ANIMAL_NO_REPRO,
-        AnimalDirection::North,
-        0,
+    world.set_eat_targeting(HERBIVORE_EAT_TARGETING, CARNIVORE_EAT_TARGETING, OMNIVORE_EAT_TARGETING);
+    world.set_strict_eating(STRICT_EATING);
+    world.set_topology(TOPOLOGY);
+    world.set_reproduction_placement(PLANT_REPRODUCTION_PLACEMENT, ANIMAL_REPRODUCTION_PLACEMENT);
+    world.set_gestation(GESTATION_TICKS, GESTATION_ENERGY_DRAIN, MAX_PENDING_BIRTHS);
+    world.set_mutation_rate(MUTATION_RATE.0, MUTATION_RATE.1, MUTATION_RATE.2);
+    world.set_max_omnivore(MAX_OMNIVORE);
+    world.set_omnivore_energy_rate(OMNIVORE_PLANT_ENERGY_RATE, OMNIVORE_MEAT_ENERGY_RATE);
+    world.set_mutation_controller(MUTATION_CONTROLLER.map(|(target_diversity, gain, bounds)| {
+        MutationController::new(target_diversity, gain, bounds).expect("Ошибка настройки регулятора мутаций!")
+    }));
+    world.set_food_focus(HERBIVORE_FOOD_FOCUS);
+    world.set_seeds_per_reproduction(PLANT_SEEDS_PER_REPRODUCTION);
+    world.set_plant_max_age(PLANT_MAX_AGE);
+    world.set_plant_update_interval(PLANT_UPDATE_INTERVAL);
+    world.set_initial_energy(PLANT_INITIAL_ENERGY, ANIMAL_INITIAL_ENERGY);
+    world.set_birth_budget(
+        MAX_HERBIVORE_BIRTHS_PER_TICK, MAX_CARNIVORE_BIRTHS_PER_TICK, MAX_OMNIVORE_BIRTHS_PER_TICK
+    );
+    world.set_scavenging(
+        (HERBIVORE_SCAVENGING, CARNIVORE_SCAVENGING, OMNIVORE_SCAVENGING),
+        CARRION_ENERGY_RATE,
+        CARRION_BITE_ENERGY
     );
-    world.add_animal(5, 5, carn).expect("Ячейка занята!");
+    world.set_carrion_decay(CARRION_DECAY_RATE, CARRION_FERTILITY_RATE, FERTILITY_DECAY_RATE);
+    world.set_carrion_lifetime(CARRION_LIFETIME);
+    if TWO_STEP_PREDATION {
+        world.set_two_step_predation(ATTACK_SUCCESS_RATE, CORPSE_LIFETIME);
+    }
+    world.add_action_policy(Box::new(
+        ReproductionFoodPolicy::new(HERBIVORE_REPRODUCE_MIN_FOOD, CARNIVORE_REPRODUCE_MIN_FOOD)
+            .with_omnivore(OMNIVORE_REPRODUCE_MIN_FOOD)
+    )).expect("Ошибка настройки правил мира!");
+    world.set_start_tick(HERBIVORE_START_TICK, CARNIVORE_START_TICK, OMNIVORE_START_TICK);
+    world.set_brain_context(BRAIN_CONTEXT);
+    world.set_season(PLANT_SEASON);
+    world.set_occupancy_tracking(OCCUPANCY_TRACKING);
+    world.set_postmortem_history(POSTMORTEM_HISTORY);
+    world.set_protection_age(JUVENILE_PROTECTION_AGE.0, JUVENILE_PROTECTION_AGE.1, JUVENILE_PROTECTION_AGE.2);
+    world.set_stats_validation_period(STATS_VALIDATION_PERIOD);
+    world.set_energy_sanitizing(SANITIZE_ENERGY);
+    world.set_fitness(FITNESS);
+    world.set_diversity_sampling(DIVERSITY_PERIOD, DIVERSITY_SAMPLE);
+    world.set_hunt_stats_window(HUNT_STATS_WINDOW);
+    world.set_energy_budget_window(ENERGY_BUDGET_WINDOW);
+    world.set_spawn_margin(SPAWN_MARGIN).expect("Ошибка настройки полосы у краев мира!");
 
-    if HEADLESS_MODE == false {
-        // Канал для пересылки сообщений о состоянии мира.
-        let (sender, receiver) = channel::<Map>();
+    // Заселение мира растениями и животными: случайными или клонами лучших животных.
+    let champions = match seed_agents {
+        Some(path) => {
+            world.populate_random(INITIAL_PLANTS, (0, PlacementStrategy::Uniform), (0, PlacementStrategy::Uniform),
+                                  &mut new_plant, &mut new_animal)
+                .expect("Ошибка заселения мира!");
+            let champions = init::read_champions::<AnimalBrain>(path, world.rng())
+                .expect("Ошибка заселения мира лучшими животными!");
+            init::seed_population(
+                &mut world,
+                &champions,
+                (SEED_HERBIVORES, PlacementStrategy::Uniform),
+                (SEED_CARNIVORES, PlacementStrategy::Uniform),
+                (MUTATION_RATE.0, MUTATION_RATE.1),
+                &mut animal_with_brain,
+            ).expect("Ошибка заселения мира лучшими животными!");
 
-        // Запуск отображения мира в отдельном потоке.
-        let handler = spawn(|| {
-            launch_screen(
-                SCREEN_TYPE,
-                GRID_WIDTH,
-                GRID_HEIGHT,
-                receiver,
-                "D:/Projects/RustroverProjects/evolution",
-                "Программа эволюция"
-            ).expect("Ошибка создания экрана!");
-        });
+            champions
+        }
+        None => {
+            world.populate_random(INITIAL_PLANTS, INITIAL_HERBIVORES, INITIAL_CARNIVORES,
+                                  &mut new_plant, &mut new_animal)
+                .expect("Ошибка заселения мира!");
 
-        // Итерации мира.
-        for _ in 0..MAX_STEPS {
-            // Одна итерация
-            world.tick();
+            Vec::new()
+        }
+    };
+    world.populate_omnivores(INITIAL_OMNIVORES, &mut new_animal).expect("Ошибка заселения мира всеядными!");
 
-            // Собираем карту состояния мира для отображения.
-            sender.send(world.get_view_state()).expect("Не удалось отправить данные для отображения в канал");
+    (world, champions)
+}
 
-            use std::thread;
-            //thread::sleep(Duration::from_millis(1000));
-        }
+/// Создает новое животное заданного типа в соответствии с настройками.
+///
+/// # Arguments
+///
+/// * `animal_type`: Тип животного.
+/// * `rng`: Генератор случайных чисел мира.
+///
+/// returns: Box<dyn AnimalAlive>
+fn new_animal(animal_type: AnimaType, rng: &mut dyn RngCore) -> Box<dyn AnimalAlive> {
+    let brain = AnimalBrain::from_rng(rng);
+    animal_with_brain(animal_type, brain, rng)
+}
 
-        // Если итерации мира закончились, ждем явного выхода из окна отображения мира.
-        handler.join().unwrap();
-    } else {
-        use chrono::Utc;
-        use round::round;
+/// Создает новое растение в соответствии с настройками.
+///
+/// returns: Box<dyn PlantAlive>
+fn new_plant() -> Box<dyn PlantAlive> {
+    Plant::new(PlantParams {
+        energy: MAX_PLANT_ENERGY,
+        max_energy: MAX_PLANT_ENERGY,
+        eaten_energy: PLANT_EATEN_ENERGY,
+        bite_mode: PLANT_BITE_MODE,
+        reproduce_energy_rate: PLANT_REPRODUCE_ENERGY_RATE,
+        no_repro: PLANT_NO_REPRO,
+        regrowth_delay: PLANT_REGROWTH_DELAY,
+        seed_energy_cost: PLANT_SEED_ENERGY_COST,
+    }).expect("Ошибка настройки растений!")
+}
 
-        let start = Utc::now().timestamp() as f64;
+/// Создает новое животное заданного типа с заданным мозгом в соответствии с настройками.
+///
+/// # Arguments
+///
+/// * `animal_type`: Тип животного.
+/// * `brain`: Мозг животного.
+/// * `rng`: Генератор случайных чисел мира (выбирает направление животного).
+///
+/// returns: Box<dyn AnimalAlive>
+fn animal_with_brain(animal_type: AnimaType, brain: AnimalBrain, rng: &mut dyn RngCore) -> Box<dyn AnimalAlive> {
+    Animal::<AnimalBrain>::with_brain(
+        animal_type,
+        AnimalParams {
+            energy: ANIMAL_BIRTH_ENERGY,
+            max_energy: MAX_ANIMAL_ENERGY,
+            live_energy: ANIMAL_LIVE_ENERGY,
+            energy_profile: ANIMAL_ENERGY_PROFILE,
+            eaten_energy_rate: ANIMAL_EATEN_ENERGY_RATE,
+            reproduce_energy_rate: ANIMAL_REPRODUCE_ENERGY_RATE,
+            no_repro: ANIMAL_NO_REPRO,
+            inherit_direction: ANIMAL_INHERIT_DIRECTION,
+            input_mask: input_mask(animal_type),
+            vision_radius: ANIMAL_VISION_RADIUS,
+            max_age: ANIMAL_MAX_AGE,
+        },
+        AnimalDirection::random(rng),
+        0,
+        brain,
+    ).expect("Ошибка настройки животных!")
+}
 
-        // Итерации мира.
-        for _ in 0..MAX_STEPS {
-            // Одна итерация
-            world.tick();
-        }
+/// Возвращает маску входов мозга для вида животных из настроек.
+///
+/// # Arguments
+///
+/// * `animal_type`: Тип животного.
+///
+/// returns: InputMask
+fn input_mask(animal_type: AnimaType) -> InputMask {
+    match animal_type {
+        AnimaType::Herbivore => HERBIVORE_INPUT_MASK,
+        AnimaType::Carnivore => CARNIVORE_INPUT_MASK,
+        AnimaType::Omnivore => OMNIVORE_INPUT_MASK,
+    }
+}
 
-        let end = Utc::now().timestamp() as f64;
+/// Проверяет бюджет памяти и сообщает, какая память была освобождена. Память
+/// освобождается в порядке: замеры временного ряда, строки родословной и событий,
+/// ждущие записи, пул умерших животных.
+///
+/// # Arguments
+///
+/// * `budget`: Бюджет памяти.
+/// * `world`: Мир.
+/// * `recorder`: Запись временного ряда (None - не ведется).
+/// * `output`: Запись в файлы (None - не ведется).
+///
+/// returns: ()
+fn enforce_memory_budget(
+    budget: &MemoryBudget,
+    world: &mut Landscape,
+    recorder: Option<&mut Recorder>,
+    output: Option<&mut RunOutput>,
+) {
+    let iteration = world.get_world_stats().iteration;
 
-        println!("Программа проработала {} минут(ы)", round((end - start)/60.0, 4));
+    let mut trackers: Vec<&mut dyn MemoryTracker> = Vec::new();
+    if let Some(recorder) = recorder {
+        trackers.push(recorder);
+    }
+    if let Some(output) = output {
+        trackers.push(&mut output.lineage_backlog);
+        trackers.push(&mut output.event_backlog);
+    }
+    trackers.push(world);
+
+    for (tracker, freed) in budget.enforce(&mut trackers) {
+        println!("Итерация {}: бюджет памяти превышен, {} освобождает {} байт", iteration, tracker, freed);
+    }
+}
+
+/// Сообщает об изменениях частоты мутаций, сделанных регулятором.
+///
+/// # Arguments
+///
+/// * `world`: Мир.
+///
+/// returns: ()
+fn report_mutation_adjustments(world: &mut Landscape) {
+    for adjustment in world.take_mutation_adjustments() {
+        let name = match adjustment.animal_type {
+            AnimaType::Herbivore => "травоядных",
+            AnimaType::Carnivore => "хищников",
+            AnimaType::Omnivore => "всеядных",
+        };
+        println!(
+            "Итерация {}: частота мутаций {} изменена с {} на {} (разнообразие {})",
+            adjustment.iteration,
+            name,
+            adjustment.from,
+            adjustment.to,
+            round(adjustment.diversity, 4)
+        );
+    }
+}
+
+/// Выводит строку статистики мира на итерациях, кратных периоду.
+///
+/// # Arguments
+///
+/// * `world`: Мир.
+/// * `period`: Период в итерациях, 0 - не выводить.
+///
+/// returns: ()
+fn print_statistics(world: &Landscape, period: usize) {
+    let stats = world.get_statistics();
+    if period == 0 || stats.tick % period != 0 {
+        return;
+    }
+
+    println!(
+        "Итерация {}: растений {}, травоядных {} (рождений {}, смертей {}, поколение {}, возраст {}/{}), \
+         хищников {} (рождений {}, смертей {}, поколение {}, возраст {}/{}), \
+         всеядных {} (рождений {}, смертей {}, поколение {}, возраст {}/{})",
+        stats.tick,
+        stats.plant_count,
+        stats.animal_count.0,
+        stats.births.0,
+        stats.deaths.0,
+        stats.max_generation.0,
+        stats.oldest_alive_age.0.unwrap_or(0),
+        stats.oldest_ever_age.0,
+        stats.animal_count.1,
+        stats.births.1,
+        stats.deaths.1,
+        stats.max_generation.1,
+        stats.oldest_alive_age.1.unwrap_or(0),
+        stats.oldest_ever_age.1,
+        stats.animal_count.2,
+        stats.births.2,
+        stats.deaths.2,
+        stats.max_generation.2,
+        stats.oldest_alive_age.2.unwrap_or(0),
+        stats.oldest_ever_age.2,
+    );
+
+    let species = [
+        ("травоядные", stats.energy_budget.0),
+        ("хищники", stats.energy_budget.1),
+        ("всеядные", stats.energy_budget.2),
+    ];
+    for (name, budget) in species {
+        println!(
+            "  энергия за {} итераций, {}: получено {:.1}, потрачено {:.1}, потомкам {:.1}, итого {:+.1}",
+            ENERGY_BUDGET_WINDOW.min(stats.tick),
+            name,
+            budget.gained,
+            budget.spent,
+            budget.offspring,
+            budget.net()
+        );
+    }
+}
+
+/// Заселяет виды животных, итерация появления которых наступила, и сообщает об этом.
+/// Если мир заселен клонами лучших животных (`--seed-agents`), виды заселяются их
+/// клонами, иначе случайными животными.
+///
+/// # Arguments
+///
+/// * `world`: Мир.
+/// * `champions`: Лучшие животные прошлого прогона.
+///
+/// returns: ()
+fn introduce_species(world: &mut Landscape, champions: &Champions) {
+    let iteration = world.get_world_stats().iteration;
+
+    let mut introduced_animal = |animal_type: AnimaType, rng: &mut dyn RngCore| {
+        init::clone_champion(champions, animal_type, (MUTATION_RATE.0, MUTATION_RATE.1), rng, &mut animal_with_brain)
+            .unwrap_or_else(|| new_animal(animal_type, rng))
+    };
+
+    match world.introduce_species(&mut introduced_animal) {
+        Ok(introduced) => {
+            for animal_type in introduced {
+                let name = match animal_type {
+                    AnimaType::Herbivore => "Травоядные",
+                    AnimaType::Carnivore => "Хищники",
+                    AnimaType::Omnivore => "Всеядные",
+                };
+                println!("{} появились в мире на итерации {}", name, iteration);
+            }
+        }
+        Err(e) => eprintln!("Не удалось заселить вид на итерации {}: {}", iteration, e),
     }
 }
@@ -0,0 +1,346 @@
+//! Манифест прогона: версия программы, настройки, зерно генератора случайных чисел и
+//! итоговая статистика. По манифесту прогон повторяется без отображения, а итоговая
+//! статистика повтора сравнивается с записанной (подкоманда `verify`).
+//!
+//! Формат - текст, по одной записи в строке:
+//!
+//! ```text
+//! version 0.1.0
+//! seed 42
+//! steps 1000
+//! degradation 0
+//! reproducible 1
+//! config MAX_STEPS 1000
+//! metric plant_count 35
+//! ```
+//!
+//! Строки, начинающиеся с `#`, и пустые строки пропускаются.
+
+use std::fmt;
+
+use crate::errors::RecoverableError;
+use crate::landscape::WorldStats;
+use crate::summary::RunSummary;
+
+/// Допустимое относительное расхождение значений статистики. Значения записываются
+/// без потери точности, поэтому у воспроизводимого прогона они совпадают точно;
+/// допуск защищает только от различий форматирования чисел.
+pub const METRIC_TOLERANCE: f64 = 1e-9;
+
+/// Манифест прогона.
+#[derive(Clone, Default)]
+pub struct RunManifest {
+    // Версия программы, выполнившей прогон.
+    pub version: String,
+    // Зерно генератора случайных чисел мира.
+    pub seed: u64,
+    // Количество выполненных итераций (с последнего перезапуска): повтор выполняет
+    // ровно столько итераций.
+    pub steps: usize,
+    // Была ли включена деградация при нехватке времени на итерацию.
+    pub degradation: bool,
+    // Причина, по которой прогон невоспроизводим (например, ручное вмешательство).
+    // None - прогон воспроизводим.
+    pub not_reproducible: Option<String>,
+    // Настройки прогона: имя и значение.
+    pub config: Vec<(String, String)>,
+    // Итоговая статистика: имя и значение.
+    pub metrics: Vec<(String, f64)>,
+}
+
+/// Результат проверки прогона по манифесту.
+pub enum Verdict {
+    /// Повтор совпал с манифестом.
+    Pass,
+    /// Повтор не совпал: описание первого расхождения.
+    Fail(String),
+}
+
+impl RunManifest {
+    /// Составляет манифест по итогам прогона.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed`: Зерно генератора случайных чисел мира.
+    /// * `config`: Настройки прогона.
+    /// * `history`: Статистика мира, собранная после каждой итерации.
+    /// * `degradation`: Количество шагов деградации, сделанных за прогон; None - деградация выключена.
+    ///
+    /// returns: RunManifest
+    pub fn new(
+        seed: u64,
+        config: Vec<(String, String)>,
+        history: &[WorldStats],
+        degradation: Option<usize>
+    ) -> RunManifest {
+        let summary = crate::summary::analyze(history);
+
+        // Деградация меняет правила мира в зависимости от скорости машины.
+        let not_reproducible = if summary.energy_adjustments > 0 {
+            Some(fmt::format(format_args!("ручные изменения энергии: {}", summary.energy_adjustments)))
+        } else if degradation.is_some_and(|steps| steps > 0) {
+            Some(fmt::format(format_args!("шаги деградации: {}", degradation.unwrap_or(0))))
+        } else {
+            None
+        };
+
+        RunManifest {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            seed,
+            steps: history.len(),
+            degradation: degradation.is_some(),
+            not_reproducible,
+            config,
+            metrics: metrics(history.last(), &summary),
+        }
+    }
+
+    /// Представляет манифест в виде текста.
+    pub fn to_text(&self) -> String {
+        let mut text = String::from("# Манифест прогона\n");
+        text.push_str(&format!("version {}\n", self.version));
+        text.push_str(&format!("seed {}\n", self.seed));
+        text.push_str(&format!("steps {}\n", self.steps));
+        text.push_str(&format!("degradation {}\n", self.degradation as u8));
+        match &self.not_reproducible {
+            None => text.push_str("reproducible 1\n"),
+            Some(reason) => text.push_str(&format!("reproducible 0 {}\n", reason)),
+        }
+        for (name, value) in &self.config {
+            text.push_str(&format!("config {} {}\n", name, value));
+        }
+        for (name, value) in &self.metrics {
+            text.push_str(&format!("metric {} {}\n", name, value));
+        }
+
+        text
+    }
+
+    /// Загружает манифест из текста, сохраненного методом `to_text`.
+    ///
+    /// # Arguments
+    ///
+    /// * `text`: Текст манифеста.
+    ///
+    /// returns: Result<RunManifest, RecoverableError>
+    pub fn parse(text: &str) -> Result<RunManifest, RecoverableError> {
+        let mut manifest = RunManifest::default();
+        let mut has_seed = false;
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let error = || RecoverableError::new(fmt::format(format_args!(
+                "Строка {} манифеста не распознана: {}",
+                number + 1,
+                line
+            )));
+
+            let (key, rest) = line.split_once(' ').ok_or_else(error)?;
+            match key {
+                "version" => manifest.version = rest.to_string(),
+                "seed" => {
+                    manifest.seed = rest.parse().map_err(|_| error())?;
+                    has_seed = true;
+                }
+                "steps" => manifest.steps = rest.parse().map_err(|_| error())?,
+                "degradation" => {
+                    manifest.degradation = match rest {
+                        "0" => false,
+                        "1" => true,
+                        _ => return Err(error()),
+                    };
+                }
+                "reproducible" => {
+                    manifest.not_reproducible = match rest.split_once(' ') {
+                        None if rest == "1" => None,
+                        None if rest == "0" => Some(String::new()),
+                        Some(("0", reason)) => Some(reason.to_string()),
+                        _ => return Err(error()),
+                    };
+                }
+                "config" => {
+                    let (name, value) = rest.split_once(' ').ok_or_else(error)?;
+                    manifest.config.push((name.to_string(), value.to_string()));
+                }
+                "metric" => {
+                    let (name, value) = rest.split_once(' ').ok_or_else(error)?;
+                    manifest.metrics.push((name.to_string(), value.parse().map_err(|_| error())?));
+                }
+                _ => return Err(error()),
+            }
+        }
+
+        if !has_seed {
+            return Err(RecoverableError::new("В манифесте нет зерна генератора случайных чисел".to_string()));
+        }
+
+        Ok(manifest)
+    }
+
+    /// Сравнивает повтор прогона с манифестом: версию, настройки и итоговую статистику.
+    ///
+    /// # Arguments
+    ///
+    /// * `rerun`: Манифест повтора.
+    ///
+    /// returns: Verdict - первое расхождение, если оно есть.
+    pub fn verify(&self, rerun: &RunManifest) -> Verdict {
+        if let Some(reason) = &self.not_reproducible {
+            return Verdict::Fail(format!("прогон помечен как невоспроизводимый: {}", reason));
+        }
+
+        if self.version != rerun.version {
+            return Verdict::Fail(format!("версия программы: {} != {}", self.version, rerun.version));
+        }
+
+        if let Some(difference) = first_difference(&self.config, &rerun.config, |a, b| a == b) {
+            return Verdict::Fail(format!("настройка {}", difference));
+        }
+
+        if let Some(difference) = first_difference(&self.metrics, &rerun.metrics, |a, b| same_metric(*a, *b)) {
+            return Verdict::Fail(format!("статистика {}", difference));
+        }
+
+        Verdict::Pass
+    }
+}
+
+/// Итоговая статистика прогона для манифеста.
+///
+/// # Arguments
+///
+/// * `last`: Статистика мира после последней итерации.
+/// * `summary`: Итоги прогона.
+///
+/// returns: Vec<(String, f64)>
+fn metrics(last: Option<&WorldStats>, summary: &RunSummary) -> Vec<(String, f64)> {
+    let mut metrics = vec![("iterations".to_string(), summary.iterations as f64)];
+
+    if let Some(stats) = last {
+        metrics.push(("plant_count".to_string(), stats.plant_count as f64));
+        metrics.push(("herbivore_count".to_string(), stats.animal_count.0 as f64));
+        metrics.push(("carnivore_count".to_string(), stats.animal_count.1 as f64));
+        metrics.push(("omnivore_count".to_string(), stats.animal_count.2 as f64));
+        metrics.push(("herbivore_max_generation".to_string(), stats.max_generation.0 as f64));
+        metrics.push(("carnivore_max_generation".to_string(), stats.max_generation.1 as f64));
+        metrics.push(("omnivore_max_generation".to_string(), stats.max_generation.2 as f64));
+    }
+
+    let species = [
+        ("herbivore", &summary.herbivore),
+        ("carnivore", &summary.carnivore),
+        ("omnivore", &summary.omnivore),
+    ];
+    for (name, species) in species {
+        metrics.push((format!("{}_peak_population", name), species.peak_population as f64));
+        metrics.push((format!("{}_eat_attempts", name), species.eat_attempts as f64));
+        metrics.push((format!("{}_eat_successes", name), species.eat_successes as f64));
+        metrics.push((format!("{}_eaten_energy", name), species.eaten_energy as f64));
+    }
+
+    metrics
+}
+
+/// Совпадают ли значения статистики с учетом допуска.
+fn same_metric(a: f64, b: f64) -> bool {
+    a == b || (a - b).abs() <= METRIC_TOLERANCE * a.abs().max(b.abs())
+}
+
+/// Находит первое расхождение двух списков значений по именам.
+///
+/// # Arguments
+///
+/// * `expected`: Записанные значения.
+/// * `actual`: Значения повтора.
+/// * `same`: Совпадают ли значения.
+///
+/// returns: Option<String> - описание первого расхождения.
+fn first_difference<T: fmt::Display>(
+    expected: &[(String, T)],
+    actual: &[(String, T)],
+    same: impl Fn(&T, &T) -> bool,
+) -> Option<String> {
+    for (name, value) in expected {
+        match actual.iter().find(|(other, _)| other == name) {
+            Some((_, other)) if same(value, other) => {}
+            Some((_, other)) => return Some(format!("{}: записано {}, получено {}", name, value, other)),
+            None => return Some(format!("{}: записано {}, в повторе отсутствует", name, value)),
+        }
+    }
+
+    actual.iter()
+        .find(|(name, _)| !expected.iter().any(|(other, _)| other == name))
+        .map(|(name, value)| format!("{}: в манифесте отсутствует, получено {}", name, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    /// Статистика короткого прогона тестового мира.
+    fn history(ticks: usize) -> Vec<WorldStats> {
+        let mut world = testing::populated_world(10, 10, (10, 4, 2));
+        (0..ticks)
+            .map(|_| {
+                world.tick();
+                world.get_world_stats()
+            })
+            .collect()
+    }
+
+    fn config() -> Vec<(String, String)> {
+        vec![("MAX_STEPS".to_string(), "1000".to_string())]
+    }
+
+    #[test]
+    fn text_round_trip_keeps_every_field() {
+        let manifest = RunManifest::new(7, config(), &history(20), Some(0));
+
+        let parsed = RunManifest::parse(&manifest.to_text()).unwrap();
+
+        assert_eq!(parsed.version, manifest.version);
+        assert_eq!(parsed.seed, 7);
+        assert_eq!(parsed.steps, 20);
+        assert!(parsed.degradation);
+        assert_eq!(parsed.not_reproducible, None);
+        assert_eq!(parsed.config, manifest.config);
+        assert_eq!(parsed.metrics, manifest.metrics);
+        assert!(matches!(manifest.verify(&parsed), Verdict::Pass));
+    }
+
+    #[test]
+    fn early_closed_run_records_its_steps() {
+        let manifest = RunManifest::new(7, config(), &history(5), None);
+
+        assert_eq!(manifest.steps, 5);
+        assert!(!manifest.degradation);
+    }
+
+    #[test]
+    fn degraded_run_is_not_reproducible() {
+        let manifest = RunManifest::new(7, config(), &history(5), Some(2));
+        let parsed = RunManifest::parse(&manifest.to_text()).unwrap();
+
+        assert!(parsed.not_reproducible.is_some());
+        assert!(matches!(parsed.verify(&manifest), Verdict::Fail(_)));
+    }
+
+    #[test]
+    fn different_statistics_fail_verification() {
+        let manifest = RunManifest::new(7, config(), &history(20), None);
+        let rerun = RunManifest::new(7, config(), &history(10), None);
+
+        assert!(matches!(manifest.verify(&rerun), Verdict::Fail(_)));
+    }
+
+    #[test]
+    fn manifest_without_seed_is_rejected() {
+        assert!(RunManifest::parse("version 0.1.0\nsteps 3\n").is_err());
+        assert!(RunManifest::parse("seed 1\ndegradation 2\n").is_err());
+    }
+}
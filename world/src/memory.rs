@@ -0,0 +1,158 @@
+//! Ограничение памяти, занимаемой долгими прогонами.
+//!
+//! Структуры, которые растут вместе с прогоном (замеры временного ряда, строки
+//! родословной и событий, ждущие записи, пул умерших животных), регистрируются как
+//! учетчики памяти. Когда приблизительный общий объем превышает бюджет, учетчиков по
+//! очереди просят освободить память.
+
+/// Учетчик памяти: структура, которая может приблизительно оценить занимаемую
+/// память и освободить ее часть.
+pub trait MemoryTracker {
+    /// Имя учетчика для журнала освобождения памяти.
+    fn tracker_name(&self) -> &'static str;
+
+    /// Приблизительный объем занимаемой памяти в байтах (количество записей,
+    /// умноженное на размер записи).
+    fn memory_usage(&self) -> usize;
+
+    /// Освобождает не меньше `bytes` байт, если это возможно, начиная с самых
+    /// старых записей.
+    ///
+    /// returns: usize - освобожденный объем в байтах.
+    fn shed_memory(&mut self, bytes: usize) -> usize;
+}
+
+/// Бюджет памяти.
+pub struct MemoryBudget {
+    // Бюджет в байтах, 0 - не ограничен.
+    budget: usize,
+}
+
+impl MemoryBudget {
+    /// Конструктор.
+    ///
+    /// # Arguments
+    ///
+    /// * `budget`: Бюджет в байтах, 0 - не ограничен.
+    ///
+    /// returns: MemoryBudget
+    pub fn new(budget: usize) -> MemoryBudget {
+        MemoryBudget { budget }
+    }
+
+    /// Проверяет бюджет и, если он превышен, просит учетчиков освободить память в
+    /// порядке их приоритета (первый учетчик освобождает память первым), пока
+    /// общий объем не уложится в бюджет.
+    ///
+    /// # Arguments
+    ///
+    /// * `trackers`: Учетчики памяти в порядке приоритета освобождения.
+    ///
+    /// returns: Vec<(&str, usize)> - имена учетчиков и освобожденный ими объем.
+    pub fn enforce(&self, trackers: &mut [&mut dyn MemoryTracker]) -> Vec<(&'static str, usize)> {
+        let mut shed = Vec::new();
+
+        if self.budget == 0 {
+            return shed;
+        }
+
+        let usage: usize = trackers.iter().map(|tracker| tracker.memory_usage()).sum();
+        let mut excess = usage.saturating_sub(self.budget);
+
+        for tracker in trackers.iter_mut() {
+            if excess == 0 {
+                break;
+            }
+
+            let freed = tracker.shed_memory(excess);
+            if freed > 0 {
+                shed.push((tracker.tracker_name(), freed));
+                excess = excess.saturating_sub(freed);
+            }
+        }
+
+        shed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// Учетчик из записей одинакового размера; запоминает в общем журнале, когда его
+    /// просили освободить память.
+    struct FakeTracker {
+        name: &'static str,
+        entries: usize,
+        entry_size: usize,
+        log: Rc<RefCell<Vec<&'static str>>>,
+    }
+
+    impl MemoryTracker for FakeTracker {
+        fn tracker_name(&self) -> &'static str {
+            self.name
+        }
+
+        fn memory_usage(&self) -> usize {
+            self.entries * self.entry_size
+        }
+
+        fn shed_memory(&mut self, bytes: usize) -> usize {
+            self.log.borrow_mut().push(self.name);
+            let count = bytes.div_ceil(self.entry_size).min(self.entries);
+            self.entries -= count;
+
+            count * self.entry_size
+        }
+    }
+
+    fn trackers(entries: [usize; 3], log: &Rc<RefCell<Vec<&'static str>>>) -> [FakeTracker; 3] {
+        let tracker = |name, entries| FakeTracker { name, entries, entry_size: 10, log: log.clone() };
+
+        [tracker("кадры", entries[0]), tracker("родословная", entries[1]), tracker("события", entries[2])]
+    }
+
+    fn usage(trackers: &[FakeTracker]) -> usize {
+        trackers.iter().map(|tracker| tracker.memory_usage()).sum()
+    }
+
+    #[test]
+    fn trackers_shed_in_priority_order_until_budget_is_met() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let [mut frames, mut lineage, mut events] = trackers([5, 10, 10], &log);
+
+        // 250 байт при бюджете 100: кадры отдают все 50 байт, родословная - остальные 100.
+        let shed = MemoryBudget::new(100).enforce(&mut [&mut frames, &mut lineage, &mut events]);
+
+        assert_eq!(shed, vec![("кадры", 50), ("родословная", 100)]);
+        assert_eq!(*log.borrow(), vec!["кадры", "родословная"]);
+        assert_eq!((frames.entries, lineage.entries, events.entries), (0, 0, 10));
+        assert!(usage(&[frames, lineage, events]) <= 100);
+    }
+
+    #[test]
+    fn shedding_stops_once_usage_is_under_budget() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let [mut frames, mut lineage, mut events] = trackers([20, 10, 10], &log);
+
+        let shed = MemoryBudget::new(300).enforce(&mut [&mut frames, &mut lineage, &mut events]);
+
+        // Лишние 100 байт освобождают одни кадры, остальных учетчиков не просят.
+        assert_eq!(shed, vec![("кадры", 100)]);
+        assert_eq!(*log.borrow(), vec!["кадры"]);
+        assert_eq!((frames.entries, lineage.entries, events.entries), (10, 10, 10));
+        assert_eq!(usage(&[frames, lineage, events]), 300);
+    }
+
+    #[test]
+    fn usage_within_budget_or_unlimited_budget_sheds_nothing() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let [mut frames, mut lineage, mut events] = trackers([10, 10, 10], &log);
+
+        assert!(MemoryBudget::new(300).enforce(&mut [&mut frames, &mut lineage, &mut events]).is_empty());
+        assert!(MemoryBudget::new(0).enforce(&mut [&mut frames, &mut lineage, &mut events]).is_empty());
+        assert!(log.borrow().is_empty());
+    }
+}
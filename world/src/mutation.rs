@@ -0,0 +1,138 @@
+//! Регулятор частоты мутаций. Когда генетическое разнообразие вида (среднее попарное
+//! расстояние между геномами) падает, эволюция останавливается; когда оно слишком
+//! велико, популяция не сходится. Регулятор после каждого замера разнообразия
+//! сдвигает частоту мутаций вида пропорционально отклонению разнообразия от целевого
+//! и удерживает ее в заданных границах.
+//!
+//! Новая частота зависит только от текущей частоты и замера, поэтому при одинаковой
+//! последовательности замеров последовательность частот одна и та же.
+
+use std::fmt;
+
+use crate::animal::AnimaType;
+use crate::errors::RecoverableError;
+
+/// Регулятор частоты мутаций. Создается конструктором `new`, который проверяет
+/// параметры.
+#[derive(Copy, Clone, Debug)]
+pub struct MutationController {
+    // Целевое генетическое разнообразие вида.
+    target_diversity: f64,
+    // Коэффициент: на сколько меняется частота мутаций на единицу отклонения
+    // разнообразия от целевого.
+    gain: f64,
+    // Границы частоты мутаций: минимальная и максимальная.
+    bounds: (f64, f64),
+}
+
+/// Изменение частоты мутаций вида регулятором.
+#[derive(Copy, Clone)]
+pub struct MutationAdjustment {
+    // Итерация, на которой частота изменена.
+    pub iteration: usize,
+    // Вид животных.
+    pub animal_type: AnimaType,
+    // Замер генетического разнообразия, по которому изменена частота.
+    pub diversity: f64,
+    // Прежняя частота мутаций.
+    pub from: f64,
+    // Новая частота мутаций.
+    pub to: f64,
+}
+
+impl MutationController {
+    /// Конструктор. Параметры должны быть конечными числами, границы частоты -
+    /// неотрицательными, нижняя граница - не больше верхней.
+    ///
+    /// # Arguments
+    ///
+    /// * `target_diversity`: Целевое генетическое разнообразие вида.
+    /// * `gain`: Изменение частоты мутаций на единицу отклонения разнообразия от целевого.
+    /// * `bounds`: Минимальная и максимальная частота мутаций.
+    ///
+    /// returns: Result<MutationController, RecoverableError>
+    pub fn new(
+        target_diversity: f64,
+        gain: f64,
+        bounds: (f64, f64)
+    ) -> Result<MutationController, RecoverableError> {
+        if !target_diversity.is_finite() || !gain.is_finite() {
+            return Err(RecoverableError::new(fmt::format(format_args!(
+                "Параметры регулятора мутаций должны быть конечными числами, задано: разнообразие {}, коэффициент {}",
+                target_diversity, gain
+            ))));
+        }
+
+        let (min, max) = bounds;
+        if !min.is_finite() || !max.is_finite() || min < 0.0 || min > max {
+            return Err(RecoverableError::new(fmt::format(format_args!(
+                "Границы частоты мутаций должны удовлетворять 0 <= min <= max, задано ({}, {})", min, max
+            ))));
+        }
+
+        Ok(MutationController { target_diversity, gain, bounds })
+    }
+
+    /// Вычисляет новую частоту мутаций: при разнообразии ниже целевого частота растет,
+    /// выше целевого - убывает. Результат не выходит за границы регулятора.
+    ///
+    /// # Arguments
+    ///
+    /// * `rate`: Текущая частота мутаций.
+    /// * `diversity`: Замер генетического разнообразия вида.
+    ///
+    /// returns: f64
+    pub fn next_rate(&self, rate: f64, diversity: f64) -> f64 {
+        let rate = rate + self.gain * (self.target_diversity - diversity);
+
+        rate.clamp(self.bounds.0, self.bounds.1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_moves_toward_target_diversity() {
+        let controller = MutationController::new(0.5, 2.0, (0.1, 3.0)).unwrap();
+
+        // Разнообразие ниже целевого - частота растет, выше - убывает, на цели - не меняется.
+        assert!((controller.next_rate(1.0, 0.25) - 1.5).abs() < 1e-9);
+        assert!((controller.next_rate(1.0, 0.75) - 0.5).abs() < 1e-9);
+        assert_eq!(controller.next_rate(1.0, 0.5), 1.0);
+    }
+
+    #[test]
+    fn rate_stays_within_bounds() {
+        let controller = MutationController::new(0.5, 10.0, (0.1, 3.0)).unwrap();
+
+        assert_eq!(controller.next_rate(1.0, 0.0), 3.0);
+        assert_eq!(controller.next_rate(1.0, 1.0), 0.1);
+        assert_eq!(controller.next_rate(3.0, 0.0), 3.0);
+    }
+
+    #[test]
+    fn same_samples_give_same_rates() {
+        let controller = MutationController::new(0.4, 1.5, (0.0, 5.0)).unwrap();
+        let samples = [0.1, 0.9, 0.35, 0.4, 0.05];
+
+        let run = || samples.iter().scan(1.0, |rate, &diversity| {
+            *rate = controller.next_rate(*rate, diversity);
+            Some(*rate)
+        }).collect::<Vec<_>>();
+
+        assert_eq!(run(), run());
+    }
+
+    #[test]
+    fn invalid_parameters_are_rejected() {
+        assert!(MutationController::new(0.5, 1.0, (2.0, 1.0)).is_err());
+        assert!(MutationController::new(0.5, 1.0, (f64::NAN, 1.0)).is_err());
+        assert!(MutationController::new(0.5, 1.0, (0.0, f64::NAN)).is_err());
+        assert!(MutationController::new(0.5, 1.0, (-1.0, 1.0)).is_err());
+        assert!(MutationController::new(f64::NAN, 1.0, (0.0, 1.0)).is_err());
+        assert!(MutationController::new(0.5, f64::INFINITY, (0.0, 1.0)).is_err());
+        assert!(MutationController::new(0.5, 1.0, (1.0, 1.0)).is_ok());
+    }
+}
@@ -1,3 +1,5 @@
+use std::fmt;
+use crate::errors::RecoverableError;
 use crate::landscape::Energy;
 
 pub mod simple;
@@ -10,13 +12,81 @@ pub enum PlantAction {
     Reproduce,  // Размножиться
 }
 
+/// Сколько энергии отдает растение за один укус.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum BiteMode {
+    /// Фиксированный укус: `eaten_energy`.
+    Fixed,
+    /// Укус пропорционален текущей энергии, но не больше фиксированного:
+    /// `min(eaten_energy, fraction * energy)`.
+    Capped(f64),
+    /// Укус пропорционален текущей энергии: `fraction * energy`.
+    Fraction(f64),
+}
+
+impl BiteMode {
+    /// Проверяет способ вычисления укуса: доля энергии должна принадлежать
+    /// диапазону (0, 1]. Нулевая доля не позволяет съесть растение, а доля больше
+    /// единицы - отдает больше энергии, чем есть у растения.
+    ///
+    /// returns: Result<(), RecoverableError>
+    pub fn validate(&self) -> Result<(), RecoverableError> {
+        match *self {
+            BiteMode::Fixed => Ok(()),
+            BiteMode::Capped(fraction) | BiteMode::Fraction(fraction) => {
+                if fraction > 0.0 && fraction <= 1.0 {
+                    Ok(())
+                } else {
+                    Err(RecoverableError::new(fmt::format(format_args!(
+                        "Доля энергии укуса растения должна принадлежать диапазону (0, 1], задано {}", fraction
+                    ))))
+                }
+            }
+        }
+    }
+}
+
+/// Состояние растения, которое не задается параметрами нового растения и
+/// сохраняется в контрольной точке мира (см. `Landscape::to_checkpoint`).
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct PlantState {
+    /// Возраст в итерациях.
+    pub age: usize,
+    /// Сколько итераций осталось до возобновления роста съеденного растения.
+    pub regrowth_cooldown: usize,
+    /// Запрет на размножение.
+    pub no_repro: bool,
+}
+
 /// Типаж, определяющий растение.
-pub trait PlantAlive {
+pub trait PlantAlive: Send {
     // Методы получения состояния растения.
 
     /// Полностью ли съедено растение?
     fn is_eaten(&self) -> bool;
 
+    /// Возвращает текущую энергию растения.
+    fn get_energy(&self) -> Energy;
+
+    /// Возвращает возраст растения в итерациях.
+    fn get_age(&self) -> usize;
+
+    /// Задает энергию растения (при заселении мира). Энергия ограничивается
+    /// диапазоном [0, максимальная энергия].
+    fn set_energy(&mut self, energy: Energy);
+
+    /// Возвращает состояние растения для контрольной точки мира.
+    fn get_state(&self) -> PlantState;
+
+    /// Восстанавливает состояние растения из контрольной точки мира.
+    fn set_state(&mut self, state: &PlantState);
+
+    /// Прошла еще одна итерация мира. Мир вызывает метод для каждого растения ровно
+    /// один раз за итерацию перед методом action (даже на итерациях, когда действие
+    /// растения не запрашивается, см. `Landscape::set_plant_update_interval`), что
+    /// позволяет растению вести собственный счет времени (возраст, периоды покоя и т.д.).
+    fn tick_passed(&mut self) {}
+
     // Метод Action
 
     /// Действие растения.
@@ -34,7 +104,8 @@ pub trait PlantAlive {
     /// на планете.
     fn grow_action(&mut self, energy: Energy);
 
-    /// Растение может размножаться.
+    /// Растение может размножаться. Метод создает одно семечко; за одно размножение
+    /// мир может вызвать метод несколько раз.
     fn reproduce_action(&mut self) -> Box<dyn PlantAlive>;
 
     /// Действие - "нет действия". Поскольку поддержание состояния требует
@@ -45,4 +116,21 @@ pub trait PlantAlive {
 
     /// Растение может поедаться.
     fn be_eaten(&mut self) -> Energy;
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bite_fraction_must_be_in_unit_interval() {
+        assert!(BiteMode::Fixed.validate().is_ok());
+        assert!(BiteMode::Capped(0.5).validate().is_ok());
+        assert!(BiteMode::Fraction(1.0).validate().is_ok());
+
+        for fraction in [0.0, -0.1, 1.5, f64::NAN, f64::INFINITY] {
+            assert!(BiteMode::Capped(fraction).validate().is_err(), "Capped({})", fraction);
+            assert!(BiteMode::Fraction(fraction).validate().is_err(), "Fraction({})", fraction);
+        }
+    }
+}
@@ -2,8 +2,11 @@
 //! Растение не должно знать свои координаты, т.е. где оно выросло. В месте с тем,
 //! растение должно хранить энергию, которую оно может отдать при поедании его животным.
 
+use std::fmt;
+
+use crate::errors::RecoverableError;
 use crate::landscape::Energy;
-use crate::plant::{PlantAction, PlantAlive};
+use crate::plant::{BiteMode, PlantAction, PlantAlive, PlantState};
 
 /// Структура, описывающая растение.
 pub struct Plant {
@@ -17,11 +20,57 @@ pub struct Plant {
     // Энергия которую отдают растения когда их поедают животные.
     eaten_energy: Energy,
 
+    // Способ вычисления энергии, которую растение отдает за один укус.
+    bite_mode: BiteMode,
+
     //
     reproduce_energy_rate: f64,
 
     // Параметр определяющий может ли растение размножаться или нет.
-    no_repro: bool
+    no_repro: bool,
+
+    // Количество итераций, в течение которых полностью съеденное растение не растет.
+    regrowth_delay: usize,
+
+    // Энергия, которую растение тратит на каждое семечко.
+    seed_energy_cost: Energy,
+
+    // Сколько итераций еще осталось до возобновления роста.
+    regrowth_cooldown: usize,
+
+    // Возраст растения в итерациях.
+    age: usize,
+}
+
+/// Параметры нового растения.
+#[derive(Copy, Clone, Debug)]
+pub struct PlantParams {
+    /// Текущая энергия растения.
+    pub energy: Energy,
+    /// Максимально возможная энергия растения.
+    pub max_energy: Energy,
+    /// Сколько энергии отдает растение за раз при его поедании.
+    pub eaten_energy: Energy,
+    /// Способ вычисления энергии укуса (по умолчанию фиксированный).
+    pub bite_mode: BiteMode,
+    /// Критерий готовности к размножению.
+    pub reproduce_energy_rate: f64,
+    /// Запрещает размножение.
+    pub no_repro: bool,
+    /// Количество итераций, в течение которых полностью съеденное растение не растет.
+    pub regrowth_delay: usize,
+    /// Энергия, которую растение тратит на каждое семечко.
+    pub seed_energy_cost: Energy,
+}
+
+impl PlantParams {
+    /// Проверяет параметры растения.
+    ///
+    /// returns: Result<(), RecoverableError> - ошибка, если способ вычисления укуса
+    /// задан неверно (см. `BiteMode::validate`).
+    pub fn validate(&self) -> Result<(), RecoverableError> {
+        self.bite_mode.validate()
+    }
 }
 
 impl Plant {
@@ -30,27 +79,48 @@ impl Plant {
     ///
     /// # Arguments
     ///
-    /// * `energy`: Текущая энергия растения.
-    /// * `max_energy`: Максимально возможная энергия растения.
-    /// * `eaten_energy`: Сколько энергии отдает растение за раз при его поедании.
-    /// * `reproduce_energy_rate`: Критерий готовности к размножению.
-    /// * `no_repro`: Запрещает размножение.
+    /// * `params`: Параметры растения.
     ///
-    /// returns: Box<Plant>
-    pub fn new(
-        energy: Energy,
-        max_energy: Energy,
-        eaten_energy: Energy,
-        reproduce_energy_rate: f64,
-        no_repro: bool
-    ) -> Box<Plant> {
-        Box::new(Plant {
-            energy,
-            max_energy,
-            eaten_energy,
-            reproduce_energy_rate,
-            no_repro,
-        })
+    /// returns: Result<Box<Plant>, RecoverableError> - ошибка, если параметры неверны
+    /// (см. `PlantParams::validate`).
+    pub fn new(params: PlantParams) -> Result<Box<Plant>, RecoverableError> {
+        params.validate().map_err(|e| RecoverableError::new(fmt::format(format_args!(
+            "Неверные параметры растения: {}", e
+        ))))?;
+
+        Ok(Box::new(Plant {
+            energy: params.energy,
+            max_energy: params.max_energy,
+            eaten_energy: params.eaten_energy,
+            bite_mode: params.bite_mode,
+            reproduce_energy_rate: params.reproduce_energy_rate,
+            no_repro: params.no_repro,
+            regrowth_delay: params.regrowth_delay,
+            seed_energy_cost: params.seed_energy_cost,
+            regrowth_cooldown: 0,
+            age: 0,
+        }))
+    }
+}
+
+impl Plant {
+    /// Энергия одного укуса в соответствии со способом вычисления укуса.
+    fn bite(&self) -> Energy {
+        match self.bite_mode {
+            BiteMode::Fixed => self.eaten_energy,
+            BiteMode::Capped(fraction) => {
+                self.eaten_energy.min((fraction * self.energy as f64) as Energy)
+            }
+            BiteMode::Fraction(fraction) => (fraction * self.energy as f64) as Energy,
+        }
+    }
+
+    /// Проверяет, что энергия растения - конечное число (только в отладочной сборке).
+    fn check_energy(&self) {
+        debug_assert!(
+            self.energy.is_finite(),
+            "Энергия растения не является конечным числом: {}", self.energy
+        );
     }
 }
 
@@ -66,6 +136,43 @@ impl PlantAlive for Plant {
         false
     }
 
+    /// Текущая энергия растения.
+    fn get_energy(&self) -> Energy {
+        self.energy
+    }
+
+    fn set_energy(&mut self, energy: Energy) {
+        self.energy = energy.clamp(0 as Energy, self.max_energy);
+    }
+
+    /// Возраст растения.
+    fn get_age(&self) -> usize {
+        self.age
+    }
+
+    fn get_state(&self) -> PlantState {
+        PlantState {
+            age: self.age,
+            regrowth_cooldown: self.regrowth_cooldown,
+            no_repro: self.no_repro,
+        }
+    }
+
+    fn set_state(&mut self, state: &PlantState) {
+        self.age = state.age;
+        self.regrowth_cooldown = state.regrowth_cooldown;
+        self.no_repro = state.no_repro;
+    }
+
+    /// Растение стареет, съеденное растение отсчитывает время до возобновления роста.
+    fn tick_passed(&mut self) {
+        self.age += 1;
+
+        if self.regrowth_cooldown > 0 {
+            self.regrowth_cooldown -= 1;
+        }
+    }
+
     // Метод Action
 
     /// Действие растения.
@@ -77,6 +184,11 @@ impl PlantAlive for Plant {
         }
 
 
+        // Съеденное растение еще не восстановилось.
+        if self.regrowth_cooldown > 0 {
+            return PlantAction::None;
+        }
+
         return if self.energy < self.max_energy {
             PlantAction::Grow
         } else {
@@ -93,16 +205,29 @@ impl PlantAlive for Plant {
         if self.energy > self.max_energy {
             self.energy = self.max_energy;
         }
+        self.check_energy();
     }
 
     /// Действие "размножение растения".
     fn reproduce_action(&mut self) -> Box<dyn PlantAlive> {
+        // Каждое семечко стоит растению энергии.
+        self.energy -= self.seed_energy_cost;
+        if self.energy < 0 as Energy {
+            self.energy = 0 as Energy;
+        }
+        self.check_energy();
+
         Box::new(Plant {
             energy: 0 as Energy, // Семечко не имеет энергии и должно прорасти в растение.
             max_energy: self.max_energy,
             eaten_energy: self.eaten_energy,
+            bite_mode: self.bite_mode,
             reproduce_energy_rate: self.reproduce_energy_rate,
-            no_repro: false
+            no_repro: false,
+            regrowth_delay: self.regrowth_delay,
+            seed_energy_cost: self.seed_energy_cost,
+            regrowth_cooldown: 0,
+            age: 0,
         })
     }
 
@@ -115,15 +240,70 @@ impl PlantAlive for Plant {
 
     /// Поедание растения.
     fn be_eaten(&mut self) -> Energy {
-        if self.eaten_energy > self.energy {
-            self.energy -= self.eaten_energy;
+        let bite = self.bite();
+
+        // Укус меньше оставшейся энергии: растение теряет укус. Иначе животное
+        // получает остаток, и растение съедено полностью.
+        if bite < self.energy {
+            self.energy -= bite;
+            self.check_energy();
 
-            self.eaten_energy
+            bite
         } else {
             let rest = self.energy;
             self.energy = 0 as Energy;
 
+            // Растение съедено полностью и какое-то время не будет расти.
+            self.regrowth_cooldown = self.regrowth_delay;
+
             rest
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    /// Растение с заданной энергией и фиксированным укусом.
+    fn plant(energy: Energy, eaten_energy: Energy) -> Box<Plant> {
+        Plant::new(PlantParams { energy, eaten_energy, bite_mode: BiteMode::Fixed, ..testing::plant_params() }).unwrap()
+    }
+
+    #[test]
+    fn bite_never_exceeds_remaining_energy() {
+        // Энергия растения больше, равна и меньше укуса.
+        let cases = [(10.0, 4.0, 4.0, 6.0), (4.0, 4.0, 4.0, 0.0), (3.0, 4.0, 3.0, 0.0)];
+
+        for (energy, eaten_energy, bite, rest) in cases {
+            let mut plant = plant(energy, eaten_energy);
+
+            assert_eq!(plant.be_eaten(), bite, "энергия {}, укус {}", energy, eaten_energy);
+            assert_eq!(plant.get_energy(), rest, "энергия {}, укус {}", energy, eaten_energy);
+            assert_eq!(plant.is_eaten(), rest == 0.0);
+        }
+    }
+
+    #[test]
+    fn repeated_bites_eat_plant_exactly() {
+        let mut plant = plant(10.0, 4.0);
+
+        let mut bites = Vec::new();
+        while !plant.is_eaten() {
+            bites.push(plant.be_eaten());
+        }
+
+        assert_eq!(bites, vec![4.0, 4.0, 2.0]);
+        assert_eq!(plant.get_energy(), 0.0);
+    }
+
+    #[test]
+    fn invalid_bite_mode_is_an_error() {
+        let error = Plant::new(PlantParams { bite_mode: BiteMode::Fraction(1.5), ..testing::plant_params() }).err().unwrap();
+
+        assert_eq!(
+            error.to_string(),
+            "Неверные параметры растения: Доля энергии укуса растения должна принадлежать диапазону (0, 1], задано 1.5"
+        );
+    }
+}
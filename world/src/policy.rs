@@ -0,0 +1,112 @@
+//! Правила мира, которые проверяют действие, выбранное мозгом животного, до его
+//! выполнения: правило может запретить действие или заменить его другим. Правила
+//! задаются цепочкой (`Landscape::add_action_policy`) и применяются по порядку,
+//! каждое следующее правило получает действие, оставленное предыдущими.
+
+use crate::animal::{AnimalAction, AnimalAlive, AnimalInputSignal, AnimaType};
+
+/// Максимальное количество правил в цепочке (по количеству счетчиков запретов
+/// в статистике мира).
+pub const MAX_ACTION_POLICIES: usize = 8;
+
+/// Обстановка, в которой правило проверяет действие.
+#[derive(Copy, Clone)]
+pub struct PolicyCtx {
+    /// Номер итерации (начиная с единицы, как в `WorldStats`).
+    pub tick: usize,
+    /// Положение животного.
+    pub at: (usize, usize),
+}
+
+/// Правило мира для действий животных.
+pub trait ActionPolicy: Send {
+    /// Имя правила (для статистики и сообщений).
+    fn name(&self) -> &'static str;
+
+    /// Проверяет действие животного.
+    ///
+    /// # Arguments
+    ///
+    /// * `animal`: Животное.
+    /// * `inputs`: Входные сигналы, которые животное восприняло на текущем ходу.
+    /// * `action`: Действие, выбранное мозгом или оставленное предыдущими правилами.
+    /// * `ctx`: Обстановка.
+    ///
+    /// returns: AnimalAction - действие, которое следует выполнить; если оно
+    /// отличается от `action`, правило считается запретившим действие.
+    fn review(
+        &self,
+        animal: &dyn AnimalAlive,
+        inputs: &AnimalInputSignal,
+        action: AnimalAction,
+        ctx: &PolicyCtx
+    ) -> AnimalAction;
+}
+
+/// Животное может размножиться, только если в его области близости достаточно
+/// еды: травоядным - растений, хищникам - травоядных. Правило не дает популяции
+/// размножаться навстречу голоду; животное, не прошедшее проверку, бездействует.
+/// Еда всеядных - и растения, и травоядные.
+pub struct ReproductionFoodPolicy {
+    // Минимальное количество еды поблизости: травоядные, хищники, всеядные. 0 - без проверки.
+    min_food: (usize, usize, usize),
+}
+
+impl ReproductionFoodPolicy {
+    /// Имя правила (см. `ActionPolicy::name`).
+    pub const NAME: &'static str = "reproduce_min_food";
+
+    /// Конструктор.
+    ///
+    /// # Arguments
+    ///
+    /// * `herbivore`: Порог для травоядных, 0 - без проверки.
+    /// * `carnivore`: Порог для хищников, 0 - без проверки.
+    ///
+    /// returns: ReproductionFoodPolicy
+    pub fn new(herbivore: usize, carnivore: usize) -> ReproductionFoodPolicy {
+        ReproductionFoodPolicy { min_food: (herbivore, carnivore, 0) }
+    }
+
+    /// Задает порог для всеядных.
+    ///
+    /// # Arguments
+    ///
+    /// * `omnivore`: Порог для всеядных (растения и травоядные вместе), 0 - без проверки.
+    ///
+    /// returns: ReproductionFoodPolicy
+    pub fn with_omnivore(mut self, omnivore: usize) -> ReproductionFoodPolicy {
+        self.min_food.2 = omnivore;
+        self
+    }
+}
+
+impl ActionPolicy for ReproductionFoodPolicy {
+    fn name(&self) -> &'static str {
+        ReproductionFoodPolicy::NAME
+    }
+
+    fn review(
+        &self,
+        animal: &dyn AnimalAlive,
+        inputs: &AnimalInputSignal,
+        action: AnimalAction,
+        _ctx: &PolicyCtx
+    ) -> AnimalAction {
+        if action != AnimalAction::Reproduce {
+            return action;
+        }
+
+        let (food, min_food) = match animal.get_type() {
+            AnimaType::Herbivore => (inputs.plant_proximity, self.min_food.0),
+            AnimaType::Carnivore => (inputs.herbivore_proximity, self.min_food.1),
+            AnimaType::Omnivore => (inputs.plant_proximity + inputs.herbivore_proximity, self.min_food.2),
+        };
+
+        if food < min_food {
+            AnimalAction::None
+        } else {
+            action
+        }
+    }
+}
@@ -0,0 +1,262 @@
+//! Запись временного ряда численности видов для прогонов без отображения: мир
+//! замеряется раз в несколько итераций, ряд хранится в памяти и сохраняется в CSV
+//! файл (одна строка на замер). Редкие замеры ограничивают память длинных прогонов;
+//! если бюджет памяти превышен, самые старые замеры отбрасываются.
+
+use std::fmt;
+use std::fs;
+use std::mem;
+
+use crate::animal::AnimaType;
+use crate::errors::RecoverableError;
+use crate::landscape::{Energy, Landscape};
+use crate::memory::MemoryTracker;
+
+/// Столбцы CSV файла временного ряда (в порядке записи).
+const COLUMNS: [&str; 17] = [
+    "iteration",
+    "plants",
+    "herbivores",
+    "carnivores",
+    "omnivores",
+    "herbivore_births",
+    "carnivore_births",
+    "omnivore_births",
+    "herbivore_deaths",
+    "carnivore_deaths",
+    "omnivore_deaths",
+    "herbivore_max_generation",
+    "carnivore_max_generation",
+    "omnivore_max_generation",
+    "herbivore_mean_energy",
+    "carnivore_mean_energy",
+    "omnivore_mean_energy",
+];
+
+/// Замер мира. Кортежи: первый элемент - травоядные, второй хищники, третий всеядные.
+#[derive(Copy, Clone)]
+pub struct Sample {
+    // Номер итерации (начиная с единицы).
+    pub iteration: usize,
+    // Количество растений.
+    pub plants: usize,
+    // Количество живых животных.
+    pub animals: (usize, usize, usize),
+    // Количество рождений с начала прогона.
+    pub births: (usize, usize, usize),
+    // Количество смертей с начала прогона.
+    pub deaths: (usize, usize, usize),
+    // Максимальное поколение с начала прогона.
+    pub max_generation: (usize, usize, usize),
+    // Средняя энергия живых животных. None - живых животных вида нет.
+    pub mean_energy: (Option<Energy>, Option<Energy>, Option<Energy>),
+}
+
+/// Запись временного ряда мира.
+pub struct Recorder {
+    // Период замеров в итерациях.
+    every: usize,
+    // Замеры в порядке итераций.
+    samples: Vec<Sample>,
+}
+
+impl Recorder {
+    /// Конструктор.
+    ///
+    /// # Arguments
+    ///
+    /// * `every`: Период замеров в итерациях (0 и 1 - каждая итерация).
+    ///
+    /// returns: Recorder
+    pub fn new(every: usize) -> Recorder {
+        Recorder {
+            every: every.max(1),
+            samples: Vec::new(),
+        }
+    }
+
+    /// Замеряет мир после итерации, если итерация входит в период замеров.
+    ///
+    /// # Arguments
+    ///
+    /// * `world`: Мир.
+    ///
+    /// returns: ()
+    pub fn sample(&mut self, world: &Landscape) {
+        let stats = world.get_statistics();
+        if !stats.tick.is_multiple_of(self.every) {
+            return;
+        }
+
+        // Суммарная энергия и количество живых животных по видам.
+        let mut energy = [(0 as Energy, 0usize); 3];
+        for (_, _, animal) in world.animals_iter() {
+            let species = match animal.species {
+                AnimaType::Herbivore => &mut energy[0],
+                AnimaType::Carnivore => &mut energy[1],
+                AnimaType::Omnivore => &mut energy[2],
+            };
+            species.0 += animal.energy;
+            species.1 += 1;
+        }
+        let mean = |(sum, count): (Energy, usize)| (count > 0).then(|| sum / count as Energy);
+
+        self.samples.push(Sample {
+            iteration: stats.tick,
+            plants: stats.plant_count,
+            animals: stats.animal_count,
+            births: stats.births,
+            deaths: stats.deaths,
+            max_generation: stats.max_generation,
+            mean_energy: (mean(energy[0]), mean(energy[1]), mean(energy[2])),
+        });
+    }
+
+    /// Замеры в порядке итераций.
+    pub fn samples(&self) -> &[Sample] {
+        &self.samples
+    }
+
+    /// Представляет временной ряд в виде CSV.
+    ///
+    /// returns: String
+    pub fn to_csv(&self) -> String {
+        let mut csv = COLUMNS.join(",");
+        csv.push('\n');
+
+        // Пропуск - живых животных вида нет.
+        let energy = |value: Option<Energy>| value.map_or(String::new(), |value| value.to_string());
+
+        for sample in &self.samples {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
+                sample.iteration,
+                sample.plants,
+                sample.animals.0,
+                sample.animals.1,
+                sample.animals.2,
+                sample.births.0,
+                sample.births.1,
+                sample.births.2,
+                sample.deaths.0,
+                sample.deaths.1,
+                sample.deaths.2,
+                sample.max_generation.0,
+                sample.max_generation.1,
+                sample.max_generation.2,
+                energy(sample.mean_energy.0),
+                energy(sample.mean_energy.1),
+                energy(sample.mean_energy.2)
+            ));
+        }
+
+        csv
+    }
+
+    /// Сохраняет временной ряд в CSV файл.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: Путь к файлу.
+    ///
+    /// returns: Result<(), RecoverableError>
+    pub fn write_csv(&self, path: &str) -> Result<(), RecoverableError> {
+        fs::write(path, self.to_csv()).map_err(|e| RecoverableError::new(fmt::format(format_args!(
+            "Не удалось сохранить временной ряд в файл {}: {}", path, e
+        ))))
+    }
+}
+
+impl MemoryTracker for Recorder {
+    fn tracker_name(&self) -> &'static str {
+        "замеры временного ряда"
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.samples.len() * mem::size_of::<Sample>()
+    }
+
+    fn shed_memory(&mut self, bytes: usize) -> usize {
+        let count = bytes.div_ceil(mem::size_of::<Sample>()).min(self.samples.len());
+        self.samples.drain(..count);
+
+        count * mem::size_of::<Sample>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn csv_rows_follow_period_and_last_row_matches_statistics() {
+        let mut world = testing::populated_world(20, 20, (30, 8, 3));
+        let mut recorder = Recorder::new(10);
+        for _ in 0..100 {
+            world.tick();
+            recorder.sample(&world);
+        }
+
+        let csv = recorder.to_csv();
+        let rows: Vec<&str> = csv.lines().collect();
+        // Заголовок и по строке на каждую десятую итерацию.
+        assert_eq!(rows.len(), 1 + 10);
+        assert_eq!(rows[0], COLUMNS.join(","));
+
+        let stats = world.get_statistics();
+        let last: Vec<&str> = rows[rows.len() - 1].split(',').collect();
+        assert_eq!(last.len(), COLUMNS.len());
+        let expected = [
+            stats.tick,
+            stats.plant_count,
+            stats.animal_count.0,
+            stats.animal_count.1,
+            stats.animal_count.2,
+            stats.births.0,
+            stats.births.1,
+            stats.births.2,
+            stats.deaths.0,
+            stats.deaths.1,
+            stats.deaths.2,
+            stats.max_generation.0,
+            stats.max_generation.1,
+            stats.max_generation.2,
+        ];
+        for (column, value) in expected.iter().enumerate() {
+            assert_eq!(last[column], value.to_string(), "столбец {}", COLUMNS[column]);
+        }
+    }
+
+    #[test]
+    fn zero_period_samples_every_tick() {
+        let mut world = testing::populated_world(10, 10, (10, 2, 1));
+        let mut recorder = Recorder::new(0);
+        for _ in 0..5 {
+            world.tick();
+            recorder.sample(&world);
+        }
+
+        let iterations: Vec<usize> = recorder.samples().iter().map(|s| s.iteration).collect();
+        assert_eq!(iterations, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn shedding_drops_oldest_samples() {
+        let mut world = testing::populated_world(10, 10, (10, 2, 1));
+        let mut recorder = Recorder::new(1);
+        for _ in 0..10 {
+            world.tick();
+            recorder.sample(&world);
+        }
+
+        let sample = mem::size_of::<Sample>();
+        assert_eq!(recorder.memory_usage(), 10 * sample);
+        assert_eq!(recorder.shed_memory(3 * sample - 1), 3 * sample);
+
+        let iterations: Vec<usize> = recorder.samples().iter().map(|s| s.iteration).collect();
+        assert_eq!(iterations, (4..=10).collect::<Vec<usize>>());
+        assert_eq!(recorder.shed_memory(100 * sample), 7 * sample);
+        assert!(recorder.samples().is_empty());
+    }
+}
@@ -0,0 +1,130 @@
+//! Генератор случайных чисел, состояние которого можно сохранить в контрольной
+//! точке мира и восстановить (см. `Landscape::to_checkpoint`): продолжение прогона
+//! из контрольной точки совпадает с прогоном без остановки.
+
+use std::fmt;
+use std::str::FromStr;
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
+
+/// Генератор случайных чисел мира и мозгов животных. Алгоритм тот же, что и у
+/// `rand::rngs::StdRng` (ChaCha12), но состояние генератора доступно.
+pub type CheckpointRng = ChaCha12Rng;
+
+/// Состояние генератора: зерно, номер потока и позиция в потоке (в 32-битных словах).
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct RngState {
+    seed: [u8; 32],
+    stream: u64,
+    word_pos: u128,
+}
+
+impl RngState {
+    /// Снимает состояние генератора.
+    ///
+    /// # Arguments
+    ///
+    /// * `rng`: Генератор.
+    ///
+    /// returns: RngState
+    pub fn of(rng: &CheckpointRng) -> RngState {
+        RngState {
+            seed: rng.get_seed(),
+            stream: rng.get_stream(),
+            word_pos: rng.get_word_pos(),
+        }
+    }
+
+    /// Создает генератор в сохраненном состоянии: он выдает те же числа, что выдал
+    /// бы генератор, с которого снято состояние.
+    ///
+    /// returns: CheckpointRng
+    pub fn restore(&self) -> CheckpointRng {
+        let mut rng = CheckpointRng::from_seed(self.seed);
+        rng.set_stream(self.stream);
+        rng.set_word_pos(self.word_pos);
+
+        rng
+    }
+}
+
+/// Текстовый вид состояния: `<зерно в шестнадцатеричном виде> <поток> <позиция>`.
+impl fmt::Display for RngState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in &self.seed {
+            write!(f, "{:02x}", byte)?;
+        }
+
+        write!(f, " {} {}", self.stream, self.word_pos)
+    }
+}
+
+impl FromStr for RngState {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let error = || format!("неверное состояние генератора \"{}\"", text);
+
+        let fields: Vec<&str> = text.split_whitespace().collect();
+        let (seed_hex, stream, word_pos) = match fields.as_slice() {
+            [seed, stream, word_pos] => (*seed, *stream, *word_pos),
+            _ => return Err(error()),
+        };
+
+        if seed_hex.len() != 64 || !seed_hex.is_ascii() {
+            return Err(error());
+        }
+        let mut seed = [0u8; 32];
+        for (index, byte) in seed.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&seed_hex[2 * index..2 * index + 2], 16).map_err(|_| error())?;
+        }
+
+        Ok(RngState {
+            seed,
+            stream: stream.parse().map_err(|_| error())?,
+            word_pos: word_pos.parse().map_err(|_| error())?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, RngCore};
+
+    #[test]
+    fn restored_rng_continues_the_sequence() {
+        let mut rng = CheckpointRng::seed_from_u64(42);
+        // Нечетное количество слов: позиция приходится на середину 64-битного числа.
+        rng.next_u32();
+        let _: f64 = rng.gen();
+
+        let mut restored = RngState::of(&rng).restore();
+        let expected: Vec<u64> = (0..100).map(|_| rng.next_u64()).collect();
+        let actual: Vec<u64> = (0..100).map(|_| restored.next_u64()).collect();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn state_survives_text_round_trip() {
+        let mut rng = CheckpointRng::seed_from_u64(7);
+        rng.set_stream(3);
+        rng.fill_bytes(&mut [0u8; 100]);
+        let state = RngState::of(&rng);
+
+        assert_eq!(state.to_string().parse::<RngState>(), Ok(state));
+        assert!("00 1 2".parse::<RngState>().is_err());
+        assert!(format!("{} 1", "zz".repeat(32)).parse::<RngState>().is_err());
+        assert!(format!("{} 1", "00".repeat(32)).parse::<RngState>().is_err());
+    }
+
+    #[test]
+    fn same_sequence_as_std_rng() {
+        let mut rng = CheckpointRng::seed_from_u64(5);
+        let mut std_rng = StdRng::seed_from_u64(5);
+
+        assert_eq!(rng.next_u64(), std_rng.next_u64());
+    }
+}
@@ -0,0 +1,641 @@
+//! Цикл прогона мира: итерации, управляющие сообщения отображения, отправка кадров и
+//! сбор статистики. Цикл общий для работы с окном и без него; окно, канал управления
+//! и часы передаются через типажи, поэтому их можно заменить (например, при выгрузке
+//! кадров в файл).
+
+use std::sync::mpsc::{Receiver, Sender, TryRecvError};
+use std::time::Instant;
+
+use display::{Annotation, ControlMessage, Frame, FrameRequest, HistoryResponse, PerceptionFootprint};
+
+use crate::config::RECENT_HISTORY;
+use crate::deadline::{CheapMode, DeadlineMonitor, DegradationStep};
+use crate::landscape::{Landscape, WorldStats};
+use crate::stats::RingBuffer;
+
+/// Получатель кадров мира.
+pub trait FrameSink {
+    /// Отправляет кадр.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame`: Кадр.
+    ///
+    /// returns: bool - false, если получатель закрыт (прогон завершается).
+    fn send(&mut self, frame: Frame) -> bool;
+}
+
+/// Результат опроса источника управляющих сообщений.
+pub enum ControlPoll {
+    /// Получено сообщение.
+    Message(ControlMessage),
+    /// Сообщений нет.
+    Empty,
+    /// Источник закрыт (прогон завершается).
+    Disconnected,
+}
+
+/// Источник управляющих сообщений.
+pub trait ControlSource {
+    /// Забирает сообщение, не дожидаясь его.
+    fn poll(&mut self) -> ControlPoll;
+
+    /// Ждет сообщение. None - источник закрыт.
+    fn wait(&mut self) -> Option<ControlMessage>;
+}
+
+/// Часы прогона.
+pub trait Clock {
+    /// Текущее время в миллисекундах от произвольной начальной точки. Дробная часть
+    /// нужна контролю продолжительности итераций: итерации короче миллисекунды
+    /// обычны.
+    fn now_ms(&self) -> f64;
+}
+
+/// Часы по монотонному системному времени.
+pub struct SystemClock {
+    // Начальная точка отсчета.
+    start: Instant,
+}
+
+impl SystemClock {
+    /// Конструктор: отсчет времени начинается с момента создания.
+    pub fn new() -> SystemClock {
+        SystemClock { start: Instant::now() }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        SystemClock::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now_ms(&self) -> f64 {
+        self.start.elapsed().as_secs_f64() * 1000.0
+    }
+}
+
+impl FrameSink for Sender<Frame> {
+    fn send(&mut self, frame: Frame) -> bool {
+        Sender::send(self, frame).is_ok()
+    }
+}
+
+impl ControlSource for Receiver<ControlMessage> {
+    fn poll(&mut self) -> ControlPoll {
+        match self.try_recv() {
+            Ok(message) => ControlPoll::Message(message),
+            Err(TryRecvError::Empty) => ControlPoll::Empty,
+            Err(TryRecvError::Disconnected) => ControlPoll::Disconnected,
+        }
+    }
+
+    fn wait(&mut self) -> Option<ControlMessage> {
+        self.recv().ok()
+    }
+}
+
+/// Прогоняемая модель: мир и то, что программа делает вокруг итерации мира.
+pub trait Simulation {
+    /// Мир.
+    fn world(&mut self) -> &mut Landscape;
+
+    /// Создает мир заново с теми же настройками.
+    fn restart(&mut self);
+
+    /// Одна итерация мира вместе с обслуживанием (заселение отложенных видов,
+    /// бюджет памяти и т.д.).
+    fn step(&mut self);
+
+    /// Сохраняет текущее состояние мира в сценарий.
+    fn export_scenario(&mut self);
+
+    /// Максимальное количество итераций.
+    fn max_steps(&self) -> usize;
+
+    /// Сколько последних итераций статистики хранится для ответов на запросы
+    /// истории от отображения. По умолчанию - `config::RECENT_HISTORY`.
+    fn recent_history(&self) -> usize {
+        RECENT_HISTORY
+    }
+
+    /// Контроль продолжительности итераций. None - итерации не ограничены по времени.
+    fn deadline_monitor(&self) -> Option<DeadlineMonitor> {
+        None
+    }
+
+    /// Включает или выключает более дешевый режим мира. Вызывается, только если
+    /// модель задала контроль продолжительности итераций.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode`: Режим.
+    /// * `enabled`: Включить режим.
+    ///
+    /// returns: ()
+    fn set_cheap_mode(&mut self, _mode: CheapMode, _enabled: bool) {}
+
+    /// Отметки поверх изображения мира для отправляемого кадра (например, ячейки,
+    /// важные для анализа пользователя библиотеки). Вызывается только перед отправкой
+    /// кадра, отметки не переносятся в следующие кадры. По умолчанию отметок нет.
+    fn annotations(&mut self) -> Vec<Annotation> {
+        Vec::new()
+    }
+}
+
+/// Ввод и вывод прогона. Без получателя кадров мир не отображается; без источника
+/// управляющих сообщений прогон завершается после последней итерации, иначе ждет
+/// перезапуска или закрытия источника.
+pub struct RunIo<'a> {
+    pub frames: Option<&'a mut dyn FrameSink>,
+    pub control: Option<&'a mut dyn ControlSource>,
+    pub clock: &'a dyn Clock,
+}
+
+/// Итоги цикла прогона.
+pub struct RunReport {
+    // Статистика мира после каждой итерации (с последнего перезапуска).
+    pub history: Vec<WorldStats>,
+    // Продолжительность прогона в миллисекундах.
+    pub elapsed_ms: u64,
+    // Шаги деградации с номерами итераций, после которых они сделаны (с последнего
+    // перезапуска).
+    pub degradations: Vec<(usize, DegradationStep)>,
+}
+
+/// Прогоняет мир: выполняет итерации, применяет управляющие сообщения на границах
+/// итераций и отправляет кадры, которые запросило отображение.
+///
+/// # Arguments
+///
+/// * `sim`: Прогоняемая модель.
+/// * `io`: Ввод и вывод прогона.
+///
+/// returns: RunReport
+pub fn run(sim: &mut dyn Simulation, io: &mut RunIo) -> RunReport {
+    let start = io.clock.now_ms();
+    let max_steps = sim.max_steps();
+
+    // История статистики мира для итогового отчета.
+    let mut history: Vec<WorldStats> = Vec::with_capacity(max_steps);
+    // Статистика последних итераций для запросов истории от отображения.
+    let mut recent: RingBuffer<WorldStats> = RingBuffer::new(sim.recent_history());
+    // Запрос истории, на который ответит следующий кадр.
+    let mut history_request: Option<usize> = None;
+    // Контроль продолжительности итераций и сделанные им шаги деградации.
+    let mut deadline = sim.deadline_monitor();
+    let mut degradations: Vec<(usize, DegradationStep)> = Vec::new();
+    // Итерации мира.
+    let mut step: usize = 0;
+    // Номер кадра, не сбрасывается при перезапуске мира.
+    let mut sequence: u64 = 0;
+    // Выбранное (щелчком) животное.
+    let mut selected: Option<u64> = None;
+    // Передавать в кадрах ячейки, которые воспринимает выбранное животное.
+    let mut show_perception = false;
+    // Запрос отображения (слои карты и частота кадров), сохраняется при перезапуске.
+    let mut frame_request = FrameRequest::default();
+
+    loop {
+        // Управляющие сообщения применяются только на границе итераций.
+        let message = match io.control {
+            Some(ref mut control) if step < max_steps => match control.poll() {
+                ControlPoll::Message(message) => Some(message),
+                ControlPoll::Empty => None,
+                ControlPoll::Disconnected => break,
+            },
+            // Итерации мира закончились, ждем перезапуска или закрытия источника.
+            Some(ref mut control) => match control.wait() {
+                Some(message) => Some(message),
+                None => break,
+            },
+            None if step < max_steps => None,
+            None => break,
+        };
+
+        // Итерации мира закончились: сообщения, кроме перезапуска, только применяются.
+        let finished = step >= max_steps;
+
+        match message {
+            Some(ControlMessage::Restart) => {
+                sim.restart();
+                sim.world().set_frame_request(frame_request);
+                history.clear();
+                recent.clear();
+                degradations.clear();
+                // Мир создан заново со всеми выключенными режимами.
+                if let Some(ref mut monitor) = deadline {
+                    monitor.reset();
+                }
+                step = 0;
+                selected = None;
+            }
+            Some(ControlMessage::FollowDynasty(x, y)) => {
+                match sim.world().follow_dynasty(x, y) {
+                    Ok(id) => {
+                        println!("Отслеживается династия животного {} в ячейке ({}, {})", id, x, y);
+                        selected = Some(id);
+                        sim.world().set_traced_animal(selected);
+                    }
+                    Err(e) => eprintln!("{}", e),
+                }
+                if finished {
+                    continue;
+                }
+            }
+            Some(ControlMessage::AdjustEnergy(delta)) => {
+                match selected.map(|id| (id, sim.world().adjust_energy(id, delta))) {
+                    Some((id, Ok(energy))) => {
+                        println!("Энергия животного {} изменена вручную на {}: {}", id, delta, energy)
+                    }
+                    Some((_, Err(e))) => eprintln!("{}", e),
+                    None => eprintln!("Животное не выбрано"),
+                }
+                if finished {
+                    continue;
+                }
+            }
+            Some(ControlMessage::FrameRequest(request)) => {
+                frame_request = request;
+                sim.world().set_frame_request(request);
+                if finished {
+                    continue;
+                }
+            }
+            Some(ControlMessage::ExportScenario) => {
+                sim.export_scenario();
+                if finished {
+                    continue;
+                }
+            }
+            Some(ControlMessage::ShowPerception(show)) => {
+                show_perception = show;
+                if finished {
+                    continue;
+                }
+            }
+            Some(ControlMessage::GetHistory(count)) => {
+                history_request = Some(count);
+                // Итерации закончились: следующего кадра не будет, отвечаем кадром
+                // с текущим состоянием мира.
+                if finished {
+                    if let Some(ref mut frames) = io.frames {
+                        let world = sim.world();
+                        sequence += 1;
+                        let mut frame = Frame::new(sequence, step as u64, world.get_view_state(), Vec::new());
+                        frame.history = history_request.take().map(|count| history_response(&recent, count));
+                        frame.annotations = sim.annotations();
+                        frame.perception = perception(sim.world(), selected, show_perception);
+                        if !frames.send(frame) {
+                            break;
+                        }
+                    }
+                    continue;
+                }
+            }
+            None => {}
+        }
+
+        // Одна итерация
+        let tick_start = io.clock.now_ms();
+        sim.step();
+        if let Some(ref mut monitor) = deadline {
+            let duration = (io.clock.now_ms() - tick_start).max(0.0);
+            if let Some(degradation) = monitor.record(duration) {
+                apply_degradation(sim, degradation);
+                degradations.push((step + 1, degradation));
+            }
+        }
+        let stats = sim.world().get_world_stats();
+        history.push(stats);
+        recent.push(stats);
+        step += 1;
+
+        // Отправляем карту состояния мира, если отображение запросило кадр этой итерации.
+        if let Some(ref mut frames) = io.frames {
+            let world = sim.world();
+            if world.is_view_ready() {
+                sequence += 1;
+                let mut frame = Frame::new(sequence, step as u64, world.get_view_state(), world.get_transient_events());
+                frame.history = history_request.take().map(|count| history_response(&recent, count));
+                frame.annotations = sim.annotations();
+                frame.perception = perception(sim.world(), selected, show_perception);
+                if !frames.send(frame) {
+                    // Окно отображения закрыто.
+                    break;
+                }
+            }
+        }
+    }
+
+    RunReport {
+        history,
+        elapsed_ms: (io.clock.now_ms() - start).max(0.0).round() as u64,
+        degradations,
+    }
+}
+
+/// Включает или выключает режим мира по решению контроля продолжительности итераций.
+///
+/// # Arguments
+///
+/// * `sim`: Прогоняемая модель.
+/// * `degradation`: Шаг деградации.
+///
+/// returns: ()
+fn apply_degradation(sim: &mut dyn Simulation, degradation: DegradationStep) {
+    match degradation {
+        DegradationStep::Enable(mode) => sim.set_cheap_mode(mode, true),
+        DegradationStep::Disable(mode) => sim.set_cheap_mode(mode, false),
+    }
+}
+
+/// Ячейки, которые воспринимает выбранное животное, для отправляемого кадра.
+///
+/// # Arguments
+///
+/// * `world`: Мир.
+/// * `selected`: Выбранное животное.
+/// * `show`: Отображение запросило ячейки.
+///
+/// returns: Option<PerceptionFootprint> - None, если ячейки не запрошены или
+/// выбранного животного нет в живых.
+fn perception(world: &Landscape, selected: Option<u64>, show: bool) -> Option<PerceptionFootprint> {
+    if !show {
+        return None;
+    }
+
+    selected.and_then(|id| world.animal_perception(id))
+}
+
+/// Ответ на запрос истории: численность видов за последние итерации.
+///
+/// # Arguments
+///
+/// * `recent`: Статистика последних итераций.
+/// * `count`: Сколько последних итераций запрошено.
+///
+/// returns: HistoryResponse
+fn history_response(recent: &RingBuffer<WorldStats>, count: usize) -> HistoryResponse {
+    let stats: Vec<&WorldStats> = recent.last(count).collect();
+
+    HistoryResponse {
+        first_tick: stats.first().map_or(0, |stats| stats.iteration as u64),
+        populations: stats.iter().map(|stats| stats.animal_count).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::collections::VecDeque;
+
+    use display::render_model::Rgba;
+
+    use super::*;
+    use crate::testing;
+
+    /// Модель с маленьким миром, считающая вызовы.
+    struct FakeSimulation {
+        world: Landscape,
+        max_steps: usize,
+        steps: usize,
+        restarts: usize,
+        exports: usize,
+        // Отведенное на итерацию время. None - без контроля продолжительности.
+        budget_ms: Option<f64>,
+        // Переключения режимов мира.
+        cheap_modes: Vec<(CheapMode, bool)>,
+    }
+
+    impl FakeSimulation {
+        fn new(max_steps: usize) -> FakeSimulation {
+            FakeSimulation {
+                world: testing::populated_world(8, 8, (10, 3, 1)),
+                max_steps,
+                steps: 0,
+                restarts: 0,
+                exports: 0,
+                budget_ms: None,
+                cheap_modes: Vec::new(),
+            }
+        }
+    }
+
+    impl Simulation for FakeSimulation {
+        fn world(&mut self) -> &mut Landscape {
+            &mut self.world
+        }
+
+        fn restart(&mut self) {
+            self.world = testing::populated_world(8, 8, (10, 3, 1));
+            self.restarts += 1;
+        }
+
+        fn step(&mut self) {
+            self.world.tick();
+            self.steps += 1;
+        }
+
+        fn export_scenario(&mut self) {
+            self.exports += 1;
+        }
+
+        fn max_steps(&self) -> usize {
+            self.max_steps
+        }
+
+        fn deadline_monitor(&self) -> Option<DeadlineMonitor> {
+            self.budget_ms.map(|budget| {
+                DeadlineMonitor::new(budget, 2, 0.5, &[CheapMode::SparsePlants, CheapMode::SparseStats])
+            })
+        }
+
+        fn set_cheap_mode(&mut self, mode: CheapMode, enabled: bool) {
+            self.cheap_modes.push((mode, enabled));
+        }
+
+        fn annotations(&mut self) -> Vec<Annotation> {
+            vec![Annotation::CellHighlight { x: 0, y: 0, color: Rgba(1.0, 0.0, 0.0, 1.0) }]
+        }
+    }
+
+    /// Часы, которые при каждом обращении уходят вперед на заданное время.
+    struct FakeClock {
+        now: Cell<f64>,
+        step_ms: f64,
+    }
+
+    impl Clock for FakeClock {
+        fn now_ms(&self) -> f64 {
+            let now = self.now.get();
+            self.now.set(now + self.step_ms);
+            now
+        }
+    }
+
+    /// Получатель кадров, который закрывается после заданного количества кадров.
+    struct FakeFrames {
+        frames: Vec<Frame>,
+        capacity: usize,
+    }
+
+    impl FrameSink for FakeFrames {
+        fn send(&mut self, frame: Frame) -> bool {
+            if self.frames.len() >= self.capacity {
+                return false;
+            }
+            self.frames.push(frame);
+            true
+        }
+    }
+
+    /// Источник сообщений по сценарию: сообщение или пропуск на каждой границе
+    /// итераций. Закончившийся сценарий - закрытый источник.
+    struct FakeControl {
+        script: VecDeque<Option<ControlMessage>>,
+    }
+
+    impl FakeControl {
+        fn new(script: Vec<Option<ControlMessage>>) -> FakeControl {
+            FakeControl { script: script.into() }
+        }
+    }
+
+    impl ControlSource for FakeControl {
+        fn poll(&mut self) -> ControlPoll {
+            match self.script.pop_front() {
+                Some(Some(message)) => ControlPoll::Message(message),
+                Some(None) => ControlPoll::Empty,
+                None => ControlPoll::Disconnected,
+            }
+        }
+
+        fn wait(&mut self) -> Option<ControlMessage> {
+            self.script.pop_front().flatten()
+        }
+    }
+
+    fn clock() -> FakeClock {
+        FakeClock { now: Cell::new(0.0), step_ms: 1.0 }
+    }
+
+    #[test]
+    fn runs_max_steps_without_io() {
+        let mut sim = FakeSimulation::new(20);
+        let clock = clock();
+
+        let report = run(&mut sim, &mut RunIo { frames: None, control: None, clock: &clock });
+
+        assert_eq!(sim.steps, 20);
+        assert_eq!(report.history.len(), 20);
+        assert_eq!(report.history.last().unwrap().iteration, 20);
+        // Начало, каждая итерация и конец прогона - по одному обращению к часам.
+        assert_eq!(report.elapsed_ms, 21);
+    }
+
+    #[test]
+    fn sends_frame_after_every_step() {
+        let mut sim = FakeSimulation::new(5);
+        let clock = clock();
+        let mut frames = FakeFrames { frames: Vec::new(), capacity: usize::MAX };
+
+        run(&mut sim, &mut RunIo { frames: Some(&mut frames), control: None, clock: &clock });
+
+        let ticks: Vec<u64> = frames.frames.iter().map(|frame| frame.tick).collect();
+        assert_eq!(ticks, vec![1, 2, 3, 4, 5]);
+        let sequences: Vec<u64> = frames.frames.iter().map(|frame| frame.sequence).collect();
+        assert_eq!(sequences, vec![1, 2, 3, 4, 5]);
+        assert!(frames.frames.iter().all(|frame| frame.annotations.len() == 1 && frame.history.is_none()));
+    }
+
+    #[test]
+    fn closed_sink_stops_run() {
+        let mut sim = FakeSimulation::new(50);
+        let clock = clock();
+        let mut frames = FakeFrames { frames: Vec::new(), capacity: 3 };
+
+        let report = run(&mut sim, &mut RunIo { frames: Some(&mut frames), control: None, clock: &clock });
+
+        assert_eq!(frames.frames.len(), 3);
+        assert_eq!(sim.steps, 4);
+        assert_eq!(report.history.len(), 4);
+    }
+
+    #[test]
+    fn disconnected_control_stops_run() {
+        let mut sim = FakeSimulation::new(50);
+        let clock = clock();
+        let mut control = FakeControl::new(vec![None, None]);
+
+        let report = run(&mut sim, &mut RunIo { frames: None, control: Some(&mut control), clock: &clock });
+
+        assert_eq!(sim.steps, 2);
+        assert_eq!(report.history.len(), 2);
+    }
+
+    #[test]
+    fn restart_clears_history_and_runs_again() {
+        let mut sim = FakeSimulation::new(3);
+        let clock = clock();
+        let mut control = FakeControl::new(vec![
+            None, Some(ControlMessage::ExportScenario), None,
+            // Итерации закончились: прогон ждет перезапуска.
+            Some(ControlMessage::Restart), None, None,
+        ]);
+
+        let report = run(&mut sim, &mut RunIo { frames: None, control: Some(&mut control), clock: &clock });
+
+        assert_eq!(sim.exports, 1);
+        assert_eq!(sim.restarts, 1);
+        // Перезапуск - тоже граница итераций, после него мир делает итерацию.
+        assert_eq!(sim.steps, 6);
+        assert_eq!(report.history.len(), 3);
+        assert_eq!(report.history.last().unwrap().iteration, 3);
+    }
+
+    #[test]
+    fn finished_run_answers_history_request() {
+        let mut sim = FakeSimulation::new(4);
+        let clock = clock();
+        let mut frames = FakeFrames { frames: Vec::new(), capacity: usize::MAX };
+        let mut control = FakeControl::new(vec![
+            None, None, None, None,
+            Some(ControlMessage::GetHistory(2)),
+            Some(ControlMessage::ShowPerception(true)),
+        ]);
+
+        let report = run(&mut sim, &mut RunIo { frames: Some(&mut frames), control: Some(&mut control), clock: &clock });
+
+        assert_eq!(sim.steps, 4);
+        assert_eq!(report.history.len(), 4);
+        // Четыре кадра итераций и кадр с ответом на запрос истории.
+        assert_eq!(frames.frames.len(), 5);
+        let answer = frames.frames.last().unwrap();
+        assert_eq!(answer.tick, 4);
+        let history = answer.history.as_ref().unwrap();
+        assert_eq!(history.first_tick, 3);
+        assert_eq!(history.populations.len(), 2);
+        // Животное не выбрано: ячеек восприятия нет.
+        assert!(answer.perception.is_none());
+    }
+
+    #[test]
+    fn sub_millisecond_ticks_trigger_degradation() {
+        let mut sim = FakeSimulation::new(6);
+        sim.budget_ms = Some(0.5);
+        // Каждая итерация длится 0.75 мс: в целых миллисекундах это был бы ноль.
+        let clock = FakeClock { now: Cell::new(0.0), step_ms: 0.75 };
+
+        let report = run(&mut sim, &mut RunIo { frames: None, control: None, clock: &clock });
+
+        // Решение принимается по окну из двух итераций, режимы включаются по очереди.
+        assert_eq!(report.degradations, vec![
+            (2, DegradationStep::Enable(CheapMode::SparsePlants)),
+            (4, DegradationStep::Enable(CheapMode::SparseStats)),
+        ]);
+        assert_eq!(sim.cheap_modes, vec![(CheapMode::SparsePlants, true), (CheapMode::SparseStats, true)]);
+    }
+}
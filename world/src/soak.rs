@@ -0,0 +1,260 @@
+//! Проверка выносливости (подкоманда `soak`): долгий прогон без отображения, во время
+//! которого периодически замеряются память процесса, продолжительность итераций и
+//! сверка счетчиков статистики. Если память растет быстрее допустимого или сверка
+//! обнаружила расхождение, проверка не пройдена.
+
+use std::fmt::Write;
+use std::fs;
+
+use crate::deadline::percentile;
+
+/// Доля первых замеров, которые не учитываются при оценке роста памяти: в начале
+/// прогона память растет вместе с населением мира.
+const WARMUP_FRACTION: f64 = 0.25;
+
+/// Замер проверки выносливости.
+#[derive(Copy, Clone)]
+pub struct SoakSample {
+    // Номер итерации.
+    pub tick: usize,
+    // Резидентная память процесса в байтах. None - память процесса не замеряется на
+    // этой платформе.
+    pub rss_bytes: Option<usize>,
+    // Память, учтенная учетчиками памяти мира (см. `MemoryTracker`), в байтах.
+    pub tracked_bytes: usize,
+    // Процентили продолжительности итераций с предыдущего замера (миллисекунды):
+    // 50-й, 95-й и 99-й.
+    pub tick_ms: (f64, f64, f64),
+}
+
+/// Проверка выносливости: собирает замеры и расхождения сверки и решает, пройдена
+/// ли проверка.
+pub struct SoakMonitor {
+    // Допустимый рост памяти (байт за итерацию) после прогрева.
+    max_slope: f64,
+    // Продолжительность итераций с предыдущего замера (миллисекунды).
+    durations: Vec<f64>,
+    // Замеры.
+    samples: Vec<SoakSample>,
+    // Расхождения сверки счетчиков: итерация и описание.
+    failures: Vec<(usize, String)>,
+}
+
+impl SoakMonitor {
+    /// Конструктор.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_slope`: Допустимый рост памяти после прогрева, байт за итерацию.
+    ///
+    /// returns: SoakMonitor
+    pub fn new(max_slope: f64) -> SoakMonitor {
+        SoakMonitor {
+            max_slope,
+            durations: Vec::new(),
+            samples: Vec::new(),
+            failures: Vec::new(),
+        }
+    }
+
+    /// Учитывает продолжительность итерации.
+    ///
+    /// # Arguments
+    ///
+    /// * `duration_ms`: Продолжительность итерации в миллисекундах.
+    ///
+    /// returns: ()
+    pub fn record_tick(&mut self, duration_ms: f64) {
+        self.durations.push(duration_ms);
+    }
+
+    /// Добавляет замер памяти. Процентили продолжительности вычисляются по итерациям
+    /// с предыдущего замера.
+    ///
+    /// # Arguments
+    ///
+    /// * `tick`: Номер итерации.
+    /// * `rss_bytes`: Резидентная память процесса (см. `resident_memory`).
+    /// * `tracked_bytes`: Память, учтенная учетчиками памяти мира.
+    ///
+    /// returns: ()
+    pub fn sample(&mut self, tick: usize, rss_bytes: Option<usize>, tracked_bytes: usize) {
+        let durations = std::mem::take(&mut self.durations);
+        let rank = |rank: f64| percentile(durations.clone(), rank).unwrap_or(0.0);
+
+        self.samples.push(SoakSample {
+            tick,
+            rss_bytes,
+            tracked_bytes,
+            tick_ms: (rank(0.5), rank(0.95), rank(0.99)),
+        });
+    }
+
+    /// Учитывает расхождение сверки счетчиков статистики.
+    ///
+    /// # Arguments
+    ///
+    /// * `tick`: Номер итерации.
+    /// * `description`: Описание расхождения.
+    ///
+    /// returns: ()
+    pub fn record_failure(&mut self, tick: usize, description: String) {
+        self.failures.push((tick, description));
+    }
+
+    /// Замеры.
+    pub fn samples(&self) -> &[SoakSample] {
+        &self.samples
+    }
+
+    /// Рост памяти после прогрева (байт за итерацию) - наклон прямой, проведенной
+    /// методом наименьших квадратов через замеры. Используется резидентная память,
+    /// а если она не замеряется - память учетчиков. None - замеров после прогрева
+    /// меньше двух.
+    pub fn memory_slope(&self) -> Option<f64> {
+        let skip = (self.samples.len() as f64 * WARMUP_FRACTION) as usize;
+        let points: Vec<(f64, f64)> = self.samples[skip..].iter()
+            .map(|sample| (sample.tick as f64, sample.rss_bytes.unwrap_or(sample.tracked_bytes) as f64))
+            .collect();
+
+        if points.len() < 2 {
+            return None;
+        }
+
+        let count = points.len() as f64;
+        let mean_x = points.iter().map(|point| point.0).sum::<f64>() / count;
+        let mean_y = points.iter().map(|point| point.1).sum::<f64>() / count;
+        let covariance: f64 = points.iter().map(|point| (point.0 - mean_x) * (point.1 - mean_y)).sum();
+        let variance: f64 = points.iter().map(|point| (point.0 - mean_x).powi(2)).sum();
+
+        if variance == 0.0 {
+            return None;
+        }
+
+        Some(covariance / variance)
+    }
+
+    /// Пройдена ли проверка: память растет не быстрее допустимого и сверка счетчиков
+    /// не обнаружила расхождений.
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty() && self.memory_slope().is_none_or(|slope| slope <= self.max_slope)
+    }
+
+    /// Итог проверки в виде текста: пройдена ли она, рост памяти и расхождения сверки.
+    ///
+    /// returns: String
+    pub fn summary(&self) -> String {
+        let mut text = String::new();
+
+        let _ = writeln!(text, "{}", if self.passed() { "PASS" } else { "FAIL" });
+        let _ = match self.memory_slope() {
+            Some(slope) => writeln!(
+                text, "рост памяти: {:.3} байт за итерацию (допустимо {})", slope, self.max_slope
+            ),
+            None => writeln!(text, "рост памяти: недостаточно замеров"),
+        };
+        let _ = writeln!(text, "расхождений сверки: {}", self.failures.len());
+        for (tick, description) in &self.failures {
+            let _ = writeln!(text, "  итерация {}: {}", tick, description);
+        }
+
+        text
+    }
+
+    /// Отчет о проверке в виде текста: итог (см. `summary`) и таблица замеров в CSV.
+    ///
+    /// returns: String
+    pub fn report(&self) -> String {
+        let mut text = self.summary();
+
+        let _ = writeln!(text, "tick,rss_bytes,tracked_bytes,tick_p50_ms,tick_p95_ms,tick_p99_ms");
+        for sample in &self.samples {
+            let rss = sample.rss_bytes.map_or(String::new(), |bytes| bytes.to_string());
+            let _ = writeln!(
+                text, "{},{},{},{},{},{}",
+                sample.tick, rss, sample.tracked_bytes, sample.tick_ms.0, sample.tick_ms.1, sample.tick_ms.2
+            );
+        }
+
+        text
+    }
+}
+
+/// Резидентная память процесса в байтах (строка VmRSS файла /proc/self/status).
+///
+/// returns: Option<usize> - None, если память процесса не замеряется на этой
+/// платформе.
+pub fn resident_memory() -> Option<usize> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kilobytes: usize = line.split_whitespace().nth(1)?.parse().ok()?;
+
+    Some(kilobytes * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Проверка с замерами на итерациях 0, 100, ..., 900: память учетчиков растет на
+    /// `slope` байт за итерацию, резидентная память не замеряется.
+    fn monitor(slope: f64, max_slope: f64) -> SoakMonitor {
+        let mut monitor = SoakMonitor::new(max_slope);
+
+        for tick in (0..1000).step_by(100) {
+            for duration in [1.0, 2.0, 3.0, 4.0] {
+                monitor.record_tick(duration);
+            }
+            monitor.sample(tick, None, 1_000_000 + (slope * tick as f64) as usize);
+        }
+
+        monitor
+    }
+
+    #[test]
+    fn memory_slope_fits_samples_after_warmup() {
+        assert!((monitor(8.0, 10.0).memory_slope().unwrap() - 8.0).abs() < 1e-9);
+        assert_eq!(monitor(0.0, 10.0).memory_slope(), Some(0.0));
+
+        // Резидентная память важнее памяти учетчиков; рост до прогрева не учитывается.
+        let mut monitor = SoakMonitor::new(10.0);
+        for (tick, rss) in [(0, 0), (100, 4_000_000)] {
+            monitor.sample(tick, Some(rss), tick * 1000);
+        }
+        for tick in (200..800).step_by(100) {
+            monitor.sample(tick, Some(5_000_000 + 3 * (tick - 200)), tick * 1000);
+        }
+        assert!((monitor.memory_slope().unwrap() - 3.0).abs() < 1e-9);
+
+        assert_eq!(SoakMonitor::new(10.0).memory_slope(), None);
+    }
+
+    #[test]
+    fn passes_only_within_slope_and_without_failures() {
+        assert!(monitor(8.0, 10.0).passed());
+        assert!(!monitor(12.0, 10.0).passed());
+        assert!(SoakMonitor::new(10.0).passed());
+
+        let mut failed = monitor(0.0, 10.0);
+        failed.record_failure(300, "сумма численности видов".to_string());
+        assert!(!failed.passed());
+    }
+
+    #[test]
+    fn report_has_verdict_and_sample_rows() {
+        let mut monitor = monitor(12.0, 10.0);
+        monitor.record_failure(300, "сумма численности видов".to_string());
+
+        let report = monitor.report();
+        let lines: Vec<&str> = report.lines().collect();
+
+        assert_eq!(lines[0], "FAIL");
+        assert!(lines[1].starts_with("рост памяти: 12.000 байт за итерацию"));
+        assert_eq!(lines[2], "расхождений сверки: 1");
+        assert_eq!(lines[3], "  итерация 300: сумма численности видов");
+        assert_eq!(lines[4], "tick,rss_bytes,tracked_bytes,tick_p50_ms,tick_p95_ms,tick_p99_ms");
+        assert_eq!(lines.len(), 5 + 10);
+        assert!(lines[5].starts_with("0,,1000000,"));
+        assert!(lines[14].starts_with("900,,1010800,"));
+    }
+}
@@ -0,0 +1,281 @@
+//! Вспомогательные типы для статистики мира.
+
+use std::collections::VecDeque;
+
+/// Скользящее среднее по последним `window` значениям.
+#[derive(Clone)]
+pub struct RollingMean {
+    // Размер окна (количество последних значений).
+    window: usize,
+    // Значения, попавшие в окно.
+    values: VecDeque<f64>,
+    // Сумма значений в окне.
+    sum: f64,
+}
+
+impl RollingMean {
+    /// Конструктор. Создает пустое скользящее среднее.
+    ///
+    /// # Arguments
+    ///
+    /// * `window`: Размер окна, не может быть меньше единицы.
+    ///
+    /// returns: RollingMean
+    pub fn new(window: usize) -> RollingMean {
+        let window = window.max(1);
+
+        RollingMean {
+            window,
+            values: VecDeque::with_capacity(window),
+            sum: 0.0,
+        }
+    }
+
+    /// Добавляет значение. Самое старое значение покидает окно, если окно заполнено.
+    ///
+    /// # Arguments
+    ///
+    /// * `value`: Новое значение.
+    ///
+    /// returns: ()
+    pub fn push(&mut self, value: f64) {
+        if self.values.len() == self.window {
+            if let Some(oldest) = self.values.pop_front() {
+                self.sum -= oldest;
+            }
+        }
+
+        self.values.push_back(value);
+        self.sum += value;
+    }
+
+    /// Среднее значений в окне. None - значений еще нет.
+    pub fn mean(&self) -> Option<f64> {
+        if self.values.is_empty() {
+            return None;
+        }
+
+        Some(self.sum / self.values.len() as f64)
+    }
+
+    /// Значения в окне от старых к новым и их сумма (для контрольной точки мира).
+    pub fn state(&self) -> (Vec<f64>, f64) {
+        (self.values.iter().copied().collect(), self.sum)
+    }
+
+    /// Восстанавливает значения и сумму, сохраненные методом `state`. Сумма
+    /// восстанавливается как есть, а не пересчитывается: иначе среднее могло бы
+    /// отличаться в последних знаках. Если значений больше окна, остаются последние,
+    /// а сумма пересчитывается.
+    ///
+    /// # Arguments
+    ///
+    /// * `values`: Значения от старых к новым.
+    /// * `sum`: Их сумма.
+    ///
+    /// returns: ()
+    pub fn restore(&mut self, values: &[f64], sum: f64) {
+        let skip = values.len().saturating_sub(self.window);
+        self.values = values[skip..].iter().copied().collect();
+        self.sum = if skip > 0 { self.values.iter().sum() } else { sum };
+    }
+}
+
+/// Кольцевой буфер последних `capacity` значений: самое старое значение вытесняется,
+/// когда буфер заполнен.
+#[derive(Clone)]
+pub struct RingBuffer<T> {
+    // Вместимость буфера.
+    capacity: usize,
+    // Значения от старых к новым.
+    values: VecDeque<T>,
+}
+
+impl<T> RingBuffer<T> {
+    /// Конструктор. Создает пустой буфер.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity`: Вместимость буфера, не может быть меньше единицы.
+    ///
+    /// returns: RingBuffer<T>
+    pub fn new(capacity: usize) -> RingBuffer<T> {
+        let capacity = capacity.max(1);
+
+        RingBuffer {
+            capacity,
+            values: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Добавляет значение, вытесняя самое старое, если буфер заполнен.
+    ///
+    /// # Arguments
+    ///
+    /// * `value`: Новое значение.
+    ///
+    /// returns: ()
+    pub fn push(&mut self, value: T) {
+        if self.values.len() == self.capacity {
+            self.values.pop_front();
+        }
+
+        self.values.push_back(value);
+    }
+
+    /// Последние `count` значений (или все, если их меньше) от старых к новым.
+    ///
+    /// # Arguments
+    ///
+    /// * `count`: Количество значений.
+    ///
+    /// returns: impl Iterator<Item=&T>
+    pub fn last(&self, count: usize) -> impl Iterator<Item = &T> {
+        self.values.iter().skip(self.values.len().saturating_sub(count))
+    }
+
+    /// Удаляет все значения.
+    pub fn clear(&mut self) {
+        self.values.clear();
+    }
+}
+
+/// Среднее попарное евклидово расстояние между геномами. Значение, близкое к нулю,
+/// означает, что популяция стала клональной. None - геномов меньше двух.
+///
+/// # Arguments
+///
+/// * `genomes`: Геномы одинаковой длины.
+///
+/// returns: Option<f64>
+pub fn mean_pairwise_distance(genomes: &[Vec<f64>]) -> Option<f64> {
+    if genomes.len() < 2 {
+        return None;
+    }
+
+    let mut sum = 0.0;
+    let mut pairs = 0usize;
+
+    for (i, a) in genomes.iter().enumerate() {
+        for b in &genomes[i + 1..] {
+            let distance: f64 = a.iter()
+                .zip(b)
+                .map(|(a, b)| (a - b) * (a - b))
+                .sum();
+
+            sum += distance.sqrt();
+            pairs += 1;
+        }
+    }
+
+    Some(sum / pairs as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ring_buffer_keeps_newest_values() {
+        let mut buffer = RingBuffer::new(3);
+        for value in 1..=5 {
+            buffer.push(value);
+        }
+
+        assert_eq!(buffer.last(10).copied().collect::<Vec<_>>(), vec![3, 4, 5]);
+        assert_eq!(buffer.last(2).copied().collect::<Vec<_>>(), vec![4, 5]);
+        assert_eq!(buffer.last(0).count(), 0);
+
+        buffer.clear();
+        assert_eq!(buffer.last(10).count(), 0);
+    }
+
+    #[test]
+    fn identical_genomes_have_zero_distance() {
+        let genome = vec![0.5, -1.0, 2.0];
+
+        assert_eq!(mean_pairwise_distance(&[genome.clone(), genome.clone(), genome]), Some(0.0));
+    }
+
+    #[test]
+    fn orthogonal_genomes_have_known_distance() {
+        // Попарные расстояния единичных ортогональных векторов равны корню из двух.
+        let unit = vec![vec![1.0, 0.0, 0.0], vec![0.0, 1.0, 0.0], vec![0.0, 0.0, 1.0]];
+        assert_eq!(mean_pairwise_distance(&unit), Some(2f64.sqrt()));
+
+        assert_eq!(mean_pairwise_distance(&[vec![3.0, 0.0], vec![0.0, 4.0]]), Some(5.0));
+    }
+
+    #[test]
+    fn fewer_than_two_genomes_have_no_distance() {
+        assert_eq!(mean_pairwise_distance(&[]), None);
+        assert_eq!(mean_pairwise_distance(&[vec![1.0]]), None);
+    }
+
+    #[test]
+    fn ring_buffer_capacity_is_at_least_one() {
+        let mut buffer = RingBuffer::new(0);
+        buffer.push(1);
+        buffer.push(2);
+
+        assert_eq!(buffer.last(10).copied().collect::<Vec<_>>(), vec![2]);
+    }
+
+    #[test]
+    fn rolling_mean_restores_state() {
+        let mut mean = RollingMean::new(3);
+        for value in [0.1, 0.2, 0.7, 0.4] {
+            mean.push(value);
+        }
+        let (values, sum) = mean.state();
+
+        let mut restored = RollingMean::new(3);
+        restored.restore(&values, sum);
+        assert_eq!(restored.mean(), mean.mean());
+        mean.push(0.3);
+        restored.push(0.3);
+        assert_eq!(restored.mean(), mean.mean());
+
+        // Значения сверх окна отбрасываются.
+        let mut narrow = RollingMean::new(2);
+        narrow.restore(&[1.0, 2.0, 4.0], 7.0);
+        assert_eq!(narrow.mean(), Some(3.0));
+    }
+
+    #[test]
+    fn rolling_mean_window_continues_after_resume() {
+        let mut straight = RollingMean::new(3);
+        straight.push(1.0);
+        straight.push(2.0);
+        let (values, sum) = straight.state();
+
+        // Прогон возобновлен из контрольной точки: окно не начинается заново, а
+        // сохраненные значения покидают его в том же порядке, что и без остановки.
+        let mut resumed = RollingMean::new(3);
+        resumed.restore(&values, sum);
+        for (value, expected) in [(3.0, 2.0), (4.0, 3.0), (8.0, 5.0), (0.0, 4.0)] {
+            straight.push(value);
+            resumed.push(value);
+            assert_eq!(resumed.mean(), Some(expected));
+            assert_eq!(resumed.mean(), straight.mean());
+        }
+
+        // Контрольная точка до первого значения: окно заполняется с нуля.
+        let mut empty = RollingMean::new(3);
+        empty.restore(&[], 0.0);
+        assert_eq!(empty.mean(), None);
+        empty.push(6.0);
+        assert_eq!(empty.mean(), Some(6.0));
+    }
+
+    #[test]
+    fn rolling_mean_over_window() {
+        let mut mean = RollingMean::new(2);
+        assert_eq!(mean.mean(), None);
+
+        mean.push(1.0);
+        mean.push(3.0);
+        mean.push(5.0);
+        assert_eq!(mean.mean(), Some(4.0));
+    }
+}
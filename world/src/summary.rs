@@ -0,0 +1,516 @@
+//! Итоговый отчет о прогоне мира: история вымирания и восстановления видов.
+
+use std::fmt;
+
+use crate::animal::AnimaType;
+use crate::events::{DeathCause, Postmortem};
+use crate::landscape::{Energy, EnergyBudget, HuntStats, WorldStats};
+
+/// Итоги прогона для одного вида животных.
+#[derive(Clone, Default)]
+pub struct SpeciesSummary {
+    // Диапазоны итераций (включительно), в течение которых вид отсутствовал в мире.
+    pub extinct_ranges: Vec<(usize, usize)>,
+    // Итерация окончательного вымирания (вид так и не восстановился).
+    pub final_extinction: Option<usize>,
+    // Пиковая численность вида.
+    pub peak_population: usize,
+    // Итерация, на которой пиковая численность была достигнута впервые.
+    pub peak_iteration: usize,
+    // Самая длинная серия итераций непрерывного роста численности.
+    pub longest_growth_streak: usize,
+    // Общее количество попыток поесть.
+    pub eat_attempts: usize,
+    // Общее количество удачных попыток поесть (для хищника - убитые жертвы).
+    pub eat_successes: usize,
+    // Общая энергия, полученная от поедания.
+    pub eaten_energy: Energy,
+    // Общее количество нападений (при двухшаговой охоте, см.
+    // `Landscape::set_two_step_predation`).
+    pub attack_attempts: usize,
+    // Общее количество убитых нападениями жертв (туши могли съесть другие животные).
+    pub kills: usize,
+    // Энергия, полученная от поедания падали (входит в eaten_energy).
+    pub carrion_energy: Energy,
+    // Энергетический баланс вида за прогон.
+    pub energy_budget: EnergyBudget,
+    // Количество последних умерших животных вида в истории умерших
+    // (см. `Landscape::set_postmortem_history`).
+    pub recent_deaths: usize,
+    // Сколько из последних умерших животных вида были съедены или убиты хищником.
+    pub recent_eaten: usize,
+    // Сколько из последних умерших животных вида умерли от старости.
+    pub recent_old_age: usize,
+    // Медиана продолжительности жизни последних умерших животных вида. None -
+    // умерших в истории нет.
+    pub median_lifespan: Option<usize>,
+}
+
+/// Итоги прогона мира по видам: травоядные, хищники и всеядные.
+#[derive(Clone, Default)]
+pub struct RunSummary {
+    // Количество проанализированных итераций.
+    pub iterations: usize,
+    // Количество ручных изменений энергии животных. Прогон с ручными изменениями
+    // не следует использовать для анализа эволюции.
+    pub energy_adjustments: usize,
+    // Оценка приспособленности, по которой выбирались лучшие животные.
+    pub fitness: &'static str,
+    // Количество растений, умерших от старости (см. `Landscape::set_plant_max_age`).
+    pub plant_deaths: usize,
+    pub herbivore: SpeciesSummary,
+    pub carnivore: SpeciesSummary,
+    pub omnivore: SpeciesSummary,
+}
+
+/// Анализирует историю статистики мира и вычисляет итоги прогона.
+///
+/// # Arguments
+///
+/// * `history`: Статистика мира, собранная после каждой итерации.
+///
+/// returns: RunSummary
+pub fn analyze(history: &[WorldStats]) -> RunSummary {
+    let herbivores: Vec<(usize, usize)> = history.iter()
+        .map(|stats| (stats.iteration, stats.animal_count.0))
+        .collect();
+
+    let carnivores: Vec<(usize, usize)> = history.iter()
+        .map(|stats| (stats.iteration, stats.animal_count.1))
+        .collect();
+
+    let omnivores: Vec<(usize, usize)> = history.iter()
+        .map(|stats| (stats.iteration, stats.animal_count.2))
+        .collect();
+
+    let mut herbivore = analyze_species(&herbivores);
+    let mut carnivore = analyze_species(&carnivores);
+    let mut omnivore = analyze_species(&omnivores);
+
+    let mut plant_deaths = 0;
+    for stats in history {
+        plant_deaths += stats.plant_deaths;
+
+        add_hunt(&mut herbivore, &stats.hunt.0);
+        add_hunt(&mut carnivore, &stats.hunt.1);
+        add_hunt(&mut omnivore, &stats.hunt.2);
+
+        herbivore.carrion_energy += stats.carrion_energy.0;
+        carnivore.carrion_energy += stats.carrion_energy.1;
+        omnivore.carrion_energy += stats.carrion_energy.2;
+
+        herbivore.energy_budget.add(&stats.energy_budget.0);
+        carnivore.energy_budget.add(&stats.energy_budget.1);
+        omnivore.energy_budget.add(&stats.energy_budget.2);
+    }
+
+    RunSummary {
+        iterations: history.len(),
+        energy_adjustments: history.last().map_or(0, |stats| stats.energy_adjustments),
+        fitness: history.last().map_or("", |stats| stats.fitness),
+        plant_deaths,
+        herbivore,
+        carnivore,
+        omnivore,
+    }
+}
+
+/// Добавляет к итогам вида эффективность поедания за одну итерацию.
+///
+/// # Arguments
+///
+/// * `summary`: Итоги вида.
+/// * `hunt`: Эффективность поедания на итерации.
+///
+/// returns: ()
+fn add_hunt(summary: &mut SpeciesSummary, hunt: &HuntStats) {
+    summary.eat_attempts += hunt.attempts;
+    summary.eat_successes += hunt.successes;
+    summary.eaten_energy += hunt.energy;
+    summary.attack_attempts += hunt.attack_attempts;
+    summary.kills += hunt.kills;
+}
+
+/// Вычисляет итоги прогона для одного вида.
+///
+/// # Arguments
+///
+/// * `population`: Пары (итерация, численность вида).
+///
+/// returns: SpeciesSummary
+fn analyze_species(population: &[(usize, usize)]) -> SpeciesSummary {
+    let mut summary = SpeciesSummary::default();
+
+    // Начало текущего периода вымирания.
+    let mut extinct_from: Option<usize> = None;
+    // Текущая серия роста.
+    let mut growth_streak: usize = 0;
+    let mut previous: Option<usize> = None;
+
+    for (index, &(iteration, count)) in population.iter().enumerate() {
+        if count == 0 {
+            if extinct_from.is_none() {
+                extinct_from = Some(iteration);
+            }
+        } else if let Some(from) = extinct_from.take() {
+            summary.extinct_ranges.push((from, population[index - 1].0));
+        }
+
+        if index == 0 || count > summary.peak_population {
+            summary.peak_population = count;
+            summary.peak_iteration = iteration;
+        }
+
+        match previous {
+            Some(previous) if count > previous => {
+                growth_streak += 1;
+                if growth_streak > summary.longest_growth_streak {
+                    summary.longest_growth_streak = growth_streak;
+                }
+            }
+            _ => {
+                growth_streak = 0;
+            }
+        }
+        previous = Some(count);
+    }
+
+    // Вид вымер и больше не появлялся.
+    if let Some(from) = extinct_from {
+        let last = population[population.len() - 1].0;
+        summary.extinct_ranges.push((from, last));
+        summary.final_extinction = Some(from);
+    }
+
+    summary
+}
+
+/// Медиана значений (для четного количества - меньшее из двух средних).
+/// None - значений нет.
+///
+/// # Arguments
+///
+/// * `values`: Значения.
+///
+/// returns: Option<usize>
+fn median(mut values: Vec<usize>) -> Option<usize> {
+    if values.is_empty() {
+        return None;
+    }
+
+    values.sort_unstable();
+
+    Some(values[(values.len() - 1) / 2])
+}
+
+impl SpeciesSummary {
+    /// Добавляет к итогам вида последних умерших животных вида.
+    ///
+    /// # Arguments
+    ///
+    /// * `deaths`: Записи о жизни умерших животных вида.
+    ///
+    /// returns: ()
+    fn add_deaths(&mut self, deaths: &[&Postmortem]) {
+        self.recent_deaths = deaths.len();
+        self.recent_eaten = deaths.iter()
+            .filter(|death| death.cause == DeathCause::Eaten || death.cause == DeathCause::Killed)
+            .count();
+        self.recent_old_age = deaths.iter().filter(|death| death.cause == DeathCause::OldAge).count();
+        self.median_lifespan = median(deaths.iter().map(|death| death.age).collect());
+    }
+
+    /// Доля удачных попыток поесть за весь прогон. None - попыток не было.
+    pub fn success_rate(&self) -> Option<f64> {
+        if self.eat_attempts == 0 {
+            return None;
+        }
+
+        Some(self.eat_successes as f64 / self.eat_attempts as f64)
+    }
+
+    /// Представляет итоги вида в виде JSON объекта.
+    fn to_json(&self) -> String {
+        let ranges: Vec<String> = self.extinct_ranges.iter()
+            .map(|range| format!("[{}, {}]", range.0, range.1))
+            .collect();
+
+        let final_extinction = match self.final_extinction {
+            Some(iteration) => iteration.to_string(),
+            None => "null".to_string(),
+        };
+
+        let success_rate = match self.success_rate() {
+            Some(rate) => rate.to_string(),
+            None => "null".to_string(),
+        };
+
+        let median_lifespan = match self.median_lifespan {
+            Some(age) => age.to_string(),
+            None => "null".to_string(),
+        };
+
+        format!(
+            "{{\"extinct_ranges\": [{}], \"final_extinction\": {}, \"peak_population\": {}, \
+             \"peak_iteration\": {}, \"longest_growth_streak\": {}, \"eat_attempts\": {}, \
+             \"eat_successes\": {}, \"eaten_energy\": {}, \"carrion_energy\": {}, \
+             \"attack_attempts\": {}, \"kills\": {}, \"success_rate\": {}, \"energy_budget\": {{\"gained\": {}, \"spent\": {}, \
+             \"offspring\": {}, \"net\": {}}}, \"recent_deaths\": {}, \"recent_eaten\": {}, \
+             \"recent_old_age\": {}, \"median_lifespan\": {}}}",
+            ranges.join(", "),
+            final_extinction,
+            self.peak_population,
+            self.peak_iteration,
+            self.longest_growth_streak,
+            self.eat_attempts,
+            self.eat_successes,
+            self.eaten_energy,
+            self.carrion_energy,
+            self.attack_attempts,
+            self.kills,
+            success_rate,
+            self.energy_budget.gained,
+            self.energy_budget.spent,
+            self.energy_budget.offspring,
+            self.energy_budget.net(),
+            self.recent_deaths,
+            self.recent_eaten,
+            self.recent_old_age,
+            median_lifespan
+        )
+    }
+}
+
+impl RunSummary {
+    /// Добавляет к итогам прогона последних умерших животных: медиану
+    /// продолжительности жизни и долю съеденных по видам.
+    ///
+    /// # Arguments
+    ///
+    /// * `deaths`: Записи о жизни последних умерших животных
+    ///   (см. `Landscape::get_postmortems`).
+    ///
+    /// returns: ()
+    pub fn add_deaths(&mut self, deaths: &[Postmortem]) {
+        let of = |species: AnimaType| -> Vec<&Postmortem> {
+            deaths.iter().filter(|death| death.species == species).collect()
+        };
+
+        self.herbivore.add_deaths(&of(AnimaType::Herbivore));
+        self.carnivore.add_deaths(&of(AnimaType::Carnivore));
+        self.omnivore.add_deaths(&of(AnimaType::Omnivore));
+    }
+
+    /// Представляет итоги прогона в виде JSON.
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\n  \"iterations\": {},\n  \"energy_adjustments\": {},\n  \"fitness\": \"{}\",\n  \"plant_deaths\": {},\n  \"herbivore\": {},\n  \"carnivore\": {},\n  \"omnivore\": {}\n}}\n",
+            self.iterations,
+            self.energy_adjustments,
+            self.fitness,
+            self.plant_deaths,
+            self.herbivore.to_json(),
+            self.carnivore.to_json(),
+            self.omnivore.to_json()
+        )
+    }
+}
+
+impl fmt::Display for SpeciesSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "  пиковая численность: {} (итерация {})", self.peak_population, self.peak_iteration)?;
+        writeln!(f, "  самая длинная серия роста: {} итераций", self.longest_growth_streak)?;
+        write!(
+            f,
+            "  попыток поесть: {}, удачных: {}, получено энергии: {}",
+            self.eat_attempts,
+            self.eat_successes,
+            self.eaten_energy
+        )?;
+        match self.success_rate() {
+            Some(rate) => writeln!(f, " (доля удачных {:.4})", rate)?,
+            None => writeln!(f)?,
+        }
+        if self.carrion_energy > 0 as Energy {
+            writeln!(f, "  из них получено от падали: {}", self.carrion_energy)?;
+        }
+        if self.attack_attempts > 0 {
+            writeln!(f, "  нападений: {}, убито жертв: {}", self.attack_attempts, self.kills)?;
+        }
+        writeln!(
+            f,
+            "  энергия: получено {:.1}, потрачено {:.1}, потомкам {:.1}, итого {:+.1}",
+            self.energy_budget.gained,
+            self.energy_budget.spent,
+            self.energy_budget.offspring,
+            self.energy_budget.net()
+        )?;
+
+        if self.extinct_ranges.is_empty() {
+            writeln!(f, "  вид не вымирал")?;
+        } else {
+            for range in &self.extinct_ranges {
+                writeln!(f, "  вид отсутствовал: итерации {} - {}", range.0, range.1)?;
+            }
+        }
+
+        if let Some(age) = self.median_lifespan {
+            writeln!(
+                f,
+                "  последние умершие: {} (съедены {}, от старости {}), медиана продолжительности жизни {} итераций",
+                self.recent_deaths,
+                self.recent_eaten,
+                self.recent_old_age,
+                age
+            )?;
+        }
+
+        match self.final_extinction {
+            Some(iteration) => writeln!(f, "  окончательно вымер на итерации {}", iteration),
+            None => writeln!(f, "  дожил до конца прогона"),
+        }
+    }
+}
+
+impl fmt::Display for RunSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Итоги прогона ({} итераций)", self.iterations)?;
+        if !self.fitness.is_empty() {
+            writeln!(f, "Приспособленность: {}", self.fitness)?;
+        }
+        if self.energy_adjustments > 0 {
+            writeln!(f, "Энергия животных изменялась вручную {} раз(а)", self.energy_adjustments)?;
+        }
+        if self.plant_deaths > 0 {
+            writeln!(f, "Растений умерло от старости: {}", self.plant_deaths)?;
+        }
+        writeln!(f, "Травоядные:")?;
+        write!(f, "{}", self.herbivore)?;
+        writeln!(f, "Хищники:")?;
+        write!(f, "{}", self.carnivore)?;
+        writeln!(f, "Всеядные:")?;
+        write!(f, "{}", self.omnivore)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    /// История статистики с заданной численностью травоядных и хищников по
+    /// итерациям (начиная с нулевой); всеядных нет.
+    fn trajectory(counts: &[(usize, usize)]) -> Vec<WorldStats> {
+        let template = testing::world(3, 3).get_world_stats();
+
+        counts.iter()
+            .enumerate()
+            .map(|(iteration, &(herbivores, carnivores))| {
+                let mut stats = template;
+                stats.iteration = iteration;
+                stats.animal_count = (herbivores, carnivores, 0);
+                stats
+            })
+            .collect()
+    }
+
+    fn death(species: AnimaType, age: usize, cause: DeathCause) -> Postmortem {
+        Postmortem {
+            id: 0,
+            species,
+            generation: 0,
+            age,
+            cause,
+            tick: age,
+            at: (0, 0),
+            kills: 0,
+            meals: 0,
+            offspring: 0,
+            genome_hash: None,
+        }
+    }
+
+    #[test]
+    fn summary_counts_recent_deaths_by_species() {
+        let deaths = [
+            death(AnimaType::Herbivore, 5, DeathCause::Eaten),
+            death(AnimaType::Herbivore, 1, DeathCause::Starved),
+            death(AnimaType::Carnivore, 7, DeathCause::OldAge),
+            death(AnimaType::Herbivore, 9, DeathCause::OldAge),
+            death(AnimaType::Herbivore, 3, DeathCause::Killed),
+        ];
+
+        let mut summary = RunSummary::default();
+        summary.add_deaths(&deaths);
+
+        assert_eq!(summary.herbivore.recent_deaths, 4);
+        assert_eq!(summary.herbivore.recent_eaten, 2);
+        assert_eq!(summary.herbivore.recent_old_age, 1);
+        assert_eq!(summary.carnivore.recent_deaths, 1);
+        assert_eq!(summary.carnivore.recent_old_age, 1);
+        assert_eq!(summary.omnivore.recent_deaths, 0);
+        assert_eq!(summary.omnivore.median_lifespan, None);
+    }
+
+    #[test]
+    fn median_lifespan_takes_lower_middle() {
+        let mut summary = RunSummary::default();
+
+        summary.add_deaths(&[5, 1, 9, 3].map(|age| death(AnimaType::Herbivore, age, DeathCause::Starved)));
+        assert_eq!(summary.herbivore.median_lifespan, Some(3));
+
+        summary.add_deaths(&[5, 1, 9].map(|age| death(AnimaType::Herbivore, age, DeathCause::Starved)));
+        assert_eq!(summary.herbivore.median_lifespan, Some(5));
+    }
+
+    #[test]
+    fn species_that_never_died_out_has_no_extinction() {
+        let summary = analyze(&trajectory(&[(3, 1), (4, 2), (6, 2), (5, 3), (7, 1)]));
+
+        assert_eq!(summary.iterations, 5);
+        assert!(summary.herbivore.extinct_ranges.is_empty());
+        assert_eq!(summary.herbivore.final_extinction, None);
+        assert_eq!(summary.herbivore.peak_population, 7);
+        assert_eq!(summary.herbivore.peak_iteration, 4);
+        assert_eq!(summary.herbivore.longest_growth_streak, 2);
+        assert_eq!(summary.carnivore.peak_population, 3);
+        assert_eq!(summary.carnivore.peak_iteration, 3);
+        assert!(summary.carnivore.extinct_ranges.is_empty());
+        assert!(summary.to_string().contains("дожил до конца прогона"));
+    }
+
+    #[test]
+    fn species_absent_from_tick_zero_is_extinct_from_start() {
+        let summary = analyze(&trajectory(&[(2, 0), (3, 0), (1, 0)]));
+
+        assert_eq!(summary.carnivore.extinct_ranges, vec![(0, 2)]);
+        assert_eq!(summary.carnivore.final_extinction, Some(0));
+        assert_eq!(summary.carnivore.peak_population, 0);
+        assert_eq!(summary.carnivore.peak_iteration, 0);
+        // Всеядных не было ни на одной итерации.
+        assert_eq!(summary.omnivore.final_extinction, Some(0));
+        assert_eq!(summary.herbivore.final_extinction, None);
+    }
+
+    #[test]
+    fn recoveries_split_extinction_ranges() {
+        let summary = analyze(&trajectory(&[
+            (2, 1), (0, 1), (0, 1), (1, 1), (3, 1), (0, 1), (2, 1), (0, 1), (0, 1),
+        ]));
+
+        assert_eq!(summary.herbivore.extinct_ranges, vec![(1, 2), (5, 5), (7, 8)]);
+        assert_eq!(summary.herbivore.final_extinction, Some(7));
+        assert_eq!(summary.herbivore.peak_population, 3);
+        assert_eq!(summary.herbivore.peak_iteration, 4);
+        assert_eq!(summary.herbivore.longest_growth_streak, 2);
+
+        // Вид восстановился после каждого вымирания, кроме последнего.
+        let text = summary.to_string();
+        assert!(text.contains("вид отсутствовал: итерации 1 - 2"));
+        assert!(text.contains("окончательно вымер на итерации 7"));
+
+        let recovered = analyze(&trajectory(&[(2, 1), (0, 1), (1, 1)]));
+        assert_eq!(recovered.herbivore.extinct_ranges, vec![(1, 1)]);
+        assert_eq!(recovered.herbivore.final_extinction, None);
+    }
+}
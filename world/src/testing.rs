@@ -0,0 +1,145 @@
+//! Вспомогательные функции модульных тестов: создание растений, животных и небольших
+//! миров с фиксированным зерном и настройками по умолчанию из `config`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use rand::{RngCore, SeedableRng};
+use rand::rngs::StdRng;
+
+use crate::animal::brains::simple::Brain;
+use crate::animal::species::simple::{Animal, AnimalParams};
+use crate::animal::{AnimalAlive, AnimalDirection, AnimaType};
+use crate::config::*;
+use crate::landscape::{Landscape, PlacementStrategy};
+use crate::plant::PlantAlive;
+use crate::plant::simple::{Plant, PlantParams};
+
+/// Зерно генератора случайных чисел тестовых миров.
+pub const TEST_SEED: u64 = 42;
+
+/// Распределитель памяти тестов: считает выделения памяти в каждом потоке, чтобы
+/// тесты могли проверить, что код не выделяет память. Тесты выполняются в разных
+/// потоках и друг другу не мешают.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        // При завершении потока счетчик может быть уже уничтожен.
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Количество выделений памяти в текущем потоке с его начала.
+pub fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+/// Параметры растения по умолчанию.
+pub fn plant_params() -> PlantParams {
+    PlantParams {
+        energy: MAX_PLANT_ENERGY,
+        max_energy: MAX_PLANT_ENERGY,
+        eaten_energy: PLANT_EATEN_ENERGY,
+        bite_mode: PLANT_BITE_MODE,
+        reproduce_energy_rate: PLANT_REPRODUCE_ENERGY_RATE,
+        no_repro: PLANT_NO_REPRO,
+        regrowth_delay: PLANT_REGROWTH_DELAY,
+        seed_energy_cost: PLANT_SEED_ENERGY_COST,
+    }
+}
+
+/// Растение с параметрами по умолчанию.
+pub fn plant() -> Box<dyn PlantAlive> {
+    Plant::new(plant_params()).expect("Неверные параметры тестового растения")
+}
+
+/// Параметры животного по умолчанию.
+pub fn animal_params() -> AnimalParams {
+    AnimalParams {
+        energy: ANIMAL_BIRTH_ENERGY,
+        max_energy: MAX_ANIMAL_ENERGY,
+        live_energy: ANIMAL_LIVE_ENERGY,
+        energy_profile: ANIMAL_ENERGY_PROFILE,
+        eaten_energy_rate: ANIMAL_EATEN_ENERGY_RATE,
+        reproduce_energy_rate: ANIMAL_REPRODUCE_ENERGY_RATE,
+        no_repro: ANIMAL_NO_REPRO,
+        inherit_direction: ANIMAL_INHERIT_DIRECTION,
+        input_mask: &[],
+        vision_radius: ANIMAL_VISION_RADIUS,
+        max_age: ANIMAL_MAX_AGE,
+    }
+}
+
+/// Животное со случайным мозгом и параметрами по умолчанию.
+///
+/// # Arguments
+///
+/// * `animal_type`: Вид животного.
+/// * `rng`: Генератор случайных чисел для мозга.
+///
+/// returns: Box<dyn AnimalAlive>
+pub fn animal(animal_type: AnimaType, rng: &mut dyn RngCore) -> Box<dyn AnimalAlive> {
+    Animal::<Brain>::new(animal_type, animal_params(), AnimalDirection::North, 0, rng)
+        .expect("Неверные параметры тестового животного")
+}
+
+/// Пустой мир с фиксированным зерном, ограничения численности не мешают тестам.
+///
+/// # Arguments
+///
+/// * `width`, `height`: Размеры мира.
+///
+/// returns: Landscape
+pub fn world(width: usize, height: usize) -> Landscape {
+    Landscape::new(width, height, width * height, width * height, width * height, MAX_PLANT_GROW_ENERGY, Some(TEST_SEED))
+        .expect("Ошибка создания тестового мира!")
+}
+
+/// Мир с фиксированным зерном, заселенный случайно растениями и животными с
+/// параметрами по умолчанию.
+///
+/// # Arguments
+///
+/// * `width`, `height`: Размеры мира.
+/// * `agents`: Количество растений, травоядных и хищников.
+///
+/// returns: Landscape
+pub fn populated_world(width: usize, height: usize, agents: (usize, usize, usize)) -> Landscape {
+    let mut landscape = world(width, height);
+    landscape.populate_random(
+        (agents.0, PlacementStrategy::Uniform),
+        (agents.1, PlacementStrategy::Uniform),
+        (agents.2, PlacementStrategy::Uniform),
+        &mut plant,
+        &mut animal,
+    ).expect("Ошибка заселения тестового мира!");
+
+    landscape
+}
+
+/// Животное со случайным мозгом из генератора с фиксированным зерном.
+///
+/// # Arguments
+///
+/// * `animal_type`: Вид животного.
+/// * `params`: Параметры животного.
+/// * `direction`: Направление животного.
+///
+/// returns: Box<dyn AnimalAlive>
+pub fn animal_facing(animal_type: AnimaType, params: AnimalParams, direction: AnimalDirection) -> Box<dyn AnimalAlive> {
+    let mut rng = StdRng::seed_from_u64(TEST_SEED);
+    Animal::<Brain>::new(animal_type, params, direction, 0, &mut rng).expect("Неверные параметры тестового животного")
+}
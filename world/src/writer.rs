@@ -0,0 +1,361 @@
+//! Запись строк в файл в отдельном потоке, чтобы ввод-вывод не задерживал итерации
+//! мира. Строки передаются пачками через канал ограниченного размера; что делать,
+//! когда канал заполнен, определяет политика переполнения. До передачи строки могут
+//! копиться в памяти (см. `LineBacklog`).
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::mem;
+use std::io::{self, Write};
+use std::sync::mpsc::{sync_channel, SyncSender, TrySendError};
+use std::thread::{self, JoinHandle};
+
+use crate::errors::RecoverableError;
+use crate::memory::MemoryTracker;
+
+/// Что делать с пачкой строк, если канал к потоку записи заполнен.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum OverflowPolicy {
+    /// Ждать, пока поток записи освободит место (строки не теряются).
+    Block,
+    /// Отбросить пачку и учесть отброшенные строки.
+    Drop,
+}
+
+/// Запись строк в отдельном потоке.
+pub struct AsyncWriter {
+    // Канал к потоку записи. None - запись завершена.
+    sender: Option<SyncSender<Vec<String>>>,
+    // Поток записи; возвращает ошибку ввода-вывода, из-за которой он остановился.
+    handle: Option<JoinHandle<io::Result<()>>>,
+    // Политика переполнения канала.
+    policy: OverflowPolicy,
+    // Количество строк, отброшенных из-за переполнения канала.
+    dropped: usize,
+}
+
+impl AsyncWriter {
+    /// Конструктор: запускает поток записи.
+    ///
+    /// # Arguments
+    ///
+    /// * `sink`: Куда записываются строки.
+    /// * `capacity`: Сколько пачек строк может ждать записи в канале.
+    /// * `policy`: Политика переполнения канала.
+    ///
+    /// returns: AsyncWriter
+    pub fn new(sink: Box<dyn Write + Send>, capacity: usize, policy: OverflowPolicy) -> AsyncWriter {
+        let (sender, receiver) = sync_channel::<Vec<String>>(capacity);
+
+        let handle = thread::spawn(move || {
+            let mut sink = io::BufWriter::new(sink);
+
+            while let Ok(batch) = receiver.recv() {
+                // Пачки, которые уже ждут в очереди, записываются вместе; буфер
+                // сбрасывается, когда очередь опустела.
+                for batch in std::iter::once(batch).chain(receiver.try_iter()) {
+                    for line in batch {
+                        sink.write_all(line.as_bytes())?;
+                    }
+                }
+                sink.flush()?;
+            }
+
+            Ok(())
+        });
+
+        AsyncWriter {
+            sender: Some(sender),
+            handle: Some(handle),
+            policy,
+            dropped: 0,
+        }
+    }
+
+    /// Передает пачку строк потоку записи. Строки записываются в порядке передачи.
+    ///
+    /// # Arguments
+    ///
+    /// * `lines`: Строки (вместе с переводами строк).
+    ///
+    /// returns: Result<(), RecoverableError> - ошибка, если поток записи остановился
+    /// из-за ошибки ввода-вывода.
+    pub fn write_lines(&mut self, lines: Vec<String>) -> Result<(), RecoverableError> {
+        let sender = match self.sender {
+            Some(ref sender) => sender,
+            None => return Err(RecoverableError::new("Запись уже завершена".to_string())),
+        };
+
+        let count = lines.len();
+        let sent = match self.policy {
+            OverflowPolicy::Block => sender.send(lines).is_ok(),
+            OverflowPolicy::Drop => match sender.try_send(lines) {
+                Ok(_) => true,
+                Err(TrySendError::Full(_)) => {
+                    self.dropped += count;
+                    true
+                }
+                Err(TrySendError::Disconnected(_)) => false,
+            },
+        };
+
+        if sent {
+            Ok(())
+        } else {
+            // Поток записи остановился: узнаем почему.
+            self.join()
+        }
+    }
+
+    /// Возвращает количество строк, отброшенных из-за переполнения канала.
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    /// Завершает запись: дожидается, пока поток запишет все переданные строки.
+    ///
+    /// returns: Result<usize, RecoverableError> - количество отброшенных строк.
+    pub fn finish(mut self) -> Result<usize, RecoverableError> {
+        self.join()?;
+
+        Ok(self.dropped)
+    }
+
+    /// Закрывает канал и дожидается завершения потока записи.
+    fn join(&mut self) -> Result<(), RecoverableError> {
+        self.sender = None;
+
+        let result = match self.handle.take() {
+            Some(handle) => handle.join(),
+            None => return Ok(()),
+        };
+
+        match result {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(e)) => Err(RecoverableError::new(fmt::format(format_args!(
+                "Ошибка записи: {}", e
+            )))),
+            Err(_) => Err(RecoverableError::new("Поток записи аварийно завершился".to_string())),
+        }
+    }
+}
+
+impl Drop for AsyncWriter {
+    fn drop(&mut self) {
+        let _ = self.join();
+    }
+}
+
+/// Строки, которые копятся в памяти до передачи потоку записи. Если бюджет памяти
+/// превышен (см. `MemoryTracker`), самые старые строки отбрасываются и учитываются.
+pub struct LineBacklog {
+    // Имя для журнала освобождения памяти.
+    name: &'static str,
+    // Строки в порядке добавления.
+    lines: VecDeque<String>,
+    // Приблизительный объем строк в байтах.
+    bytes: usize,
+    // Количество отброшенных строк.
+    dropped: usize,
+}
+
+impl LineBacklog {
+    /// Конструктор.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: Имя для журнала освобождения памяти.
+    ///
+    /// returns: LineBacklog
+    pub fn new(name: &'static str) -> LineBacklog {
+        LineBacklog { name, lines: VecDeque::new(), bytes: 0, dropped: 0 }
+    }
+
+    /// Добавляет строки в конец.
+    ///
+    /// # Arguments
+    ///
+    /// * `lines`: Строки (вместе с переводами строк).
+    ///
+    /// returns: ()
+    pub fn push(&mut self, lines: impl IntoIterator<Item = String>) {
+        for line in lines {
+            self.bytes += Self::line_size(&line);
+            self.lines.push_back(line);
+        }
+    }
+
+    /// Забирает накопленные строки в порядке добавления.
+    ///
+    /// returns: Vec<String>
+    pub fn take(&mut self) -> Vec<String> {
+        self.bytes = 0;
+        self.lines.drain(..).collect()
+    }
+
+    /// Возвращает количество строк, отброшенных из-за нехватки памяти.
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    /// Приблизительный объем строки в памяти.
+    fn line_size(line: &str) -> usize {
+        mem::size_of::<String>() + line.len()
+    }
+}
+
+impl MemoryTracker for LineBacklog {
+    fn tracker_name(&self) -> &'static str {
+        self.name
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.bytes
+    }
+
+    fn shed_memory(&mut self, bytes: usize) -> usize {
+        let mut freed = 0;
+
+        while freed < bytes {
+            match self.lines.pop_front() {
+                Some(line) => {
+                    freed += Self::line_size(&line);
+                    self.dropped += 1;
+                }
+                None => break,
+            }
+        }
+        self.bytes -= freed;
+
+        freed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    /// Медленный приемник строк: каждая запись задерживается, записанное доступно
+    /// тесту через общий буфер. Может отказывать в записи.
+    struct SlowSink {
+        written: Arc<Mutex<Vec<u8>>>,
+        delay: Duration,
+        failing: bool,
+    }
+
+    impl Write for SlowSink {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            thread::sleep(self.delay);
+            if self.failing {
+                return Err(io::Error::new(io::ErrorKind::Other, "диск заполнен"));
+            }
+            self.written.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn slow_writer(capacity: usize, policy: OverflowPolicy, failing: bool) -> (AsyncWriter, Arc<Mutex<Vec<u8>>>) {
+        let written = Arc::new(Mutex::new(Vec::new()));
+        let sink = SlowSink { written: written.clone(), delay: Duration::from_millis(5), failing };
+
+        (AsyncWriter::new(Box::new(sink), capacity, policy), written)
+    }
+
+    fn batch(index: usize) -> Vec<String> {
+        vec![format!("{}a\n", index), format!("{}b\n", index)]
+    }
+
+    fn expected(batches: usize) -> String {
+        (0..batches).flat_map(batch).collect()
+    }
+
+    #[test]
+    fn blocking_writer_keeps_every_line_in_order() {
+        let (mut writer, written) = slow_writer(1, OverflowPolicy::Block, false);
+
+        for index in 0..20 {
+            writer.write_lines(batch(index)).unwrap();
+        }
+
+        assert_eq!(writer.finish().unwrap(), 0);
+        assert_eq!(String::from_utf8(written.lock().unwrap().clone()).unwrap(), expected(20));
+    }
+
+    #[test]
+    fn dropping_writer_counts_dropped_lines() {
+        let (mut writer, written) = slow_writer(1, OverflowPolicy::Drop, false);
+
+        for index in 0..20 {
+            writer.write_lines(batch(index)).unwrap();
+        }
+        let dropped = writer.dropped();
+
+        // Медленный приемник не успевает: часть пачек отброшена, остальные записаны
+        // целиком и по порядку.
+        assert!(dropped > 0);
+        assert_eq!(writer.finish().unwrap(), dropped);
+        let text = String::from_utf8(written.lock().unwrap().clone()).unwrap();
+        assert_eq!(text.lines().count() + dropped, 40);
+        let indices: Vec<usize> = text.lines()
+            .step_by(2)
+            .map(|line| line.trim_end_matches('a').parse().unwrap())
+            .collect();
+        assert!(indices.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn drop_flushes_pending_lines() {
+        let (mut writer, written) = slow_writer(8, OverflowPolicy::Block, false);
+
+        for index in 0..5 {
+            writer.write_lines(batch(index)).unwrap();
+        }
+        drop(writer);
+
+        assert_eq!(String::from_utf8(written.lock().unwrap().clone()).unwrap(), expected(5));
+    }
+
+    #[test]
+    fn write_error_is_reported() {
+        let (mut writer, written) = slow_writer(1, OverflowPolicy::Block, true);
+
+        // Поток записи останавливается на первой пачке; ошибку возвращает одна из
+        // следующих передач или завершение записи.
+        let failed = (0..10).any(|index| writer.write_lines(batch(index)).is_err());
+
+        assert!(failed || writer.finish().is_err());
+        assert!(written.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn writing_after_finish_fails() {
+        let (mut writer, _) = slow_writer(1, OverflowPolicy::Block, false);
+
+        writer.join().unwrap();
+
+        assert!(writer.write_lines(batch(0)).is_err());
+    }
+
+    #[test]
+    fn backlog_sheds_oldest_lines_first() {
+        let mut backlog = LineBacklog::new("строки");
+        backlog.push((0..5).flat_map(batch));
+        let line = LineBacklog::line_size(&batch(0)[0]);
+        assert_eq!(backlog.memory_usage(), 10 * line);
+
+        assert_eq!(backlog.shed_memory(3 * line - 1), 3 * line);
+        assert_eq!(backlog.dropped(), 3);
+        assert_eq!(backlog.memory_usage(), 7 * line);
+
+        let rest: String = backlog.take().concat();
+        assert_eq!(rest, format!("1b\n{}", (2..5).flat_map(batch).collect::<String>()));
+        assert_eq!(backlog.memory_usage(), 0);
+        assert_eq!(backlog.shed_memory(line), 0);
+    }
+}
@@ -0,0 +1,156 @@
+//! Проверка здоровья модели: долгий прогон сбалансированного мира с заданным
+//! зерном и настройками по умолчанию, в котором хищники и травоядные должны
+//! сосуществовать. При нарушении выводится траектория последних итераций.
+
+use evolution::animal::brains::simple::Brain;
+use evolution::animal::species::simple::{Animal, AnimalParams};
+use evolution::animal::{AnimalAlive, AnimalDirection, AnimaType};
+use evolution::config::*;
+use evolution::landscape::{Energy, Landscape};
+use evolution::plant::PlantAlive;
+use evolution::plant::simple::{Plant, PlantParams};
+use rand::RngCore;
+
+// Зерно мира: прогон воспроизводим.
+const WORLD_SEED: u64 = 7;
+
+// Продолжительность прогона (итерации).
+const TICKS: usize = 4000;
+
+// Ни один вид не должен вымереть раньше этой итерации.
+const EXTINCTION_TICK: usize = 3000;
+
+// Допустимые границы количества растений.
+const PLANTS_RANGE: (usize, usize) = (5, MAX_PLANTS);
+
+// Погрешность сравнения энергии.
+const ENERGY_EPSILON: Energy = 1e-2;
+
+/// Состояние мира после итерации для вывода траектории при ошибке.
+struct Sample {
+    tick: usize,
+    plants: usize,
+    animals: (usize, usize, usize),
+    energy: Energy,
+    inflow: Energy,
+}
+
+/// Создает растение с настройками по умолчанию.
+fn new_plant() -> Box<dyn PlantAlive> {
+    Plant::new(PlantParams {
+        energy: MAX_PLANT_ENERGY,
+        max_energy: MAX_PLANT_ENERGY,
+        eaten_energy: PLANT_EATEN_ENERGY,
+        bite_mode: PLANT_BITE_MODE,
+        reproduce_energy_rate: PLANT_REPRODUCE_ENERGY_RATE,
+        no_repro: PLANT_NO_REPRO,
+        regrowth_delay: PLANT_REGROWTH_DELAY,
+        seed_energy_cost: PLANT_SEED_ENERGY_COST,
+    }).expect("Ошибка настройки растений!")
+}
+
+/// Создает животное со случайным простым мозгом и настройками по умолчанию.
+fn new_animal(animal_type: AnimaType, rng: &mut dyn RngCore) -> Box<dyn AnimalAlive> {
+    Animal::<Brain>::new(
+        animal_type,
+        AnimalParams {
+            energy: ANIMAL_BIRTH_ENERGY,
+            max_energy: MAX_ANIMAL_ENERGY,
+            live_energy: ANIMAL_LIVE_ENERGY,
+            energy_profile: ANIMAL_ENERGY_PROFILE,
+            eaten_energy_rate: ANIMAL_EATEN_ENERGY_RATE,
+            reproduce_energy_rate: ANIMAL_REPRODUCE_ENERGY_RATE,
+            no_repro: ANIMAL_NO_REPRO,
+            inherit_direction: ANIMAL_INHERIT_DIRECTION,
+            input_mask: &[],
+            vision_radius: ANIMAL_VISION_RADIUS,
+            max_age: ANIMAL_MAX_AGE,
+        },
+        AnimalDirection::North,
+        0,
+        rng,
+    ).expect("Ошибка настройки животных!")
+}
+
+/// Суммарная энергия мира: растения, животные, падаль и туши.
+fn total_energy(world: &Landscape) -> Energy {
+    let mut total = 0 as Energy;
+    for x in 0..GRID_WIDTH {
+        for y in 0..GRID_HEIGHT {
+            if let Some(cell) = world.get_cell_info(x, y) {
+                total += cell.plant.map_or(0 as Energy, |plant| plant.energy);
+                total += cell.animal.map_or(0 as Energy, |animal| animal.energy);
+                total += cell.carrion + cell.corpse;
+            }
+        }
+    }
+
+    total
+}
+
+/// Проверяет грубые экологические инварианты и возвращает описание первого нарушения.
+fn check(sample: &Sample, previous: Energy) -> Result<(), String> {
+    let (herbivores, carnivores, _) = sample.animals;
+    if sample.tick < EXTINCTION_TICK && (herbivores == 0 || carnivores == 0) {
+        return Err(format!("итерация {}: вид вымер", sample.tick));
+    }
+    if sample.plants < PLANTS_RANGE.0 || sample.plants > PLANTS_RANGE.1 {
+        return Err(format!("итерация {}: растений {} вне границ {:?}", sample.tick, sample.plants, PLANTS_RANGE));
+    }
+    if sample.energy - previous > sample.inflow + ENERGY_EPSILON {
+        return Err(format!(
+            "итерация {}: энергия мира выросла на {} при притоке {}",
+            sample.tick,
+            sample.energy - previous,
+            sample.inflow
+        ));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn predators_and_prey_coexist() {
+    let mut world = Landscape::new(
+        GRID_WIDTH,
+        GRID_HEIGHT,
+        MAX_PLANTS,
+        MAX_HERBIVORE,
+        MAX_CARNIVORE,
+        MAX_PLANT_GROW_ENERGY,
+        Some(WORLD_SEED)
+    ).expect("Ошибка создания мира!");
+    world.populate_random(INITIAL_PLANTS, INITIAL_HERBIVORES, INITIAL_CARNIVORES, &mut new_plant, &mut new_animal)
+        .expect("Ошибка заселения мира!");
+
+    let mut trajectory: Vec<Sample> = Vec::with_capacity(TICKS);
+    let mut energy = total_energy(&world);
+    for _ in 0..TICKS {
+        world.tick();
+
+        // Энергия поступает в мир только с ростом растений: съеденное животное
+        // получает лишь часть энергии еды.
+        let stats = world.get_world_stats();
+        let sample = Sample {
+            tick: stats.iteration,
+            plants: stats.plant_count,
+            animals: stats.animal_count,
+            energy: total_energy(&world),
+            inflow: stats.plant_count as Energy * MAX_PLANT_GROW_ENERGY,
+        };
+
+        let result = check(&sample, energy);
+        energy = sample.energy;
+        trajectory.push(sample);
+
+        if let Err(error) = result {
+            for sample in trajectory.iter().rev().take(50).rev() {
+                eprintln!(
+                    "{:>5} растений {:>3} животных {:?} энергия {:.3} приток {:.3}",
+                    sample.tick, sample.plants, sample.animals, sample.energy, sample.inflow
+                );
+            }
+            panic!("{}", error);
+        }
+    }
+}